@@ -1,4 +1,3 @@
-use chrono_merkle::{ChronoMerkleTree, Blake3Hasher, security::NoOpLogger};
 use criterion::{criterion_group, criterion_main, Criterion};
 
 #[cfg(feature = "clockhash")]
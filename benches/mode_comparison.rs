@@ -0,0 +1,178 @@
+//! Side-by-side comparison of the tree's alternative execution modes
+//!
+//! Each group benchmarks two or more modes of one subsystem across tree
+//! sizes. After the run, the mean time of every mode is written to
+//! `<criterion home>/mode_comparison.json` so CI can compare the modes of a
+//! group and flag regressions without parsing criterion's reports.
+
+use chrono_merkle::config::TreeConfig;
+use chrono_merkle::{Blake3Hasher, ChronoMerkleTree};
+use criterion::{BatchSize, BenchmarkId, Criterion};
+use std::path::{Path, PathBuf};
+
+type Tree = ChronoMerkleTree<[u8; 32], Blake3Hasher>;
+
+const SIZES: [usize; 2] = [1_000, 10_000];
+const GROUPS: [&str; 4] = ["mode_rebuild", "mode_update", "mode_insert", "mode_proof"];
+
+fn entries(start: usize, count: usize) -> Vec<(Vec<u8>, u64)> {
+    (start..start + count).map(|i| (format!("leaf_{}", i).into_bytes(), i as u64)).collect()
+}
+
+fn tree_with(config: TreeConfig, size: usize) -> Tree {
+    let mut tree = Tree::with_config(Blake3Hasher, config).unwrap();
+    let entries = entries(0, size);
+    let batch: Vec<(&[u8], u64)> = entries.iter().map(|(data, ts)| (data.as_slice(), *ts)).collect();
+    tree.insert_batch(&batch).unwrap();
+    tree
+}
+
+/// Full construction of a tree, sequential vs parallel
+fn bench_rebuild(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mode_rebuild");
+    let mut modes = vec![("sequential", TreeConfig { parallel_construction: false, ..Default::default() })];
+    if cfg!(feature = "parallel") {
+        modes.push(("parallel", TreeConfig { parallel_construction: true, ..Default::default() }));
+    }
+    for size in SIZES {
+        for (mode, config) in &modes {
+            group.bench_with_input(BenchmarkId::new(*mode, size), &size, |b, &size| {
+                b.iter(|| std::hint::black_box(tree_with(config.clone(), size)));
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Appending 100 leaves one by one, incremental vs full rebuild
+fn bench_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mode_update");
+    for size in SIZES {
+        let appended = entries(size, 100);
+        for (mode, incremental_updates) in [("incremental", true), ("full", false)] {
+            let base = tree_with(TreeConfig { incremental_updates, ..Default::default() }, size);
+            group.bench_with_input(BenchmarkId::new(mode, size), &size, |b, _| {
+                b.iter_batched(
+                    || base.clone(),
+                    |mut tree| {
+                        for (data, ts) in &appended {
+                            tree.insert(data, *ts).unwrap();
+                        }
+                        tree
+                    },
+                    BatchSize::LargeInput,
+                );
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Appending 100 leaves, one `insert_batch` vs 100 `insert` calls
+fn bench_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mode_insert");
+    for size in SIZES {
+        let appended = entries(size, 100);
+        let batch: Vec<(&[u8], u64)> = appended.iter().map(|(data, ts)| (data.as_slice(), *ts)).collect();
+        let base = tree_with(TreeConfig::default(), size);
+        group.bench_with_input(BenchmarkId::new("single", size), &size, |b, _| {
+            b.iter_batched(
+                || base.clone(),
+                |mut tree| {
+                    for (data, ts) in &batch {
+                        tree.insert(data, *ts).unwrap();
+                    }
+                    tree
+                },
+                BatchSize::LargeInput,
+            );
+        });
+        group.bench_with_input(BenchmarkId::new("batch", size), &size, |b, _| {
+            b.iter_batched(
+                || base.clone(),
+                |mut tree| {
+                    tree.insert_batch(&batch).unwrap();
+                    tree
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+/// Proofs for 64 recurring leaves, with and without the proof cache
+fn bench_proof(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mode_proof");
+    for size in SIZES {
+        let uncached = tree_with(TreeConfig::default(), size);
+        let mut cached = uncached.clone();
+        cached.enable_proof_cache(64);
+        let leaves: Vec<usize> = (0..64).map(|i| i * size / 64).collect();
+        for (mode, tree) in [("uncached", &uncached), ("cached", &cached)] {
+            group.bench_with_input(BenchmarkId::new(mode, size), &size, |b, _| {
+                b.iter(|| {
+                    for &leaf in &leaves {
+                        std::hint::black_box(tree.generate_proof(leaf).unwrap());
+                    }
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+fn criterion_home() -> PathBuf {
+    std::env::var_os("CRITERION_HOME").map_or_else(|| PathBuf::from("target/criterion"), PathBuf::from)
+}
+
+/// Mean time in nanoseconds of the latest run of one benchmark, if it ran
+fn mean_ns(home: &Path, group: &str, mode: &str, size: usize) -> Option<f64> {
+    let path = home.join(group).join(mode).join(size.to_string()).join("new/estimates.json");
+    let estimates: serde_json::Value = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+    estimates["mean"]["point_estimate"].as_f64()
+}
+
+/// Write the mean of every mode per group and size as JSON
+fn write_summary() {
+    let home = criterion_home();
+    let mut comparisons = Vec::new();
+    for group in GROUPS {
+        let Ok(modes) = std::fs::read_dir(home.join(group)) else {
+            continue;
+        };
+        let mut modes: Vec<String> = modes
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|mode| mode != "report")
+            .collect();
+        modes.sort();
+        for size in SIZES {
+            let means: serde_json::Map<String, serde_json::Value> = modes
+                .iter()
+                .filter_map(|mode| Some((mode.clone(), mean_ns(&home, group, mode, size)?.into())))
+                .collect();
+            if !means.is_empty() {
+                comparisons.push(serde_json::json!({ "group": group, "size": size, "mean_ns": means }));
+            }
+        }
+    }
+    if comparisons.is_empty() {
+        return;
+    }
+    let summary = serde_json::json!({ "comparisons": comparisons });
+    let path = home.join("mode_comparison.json");
+    match std::fs::write(&path, serde_json::to_vec_pretty(&summary).unwrap()) {
+        Ok(()) => println!("Mode comparison summary written to {}", path.display()),
+        Err(e) => eprintln!("Failed to write {}: {}", path.display(), e),
+    }
+}
+
+fn main() {
+    let mut criterion = Criterion::default().sample_size(10).configure_from_args();
+    bench_rebuild(&mut criterion);
+    bench_update(&mut criterion);
+    bench_insert(&mut criterion);
+    bench_proof(&mut criterion);
+    criterion.final_summary();
+    write_summary();
+}
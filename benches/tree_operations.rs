@@ -1,4 +1,6 @@
 use chrono_merkle::{ChronoMerkleTree, Blake3Hasher, security::NoOpLogger};
+#[cfg(not(feature = "minimal"))]
+use chrono_merkle::{Accumulator, HashChainAccumulator, MerkleAccumulator};
 use criterion::{criterion_group, criterion_main, Criterion};
 
 fn bench_tree_construction(c: &mut Criterion) {
@@ -6,14 +8,14 @@ fn bench_tree_construction(c: &mut Criterion) {
 
     group.bench_function("create_empty_tree", |b| {
         b.iter(|| {
-            let tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher::default());
+            let tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher);
             std::hint::black_box(tree);
         });
     });
 
     group.bench_function("insert_1000_leaves", |b| {
         b.iter(|| {
-            let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher::default());
+            let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher);
             for i in 0..1000 {
                 let data = format!("leaf_{}", i).into_bytes();
                 tree.insert(&data, i as u64).unwrap();
@@ -24,7 +26,7 @@ fn bench_tree_construction(c: &mut Criterion) {
 
     group.bench_function("insert_10000_leaves", |b| {
         b.iter(|| {
-            let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher::default());
+            let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher);
             for i in 0..10000 {
                 let data = format!("leaf_{}", i).into_bytes();
                 tree.insert(&data, i as u64).unwrap();
@@ -40,7 +42,7 @@ fn bench_proof_operations(c: &mut Criterion) {
     let mut group = c.benchmark_group("proof_operations");
 
     // Setup tree with 1000 leaves
-    let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher::default());
+    let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher);
     for i in 0..1000 {
         let data = format!("leaf_{}", i).into_bytes();
         tree.insert(&data, i as u64).unwrap();
@@ -62,7 +64,7 @@ fn bench_proof_operations(c: &mut Criterion) {
     });
 
     // Setup larger tree with 10000 leaves
-    let mut large_tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher::default());
+    let mut large_tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher);
     for i in 0..10000 {
         let data = format!("leaf_{}", i).into_bytes();
         large_tree.insert(&data, i as u64).unwrap();
@@ -90,7 +92,7 @@ fn bench_query_operations(c: &mut Criterion) {
     let mut group = c.benchmark_group("query_operations");
 
     // Setup tree with 10000 leaves at different timestamps
-    let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher::default());
+    let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher);
     for i in 0..10000 {
         let data = format!("data_{}", i).into_bytes();
         let timestamp = i as u64 * 1000; // Spread timestamps
@@ -125,7 +127,7 @@ fn bench_tree_properties(c: &mut Criterion) {
     let mut group = c.benchmark_group("tree_properties");
 
     // Setup tree with 1000 leaves
-    let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher::default());
+    let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher);
     for i in 0..1000 {
         let data = format!("leaf_{}", i).into_bytes();
         tree.insert(&data, i as u64).unwrap();
@@ -159,7 +161,7 @@ fn bench_incremental_updates(c: &mut Criterion) {
     let mut group = c.benchmark_group("incremental_updates");
 
     // Start with a tree that has some data
-    let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher::default());
+    let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher);
     for i in 0..100 {
         let data = format!("initial_{}", i).into_bytes();
         tree.insert(&data, i as u64).unwrap();
@@ -205,14 +207,14 @@ fn bench_memory_usage(c: &mut Criterion) {
     group.bench_function("tree_memory_overhead", |b| {
         b.iter(|| {
             // Measure memory overhead of empty tree
-            let tree = ChronoMerkleTree::<[u8; 32], Blake3Hasher>::new(Blake3Hasher::default());
+            let tree = ChronoMerkleTree::<[u8; 32], Blake3Hasher>::new(Blake3Hasher);
             std::hint::black_box(tree);
         });
     });
 
     group.bench_function("scaling_memory_usage", |b| {
         b.iter(|| {
-            let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher::default());
+            let mut tree: ChronoMerkleTree<[u8; 32], Blake3Hasher, NoOpLogger> = ChronoMerkleTree::new(Blake3Hasher);
             // Add increasing numbers of leaves and measure memory scaling
             for i in 0..100 {
                 let data = [i as u8; 32]; // Fixed-size data
@@ -225,6 +227,58 @@ fn bench_memory_usage(c: &mut Criterion) {
     group.finish();
 }
 
+#[cfg(not(feature = "minimal"))]
+fn bench_accumulator<A: Accumulator>(c: &mut Criterion, name: &str, new: impl Fn() -> A) {
+    let mut group = c.benchmark_group(format!("accumulator_{}", name));
+
+    group.bench_function("insert_1000", |b| {
+        b.iter(|| {
+            let mut acc = new();
+            for i in 0..1000 {
+                acc.insert(format!("leaf_{}", i).as_bytes()).unwrap();
+            }
+            std::hint::black_box(acc.digest());
+        });
+    });
+
+    let mut acc = new();
+    for i in 0..1000 {
+        acc.insert(format!("leaf_{}", i).as_bytes()).unwrap();
+    }
+    let digest = acc.digest();
+    let witness = acc.prove(500).unwrap();
+    println!("{} witness size: {} bytes", name, acc.witness_size(&witness));
+
+    group.bench_function("prove", |b| {
+        b.iter(|| std::hint::black_box(acc.prove(500).unwrap()));
+    });
+
+    group.bench_function("verify", |b| {
+        b.iter(|| std::hint::black_box(acc.verify(&digest, b"leaf_500", &witness)));
+    });
+
+    group.finish();
+}
+
+#[cfg(not(feature = "minimal"))]
+fn bench_accumulators(c: &mut Criterion) {
+    bench_accumulator(c, "merkle", || MerkleAccumulator::new(Blake3Hasher));
+    bench_accumulator(c, "hash_chain", || HashChainAccumulator::new(Blake3Hasher));
+}
+
+#[cfg(not(feature = "minimal"))]
+criterion_group!(
+    benches,
+    bench_tree_construction,
+    bench_proof_operations,
+    bench_query_operations,
+    bench_tree_properties,
+    bench_incremental_updates,
+    bench_memory_usage,
+    bench_accumulators
+);
+
+#[cfg(feature = "minimal")]
 criterion_group!(
     benches,
     bench_tree_construction,
@@ -12,7 +12,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🕒 ChronoMerkle Tree - Basic Usage Example\n");
 
     // Create a new ChronoMerkle tree with Blake3 hasher
-    let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher::default());
+    let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher);
     println!("✓ Created new ChronoMerkle tree");
 
     // Insert some data with timestamps
@@ -25,7 +25,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("📥 Inserting data with timestamps:");
     for (data_item, timestamp) in &data {
-        tree.insert(*data_item, *timestamp)?;
+        tree.insert(data_item, *timestamp)?;
         println!("  - {:?} at timestamp {}", String::from_utf8_lossy(data_item), timestamp);
     }
 
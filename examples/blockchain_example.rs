@@ -8,6 +8,7 @@
 //! - Historical state verification
 
 use chrono_merkle::{Blake3Hasher, DefaultChronoMerkleTree};
+use chrono_merkle::encoding::encode_u64;
 
 /// Represents a simple transaction
 #[derive(Debug, Clone)]
@@ -47,7 +48,7 @@ struct Block {
 impl Block {
     fn new(height: u64, timestamp: u64, transactions: Vec<Transaction>, previous_hash: Option<[u8; 32]>) -> Self {
         // Create Merkle tree for transactions
-        let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher::default());
+        let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher);
 
         // Insert transactions with their individual timestamps
         for (i, tx) in transactions.iter().enumerate() {
@@ -71,10 +72,10 @@ impl Block {
     }
 
     fn hash(&self) -> [u8; 32] {
-        let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher::default());
+        let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher);
 
         // Include block header data in deterministic order
-        tree.insert(&self.height.to_le_bytes(), self.timestamp).unwrap();
+        tree.insert(&encode_u64(self.height), self.timestamp).unwrap();
         tree.insert(&self.merkle_root, self.timestamp + 1).unwrap();
 
         if let Some(prev_hash) = self.previous_hash {
@@ -143,7 +144,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // Verify transaction inclusion proof
         if !transactions.is_empty() {
             println!("  Verifying transaction inclusion:");
-            let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher::default());
+            let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher);
             for (i, tx) in transactions.iter().enumerate() {
                 tree.insert(&tx.hash_data(), current_timestamp + i as u64).unwrap();
             }
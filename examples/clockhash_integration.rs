@@ -7,6 +7,7 @@
 //! execution traces while maintaining time-based verification capabilities.
 
 use chrono_merkle::{Blake3Hasher, DefaultChronoMerkleTree};
+use chrono_merkle::encoding::encode_u64;
 #[cfg(feature = "clockhash")]
 use chrono_merkle::ClockHashAdapter;
 
@@ -29,7 +30,7 @@ impl TraceEntry {
 
     fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
-        bytes.extend_from_slice(&self.timestamp.to_le_bytes());
+        bytes.extend_from_slice(&encode_u64(self.timestamp));
         bytes.extend_from_slice(self.operation.as_bytes());
         bytes.extend_from_slice(&self.data);
         bytes
@@ -104,7 +105,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Demonstrate integration with regular ChronoMerkle tree for comparison
     println!("\n🔄 Comparison with regular ChronoMerkle tree:");
 
-    let mut regular_tree = DefaultChronoMerkleTree::new(Blake3Hasher::default());
+    let mut regular_tree = DefaultChronoMerkleTree::new(Blake3Hasher);
     for entry in &trace {
         regular_tree.insert(&entry.to_bytes(), entry.timestamp)?;
     }
@@ -131,7 +132,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Demonstrate proof generation capabilities
-    if trace.len() > 0 {
+    if !trace.is_empty() {
         println!("\n🔐 Proof generation capabilities:");
 
         // Generate proof for first trace entry
@@ -45,8 +45,8 @@ fn balance_validator(min_balance: u64) -> ProgrammableNode {
         move |left_data, right_data| {
             // Simple balance check simulation
             // In real usage, this would check actual account balances
-            let left_balance = left_data.get(0).copied().unwrap_or(0) as u64;
-            let right_amount = right_data.get(0).copied().unwrap_or(0) as u64;
+            let left_balance = left_data.first().copied().unwrap_or(0) as u64;
+            let right_amount = right_data.first().copied().unwrap_or(0) as u64;
 
             if left_balance >= right_amount && left_balance >= min_balance {
                 Ok(())
@@ -97,7 +97,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🎛️  ChronoMerkle Tree - Programmable Nodes Example\n");
 
     // Create a tree with programmable nodes
-    let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher::default());
+    let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher);
     println!("✓ Created ChronoMerkle tree with programmable node support");
 
     // Define some programmable nodes
@@ -111,7 +111,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("  3. {}", compliance_check.description);
 
     // Example data that should pass validation
-    let valid_transactions = vec![
+    let valid_transactions = [
         (vec![150, 0, 0, 0, b'v', b'a', b'l', b'i', b'd', b'_', b's', b'i', b'g'], 1000), // balance=150, valid sig
         (vec![200, 0, 0, 0, b'v', b'a', b'l', b'i', b'd', b'_', b's', b'i', b'g'], 1001), // balance=200, valid sig
     ];
@@ -142,7 +142,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     // Example data that should fail validation
-    let invalid_transactions = vec![
+    let invalid_transactions = [
         (vec![50, 0, 0, 0, b'v', b'a', b'l', b'i', b'd', b'_', b's', b'i', b'g'], 1002),  // low balance=50
         (vec![150, 0, 0, 0, b'i', b'n', b'v', b'a', b'l', b'i', b'd'], 1003),            // invalid sig
         (vec![255, 0, 0, 0, b'v', b'a', b'l', b'i', b'd', b'_', b's', b'i', b'g'], 1004), // suspicious pattern
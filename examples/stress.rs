@@ -0,0 +1,234 @@
+//! # Soak / Stress Example
+//!
+//! Runs a randomized mix of inserts, proofs, rollbacks and checkpoints for a
+//! configurable duration, asserting tree invariants after every operation and
+//! recording per-operation latency percentiles. Use it to qualify the crate
+//! under production-like load before adopting it:
+//!
+//! ```bash
+//! cargo run --release --example stress -- --duration 2h --max-leaves 20000
+//! ```
+//!
+//! Options (all optional):
+//! - `--duration <N>[s|m|h]`: total run time (default `10s`)
+//! - `--report-every <N>[s|m|h]`: interval between progress reports (default `60s`)
+//! - `--max-leaves <N>`: roll back to half this size when exceeded (default `5000`)
+//! - `--seed <N>`: seed of the operation mix (default `1`)
+//!
+//! Invariants checked:
+//! - every generated proof verifies, and the same proof with a wrong timestamp does not
+//! - after a rollback, the root equals the root the tree had at that size
+//! - a checkpoint reports the root and leaf count of the tree it was taken from,
+//!   and with the `storage` feature a saved checkpoint reloads to the same root
+//!
+//! The process exits with an error at the first violated invariant.
+
+use chrono_merkle::{Blake3Hasher, DefaultChronoMerkleTree};
+use std::time::{Duration, Instant};
+
+/// Latency samples kept per operation; older samples are replaced at random
+const RESERVOIR: usize = 100_000;
+/// Most leaves removed by a random rollback
+const MAX_UNDO: usize = 8;
+/// First leaf timestamp; each insert uses the next second
+const START_TIMESTAMP: u64 = 1_600_000_000;
+
+struct Options {
+    duration: Duration,
+    report_every: Duration,
+    max_leaves: usize,
+    seed: u64,
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len()));
+    let number: u64 = number.parse().map_err(|_| format!("invalid duration {:?}", value))?;
+    let seconds = match unit {
+        "" | "s" => number,
+        "m" => number * 60,
+        "h" => number * 3600,
+        _ => return Err(format!("invalid duration unit in {:?}", value)),
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        duration: Duration::from_secs(10),
+        report_every: Duration::from_secs(60),
+        max_leaves: 5000,
+        seed: 1,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--duration" => options.duration = parse_duration(&value)?,
+            "--report-every" => options.report_every = parse_duration(&value)?,
+            "--max-leaves" => options.max_leaves = value.parse().map_err(|_| format!("invalid leaf count {:?}", value))?,
+            "--seed" => options.seed = value.parse().map_err(|_| format!("invalid seed {:?}", value))?,
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    options.max_leaves = options.max_leaves.max(2);
+    Ok(options)
+}
+
+/// splitmix64 generator driving the operation mix
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound.max(1) as u64) as usize
+    }
+}
+
+/// Latency samples of one operation kind
+struct Latencies {
+    name: &'static str,
+    count: u64,
+    max: Duration,
+    samples: Vec<Duration>,
+}
+
+impl Latencies {
+    fn new(name: &'static str) -> Self {
+        Self { name, count: 0, max: Duration::ZERO, samples: Vec::new() }
+    }
+
+    fn record(&mut self, latency: Duration, rng: &mut Rng) {
+        self.count += 1;
+        self.max = self.max.max(latency);
+        if self.samples.len() < RESERVOIR {
+            self.samples.push(latency);
+        } else {
+            let slot = rng.below(self.count as usize);
+            if slot < RESERVOIR {
+                self.samples[slot] = latency;
+            }
+        }
+    }
+
+    fn report(&self) {
+        if self.count == 0 {
+            println!("  {:<10} no samples", self.name);
+            return;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+        println!(
+            "  {:<10} n={:<10} p50={:>10.1?} p90={:>10.1?} p99={:>10.1?} p99.9={:>10.1?} max={:>10.1?}",
+            self.name,
+            self.count,
+            percentile(0.50),
+            percentile(0.90),
+            percentile(0.99),
+            percentile(0.999),
+            self.max
+        );
+    }
+}
+
+/// Fail with `message` unless `condition` holds
+fn ensure(condition: bool, message: impl FnOnce() -> String) -> Result<(), Box<dyn std::error::Error>> {
+    if condition {
+        Ok(())
+    } else {
+        Err(format!("invariant violated: {}", message()).into())
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let options = parse_options()?;
+    println!(
+        "🔥 ChronoMerkle stress run: {:?}, max {} leaves, seed {}\n",
+        options.duration, options.max_leaves, options.seed
+    );
+
+    let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher);
+    let mut rng = Rng(options.seed);
+    // roots[n] is the root the tree had with n leaves
+    let mut roots = vec![None];
+    let mut next_timestamp = START_TIMESTAMP;
+    let (mut inserts, mut proofs, mut rollbacks, mut checkpoints) =
+        (Latencies::new("insert"), Latencies::new("proof"), Latencies::new("rollback"), Latencies::new("checkpoint"));
+
+    let started = Instant::now();
+    let mut last_report = started;
+    while started.elapsed() < options.duration {
+        let roll = rng.below(100);
+        if tree.leaf_count() < 2 || roll < 60 {
+            let data = rng.next().to_le_bytes();
+            let timer = Instant::now();
+            tree.insert(&data, next_timestamp)?;
+            inserts.record(timer.elapsed(), &mut rng);
+            next_timestamp += 1;
+            roots.push(tree.root());
+            ensure(tree.leaf_count() + 1 == roots.len(), || "leaf count does not match inserts".to_string())?;
+        } else if roll < 85 {
+            let index = rng.below(tree.leaf_count());
+            let timer = Instant::now();
+            let proof = tree.generate_proof(index)?;
+            let valid = tree.verify_proof(&proof)?;
+            proofs.record(timer.elapsed(), &mut rng);
+            ensure(valid, || format!("proof for leaf {} does not verify", index))?;
+            let mut wrong = proof;
+            wrong.timestamp += 1;
+            ensure(!tree.verify_proof(&wrong)?, || format!("altered proof for leaf {} verifies", index))?;
+        } else if roll < 90 || tree.leaf_count() > options.max_leaves {
+            let keep = if tree.leaf_count() > options.max_leaves {
+                options.max_leaves / 2
+            } else {
+                // Undo a short suffix so the tree keeps growing towards max_leaves
+                tree.leaf_count() - 1 - rng.below((tree.leaf_count() - 1).min(MAX_UNDO))
+            };
+            let target = START_TIMESTAMP + keep as u64 - 1;
+            let timer = Instant::now();
+            tree.rollback_to_timestamp(target)?;
+            rollbacks.record(timer.elapsed(), &mut rng);
+            ensure(tree.leaf_count() == keep, || format!("rollback kept {} leaves, expected {}", tree.leaf_count(), keep))?;
+            ensure(tree.root() == roots[keep], || format!("root after rollback to {} leaves differs from history", keep))?;
+            roots.truncate(keep + 1);
+            next_timestamp = target + 1;
+        } else {
+            let timer = Instant::now();
+            let snapshot = tree.snapshot();
+            #[cfg(feature = "storage")]
+            let reloaded = {
+                let mut storage = chrono_merkle::MemoryStorage::new();
+                snapshot.save(&mut storage, "checkpoint")?;
+                DefaultChronoMerkleTree::load_state(&storage, "checkpoint", Blake3Hasher, Default::default())?.root()
+            };
+            checkpoints.record(timer.elapsed(), &mut rng);
+            ensure(snapshot.root() == tree.root() && snapshot.leaf_count() == tree.leaf_count(), || {
+                "checkpoint does not match the tree".to_string()
+            })?;
+            #[cfg(feature = "storage")]
+            ensure(reloaded == tree.root(), || "reloaded checkpoint has a different root".to_string())?;
+        }
+
+        if last_report.elapsed() >= options.report_every {
+            last_report = Instant::now();
+            println!("⏱️  {:?} elapsed, {} leaves", started.elapsed(), tree.leaf_count());
+            for latencies in [&inserts, &proofs, &rollbacks, &checkpoints] {
+                latencies.report();
+            }
+        }
+    }
+
+    println!("✅ All invariants held for {:?} ({} leaves at the end)", started.elapsed(), tree.leaf_count());
+    println!("\n📊 Latency percentiles:");
+    for latencies in [&inserts, &proofs, &rollbacks, &checkpoints] {
+        latencies.report();
+    }
+    Ok(())
+}
@@ -0,0 +1,230 @@
+//! Authentication and rate limiting for [`TreeService`](crate::server::TreeService)
+//!
+//! An [`Authenticator`] maps a request to a client identifier or rejects it.
+//! [`ApiKeyAuthenticator`] checks static API keys; [`BearerAuthenticator`]
+//! delegates `Authorization: Bearer` tokens to a [`TokenVerifier`], which is
+//! where JWT signature and claim validation plugs in. [`RateLimiter`] keeps a
+//! token bucket per client.
+
+use crate::security::constant_time_eq;
+use crate::server::Request;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Decides who sent a request
+pub trait Authenticator: Send + Sync {
+    /// Return the client identifier, or the reason the request is rejected
+    fn authenticate(&self, request: &Request) -> core::result::Result<String, String>;
+}
+
+/// Extract the token of an `Authorization: Bearer` header
+fn bearer_token(request: &Request) -> Option<&str> {
+    let value = request.header("authorization")?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+/// Authenticator accepting a fixed set of API keys
+///
+/// Keys are read from the `X-Api-Key` header or an `Authorization: Bearer` header.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeyAuthenticator {
+    keys: Vec<(String, String)>,
+}
+
+impl ApiKeyAuthenticator {
+    /// Create an authenticator without keys
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept `key`, identifying its holder as `client`
+    pub fn with_key(mut self, key: impl Into<String>, client: impl Into<String>) -> Self {
+        self.keys.push((key.into(), client.into()));
+        self
+    }
+}
+
+impl Authenticator for ApiKeyAuthenticator {
+    fn authenticate(&self, request: &Request) -> core::result::Result<String, String> {
+        let presented = request
+            .header("x-api-key")
+            .or_else(|| bearer_token(request))
+            .ok_or("missing API key")?;
+        // Compare against every key so timing does not reveal which one matched
+        let mut client = None;
+        for (key, holder) in &self.keys {
+            if constant_time_eq(key.as_bytes(), presented.as_bytes()) {
+                client = Some(holder.clone());
+            }
+        }
+        client.ok_or_else(|| "invalid API key".to_string())
+    }
+}
+
+/// Verifies bearer tokens such as JWTs
+pub trait TokenVerifier: Send + Sync {
+    /// Validate `token` and return its subject, or the reason it is invalid
+    fn verify(&self, token: &str) -> core::result::Result<String, String>;
+}
+
+/// Authenticator delegating `Authorization: Bearer` tokens to a [`TokenVerifier`]
+pub struct BearerAuthenticator<V> {
+    verifier: V,
+}
+
+impl<V: TokenVerifier> BearerAuthenticator<V> {
+    /// Authenticate with `verifier`
+    pub fn new(verifier: V) -> Self {
+        Self { verifier }
+    }
+}
+
+impl<V: TokenVerifier> Authenticator for BearerAuthenticator<V> {
+    fn authenticate(&self, request: &Request) -> core::result::Result<String, String> {
+        let token = bearer_token(request).ok_or("missing bearer token")?;
+        self.verifier.verify(token)
+    }
+}
+
+/// Token bucket parameters of a [`RateLimiter`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Requests a client may burst
+    pub burst: u32,
+    /// Sustained requests per second
+    pub per_second: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refreshed: Instant,
+}
+
+/// Per-client token bucket rate limiter
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Limit every client to `limit`
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the limit
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Take a token for `client`, returning whether the request may proceed
+    pub fn try_acquire(&self, client: &str) -> bool {
+        let now = Instant::now();
+        let burst = f64::from(self.limit.burst.max(1));
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Full buckets carry no information; drop them so idle clients do not accumulate
+        if buckets.len() > 10_000 {
+            let per_second = self.limit.per_second;
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.refreshed).as_secs_f64() * per_second < burst
+            });
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            refreshed: now,
+        });
+        let elapsed = now.duration_since(bucket.refreshed);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.limit.per_second).min(burst);
+        bucket.refreshed = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether `client` has no token left, without taking one
+    pub fn is_exhausted(&self, client: &str) -> bool {
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets.get(client).is_some_and(|bucket| {
+            let refill = bucket.refreshed.elapsed().as_secs_f64() * self.limit.per_second;
+            bucket.tokens + refill < 1.0
+        })
+    }
+
+    /// Time until `client` may send another request
+    pub fn retry_after(&self, client: &str) -> Duration {
+        let buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        match buckets.get(client) {
+            Some(bucket) if bucket.tokens < 1.0 && self.limit.per_second > 0.0 => {
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.limit.per_second)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_header(name: &str, value: &str) -> Request {
+        Request {
+            headers: vec![(name.to_string(), value.to_string())],
+            ..Request::get("/root")
+        }
+    }
+
+    #[test]
+    fn test_api_keys() {
+        let auth = ApiKeyAuthenticator::new().with_key("secret", "alice");
+        assert_eq!(auth.authenticate(&with_header("x-api-key", "secret")), Ok("alice".to_string()));
+        assert_eq!(auth.authenticate(&with_header("authorization", "Bearer secret")), Ok("alice".to_string()));
+        assert!(auth.authenticate(&with_header("x-api-key", "guess")).is_err());
+        assert!(auth.authenticate(&Request::get("/root")).is_err());
+    }
+
+    struct PrefixVerifier;
+
+    impl TokenVerifier for PrefixVerifier {
+        fn verify(&self, token: &str) -> core::result::Result<String, String> {
+            token.strip_prefix("valid.").map(str::to_string).ok_or_else(|| "bad signature".to_string())
+        }
+    }
+
+    #[test]
+    fn test_bearer_tokens() {
+        let auth = BearerAuthenticator::new(PrefixVerifier);
+        assert_eq!(auth.authenticate(&with_header("authorization", "bearer valid.bob")), Ok("bob".to_string()));
+        assert_eq!(
+            auth.authenticate(&with_header("authorization", "Bearer forged")),
+            Err("bad signature".to_string())
+        );
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(RateLimit { burst: 2, per_second: 0.0 });
+        assert!(limiter.try_acquire("a"));
+        assert!(limiter.try_acquire("a"));
+        assert!(!limiter.try_acquire("a"));
+        assert!(limiter.try_acquire("b"));
+        assert!(limiter.is_exhausted("a") && !limiter.is_exhausted("b") && !limiter.is_exhausted("c"));
+
+        let limiter = RateLimiter::new(RateLimit { burst: 1, per_second: 1000.0 });
+        assert!(limiter.try_acquire("a"));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(limiter.try_acquire("a"));
+    }
+}
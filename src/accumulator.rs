@@ -0,0 +1,591 @@
+//! Set accumulators behind a common interface
+//!
+//! [`Accumulator`] exposes insert, prove and verify so tree proofs can be
+//! benchmarked against other accumulator witnesses for a given workload:
+//!
+//! - [`MerkleAccumulator`]: a [`ChronoMerkleTree`], logarithmic witnesses
+//! - [`HashChainAccumulator`]: a running hash, constant-time inserts and
+//!   witnesses linear in the number of later elements
+//! - [`RsaAccumulator`] (feature `rsa-accumulator`): constant-size digests and
+//!   witnesses, at the cost of modular exponentiations and a trusted modulus
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::ChronoProof;
+use crate::security::NoOpLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{string::ToString, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::vec::Vec;
+
+/// A set commitment supporting membership witnesses
+pub trait Accumulator {
+    /// Commitment to all inserted elements
+    type Digest: Clone + PartialEq + core::fmt::Debug;
+    /// Membership witness of one element
+    type Witness: Clone;
+
+    /// Add an element, returning its position
+    fn insert(&mut self, data: &[u8]) -> Result<usize>;
+
+    /// Number of elements
+    fn len(&self) -> usize;
+
+    /// Whether no element was inserted
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Current commitment
+    fn digest(&self) -> Self::Digest;
+
+    /// Witness that the element at `index` is a member
+    fn prove(&self, index: usize) -> Result<Self::Witness>;
+
+    /// Check that `data` is a member of the set committed to by `digest`
+    fn verify(&self, digest: &Self::Digest, data: &[u8], witness: &Self::Witness) -> bool;
+
+    /// Encoded size of `witness` in bytes
+    fn witness_size(&self, witness: &Self::Witness) -> usize;
+}
+
+fn out_of_bounds(index: usize, len: usize) -> ChronoMerkleError {
+    ChronoMerkleError::IndexOutOfBounds { index, leaf_count: len }
+}
+
+/// [`ChronoMerkleTree`] as an accumulator; elements are timestamped with their position
+pub struct MerkleAccumulator<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+{
+    tree: ChronoMerkleTree<H, Hasher, NoOpLogger>,
+}
+
+impl<H, Hasher> MerkleAccumulator<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Default + Sync,
+{
+    /// Create an empty accumulator
+    pub fn new(hasher: Hasher) -> Self {
+        Self {
+            tree: ChronoMerkleTree::new(hasher),
+        }
+    }
+}
+
+impl<H, Hasher> Accumulator for MerkleAccumulator<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+{
+    type Digest = Option<H>;
+    type Witness = ChronoProof<H>;
+
+    fn insert(&mut self, data: &[u8]) -> Result<usize> {
+        let index = self.tree.leaf_count();
+        self.tree.insert(data, index as u64)?;
+        Ok(index)
+    }
+
+    fn len(&self) -> usize {
+        self.tree.leaf_count()
+    }
+
+    fn digest(&self) -> Option<H> {
+        self.tree.root()
+    }
+
+    fn prove(&self, index: usize) -> Result<ChronoProof<H>> {
+        self.tree.generate_proof(index)
+    }
+
+    fn verify(&self, digest: &Option<H>, data: &[u8], witness: &ChronoProof<H>) -> bool {
+        let Some(root) = digest else {
+            return false;
+        };
+        let leaf_hash = self.tree.hasher.hash(data);
+        crate::proof::verify_proof(witness, &leaf_hash, root, &self.tree.hasher).unwrap_or(false)
+    }
+
+    fn witness_size(&self, witness: &ChronoProof<H>) -> usize {
+        let hash_len = self.tree.root().map_or(0, |root| root.as_ref().len());
+        // Leaf index and timestamp, then one hash and a direction bit per step
+        16 + witness.path.len() * hash_len + witness.path.len().div_ceil(8)
+    }
+}
+
+/// Running hash `acc = hash(acc || hash(element))` over all elements
+pub struct HashChainAccumulator<Hasher: HashFunction> {
+    hasher: Hasher,
+    element_hashes: Vec<Hasher::Output>,
+    /// Digest before each element, then the current digest
+    digests: Vec<Hasher::Output>,
+}
+
+/// Witness of a [`HashChainAccumulator`] element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashChainWitness<H> {
+    /// Digest before the element was inserted
+    pub prefix: H,
+    /// Hashes of the elements inserted after it
+    pub suffix: Vec<H>,
+}
+
+impl<Hasher: HashFunction> HashChainAccumulator<Hasher>
+where
+    Hasher::Output: AsRef<[u8]> + Clone,
+{
+    /// Create an empty accumulator
+    pub fn new(hasher: Hasher) -> Self {
+        let seed = hasher.hash(b"chrono-merkle-hash-chain-v1");
+        Self {
+            hasher,
+            element_hashes: Vec::new(),
+            digests: vec![seed],
+        }
+    }
+
+    fn chain(&self, digest: &Hasher::Output, element_hash: &Hasher::Output) -> Hasher::Output {
+        self.hasher.hash_multiple(&[digest.as_ref(), element_hash.as_ref()])
+    }
+}
+
+impl<Hasher: HashFunction> Accumulator for HashChainAccumulator<Hasher>
+where
+    Hasher::Output: AsRef<[u8]> + Clone + PartialEq + core::fmt::Debug,
+{
+    type Digest = Hasher::Output;
+    type Witness = HashChainWitness<Hasher::Output>;
+
+    fn insert(&mut self, data: &[u8]) -> Result<usize> {
+        let element_hash = self.hasher.hash(data);
+        let digest = self.chain(&self.digest(), &element_hash);
+        self.element_hashes.push(element_hash);
+        self.digests.push(digest);
+        Ok(self.element_hashes.len() - 1)
+    }
+
+    fn len(&self) -> usize {
+        self.element_hashes.len()
+    }
+
+    fn digest(&self) -> Hasher::Output {
+        self.digests[self.digests.len() - 1].clone()
+    }
+
+    fn prove(&self, index: usize) -> Result<Self::Witness> {
+        if index >= self.len() {
+            return Err(out_of_bounds(index, self.len()));
+        }
+        Ok(HashChainWitness {
+            prefix: self.digests[index].clone(),
+            suffix: self.element_hashes[index + 1..].to_vec(),
+        })
+    }
+
+    fn verify(&self, digest: &Hasher::Output, data: &[u8], witness: &Self::Witness) -> bool {
+        let mut acc = self.chain(&witness.prefix, &self.hasher.hash(data));
+        for element_hash in &witness.suffix {
+            acc = self.chain(&acc, element_hash);
+        }
+        crate::security::constant_time_eq(acc.as_ref(), digest.as_ref())
+    }
+
+    fn witness_size(&self, witness: &Self::Witness) -> usize {
+        (1 + witness.suffix.len()) * witness.prefix.as_ref().len()
+    }
+}
+
+#[cfg(feature = "rsa-accumulator")]
+pub use rsa::RsaAccumulator;
+
+#[cfg(feature = "rsa-accumulator")]
+mod rsa {
+    use super::*;
+
+    /// Arithmetic modulo an odd modulus in Montgomery form, on little-endian 32-bit limbs
+    #[derive(Debug, Clone)]
+    struct Montgomery {
+        modulus: Vec<u32>,
+        /// `-modulus^-1 mod 2^32`
+        n0_inv: u32,
+        /// `R^2 mod modulus` with `R = 2^(32 * limbs)`
+        r2: Vec<u32>,
+    }
+
+    impl Montgomery {
+        fn new(modulus: Vec<u32>) -> Self {
+            let mut inv: u32 = 1;
+            for _ in 0..5 {
+                inv = inv.wrapping_mul(2u32.wrapping_sub(modulus[0].wrapping_mul(inv)));
+            }
+            let mut montgomery = Self {
+                n0_inv: inv.wrapping_neg(),
+                r2: Vec::new(),
+                modulus,
+            };
+            // Double 1 up to R^2 = 2^(64 * limbs), reducing as we go
+            let mut r2 = montgomery.limbs(1);
+            for _ in 0..64 * montgomery.modulus.len() {
+                let overflow = shl1(&mut r2);
+                if overflow || !less_than(&r2, &montgomery.modulus) {
+                    sub_assign(&mut r2, &montgomery.modulus);
+                }
+            }
+            montgomery.r2 = r2;
+            montgomery
+        }
+
+        /// `value` as little-endian limbs of the modulus width
+        fn limbs(&self, value: u64) -> Vec<u32> {
+            let mut limbs = vec![0u32; self.modulus.len()];
+            limbs[0] = value as u32;
+            if limbs.len() > 1 {
+                limbs[1] = (value >> 32) as u32;
+            }
+            limbs
+        }
+
+        /// `a * b / R mod modulus` (CIOS)
+        fn mul(&self, a: &[u32], b: &[u32]) -> Vec<u32> {
+            let n = &self.modulus;
+            let k = n.len();
+            let mut t = vec![0u32; k + 2];
+            for &b_i in b {
+                let mut carry = 0u64;
+                for j in 0..k {
+                    let sum = u64::from(t[j]) + u64::from(a[j]) * u64::from(b_i) + carry;
+                    t[j] = sum as u32;
+                    carry = sum >> 32;
+                }
+                let sum = u64::from(t[k]) + carry;
+                t[k] = sum as u32;
+                t[k + 1] = (sum >> 32) as u32;
+
+                let m = t[0].wrapping_mul(self.n0_inv);
+                let mut carry = (u64::from(t[0]) + u64::from(m) * u64::from(n[0])) >> 32;
+                for j in 1..k {
+                    let sum = u64::from(t[j]) + u64::from(m) * u64::from(n[j]) + carry;
+                    t[j - 1] = sum as u32;
+                    carry = sum >> 32;
+                }
+                let sum = u64::from(t[k]) + carry;
+                t[k - 1] = sum as u32;
+                t[k] = t[k + 1] + (sum >> 32) as u32;
+                t[k + 1] = 0;
+            }
+            let overflow = t[k] != 0;
+            t.truncate(k);
+            if overflow || !less_than(&t, n) {
+                sub_assign(&mut t, n);
+            }
+            t
+        }
+
+        /// Convert into Montgomery form
+        fn enter(&self, value: &[u32]) -> Vec<u32> {
+            self.mul(value, &self.r2)
+        }
+
+        /// Convert out of Montgomery form
+        fn leave(&self, value: &[u32]) -> Vec<u32> {
+            self.mul(value, &self.limbs(1))
+        }
+
+        /// `base^exponent` for `base` in Montgomery form
+        fn pow(&self, base: &[u32], exponent: u64) -> Vec<u32> {
+            let mut result = self.enter(&self.limbs(1));
+            for bit in (0..64 - exponent.leading_zeros()).rev() {
+                result = self.mul(&result, &result);
+                if (exponent >> bit) & 1 == 1 {
+                    result = self.mul(&result, base);
+                }
+            }
+            result
+        }
+
+        /// Parse a big-endian value, rejecting values not below the modulus
+        fn parse(&self, bytes: &[u8]) -> Option<Vec<u32>> {
+            let limbs = limbs_from_be(bytes, self.modulus.len())?;
+            less_than(&limbs, &self.modulus).then_some(limbs)
+        }
+
+        fn to_be_bytes(&self, limbs: &[u32]) -> Vec<u8> {
+            limbs.iter().rev().flat_map(|limb| limb.to_be_bytes()).collect()
+        }
+    }
+
+    fn limbs_from_be(bytes: &[u8], limbs: usize) -> Option<Vec<u32>> {
+        let bytes = &bytes[bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len())..];
+        if bytes.len() > limbs * 4 {
+            return None;
+        }
+        let mut out = vec![0u32; limbs];
+        for (i, &byte) in bytes.iter().rev().enumerate() {
+            out[i / 4] |= u32::from(byte) << (8 * (i % 4));
+        }
+        Some(out)
+    }
+
+    fn less_than(a: &[u32], b: &[u32]) -> bool {
+        for (x, y) in a.iter().rev().zip(b.iter().rev()) {
+            if x != y {
+                return x < y;
+            }
+        }
+        false
+    }
+
+    fn sub_assign(a: &mut [u32], b: &[u32]) {
+        let mut borrow = 0u64;
+        for (x, &y) in a.iter_mut().zip(b) {
+            let diff = u64::from(*x).wrapping_sub(u64::from(y)).wrapping_sub(borrow);
+            *x = diff as u32;
+            borrow = (diff >> 63) & 1;
+        }
+    }
+
+    fn shl1(a: &mut [u32]) -> bool {
+        let mut carry = 0;
+        for limb in a.iter_mut() {
+            let next = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+        carry == 1
+    }
+
+    fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+        (u128::from(a) * u128::from(b) % u128::from(m)) as u64
+    }
+
+    /// Deterministic Miller-Rabin for 64-bit integers
+    fn is_prime(n: u64) -> bool {
+        const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+        if n < 2 {
+            return false;
+        }
+        for p in BASES {
+            if n % p == 0 {
+                return n == p;
+            }
+        }
+        let (mut d, mut s) = (n - 1, 0);
+        while d % 2 == 0 {
+            d /= 2;
+            s += 1;
+        }
+        'bases: for a in BASES {
+            let mut x = 1u64;
+            let (mut base, mut e) = (a, d);
+            while e > 0 {
+                if e & 1 == 1 {
+                    x = mul_mod(x, base, n);
+                }
+                base = mul_mod(base, base, n);
+                e >>= 1;
+            }
+            if x == 1 || x == n - 1 {
+                continue;
+            }
+            for _ in 1..s {
+                x = mul_mod(x, x, n);
+                if x == n - 1 {
+                    continue 'bases;
+                }
+            }
+            return false;
+        }
+        true
+    }
+
+    /// RSA accumulator `g^(p_1 * ... * p_n) mod N` over hash-derived primes
+    ///
+    /// The modulus must come from a trusted setup whose factorization was
+    /// discarded. Elements map to 64-bit primes, which suits benchmarking
+    /// witness sizes and costs; production use needs larger primes. Proving
+    /// costs one exponentiation per other element.
+    pub struct RsaAccumulator<Hasher: HashFunction> {
+        hasher: Hasher,
+        montgomery: Montgomery,
+        generator: Vec<u32>,
+        value: Vec<u32>,
+        primes: Vec<u64>,
+    }
+
+    impl<Hasher: HashFunction> RsaAccumulator<Hasher>
+    where
+        Hasher::Output: AsRef<[u8]>,
+    {
+        /// Create an empty accumulator from a big-endian odd modulus and generator
+        pub fn new(hasher: Hasher, modulus: &[u8], generator: &[u8]) -> Result<Self> {
+            let invalid = |parameter: &str, reason: &str| ChronoMerkleError::InvalidConfiguration {
+                parameter: parameter.to_string(),
+                reason: reason.to_string(),
+            };
+            let limbs = modulus.iter().skip_while(|&&b| b == 0).count().div_ceil(4).max(1);
+            let modulus = limbs_from_be(modulus, limbs).ok_or_else(|| invalid("modulus", "Malformed modulus"))?;
+            if modulus[0] & 1 == 0 || (limbs == 1 && modulus[0] < 3) {
+                return Err(invalid("modulus", "Modulus must be odd and greater than 2"));
+            }
+            let montgomery = Montgomery::new(modulus);
+            let generator = montgomery
+                .parse(generator)
+                .filter(|g| less_than(&montgomery.limbs(1), g))
+                .ok_or_else(|| invalid("generator", "Generator must be greater than 1 and below the modulus"))?;
+            let generator = montgomery.enter(&generator);
+            Ok(Self {
+                hasher,
+                value: generator.clone(),
+                generator,
+                montgomery,
+                primes: Vec::new(),
+            })
+        }
+
+        /// Map an element to a 64-bit prime
+        fn hash_to_prime(&self, data: &[u8]) -> u64 {
+            (0u32..)
+                .map(|counter| {
+                    let hash = self.hasher.hash_multiple(&[b"chrono-merkle-prime", data, &crate::encoding::encode_u32(counter)]);
+                    let mut bytes = [0u8; 8];
+                    let len = hash.as_ref().len().min(8);
+                    bytes[..len].copy_from_slice(&hash.as_ref()[..len]);
+                    u64::from_le_bytes(bytes) | (1 << 63) | 1
+                })
+                .find(|&candidate| is_prime(candidate))
+                .unwrap_or(u64::MAX)
+        }
+    }
+
+    impl<Hasher: HashFunction> Accumulator for RsaAccumulator<Hasher>
+    where
+        Hasher::Output: AsRef<[u8]>,
+    {
+        type Digest = Vec<u8>;
+        type Witness = Vec<u8>;
+
+        fn insert(&mut self, data: &[u8]) -> Result<usize> {
+            let prime = self.hash_to_prime(data);
+            self.value = self.montgomery.pow(&self.value, prime);
+            self.primes.push(prime);
+            Ok(self.primes.len() - 1)
+        }
+
+        fn len(&self) -> usize {
+            self.primes.len()
+        }
+
+        fn digest(&self) -> Vec<u8> {
+            self.montgomery.to_be_bytes(&self.montgomery.leave(&self.value))
+        }
+
+        fn prove(&self, index: usize) -> Result<Vec<u8>> {
+            if index >= self.len() {
+                return Err(out_of_bounds(index, self.len()));
+            }
+            let mut witness = self.generator.clone();
+            for (i, &prime) in self.primes.iter().enumerate() {
+                if i != index {
+                    witness = self.montgomery.pow(&witness, prime);
+                }
+            }
+            Ok(self.montgomery.to_be_bytes(&self.montgomery.leave(&witness)))
+        }
+
+        fn verify(&self, digest: &Vec<u8>, data: &[u8], witness: &Vec<u8>) -> bool {
+            let (Some(witness), Some(digest)) = (self.montgomery.parse(witness), self.montgomery.parse(digest)) else {
+                return false;
+            };
+            let witness = self.montgomery.enter(&witness);
+            let value = self.montgomery.pow(&witness, self.hash_to_prime(data));
+            self.montgomery.leave(&value) == digest
+        }
+
+        fn witness_size(&self, witness: &Vec<u8>) -> usize {
+            witness.len()
+        }
+    }
+
+    #[cfg(all(test, feature = "blake3-hash"))]
+    mod tests {
+        use super::*;
+        use crate::hash::Blake3Hasher;
+
+        fn pow_mod(mut base: u64, mut exponent: u64, modulus: u64) -> u64 {
+            let mut result = 1;
+            base %= modulus;
+            while exponent > 0 {
+                if exponent & 1 == 1 {
+                    result = mul_mod(result, base, modulus);
+                }
+                base = mul_mod(base, base, modulus);
+                exponent >>= 1;
+            }
+            result
+        }
+
+        #[test]
+        fn test_montgomery_matches_u128() {
+            let modulus = 1_000_000_007u64 * 998_244_353;
+            let montgomery = Montgomery::new(limbs_from_be(&modulus.to_be_bytes(), 2).unwrap());
+            for (base, exponent) in [(2u64, 65_537u64), (123_456_789_012, u64::MAX), (modulus - 1, 3)] {
+                let result = montgomery.pow(&montgomery.enter(&montgomery.limbs(base)), exponent);
+                let bytes = montgomery.to_be_bytes(&montgomery.leave(&result));
+                assert_eq!(u64::from_be_bytes(bytes.try_into().unwrap()), pow_mod(base, exponent, modulus));
+            }
+            assert!(is_prime(18_446_744_073_709_551_557));
+            // Strong pseudoprime to bases 2, 3, 5 and 7
+            assert!(!is_prime(3_215_031_751));
+        }
+
+        #[test]
+        fn test_rsa_membership() {
+            // Product of two 64-bit primes, so arithmetic spans several limbs
+            let modulus = (18_446_744_073_709_551_557u128 * 18_446_744_073_709_551_533u128).to_be_bytes();
+            let mut acc = RsaAccumulator::new(Blake3Hasher, &modulus, &[3]).unwrap();
+            for i in 0..4 {
+                acc.insert(format!("element{}", i).as_bytes()).unwrap();
+            }
+            let digest = acc.digest();
+            let witness = acc.prove(2).unwrap();
+            assert!(acc.verify(&digest, b"element2", &witness));
+            assert!(!acc.verify(&digest, b"element9", &witness));
+            assert_eq!(acc.witness_size(&witness), 16);
+
+            assert!(RsaAccumulator::new(Blake3Hasher, &[4], &[3]).is_err());
+            assert!(RsaAccumulator::new(Blake3Hasher, &[35], &[1]).is_err());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    fn exercise<A: Accumulator>(mut acc: A) {
+        for i in 0..5 {
+            assert_eq!(acc.insert(format!("element{}", i).as_bytes()).unwrap(), i);
+        }
+        let digest = acc.digest();
+        let witness = acc.prove(1).unwrap();
+        assert!(acc.verify(&digest, b"element1", &witness));
+        assert!(!acc.verify(&digest, b"element7", &witness));
+        assert!(acc.witness_size(&witness) > 0);
+        assert!(acc.prove(5).is_err());
+
+        acc.insert(b"element5").unwrap();
+        assert!(!acc.verify(&acc.digest(), b"element1", &witness));
+    }
+
+    #[test]
+    fn test_accumulators() {
+        exercise(MerkleAccumulator::new(Blake3Hasher));
+        exercise(HashChainAccumulator::new(Blake3Hasher));
+    }
+}
@@ -0,0 +1,339 @@
+//! Quota-aware admission control for inserts
+//!
+//! A tree shared by several producers can be flooded by one of them. An
+//! [`AdmissionController`] registered with
+//! [`ChronoMerkleTree::set_admission_controller`] is consulted by `insert`,
+//! `insert_prehashed` and `insert_batch` before anything is hashed or stored.
+//! It keeps two token buckets, one counting inserts and one counting bytes of
+//! leaf data, that refill at the configured sustained rates up to their burst
+//! budgets. Writes over quota fail with [`ChronoMerkleError::QuotaExceeded`]
+//! and an [`AccessDenied`](crate::SecurityEventType::AccessDenied) event, or,
+//! with the `std` feature, can be delayed until the budget allows them.
+//!
+//! Without `std` there is no clock; the embedder reports elapsed time with
+//! [`AdmissionController::advance_clock`].
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+use core::time::Duration;
+
+#[cfg(feature = "no-std")]
+use alloc::string::ToString;
+
+/// Sustained rates and burst budgets of an [`AdmissionController`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdmissionPolicy {
+    /// Sustained inserts per second
+    pub max_inserts_per_sec: f64,
+    /// Sustained bytes of leaf data per second
+    pub max_bytes_per_sec: f64,
+    /// Inserts that may be made at once after an idle period
+    pub burst_inserts: u32,
+    /// Bytes that may be inserted at once after an idle period
+    pub burst_bytes: u64,
+}
+
+impl AdmissionPolicy {
+    /// Allow `inserts_per_sec` and `bytes_per_sec`, bursting up to one second's worth
+    pub fn per_second(inserts_per_sec: u32, bytes_per_sec: u64) -> Self {
+        Self {
+            max_inserts_per_sec: f64::from(inserts_per_sec),
+            max_bytes_per_sec: bytes_per_sec as f64,
+            burst_inserts: inserts_per_sec,
+            burst_bytes: bytes_per_sec,
+        }
+    }
+
+    /// Check that the policy admits anything at all
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |parameter: &str, reason: &str| ChronoMerkleError::InvalidConfiguration {
+            parameter: parameter.to_string(),
+            reason: reason.to_string(),
+        };
+        if !(self.max_inserts_per_sec.is_finite() && self.max_inserts_per_sec >= 0.0) {
+            return Err(invalid("max_inserts_per_sec", "must be a finite non-negative rate"));
+        }
+        if !(self.max_bytes_per_sec.is_finite() && self.max_bytes_per_sec >= 0.0) {
+            return Err(invalid("max_bytes_per_sec", "must be a finite non-negative rate"));
+        }
+        if self.burst_inserts == 0 {
+            return Err(invalid("burst_inserts", "must allow at least one insert"));
+        }
+        Ok(())
+    }
+}
+
+/// What happens to a write over quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverQuota {
+    /// Fail with [`ChronoMerkleError::QuotaExceeded`]
+    Reject,
+    /// Block until the budget allows the write, rejecting it if that takes
+    /// longer than `max_wait`
+    #[cfg(feature = "std")]
+    Delay {
+        /// Longest a write may be held back
+        max_wait: Duration,
+    },
+}
+
+/// Counts of admission decisions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AdmissionStats {
+    /// Inserts admitted, including delayed ones
+    pub admitted: u64,
+    /// Inserts admitted after waiting for budget
+    pub delayed: u64,
+    /// Inserts rejected
+    pub rejected: u64,
+}
+
+/// Token buckets limiting the insert rate of one tree
+#[derive(Debug, Clone)]
+pub struct AdmissionController {
+    policy: AdmissionPolicy,
+    over_quota: OverQuota,
+    inserts: f64,
+    bytes: f64,
+    refreshed: Duration,
+    #[cfg(feature = "std")]
+    started: std::time::Instant,
+    #[cfg(not(feature = "std"))]
+    clock: Duration,
+    stats: AdmissionStats,
+}
+
+impl AdmissionController {
+    /// Reject writes over `policy`, starting with full burst budgets
+    pub fn new(policy: AdmissionPolicy) -> Result<Self> {
+        policy.validate()?;
+        Ok(Self {
+            policy,
+            over_quota: OverQuota::Reject,
+            inserts: f64::from(policy.burst_inserts),
+            bytes: policy.burst_bytes as f64,
+            refreshed: Duration::ZERO,
+            #[cfg(feature = "std")]
+            started: std::time::Instant::now(),
+            #[cfg(not(feature = "std"))]
+            clock: Duration::ZERO,
+            stats: AdmissionStats::default(),
+        })
+    }
+
+    /// Delay writes over quota by up to `max_wait` instead of rejecting them
+    #[cfg(feature = "std")]
+    pub fn delaying(mut self, max_wait: Duration) -> Self {
+        self.over_quota = OverQuota::Delay { max_wait };
+        self
+    }
+
+    /// Get the policy
+    pub fn policy(&self) -> AdmissionPolicy {
+        self.policy
+    }
+
+    /// Get what happens to writes over quota
+    pub fn over_quota(&self) -> OverQuota {
+        self.over_quota
+    }
+
+    /// Get the counts of admission decisions
+    pub fn stats(&self) -> AdmissionStats {
+        self.stats
+    }
+
+    /// Report time passed since the last call, refilling the budgets
+    #[cfg(not(feature = "std"))]
+    pub fn advance_clock(&mut self, elapsed: Duration) {
+        self.clock = self.clock.saturating_add(elapsed);
+    }
+
+    fn now(&self) -> Duration {
+        #[cfg(feature = "std")]
+        {
+            self.started.elapsed()
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            self.clock
+        }
+    }
+
+    fn refill(&mut self, now: Duration) {
+        let elapsed = now.saturating_sub(self.refreshed).as_secs_f64();
+        self.inserts = (self.inserts + elapsed * self.policy.max_inserts_per_sec).min(f64::from(self.policy.burst_inserts));
+        self.bytes = (self.bytes + elapsed * self.policy.max_bytes_per_sec).min(self.policy.burst_bytes as f64);
+        self.refreshed = self.refreshed.max(now);
+    }
+
+    /// Time until both buckets hold the budget, `None` if they never will
+    fn wait_for(&self, inserts: f64, bytes: f64) -> Option<Duration> {
+        let wait = |have: f64, need: f64, rate: f64| {
+            if have >= need {
+                Some(0.0)
+            } else if rate > 0.0 {
+                Some((need - have) / rate)
+            } else {
+                None
+            }
+        };
+        let secs = wait(self.inserts, inserts, self.policy.max_inserts_per_sec)?
+            .max(wait(self.bytes, bytes, self.policy.max_bytes_per_sec)?);
+        Some(Duration::from_secs_f64(secs))
+    }
+
+    /// Take the budget for `inserts` writes of `bytes` in total, or fail
+    ///
+    /// Admission is all or nothing: a rejected batch consumes no budget.
+    pub fn admit(&mut self, inserts: usize, bytes: usize) -> Result<()> {
+        let (need_inserts, need_bytes) = (inserts as f64, bytes as f64);
+        let exceeded = |quota: &str, retry_after: Option<Duration>| ChronoMerkleError::QuotaExceeded {
+            quota: quota.to_string(),
+            retry_after_ms: retry_after.map(|wait| wait.as_millis().min(u128::from(u64::MAX)) as u64),
+        };
+        if inserts > self.policy.burst_inserts as usize || bytes as u64 > self.policy.burst_bytes {
+            self.stats.rejected += inserts as u64;
+            let quota = if inserts > self.policy.burst_inserts as usize { "inserts" } else { "bytes" };
+            return Err(exceeded(quota, None));
+        }
+
+        self.refill(self.now());
+        let Some(wait) = self.wait_for(need_inserts, need_bytes) else {
+            self.stats.rejected += inserts as u64;
+            return Err(exceeded(if self.inserts < need_inserts { "inserts" } else { "bytes" }, None));
+        };
+        if !wait.is_zero() {
+            match self.over_quota {
+                #[cfg(feature = "std")]
+                OverQuota::Delay { max_wait } if wait <= max_wait => {
+                    std::thread::sleep(wait);
+                    self.refill(self.now().max(self.refreshed + wait));
+                    self.stats.delayed += inserts as u64;
+                }
+                _ => {
+                    self.stats.rejected += inserts as u64;
+                    return Err(exceeded(if self.inserts < need_inserts { "inserts" } else { "bytes" }, Some(wait)));
+                }
+            }
+        }
+        self.inserts = (self.inserts - need_inserts).max(0.0);
+        self.bytes = (self.bytes - need_bytes).max(0.0);
+        self.stats.admitted += inserts as u64;
+        Ok(())
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Limit inserts into this tree with `controller`
+    pub fn set_admission_controller(&mut self, controller: AdmissionController) {
+        self.admission = Some(controller);
+    }
+
+    /// Stop limiting inserts, returning the controller that did
+    pub fn clear_admission_controller(&mut self) -> Option<AdmissionController> {
+        self.admission.take()
+    }
+
+    /// Get the controller limiting inserts, if any
+    pub fn admission_controller(&self) -> Option<&AdmissionController> {
+        self.admission.as_ref()
+    }
+
+    /// Admit `inserts` writes of `bytes` in total, logging rejections
+    pub(crate) fn check_admission(&mut self, inserts: usize, bytes: usize) -> Result<()> {
+        let Some(controller) = &mut self.admission else {
+            return Ok(());
+        };
+        let admitted = controller.admit(inserts, bytes);
+        if let Err(ChronoMerkleError::QuotaExceeded { quota, .. }) = &admitted {
+            crate::security::log_security_event!(
+                self.security_logger,
+                crate::security::events::insert_quota_exceeded(inserts, bytes, quota)
+            );
+        }
+        admitted
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    fn limited(policy: AdmissionPolicy) -> ChronoMerkleTree {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.set_admission_controller(AdmissionController::new(policy).unwrap());
+        tree
+    }
+
+    #[test]
+    fn test_over_quota_inserts_are_rejected() {
+        let mut tree = limited(AdmissionPolicy {
+            max_inserts_per_sec: 0.0,
+            max_bytes_per_sec: 0.0,
+            burst_inserts: 3,
+            burst_bytes: 1024,
+        });
+        tree.insert(b"a", 1000).unwrap();
+        tree.insert(b"b", 1001).unwrap();
+        // A batch over the remaining budget is rejected as a whole
+        let batch: Vec<(&[u8], u64)> = vec![(b"c", 1002), (b"d", 1003)];
+        let err = tree.insert_batch(&batch).unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::QuotaExceeded { ref quota, retry_after_ms: None } if quota == "inserts"));
+        assert_eq!(tree.leaf_count(), 2);
+        tree.insert(b"c", 1002).unwrap();
+        assert!(tree.insert(b"d", 1003).is_err());
+
+        let stats = tree.admission_controller().unwrap().stats();
+        assert_eq!((stats.admitted, stats.delayed, stats.rejected), (3, 0, 3));
+        assert!(tree.clear_admission_controller().is_some());
+        tree.insert(b"d", 1003).unwrap();
+    }
+
+    #[test]
+    fn test_byte_quota() {
+        let mut tree = limited(AdmissionPolicy::per_second(100, 10));
+        assert!(matches!(
+            tree.insert(&[0u8; 11], 1000),
+            Err(ChronoMerkleError::QuotaExceeded { ref quota, retry_after_ms: None }) if quota == "bytes"
+        ));
+        tree.insert(&[0u8; 8], 1000).unwrap();
+        match tree.insert(&[0u8; 8], 1001) {
+            Err(ChronoMerkleError::QuotaExceeded { quota, retry_after_ms: Some(ms) }) => {
+                assert_eq!(quota, "bytes");
+                assert!(ms <= 600);
+            }
+            other => panic!("expected a byte quota rejection, got {:?}", other),
+        }
+        assert!(AdmissionController::new(AdmissionPolicy::per_second(0, 10)).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_delayed_inserts_wait_for_budget() {
+        let policy = AdmissionPolicy {
+            max_inserts_per_sec: 200.0,
+            max_bytes_per_sec: 1e6,
+            burst_inserts: 1,
+            burst_bytes: 1024,
+        };
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.set_admission_controller(AdmissionController::new(policy).unwrap().delaying(Duration::from_secs(1)));
+        let started = std::time::Instant::now();
+        for i in 0..5u64 {
+            tree.insert(format!("leaf{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(15));
+        let stats = tree.admission_controller().unwrap().stats();
+        assert_eq!(stats.admitted, 5);
+        assert!(stats.delayed >= 3);
+    }
+}
@@ -0,0 +1,247 @@
+//! Activity counts per time bucket, optionally with differential privacy
+//!
+//! [`ChronoMerkleTree::aggregate_by_bucket`] counts the leaves stamped in each
+//! fixed-width bucket of a time window. Counts published as they are reveal
+//! when individual records arrived, e.g. a bucket holding a single leaf. A
+//! [`PrivacyPolicy`] (with `std`) adds Laplace noise of scale `1 / epsilon` to
+//! every bucket, which makes the histogram `epsilon`-differentially private
+//! with respect to adding or removing one record, and then suppresses buckets
+//! whose noisy count is below a threshold. Suppression looks only at noisy
+//! counts, so it does not reveal anything the noise hides.
+//!
+//! The guarantee holds for one release. Fresh noise on every query would let
+//! a reader average it away by asking again, so a service answering repeated
+//! queries should use [`PrivacyPolicy::apply_keyed`], whose noise is fixed per
+//! bucket start and width by a secret key: asking again returns the same
+//! noise. Every distinct bucket released, e.g. the same window with another
+//! width or a shifted start, still spends another `epsilon` on the records it
+//! covers, and the difference between two releases of a bucket whose records
+//! changed reveals that change exactly.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{format, string::ToString, vec::Vec};
+
+/// Most buckets one aggregate query may produce
+pub const MAX_BUCKETS: u64 = 100_000;
+
+/// Number of leaves in one time bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BucketCount {
+    /// First timestamp of the bucket
+    pub start: u64,
+    /// Leaves stamped in the bucket
+    pub count: u64,
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Count the leaves stamped in `start..=end` per bucket of `width` timestamps
+    ///
+    /// Buckets start at `start`; the last one may extend past `end` but only
+    /// counts leaves up to `end`. Every bucket is returned, empty ones included.
+    pub fn aggregate_by_bucket(&self, start: u64, end: u64, width: u64) -> Result<Vec<BucketCount>> {
+        if width == 0 {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "width".to_string(),
+                reason: "bucket width must be positive".to_string(),
+            });
+        }
+        if end < start {
+            return Ok(Vec::new());
+        }
+        let buckets = (end - start) / width;
+        if buckets >= MAX_BUCKETS {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "width".to_string(),
+                reason: format!("more than {} buckets", MAX_BUCKETS),
+            });
+        }
+        let buckets = buckets + 1;
+
+        let mut counts: Vec<BucketCount> = (0..buckets)
+            .map(|bucket| BucketCount { start: start + bucket * width, count: 0 })
+            .collect();
+        self.search_range(start, end, |index| {
+            let (timestamp, _) = self.nodes[index].timestamp_info();
+            counts[((timestamp - start) / width) as usize].count += 1;
+        });
+        Ok(counts)
+    }
+}
+
+/// Noise and suppression applied to published bucket counts
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrivacyPolicy {
+    /// Privacy budget of one published histogram; smaller is more private and noisier
+    pub epsilon: f64,
+    /// Buckets whose noisy count is below this are suppressed
+    pub min_count: u64,
+}
+
+/// Bucket count after a [`PrivacyPolicy`]
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrivateBucket {
+    /// First timestamp of the bucket
+    pub start: u64,
+    /// Noisy count, `None` if suppressed
+    pub count: Option<u64>,
+}
+
+#[cfg(feature = "std")]
+impl PrivacyPolicy {
+    /// Policy with budget `epsilon` suppressing noisy counts below `min_count`
+    pub fn new(epsilon: f64, min_count: u64) -> Result<Self> {
+        if !(epsilon.is_finite() && epsilon > 0.0) {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "epsilon".to_string(),
+                reason: format!("{} is not a positive finite number", epsilon),
+            });
+        }
+        Ok(Self { epsilon, min_count })
+    }
+
+    /// Noise and suppress `buckets`, drawing uniform numbers in `[0, 1)` from `uniform`
+    ///
+    /// `uniform` must be unpredictable to the readers of the result, e.g.
+    /// `rand::random`; the noise protects nothing if it can be recomputed.
+    /// Each call draws fresh noise, so this is only private if the same
+    /// buckets are not released again; see [`apply_keyed`](Self::apply_keyed).
+    pub fn apply(&self, buckets: &[BucketCount], mut uniform: impl FnMut() -> f64) -> Vec<PrivateBucket> {
+        self.apply_each(buckets, |_| uniform())
+    }
+
+    /// Noise and suppress buckets of `width`, deriving each bucket's noise from `key`
+    ///
+    /// The noise of a bucket is drawn from `hasher` over `key`, the bucket
+    /// start and `width`, so releasing the same bucket again returns the same
+    /// noise instead of a fresh sample to average. `key` must be secret and
+    /// long enough not to be guessed, e.g. 32 random bytes.
+    pub fn apply_keyed<Hasher: HashFunction>(
+        &self,
+        buckets: &[BucketCount],
+        width: u64,
+        key: &[u8],
+        hasher: &Hasher,
+    ) -> Vec<PrivateBucket> {
+        let mut input = key.to_vec();
+        self.apply_each(buckets, |bucket| {
+            input.truncate(key.len());
+            input.extend_from_slice(&crate::encoding::encode_u64(bucket.start));
+            input.extend_from_slice(&crate::encoding::encode_u64(width));
+            let digest = hasher.hash(&input);
+            let mut bits = [0u8; 8];
+            let len = digest.as_ref().len().min(8);
+            bits[..len].copy_from_slice(&digest.as_ref()[..len]);
+            (crate::encoding::decode_u64(&bits).expect("eight bytes") >> 11) as f64 / (1u64 << 53) as f64
+        })
+    }
+
+    /// Noise and suppress `buckets`, drawing each bucket's uniform number in `[0, 1)` from `uniform`
+    fn apply_each(&self, buckets: &[BucketCount], mut uniform: impl FnMut(&BucketCount) -> f64) -> Vec<PrivateBucket> {
+        let scale = 1.0 / self.epsilon;
+        buckets
+            .iter()
+            .map(|bucket| {
+                // Inverse CDF of the Laplace distribution
+                let u = uniform(bucket) - 0.5;
+                let noise = -scale * u.signum() * (1.0 - 2.0 * u.abs()).max(f64::MIN_POSITIVE).ln();
+                let noisy = (bucket.count as f64 + noise).round().max(0.0) as u64;
+                PrivateBucket {
+                    start: bucket.start,
+                    count: (noisy >= self.min_count).then_some(noisy),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate_by_bucket() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for timestamp in [1000u64, 1001, 1009, 1010, 1031, 1005] {
+            tree.insert(&timestamp.to_le_bytes(), timestamp).unwrap();
+        }
+        let counts: Vec<(u64, u64)> = tree
+            .aggregate_by_bucket(1000, 1035, 10)
+            .unwrap()
+            .iter()
+            .map(|bucket| (bucket.start, bucket.count))
+            .collect();
+        assert_eq!(counts, vec![(1000, 4), (1010, 1), (1020, 0), (1030, 1)]);
+        assert_eq!(tree.aggregate_by_bucket(1000, 1030, 10).unwrap()[3].count, 0);
+
+        assert!(tree.aggregate_by_bucket(1000, 1010, 0).is_err());
+        assert!(tree.aggregate_by_bucket(0, u64::MAX, 1).is_err());
+        assert!(tree.aggregate_by_bucket(2000, 1000, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_privacy_policy() {
+        assert!(PrivacyPolicy::new(0.0, 0).is_err());
+        assert!(PrivacyPolicy::new(f64::NAN, 0).is_err());
+
+        let buckets = [BucketCount { start: 0, count: 1 }, BucketCount { start: 10, count: 50 }];
+        let policy = PrivacyPolicy::new(1.0, 5).unwrap();
+
+        // The median draw adds no noise; extreme draws stay finite
+        let exact = policy.apply(&buckets, || 0.5);
+        assert_eq!(exact, vec![PrivateBucket { start: 0, count: None }, PrivateBucket { start: 10, count: Some(50) }]);
+        assert_eq!(policy.apply(&buckets, || 0.0)[1].count, None);
+
+        // Noise averages out and scales with 1 / epsilon
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut uniform = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let spread = |epsilon: f64, uniform: &mut dyn FnMut() -> f64| {
+            let policy = PrivacyPolicy::new(epsilon, 0).unwrap();
+            let counts: Vec<f64> = (0..2000)
+                .map(|_| policy.apply(&buckets[1..], &mut *uniform)[0].count.unwrap() as f64)
+                .collect();
+            let mean = counts.iter().sum::<f64>() / counts.len() as f64;
+            let deviation = counts.iter().map(|count| (count - 50.0).abs()).sum::<f64>() / counts.len() as f64;
+            (mean, deviation)
+        };
+        let (mean, tight) = spread(2.0, &mut uniform);
+        let (_, loose) = spread(0.2, &mut uniform);
+        assert!((mean - 50.0).abs() < 0.2, "{}", mean);
+        assert!(tight < 1.0 && loose > 3.0, "{} {}", tight, loose);
+    }
+
+    #[test]
+    fn test_keyed_noise_is_fixed_per_bucket() {
+        let hasher = crate::hash::Blake3Hasher;
+        let policy = PrivacyPolicy::new(0.1, 0).unwrap();
+        let buckets: Vec<BucketCount> = (0..64).map(|i| BucketCount { start: i * 10, count: 50 }).collect();
+
+        // Asking again returns the same noise, so averaging answers gains nothing
+        let first = policy.apply_keyed(&buckets, 10, b"secret", &hasher);
+        assert_eq!(policy.apply_keyed(&buckets, 10, b"secret", &hasher), first);
+        assert_eq!(policy.apply_keyed(&buckets[5..6], 10, b"secret", &hasher)[0], first[5]);
+
+        // Buckets, widths and keys each draw their own noise
+        assert!(first.iter().any(|bucket| bucket.count != first[0].count));
+        assert_ne!(policy.apply_keyed(&buckets, 5, b"secret", &hasher), first);
+        assert_ne!(policy.apply_keyed(&buckets, 10, b"other", &hasher), first);
+    }
+}
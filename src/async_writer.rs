@@ -0,0 +1,314 @@
+//! Batched asynchronous insert pipeline
+//!
+//! [`AsyncTreeWriter`] moves a tree onto a dedicated blocking worker thread.
+//! Inserts are sent over a channel, coalesced into batches and applied with a
+//! single rebuild per batch. Each insert returns an [`InsertFuture`] resolving to
+//! the root after its batch was applied, or with
+//! [`insert_with_receipt`](AsyncTreeWriter::insert_with_receipt) to an
+//! [`InsertReceipt`] naming the leaf. The futures do not depend on any
+//! particular executor.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::operations::InsertReceipt;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::JoinHandle;
+
+/// Shared state between an [`InsertFuture`] and the worker
+struct Slot<T> {
+    result: Option<Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Worker-side handle that resolves an [`InsertFuture`]
+///
+/// Dropping it without completing resolves the future with `WriterClosed`.
+struct Completer<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+impl<T> Completer<T> {
+    fn complete(&self, result: Result<T>) {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        if slot.result.is_none() {
+            slot.result = Some(result);
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        self.complete(Err(ChronoMerkleError::WriterClosed));
+    }
+}
+
+/// Future resolving once the insert's batch was applied
+///
+/// `T` is the tree root for [`AsyncTreeWriter::insert`] and an
+/// [`InsertReceipt`] for [`AsyncTreeWriter::insert_with_receipt`].
+pub struct InsertFuture<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+impl<T> Future for InsertFuture<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn pending_pair<T>() -> (InsertFuture<T>, Completer<T>) {
+    let slot = Arc::new(Mutex::new(Slot { result: None, waker: None }));
+    (InsertFuture { slot: slot.clone() }, Completer { slot })
+}
+
+/// What a queued insert resolves to
+enum Reply<H> {
+    Root(Completer<H>),
+    Receipt(Completer<InsertReceipt<H>>),
+}
+
+impl<H> Reply<H> {
+    fn fail(&self, error: ChronoMerkleError) {
+        match self {
+            Reply::Root(completer) => completer.complete(Err(error)),
+            Reply::Receipt(completer) => completer.complete(Err(error)),
+        }
+    }
+}
+
+/// A queued insert request
+struct WriteRequest<H> {
+    data: Vec<u8>,
+    timestamp: u64,
+    reply: Reply<H>,
+}
+
+/// Counters describing the writer's batching behaviour
+#[derive(Debug, Default)]
+struct WriterCounters {
+    batches: AtomicU64,
+    inserts: AtomicU64,
+}
+
+/// Snapshot of writer statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriterStats {
+    /// Number of batches applied
+    pub batches: u64,
+    /// Number of leaves inserted
+    pub inserts: u64,
+}
+
+/// Asynchronous, batching front-end for a [`ChronoMerkleTree`]
+pub struct AsyncTreeWriter<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    sender: mpsc::Sender<WriteRequest<H>>,
+    worker: JoinHandle<ChronoMerkleTree<H, Hasher, Logger>>,
+    counters: Arc<WriterCounters>,
+}
+
+impl<H, Hasher, Logger> AsyncTreeWriter<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde + 'static,
+    Hasher: HashFunction<Output = H> + Send + Sync + 'static,
+    Logger: SecurityLogger + 'static,
+{
+    /// Move `tree` onto a worker thread that applies at most `max_batch` inserts per rebuild
+    pub fn spawn(tree: ChronoMerkleTree<H, Hasher, Logger>, max_batch: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let counters = Arc::new(WriterCounters::default());
+        let worker_counters = counters.clone();
+        let max_batch = max_batch.max(1);
+        let worker = std::thread::spawn(move || run_worker(tree, receiver, max_batch, &worker_counters));
+        Self { sender, worker, counters }
+    }
+
+    /// Queue an insert, returning a future that resolves to the resulting root
+    pub fn insert(&self, data: Vec<u8>, timestamp: u64) -> InsertFuture<H> {
+        let (future, completer) = pending_pair();
+        // On a closed channel the request is dropped, resolving the future with WriterClosed
+        let _ = self.sender.send(WriteRequest { data, timestamp, reply: Reply::Root(completer) });
+        future
+    }
+
+    /// Queue an insert, returning a future that resolves to its receipt
+    ///
+    /// The receipt's old root is the root before the insert's batch.
+    pub fn insert_with_receipt(&self, data: Vec<u8>, timestamp: u64) -> InsertFuture<InsertReceipt<H>> {
+        let (future, completer) = pending_pair();
+        let _ = self.sender.send(WriteRequest { data, timestamp, reply: Reply::Receipt(completer) });
+        future
+    }
+
+    /// Get batching statistics
+    pub fn stats(&self) -> WriterStats {
+        WriterStats {
+            batches: self.counters.batches.load(Ordering::Relaxed),
+            inserts: self.counters.inserts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Stop accepting inserts, drain the queue and return the tree
+    pub fn shutdown(self) -> Result<ChronoMerkleTree<H, Hasher, Logger>> {
+        drop(self.sender);
+        self.worker.join().map_err(|_| ChronoMerkleError::WriterClosed)
+    }
+}
+
+fn run_worker<H, Hasher, Logger>(
+    mut tree: ChronoMerkleTree<H, Hasher, Logger>,
+    receiver: mpsc::Receiver<WriteRequest<H>>,
+    max_batch: usize,
+    counters: &WriterCounters,
+) -> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        while batch.len() < max_batch {
+            match receiver.try_recv() {
+                Ok(request) => batch.push(request),
+                Err(_) => break,
+            }
+        }
+
+        // Reject invalid requests individually so they don't fail the whole batch
+        let mut accepted = Vec::with_capacity(batch.len());
+        for request in batch {
+            match tree.validate_insert_inputs(&request.data, request.timestamp) {
+                Ok(()) => accepted.push(request),
+                Err(e) => request.reply.fail(e),
+            }
+        }
+        if accepted.is_empty() {
+            continue;
+        }
+
+        let entries: Vec<(&[u8], u64)> = accepted
+            .iter()
+            .map(|request| (request.data.as_slice(), request.timestamp))
+            .collect();
+        let (old_root, first_index) = (tree.root(), tree.leaf_count());
+        let result = tree
+            .insert_batch(&entries)
+            .and_then(|_| tree.root().ok_or(ChronoMerkleError::EmptyTree));
+
+        counters.batches.fetch_add(1, Ordering::Relaxed);
+        if result.is_ok() {
+            counters.inserts.fetch_add(accepted.len() as u64, Ordering::Relaxed);
+        }
+        for (offset, request) in accepted.iter().enumerate() {
+            match &request.reply {
+                Reply::Root(completer) => completer.complete(result.clone()),
+                Reply::Receipt(completer) => completer.complete(result.clone().map(|new_root| InsertReceipt {
+                    leaf_index: first_index + offset,
+                    timestamp: request.timestamp,
+                    old_root: old_root.clone(),
+                    new_root,
+                })),
+            }
+        }
+    }
+    tree
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct ThreadWaker(std::thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Arc::new(ThreadWaker(std::thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_async_writer_matches_sequential_tree() {
+        let writer = AsyncTreeWriter::spawn(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default(), 8);
+        let futures: Vec<_> = (0..20u64)
+            .map(|i| writer.insert(format!("data{}", i).into_bytes(), 1000 + i))
+            .collect();
+        let roots: Vec<_> = futures.into_iter().map(|f| block_on(f).unwrap()).collect();
+
+        let tree = writer.shutdown().unwrap();
+        assert_eq!(tree.leaf_count(), 20);
+        assert_eq!(roots.last().copied(), tree.root());
+
+        let mut reference: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..20u64 {
+            reference.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        assert_eq!(reference.root(), tree.root());
+    }
+
+    #[test]
+    fn test_receipts_name_each_leaf() {
+        let writer = AsyncTreeWriter::spawn(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default(), 8);
+        let first = block_on(writer.insert_with_receipt(b"data0".to_vec(), 1000)).unwrap();
+        assert_eq!((first.leaf_index, first.timestamp, first.old_root), (0, 1000, None));
+        let futures: Vec<_> = (1..6u64)
+            .map(|i| writer.insert_with_receipt(format!("data{}", i).into_bytes(), 1000 + i))
+            .collect();
+        let receipts: Vec<_> = futures.into_iter().map(|f| block_on(f).unwrap()).collect();
+        let tree = writer.shutdown().unwrap();
+        for (i, receipt) in receipts.iter().enumerate() {
+            assert_eq!(receipt.leaf_index, i + 1);
+            assert_eq!(tree.get_leaf_timestamp(receipt.leaf_index).unwrap(), receipt.timestamp);
+        }
+        assert_eq!(receipts.last().map(|receipt| receipt.new_root), tree.root());
+    }
+
+    #[test]
+    fn test_invalid_insert_rejected_individually() {
+        let writer = AsyncTreeWriter::spawn(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default(), 8);
+        let bad = writer.insert(Vec::new(), 1000);
+        let good = writer.insert(b"data".to_vec(), 1000);
+        assert!(block_on(bad).is_err());
+        assert!(block_on(good).is_ok());
+        assert_eq!(writer.stats().inserts, 1);
+        assert_eq!(writer.shutdown().unwrap().leaf_count(), 1);
+    }
+}
@@ -0,0 +1,241 @@
+//! Self-auditing security log anchored in a Merkle tree
+//!
+//! [`TreeBackedLogger`] buffers security events and commits each batch as one
+//! leaf of an audit tree. The leaf data is the [`canonical_encoding`] of the
+//! batch, so anyone holding the events can recompute the leaf and check its
+//! inclusion proof against a published audit root, making the operational log
+//! itself tamper-evident and time-anchored.
+
+use crate::encoding::{encode_index, encode_u32, encode_u64};
+use crate::error::Result;
+use crate::hash::HashFunction;
+use crate::security::{current_timestamp, SecurityEvent, SecurityLevel, SecurityLogger, SecurityMetadata};
+use crate::tree::ChronoMerkleTree;
+
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Version byte leading a [`canonical_encoding`] whose events carry correlation IDs
+pub const AUDIT_ENCODING_VERSION: u8 = 2;
+
+/// Logger committing batches of events to an audit tree
+///
+/// A batch is committed when it reaches `batch_size` events, when an event
+/// is logged more than `max_delay` seconds after the first buffered one
+/// occurred, on
+/// [`flush`](Self::flush) and when the logger is dropped. The audit tree uses
+/// [`NoOpLogger`](crate::NoOpLogger), so committing never logs back into this
+/// logger; it must not be the tree whose events are being logged.
+pub struct TreeBackedLogger<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+{
+    audit_tree: Arc<RwLock<ChronoMerkleTree<H, Hasher>>>,
+    buffer: Mutex<Vec<SecurityEvent>>,
+    batch_size: usize,
+    max_delay: u64,
+}
+
+impl<H, Hasher> TreeBackedLogger<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+{
+    /// Commit batches of up to `batch_size` events, or older than `max_delay` seconds, to `audit_tree`
+    pub fn new(audit_tree: Arc<RwLock<ChronoMerkleTree<H, Hasher>>>, batch_size: usize, max_delay: u64) -> Self {
+        Self {
+            audit_tree,
+            buffer: Mutex::new(Vec::new()),
+            batch_size: batch_size.max(1),
+            max_delay,
+        }
+    }
+
+    /// Audit tree receiving the commitments
+    pub fn audit_tree(&self) -> &Arc<RwLock<ChronoMerkleTree<H, Hasher>>> {
+        &self.audit_tree
+    }
+
+    /// Number of buffered events not yet committed
+    pub fn pending(&self) -> usize {
+        self.buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).len()
+    }
+
+    /// Commit the buffered events now
+    ///
+    /// Returns the index of the new audit leaf, or `None` if nothing was buffered.
+    pub fn flush(&self) -> Result<Option<usize>> {
+        let mut buffer = self.buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.commit(&mut buffer)
+    }
+
+    fn commit(&self, buffer: &mut Vec<SecurityEvent>) -> Result<Option<usize>> {
+        let Some(timestamp) = buffer.iter().map(|event| event.timestamp).max() else {
+            return Ok(None);
+        };
+        let mut tree = self.audit_tree.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Keep the audit leaves in time order even if event clocks disagree
+        let timestamp = tree
+            .leaf_count()
+            .checked_sub(1)
+            .and_then(|last| tree.get_leaf_timestamp(last).ok())
+            .map_or(timestamp, |last| timestamp.max(last));
+        let receipt = tree.insert_with_receipt(&canonical_encoding(buffer), timestamp)?;
+        buffer.clear();
+        Ok(Some(receipt.leaf_index))
+    }
+}
+
+impl<H, Hasher> SecurityLogger for TreeBackedLogger<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync + Send,
+{
+    fn log_event(&self, event: &SecurityEvent) -> Result<()> {
+        let mut buffer = self.buffer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let overdue = buffer
+            .first()
+            .is_some_and(|first| current_timestamp().saturating_sub(first.timestamp) > self.max_delay);
+        buffer.push(event.clone());
+        if overdue || buffer.len() >= self.batch_size {
+            self.commit(&mut buffer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<H, Hasher> Drop for TreeBackedLogger<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+{
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Encode a batch of events in the byte form committed by [`TreeBackedLogger`]
+///
+/// Integers are little-endian, strings and the event count are prefixed with
+/// their `u32` length, and enum variants are single tag bytes in declaration
+/// order. Absent metadata and absent optional strings are a zero tag.
+///
+/// Each event's correlation ID follows its metadata as an optional string.
+/// A batch without any correlation ID is encoded as version 1, which omits
+/// them, so batches committed before IDs existed still recompute.
+pub fn canonical_encoding(events: &[SecurityEvent]) -> Vec<u8> {
+    let with_context = events.iter().any(|event| event.context.is_some());
+    let mut out = vec![if with_context { AUDIT_ENCODING_VERSION } else { 1 }];
+    out.extend_from_slice(&encode_u32(events.len() as u32));
+    for event in events {
+        out.extend_from_slice(&encode_u64(event.timestamp));
+        out.push(event.event_type.index() as u8);
+        out.push(match event.level {
+            SecurityLevel::Info => 0,
+            SecurityLevel::Warning => 1,
+            SecurityLevel::Critical => 2,
+        });
+        put_str(&mut out, &event.description);
+        match &event.metadata {
+            None => out.push(0),
+            Some(SecurityMetadata::LeafInsertion { leaf_index, timestamp, data_hash }) => {
+                out.push(1);
+                out.extend_from_slice(&encode_index(*leaf_index));
+                out.extend_from_slice(&encode_u64(*timestamp));
+                put_str(&mut out, data_hash);
+            }
+            Some(SecurityMetadata::ProofVerification { leaf_index, proof_timestamp, verification_result, failure_reason }) => {
+                out.push(2);
+                out.extend_from_slice(&encode_index(*leaf_index));
+                out.extend_from_slice(&encode_u64(*proof_timestamp));
+                out.push(u8::from(*verification_result));
+                put_opt_str(&mut out, failure_reason.as_deref());
+            }
+            Some(SecurityMetadata::ConfigChange { parameter, old_value, new_value }) => {
+                out.push(3);
+                put_str(&mut out, parameter);
+                put_str(&mut out, old_value);
+                put_str(&mut out, new_value);
+            }
+            Some(SecurityMetadata::AccessDenied { client, resource, reason }) => {
+                out.push(4);
+                put_str(&mut out, client);
+                put_str(&mut out, resource);
+                put_str(&mut out, reason);
+            }
+            Some(SecurityMetadata::ValidationFailure { input_type, reason, input_value }) => {
+                out.push(5);
+                put_str(&mut out, input_type);
+                put_str(&mut out, reason);
+                put_opt_str(&mut out, input_value.as_deref());
+            }
+        }
+        if with_context {
+            put_opt_str(&mut out, event.context.as_ref().map(|id| id.as_str()));
+        }
+    }
+    out
+}
+
+fn put_str(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&encode_u32(value.len() as u32));
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn put_opt_str(out: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            out.push(1);
+            put_str(out, value);
+        }
+        None => out.push(0),
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+    use crate::security::events;
+
+    #[test]
+    fn test_batches_are_committed_as_leaves() {
+        let audit_tree = Arc::new(RwLock::new(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default()));
+        let logger = TreeBackedLogger::new(audit_tree.clone(), 2, 3600);
+
+        let logged: Vec<SecurityEvent> = (0..5).map(events::proof_generation).collect();
+        for event in &logged {
+            logger.log_event(event).unwrap();
+        }
+        assert_eq!((audit_tree.read().unwrap().leaf_count(), logger.pending()), (2, 1));
+        assert_eq!(logger.flush().unwrap(), Some(2));
+        assert_eq!(logger.flush().unwrap(), None);
+
+        let tree = audit_tree.read().unwrap();
+        let expected = canonical_encoding(&logged[2..4]);
+        assert_eq!(tree.get_leaf_data(1).unwrap().data(), Some(expected.as_slice()));
+        assert_eq!(tree.get_leaf_hash(2).unwrap(), Blake3Hasher.hash(&canonical_encoding(&logged[4..])));
+        assert!(tree.verify_proof(&tree.generate_proof(1).unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_overdue_batch_and_drop_flush() {
+        let audit_tree = Arc::new(RwLock::new(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default()));
+        let logger = TreeBackedLogger::new(audit_tree.clone(), 100, 60);
+        let mut stale = events::tree_initialization("old");
+        stale.timestamp -= 120;
+        logger.log_event(&stale).unwrap();
+        assert_eq!(audit_tree.read().unwrap().leaf_count(), 0);
+        logger.log_event(&events::access_denied("client", "/root", "no token")).unwrap();
+        assert_eq!(audit_tree.read().unwrap().leaf_count(), 1);
+
+        logger.log_event(&events::leaf_redaction(0, "gdpr")).unwrap();
+        drop(logger);
+        assert_eq!(audit_tree.read().unwrap().leaf_count(), 2);
+        assert_ne!(canonical_encoding(&[stale.clone()]), canonical_encoding(&[stale.clone(), stale.clone()]));
+
+        let correlated = stale.clone().with_context("req-1".into());
+        assert_eq!(canonical_encoding(&[stale.clone()])[0], 1);
+        assert_eq!(canonical_encoding(&[stale, correlated])[0], AUDIT_ENCODING_VERSION);
+    }
+}
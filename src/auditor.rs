@@ -0,0 +1,247 @@
+//! Gossip audit of published roots
+//!
+//! A log operator that shows different roots to different clients can hide
+//! entries from some of them. [`RootAuditor`] collects the roots observed from
+//! servers, peers and anchors and flags equivocation: two different roots
+//! claimed for the same tree size (or epoch). Each conflict is logged as a
+//! Critical [`TamperingDetected`](crate::SecurityEventType::TamperingDetected)
+//! event and returned as an [`EquivocationProof`] holding both observations,
+//! which serializes for handing to other parties.
+
+use crate::security::{NoOpLogger, SecurityLogger};
+
+#[cfg(feature = "no-std")]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::{collections::BTreeMap, string::String, vec::Vec};
+
+/// Where a root was observed
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObservationSource {
+    /// The log server itself
+    Server(String),
+    /// Another client gossiping the root it was shown
+    Peer(String),
+    /// An external anchor such as a blockchain transaction
+    Anchor(String),
+}
+
+impl core::fmt::Display for ObservationSource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ObservationSource::Server(name) => write!(f, "server:{}", name),
+            ObservationSource::Peer(name) => write!(f, "peer:{}", name),
+            ObservationSource::Anchor(name) => write!(f, "anchor:{}", name),
+        }
+    }
+}
+
+/// A root claimed for a tree size by one source
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootObservation<H> {
+    /// Where the root was seen
+    pub source: ObservationSource,
+    /// Tree size, or epoch number, the root is claimed for
+    pub tree_size: u64,
+    /// Claimed root
+    pub root: H,
+    /// When the root was observed (Unix timestamp)
+    pub observed_at: u64,
+    /// Operator signature over the root, if it was signed
+    pub signature: Option<Vec<u8>>,
+}
+
+impl<H> RootObservation<H> {
+    /// Create an unsigned observation made now
+    pub fn new(source: ObservationSource, tree_size: u64, root: H) -> Self {
+        Self {
+            source,
+            tree_size,
+            root,
+            observed_at: crate::security::current_timestamp(),
+            signature: None,
+        }
+    }
+
+    /// Attach the operator signature the root was published with
+    pub fn with_signature(mut self, signature: Vec<u8>) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+}
+
+/// Two observations claiming different roots for the same tree size
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquivocationProof<H> {
+    /// First root seen for the tree size
+    pub first: RootObservation<H>,
+    /// Conflicting root
+    pub second: RootObservation<H>,
+}
+
+impl<H: Eq> EquivocationProof<H> {
+    /// Tree size both roots are claimed for
+    pub fn tree_size(&self) -> u64 {
+        self.first.tree_size
+    }
+
+    /// Whether the observations actually conflict
+    ///
+    /// Signatures are not checked here; with signed observations the proof is
+    /// only conclusive once both signatures verify under the operator key.
+    pub fn is_conflicting(&self) -> bool {
+        self.first.tree_size == self.second.tree_size && self.first.root != self.second.root
+    }
+}
+
+/// Collects observed roots and detects equivocation
+pub struct RootAuditor<H, Logger = NoOpLogger> {
+    /// Distinct roots per tree size, in the order they were first seen
+    roots: BTreeMap<u64, Vec<RootObservation<H>>>,
+    equivocations: Vec<EquivocationProof<H>>,
+    observations: u64,
+    logger: Logger,
+}
+
+impl<H, Logger: Default> Default for RootAuditor<H, Logger> {
+    fn default() -> Self {
+        Self::new(Logger::default())
+    }
+}
+
+impl<H, Logger> RootAuditor<H, Logger> {
+    /// Create an auditor logging equivocations to `logger`
+    pub fn new(logger: Logger) -> Self {
+        Self {
+            roots: BTreeMap::new(),
+            equivocations: Vec::new(),
+            observations: 0,
+            logger,
+        }
+    }
+}
+
+impl<H, Logger> RootAuditor<H, Logger>
+where
+    H: Clone + Eq,
+    Logger: SecurityLogger,
+{
+    /// Record an observation
+    ///
+    /// Returns a proof if the root differs from the first root seen for the
+    /// same tree size. A root already seen for that size is not reported again.
+    pub fn observe(&mut self, observation: RootObservation<H>) -> Option<EquivocationProof<H>> {
+        self.observations += 1;
+        let seen = self.roots.entry(observation.tree_size).or_default();
+        if seen.iter().any(|known| known.root == observation.root) {
+            return None;
+        }
+        seen.push(observation.clone());
+        let first = seen.first().filter(|first| first.root != observation.root)?.clone();
+
+        crate::security::log_security_event!(
+            self.logger,
+            crate::security::events::root_equivocation(
+                observation.tree_size,
+                &first.source.to_string(),
+                &observation.source.to_string(),
+            )
+        );
+        let proof = EquivocationProof { first, second: observation };
+        self.equivocations.push(proof.clone());
+        Some(proof)
+    }
+
+    /// Every equivocation detected so far
+    pub fn equivocations(&self) -> &[EquivocationProof<H>] {
+        &self.equivocations
+    }
+
+    /// Distinct roots observed for `tree_size`
+    pub fn roots_for(&self, tree_size: u64) -> &[RootObservation<H>] {
+        self.roots.get(&tree_size).map_or(&[], Vec::as_slice)
+    }
+
+    /// Number of observations recorded
+    pub fn observations(&self) -> u64 {
+        self.observations
+    }
+
+    /// Forget observations for tree sizes below `tree_size`
+    ///
+    /// Detected equivocations are kept.
+    pub fn prune_below(&mut self, tree_size: u64) {
+        self.roots = self.roots.split_off(&tree_size);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::security::{SecurityEvent, SecurityEventType, SecurityLevel};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<SecurityEvent>>);
+
+    impl SecurityLogger for Recorder {
+        fn log_event(&self, event: &SecurityEvent) -> crate::error::Result<()> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    fn seen(source: ObservationSource, tree_size: u64, root: [u8; 4]) -> RootObservation<[u8; 4]> {
+        RootObservation::new(source, tree_size, root)
+    }
+
+    #[test]
+    fn test_equivocation_is_flagged_once() {
+        let mut auditor: RootAuditor<[u8; 4], Recorder> = RootAuditor::default();
+        let server = ObservationSource::Server("log.example".into());
+        let peer = ObservationSource::Peer("alice".into());
+        let anchor = ObservationSource::Anchor("btc:1234".into());
+
+        assert_eq!(auditor.observe(seen(server.clone(), 10, [1; 4])), None);
+        assert_eq!(auditor.observe(seen(peer.clone(), 10, [1; 4])), None);
+        assert_eq!(auditor.observe(seen(anchor.clone(), 11, [2; 4])), None);
+
+        let proof = auditor.observe(seen(peer.clone(), 11, [3; 4]).with_signature(vec![9])).unwrap();
+        assert!(proof.is_conflicting());
+        assert_eq!((proof.tree_size(), &proof.first.source, &proof.second.source), (11, &anchor, &peer));
+        assert_eq!(proof.second.signature, Some(vec![9]));
+        // The same conflicting root from another source is not reported again
+        assert_eq!(auditor.observe(seen(server, 11, [3; 4])), None);
+        assert_eq!(auditor.roots_for(11).len(), 2);
+        assert_eq!((auditor.observations(), auditor.equivocations().len()), (5, 1));
+
+        let logged = auditor.logger.0.lock().unwrap();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].event_type, SecurityEventType::TamperingDetected);
+        assert_eq!(logged[0].level, SecurityLevel::Critical);
+        assert!(logged[0].description.contains("anchor:btc:1234 and peer:alice"));
+    }
+
+    #[test]
+    fn test_prune_keeps_equivocations() {
+        let mut auditor: RootAuditor<[u8; 4]> = RootAuditor::default();
+        let peer = |name: &str| ObservationSource::Peer(name.into());
+        auditor.observe(seen(peer("a"), 1, [1; 4]));
+        auditor.observe(seen(peer("b"), 1, [2; 4])).unwrap();
+        auditor.observe(seen(peer("a"), 2, [1; 4]));
+        auditor.prune_below(2);
+        assert!(auditor.roots_for(1).is_empty());
+        assert_eq!(auditor.roots_for(2).len(), 1);
+        assert_eq!(auditor.equivocations().len(), 1);
+
+        #[cfg(all(feature = "serde", feature = "storage"))]
+        {
+            let proof = &auditor.equivocations()[0];
+            let json = serde_json::to_string(proof).unwrap();
+            assert_eq!(&serde_json::from_str::<EquivocationProof<[u8; 4]>>(&json).unwrap(), proof);
+        }
+    }
+}
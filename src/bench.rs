@@ -0,0 +1,135 @@
+//! Startup latency checks
+//!
+//! The Criterion benches in `benches/` are for development. [`quick_profile`]
+//! is for services that want to confirm at startup, in well under a second,
+//! that a tree with their configuration meets their latency budget on the
+//! machine they were deployed to:
+//!
+//! ```no_run
+//! # #[cfg(feature = "blake3-hash")]
+//! # {
+//! use chrono_merkle::bench::{quick_profile, QuickProfile};
+//!
+//! let budget = QuickProfile { leaves: 0, insert_ns: 50_000, proof_ns: 20_000, verify_ns: 20_000 };
+//! assert!(quick_profile().within(&budget));
+//! # }
+//! ```
+//!
+//! The estimates are averages over a small tree and vary from run to run;
+//! budgets should leave headroom.
+
+use std::time::Instant;
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::tree::{ChronoMerkleTree, TreeConfig};
+
+/// Leaves inserted by [`quick_profile`]
+pub const QUICK_PROFILE_LEAVES: usize = 1024;
+
+/// Leaves sampled for proof generation and verification
+const PROOF_SAMPLES: usize = 256;
+
+/// Average latency of tree operations on this machine, in nanoseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuickProfile {
+    /// Leaves in the profiled tree
+    pub leaves: usize,
+    /// Time per insert while the tree grew to `leaves`
+    pub insert_ns: u64,
+    /// Time per inclusion proof generated from the full tree
+    pub proof_ns: u64,
+    /// Time per inclusion proof verified against the full tree
+    pub verify_ns: u64,
+}
+
+impl QuickProfile {
+    /// Whether every estimate is at most the corresponding one in `budget`
+    ///
+    /// `budget.leaves` is ignored.
+    pub fn within(&self, budget: &QuickProfile) -> bool {
+        self.insert_ns <= budget.insert_ns && self.proof_ns <= budget.proof_ns && self.verify_ns <= budget.verify_ns
+    }
+}
+
+/// Profile a BLAKE3 tree with the default configuration over [`QUICK_PROFILE_LEAVES`] leaves
+#[cfg(feature = "blake3-hash")]
+pub fn quick_profile() -> QuickProfile {
+    profile_config(crate::hash::Blake3Hasher, TreeConfig::default(), QUICK_PROFILE_LEAVES)
+        .expect("default config accepts the profile's inserts")
+}
+
+/// Profile a tree with `hasher` and `config` grown to `leaves` leaves
+///
+/// Inserts use the current time as timestamp. Proofs are generated and
+/// verified for up to 256 leaves spread across the tree, using
+/// [`ChronoMerkleTree::verify_proof`] so configured checks such as
+/// constant-time verification are included. Errors are those of building
+/// the tree, e.g. an invalid `config`.
+pub fn profile_config<H, Hasher>(hasher: Hasher, config: TreeConfig, leaves: usize) -> Result<QuickProfile>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Default + Sync,
+{
+    if leaves == 0 {
+        return Err(ChronoMerkleError::InvalidConfiguration {
+            parameter: "leaves".to_string(),
+            reason: "profile needs at least one leaf".to_string(),
+        });
+    }
+    let mut tree: ChronoMerkleTree<H, Hasher> = ChronoMerkleTree::with_config(hasher, config)?;
+    let timestamp = crate::security::current_timestamp();
+
+    let started = Instant::now();
+    for i in 0..leaves {
+        tree.insert(&crate::encoding::encode_index(i), timestamp)?;
+    }
+    let insert_ns = per_op(started, leaves);
+
+    let samples: Vec<usize> = (0..leaves.min(PROOF_SAMPLES)).map(|i| i * leaves / leaves.min(PROOF_SAMPLES)).collect();
+    let started = Instant::now();
+    let proofs = samples.iter().map(|&index| tree.generate_proof(index)).collect::<Result<Vec<_>>>()?;
+    let proof_ns = per_op(started, proofs.len());
+
+    let started = Instant::now();
+    for proof in &proofs {
+        if !tree.verify_proof(proof)? {
+            return Err(ChronoMerkleError::ProofVerificationFailed {
+                reason: "profiled proof did not verify".to_string(),
+            });
+        }
+    }
+    let verify_ns = per_op(started, proofs.len());
+
+    Ok(QuickProfile { leaves, insert_ns, proof_ns, verify_ns })
+}
+
+/// Average nanoseconds per operation since `started`
+fn per_op(started: Instant, ops: usize) -> u64 {
+    (started.elapsed().as_nanos() / ops as u128).try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::Blake3Hasher;
+
+    #[test]
+    fn test_quick_profile() {
+        let started = Instant::now();
+        let profile = quick_profile();
+        assert_eq!(profile.leaves, QUICK_PROFILE_LEAVES);
+        assert!(profile.insert_ns > 0 && profile.proof_ns > 0 && profile.verify_ns > 0);
+        assert!(started.elapsed().as_secs() < 5);
+
+        assert!(profile.within(&QuickProfile { leaves: 0, insert_ns: u64::MAX, proof_ns: u64::MAX, verify_ns: u64::MAX }));
+        assert!(!profile.within(&QuickProfile { insert_ns: 0, ..profile }));
+    }
+
+    #[test]
+    fn test_profile_config() {
+        let config = TreeConfig { constant_time_proofs: true, ..Default::default() };
+        assert_eq!(profile_config(Blake3Hasher, config, 3).unwrap().leaves, 3);
+        assert!(profile_config(Blake3Hasher, TreeConfig::default(), 0).is_err());
+    }
+}
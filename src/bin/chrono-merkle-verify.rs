@@ -0,0 +1,61 @@
+//! Offline verifier for printable inclusion receipts
+//!
+//! ```text
+//! chrono-merkle-verify <TRUSTED_ROOT_HEX> [RECEIPT_FILE]
+//! ```
+//!
+//! Reads a receipt produced by `PrintableReceipt::to_text` from the file, or
+//! from stdin when the file is omitted or `-`, and checks it against the
+//! trusted root with BLAKE3. Exits with 0 if the receipt proves its leaf under
+//! that root and 1 otherwise. Built with the `verify-cli` feature only, so it
+//! needs neither storage nor server dependencies:
+//!
+//! ```text
+//! cargo install chrono-merkle --no-default-features --features verify-cli
+//! ```
+
+use chrono_merkle::{Blake3Hasher, PrintableReceipt, RootHash};
+use std::io::Read;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: chrono-merkle-verify <TRUSTED_ROOT_HEX> [RECEIPT_FILE]";
+
+fn run(args: &[String]) -> Result<PrintableReceipt<[u8; 32]>, String> {
+    let (root, path) = match args {
+        [root] => (root, None),
+        [root, path] => (root, Some(path).filter(|path| *path != "-")),
+        _ => return Err(USAGE.to_string()),
+    };
+    let trusted_root: RootHash<[u8; 32]> = root.parse().map_err(|e| format!("invalid trusted root: {}", e))?;
+
+    let mut text = String::new();
+    match path {
+        Some(path) => std::fs::File::open(path).and_then(|mut file| file.read_to_string(&mut text)),
+        None => std::io::stdin().read_to_string(&mut text),
+    }
+    .map_err(|e| format!("cannot read receipt: {}", e))?;
+
+    let receipt = PrintableReceipt::parse(&text).map_err(|e| format!("invalid receipt: {}", e))?;
+    match receipt.verify(&trusted_root.into_inner(), &Blake3Hasher) {
+        Ok(true) => Ok(receipt),
+        Ok(false) if receipt.root != trusted_root.into_inner() => {
+            Err(format!("receipt is for root {}, not the trusted root", RootHash(receipt.root)))
+        }
+        Ok(false) => Err("receipt path does not lead to the trusted root".to_string()),
+        Err(e) => Err(format!("invalid receipt: {}", e)),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(receipt) => {
+            println!("OK leaf {} at {}", receipt.leaf_index(), receipt.timestamp());
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("FAILED: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
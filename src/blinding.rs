@@ -0,0 +1,116 @@
+//! Blinded leaf commitments
+//!
+//! [`ChronoMerkleTree::insert_blinded`] commits to `hash(data || r)` for a
+//! random 32-byte [`BlindingFactor`] `r` and keeps no copy of the data, so
+//! publishing roots and proofs reveals nothing about low-entropy records. Only
+//! a verifier given both the record and its factor can check inclusion with
+//! [`verify_blinded`].
+
+use crate::error::Result;
+use crate::hash::HashFunction;
+use crate::proof::ChronoProof;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+/// Random value mixed into a blinded leaf hash
+///
+/// The factor must be kept secret and shared only with authorized verifiers;
+/// `Debug` does not print it.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct BlindingFactor(pub [u8; 32]);
+
+impl BlindingFactor {
+    /// Draw a factor from the thread-local cryptographically secure generator
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+
+    /// Get the factor bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl core::fmt::Debug for BlindingFactor {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("BlindingFactor(..)")
+    }
+}
+
+/// Hash committed to by a blinded leaf
+pub fn blinded_leaf_hash<Hasher: HashFunction>(hasher: &Hasher, data: &[u8], factor: &BlindingFactor) -> Hasher::Output {
+    hasher.hash_multiple(&[data, &factor.0])
+}
+
+/// Verify that `data` blinded with `factor` is included under `root`
+pub fn verify_blinded<H, Hasher>(
+    data: &[u8],
+    factor: &BlindingFactor,
+    proof: &ChronoProof<H>,
+    root: &H,
+    hasher: &Hasher,
+) -> Result<bool>
+where
+    H: AsRef<[u8]> + Clone + Eq,
+    Hasher: HashFunction<Output = H>,
+{
+    let leaf_hash = blinded_leaf_hash(hasher, data, factor);
+    crate::proof::verify_proof(proof, &leaf_hash, root, hasher)
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Insert a blinded commitment to `data` and return its blinding factor
+    ///
+    /// The leaf stores no data. Losing the returned factor makes the leaf
+    /// unverifiable.
+    pub fn insert_blinded(&mut self, data: &[u8], timestamp: u64) -> Result<BlindingFactor> {
+        let factor = BlindingFactor::random();
+        self.insert_blinded_with(data, timestamp, &factor)?;
+        Ok(factor)
+    }
+
+    /// Insert a blinded commitment to `data` using a caller-supplied factor
+    ///
+    /// Factors must be unpredictable and never reused, or equal records
+    /// become linkable.
+    pub fn insert_blinded_with(&mut self, data: &[u8], timestamp: u64, factor: &BlindingFactor) -> Result<()> {
+        self.require_leaf_hash_mode(crate::config::LeafHashMode::HashData)?;
+        self.validate_insert_inputs(data, timestamp)?;
+        let hash = blinded_leaf_hash(&self.hasher, data, factor);
+        self.insert_leaf(hash, timestamp, None)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    #[test]
+    fn test_blinded_inclusion() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert(b"public", 1000).unwrap();
+        let factor = tree.insert_blinded(b"salary=100", 1001).unwrap();
+        tree.insert(b"public2", 1002).unwrap();
+
+        assert_eq!(tree.get_leaf_data(1).unwrap(), crate::LeafData::NotRetained);
+        let proof = tree.generate_proof(1).unwrap();
+        let root = tree.root().unwrap();
+        assert!(tree.verify_proof(&proof).unwrap());
+        assert!(verify_blinded(b"salary=100", &factor, &proof, &root, &Blake3Hasher).unwrap());
+        assert!(!verify_blinded(b"salary=101", &factor, &proof, &root, &Blake3Hasher).unwrap());
+        assert!(!verify_blinded(b"salary=100", &BlindingFactor([0; 32]), &proof, &root, &Blake3Hasher).unwrap());
+
+        // The same record blinded twice commits to different hashes
+        let again = tree.insert_blinded(b"salary=100", 1003).unwrap();
+        assert_ne!(factor, again);
+        assert_ne!(tree.get_leaf_hash(1).unwrap(), tree.get_leaf_hash(3).unwrap());
+        assert_eq!(format!("{:?}", factor), "BlindingFactor(..)");
+    }
+}
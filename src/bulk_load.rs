@@ -0,0 +1,361 @@
+//! Streaming bulk loaders for migrating existing event archives
+//!
+//! Records are read line by line, validated, inserted in batches with a single
+//! rebuild per batch and optionally checkpointed to a storage backend, so memory
+//! use is bounded by the batch size rather than the archive size.
+//!
+//! A failed or cancelled load (see [`CancellationToken`](crate::CancellationToken))
+//! returns the tree to its state before the load; checkpoints already written
+//! to storage are kept. Only records that fail parsing or validation can be
+//! skipped; read and storage errors always end the load.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::storage::StorageBackend;
+use crate::tree::ChronoMerkleTree;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Options controlling a bulk load
+#[derive(Debug, Clone)]
+pub struct BulkLoadOptions {
+    /// Number of records inserted per rebuild
+    pub batch_size: usize,
+    /// Save a checkpoint after every N inserted records (`None` = only at the end)
+    pub checkpoint_every: Option<usize>,
+    /// Storage key used for checkpoints
+    pub checkpoint_key: String,
+    /// Skip records that fail parsing or validation instead of aborting
+    ///
+    /// Without it such a record fails the load with
+    /// [`ChronoMerkleError::InvalidRecord`].
+    pub skip_invalid: bool,
+}
+
+impl Default for BulkLoadOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 1024,
+            checkpoint_every: None,
+            checkpoint_key: "bulk_load".to_string(),
+            skip_invalid: false,
+        }
+    }
+}
+
+impl BulkLoadOptions {
+    /// Default options with the batch size chosen by [`TreeConfig::auto_tune`](crate::TreeConfig::auto_tune), if any
+    pub fn for_config(config: &crate::TreeConfig) -> Self {
+        let defaults = Self::default();
+        Self {
+            batch_size: config.tuning.map_or(defaults.batch_size, |profile| profile.batch_size),
+            ..defaults
+        }
+    }
+}
+
+/// Running counters reported to the progress callback and returned at the end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BulkLoadProgress {
+    /// Lines read from the input (excluding a CSV header)
+    pub records_read: usize,
+    /// Records inserted into the tree
+    pub inserted: usize,
+    /// Records skipped because they were invalid
+    pub skipped: usize,
+    /// Checkpoints written to storage
+    pub checkpoints: usize,
+}
+
+/// Column layout of a CSV archive
+#[derive(Debug, Clone)]
+pub struct CsvSchema {
+    /// Zero-based column holding the timestamp
+    pub timestamp_column: usize,
+    /// Zero-based column holding the record data
+    pub data_column: usize,
+    /// Whether the first line is a header to skip
+    pub has_header: bool,
+    /// Field delimiter
+    pub delimiter: char,
+}
+
+impl Default for CsvSchema {
+    fn default() -> Self {
+        Self {
+            timestamp_column: 0,
+            data_column: 1,
+            has_header: true,
+            delimiter: ',',
+        }
+    }
+}
+
+/// Input line read without I/O errors, parsed into a record or a parse error
+type ParsedLine = Result<Result<(Vec<u8>, u64)>>;
+
+/// A single JSONL record: `{"timestamp": 1700000000, "data": "..."}`
+#[derive(serde::Deserialize)]
+struct JsonRecord {
+    timestamp: u64,
+    data: String,
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Stream records from a JSON-lines file into the tree
+    pub fn bulk_load_jsonl(
+        &mut self,
+        path: impl AsRef<Path>,
+        options: &BulkLoadOptions,
+        storage: Option<&mut dyn StorageBackend>,
+        progress: impl FnMut(&BulkLoadProgress),
+    ) -> Result<BulkLoadProgress> {
+        let records = open_lines(path.as_ref())?.map(|line| {
+            Ok(serde_json::from_str::<JsonRecord>(&line?)
+                .map(|record| (record.data.into_bytes(), record.timestamp))
+                .map_err(|e| ChronoMerkleError::DeserializationError(e.to_string())))
+        });
+        self.bulk_load_records(records, options, storage, progress)
+    }
+
+    /// Stream records from a CSV file into the tree
+    pub fn bulk_load_csv(
+        &mut self,
+        path: impl AsRef<Path>,
+        schema: &CsvSchema,
+        options: &BulkLoadOptions,
+        storage: Option<&mut dyn StorageBackend>,
+        progress: impl FnMut(&BulkLoadProgress),
+    ) -> Result<BulkLoadProgress> {
+        let skip = usize::from(schema.has_header);
+        let records = open_lines(path.as_ref())?.skip(skip).map(|line| {
+            let fields = split_csv_line(&line?, schema.delimiter);
+            let field = |column: usize| {
+                fields.get(column).ok_or_else(|| {
+                    ChronoMerkleError::DeserializationError(format!("Missing CSV column {}", column))
+                })
+            };
+            Ok(field(schema.timestamp_column).and_then(|timestamp| {
+                let timestamp = timestamp.trim().parse::<u64>().map_err(|e| {
+                    ChronoMerkleError::DeserializationError(format!("Invalid timestamp: {}", e))
+                })?;
+                Ok((field(schema.data_column)?.clone().into_bytes(), timestamp))
+            }))
+        });
+        self.bulk_load_records(records, options, storage, progress)
+    }
+
+    /// Load records, restoring the tree to its state before the load if it fails
+    fn bulk_load_records(
+        &mut self,
+        records: impl Iterator<Item = ParsedLine>,
+        options: &BulkLoadOptions,
+        storage: Option<&mut dyn StorageBackend>,
+        progress: impl FnMut(&BulkLoadProgress),
+    ) -> Result<BulkLoadProgress> {
+        let checkpoint = self.snapshot();
+        let loaded = self.load_records(records, options, storage, progress);
+        if loaded.is_err() {
+            self.restore_snapshot(checkpoint);
+        }
+        loaded
+    }
+
+    fn load_records(
+        &mut self,
+        records: impl Iterator<Item = ParsedLine>,
+        options: &BulkLoadOptions,
+        mut storage: Option<&mut dyn StorageBackend>,
+        mut progress: impl FnMut(&BulkLoadProgress),
+    ) -> Result<BulkLoadProgress> {
+        let batch_size = options.batch_size.max(1);
+        let mut state = BulkLoadProgress::default();
+        let mut batch: Vec<(Vec<u8>, u64)> = Vec::with_capacity(batch_size);
+        let mut since_checkpoint = 0;
+
+        for (line, record) in records.enumerate() {
+            self.check_cancelled("bulk load")?;
+            state.records_read += 1;
+            let record = record?.and_then(|(data, timestamp)| {
+                self.validate_insert_inputs(&data, timestamp)?;
+                Ok((data, timestamp))
+            });
+            match record {
+                Ok(record) => batch.push(record),
+                Err(_) if options.skip_invalid => state.skipped += 1,
+                Err(e) => {
+                    return Err(ChronoMerkleError::InvalidRecord {
+                        record: line + 1,
+                        source: Box::new(e),
+                    });
+                }
+            }
+
+            if batch.len() >= batch_size {
+                since_checkpoint += self.flush_bulk_batch(&mut batch, &mut state)?;
+                if options.checkpoint_every.is_some_and(|every| since_checkpoint >= every) {
+                    if let Some(storage) = storage.as_deref_mut() {
+                        self.save_state(storage, &options.checkpoint_key)?;
+                        state.checkpoints += 1;
+                        since_checkpoint = 0;
+                    }
+                }
+                progress(&state);
+            }
+        }
+
+        self.flush_bulk_batch(&mut batch, &mut state)?;
+        if let Some(storage) = storage {
+            self.save_state(storage, &options.checkpoint_key)?;
+            state.checkpoints += 1;
+        }
+        progress(&state);
+        Ok(state)
+    }
+
+    fn flush_bulk_batch(&mut self, batch: &mut Vec<(Vec<u8>, u64)>, state: &mut BulkLoadProgress) -> Result<usize> {
+        let entries: Vec<(&[u8], u64)> = batch.iter().map(|(data, ts)| (data.as_slice(), *ts)).collect();
+        self.insert_batch(&entries)?;
+        let inserted = entries.len();
+        state.inserted += inserted;
+        batch.clear();
+        Ok(inserted)
+    }
+}
+
+fn open_lines(path: &Path) -> Result<impl Iterator<Item = Result<String>>> {
+    let file = File::open(path).map_err(|e| ChronoMerkleError::StorageError {
+        reason: format!("Failed to open {}: {}", path.display(), e),
+    })?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map(|line| {
+            line.map_err(|e| ChronoMerkleError::StorageError {
+                reason: format!("Failed to read line: {}", e),
+            })
+        })
+        .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty())))
+}
+
+/// Split a CSV line, honouring double-quoted fields with `""` escapes
+fn split_csv_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(core::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use std::io::Write;
+
+    #[test]
+    fn test_split_csv_line() {
+        assert_eq!(split_csv_line("1,hello", ','), vec!["1", "hello"]);
+        assert_eq!(split_csv_line("1,\"a, \"\"b\"\"\"", ','), vec!["1", "a, \"b\""]);
+    }
+
+    #[test]
+    fn test_bulk_load_jsonl_with_checkpoints() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for i in 0..25u64 {
+            writeln!(file, "{{\"timestamp\": {}, \"data\": \"event {}\"}}", 1000 + i, i).unwrap();
+        }
+
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut storage = MemoryStorage::new();
+        let options = BulkLoadOptions {
+            batch_size: 10,
+            checkpoint_every: Some(10),
+            ..Default::default()
+        };
+        let mut updates = 0;
+        let report = tree
+            .bulk_load_jsonl(file.path(), &options, Some(&mut storage), |_| updates += 1)
+            .unwrap();
+
+        assert_eq!(report.inserted, 25);
+        assert_eq!(report.checkpoints, 3);
+        assert_eq!(updates, 3);
+        assert_eq!(tree.leaf_count(), 25);
+
+        let restored: ChronoMerkleTree = ChronoMerkleTree::load_state(
+            &storage,
+            "bulk_load",
+            crate::hash::Blake3Hasher,
+            crate::security::NoOpLogger,
+        )
+        .unwrap();
+        assert_eq!(restored.root(), tree.root());
+    }
+
+    #[test]
+    fn test_bulk_load_csv_skips_invalid() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "timestamp,data").unwrap();
+        writeln!(file, "1000,first").unwrap();
+        writeln!(file, "not-a-number,second").unwrap();
+        writeln!(file, "1002,\"third, quoted\"").unwrap();
+
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let options = BulkLoadOptions { batch_size: 1, ..Default::default() };
+        let strict = tree.bulk_load_csv(file.path(), &CsvSchema::default(), &options, None, |_| {});
+        match strict.unwrap_err() {
+            ChronoMerkleError::InvalidRecord { record, source } => {
+                assert_eq!(record, 2);
+                assert!(matches!(*source, ChronoMerkleError::DeserializationError(_)));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        // The first record's batch was rolled back with the failed load
+        assert_eq!(tree.leaf_count(), 0);
+
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let options = BulkLoadOptions { skip_invalid: true, ..Default::default() };
+        let report = tree
+            .bulk_load_csv(file.path(), &CsvSchema::default(), &options, None, |_| {})
+            .unwrap();
+        assert_eq!(report.records_read, 3);
+        assert_eq!(report.inserted, 2);
+        assert_eq!(report.skipped, 1);
+    }
+
+    #[test]
+    fn test_read_errors_are_not_skipped() {
+        let read_error = || Err(ChronoMerkleError::StorageError { reason: "disk".to_string() });
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert(b"existing", 999).unwrap();
+        let root = tree.root();
+
+        // An endless stream of read errors ends the load instead of being skipped forever
+        let failing = std::iter::once(Ok(Ok((b"first".to_vec(), 1000)))).chain(std::iter::repeat_with(read_error));
+        let options = BulkLoadOptions { batch_size: 1, skip_invalid: true, ..Default::default() };
+        let err = tree.bulk_load_records(failing, &options, None, |_| {}).unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::StorageError { .. }));
+        assert_eq!(tree.root(), root);
+    }
+}
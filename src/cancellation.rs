@@ -0,0 +1,132 @@
+//! Cooperative cancellation of long operations
+//!
+//! A [`CancellationToken`] registered with
+//! [`ChronoMerkleTree::set_cancellation_token`] is checked between the levels
+//! of a rebuild, between records of a bulk load and between pages of a paged
+//! save. Once it is cancelled these operations fail with
+//! [`ChronoMerkleError::Cancelled`]; operations that modify the tree restore
+//! the state they started from, so a service shutting down can abort them
+//! without leaving a half-built tree behind.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::snapshot::TreeSnapshot;
+use crate::tree::ChronoMerkleTree;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "no-std")]
+use alloc::{string::ToString, sync::Arc};
+#[cfg(not(feature = "no-std"))]
+use std::sync::Arc;
+
+/// Shared flag requesting that long operations stop
+///
+/// Clones share the flag, so one clone can be kept by the shutdown logic while
+/// another is registered with the tree.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation; this cannot be undone
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    /// Whether cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Abort long operations on this tree once `token` is cancelled
+    ///
+    /// A cancelled token stays cancelled, so every later rebuild, including
+    /// the one of each insert, fails until the token is cleared or replaced.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    /// Stop honoring the cancellation token
+    pub fn clear_cancellation_token(&mut self) {
+        self.cancellation = None;
+    }
+
+    /// Fail with `Cancelled` if the registered token was cancelled
+    pub(crate) fn check_cancelled(&self, operation: &str) -> Result<()> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(ChronoMerkleError::Cancelled {
+                operation: operation.to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Snapshot to restore if the operation about to start is cancelled
+    ///
+    /// Only taken while a token is registered; the snapshot is copy-on-write,
+    /// so the cost is a single copy of the nodes on the next mutation.
+    pub(crate) fn cancellation_checkpoint(&self) -> Option<TreeSnapshot<H>> {
+        self.cancellation.as_ref().map(|_| self.snapshot())
+    }
+
+    /// Return `result`, first restoring `checkpoint` if it is a cancellation
+    pub(crate) fn restore_if_cancelled<T>(&mut self, checkpoint: Option<TreeSnapshot<H>>, result: Result<T>) -> Result<T> {
+        if let (Some(checkpoint), Err(ChronoMerkleError::Cancelled { .. })) = (checkpoint, &result) {
+            self.restore_snapshot(checkpoint);
+        }
+        result
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::ChronoMerkleTree;
+
+    fn tree(count: u64) -> ChronoMerkleTree {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..count {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn test_cancelled_insert_leaves_tree_unchanged() {
+        let mut tree = tree(4);
+        let root = tree.root();
+        let token = CancellationToken::new();
+        tree.set_cancellation_token(token.clone());
+        tree.insert(b"data4", 1004).unwrap();
+        let root_after_insert = tree.root();
+        assert_ne!(root_after_insert, root);
+
+        token.cancel();
+        let entries: Vec<(&[u8], u64)> = vec![(b"data5", 1005), (b"data6", 1006)];
+        let err = tree.insert_batch(&entries).unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::Cancelled { .. }));
+        assert!(matches!(tree.insert(b"data5", 1005), Err(ChronoMerkleError::Cancelled { .. })));
+        assert!(matches!(tree.rollback_to_timestamp(1002), Err(ChronoMerkleError::Cancelled { .. })));
+        assert_eq!(tree.root(), root_after_insert);
+        assert_eq!(tree.leaf_count(), 5);
+        assert_eq!(tree.find_by_timestamp(1005), Vec::<usize>::new());
+        assert_eq!(tree.find_by_timestamp(1004), vec![4]);
+
+        tree.clear_cancellation_token();
+        tree.insert(b"data5", 1005).unwrap();
+        assert_eq!(tree.leaf_count(), 6);
+    }
+}
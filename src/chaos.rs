@@ -0,0 +1,411 @@
+//! Failure injection for storage-integrated tests
+//!
+//! [`ChaosStorage`] wraps a backend and, with configurable probabilities,
+//! delays operations, fails them with [`ChronoMerkleError::StorageError`] and
+//! kills saves part way through. A killed streamed save stops feeding the
+//! inner backend mid-stream, as a crashed writer would; a killed plain save
+//! leaves a torn prefix of the value, as a non-atomic write would. Decisions
+//! come from a seeded generator, so a failing run can be replayed.
+//!
+//! [`crash_consistency_harness`] drives a tree through repeated inserts and
+//! saves under chaos and, after every save, recovers the tree from the inner
+//! backend as a restarted process would. Recovery must always yield either the
+//! last acknowledged state or the state being saved.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::NoOpLogger;
+use crate::storage::{BackendHealth, StorageBackend};
+use crate::tree::ChronoMerkleTree;
+
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Probabilities and seed of a [`ChaosStorage`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosConfig {
+    /// Probability that an operation fails before reaching the inner backend
+    pub fail_probability: f64,
+    /// Probability that an operation is delayed
+    pub delay_probability: f64,
+    /// Upper bound of an injected delay
+    pub max_delay: Duration,
+    /// Probability that a save is killed part way through
+    pub kill_probability: f64,
+    /// Seed of the decision generator
+    pub seed: u64,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            fail_probability: 0.1,
+            delay_probability: 0.0,
+            max_delay: Duration::from_millis(10),
+            kill_probability: 0.1,
+            seed: 0x5eed,
+        }
+    }
+}
+
+/// Faults injected by a [`ChaosStorage`] so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChaosStats {
+    /// Operations failed before reaching the inner backend
+    pub failures: u64,
+    /// Operations delayed
+    pub delays: u64,
+    /// Saves killed part way through
+    pub kills: u64,
+}
+
+/// Storage wrapper injecting failures, delays and killed saves
+pub struct ChaosStorage<S> {
+    inner: S,
+    config: ChaosConfig,
+    state: AtomicU64,
+    failures: AtomicU64,
+    delays: AtomicU64,
+    kills: AtomicU64,
+}
+
+impl<S: StorageBackend> ChaosStorage<S> {
+    /// Wrap `inner`, injecting faults as configured
+    pub fn new(inner: S, config: ChaosConfig) -> Self {
+        Self {
+            inner,
+            state: AtomicU64::new(config.seed),
+            config,
+            failures: AtomicU64::new(0),
+            delays: AtomicU64::new(0),
+            kills: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the chaos configuration
+    pub fn config(&self) -> &ChaosConfig {
+        &self.config
+    }
+
+    /// Faults injected so far
+    pub fn stats(&self) -> ChaosStats {
+        ChaosStats {
+            failures: self.failures.load(Ordering::Relaxed),
+            delays: self.delays.load(Ordering::Relaxed),
+            kills: self.kills.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get a reference to the wrapped backend, bypassing fault injection
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwrap into the inner backend
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Next value of the splitmix64 decision generator
+    fn next_u64(&self) -> u64 {
+        let mut z = self.state.fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn roll(&self, probability: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+
+    /// Delay and fail `operation` as configured
+    fn disturb(&self, operation: &str, key: &str) -> Result<()> {
+        if self.roll(self.config.delay_probability) {
+            self.delays.fetch_add(1, Ordering::Relaxed);
+            let max = self.config.max_delay.as_micros().max(1) as u64;
+            std::thread::sleep(Duration::from_micros(self.next_u64() % max));
+        }
+        if self.roll(self.config.fail_probability) {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            return Err(ChronoMerkleError::StorageError {
+                reason: format!("chaos: injected {} failure for key {}", operation, key),
+            });
+        }
+        Ok(())
+    }
+
+    fn killed(&self, key: &str, written: u64) -> ChronoMerkleError {
+        self.kills.fetch_add(1, Ordering::Relaxed);
+        ChronoMerkleError::StorageError {
+            reason: format!("chaos: save of key {} killed after {} bytes", key, written),
+        }
+    }
+}
+
+impl<S: StorageBackend> StorageBackend for ChaosStorage<S> {
+    fn save(&mut self, key: &str, data: &[u8]) -> Result<()> {
+        self.disturb("save", key)?;
+        if self.roll(self.config.kill_probability) {
+            let cut = self.next_u64() as usize % data.len().max(1);
+            self.inner.save(key, &data[..cut])?;
+            return Err(self.killed(key, cut as u64));
+        }
+        self.inner.save(key, data)
+    }
+
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.disturb("load", key)?;
+        self.inner.load(key)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        self.disturb("delete", key)?;
+        self.inner.delete(key)
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>> {
+        self.disturb("list_keys", "*")?;
+        self.inner.list_keys()
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        self.disturb("exists", key)?;
+        self.inner.exists(key)
+    }
+
+    fn save_stream(&mut self, key: &str, reader: &mut dyn Read) -> Result<u64> {
+        self.disturb("save_stream", key)?;
+        if !self.roll(self.config.kill_probability) {
+            return self.inner.save_stream(key, reader);
+        }
+        let mut dying = DyingReader {
+            inner: reader,
+            remaining: self.next_u64() % 4096,
+            read: 0,
+            died: false,
+        };
+        let result = self.inner.save_stream(key, &mut dying);
+        if dying.died {
+            return Err(self.killed(key, dying.read));
+        }
+        result
+    }
+
+    fn load_stream(&self, key: &str) -> Result<Option<Box<dyn Read + '_>>> {
+        self.disturb("load_stream", key)?;
+        self.inner.load_stream(key)
+    }
+
+    fn health_check(&self) -> Result<BackendHealth> {
+        self.inner.health_check()
+    }
+}
+
+/// Reader failing once `remaining` bytes have been passed through
+struct DyingReader<'a> {
+    inner: &'a mut dyn Read,
+    remaining: u64,
+    read: u64,
+    died: bool,
+}
+
+impl Read for DyingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 && !buf.is_empty() {
+            self.died = true;
+            return Err(std::io::Error::other("chaos: writer killed"));
+        }
+        let limit = buf.len().min(self.remaining as usize);
+        let n = self.inner.read(&mut buf[..limit])?;
+        self.remaining -= n as u64;
+        self.read += n as u64;
+        Ok(n)
+    }
+}
+
+/// Outcome of a [`crash_consistency_harness`] run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HarnessReport {
+    /// Insert-and-save rounds run
+    pub rounds: usize,
+    /// Saves acknowledged by the backend
+    pub acknowledged: usize,
+    /// Saves that returned an error
+    pub interrupted: usize,
+    /// Interrupted saves whose state was nevertheless recovered
+    pub landed_despite_error: usize,
+    /// Faults injected by the chaos wrapper
+    pub faults: ChaosStats,
+}
+
+/// Check crash consistency of saves to `storage` under `config`
+///
+/// Each round inserts a leaf, saves the tree under `key` through a
+/// [`ChaosStorage`] and then loads the tree from `storage` directly. The
+/// recovered root must be the root of the last acknowledged save or of the
+/// save just attempted; anything else, including an unreadable state, is
+/// reported as a [`ChronoMerkleError::StorageError`].
+pub fn crash_consistency_harness<H, Hasher, S>(
+    storage: S,
+    config: ChaosConfig,
+    key: &str,
+    rounds: usize,
+) -> Result<HarnessReport>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Default + Sync,
+    S: StorageBackend,
+{
+    let mut storage = ChaosStorage::new(storage, config);
+    let mut tree: ChronoMerkleTree<H, Hasher> = ChronoMerkleTree::new(Hasher::default());
+    let mut report = HarnessReport { rounds, ..Default::default() };
+    let mut durable: Option<H> = None;
+
+    for round in 0..rounds {
+        tree.insert(format!("chaos-record-{}", round).as_bytes(), round as u64)?;
+        let attempted = tree.root();
+        match tree.save_state(&mut storage, key) {
+            Ok(()) => {
+                report.acknowledged += 1;
+                durable = attempted.clone();
+            }
+            Err(_) => report.interrupted += 1,
+        }
+
+        let recovered = if storage.inner().exists(key)? {
+            let recovered: ChronoMerkleTree<H, Hasher> =
+                ChronoMerkleTree::load_state(storage.inner(), key, Hasher::default(), NoOpLogger).map_err(|e| {
+                    ChronoMerkleError::StorageError {
+                        reason: format!("crash consistency violated in round {}: state unreadable: {}", round, e),
+                    }
+                })?;
+            recovered.root()
+        } else {
+            None
+        };
+        if recovered != durable {
+            if recovered != attempted {
+                return Err(ChronoMerkleError::StorageError {
+                    reason: format!(
+                        "crash consistency violated in round {}: recovered root {:?} is neither {:?} nor {:?}",
+                        round, recovered, durable, attempted
+                    ),
+                });
+            }
+            report.landed_despite_error += 1;
+            durable = recovered;
+        }
+    }
+
+    report.faults = storage.stats();
+    Ok(report)
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+    use crate::storage::MemoryStorage;
+
+    fn config(fail_probability: f64, kill_probability: f64) -> ChaosConfig {
+        ChaosConfig {
+            fail_probability,
+            kill_probability,
+            ..ChaosConfig::default()
+        }
+    }
+
+    /// Backend whose streamed saves write each chunk as it arrives
+    struct NonAtomicStreams(MemoryStorage);
+
+    impl StorageBackend for NonAtomicStreams {
+        fn save(&mut self, key: &str, data: &[u8]) -> Result<()> {
+            self.0.save(key, data)
+        }
+
+        fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            self.0.load(key)
+        }
+
+        fn delete(&mut self, key: &str) -> Result<()> {
+            self.0.delete(key)
+        }
+
+        fn list_keys(&self) -> Result<Vec<String>> {
+            self.0.list_keys()
+        }
+
+        fn exists(&self, key: &str) -> Result<bool> {
+            self.0.exists(key)
+        }
+
+        fn save_stream(&mut self, key: &str, reader: &mut dyn Read) -> Result<u64> {
+            let mut written = Vec::new();
+            let mut chunk = [0u8; 256];
+            loop {
+                let n = reader.read(&mut chunk).map_err(|e| ChronoMerkleError::StorageError { reason: e.to_string() })?;
+                if n == 0 {
+                    return Ok(written.len() as u64);
+                }
+                written.extend_from_slice(&chunk[..n]);
+                self.0.save(key, &written)?;
+            }
+        }
+    }
+
+    #[test]
+    fn test_injected_failures_and_kills() {
+        let mut storage = ChaosStorage::new(MemoryStorage::new(), config(1.0, 0.0));
+        assert!(storage.save("key", b"value").is_err());
+        assert!(storage.load("key").is_err());
+        assert_eq!(storage.stats().failures, 2);
+
+        let mut storage = ChaosStorage::new(MemoryStorage::new(), config(0.0, 1.0));
+        storage.inner.save("key", b"old").unwrap();
+        let err = storage.save_stream("key", &mut &[7u8; 8192][..]).unwrap_err();
+        assert!(err.to_string().contains("killed"));
+        assert_eq!(storage.inner().load("key").unwrap(), Some(b"old".to_vec()));
+        assert!(storage.save("key", b"torn value").is_err());
+        assert!(b"torn value".starts_with(&storage.inner().load("key").unwrap().unwrap()));
+        assert_eq!(storage.stats().kills, 2);
+    }
+
+    #[test]
+    fn test_decisions_are_reproducible() {
+        let outcomes = |seed| {
+            let storage = ChaosStorage::new(MemoryStorage::new(), ChaosConfig { seed, ..config(0.5, 0.0) });
+            (0..32).map(|_| storage.load("key").is_ok()).collect::<Vec<_>>()
+        };
+        assert_eq!(outcomes(1), outcomes(1));
+        assert_ne!(outcomes(1), outcomes(2));
+    }
+
+    #[test]
+    fn test_atomic_saves_are_crash_consistent() {
+        let report =
+            crash_consistency_harness::<_, Blake3Hasher, _>(MemoryStorage::new(), config(0.2, 0.3), "tree", 60).unwrap();
+        assert_eq!(report.acknowledged + report.interrupted, 60);
+        assert!(report.faults.failures > 0 && report.faults.kills > 0);
+
+        #[cfg(feature = "file-storage")]
+        {
+            let dir = tempfile::tempdir().unwrap();
+            let files = crate::storage::FileStorage::new(dir.path().to_path_buf());
+            let report = crash_consistency_harness::<_, Blake3Hasher, _>(files, config(0.2, 0.3), "tree", 40).unwrap();
+            assert!(report.faults.kills > 0);
+        }
+    }
+
+    #[test]
+    fn test_harness_detects_torn_saves() {
+        let err = crash_consistency_harness::<_, Blake3Hasher, _>(
+            NonAtomicStreams(MemoryStorage::new()),
+            config(0.0, 0.5),
+            "tree",
+            60,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("crash consistency violated"));
+    }
+}
@@ -0,0 +1,300 @@
+//! Compact binary encoding helpers shared by proof and archive formats
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::traits::HashOutput;
+
+#[cfg(feature = "no-std")]
+use alloc::{string::{String, ToString}, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::vec::Vec;
+
+/// Append an unsigned LEB128 varint to the buffer
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Encode bytes as lowercase hex
+#[cfg(any(feature = "std", feature = "storage", feature = "encoding-strings"))]
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    use core::fmt::Write;
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+/// Encode bytes as standard padded base64 (RFC 4648)
+#[cfg(any(feature = "server", feature = "encoding-strings", feature = "trillian"))]
+pub(crate) fn to_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for position in 0..4 {
+            if position <= chunk.len() {
+                out.push(ALPHABET[(group >> (18 - 6 * position)) as usize & 0x3f] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode hex of either case, `None` if it is malformed
+#[cfg(any(feature = "serde", feature = "encoding-strings"))]
+pub(crate) fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let digit = |c: u8| (c as char).to_digit(16);
+            Some((digit(pair[0])? << 4 | digit(pair[1])?) as u8)
+        })
+        .collect()
+}
+
+/// Decode standard padded base64 (RFC 4648), `None` if it is malformed
+#[cfg(feature = "encoding-strings")]
+pub(crate) fn from_base64(text: &str) -> Option<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let bytes = text.as_bytes();
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(bytes.len() / 4 * 3);
+    for (i, chunk) in bytes.chunks(4).enumerate() {
+        let last = i + 1 == bytes.len() / 4;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+        let mut group = 0u32;
+        for &c in &chunk[..4 - padding] {
+            group = group << 6 | u32::from(sextet(c)?);
+        }
+        group <<= 6 * padding as u32;
+        out.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
+/// Encode bytes as unpadded uppercase base32 (RFC 4648)
+#[cfg(feature = "encoding-strings")]
+pub(crate) fn to_base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = buffer << 8 | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(ALPHABET[(buffer >> bits) as usize & 0x1f] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(ALPHABET[(buffer << (5 - bits)) as usize & 0x1f] as char);
+    }
+    out
+}
+
+/// Decode unpadded base32 of either case, `None` if it is malformed
+#[cfg(feature = "encoding-strings")]
+pub(crate) fn from_base32(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = buffer << 5 | u32::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    // Trailing bits are padding and must be zero
+    if bits >= 5 || buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+/// CRC-32 (IEEE) of the bytes
+#[cfg(feature = "encoding-strings")]
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { crc >> 1 ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// Append a little-endian u64 to the buffer
+pub(crate) fn write_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&crate::encoding::encode_u64(value));
+}
+
+/// Pack booleans into a bitset (LSB first)
+pub(crate) fn pack_bits(bits: &[bool]) -> Vec<u8> {
+    let mut packed = vec![0u8; bits.len().div_ceil(8)];
+    for (i, &bit) in bits.iter().enumerate() {
+        if bit {
+            packed[i / 8] |= 1 << (i % 8);
+        }
+    }
+    packed
+}
+
+/// Unpack `count` booleans from a bitset produced by [`pack_bits`]
+pub(crate) fn unpack_bits(packed: &[u8], count: usize) -> Vec<bool> {
+    (0..count).map(|i| packed[i / 8] & (1 << (i % 8)) != 0).collect()
+}
+
+/// Cursor over an encoded byte slice
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Create a reader positioned at the start of `bytes`
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Whether all input has been consumed
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    /// Read exactly `len` raw bytes
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len()).ok_or_else(|| {
+            ChronoMerkleError::InvalidProof {
+                message: "Unexpected end of encoded data".to_string(),
+            }
+        })?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    /// Read a single byte
+    pub(crate) fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Read a little-endian u64
+    pub(crate) fn read_u64(&mut self) -> Result<u64> {
+        Ok(crate::encoding::decode_u64(self.take(8)?).unwrap_or_default())
+    }
+
+    /// Read an unsigned LEB128 varint
+    pub(crate) fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ChronoMerkleError::InvalidProof {
+            message: "Varint exceeds 64 bits".to_string(),
+        })
+    }
+
+    /// Read a varint used as a length or index
+    pub(crate) fn read_len(&mut self) -> Result<usize> {
+        usize::try_from(self.read_varint()?).map_err(|_| ChronoMerkleError::InvalidProof {
+            message: "Encoded length does not fit in usize".to_string(),
+        })
+    }
+
+    /// Read a hash of `hash_len` bytes
+    pub(crate) fn read_hash<H: HashOutput>(&mut self, hash_len: usize) -> Result<H> {
+        H::from_bytes(self.take(hash_len)?).ok_or_else(|| ChronoMerkleError::InvalidProof {
+            message: "Invalid hash length in encoded data".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint_roundtrip() {
+        let mut out = Vec::new();
+        for value in [0u64, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            write_varint(&mut out, value);
+        }
+        let mut reader = Reader::new(&out);
+        for value in [0u64, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            assert_eq!(reader.read_varint().unwrap(), value);
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_bit_packing() {
+        let bits = [true, false, true, true, false, false, false, true, true];
+        let packed = pack_bits(&bits);
+        assert_eq!(packed.len(), 2);
+        assert_eq!(unpack_bits(&packed, bits.len()), bits.to_vec());
+    }
+
+    #[cfg(feature = "encoding-strings")]
+    #[test]
+    fn test_string_encodings_roundtrip() {
+        for len in 0..8 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 97 + 200) as u8).collect();
+            assert_eq!(from_hex(&to_hex(&bytes)).unwrap(), bytes);
+            assert_eq!(from_base64(&to_base64(&bytes)).unwrap(), bytes);
+        }
+        assert_eq!(from_hex("00FFab").unwrap(), vec![0, 255, 171]);
+        assert_eq!(to_base64(b"ab"), "YWI=");
+        assert!(from_hex("abc").is_none() && from_hex("zz").is_none());
+        assert!(from_base64("YW=I").is_none() && from_base64("YWI").is_none() && from_base64("Y===").is_none());
+        for len in 0..12 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 53 + 7) as u8).collect();
+            assert_eq!(from_base32(&to_base32(&bytes)).unwrap(), bytes);
+            assert_eq!(from_base32(&to_base32(&bytes).to_lowercase()).unwrap(), bytes);
+        }
+        assert_eq!(to_base32(b"foobar"), "MZXW6YTBOI");
+        assert!(from_base32("MZXW6YTBO1").is_none() && from_base32("MZXW6YTBOJ").is_none() && from_base32("M").is_none());
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_truncated_input() {
+        let mut reader = Reader::new(&[0x80]);
+        assert!(reader.read_varint().is_err());
+        let mut reader = Reader::new(&[1, 2, 3]);
+        assert!(reader.read_u64().is_err());
+    }
+}
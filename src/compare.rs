@@ -0,0 +1,206 @@
+//! Equality and structural comparison of trees
+//!
+//! Two trees are equal (`==`) when they have the same leaf count and root,
+//! which is all a verifier can tell apart. [`ChronoMerkleTree::structurally_equal`]
+//! goes further and compares every leaf and every internal node, and
+//! [`ChronoMerkleTree::first_difference`] names the first place two trees
+//! diverge, which is what [`assert_trees_equal!`](crate::assert_trees_equal)
+//! reports when it fails.
+
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+/// First place two trees diverge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeDifference {
+    /// The trees hold different numbers of leaves
+    LeafCount {
+        /// Leaves of the left tree
+        left: usize,
+        /// Leaves of the right tree
+        right: usize,
+    },
+    /// A leaf has a different hash
+    LeafHash {
+        /// Leaf index
+        index: usize,
+    },
+    /// A leaf has a different timestamp
+    LeafTimestamp {
+        /// Leaf index
+        index: usize,
+        /// Timestamp in the left tree
+        left: u64,
+        /// Timestamp in the right tree
+        right: u64,
+    },
+    /// An internal node has a different hash, or exists in only one tree
+    Node {
+        /// Level above the leaves, starting at 1
+        level: usize,
+        /// Position of the node within its level
+        index: usize,
+    },
+}
+
+impl core::fmt::Display for TreeDifference {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TreeDifference::LeafCount { left, right } => write!(f, "leaf counts differ: {} vs {}", left, right),
+            TreeDifference::LeafHash { index } => write!(f, "hash of leaf {} differs", index),
+            TreeDifference::LeafTimestamp { index, left, right } => {
+                write!(f, "timestamp of leaf {} differs: {} vs {}", index, left, right)
+            }
+            TreeDifference::Node { level, index } => write!(f, "node {} at level {} differs", index, level),
+        }
+    }
+}
+
+impl<H, Hasher, Logger, OtherHasher, OtherLogger> PartialEq<ChronoMerkleTree<H, OtherHasher, OtherLogger>>
+    for ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+    OtherHasher: HashFunction<Output = H> + Sync,
+    OtherLogger: SecurityLogger,
+{
+    /// Same leaf count and root
+    fn eq(&self, other: &ChronoMerkleTree<H, OtherHasher, OtherLogger>) -> bool {
+        self.leaf_count == other.leaf_count && self.root() == other.root()
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Whether every leaf hash and timestamp and every internal node match
+    ///
+    /// Leaf data, deltas and configuration are not compared; the leaf hashes
+    /// already commit to the data.
+    pub fn structurally_equal<OtherHasher, OtherLogger>(&self, other: &ChronoMerkleTree<H, OtherHasher, OtherLogger>) -> bool
+    where
+        OtherHasher: HashFunction<Output = H> + Sync,
+        OtherLogger: SecurityLogger,
+    {
+        self.first_difference(other).is_none()
+    }
+
+    /// First leaf, then lowest level, at which the trees differ
+    pub fn first_difference<OtherHasher, OtherLogger>(
+        &self,
+        other: &ChronoMerkleTree<H, OtherHasher, OtherLogger>,
+    ) -> Option<TreeDifference>
+    where
+        OtherHasher: HashFunction<Output = H> + Sync,
+        OtherLogger: SecurityLogger,
+    {
+        if self.leaf_count != other.leaf_count {
+            return Some(TreeDifference::LeafCount {
+                left: self.leaf_count,
+                right: other.leaf_count,
+            });
+        }
+        for (index, (left, right)) in self.nodes.leaves().iter().zip(other.nodes.leaves()).enumerate() {
+            if left.hash() != right.hash() {
+                return Some(TreeDifference::LeafHash { index });
+            }
+            let (left_ts, right_ts) = (left.timestamp_info().0, right.timestamp_info().0);
+            if left_ts != right_ts {
+                return Some(TreeDifference::LeafTimestamp { index, left: left_ts, right: right_ts });
+            }
+        }
+
+        let (mut offset, mut width, mut level) = (self.leaf_count, self.leaf_count, 0);
+        while width > 1 {
+            width = width.div_ceil(2);
+            level += 1;
+            for index in 0..width {
+                let left = self.nodes.get(offset + index).map(|node| node.hash());
+                let right = other.nodes.get(offset + index).map(|node| node.hash());
+                if left != right {
+                    return Some(TreeDifference::Node { level, index });
+                }
+            }
+            offset += width;
+        }
+        None
+    }
+}
+
+/// Assert that two trees are structurally equal
+///
+/// On failure, panics naming the first differing leaf or node, as reported by
+/// [`ChronoMerkleTree::first_difference`](crate::ChronoMerkleTree::first_difference).
+///
+/// ```rust
+/// # use chrono_merkle::{assert_trees_equal, ChronoMerkleTree};
+/// let mut a: ChronoMerkleTree = ChronoMerkleTree::default();
+/// let mut b: ChronoMerkleTree = ChronoMerkleTree::default();
+/// a.insert(b"data", 1000).unwrap();
+/// b.insert(b"data", 1000).unwrap();
+/// assert_trees_equal!(a, b);
+/// ```
+#[macro_export]
+macro_rules! assert_trees_equal {
+    ($left:expr, $right:expr $(,)?) => {
+        if let Some(difference) = $left.first_difference(&$right) {
+            panic!("assertion `left == right` failed: trees differ: {}", difference);
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        if let Some(difference) = $left.first_difference(&$right) {
+            panic!("assertion `left == right` failed: trees differ: {}: {}", difference, format_args!($($arg)+));
+        }
+    };
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    fn tree(count: u64) -> ChronoMerkleTree {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..count {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn test_equality_and_first_difference() {
+        let (a, b) = (tree(7), tree(7));
+        assert!(a == b);
+        assert!(a.structurally_equal(&b));
+        assert_trees_equal!(a, b);
+        assert!(a != tree(6));
+        assert_eq!(a.first_difference(&tree(6)), Some(TreeDifference::LeafCount { left: 7, right: 6 }));
+
+        let mut c: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..7u64 {
+            let data = if i == 4 { "other".to_string() } else { format!("data{}", i) };
+            c.insert(data.as_bytes(), 1000 + i).unwrap();
+        }
+        assert!(a != c);
+        assert_eq!(a.first_difference(&c), Some(TreeDifference::LeafHash { index: 4 }));
+
+        // Same root, but an internal node was tampered with
+        let mut d = tree(7);
+        let node = d.leaf_count + 1;
+        d.nodes[node] = d.nodes[0].clone();
+        assert!(a == d);
+        assert_eq!(a.first_difference(&d), Some(TreeDifference::Node { level: 1, index: 1 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "timestamp of leaf 0 differs: 1000 vs 5: after import")]
+    fn test_assert_macro_names_difference() {
+        let mut other: ChronoMerkleTree = ChronoMerkleTree::default();
+        other.insert(b"data0", 5).unwrap();
+        assert_trees_equal!(tree(1), other, "after {}", "import");
+    }
+}
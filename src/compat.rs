@@ -0,0 +1,166 @@
+//! Root compatibility with other Merkle tree crates
+//!
+//! [`cross_validate`] builds a set of records into a [`ChronoMerkleTree`] in
+//! the hashing mode matching another crate, builds the same records with that
+//! crate itself and checks that both roots are equal. Users migrating from
+//! `merkle_light` can run it over their own records before switching.
+//!
+//! | Target | Leaf | Node | Odd node on a level |
+//! |--------|------|------|---------------------|
+//! | `merkle_light` | `H(0x00 \|\| H(record))` | `H(0x01 \|\| left \|\| right)` | paired with itself |
+//!
+//! `rs_merkle` is not a target: it promotes the odd node of a level unchanged,
+//! while ChronoMerkle pairs it with itself, so their roots differ for every
+//! leaf count that is not a power of two.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{format, string::ToString, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::vec::Vec;
+
+/// Domain prefix of `merkle_light` leaf hashes
+pub const MERKLE_LIGHT_LEAF: u8 = 0x00;
+/// Domain prefix of `merkle_light` interior node hashes
+pub const MERKLE_LIGHT_INTERIOR: u8 = 0x01;
+
+/// Merkle crate whose root construction is reproduced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompatTarget {
+    /// `merkle_light::merkle::MerkleTree::from_data` over the records
+    MerkleLight,
+}
+
+/// Hasher matching `merkle_light`'s `from_data` leaves and interior nodes
+///
+/// `from_data` hashes each record before applying the leaf prefix, so a leaf
+/// is `H(0x00 || H(record))`.
+#[derive(Debug, Clone, Default)]
+pub struct MerkleLightHasher<Hasher>(pub Hasher);
+
+impl<Hasher: HashFunction> HashFunction for MerkleLightHasher<Hasher> {
+    type Output = Hasher::Output;
+
+    fn name(&self) -> &str {
+        "merkle-light"
+    }
+
+    fn hash(&self, data: &[u8]) -> Self::Output {
+        self.0.hash_multiple(&[&[MERKLE_LIGHT_LEAF], self.0.hash(data).as_ref()])
+    }
+
+    fn hash_pair(&self, left: &Self::Output, right: &Self::Output) -> Self::Output {
+        self.0.hash_multiple(&[&[MERKLE_LIGHT_INTERIOR], left.as_ref(), right.as_ref()])
+    }
+}
+
+/// `merkle_light` algorithm buffering its input for a ChronoMerkle hash function
+#[derive(Default)]
+struct MerkleLightAlgorithm<Hasher> {
+    hasher: Hasher,
+    buffer: Vec<u8>,
+}
+
+impl<Hasher: HashFunction> core::hash::Hasher for MerkleLightAlgorithm<Hasher> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.hasher.hash(&self.buffer);
+        let mut bytes = [0u8; 8];
+        let len = digest.as_ref().len().min(8);
+        bytes[..len].copy_from_slice(&digest.as_ref()[..len]);
+        u64::from_le_bytes(bytes)
+    }
+}
+
+impl<Hasher> merkle_light::hash::Algorithm<Hasher::Output> for MerkleLightAlgorithm<Hasher>
+where
+    Hasher: HashFunction + Default,
+    Hasher::Output: Clone,
+{
+    fn hash(&mut self) -> Hasher::Output {
+        self.hasher.hash(&self.buffer)
+    }
+}
+
+/// Root `merkle_light` computes for `records`
+///
+/// Fails unless there are at least two records; `merkle_light` cannot build
+/// smaller trees.
+pub fn merkle_light_root<H, Hasher>(records: &[&[u8]]) -> Result<H>
+where
+    H: AsRef<[u8]> + Clone + Ord,
+    Hasher: HashFunction<Output = H> + Default,
+{
+    if records.len() < 2 {
+        return Err(ChronoMerkleError::InvalidConfiguration {
+            parameter: "records".to_string(),
+            reason: format!("merkle_light needs at least two records, got {}", records.len()),
+        });
+    }
+    let tree: merkle_light::merkle::MerkleTree<H, MerkleLightAlgorithm<Hasher>> =
+        merkle_light::merkle::MerkleTree::from_data(records.iter().map(|record| record.to_vec()));
+    Ok(tree.root())
+}
+
+/// Build `records` into a tree in `target`'s hashing mode and check its root
+///
+/// Returns the common root, or an error naming both roots if they differ.
+pub fn cross_validate<H, Hasher>(target: CompatTarget, records: &[&[u8]], hasher: Hasher) -> Result<H>
+where
+    H: AsRef<[u8]> + Clone + Eq + Ord + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Clone + Default + Sync,
+{
+    let entries: Vec<(&[u8], u64)> = records.iter().enumerate().map(|(i, record)| (*record, i as u64)).collect();
+    let (root, expected) = match target {
+        CompatTarget::MerkleLight => {
+            let expected = merkle_light_root::<H, Hasher>(records)?;
+            let mut tree: ChronoMerkleTree<H, MerkleLightHasher<Hasher>> =
+                ChronoMerkleTree::new(MerkleLightHasher(hasher));
+            tree.insert_batch(&entries)?;
+            (tree.root().ok_or(ChronoMerkleError::EmptyTree)?, expected)
+        }
+    };
+    if root != expected {
+        return Err(ChronoMerkleError::InvalidConfiguration {
+            parameter: "compat".to_string(),
+            reason: format!("tree root {:?} differs from {:?} root {:?}", root, target, expected),
+        });
+    }
+    Ok(root)
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    fn records(count: usize) -> Vec<Vec<u8>> {
+        (0..count).map(|i| format!("record{}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_merkle_light_roots_match() {
+        for count in 2..=17 {
+            let records = records(count);
+            let records: Vec<&[u8]> = records.iter().map(Vec::as_slice).collect();
+            cross_validate(CompatTarget::MerkleLight, &records, Blake3Hasher).unwrap();
+        }
+
+        // Three records: leaves hash the record hash, the third is paired with itself
+        let h = |parts: &[&[u8]]| Blake3Hasher.hash(&parts.concat());
+        let leaf = |record: &[u8]| h(&[&[0], &Blake3Hasher.hash(record)]);
+        let (a, b, c) = (leaf(b"a"), leaf(b"b"), leaf(b"c"));
+        let expected = h(&[&[1], &h(&[&[1], &a, &b]), &h(&[&[1], &c, &c])]);
+        assert_eq!(merkle_light_root::<_, Blake3Hasher>(&[b"a", b"b", b"c"]).unwrap(), expected);
+
+        assert!(cross_validate(CompatTarget::MerkleLight, &[b"a"], Blake3Hasher).is_err());
+        assert!(cross_validate(CompatTarget::MerkleLight, &[], Blake3Hasher).is_err());
+    }
+}
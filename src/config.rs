@@ -7,12 +7,28 @@ use alloc::string::ToString;
 #[cfg(not(feature = "no-std"))]
 use std::string::ToString;
 
+/// How leaf hashes are derived from inserted records
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LeafHashMode {
+    /// Leaves are the hash of the inserted data
+    #[default]
+    HashData,
+    /// Leaves are hash commitments computed outside the tree, inserted as is
+    /// with [`insert_prehashed`](crate::ChronoMerkleTree::insert_prehashed)
+    TakeAsIs,
+}
+
 /// Configuration for ChronoMerkleTree
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone)]
 pub struct TreeConfig {
-    /// Sparse index sparsity factor
+    /// Sparse index sparsity factor: index every Nth inserted leaf
     pub sparse_index_sparsity: u64,
+    /// Maximum number of sparse index entries; the sparsity doubles whenever
+    /// the index would grow past it (`None` = unbounded)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sparse_index_max_entries: Option<usize>,
     /// Enable delta nodes for incremental updates
     pub enable_deltas: bool,
     /// Use incremental tree updates (vs full rebuilds)
@@ -22,12 +38,90 @@ pub struct TreeConfig {
     /// Use parallel tree construction (requires "parallel" feature)
     #[cfg_attr(feature = "serde", serde(default))]
     pub parallel_construction: bool,
+    /// Number of worker threads for parallel construction (`None` = global rayon pool)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parallel_threads: Option<usize>,
+    /// After a parallel rebuild, recompute every Nth level sequentially and
+    /// compare (`None` = off); divergence is logged as a Critical event
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub parallel_verify_every: Option<usize>,
+    /// Generate and verify proofs without data-dependent shortcuts: proofs bypass
+    /// the proof cache and verification runs one hash per level of the tree
+    /// whatever step fails
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub constant_time_proofs: bool,
+    /// How leaf hashes are derived; recorded in every generated proof
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub leaf_hash_mode: LeafHashMode,
+    /// Record the root after every change in the tree's root history
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub record_root_history: bool,
+    /// Include the delta log in saved states and state diffs
+    #[cfg_attr(feature = "serde", serde(default = "persisted_by_default"))]
+    pub persist_deltas: bool,
+    /// Include the root history in saved states and state diffs
+    #[cfg_attr(feature = "serde", serde(default = "persisted_by_default"))]
+    pub persist_root_history: bool,
+    /// Profile chosen by [`auto_tune`](Self::auto_tune), kept so a tuned
+    /// configuration can be reproduced with [`with_tuning`](Self::with_tuning)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tuning: Option<TuningProfile>,
+}
+
+/// Machine measurements and the settings [`TreeConfig::auto_tune`] derived from them
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TuningProfile {
+    /// Available CPU cores
+    pub cores: usize,
+    /// Measured internal node hashes per second on one core
+    pub hash_pairs_per_sec: u64,
+    /// Whether to build the tree in parallel
+    pub parallel_construction: bool,
+    /// Records per batch for `insert_batch` and bulk loading
+    pub batch_size: usize,
+    /// Sparse index sparsity factor
+    pub sparse_index_sparsity: u64,
+}
+
+impl TuningProfile {
+    /// Rebuild time above which parallel construction pays off
+    const PARALLEL_REBUILD_MICROS: u64 = 1_000;
+    /// Leaves of the reference tree used to estimate rebuild time
+    const REFERENCE_LEAVES: u64 = 1 << 16;
+
+    /// Derive settings from measurements
+    ///
+    /// - parallel construction when there are spare cores and rebuilding a
+    ///   tree of 64Ki leaves takes longer than 1 ms on one core;
+    /// - a batch is the number of hashes done in about 1 ms, so a batch
+    ///   rebuild stays cheap relative to the inserts it amortises;
+    /// - the sparse index is sparser when hashing is fast, because scanning
+    ///   the leaves between two index entries is then cheap.
+    pub fn from_measurements(cores: usize, hash_pairs_per_sec: u64) -> Self {
+        let pairs_per_milli = (hash_pairs_per_sec / 1_000).max(1);
+        let rebuild_micros = Self::REFERENCE_LEAVES.saturating_mul(1_000_000) / hash_pairs_per_sec.max(1);
+        Self {
+            cores,
+            hash_pairs_per_sec,
+            parallel_construction: cfg!(feature = "parallel") && cores > 1 && rebuild_micros > Self::PARALLEL_REBUILD_MICROS,
+            batch_size: (pairs_per_milli as usize).next_power_of_two().clamp(64, 1 << 16),
+            sparse_index_sparsity: (pairs_per_milli / 64).next_power_of_two().clamp(1, 64),
+        }
+    }
+}
+
+/// Configurations saved before the persistence flags existed kept everything
+#[cfg(feature = "serde")]
+fn persisted_by_default() -> bool {
+    true
 }
 
 impl Default for TreeConfig {
     fn default() -> Self {
         Self {
             sparse_index_sparsity: 1, // Index all timestamps by default
+            sparse_index_max_entries: None,
             enable_deltas: true, // Delta updates are working and provide rollback capabilities
             incremental_updates: true, // Enable incremental updates by default
             max_depth: 32, // SECURITY: Reduced from 64 to prevent excessive memory usage
@@ -35,6 +129,14 @@ impl Default for TreeConfig {
             parallel_construction: false, // SECURITY: Disable parallel by default to prevent timing issues
             #[cfg(not(feature = "parallel"))]
             parallel_construction: false,
+            parallel_threads: None,
+            parallel_verify_every: None,
+            constant_time_proofs: false,
+            leaf_hash_mode: LeafHashMode::HashData,
+            record_root_history: false,
+            persist_deltas: true,
+            persist_root_history: true,
+            tuning: None,
         }
     }
 }
@@ -49,6 +151,12 @@ impl TreeConfig {
                 reason: "Sparsity factor must be greater than 0".to_string(),
             });
         }
+        if self.sparse_index_max_entries == Some(0) {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "sparse_index_max_entries".to_string(),
+                reason: "Sparse index budget must be greater than 0".to_string(),
+            });
+        }
 
         // SECURITY: Limit maximum depth to prevent DoS through excessive memory usage
         if self.max_depth == 0 || self.max_depth > 64 {
@@ -58,6 +166,20 @@ impl TreeConfig {
             });
         }
 
+        if self.parallel_threads == Some(0) {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "parallel_threads".to_string(),
+                reason: "Thread count must be greater than 0".to_string(),
+            });
+        }
+
+        if self.parallel_verify_every == Some(0) {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "parallel_verify_every".to_string(),
+                reason: "Verification interval must be greater than 0".to_string(),
+            });
+        }
+
         // Delta nodes are now working correctly with rebuild mode
         if self.enable_deltas {
             // Deltas provide efficient incremental updates and rollback capabilities
@@ -76,10 +198,92 @@ impl TreeConfig {
     pub fn secure_defaults() -> Self {
         Self {
             sparse_index_sparsity: 1,
+            sparse_index_max_entries: None,
             enable_deltas: true, // Deltas are now working correctly
             incremental_updates: true, // Incremental updates are now working
             max_depth: 32, // Conservative limit
             parallel_construction: false, // Disabled to prevent timing variations
+            parallel_threads: None,
+            parallel_verify_every: None,
+            constant_time_proofs: false,
+            leaf_hash_mode: LeafHashMode::HashData,
+            record_root_history: false,
+            persist_deltas: true,
+            persist_root_history: true,
+            tuning: None,
+        }
+    }
+
+    /// Apply a [`TuningProfile`] and record it in the configuration
+    pub fn with_tuning(mut self, profile: TuningProfile) -> Self {
+        self.parallel_construction = profile.parallel_construction;
+        self.sparse_index_sparsity = profile.sparse_index_sparsity;
+        self.tuning = Some(profile);
+        self
+    }
+
+    /// Tune the configuration for this machine
+    ///
+    /// The first call in a process counts the cores and measures BLAKE3 node
+    /// hashing for a few milliseconds; later calls reuse the measurement. The
+    /// chosen [`TuningProfile`] is stored in [`tuning`](Self::tuning).
+    #[cfg(all(feature = "std", feature = "blake3-hash"))]
+    pub fn auto_tune(self) -> Self {
+        static PROFILE: std::sync::OnceLock<TuningProfile> = std::sync::OnceLock::new();
+        let profile = *PROFILE.get_or_init(|| {
+            let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+            TuningProfile::from_measurements(cores, measure_hash_pairs_per_sec())
+        });
+        self.with_tuning(profile)
+    }
+}
+
+/// Measure BLAKE3 internal node hashes per second on the current thread
+#[cfg(all(feature = "std", feature = "blake3-hash"))]
+fn measure_hash_pairs_per_sec() -> u64 {
+    use crate::hash::{Blake3Hasher, HashFunction};
+    use std::time::{Duration, Instant};
+
+    const ROUND: u64 = 256;
+    let hasher = Blake3Hasher;
+    let started = Instant::now();
+    let (mut pairs, mut node) = (0u64, [0u8; 32]);
+    while started.elapsed() < Duration::from_millis(5) {
+        for _ in 0..ROUND {
+            node = hasher.hash_pair(&node, &node);
         }
+        pairs += ROUND;
     }
-}
\ No newline at end of file
+    std::hint::black_box(node);
+    (pairs as u128 * 1_000_000_000 / started.elapsed().as_nanos().max(1)) as u64
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tuning_profile_from_measurements() {
+        let slow = TuningProfile::from_measurements(8, 1_000_000);
+        assert_eq!(slow.parallel_construction, cfg!(feature = "parallel"));
+        assert_eq!((slow.batch_size, slow.sparse_index_sparsity), (1024, 16));
+
+        let fast = TuningProfile::from_measurements(1, 200_000_000);
+        assert!(!fast.parallel_construction);
+        assert_eq!((fast.batch_size, fast.sparse_index_sparsity), (1 << 16, 64));
+
+        let config = TreeConfig::default().with_tuning(slow);
+        assert_eq!((config.sparse_index_sparsity, config.tuning), (16, Some(slow)));
+        config.validate().unwrap();
+    }
+
+    #[cfg(all(feature = "std", feature = "blake3-hash"))]
+    #[test]
+    fn test_auto_tune_is_measured_once() {
+        let first = TreeConfig::default().auto_tune();
+        let second = TreeConfig::secure_defaults().auto_tune();
+        let profile = first.tuning.unwrap();
+        assert_eq!(second.tuning, Some(profile));
+        assert!(profile.cores >= 1 && profile.hash_pairs_per_sec > 0);
+        first.validate().unwrap();
+    }
+}
@@ -0,0 +1,407 @@
+//! Event pipeline connectors
+//!
+//! [`Ingestor`] consumes records from a [`RecordSource`] — a Kafka consumer
+//! group or NATS JetStream consumer — and inserts them into a tree. It tracks
+//! the next offset of every partition and commits offsets back to the source
+//! only after a batch was applied. Records redelivered after a crash are
+//! recognised by their timestamp and payload hash already being in the tree,
+//! so every record is inserted exactly once. Records the tree can never accept,
+//! such as empty or oversized payloads, are skipped and committed past so they
+//! cannot stall their partition.
+//!
+//! [`HeadPublisher`] sends [`TreeHead`]s to a [`HeadSink`] whenever the root
+//! changes, optionally signed by a [`HeadSigner`].
+//!
+//! Client libraries are not bundled; adapters implement the traits on top of
+//! the consumer and producer of the broker in use.
+
+use crate::error::Result;
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A record read from a topic partition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceRecord {
+    /// Partition (or NATS stream shard) the record came from
+    pub partition: u32,
+    /// Offset of the record within its partition
+    pub offset: u64,
+    /// Timestamp to insert the record under, in the tree's time unit
+    pub timestamp: u64,
+    /// Record payload, inserted as leaf data
+    pub payload: Vec<u8>,
+}
+
+/// Source of records, such as a Kafka consumer or NATS subscription
+pub trait RecordSource {
+    /// Fetch up to `max` records; an empty result means nothing is available
+    fn poll(&mut self, max: usize) -> Result<Vec<SourceRecord>>;
+
+    /// Acknowledge every record of `partition` before `next_offset`
+    fn commit(&mut self, partition: u32, next_offset: u64) -> Result<()>;
+}
+
+/// Published summary of a tree state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeHead {
+    /// Root hash bytes
+    pub root: Vec<u8>,
+    /// Number of leaves
+    pub leaf_count: u64,
+    /// Timestamp of the newest leaf
+    pub timestamp: u64,
+    /// Signature over [`signing_bytes`](Self::signing_bytes), if a signer is configured
+    pub signature: Option<Vec<u8>>,
+}
+
+impl TreeHead {
+    /// Bytes covered by the signature: leaf count and timestamp (little-endian) followed by the root
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(16 + self.root.len());
+        out.extend_from_slice(&crate::encoding::encode_u64(self.leaf_count));
+        out.extend_from_slice(&crate::encoding::encode_u64(self.timestamp));
+        out.extend_from_slice(&self.root);
+        out
+    }
+}
+
+/// Signs tree heads before publication
+pub trait HeadSigner {
+    /// Sign `message`
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Destination for tree heads, such as a Kafka producer or NATS publisher
+pub trait HeadSink {
+    /// Publish a tree head
+    fn publish(&mut self, head: &TreeHead) -> Result<()>;
+}
+
+/// Outcome of one [`Ingestor::ingest`] call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IngestReport {
+    /// Records returned by the source
+    pub received: usize,
+    /// Records inserted into the tree
+    pub inserted: usize,
+    /// Records skipped because their offset was already processed
+    pub redelivered: usize,
+    /// Records skipped because the tree already holds them
+    pub duplicates: usize,
+    /// Records skipped because the tree rejects them, such as empty or oversized payloads
+    pub rejected: usize,
+}
+
+/// Offset-aware consumer feeding a tree
+pub struct Ingestor<Src> {
+    source: Src,
+    offsets: BTreeMap<u32, u64>,
+}
+
+impl<Src: RecordSource> Ingestor<Src> {
+    /// Consume from `source`, starting at whatever offsets it delivers
+    pub fn new(source: Src) -> Self {
+        Self {
+            source,
+            offsets: BTreeMap::new(),
+        }
+    }
+
+    /// Resume from previously stored next offsets per partition
+    pub fn with_offsets(source: Src, offsets: BTreeMap<u32, u64>) -> Self {
+        Self { source, offsets }
+    }
+
+    /// Next offset to process for every partition seen so far
+    pub fn offsets(&self) -> &BTreeMap<u32, u64> {
+        &self.offsets
+    }
+
+    /// Get a reference to the source
+    pub fn source(&self) -> &Src {
+        &self.source
+    }
+
+    /// Unwrap into the source
+    pub fn into_source(self) -> Src {
+        self.source
+    }
+
+    /// Poll up to `max` records and insert the new ones in as few batches as possible
+    ///
+    /// Each record is validated first; records failing validation or larger
+    /// than the tree's admission burst budget are counted as rejected and
+    /// skipped. The rest are split into batches fitting that budget. Offsets
+    /// are committed to the source only after every batch was inserted; if
+    /// inserting fails nothing is committed and the records are redelivered,
+    /// where those already inserted are then skipped as duplicates.
+    pub fn ingest<H, Hasher, Logger>(
+        &mut self,
+        tree: &mut ChronoMerkleTree<H, Hasher, Logger>,
+        max: usize,
+    ) -> Result<IngestReport>
+    where
+        H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+        Hasher: HashFunction<Output = H> + Sync,
+        Logger: SecurityLogger,
+    {
+        let records = self.source.poll(max)?;
+        let mut report = IngestReport {
+            received: records.len(),
+            ..IngestReport::default()
+        };
+
+        // Burst limits of the tree's admission controller, as (inserts, bytes)
+        #[cfg(not(feature = "minimal"))]
+        let budget = tree
+            .admission_controller()
+            .map(|controller| (controller.policy().burst_inserts as usize, controller.policy().burst_bytes));
+        #[cfg(feature = "minimal")]
+        let budget: Option<(usize, u64)> = None;
+        let mut next_offsets = self.offsets.clone();
+        let mut batch_keys = BTreeSet::new();
+        let mut accepted = Vec::new();
+        for record in &records {
+            let next = next_offsets.entry(record.partition).or_insert(0);
+            if record.offset < *next {
+                report.redelivered += 1;
+                continue;
+            }
+            *next = record.offset + 1;
+
+            let hash = tree.hasher.hash(&record.payload);
+            let in_tree = tree
+                .find_by_timestamp(record.timestamp)
+                .into_iter()
+                .any(|index| tree.get_leaf_hash(index).is_ok_and(|leaf| leaf == hash));
+            if in_tree || !batch_keys.insert((record.timestamp, hash.as_ref().to_vec())) {
+                report.duplicates += 1;
+                continue;
+            }
+            let over_budget = budget.is_some_and(|(_, burst_bytes)| record.payload.len() as u64 > burst_bytes);
+            if over_budget || tree.validate_insert_inputs(&record.payload, record.timestamp).is_err() {
+                report.rejected += 1;
+                continue;
+            }
+            accepted.push((record.payload.as_slice(), record.timestamp));
+        }
+
+        let mut batch_start = 0;
+        while batch_start < accepted.len() {
+            let mut batch_end = batch_start + 1;
+            if let Some((burst_inserts, burst_bytes)) = budget {
+                let mut bytes = accepted[batch_start].0.len() as u64;
+                while batch_end < accepted.len()
+                    && batch_end - batch_start < burst_inserts
+                    && bytes + accepted[batch_end].0.len() as u64 <= burst_bytes
+                {
+                    bytes += accepted[batch_end].0.len() as u64;
+                    batch_end += 1;
+                }
+            } else {
+                batch_end = accepted.len();
+            }
+            tree.insert_batch(&accepted[batch_start..batch_end])?;
+            batch_start = batch_end;
+        }
+        report.inserted = accepted.len();
+
+        for (&partition, &next) in &next_offsets {
+            if self.offsets.get(&partition) != Some(&next) {
+                self.source.commit(partition, next)?;
+                self.offsets.insert(partition, next);
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Publishes tree heads when the root changes
+pub struct HeadPublisher<Snk> {
+    sink: Snk,
+    signer: Option<Box<dyn HeadSigner + Send + Sync>>,
+    last_root: Option<Vec<u8>>,
+}
+
+impl<Snk: HeadSink> HeadPublisher<Snk> {
+    /// Publish unsigned heads to `sink`
+    pub fn new(sink: Snk) -> Self {
+        Self {
+            sink,
+            signer: None,
+            last_root: None,
+        }
+    }
+
+    /// Sign every published head with `signer`
+    pub fn with_signer(mut self, signer: impl HeadSigner + Send + Sync + 'static) -> Self {
+        self.signer = Some(Box::new(signer));
+        self
+    }
+
+    /// Get a reference to the sink
+    pub fn sink(&self) -> &Snk {
+        &self.sink
+    }
+
+    /// Publish the tree's head if its root changed since the last publication
+    pub fn publish<H, Hasher, Logger>(
+        &mut self,
+        tree: &ChronoMerkleTree<H, Hasher, Logger>,
+    ) -> Result<Option<TreeHead>>
+    where
+        H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+        Hasher: HashFunction<Output = H> + Sync,
+        Logger: SecurityLogger,
+    {
+        let Some(root) = tree.root() else {
+            return Ok(None);
+        };
+        let root = root.as_ref().to_vec();
+        if self.last_root.as_ref() == Some(&root) {
+            return Ok(None);
+        }
+
+        let leaf_count = tree.leaf_count();
+        let mut head = TreeHead {
+            root,
+            leaf_count: leaf_count as u64,
+            timestamp: tree.get_leaf_timestamp(leaf_count - 1)?,
+            signature: None,
+        };
+        if let Some(signer) = &self.signer {
+            head.signature = Some(signer.sign(&head.signing_bytes())?);
+        }
+        self.sink.publish(&head)?;
+        self.last_root = Some(head.root.clone());
+        Ok(Some(head))
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Source replaying queued records, remembering commits
+    #[derive(Default)]
+    struct QueueSource {
+        queue: VecDeque<SourceRecord>,
+        commits: Vec<(u32, u64)>,
+    }
+
+    impl RecordSource for QueueSource {
+        fn poll(&mut self, max: usize) -> Result<Vec<SourceRecord>> {
+            let count = max.min(self.queue.len());
+            Ok(self.queue.drain(..count).collect())
+        }
+
+        fn commit(&mut self, partition: u32, next_offset: u64) -> Result<()> {
+            self.commits.push((partition, next_offset));
+            Ok(())
+        }
+    }
+
+    fn record(partition: u32, offset: u64, timestamp: u64) -> SourceRecord {
+        SourceRecord {
+            partition,
+            offset,
+            timestamp,
+            payload: format!("event-{}", timestamp).into_bytes(),
+        }
+    }
+
+    #[test]
+    fn test_ingest_is_exactly_once() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut source = QueueSource::default();
+        source.queue.extend([record(0, 0, 1000), record(0, 1, 1001), record(1, 0, 1002)]);
+        let mut ingestor = Ingestor::new(source);
+
+        let report = ingestor.ingest(&mut tree, 10).unwrap();
+        assert_eq!((report.received, report.inserted), (3, 3));
+        assert_eq!(ingestor.offsets().get(&0), Some(&2));
+        assert_eq!(ingestor.source().commits, vec![(0, 2), (1, 1)]);
+
+        // Redelivery to the same consumer is filtered by offset
+        let mut source = ingestor.into_source();
+        source.queue.extend([record(0, 1, 1001), record(0, 2, 1003)]);
+        let mut ingestor = Ingestor::with_offsets(source, BTreeMap::from([(0, 2), (1, 1)]));
+        let report = ingestor.ingest(&mut tree, 10).unwrap();
+        assert_eq!((report.redelivered, report.inserted), (1, 1));
+
+        // After losing committed offsets, records already in the tree are skipped
+        let mut source = ingestor.into_source();
+        source.queue.extend([record(0, 0, 1000), record(1, 0, 1002)]);
+        let mut ingestor = Ingestor::new(source);
+        let report = ingestor.ingest(&mut tree, 10).unwrap();
+        assert_eq!((report.duplicates, report.inserted), (2, 0));
+        assert_eq!(tree.leaf_count(), 4);
+    }
+
+    #[cfg(not(feature = "minimal"))]
+    #[test]
+    fn test_invalid_records_are_skipped_and_committed() {
+        use crate::admission::{AdmissionController, AdmissionPolicy};
+
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        // Every record needs a batch of its own; payloads over 16 bytes never fit
+        tree.set_admission_controller(
+            AdmissionController::new(AdmissionPolicy {
+                max_inserts_per_sec: 1e9,
+                max_bytes_per_sec: 1e9,
+                burst_inserts: 2,
+                burst_bytes: 16,
+            })
+            .unwrap(),
+        );
+        let mut source = QueueSource::default();
+        let empty = SourceRecord { payload: Vec::new(), ..record(0, 1, 1001) };
+        let oversized = SourceRecord { payload: vec![7; 17], ..record(0, 3, 1003) };
+        source.queue.extend([record(0, 0, 1000), empty, record(0, 2, 1002), oversized, record(0, 4, 1004)]);
+        let mut ingestor = Ingestor::new(source);
+
+        let report = ingestor.ingest(&mut tree, 10).unwrap();
+        assert_eq!((report.received, report.inserted, report.rejected), (5, 3, 2));
+        assert_eq!(ingestor.source().commits, vec![(0, 5)]);
+        assert_eq!(tree.leaf_count(), 3);
+        assert_eq!(tree.find_by_timestamp(1004), vec![2]);
+    }
+
+    struct VecSink(Vec<TreeHead>);
+
+    impl HeadSink for VecSink {
+        fn publish(&mut self, head: &TreeHead) -> Result<()> {
+            self.0.push(head.clone());
+            Ok(())
+        }
+    }
+
+    struct XorSigner;
+
+    impl HeadSigner for XorSigner {
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+            Ok(vec![message.iter().fold(0, |acc, byte| acc ^ byte)])
+        }
+    }
+
+    #[test]
+    fn test_publishes_heads_on_root_change() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut publisher = HeadPublisher::new(VecSink(Vec::new())).with_signer(XorSigner);
+        assert!(publisher.publish(&tree).unwrap().is_none());
+
+        tree.insert(b"data1", 1000).unwrap();
+        let head = publisher.publish(&tree).unwrap().unwrap();
+        assert_eq!((head.leaf_count, head.timestamp), (1, 1000));
+        assert!(head.signature.is_some());
+        assert!(publisher.publish(&tree).unwrap().is_none());
+
+        tree.insert(b"data2", 1001).unwrap();
+        publisher.publish(&tree).unwrap();
+        assert_eq!(publisher.sink().0.len(), 2);
+    }
+}
@@ -4,6 +4,8 @@ use crate::config::TreeConfig;
 use crate::error::Result;
 use crate::hash::HashFunction;
 use crate::security::SecurityLogger;
+use crate::levels::Levels;
+use crate::snapshot::SharedVec;
 use crate::sparse_index::SparseIndex;
 use crate::tree::ChronoMerkleTree;
 
@@ -12,7 +14,7 @@ use alloc::format;
 #[cfg(not(feature = "no-std"))]
 impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: HashFunction<Output = H> + Default + Sync,
     Logger: SecurityLogger + Default,
 {
@@ -23,15 +25,33 @@ where
     {
         let config = TreeConfig::default();
         Self {
-            nodes: Vec::new(),
+            nodes: Levels::new(),
             leaf_count: 0,
-            sparse_index: SparseIndex::new(config.sparse_index_sparsity),
+            sparse_index: SparseIndex::with_budget(config.sparse_index_sparsity, config.sparse_index_max_entries),
             hasher,
             config: config.clone(),
             incremental_updates: config.incremental_updates,
             delta_chains: SparseIndex::new(config.sparse_index_sparsity),
-            stored_deltas: Vec::new(),
+            stored_deltas: SharedVec::new(),
+            root_history: SharedVec::new(),
             security_logger: Logger::default(),
+            #[cfg(feature = "std")]
+            proof_cache: None,
+            #[cfg(feature = "std")]
+            event_emitter: None,
+            redactions: Default::default(),
+            leaf_store: None,
+            redaction_signer: None,
+            #[cfg(not(feature = "minimal"))]
+            cancellation: None,
+            #[cfg(not(feature = "minimal"))]
+            admission: None,
+            #[cfg(not(feature = "minimal"))]
+            interceptors: Vec::new(),
+            #[cfg(feature = "std")]
+            progress_observer: None,
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
         }
     }
 
@@ -39,15 +59,33 @@ where
     pub fn with_logger(hasher: Hasher, logger: Logger) -> Self {
         let config = TreeConfig::default();
         Self {
-            nodes: Vec::new(),
+            nodes: Levels::new(),
             leaf_count: 0,
-            sparse_index: SparseIndex::new(config.sparse_index_sparsity),
+            sparse_index: SparseIndex::with_budget(config.sparse_index_sparsity, config.sparse_index_max_entries),
             hasher,
             config: config.clone(),
             incremental_updates: config.incremental_updates,
             delta_chains: SparseIndex::new(config.sparse_index_sparsity),
-            stored_deltas: Vec::new(),
+            stored_deltas: SharedVec::new(),
+            root_history: SharedVec::new(),
             security_logger: logger,
+            #[cfg(feature = "std")]
+            proof_cache: None,
+            #[cfg(feature = "std")]
+            event_emitter: None,
+            redactions: Default::default(),
+            leaf_store: None,
+            redaction_signer: None,
+            #[cfg(not(feature = "minimal"))]
+            cancellation: None,
+            #[cfg(not(feature = "minimal"))]
+            admission: None,
+            #[cfg(not(feature = "minimal"))]
+            interceptors: Vec::new(),
+            #[cfg(feature = "std")]
+            progress_observer: None,
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
         }
     }
 
@@ -57,27 +95,49 @@ where
         Logger: Default,
     {
         config.validate()?;
-        let sparse_index = SparseIndex::new(config.sparse_index_sparsity);
+        let sparse_index = SparseIndex::with_budget(config.sparse_index_sparsity, config.sparse_index_max_entries);
         let delta_chains = SparseIndex::new(config.sparse_index_sparsity);
         let tree = Self {
-            nodes: Vec::new(),
+            nodes: Levels::new(),
             leaf_count: 0,
             sparse_index,
             hasher,
             config: config.clone(),
             incremental_updates: config.incremental_updates,
             delta_chains,
-            stored_deltas: Vec::new(),
+            stored_deltas: SharedVec::new(),
+            root_history: SharedVec::new(),
             security_logger: Logger::default(),
+            #[cfg(feature = "std")]
+            proof_cache: None,
+            #[cfg(feature = "std")]
+            event_emitter: None,
+            redactions: Default::default(),
+            leaf_store: None,
+            redaction_signer: None,
+            #[cfg(not(feature = "minimal"))]
+            cancellation: None,
+            #[cfg(not(feature = "minimal"))]
+            admission: None,
+            #[cfg(not(feature = "minimal"))]
+            interceptors: Vec::new(),
+            #[cfg(feature = "std")]
+            progress_observer: None,
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
         };
 
         // Log tree initialization
-        let config_summary = format!("sparsity={}, deltas={}, incremental={}, max_depth={}",
-                                   config.sparse_index_sparsity,
-                                   config.enable_deltas,
-                                   config.incremental_updates,
-                                   config.max_depth);
-        let _ = tree.security_logger.log_event(&crate::security::events::tree_initialization(&config_summary));
+        crate::security::log_security_event!(
+            tree.security_logger,
+            crate::security::events::tree_initialization(&format!(
+                "sparsity={}, deltas={}, incremental={}, max_depth={}",
+                config.sparse_index_sparsity,
+                config.enable_deltas,
+                config.incremental_updates,
+                config.max_depth
+            ))
+        );
 
         Ok(tree)
     }
@@ -85,27 +145,49 @@ where
     /// Create a new tree with custom configuration and logger
     pub fn with_config_and_logger(hasher: Hasher, config: TreeConfig, logger: Logger) -> Result<Self> {
         config.validate()?;
-        let sparse_index = SparseIndex::new(config.sparse_index_sparsity);
+        let sparse_index = SparseIndex::with_budget(config.sparse_index_sparsity, config.sparse_index_max_entries);
         let delta_chains = SparseIndex::new(config.sparse_index_sparsity);
         let tree = Self {
-            nodes: Vec::new(),
+            nodes: Levels::new(),
             leaf_count: 0,
             sparse_index,
             hasher,
             config: config.clone(),
             incremental_updates: config.incremental_updates,
             delta_chains,
-            stored_deltas: Vec::new(),
+            stored_deltas: SharedVec::new(),
+            root_history: SharedVec::new(),
             security_logger: logger,
+            #[cfg(feature = "std")]
+            proof_cache: None,
+            #[cfg(feature = "std")]
+            event_emitter: None,
+            redactions: Default::default(),
+            leaf_store: None,
+            redaction_signer: None,
+            #[cfg(not(feature = "minimal"))]
+            cancellation: None,
+            #[cfg(not(feature = "minimal"))]
+            admission: None,
+            #[cfg(not(feature = "minimal"))]
+            interceptors: Vec::new(),
+            #[cfg(feature = "std")]
+            progress_observer: None,
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
         };
 
         // Log tree initialization
-        let config_summary = format!("sparsity={}, deltas={}, incremental={}, max_depth={}",
-                                   config.sparse_index_sparsity,
-                                   config.enable_deltas,
-                                   config.incremental_updates,
-                                   config.max_depth);
-        let _ = tree.security_logger.log_event(&crate::security::events::tree_initialization(&config_summary));
+        crate::security::log_security_event!(
+            tree.security_logger,
+            crate::security::events::tree_initialization(&format!(
+                "sparsity={}, deltas={}, incremental={}, max_depth={}",
+                config.sparse_index_sparsity,
+                config.enable_deltas,
+                config.incremental_updates,
+                config.max_depth
+            ))
+        );
 
         Ok(tree)
     }
@@ -113,7 +195,7 @@ where
 
 impl<H, Hasher> Default for ChronoMerkleTree<H, Hasher>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: HashFunction<Output = H> + Default + Sync,
 {
     fn default() -> Self {
@@ -123,7 +205,7 @@ where
 
 impl<H, Hasher, Logger> Clone for ChronoMerkleTree<H, Hasher, Logger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: HashFunction<Output = H> + Clone + Sync,
     Logger: SecurityLogger + Clone,
 {
@@ -137,7 +219,28 @@ where
             incremental_updates: self.incremental_updates,
             delta_chains: self.delta_chains.clone(),
             stored_deltas: self.stored_deltas.clone(),
+            root_history: self.root_history.clone(),
             security_logger: self.security_logger.clone(),
+            #[cfg(feature = "std")]
+            proof_cache: self.proof_cache.as_ref().map(|cache| {
+                let capacity = cache.lock().unwrap_or_else(|e| e.into_inner()).capacity();
+                std::sync::Mutex::new(crate::proof_cache::ProofCache::new(capacity))
+            }),
+            #[cfg(feature = "std")]
+            event_emitter: self.event_emitter.clone(),
+            redactions: self.redactions.clone(),
+            leaf_store: self.leaf_store.clone(),
+            redaction_signer: self.redaction_signer.clone(),
+            #[cfg(not(feature = "minimal"))]
+            cancellation: self.cancellation.clone(),
+            #[cfg(not(feature = "minimal"))]
+            admission: self.admission.clone(),
+            #[cfg(not(feature = "minimal"))]
+            interceptors: self.interceptors.clone(),
+            #[cfg(feature = "std")]
+            progress_observer: self.progress_observer.clone(),
+            #[cfg(feature = "parallel")]
+            thread_pool: self.thread_pool.clone(),
         }
     }
 }
\ No newline at end of file
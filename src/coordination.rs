@@ -0,0 +1,433 @@
+//! Lease-based leader election for processes sharing a backend
+//!
+//! Several writer processes pointed at the same distributed or database backend
+//! elect a single writer with [`LeaderElector`]. The leader holds a lease with a
+//! time-to-live and renews it; when it stops renewing, another candidate takes
+//! over once the lease expires. Every takeover increments a [`FencingToken`].
+//!
+//! [`FencedStorage`] embeds the writer's token in every value it saves and
+//! refuses to overwrite values saved under a newer token, so a deposed leader
+//! that still believes it holds the lease cannot clobber its successor's state.
+//! Deletes leave a tombstone carrying the token, so a deleted key stays fenced.
+//!
+//! Leases live in a [`LeaseStore`], which needs an atomic compare-and-swap.
+//! An etcd or Consul client implements it with a transaction or check-and-set;
+//! the provided implementation for `Mutex<S>` over any [`StorageBackend`] is
+//! only atomic within one process.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::storage::{BackendHealth, StorageBackend};
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Magic bytes prefixing an encoded lease
+const LEASE_MAGIC: &[u8; 4] = b"CML1";
+
+/// Magic bytes prefixing a value saved through [`FencedStorage`]
+const FENCE_MAGIC: &[u8; 4] = b"CMF1";
+
+/// Magic bytes prefixing a tombstone left by [`FencedStorage::delete`]
+const TOMBSTONE_MAGIC: &[u8; 4] = b"CMT1";
+
+/// Fenced value header length: magic and token
+const FENCE_HEADER: usize = 4 + 8;
+
+/// Monotonic number identifying a leadership term
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FencingToken(pub u64);
+
+impl core::fmt::Display for FencingToken {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Store for lease records supporting atomic compare-and-swap
+pub trait LeaseStore {
+    /// Read the current value of `key`
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Replace `key` with `new` (deleting it for `None`) if it currently equals `expected`
+    ///
+    /// Returns whether the swap happened.
+    fn compare_and_swap(&self, key: &str, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool>;
+}
+
+impl<S: StorageBackend> LeaseStore for Mutex<S> {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.lock().unwrap_or_else(|e| e.into_inner()).load(key)
+    }
+
+    fn compare_and_swap(&self, key: &str, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        let mut storage = self.lock().unwrap_or_else(|e| e.into_inner());
+        if storage.load(key)?.as_deref() != expected {
+            return Ok(false);
+        }
+        match new {
+            Some(data) => storage.save(key, data)?,
+            None => storage.delete(key)?,
+        }
+        Ok(true)
+    }
+}
+
+impl<L: LeaseStore + ?Sized> LeaseStore for Arc<L> {
+    fn read(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        (**self).read(key)
+    }
+
+    fn compare_and_swap(&self, key: &str, expected: Option<&[u8]>, new: Option<&[u8]>) -> Result<bool> {
+        (**self).compare_and_swap(key, expected, new)
+    }
+}
+
+/// A lease record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    /// Identifier of the holding node
+    pub holder: String,
+    /// Token of the holder's leadership term
+    pub token: FencingToken,
+    /// Expiry in milliseconds since the Unix epoch
+    pub expires_at_ms: u64,
+}
+
+impl Lease {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(20 + self.holder.len());
+        out.extend_from_slice(LEASE_MAGIC);
+        out.extend_from_slice(&crate::encoding::encode_u64(self.token.0));
+        out.extend_from_slice(&crate::encoding::encode_u64(self.expires_at_ms));
+        out.extend_from_slice(self.holder.as_bytes());
+        out
+    }
+
+    fn decode(data: &[u8]) -> Result<Self> {
+        if data.len() < 20 || &data[..4] != LEASE_MAGIC {
+            return Err(ChronoMerkleError::DeserializationError("Invalid lease record".to_string()));
+        }
+        let holder = String::from_utf8(data[20..].to_vec())
+            .map_err(|e| ChronoMerkleError::DeserializationError(format!("Invalid lease holder: {}", e)))?;
+        Ok(Self {
+            holder,
+            token: FencingToken(crate::encoding::decode_u64(&data[4..12]).unwrap()),
+            expires_at_ms: crate::encoding::decode_u64(&data[12..20]).unwrap(),
+        })
+    }
+
+    /// Whether the lease has expired at `now_ms`
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        now_ms >= self.expires_at_ms
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Candidate in a lease-based leader election
+pub struct LeaderElector<L> {
+    store: L,
+    key: String,
+    node_id: String,
+    ttl: Duration,
+    token: Option<FencingToken>,
+}
+
+impl<L: LeaseStore> LeaderElector<L> {
+    /// Create a candidate `node_id` competing for the lease at `key`
+    pub fn new(store: L, key: impl Into<String>, node_id: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            store,
+            key: key.into(),
+            node_id: node_id.into(),
+            ttl,
+            token: None,
+        }
+    }
+
+    /// Identifier of this candidate
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Token of the term this candidate last won, if it believes it is leader
+    ///
+    /// The lease may have expired since; call [`renew`](Self::renew) regularly.
+    pub fn token(&self) -> Option<FencingToken> {
+        self.token
+    }
+
+    /// Whether this candidate believes it is leader
+    pub fn is_leader(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Read the current lease, if any
+    pub fn current_lease(&self) -> Result<Option<Lease>> {
+        self.store.read(&self.key)?.map(|data| Lease::decode(&data)).transpose()
+    }
+
+    /// Try to become or stay leader, returning the term's token on success
+    ///
+    /// Takes over a lease that is missing or expired with the next token, and
+    /// extends a lease this candidate already holds under the same token.
+    pub fn try_acquire(&mut self) -> Result<Option<FencingToken>> {
+        let now = now_ms();
+        let current = self.store.read(&self.key)?;
+        let lease = current.as_deref().map(Lease::decode).transpose()?;
+        let token = match &lease {
+            Some(lease) if lease.holder == self.node_id && !lease.is_expired(now) => lease.token,
+            Some(lease) if !lease.is_expired(now) => {
+                self.token = None;
+                return Ok(None);
+            }
+            Some(lease) => FencingToken(lease.token.0 + 1),
+            None => FencingToken(1),
+        };
+
+        let renewed = Lease {
+            holder: self.node_id.clone(),
+            token,
+            expires_at_ms: now.saturating_add(self.ttl.as_millis() as u64),
+        };
+        if self.store.compare_and_swap(&self.key, current.as_deref(), Some(&renewed.encode()))? {
+            self.token = Some(token);
+            Ok(Some(token))
+        } else {
+            // Another candidate changed the lease concurrently
+            self.token = None;
+            Ok(None)
+        }
+    }
+
+    /// Extend the lease, returning whether this candidate is still leader
+    pub fn renew(&mut self) -> Result<bool> {
+        if self.token.is_none() {
+            return Ok(false);
+        }
+        let previous = self.token;
+        let token = self.try_acquire()?;
+        // A fresh term means the old one lapsed and writes under it may have been fenced
+        Ok(token.is_some() && token == previous)
+    }
+
+    /// Give up leadership so another candidate can take over immediately
+    pub fn resign(&mut self) -> Result<()> {
+        let Some(token) = self.token.take() else {
+            return Ok(());
+        };
+        let current = self.store.read(&self.key)?;
+        if let Some(data) = current.as_deref() {
+            let lease = Lease::decode(data)?;
+            if lease.holder == self.node_id && lease.token == token {
+                // Keep the token so the successor's term is still numbered higher
+                let expired = Lease { expires_at_ms: 0, ..lease };
+                self.store.compare_and_swap(&self.key, current.as_deref(), Some(&expired.encode()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Storage wrapper stamping writes with a fencing token
+///
+/// Saving or deleting a value that was written under a newer token fails with
+/// [`ChronoMerkleError::Fenced`]. Deleting replaces the value with a tombstone
+/// stamped with the token, which `load`, `exists` and `list_keys` treat as
+/// absent. The check and the write are separate backend
+/// calls, so fencing relies on the lease TTL exceeding the time a deposed
+/// leader needs to notice it lost the lease.
+pub struct FencedStorage<S> {
+    inner: S,
+    token: FencingToken,
+}
+
+impl<S: StorageBackend> FencedStorage<S> {
+    /// Wrap `inner`, writing under `token`
+    pub fn new(inner: S, token: FencingToken) -> Self {
+        Self { inner, token }
+    }
+
+    /// Token stamped on writes
+    pub fn token(&self) -> FencingToken {
+        self.token
+    }
+
+    /// Switch to the token of a newly won term
+    pub fn set_token(&mut self, token: FencingToken) {
+        self.token = token;
+    }
+
+    /// Token a stored value or tombstone was written under, if it was written through a `FencedStorage`
+    pub fn token_of(&self, key: &str) -> Result<Option<FencingToken>> {
+        Ok(self.inner.load(key)?.as_deref().and_then(split_fenced).map(|(token, _)| token))
+    }
+
+    /// Get a reference to the wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwrap into the inner backend
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn check_fence(&self, key: &str) -> Result<()> {
+        match self.token_of(key)? {
+            Some(current) if current > self.token => Err(ChronoMerkleError::Fenced {
+                key: key.to_string(),
+                token: self.token.0,
+                current: current.0,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Split a fenced value into its token and payload, which is `None` for a tombstone
+fn split_fenced(data: &[u8]) -> Option<(FencingToken, Option<&[u8]>)> {
+    if data.len() < FENCE_HEADER {
+        return None;
+    }
+    let token = FencingToken(crate::encoding::decode_u64(&data[4..FENCE_HEADER])?);
+    match &data[..4] {
+        magic if magic == FENCE_MAGIC => Some((token, Some(&data[FENCE_HEADER..]))),
+        magic if magic == TOMBSTONE_MAGIC && data.len() == FENCE_HEADER => Some((token, None)),
+        _ => None,
+    }
+}
+
+/// Whether a stored value is a tombstone
+fn is_tombstone(data: &[u8]) -> bool {
+    matches!(split_fenced(data), Some((_, None)))
+}
+
+impl<S: StorageBackend> StorageBackend for FencedStorage<S> {
+    fn save(&mut self, key: &str, data: &[u8]) -> Result<()> {
+        self.check_fence(key)?;
+        let mut stamped = Vec::with_capacity(FENCE_HEADER + data.len());
+        stamped.extend_from_slice(FENCE_MAGIC);
+        stamped.extend_from_slice(&crate::encoding::encode_u64(self.token.0));
+        stamped.extend_from_slice(data);
+        self.inner.save(key, &stamped)
+    }
+
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.inner.load(key)?.and_then(|data| match split_fenced(&data) {
+            Some((_, payload)) => payload.map(<[u8]>::to_vec),
+            None => Some(data),
+        }))
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        self.check_fence(key)?;
+        if !self.inner.exists(key)? {
+            return Ok(());
+        }
+        let mut tombstone = Vec::with_capacity(FENCE_HEADER);
+        tombstone.extend_from_slice(TOMBSTONE_MAGIC);
+        tombstone.extend_from_slice(&crate::encoding::encode_u64(self.token.0));
+        self.inner.save(key, &tombstone)
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for key in self.inner.list_keys()? {
+            if self.exists(&key)? {
+                keys.push(key);
+            }
+        }
+        Ok(keys)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.inner.load(key)?.is_some_and(|data| !is_tombstone(&data)))
+    }
+
+    fn health_check(&self) -> Result<BackendHealth> {
+        self.inner.health_check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn shared_store() -> Arc<Mutex<MemoryStorage>> {
+        Arc::new(Mutex::new(MemoryStorage::new()))
+    }
+
+    #[test]
+    fn test_single_leader_and_failover() {
+        let store = shared_store();
+        let mut a = LeaderElector::new(store.clone(), "leader", "a", Duration::from_secs(60));
+        let mut b = LeaderElector::new(store.clone(), "leader", "b", Duration::from_secs(60));
+
+        assert_eq!(a.try_acquire().unwrap(), Some(FencingToken(1)));
+        assert_eq!(b.try_acquire().unwrap(), None);
+        assert!(a.renew().unwrap());
+
+        a.resign().unwrap();
+        assert_eq!(b.try_acquire().unwrap(), Some(FencingToken(2)));
+        assert_eq!(a.try_acquire().unwrap(), None);
+        assert_eq!(b.current_lease().unwrap().unwrap().holder, "b");
+    }
+
+    #[test]
+    fn test_expired_lease_is_taken_over() {
+        let store = shared_store();
+        let mut a = LeaderElector::new(store.clone(), "leader", "a", Duration::ZERO);
+        let mut b = LeaderElector::new(store.clone(), "leader", "b", Duration::from_secs(60));
+        a.try_acquire().unwrap();
+        assert_eq!(b.try_acquire().unwrap(), Some(FencingToken(2)));
+        assert!(!a.renew().unwrap());
+    }
+
+    #[test]
+    fn test_deposed_leader_is_fenced() {
+        let mut old = FencedStorage::new(MemoryStorage::new(), FencingToken(1));
+        old.save("state", b"v1").unwrap();
+        assert_eq!(old.load("state").unwrap(), Some(b"v1".to_vec()));
+
+        // The successor writes to the same backend under a newer token
+        let mut new = FencedStorage::new(old.into_inner(), FencingToken(2));
+        new.save("state", b"v2").unwrap();
+        assert_eq!(new.token_of("state").unwrap(), Some(FencingToken(2)));
+
+        let mut old = FencedStorage::new(new.into_inner(), FencingToken(1));
+        let err = old.save("state", b"stale").unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::Fenced { token: 1, current: 2, .. }));
+        assert!(old.delete("state").is_err());
+        assert_eq!(old.load("state").unwrap(), Some(b"v2".to_vec()));
+    }
+
+    #[test]
+    fn test_deleted_keys_stay_fenced() {
+        let mut old = FencedStorage::new(MemoryStorage::new(), FencingToken(1));
+        old.save("state", b"v1").unwrap();
+
+        let mut new = FencedStorage::new(old.into_inner(), FencingToken(2));
+        new.delete("state").unwrap();
+        assert_eq!(new.load("state").unwrap(), None);
+        assert!(!new.exists("state").unwrap());
+        assert!(new.list_keys().unwrap().is_empty());
+        assert_eq!(new.token_of("state").unwrap(), Some(FencingToken(2)));
+
+        let mut old = FencedStorage::new(new.into_inner(), FencingToken(1));
+        let err = old.save("state", b"stale").unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::Fenced { token: 1, current: 2, .. }));
+        assert_eq!(old.load("state").unwrap(), None);
+
+        // The leader that deleted the key can write it again
+        let mut new = FencedStorage::new(old.into_inner(), FencingToken(2));
+        new.save("state", b"v3").unwrap();
+        assert_eq!(new.load("state").unwrap(), Some(b"v3".to_vec()));
+    }
+}
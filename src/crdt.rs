@@ -0,0 +1,350 @@
+//! Conflict-free merging of appends from several writers
+//!
+//! Each writer tags its appends with its writer ID and a hybrid logical clock
+//! ([`Hlc`]) reading. A [`CrdtTree`] keeps its leaves sorted by `(hlc, writer)`
+//! rather than by arrival, so replicas that have seen the same set of
+//! [`CrdtOp`]s have the same root no matter in which order or how often the
+//! ops were delivered. An op that sorts before already applied ones is spliced
+//! in and the leaves after it are rehashed.
+
+use crate::config::TreeConfig;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::node::Node;
+use crate::proof::ChronoProof;
+use crate::security::{NoOpLogger, SecurityLogger};
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{collections::BTreeMap, string::ToString, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::collections::BTreeMap;
+
+/// Hybrid logical clock reading
+///
+/// Orders by wall time first and by the logical counter among readings with
+/// the same wall time.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hlc {
+    /// Wall clock time, used as the leaf timestamp
+    pub wall: u64,
+    /// Counter distinguishing events within one wall time
+    pub logical: u32,
+}
+
+impl Hlc {
+    /// Reading for a local event at wall time `now`
+    pub fn tick(self, now: u64) -> Hlc {
+        if now > self.wall {
+            Hlc { wall: now, logical: 0 }
+        } else {
+            Hlc { wall: self.wall, logical: self.logical.saturating_add(1) }
+        }
+    }
+
+    /// Reading after receiving `remote` at wall time `now`
+    pub fn observe(self, remote: Hlc, now: u64) -> Hlc {
+        let wall = now.max(self.wall).max(remote.wall);
+        let logical = match (wall == self.wall, wall == remote.wall) {
+            (true, true) => self.logical.max(remote.logical).saturating_add(1),
+            (true, false) => self.logical.saturating_add(1),
+            (false, true) => remote.logical.saturating_add(1),
+            (false, false) => 0,
+        };
+        Hlc { wall, logical }
+    }
+}
+
+/// One append by one writer
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrdtOp {
+    /// Writer that made the append
+    pub writer: u64,
+    /// Writer's clock reading for the append
+    pub hlc: Hlc,
+    /// Appended data
+    pub data: Vec<u8>,
+}
+
+impl CrdtOp {
+    /// Leaf hash committing to the writer, the clock reading and the data
+    pub fn leaf_hash<Hasher: HashFunction>(&self, hasher: &Hasher) -> Hasher::Output {
+        hasher.hash_multiple(&[
+            &crate::encoding::encode_u64(self.writer),
+            &crate::encoding::encode_u64(self.hlc.wall),
+            &crate::encoding::encode_u32(self.hlc.logical),
+            &self.data,
+        ])
+    }
+}
+
+/// Tree merging appends from several writers into one deterministic order
+pub struct CrdtTree<Hasher, Logger = NoOpLogger>
+where
+    Hasher: HashFunction + Sync,
+    Hasher::Output: Send + Sync + crate::traits::MaybeSerde,
+    Logger: SecurityLogger,
+{
+    tree: ChronoMerkleTree<Hasher::Output, Hasher, Logger>,
+    writer: u64,
+    clock: Hlc,
+    ops: BTreeMap<(Hlc, u64), Vec<u8>>,
+}
+
+impl<Hasher, Logger> CrdtTree<Hasher, Logger>
+where
+    Hasher: HashFunction + Default + Sync,
+    Hasher::Output: Send + Sync + crate::traits::MaybeSerde,
+    Logger: SecurityLogger + Default,
+{
+    /// Create an empty replica appending as `writer`
+    pub fn new(hasher: Hasher, writer: u64) -> Self {
+        Self {
+            tree: ChronoMerkleTree::new(hasher),
+            writer,
+            clock: Hlc::default(),
+            ops: BTreeMap::new(),
+        }
+    }
+
+    /// Create an empty replica appending as `writer` with a custom configuration
+    pub fn with_config(hasher: Hasher, writer: u64, config: TreeConfig) -> Result<Self> {
+        Ok(Self {
+            tree: ChronoMerkleTree::with_config(hasher, config)?,
+            writer,
+            clock: Hlc::default(),
+            ops: BTreeMap::new(),
+        })
+    }
+}
+
+impl<Hasher, Logger> CrdtTree<Hasher, Logger>
+where
+    Hasher: HashFunction + Sync,
+    Hasher::Output: Send + Sync + crate::traits::MaybeSerde,
+    Logger: SecurityLogger,
+{
+    /// Append `data` as this replica's writer at wall time `now`
+    ///
+    /// Returns the op to send to the other replicas.
+    pub fn append(&mut self, data: &[u8], now: u64) -> Result<CrdtOp> {
+        let op = CrdtOp {
+            writer: self.writer,
+            hlc: self.clock.tick(now),
+            data: data.to_vec(),
+        };
+        self.merge(core::iter::once(op.clone()), now)?;
+        self.clock = op.hlc;
+        Ok(op)
+    }
+
+    /// Merge ops received from other replicas
+    ///
+    /// Ops already applied are ignored, so delivering an op twice is harmless.
+    /// The merge is atomic: if any op is invalid, or reuses a writer's clock
+    /// reading for different data, nothing is applied. Returns the number of
+    /// new ops.
+    pub fn merge_remote(&mut self, ops: impl IntoIterator<Item = CrdtOp>) -> Result<usize> {
+        let now = crate::security::current_timestamp();
+        self.merge(ops, now)
+    }
+
+    fn merge(&mut self, ops: impl IntoIterator<Item = CrdtOp>, now: u64) -> Result<usize> {
+        let mut fresh = BTreeMap::new();
+        for op in ops {
+            let key = (op.hlc, op.writer);
+            match self.ops.get(&key).or_else(|| fresh.get(&key)) {
+                Some(data) if *data == op.data => continue,
+                Some(_) => {
+                    return Err(ChronoMerkleError::InvalidConfiguration {
+                        parameter: "op".to_string(),
+                        reason: format!("writer {} reused clock reading {:?} for different data", op.writer, op.hlc),
+                    });
+                }
+                None => self.tree.validate_insert_inputs(&op.data, op.hlc.wall)?,
+            }
+            fresh.insert(key, op.data);
+        }
+        let (Some(&first), Some(&(latest, _))) = (fresh.keys().next(), fresh.keys().next_back()) else {
+            return Ok(0);
+        };
+
+        // Leaves before the first new op keep their position
+        let at = self.ops.range(..first).count();
+        let leaves: Vec<Node<Hasher::Output>> = merged_suffix(&self.ops, &fresh, first)
+            .map(|(&(hlc, writer), data)| {
+                let op = CrdtOp { writer, hlc, data: data.clone() };
+                Node::leaf(op.leaf_hash(&self.tree.hasher), hlc.wall, Some(op.data))
+            })
+            .collect();
+        self.splice_leaves(at, leaves)?;
+
+        let added = fresh.len();
+        self.ops.extend(fresh);
+        self.clock = self.clock.observe(latest, now);
+        Ok(added)
+    }
+
+    /// Replace the leaves from `at` on and recompute the internal nodes
+    fn splice_leaves(&mut self, at: usize, leaves: Vec<Node<Hasher::Output>>) -> Result<()> {
+        let tree = &mut self.tree;
+        let old_root = tree.root();
+        let last_timestamp = leaves.last().map_or(0, |leaf| leaf.timestamp_info().0);
+        // Only a pure append leaves the prior internal nodes recoverable
+        let reverse = if at == tree.leaf_count { tree.reverse_point() } else { None };
+
+        let checkpoint = tree.cancellation_checkpoint();
+        tree.nodes.leaves_mut().truncate(at);
+        tree.leaf_count = at + leaves.len();
+        tree.nodes.leaves_mut().extend(leaves);
+        tree.rebuild_sparse_index();
+        let refreshed = tree.refresh_internal_nodes(0);
+        tree.restore_if_cancelled(checkpoint, refreshed)?;
+        tree.record_root_delta(old_root, last_timestamp, reverse);
+        Ok(())
+    }
+
+    /// All applied ops in leaf order, e.g. for syncing a new replica
+    pub fn ops(&self) -> impl Iterator<Item = CrdtOp> + '_ {
+        self.ops.iter().map(|(&(hlc, writer), data)| CrdtOp { writer, hlc, data: data.clone() })
+    }
+
+    /// Applied ops with a clock reading after `hlc`
+    pub fn ops_after(&self, hlc: Hlc) -> impl Iterator<Item = CrdtOp> + '_ {
+        self.ops().skip_while(move |op| op.hlc <= hlc)
+    }
+
+    /// Get the root hash
+    pub fn root(&self) -> Option<Hasher::Output> {
+        self.tree.root()
+    }
+
+    /// Generate a proof for the leaf at `leaf_index` in leaf order
+    pub fn generate_proof(&self, leaf_index: usize) -> Result<ChronoProof<Hasher::Output>> {
+        self.tree.generate_proof(leaf_index)
+    }
+
+    /// Number of applied ops
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether no op was applied
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Writer ID of this replica
+    pub fn writer(&self) -> u64 {
+        self.writer
+    }
+
+    /// Current clock reading of this replica
+    pub fn clock(&self) -> Hlc {
+        self.clock
+    }
+
+    /// Underlying tree
+    pub fn tree(&self) -> &ChronoMerkleTree<Hasher::Output, Hasher, Logger> {
+        &self.tree
+    }
+}
+
+/// Existing and new entries from `first` on, in key order
+fn merged_suffix<'a>(
+    existing: &'a BTreeMap<(Hlc, u64), Vec<u8>>,
+    fresh: &'a BTreeMap<(Hlc, u64), Vec<u8>>,
+    first: (Hlc, u64),
+) -> impl Iterator<Item = (&'a (Hlc, u64), &'a Vec<u8>)> {
+    let mut existing = existing.range(first..).peekable();
+    let mut fresh = fresh.iter().peekable();
+    core::iter::from_fn(move || match (existing.peek(), fresh.peek()) {
+        (Some(a), Some(b)) if a.0 < b.0 => existing.next(),
+        (Some(_), None) => existing.next(),
+        _ => fresh.next(),
+    })
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    fn replica(writer: u64) -> CrdtTree<Blake3Hasher> {
+        CrdtTree::new(Blake3Hasher, writer)
+    }
+
+    #[test]
+    fn test_hlc() {
+        let clock = Hlc::default().tick(1000);
+        assert_eq!(clock, Hlc { wall: 1000, logical: 0 });
+        assert_eq!(clock.tick(999), Hlc { wall: 1000, logical: 1 });
+        assert_eq!(clock.observe(Hlc { wall: 1005, logical: 3 }, 1001), Hlc { wall: 1005, logical: 4 });
+        assert_eq!(clock.observe(Hlc { wall: 1000, logical: 7 }, 1000), Hlc { wall: 1000, logical: 8 });
+        assert_eq!(clock.observe(Hlc { wall: 900, logical: 7 }, 1100), Hlc { wall: 1100, logical: 0 });
+    }
+
+    #[test]
+    fn test_replicas_converge() {
+        let (mut a, mut b, mut c) = (replica(1), replica(2), replica(3));
+        let mut ops = Vec::new();
+        for i in 0..4u64 {
+            ops.push(a.append(format!("a{}", i).as_bytes(), 1000 + 2 * i).unwrap());
+            ops.push(b.append(format!("b{}", i).as_bytes(), 1001 + 2 * i).unwrap());
+            // Concurrent appends with equal clock readings are ordered by writer
+            ops.push(c.append(format!("c{}", i).as_bytes(), 1000 + 2 * i).unwrap());
+        }
+
+        let mut roots = Vec::new();
+        for order in [vec![0, 1, 2], vec![2, 1, 0], vec![1, 0, 2]] {
+            let mut merged = replica(9);
+            for writer in order {
+                let from_writer = ops.iter().filter(|op| op.writer == writer + 1).cloned();
+                merged.merge_remote(from_writer.rev()).unwrap();
+            }
+            assert_eq!(merged.len(), 12);
+            roots.push(merged.root().unwrap());
+        }
+        let mut one_by_one = replica(9);
+        for op in ops.iter().rev() {
+            assert_eq!(one_by_one.merge_remote([op.clone()]).unwrap(), 1);
+        }
+        roots.push(one_by_one.root().unwrap());
+        assert!(roots.windows(2).all(|pair| pair[0] == pair[1]));
+
+        // The converged root equals a plain tree over the ops in clock order
+        let mut sorted = ops.clone();
+        sorted.sort_by_key(|op| (op.hlc, op.writer));
+        let mut expected: ChronoMerkleTree = ChronoMerkleTree::default();
+        for op in &sorted {
+            expected.insert_leaf(op.leaf_hash(&Blake3Hasher), op.hlc.wall, Some(&op.data)).unwrap();
+        }
+        assert_eq!(roots[0], expected.root().unwrap());
+
+        // Replicas catch up with each other and redelivery changes nothing
+        a.merge_remote(b.ops().chain(c.ops())).unwrap();
+        assert_eq!(a.root(), Some(roots[0]));
+        assert_eq!(a.merge_remote(ops.clone()).unwrap(), 0);
+        assert_eq!(a.root(), Some(roots[0]));
+        let proof = a.generate_proof(5).unwrap();
+        assert!(a.tree().verify_proof(&proof).unwrap());
+    }
+
+    #[test]
+    fn test_merge_advances_clock_and_rejects_conflicts() {
+        let mut a = replica(1);
+        let remote = CrdtOp { writer: 2, hlc: Hlc { wall: 5000, logical: 4 }, data: b"remote".to_vec() };
+        a.merge_remote([remote.clone()]).unwrap();
+        let local = a.append(b"local", 1000).unwrap();
+        assert!(local.hlc > remote.hlc);
+        assert_eq!(a.ops_after(remote.hlc).collect::<Vec<_>>(), vec![local]);
+
+        let forged = CrdtOp { data: b"other".to_vec(), ..remote };
+        let fresh = CrdtOp { writer: 3, hlc: Hlc { wall: 6000, logical: 0 }, data: b"fresh".to_vec() };
+        let root = a.root();
+        assert!(a.merge_remote([fresh, forged]).is_err());
+        assert_eq!((a.len(), a.root()), (2, root));
+    }
+}
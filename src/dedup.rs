@@ -0,0 +1,324 @@
+//! Deduplicated storage of leaf data
+//!
+//! With [`ChronoMerkleTree::enable_leaf_dedup`] identical leaf payloads are
+//! stored once, keyed by their leaf hash and counted by the leaves holding
+//! them. Leaves hash their data, so equal hashes mean equal payloads.
+//! [`get_leaf_data`](ChronoMerkleTree::get_leaf_data), saved states and
+//! exported leaves read the shared payloads as if every leaf held its own
+//! copy.
+
+use crate::config::LeafHashMode;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::node::{Node, NodeType};
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{borrow::Cow, collections::BTreeMap, string::ToString, sync::Arc, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc, vec::Vec};
+
+/// Memory use of the deduplicated leaf data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DedupStats {
+    /// Distinct payloads stored
+    pub payloads: usize,
+    /// Leaves referencing a stored payload
+    pub references: usize,
+    /// Bytes of the stored payloads
+    pub stored_bytes: usize,
+    /// Bytes a copy per leaf would need on top of `stored_bytes`
+    pub saved_bytes: usize,
+}
+
+/// Leaf hash ordered by its bytes, so it can be looked up by `&[u8]`
+#[derive(Debug, Clone)]
+struct HashKey<H>(H);
+
+impl<H: AsRef<[u8]>> PartialEq for HashKey<H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_ref() == other.0.as_ref()
+    }
+}
+
+impl<H: AsRef<[u8]>> Eq for HashKey<H> {}
+
+impl<H: AsRef<[u8]>> PartialOrd for HashKey<H> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<H: AsRef<[u8]>> Ord for HashKey<H> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.0.as_ref().cmp(other.0.as_ref())
+    }
+}
+
+impl<H: AsRef<[u8]>> core::borrow::Borrow<[u8]> for HashKey<H> {
+    fn borrow(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+/// A stored payload and the number of leaves referencing it
+#[derive(Debug, Clone)]
+struct Shared {
+    data: Vec<u8>,
+    references: usize,
+}
+
+/// Payloads shared by leaves with identical data, keyed by leaf hash
+///
+/// Leaves referencing a payload hold no data of their own and are not
+/// redacted.
+#[derive(Debug, Clone)]
+pub(crate) struct LeafStore<H> {
+    payloads: BTreeMap<HashKey<H>, Shared>,
+}
+
+impl<H: AsRef<[u8]> + Clone> LeafStore<H> {
+    fn new() -> Self {
+        Self { payloads: BTreeMap::new() }
+    }
+
+    /// Add a reference from a leaf hashing to `hash`, storing `data` if it is new
+    fn retain(&mut self, hash: &H, data: &[u8]) {
+        match self.payloads.get_mut(hash.as_ref()) {
+            Some(shared) => shared.references += 1,
+            None => {
+                self.payloads.insert(HashKey(hash.clone()), Shared { data: data.to_vec(), references: 1 });
+            }
+        }
+    }
+
+    /// Drop a reference, and the payload with its last one
+    fn release(&mut self, hash: &H) {
+        if let Some(shared) = self.payloads.get_mut(hash.as_ref()) {
+            shared.references -= 1;
+            if shared.references == 0 {
+                self.payloads.remove(hash.as_ref());
+            }
+        }
+    }
+
+    /// Payload of leaves hashing to `hash`
+    pub(crate) fn get(&self, hash: &H) -> Option<&[u8]> {
+        self.payloads.get(hash.as_ref()).map(|shared| shared.data.as_slice())
+    }
+
+    /// `nodes` with shared payloads copied back into their leaves
+    ///
+    /// `nodes` start at node `first` of a tree with `leaf_count` leaves;
+    /// leaves for which `redacted` holds are left without data. Only the
+    /// leaves that get data back are copied.
+    pub(crate) fn fill<'a: 'b, 'b>(
+        store: Option<&'a Self>,
+        nodes: impl IntoIterator<Item = &'a Node<H>> + 'b,
+        first: usize,
+        leaf_count: usize,
+        redacted: impl Fn(usize) -> bool + 'b,
+    ) -> impl Iterator<Item = Cow<'a, Node<H>>> + 'b {
+        let store = store.filter(|store| !store.payloads.is_empty());
+        (first..).zip(nodes).map(move |(index, node)| {
+            let payload = match (&node.node_type, store) {
+                (NodeType::Leaf { hash, data: None, .. }, Some(store)) if index < leaf_count && !redacted(index) => {
+                    store.get(hash)
+                }
+                _ => None,
+            };
+            let Some(payload) = payload else {
+                return Cow::Borrowed(node);
+            };
+            let mut node = node.clone();
+            if let NodeType::Leaf { data, .. } = &mut node.node_type {
+                *data = Some(payload.to_vec());
+            }
+            Cow::Owned(node)
+        })
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Store identical leaf payloads once from now on
+    ///
+    /// The data of existing leaves moves into the shared store. Requires
+    /// [`LeafHashMode::HashData`], since payloads are keyed by leaf hash.
+    /// The setting is not saved with the tree: a loaded tree holds a copy
+    /// per leaf until dedup is enabled again.
+    pub fn enable_leaf_dedup(&mut self) -> Result<()> {
+        if self.config.leaf_hash_mode != LeafHashMode::HashData {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "leaf_hash_mode".to_string(),
+                reason: "Leaf data can only be deduplicated when leaves hash their data".to_string(),
+            });
+        }
+        if self.leaf_store.is_some() {
+            return Ok(());
+        }
+        let mut store = LeafStore::new();
+        for node in self.nodes.leaves_mut().iter_mut() {
+            if let NodeType::Leaf { hash, data, .. } = &mut node.node_type {
+                if let Some(data) = data.take() {
+                    store.retain(hash, &data);
+                }
+            }
+        }
+        self.leaf_store = Some(Arc::new(store));
+        Ok(())
+    }
+
+    /// Give every leaf its own copy of its data again
+    pub fn disable_leaf_dedup(&mut self) {
+        let Some(store) = self.leaf_store.take() else {
+            return;
+        };
+        let leaf_count = self.leaf_count;
+        let leaves: Vec<Node<H>> = LeafStore::fill(Some(&store), self.nodes.leaves(), 0, leaf_count, |index| {
+            self.redactions.contains_key(&index)
+        })
+        .map(Cow::into_owned)
+        .collect();
+        *self.nodes.leaves_mut() = leaves.into();
+    }
+
+    /// Check if identical leaf payloads are stored once
+    pub fn is_leaf_dedup_enabled(&self) -> bool {
+        self.leaf_store.is_some()
+    }
+
+    /// Memory use of the shared leaf data, if dedup is enabled
+    pub fn dedup_stats(&self) -> Option<DedupStats> {
+        let store = self.leaf_store.as_ref()?;
+        Some(store.payloads.values().fold(DedupStats::default(), |mut stats, shared| {
+            stats.payloads += 1;
+            stats.references += shared.references;
+            stats.stored_bytes += shared.data.len();
+            stats.saved_bytes += shared.data.len() * (shared.references - 1);
+            stats
+        }))
+    }
+
+    /// Data to keep in a new leaf hashing to `hash`: `None` if it went to the shared store
+    pub(crate) fn retain_leaf_data(&mut self, hash: &H, data: &[u8]) -> Option<Vec<u8>> {
+        match &mut self.leaf_store {
+            Some(store) => {
+                Arc::make_mut(store).retain(hash, data);
+                None
+            }
+            None => Some(data.to_vec()),
+        }
+    }
+
+    /// Drop the shared payload reference of leaf `index` before its data is erased
+    pub(crate) fn release_leaf_data(&mut self, index: usize) {
+        if let (Some(store), NodeType::Leaf { hash, data: None, .. }) = (&mut self.leaf_store, &self.nodes[index].node_type) {
+            if !self.redactions.contains_key(&index) {
+                Arc::make_mut(store).release(hash);
+            }
+        }
+    }
+
+    /// Recount the references to shared payloads after leaves were removed
+    pub(crate) fn prune_leaf_store(&mut self) {
+        let Some(store) = &mut self.leaf_store else {
+            return;
+        };
+        let store = Arc::make_mut(store);
+        for shared in store.payloads.values_mut() {
+            shared.references = 0;
+        }
+        for (index, node) in self.nodes.leaves().iter().enumerate() {
+            if let NodeType::Leaf { hash, data: None, .. } = &node.node_type {
+                if !self.redactions.contains_key(&index) {
+                    if let Some(shared) = store.payloads.get_mut(hash.as_ref()) {
+                        shared.references += 1;
+                    }
+                }
+            }
+        }
+        store.payloads.retain(|_, shared| shared.references > 0);
+    }
+
+    /// Nodes starting at node `first`, with shared payloads copied back into their leaves
+    pub(crate) fn with_leaf_data<'a: 'b, 'b>(
+        &'a self,
+        first: usize,
+        nodes: impl IntoIterator<Item = &'a Node<H>> + 'b,
+    ) -> impl Iterator<Item = Cow<'a, Node<H>>> + 'b {
+        LeafStore::fill(self.leaf_store.as_deref(), nodes, first, self.leaf_count, |index| {
+            self.redactions.contains_key(&index)
+        })
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    fn tree() -> ChronoMerkleTree {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..6u64 {
+            tree.insert(format!("record {}", i % 2).as_bytes(), 1000 + i).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn test_identical_payloads_are_stored_once() {
+        let mut tree = tree();
+        let root = tree.root();
+        tree.enable_leaf_dedup().unwrap();
+        tree.insert(b"record 1", 2000).unwrap();
+        tree.insert_batch(&[(b"record 0", 2001), (b"record 2", 2002)]).unwrap();
+
+        let stats = tree.dedup_stats().unwrap();
+        assert_eq!((stats.payloads, stats.references), (3, 9));
+        assert_eq!(stats.saved_bytes, 6 * b"record 0".len());
+        assert_eq!(tree.get_leaf_data(6).unwrap().data(), Some(&b"record 1"[..]));
+        assert_eq!(tree.get_leaf_data(7).unwrap().data(), Some(&b"record 0"[..]));
+        let (leaves, _) = tree.extract_leaves_and_deltas();
+        assert!(leaves.iter().all(|leaf| matches!(&leaf.node_type, NodeType::Leaf { data: Some(_), .. })));
+        #[cfg(feature = "storage")]
+        assert_eq!(tree.extract_state().nodes[..9], leaves[..]);
+
+        tree.disable_leaf_dedup();
+        assert!(tree.dedup_stats().is_none());
+        assert_eq!(tree.get_leaf_data(8).unwrap().data(), Some(&b"record 2"[..]));
+        tree.rollback_to_timestamp(1005).unwrap();
+        assert_eq!(tree.root(), root);
+    }
+
+    #[test]
+    fn test_redaction_releases_shared_payload() {
+        let mut tree = tree();
+        tree.enable_leaf_dedup().unwrap();
+        for index in [0, 2] {
+            tree.redact_leaf(index, "erasure request").unwrap();
+        }
+        assert!(tree.get_leaf_data(0).unwrap().data().is_none());
+        assert_eq!(tree.get_leaf_data(4).unwrap().data(), Some(&b"record 0"[..]));
+        assert_eq!(tree.dedup_stats().unwrap().references, 4);
+
+        tree.redact_leaf(4, "erasure request").unwrap();
+        assert_eq!(tree.dedup_stats().unwrap().payloads, 1);
+        let (leaves, _) = tree.extract_leaves_and_deltas();
+        assert!(matches!(&leaves[2].node_type, NodeType::Leaf { data: None, .. }));
+    }
+
+    #[test]
+    fn test_rollback_drops_unreferenced_payloads() {
+        let mut tree = tree();
+        tree.enable_leaf_dedup().unwrap();
+        tree.insert(b"record 9", 2000).unwrap();
+        tree.rollback_to_timestamp(1005).unwrap();
+        let stats = tree.dedup_stats().unwrap();
+        assert_eq!((stats.payloads, stats.references), (2, 6));
+    }
+}
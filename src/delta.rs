@@ -2,8 +2,8 @@
 
 use crate::error::{ChronoMerkleError, Result};
 use crate::hash::HashFunction;
-use crate::node::{Node, NodeType};
-use crate::sparse_index::SparseIndex;
+use crate::levels::Levels;
+use crate::node::{Node, NodeType, ReverseDelta};
 use crate::tree::ChronoMerkleTree;
 
 #[cfg(feature = "no-std")]
@@ -13,11 +13,14 @@ use std::vec::Vec;
 
 impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: HashFunction<Output = H> + Sync,
     Logger: crate::security::SecurityLogger,
 {
     /// Get the delta chain for a given timestamp (for verification/rollback)
+    ///
+    /// Only timestamps present in the delta index are found; prefer
+    /// [`delta_log`](Self::delta_log) for querying the history.
     pub fn get_delta_chain(&self, timestamp: u64) -> Vec<Node<H>> {
         if let Some(delta_index) = self.delta_chains.find_exact(timestamp) {
             // Return all deltas from this index onward (simplified chain)
@@ -47,22 +50,69 @@ where
     }
 
     /// Apply a delta to rollback to a previous state
+    ///
+    /// Deltas carrying a [`ReverseDelta`] restore the exact tree they were
+    /// recorded from, even if more leaves were appended since, and drop
+    /// themselves and every later delta. Older deltas without one only
+    /// remove the last leaf.
     pub fn apply_delta_rollback(&mut self, delta: &Node<H>) -> Result<()> {
+        if let NodeType::Delta { base_hash, timestamp, reverse: Some(reverse), .. } = &delta.node_type {
+            #[cfg(feature = "std")]
+            let (old_root, old_leaf_count) = (self.root(), self.leaf_count);
+
+            self.restore_reverse_delta(reverse, base_hash)?;
+            if let Some(position) = self.stored_deltas.iter().position(|stored| stored == delta) {
+                self.stored_deltas.truncate(position);
+                self.rebuild_delta_chains();
+            }
+
+            #[cfg(feature = "std")]
+            {
+                self.emit_event(|| crate::event_emitter::TreeEvent::RolledBack {
+                    target_timestamp: *timestamp,
+                    removed_leaves: old_leaf_count - self.leaf_count,
+                });
+                self.emit_root_change(old_root);
+            }
+            #[cfg(not(feature = "std"))]
+            let _ = timestamp;
+            return Ok(());
+        }
+
         // Apply a delta in reverse to rollback state
-        if let NodeType::Delta { delta_hash: _, base_hash: _, timestamp: _ } = &delta.node_type {
+        if let NodeType::Delta { delta_hash: _, base_hash: _, timestamp, .. } = &delta.node_type {
             // The delta represents a change from base_hash to delta_hash
             // To rollback, we need to change delta_hash back to base_hash
             // Since we don't know exactly where this change occurred in the current tree,
             // we'll rebuild the tree from scratch with one less leaf
 
             if self.leaf_count > 0 {
+                #[cfg(feature = "std")]
+                let old_root = self.root();
+
                 // Remove the last leaf (simplified rollback)
+                let checkpoint = self.cancellation_checkpoint();
                 self.leaf_count -= 1;
-                self.nodes.truncate(self.leaf_count + (self.leaf_count.saturating_sub(1)));
+                self.nodes.leaves_mut().truncate(self.leaf_count);
 
                 // Rebuild the tree without the rolled-back leaf
-                self.rebuild_tree()?;
+                let rebuilt = self.rebuild_tree();
+                self.restore_if_cancelled(checkpoint, rebuilt)?;
+                self.prune_redactions();
+                self.prune_leaf_store();
+                self.prune_root_history();
+
+                #[cfg(feature = "std")]
+                {
+                    self.emit_event(|| crate::event_emitter::TreeEvent::RolledBack {
+                        target_timestamp: *timestamp,
+                        removed_leaves: 1,
+                    });
+                    self.emit_root_change(old_root);
+                }
             }
+            #[cfg(not(feature = "std"))]
+            let _ = timestamp;
 
             Ok(())
         } else {
@@ -73,6 +123,11 @@ where
     }
 
     /// Rollback the tree to a previous state using delta chain
+    ///
+    /// If deltas are enabled, the leaves to keep are a prefix of the tree and
+    /// a stored delta was recorded from exactly that prefix, its
+    /// [`ReverseDelta`] restores the internal nodes without rehashing.
+    /// Otherwise the tree is rebuilt from the kept leaves.
     pub fn rollback_to_timestamp(&mut self, target_timestamp: u64) -> Result<()> {
         // Simple rollback: remove leaves added after the target timestamp
         // This provides the core rollback functionality
@@ -85,14 +140,10 @@ where
 
         // Collect all leaves with timestamp <= target_timestamp
         let mut leaves_to_keep = Vec::new();
-        let mut timestamps_to_keep = Vec::new();
-
-        // Collect all leaves with timestamp <= target_timestamp
-        for node in &self.nodes {
+        for node in self.nodes.leaves() {
             if let NodeType::Leaf { timestamp, .. } = &node.node_type {
                 if *timestamp <= target_timestamp {
                     leaves_to_keep.push(node.clone());
-                    timestamps_to_keep.push(*timestamp);
                 }
             }
         }
@@ -103,20 +154,46 @@ where
             });
         }
 
-        // Replace the tree with just the kept leaves
-        self.nodes.clear();
-        self.nodes.extend(leaves_to_keep);
-        self.leaf_count = self.nodes.len();
+        #[cfg(feature = "std")]
+        let (old_root, old_leaf_count) = (self.root(), self.leaf_count);
+
+        let kept_prefix = self
+            .nodes
+            .leaves()
+            .iter()
+            .take(leaves_to_keep.len())
+            .all(|node| node.timestamp_info().0 <= target_timestamp);
+        let reverse = self
+            .stored_deltas
+            .iter()
+            .rev()
+            .filter(|_| kept_prefix && leaves_to_keep.len() < self.leaf_count)
+            .find_map(|delta| match &delta.node_type {
+                NodeType::Delta { base_hash, reverse: Some(reverse), .. } if reverse.leaf_count == leaves_to_keep.len() => {
+                    Some((reverse.clone(), base_hash.clone()))
+                }
+                _ => None,
+            });
 
-        // Rebuild the sparse index for remaining leaves
-        self.sparse_index = SparseIndex::new(self.config.sparse_index_sparsity);
-        for (idx, timestamp) in timestamps_to_keep.into_iter().enumerate() {
-            self.sparse_index.insert(timestamp, idx);
+        if let Some((reverse, base_hash)) = reverse {
+            self.restore_reverse_delta(&reverse, &base_hash)?;
+        } else {
+            // Replace the tree with just the kept leaves
+            let checkpoint = self.cancellation_checkpoint();
+            self.leaf_count = leaves_to_keep.len();
+            self.nodes = Levels::from_flat(leaves_to_keep, self.leaf_count);
+
+            // Rebuild the sparse index for remaining leaves
+            self.rebuild_sparse_index();
+
+            // Rebuild the tree with remaining leaves
+            let rebuilt = self.rebuild_tree();
+            self.restore_if_cancelled(checkpoint, rebuilt)?;
+            self.prune_redactions();
+            self.prune_leaf_store();
+            self.prune_root_history();
         }
 
-        // Rebuild the tree with remaining leaves
-        self.rebuild_tree()?;
-
         // Clear deltas that occurred after the target timestamp
         self.stored_deltas.retain(|delta| {
             if let NodeType::Delta { timestamp, .. } = &delta.node_type {
@@ -126,205 +203,93 @@ where
             }
         });
 
-        // Rebuild delta chains index
-        self.delta_chains = SparseIndex::new(self.config.sparse_index_sparsity);
-        for (i, delta) in self.stored_deltas.iter().enumerate() {
-            if let NodeType::Delta { timestamp, .. } = &delta.node_type {
-                self.delta_chains.insert(*timestamp, i);
-            }
+        self.rebuild_delta_chains();
+
+        #[cfg(feature = "std")]
+        {
+            self.emit_event(|| crate::event_emitter::TreeEvent::RolledBack {
+                target_timestamp,
+                removed_leaves: old_leaf_count - self.leaf_count,
+            });
+            self.emit_root_change(old_root);
         }
 
         Ok(())
     }
 
-    /// Compute deltas for the path from a leaf to the root (legacy - kept for compatibility)
-    #[allow(dead_code)]
-    pub(crate) fn compute_path_deltas(&mut self, leaf_index: usize) -> Result<()> {
-        // Ensure the tree has enough space for all nodes
-        self.ensure_tree_capacity()?;
-
-        // Update only the path from leaf to root, maintaining complete binary tree structure
-        let mut current_index = leaf_index;
-        let mut current_node = self.nodes[leaf_index].clone();
-
-        // Store the old root for delta computation
-        let old_root = self.root();
-
-        // Traverse from leaf to root, updating only the affected path
-        while current_index > 0 {
-            let parent_index = (current_index - 1) / 2;
-
-            // Get the old parent hash before update (for delta computation)
-            let old_parent_hash = if parent_index < self.nodes.len() {
-                Some(self.nodes[parent_index].hash())
-            } else {
-                None
-            };
-
-            // Get the sibling at this level
-            let sibling_index = if current_index % 2 == 0 {
-                current_index - 1 // left sibling
-            } else {
-                current_index + 1 // right sibling
-            };
-
-            // For complete binary trees, we need to check if the sibling actually exists
-            // at this level of the tree. The sibling exists if:
-            // 1. It's within bounds of current leaf count, AND
-            // 2. It's at the same level in the tree (same depth from root)
-            let current_level = self.get_node_level(current_index);
-            let sibling_level = self.get_node_level(sibling_index);
-
-            let sibling_node = if sibling_index < self.leaf_count &&
-                                current_level == sibling_level &&
-                                sibling_index != current_index {
-                Some(&self.nodes[sibling_index])
-            } else {
-                None
-            };
-
-            // Compute new parent hash based on available children
-            let (new_parent_hash, left_hash, right_hash) = if let Some(sibling) = sibling_node {
-                // Two children case
-                let left_hash = if current_index % 2 == 0 { sibling.hash() } else { current_node.hash() };
-                let right_hash = if current_index % 2 == 0 { current_node.hash() } else { sibling.hash() };
-                let parent_hash = self.hasher.hash_pair(&left_hash, &right_hash);
-                (parent_hash, left_hash, right_hash)
-            } else {
-                // Single child case - promote the current node
-                (current_node.hash(), current_node.hash(), current_node.hash())
-            };
-
-            // Compute timestamp range for the parent
-            let timestamp_range = if let Some(sibling) = sibling_node {
-                let current_ts = current_node.timestamp_info();
-                let sibling_ts = sibling.timestamp_info();
-
-                let start = current_ts.0.min(sibling_ts.0);
-                let end = match (current_ts.1, sibling_ts.1) {
-                    (Some(c), Some(s)) => Some(c.max(s)),
-                    (Some(c), None) => Some(c.max(sibling_ts.0)),
-                    (None, Some(s)) => Some(s.max(current_ts.0)),
-                    (None, None) => Some(current_ts.0.max(sibling_ts.0)),
-                };
-
-                (start, end)
-            } else {
-                current_node.timestamp_info()
-            };
-
-            // Create the new parent node
-            let new_parent = if sibling_node.is_some() {
-                Node::internal(new_parent_hash.clone(), left_hash, right_hash,
-                             (timestamp_range.0, timestamp_range.1.unwrap_or(timestamp_range.0)))
-            } else {
-                // Single child - create internal node with promoted child
-                Node {
-                    node_type: NodeType::Internal {
-                        hash: new_parent_hash.clone(),
-                        left_hash: current_node.hash(),
-                        right_hash: current_node.hash(),
-                        timestamp_range: (timestamp_range.0, timestamp_range.1.unwrap_or(timestamp_range.0)),
-                    },
-                    children: vec![current_node.clone()],
-                }
-            };
-
-            // Store delta if parent changed
-            if let Some(old_hash) = old_parent_hash {
-                if old_hash != new_parent_hash {
-                    let delta_hash = self.hasher.hash_pair(&old_hash, &new_parent_hash);
-                    let delta_node = Node::delta(delta_hash, old_hash, timestamp_range.0);
-
-                    let delta_index = self.stored_deltas.len();
-                    self.stored_deltas.push(delta_node);
-                    self.delta_chains.insert(timestamp_range.0, delta_index);
-                }
-            }
-
-            // Update or add the parent node
-            if parent_index >= self.nodes.len() {
-                self.nodes.push(new_parent.clone());
-            } else {
-                self.nodes[parent_index] = new_parent.clone();
-            }
-
-            // Move up to parent
-            current_index = parent_index;
-            current_node = new_parent;
+    /// Capture what an append will overwrite, if deltas are enabled
+    pub(crate) fn reverse_point(&self) -> Option<ReverseDelta<H>> {
+        if !self.config.enable_deltas || self.leaf_count == 0 {
+            return None;
         }
+        let boundary = (1..self.nodes.depth()).filter_map(|level| self.nodes.level(level).last().cloned()).collect();
+        Some(ReverseDelta { leaf_count: self.leaf_count, boundary })
+    }
 
-        // Create root-level delta if the root changed
-        if let (Some(old_root_hash), Some(new_root_hash)) = (old_root, self.root()) {
-            if old_root_hash != new_root_hash {
-                let delta_hash = self.hasher.hash_pair(&old_root_hash, &new_root_hash);
-                let delta_node = Node::delta(delta_hash, old_root_hash, current_node.timestamp_info().0);
-
-                let delta_index = self.stored_deltas.len();
-                self.stored_deltas.push(delta_node);
-                self.delta_chains.insert(current_node.timestamp_info().0, delta_index);
+    /// Drop the reverse deltas of states holding more than `index` leaves
+    ///
+    /// Their boundary nodes predate a change to leaf `index`, so restoring
+    /// them would pair the changed leaf with ancestors hashed over its old
+    /// value. Rollbacks past such states rebuild from the kept leaves instead.
+    #[cfg_attr(feature = "minimal", allow(dead_code))]
+    pub(crate) fn invalidate_reverse_deltas(&mut self, index: usize) {
+        let stale = |delta: &Node<H>| matches!(&delta.node_type, NodeType::Delta { reverse: Some(reverse), .. } if reverse.leaf_count > index);
+        if !self.stored_deltas.iter().any(stale) {
+            return;
+        }
+        for delta in self.stored_deltas.iter_mut() {
+            if let NodeType::Delta { reverse, .. } = &mut delta.node_type {
+                if reverse.as_ref().is_some_and(|reverse| reverse.leaf_count > index) {
+                    *reverse = None;
+                }
             }
         }
-
-        Ok(())
     }
 
-    /// Get the level (depth) of a node in the complete binary tree
-    #[allow(dead_code)]
-    pub(crate) fn get_node_level(&self, node_index: usize) -> usize {
-        // For a complete binary tree stored in heap order,
-        // the level can be determined by finding the highest power of 2
-        // that divides the index + 1
-        let mut level = 0;
-        let mut temp = node_index + 1;
-        while temp > 1 {
-            temp >>= 1;
-            level += 1;
+    /// Cut the tree back to the state captured in `reverse` without rehashing
+    ///
+    /// Fails without changes if the restored root is not `base_hash` or the
+    /// cancellation token is set.
+    pub(crate) fn restore_reverse_delta(&mut self, reverse: &ReverseDelta<H>, base_hash: &H) -> Result<()> {
+        self.check_cancelled("rollback")?;
+        let old_count = reverse.leaf_count;
+        if old_count == 0 || old_count > self.leaf_count {
+            return Err(ChronoMerkleError::DeltaProofFailed {
+                reason: format!("cannot restore {} leaves from a tree of {}", old_count, self.leaf_count),
+            });
         }
-        level
-    }
 
-    /// Recompute parent hashes from a given node index up to the root
-    #[allow(dead_code)]
-    pub(crate) fn recompute_parent_hashes_from_node(&mut self, start_index: usize) -> Result<()> {
-        let mut current_index = start_index;
-
-        // Work our way up the tree, recomputing hashes
-        while current_index > 0 {
-            let parent_index = (current_index - 1) / 2;
-
-            // Get the two children
-            let left_idx = 2 * parent_index;
-            let right_idx = 2 * parent_index + 1;
-
-            if left_idx < self.nodes.len() && right_idx < self.nodes.len() {
-                let left_hash = self.nodes[left_idx].hash();
-                let right_hash = self.nodes[right_idx].hash();
-
-                // Get timestamp range
-                let (left_start, left_end) = self.nodes[left_idx].timestamp_info();
-                let (right_start, right_end) = self.nodes[right_idx].timestamp_info();
-
-                let timestamp_range = (
-                    left_start.min(right_start),
-                    left_end.unwrap_or(left_start).max(right_end.unwrap_or(right_start)),
-                );
-
-                // Recompute parent hash
-                let new_parent_hash = self.hasher.hash_pair(&left_hash, &right_hash);
-
-                // Update parent node
-                if let NodeType::Internal { hash, left_hash: lh, right_hash: rh, timestamp_range: tr } = &mut self.nodes[parent_index].node_type {
-                    *hash = new_parent_hash;
-                    *lh = left_hash;
-                    *rh = right_hash;
-                    *tr = timestamp_range;
-                }
-            }
-
-            current_index = parent_index;
+        // Levels of the restored tree; its root is the boundary node of the top one
+        let (mut depth, mut width) = (1, old_count);
+        while width > 1 && depth <= reverse.boundary.len() {
+            width = width.div_ceil(2);
+            depth += 1;
+        }
+        let root = match depth {
+            1 => self.nodes.leaves().first(),
+            _ => reverse.boundary.get(depth - 2),
+        };
+        if width != 1 || root.map(|root| root.hash()).as_ref() != Some(base_hash) {
+            return Err(ChronoMerkleError::DeltaProofFailed {
+                reason: "reverse delta does not restore its base root".to_string(),
+            });
         }
 
+        // Every level keeps its nodes except the last, which the append replaced
+        self.nodes.leaves_mut().truncate(old_count);
+        let mut width = old_count;
+        for (level, boundary) in (1..depth).zip(&reverse.boundary) {
+            width = width.div_ceil(2);
+            let nodes = self.nodes.level_mut(level);
+            nodes.truncate(width - 1);
+            nodes.push(boundary.clone());
+        }
+        self.nodes.truncate_levels(depth);
+        self.leaf_count = old_count;
+        self.rebuild_sparse_index();
+        self.prune_redactions();
+        self.prune_leaf_store();
+        self.prune_root_history();
         Ok(())
     }
-}
\ No newline at end of file
+}
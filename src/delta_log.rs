@@ -0,0 +1,165 @@
+//! Read-only view of a tree's delta history
+//!
+//! Every change of the root records a delta `hash_pair(old_root, new_root)`
+//! together with the old root. [`DeltaLog`] exposes these records as typed
+//! [`DeltaEntry`] values in recording order, so replication tools can consume
+//! and check the history without touching the delta nodes or their index.
+
+use crate::hash::HashFunction;
+use crate::node::{Node, NodeType};
+use crate::security::SecurityLogger;
+use crate::snapshot::SharedVec;
+use crate::sparse_index::SparseIndex;
+use crate::tree::ChronoMerkleTree;
+
+use core::ops::RangeBounds;
+
+#[cfg(feature = "no-std")]
+use alloc::vec::Vec;
+
+/// One recorded root change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeltaEntry<'a, H> {
+    /// Position in the log
+    pub index: usize,
+    /// Timestamp of the leaf that caused the change
+    pub timestamp: u64,
+    /// Root before the change
+    pub base_hash: &'a H,
+    /// `hash_pair(base_hash, new_root)`
+    pub delta_hash: &'a H,
+}
+
+/// Delta history of a tree, oldest first
+pub struct DeltaLog<'a, H, Hasher> {
+    deltas: &'a SharedVec<Node<H>>,
+    index: &'a SparseIndex,
+    head: Option<H>,
+    hasher: &'a Hasher,
+}
+
+impl<'a, H, Hasher> DeltaLog<'a, H, Hasher>
+where
+    H: Clone + Eq,
+    Hasher: HashFunction<Output = H>,
+{
+    /// Iterate over all entries in recording order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = DeltaEntry<'a, H>> + 'a {
+        self.deltas.iter().enumerate().filter_map(|(index, node)| entry(index, node))
+    }
+
+    /// Iterate over the entries whose timestamp lies in `range`
+    pub fn range(&self, range: impl RangeBounds<u64> + 'a) -> impl Iterator<Item = DeltaEntry<'a, H>> + 'a {
+        self.iter().filter(move |entry| range.contains(&entry.timestamp))
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    /// Whether no root change was recorded
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Get the most recent entry with a timestamp at or before `timestamp`
+    pub fn latest_for(&self, timestamp: u64) -> Option<DeltaEntry<'a, H>> {
+        // With ordered timestamps no entry after the next indexed one can match
+        let end = if self.index.is_ordered() {
+            self.index.nearest_after(timestamp).map_or(self.deltas.len(), |i| i.min(self.deltas.len()))
+        } else {
+            self.deltas.len()
+        };
+        self.deltas
+            .iter()
+            .take(end)
+            .enumerate()
+            .rev()
+            .filter_map(|(index, node)| entry(index, node))
+            .find(|entry| entry.timestamp <= timestamp)
+    }
+
+    /// Check that the log links `from_root` to the current root
+    ///
+    /// Starting at the first entry whose base is `from_root`, every delta hash
+    /// must equal `hash_pair` of its base and the next entry's base, and the
+    /// last must lead to the current root. Returns `false` if `from_root` does
+    /// not appear in the log and is not the current root.
+    pub fn verify_chain(&self, from_root: &H) -> bool {
+        let entries: Vec<_> = self.iter().collect();
+        let Some(start) = entries.iter().position(|entry| entry.base_hash == from_root) else {
+            return self.head.as_ref() == Some(from_root);
+        };
+        let Some(head) = &self.head else {
+            return false;
+        };
+        entries[start..].iter().enumerate().all(|(offset, entry)| {
+            let next = entries.get(start + offset + 1).map_or(head, |next| next.base_hash);
+            *entry.delta_hash == self.hasher.hash_pair(entry.base_hash, next)
+        })
+    }
+}
+
+fn entry<H>(index: usize, node: &Node<H>) -> Option<DeltaEntry<'_, H>> {
+    match &node.node_type {
+        NodeType::Delta { delta_hash, base_hash, timestamp, .. } => Some(DeltaEntry {
+            index,
+            timestamp: *timestamp,
+            base_hash,
+            delta_hash,
+        }),
+        _ => None,
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Get the delta history of the tree
+    pub fn delta_log(&self) -> DeltaLog<'_, H, Hasher> {
+        DeltaLog {
+            deltas: &self.stored_deltas,
+            index: &self.delta_chains,
+            head: self.root(),
+            hasher: &self.hasher,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_log() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut roots = Vec::new();
+        for i in 0..5u64 {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+            roots.push(tree.root().unwrap());
+        }
+
+        let log = tree.delta_log();
+        assert_eq!(log.len(), 4);
+        assert_eq!(log.iter().map(|entry| *entry.base_hash).collect::<Vec<_>>(), roots[..4].to_vec());
+        assert_eq!(log.range(1002..=1003).map(|entry| entry.timestamp).collect::<Vec<_>>(), vec![1002, 1003]);
+        assert_eq!(log.latest_for(1002).unwrap().base_hash, &roots[1]);
+        assert!(log.latest_for(999).is_none());
+
+        assert!(log.verify_chain(&roots[0]));
+        assert!(log.verify_chain(&roots[2]));
+        assert!(log.verify_chain(&roots[4]));
+        assert!(!log.verify_chain(&[0u8; 32]));
+
+        let mut tampered = tree.clone();
+        if let NodeType::Delta { delta_hash, .. } = &mut tampered.stored_deltas[2].node_type {
+            delta_hash[0] ^= 1;
+        }
+        assert!(!tampered.delta_log().verify_chain(&roots[0]));
+        assert!(tampered.delta_log().verify_chain(&roots[3]));
+    }
+}
@@ -0,0 +1,162 @@
+//! Human-readable formatting of hashes, roots and proofs
+//!
+//! Hashes are byte arrays, and their derived `Debug` output is a list of
+//! decimal numbers that is unreadable in logs. [`RootHash`] prints a root as
+//! lowercase hex, [`ChronoProof::fmt_summary`] prints a proof on one line, and
+//! the `Debug` output of nodes shortens every hash to its first 8 hex digits.
+
+use crate::hash::HashFunction;
+use crate::proof::{ChronoProof, ProofStep};
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+use core::fmt;
+
+/// Hex digits a shortened hash keeps
+const SHORT_HEX_BYTES: usize = 4;
+
+/// Write `bytes` as lowercase hex
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+}
+
+/// A hash shown as its first 8 hex digits, followed by `..` if longer
+pub(crate) struct ShortHex<'a>(pub(crate) &'a [u8]);
+
+impl fmt::Debug for ShortHex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for ShortHex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0[..self.0.len().min(SHORT_HEX_BYTES)])?;
+        if self.0.len() > SHORT_HEX_BYTES {
+            f.write_str("..")?;
+        }
+        Ok(())
+    }
+}
+
+/// A tree root that displays as lowercase hex
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RootHash<H>(#[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))] pub H);
+
+impl<H> RootHash<H> {
+    /// Get the wrapped hash
+    pub fn into_inner(self) -> H {
+        self.0
+    }
+}
+
+impl<H> From<H> for RootHash<H> {
+    fn from(hash: H) -> Self {
+        Self(hash)
+    }
+}
+
+impl<H: AsRef<[u8]>> AsRef<[u8]> for RootHash<H> {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl<H: AsRef<[u8]>> fmt::Display for RootHash<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, self.0.as_ref())
+    }
+}
+
+impl<H: AsRef<[u8]>> fmt::LowerHex for RootHash<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, self.0.as_ref())
+    }
+}
+
+impl<H: AsRef<[u8]>> fmt::Debug for RootHash<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RootHash(")?;
+        write_hex(f, self.0.as_ref())?;
+        f.write_str(")")
+    }
+}
+
+/// One-line summary of a proof, returned by [`ChronoProof::fmt_summary`]
+pub struct ProofSummary<'a, H>(&'a ChronoProof<H>);
+
+impl<H: AsRef<[u8]>> fmt::Display for ProofSummary<'_, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let proof = self.0;
+        write!(f, "leaf {} @ {}, {} steps [", proof.leaf_index, proof.timestamp, proof.path.len())?;
+        for (i, step) in proof.path.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            match step {
+                ProofStep::Left(sibling) => write!(f, "L {}", ShortHex(sibling.as_ref()))?,
+                ProofStep::Right(sibling) => write!(f, "R {}", ShortHex(sibling.as_ref()))?,
+                ProofStep::Delta(old, new) => write!(f, "D {}->{}", ShortHex(old.as_ref()), ShortHex(new.as_ref()))?,
+            }
+        }
+        f.write_str("]")?;
+        if let Some(chain) = &proof.delta_chain {
+            write!(f, ", {} deltas", chain.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl<H: AsRef<[u8]>> ChronoProof<H> {
+    /// Summarize the proof on one line with shortened hashes
+    ///
+    /// e.g. `leaf 2 @ 1002, 2 steps [R 3f9a01c2.., L 77b0e4d1..]`
+    pub fn fmt_summary(&self) -> ProofSummary<'_, H> {
+        ProofSummary(self)
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Get the root hash in its displayable form
+    pub fn root_hash(&self) -> Option<RootHash<H>> {
+        self.root().map(RootHash)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "no-std")]
+    use alloc::format;
+
+    #[test]
+    fn test_hex_formatting() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..3u64 {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        let root = tree.root_hash().unwrap();
+        let hex = format!("{}", root);
+        assert_eq!(hex.len(), 64);
+        assert!(hex.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        assert_eq!(format!("{:x}", root), hex);
+        assert_eq!(format!("{:?}", root), format!("RootHash({})", hex));
+
+        let proof = tree.generate_proof(2).unwrap();
+        let summary = proof.fmt_summary().to_string();
+        assert!(summary.starts_with("leaf 2 @ 1002, 2 steps [R "), "{}", summary);
+        assert_eq!(summary.matches("..").count(), 2);
+
+        let node = format!("{:?}", tree.get_leaf(0).unwrap());
+        assert!(node.contains(&format!("hash: {}..", &hex::encode(tree.get_leaf_hash(0).unwrap())[..8])), "{}", node);
+        assert!(!node.contains(", ["));
+    }
+}
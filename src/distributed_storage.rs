@@ -0,0 +1,868 @@
+//! Replicated storage over several backends
+//!
+//! [`DistributedStorage`] places each key on `replication_factor` backends chosen
+//! by a consistent-hash ring with virtual nodes, and requires `write_quorum`
+//! acknowledgements. Reads query all replicas of a key, require `read_quorum`
+//! answers, resolve disagreements with a [`ConflictResolution`] strategy and
+//! repair stale replicas in place.
+//!
+//! Values are stored in a small envelope carrying a version number and a
+//! tombstone flag, so deletes and newer writes win over stale copies.
+//!
+//! Replica writes that fail, or that [`ReplicationMode::Async`] defers after the
+//! write quorum acknowledged, are queued as hints and delivered by
+//! [`DistributedStorage::replay_hints`] once the replica is reachable again.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::storage::StorageBackend;
+
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Magic bytes prefixing every stored envelope
+const ENVELOPE_MAGIC: &[u8; 4] = b"CMD1";
+
+/// Envelope header length: magic, kind byte and version
+const ENVELOPE_HEADER: usize = 4 + 1 + 8;
+
+const KIND_VALUE: u8 = 0;
+const KIND_TOMBSTONE: u8 = 1;
+
+/// How a read chooses between replicas that disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Pick the value returned by most replicas; ties are conflicts
+    Majority,
+    /// Pick the value with the highest embedded version; equal versions with different values are conflicts
+    NewestVersion,
+}
+
+/// When a write is acknowledged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplicationMode {
+    /// Write every replica before returning
+    Sync,
+    /// Return once `write_quorum` replicas acknowledged; remaining replicas are queued as hints
+    Async,
+}
+
+/// Replication settings of a [`DistributedStorage`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistributedConfig {
+    /// Number of backends each key is written to
+    pub replication_factor: usize,
+    /// Acknowledgements required for a write to succeed
+    pub write_quorum: usize,
+    /// Replica responses required for a read to succeed
+    pub read_quorum: usize,
+    /// Strategy for choosing between diverging replicas
+    pub resolution: ConflictResolution,
+    /// Points each backend occupies on the hash ring
+    pub virtual_nodes: usize,
+    /// When writes are acknowledged
+    pub replication: ReplicationMode,
+    /// Maximum queued hints; the oldest are dropped beyond this and left to read repair
+    pub max_hints: usize,
+}
+
+impl Default for DistributedConfig {
+    fn default() -> Self {
+        Self {
+            replication_factor: 3,
+            write_quorum: 2,
+            read_quorum: 2,
+            resolution: ConflictResolution::NewestVersion,
+            virtual_nodes: 64,
+            replication: ReplicationMode::Sync,
+            max_hints: 10_000,
+        }
+    }
+}
+
+/// Counters describing replica health
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DistributedStats {
+    /// Stale or missing replicas rewritten during reads
+    pub read_repairs: u64,
+    /// Reads whose replicas disagreed
+    pub conflicts_resolved: u64,
+}
+
+/// Replication lag metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplicationLag {
+    /// Replica writes waiting to be delivered
+    pub pending_hints: usize,
+    /// Age of the oldest pending hint
+    pub oldest_hint_age: Option<Duration>,
+    /// Hints delivered by `replay_hints`
+    pub hints_replayed: u64,
+    /// Hints dropped because the queue was full
+    pub hints_dropped: u64,
+}
+
+/// A replica write waiting to be delivered
+struct Hint {
+    replica_id: u64,
+    key: String,
+    /// Version of the envelope in `encoded`
+    version: u64,
+    encoded: Vec<u8>,
+    queued_at: Instant,
+}
+
+/// Outcome of moving keys after the set of backends changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RebalanceReport {
+    /// Keys examined
+    pub keys: usize,
+    /// Replica copies written to newly responsible backends
+    pub copied: usize,
+    /// Replica copies removed from backends no longer responsible
+    pub removed: usize,
+    /// Keys left untouched because their replicas conflict
+    pub conflicts: usize,
+}
+
+/// 64-bit FNV-1a with a SplitMix64 finalizer; stable across releases and platforms
+fn ring_hash(data: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in data {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// Consistent-hash ring mapping keys to backend ids
+#[derive(Debug, Clone)]
+struct HashRing {
+    points: Vec<(u64, u64)>,
+}
+
+impl HashRing {
+    fn new(ids: impl Iterator<Item = u64>, virtual_nodes: usize) -> Self {
+        let mut points: Vec<(u64, u64)> = ids
+            .flat_map(|id| {
+                (0..virtual_nodes as u64).map(move |vnode| {
+                    let mut seed = [0u8; 16];
+                    seed[..8].copy_from_slice(&crate::encoding::encode_u64(id));
+                    seed[8..].copy_from_slice(&crate::encoding::encode_u64(vnode));
+                    (ring_hash(&seed), id)
+                })
+            })
+            .collect();
+        points.sort_unstable();
+        Self { points }
+    }
+
+    /// The first `count` distinct backend ids clockwise from the key's position
+    fn owners(&self, key: &str, count: usize) -> Vec<u64> {
+        let start = self.points.partition_point(|&(point, _)| point < ring_hash(key.as_bytes()));
+        let mut owners = Vec::with_capacity(count);
+        for offset in 0..self.points.len() {
+            let (_, id) = self.points[(start + offset) % self.points.len()];
+            if !owners.contains(&id) {
+                owners.push(id);
+                if owners.len() == count {
+                    break;
+                }
+            }
+        }
+        owners
+    }
+}
+
+/// An inner backend with its stable ring identity
+struct Replica {
+    id: u64,
+    backend: Mutex<Box<dyn StorageBackend>>,
+}
+
+/// A decoded replica value
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Envelope {
+    version: u64,
+    tombstone: bool,
+    payload: Vec<u8>,
+}
+
+impl Envelope {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(ENVELOPE_HEADER + self.payload.len());
+        out.extend_from_slice(ENVELOPE_MAGIC);
+        out.push(if self.tombstone { KIND_TOMBSTONE } else { KIND_VALUE });
+        out.extend_from_slice(&crate::encoding::encode_u64(self.version));
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    /// Decode a stored blob; data written without an envelope is treated as version 0
+    fn decode(data: Vec<u8>) -> Self {
+        if data.len() >= ENVELOPE_HEADER && data.starts_with(ENVELOPE_MAGIC) && data[4] <= KIND_TOMBSTONE {
+            Self {
+                version: crate::encoding::decode_u64(&data[5..ENVELOPE_HEADER]).unwrap_or(0),
+                tombstone: data[4] == KIND_TOMBSTONE,
+                payload: data[ENVELOPE_HEADER..].to_vec(),
+            }
+        } else {
+            Self {
+                version: 0,
+                tombstone: false,
+                payload: data,
+            }
+        }
+    }
+}
+
+/// Storage backend replicating keys across several inner backends
+pub struct DistributedStorage {
+    backends: Vec<Replica>,
+    ring: HashRing,
+    next_id: u64,
+    config: DistributedConfig,
+    last_version: AtomicU64,
+    read_repairs: AtomicU64,
+    conflicts_resolved: AtomicU64,
+    hints: Mutex<VecDeque<Hint>>,
+    hints_replayed: AtomicU64,
+    hints_dropped: AtomicU64,
+}
+
+impl DistributedStorage {
+    /// Create a replicated store over `backends`
+    pub fn new(backends: Vec<Box<dyn StorageBackend>>, config: DistributedConfig) -> Result<Self> {
+        let invalid = |parameter: &str, reason: String| ChronoMerkleError::InvalidConfiguration {
+            parameter: parameter.to_string(),
+            reason,
+        };
+        if config.replication_factor == 0 || config.replication_factor > backends.len() {
+            return Err(invalid(
+                "replication_factor",
+                format!("Must be between 1 and the number of backends ({})", backends.len()),
+            ));
+        }
+        if config.write_quorum == 0 || config.write_quorum > config.replication_factor {
+            return Err(invalid("write_quorum", "Must be between 1 and replication_factor".to_string()));
+        }
+        if config.read_quorum == 0 || config.read_quorum > config.replication_factor {
+            return Err(invalid("read_quorum", "Must be between 1 and replication_factor".to_string()));
+        }
+        if config.virtual_nodes == 0 {
+            return Err(invalid("virtual_nodes", "Must be greater than 0".to_string()));
+        }
+
+        let backends: Vec<Replica> = backends
+            .into_iter()
+            .zip(0u64..)
+            .map(|(backend, id)| Replica { id, backend: Mutex::new(backend) })
+            .collect();
+        Ok(Self {
+            ring: HashRing::new(backends.iter().map(|r| r.id), config.virtual_nodes),
+            next_id: backends.len() as u64,
+            backends,
+            config,
+            last_version: AtomicU64::new(0),
+            read_repairs: AtomicU64::new(0),
+            conflicts_resolved: AtomicU64::new(0),
+            hints: Mutex::new(VecDeque::new()),
+            hints_replayed: AtomicU64::new(0),
+            hints_dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// Get the replication settings
+    pub fn config(&self) -> &DistributedConfig {
+        &self.config
+    }
+
+    /// Number of inner backends
+    pub fn backend_count(&self) -> usize {
+        self.backends.len()
+    }
+
+    /// Get replica health counters
+    pub fn stats(&self) -> DistributedStats {
+        DistributedStats {
+            read_repairs: self.read_repairs.load(Ordering::Relaxed),
+            conflicts_resolved: self.conflicts_resolved.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get replication lag metrics
+    pub fn replication_lag(&self) -> ReplicationLag {
+        let hints = self.hints.lock().unwrap_or_else(|e| e.into_inner());
+        ReplicationLag {
+            pending_hints: hints.len(),
+            oldest_hint_age: hints.front().map(|hint| hint.queued_at.elapsed()),
+            hints_replayed: self.hints_replayed.load(Ordering::Relaxed),
+            hints_dropped: self.hints_dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Try to deliver every queued hint, returning how many were delivered
+    ///
+    /// Hints for replicas that are still failing stay queued; hints for removed
+    /// backends, and hints older than the copy the replica already holds, are
+    /// discarded.
+    pub fn replay_hints(&self) -> usize {
+        let pending: Vec<Hint> = self.hints.lock().unwrap_or_else(|e| e.into_inner()).drain(..).collect();
+        let mut delivered = 0;
+        let mut retained = Vec::new();
+        for hint in pending {
+            let Some(index) = self.backends.iter().position(|replica| replica.id == hint.replica_id) else {
+                continue;
+            };
+            // Checked under the backend lock, so a direct write cannot land between the check and the save
+            let replayed = self.with_backend(index, |backend| {
+                let current = backend.load(&hint.key)?.map(|data| Envelope::decode(data).version);
+                if current.is_some_and(|version| version >= hint.version) {
+                    return Ok(false);
+                }
+                backend.save(&hint.key, &hint.encoded).map(|()| true)
+            });
+            match replayed {
+                Ok(true) => delivered += 1,
+                Ok(false) => {}
+                Err(_) => retained.push(hint),
+            }
+        }
+        self.hints_replayed.fetch_add(delivered as u64, Ordering::Relaxed);
+
+        // Hints queued while replaying are newer; keep them behind the retained ones
+        let mut hints = self.hints.lock().unwrap_or_else(|e| e.into_inner());
+        let newer: Vec<Hint> = hints.drain(..).collect();
+        for hint in retained.into_iter().chain(newer) {
+            Self::push_hint(&mut hints, hint, self.config.max_hints, &self.hints_dropped);
+        }
+        delivered
+    }
+
+    /// Queue a hint, replacing an older hint for the same replica and key
+    fn push_hint(hints: &mut VecDeque<Hint>, hint: Hint, max_hints: usize, dropped: &AtomicU64) {
+        if let Some(existing) = hints
+            .iter_mut()
+            .find(|existing| existing.replica_id == hint.replica_id && existing.key == hint.key)
+        {
+            if hint.version > existing.version {
+                existing.version = hint.version;
+                existing.encoded = hint.encoded;
+            }
+            return;
+        }
+        while hints.len() >= max_hints.max(1) {
+            hints.pop_front();
+            dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        hints.push_back(hint);
+    }
+
+    /// Indices of the backends holding replicas of `key`
+    pub fn replicas_for(&self, key: &str) -> Vec<usize> {
+        self.placement(&self.ring, key)
+    }
+
+    /// Add a backend and move the keys it becomes responsible for onto it
+    pub fn add_backend(&mut self, backend: Box<dyn StorageBackend>) -> Result<RebalanceReport> {
+        self.backends.push(Replica {
+            id: self.next_id,
+            backend: Mutex::new(backend),
+        });
+        self.next_id += 1;
+        self.ring = HashRing::new(self.backends.iter().map(|r| r.id), self.config.virtual_nodes);
+        self.rebalance()
+    }
+
+    /// Move every key off the backend at `index`, then detach and return it
+    pub fn remove_backend(&mut self, index: usize) -> Result<(Box<dyn StorageBackend>, RebalanceReport)> {
+        if index >= self.backends.len() || self.backends.len() <= self.config.replication_factor {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "replication_factor".to_string(),
+                reason: "Cannot remove a backend without dropping below replication_factor".to_string(),
+            });
+        }
+        let removed_id = self.backends[index].id;
+        self.ring = HashRing::new(
+            self.backends.iter().map(|r| r.id).filter(|&id| id != removed_id),
+            self.config.virtual_nodes,
+        );
+        let report = self.rebalance()?;
+        let replica = self.backends.remove(index);
+        Ok((replica.backend.into_inner().unwrap_or_else(|e| e.into_inner()), report))
+    }
+
+    /// Ensure every key lives exactly on the backends the ring assigns it to
+    ///
+    /// The newest version found on any backend is copied to responsible
+    /// backends and removed from the others. Keys whose copies conflict at the
+    /// same version are left in place and counted.
+    pub fn rebalance(&mut self) -> Result<RebalanceReport> {
+        let mut keys = BTreeSet::new();
+        for index in 0..self.backends.len() {
+            keys.extend(self.with_backend(index, |backend| backend.list_keys())?);
+        }
+
+        let mut report = RebalanceReport::default();
+        for key in keys {
+            report.keys += 1;
+            let mut copies = Vec::new();
+            for index in 0..self.backends.len() {
+                if let Some(data) = self.with_backend(index, |backend| backend.load(&key))? {
+                    copies.push((index, Envelope::decode(data)));
+                }
+            }
+            let Some(chosen) = copies.iter().map(|(_, copy)| copy).max_by_key(|copy| copy.version).cloned() else {
+                continue;
+            };
+            if copies.iter().any(|(_, copy)| copy.version == chosen.version && *copy != chosen) {
+                report.conflicts += 1;
+                continue;
+            }
+
+            let owners = self.placement(&self.ring, &key);
+            let encoded = chosen.encode();
+            for &index in &owners {
+                if !copies.iter().any(|(i, copy)| *i == index && *copy == chosen) {
+                    self.with_backend(index, |backend| backend.save(&key, &encoded))?;
+                    report.copied += 1;
+                }
+            }
+            for (index, _) in &copies {
+                if !owners.contains(index) {
+                    self.with_backend(*index, |backend| backend.delete(&key))?;
+                    report.removed += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Backend indices the given ring assigns `key` to
+    fn placement(&self, ring: &HashRing, key: &str) -> Vec<usize> {
+        ring.owners(key, self.config.replication_factor)
+            .into_iter()
+            .filter_map(|id| self.backends.iter().position(|replica| replica.id == id))
+            .collect()
+    }
+
+    /// Run `op` against one backend
+    fn with_backend<T>(&self, index: usize, op: impl FnOnce(&mut dyn StorageBackend) -> Result<T>) -> Result<T> {
+        let mut backend = self.backends[index].backend.lock().unwrap_or_else(|e| e.into_inner());
+        op(backend.as_mut())
+    }
+
+    /// Next write version: wall-clock microseconds, forced to increase monotonically
+    fn next_version(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+        let mut last = self.last_version.load(Ordering::Relaxed);
+        loop {
+            let next = now.max(last + 1);
+            match self
+                .last_version
+                .compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => return next,
+                Err(current) => last = current,
+            }
+        }
+    }
+
+    /// Write an envelope to the replicas of `key`, requiring a write quorum
+    ///
+    /// Replica writes that fail, or that async mode defers once the quorum is
+    /// reached, are queued as hints. Older hints for the replicas written
+    /// directly are dropped, so replaying them cannot undo this write.
+    fn write_envelope(&self, operation: &str, key: &str, envelope: &Envelope) -> Result<()> {
+        let encoded = envelope.encode();
+        let mut written = Vec::new();
+        let mut deferred = Vec::new();
+        for index in self.replicas_for(key) {
+            let quorum_reached = written.len() >= self.config.write_quorum;
+            if self.config.replication == ReplicationMode::Async && quorum_reached {
+                deferred.push(index);
+            } else if self.with_backend(index, |backend| backend.save(key, &encoded)).is_ok() {
+                written.push(self.backends[index].id);
+            } else {
+                deferred.push(index);
+            }
+        }
+        self.hints.lock().unwrap_or_else(|e| e.into_inner()).retain(|hint| {
+            hint.key != key || !written.contains(&hint.replica_id) || hint.version > envelope.version
+        });
+        let acknowledged = written.len();
+        if acknowledged < self.config.write_quorum {
+            return Err(ChronoMerkleError::QuorumNotReached {
+                operation: operation.to_string(),
+                required: self.config.write_quorum,
+                received: acknowledged,
+            });
+        }
+
+        let mut hints = self.hints.lock().unwrap_or_else(|e| e.into_inner());
+        for index in deferred {
+            let hint = Hint {
+                replica_id: self.backends[index].id,
+                key: key.to_string(),
+                version: envelope.version,
+                encoded: encoded.clone(),
+                queued_at: Instant::now(),
+            };
+            Self::push_hint(&mut hints, hint, self.config.max_hints, &self.hints_dropped);
+        }
+        Ok(())
+    }
+
+    /// Read all replicas of `key`, resolve conflicts and repair stale replicas
+    fn read_resolved(&self, key: &str) -> Result<Option<Envelope>> {
+        let responses: Vec<(usize, Option<Envelope>)> = self
+            .replicas_for(key)
+            .into_iter()
+            .filter_map(|index| {
+                let loaded = self.with_backend(index, |backend| backend.load(key)).ok()?;
+                Some((index, loaded.map(Envelope::decode)))
+            })
+            .collect();
+        if responses.len() < self.config.read_quorum {
+            return Err(ChronoMerkleError::QuorumNotReached {
+                operation: "load".to_string(),
+                required: self.config.read_quorum,
+                received: responses.len(),
+            });
+        }
+
+        let values: Vec<&Envelope> = responses.iter().filter_map(|(_, value)| value.as_ref()).collect();
+        let Some(chosen) = self.resolve(key, &values)? else {
+            return Ok(None);
+        };
+        if values.len() != responses.len() || values.iter().any(|value| **value != chosen) {
+            self.conflicts_resolved.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let encoded = chosen.encode();
+        for (index, value) in &responses {
+            if value.as_ref() != Some(&chosen)
+                && self.with_backend(*index, |backend| backend.save(key, &encoded)).is_ok()
+            {
+                self.read_repairs.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Ok(Some(chosen))
+    }
+
+    /// Choose one value among the replica values
+    fn resolve(&self, key: &str, values: &[&Envelope]) -> Result<Option<Envelope>> {
+        let mut distinct: Vec<(&Envelope, usize)> = Vec::new();
+        for value in values {
+            match distinct.iter_mut().find(|(candidate, _)| candidate == value) {
+                Some((_, votes)) => *votes += 1,
+                None => distinct.push((value, 1)),
+            }
+        }
+
+        let (Some(most_votes), Some(newest)) = (
+            distinct.iter().map(|(_, votes)| *votes).max(),
+            distinct.iter().map(|(value, _)| value.version).max(),
+        ) else {
+            return Ok(None);
+        };
+        let winners: Vec<&Envelope> = distinct
+            .iter()
+            .filter(|(value, votes)| match self.config.resolution {
+                ConflictResolution::Majority => *votes == most_votes,
+                ConflictResolution::NewestVersion => value.version == newest,
+            })
+            .map(|(value, _)| *value)
+            .collect();
+
+        match winners.as_slice() {
+            [winner] => Ok(Some((*winner).clone())),
+            _ => Err(ChronoMerkleError::ReplicaConflict {
+                key: key.to_string(),
+                candidates: winners.len(),
+            }),
+        }
+    }
+}
+
+impl StorageBackend for DistributedStorage {
+    fn save(&mut self, key: &str, data: &[u8]) -> Result<()> {
+        let envelope = Envelope {
+            version: self.next_version(),
+            tombstone: false,
+            payload: data.to_vec(),
+        };
+        self.write_envelope("save", key, &envelope)
+    }
+
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self
+            .read_resolved(key)?
+            .filter(|value| !value.tombstone)
+            .map(|value| value.payload))
+    }
+
+    /// Deletes write a versioned tombstone so stale replicas cannot resurrect the key
+    fn delete(&mut self, key: &str) -> Result<()> {
+        let envelope = Envelope {
+            version: self.next_version(),
+            tombstone: true,
+            payload: Vec::new(),
+        };
+        self.write_envelope("delete", key, &envelope)
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>> {
+        let mut keys = BTreeSet::new();
+        for index in 0..self.backends.len() {
+            if let Ok(backend_keys) = self.with_backend(index, |backend| backend.list_keys()) {
+                keys.extend(backend_keys);
+            }
+        }
+        let mut live = Vec::with_capacity(keys.len());
+        for key in keys {
+            if self.exists(&key)? {
+                live.push(key);
+            }
+        }
+        Ok(live)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.read_resolved(key)?.is_some_and(|value| !value.tombstone))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn cluster(config: DistributedConfig) -> DistributedStorage {
+        let backends: Vec<Box<dyn StorageBackend>> = (0..3)
+            .map(|_| Box::new(MemoryStorage::new()) as Box<dyn StorageBackend>)
+            .collect();
+        DistributedStorage::new(backends, config).unwrap()
+    }
+
+    fn corrupt(storage: &DistributedStorage, index: usize, key: &str, envelope: &Envelope) {
+        storage.with_backend(index, |b| b.save(key, &envelope.encode())).unwrap();
+    }
+
+    #[test]
+    fn test_newest_version_wins_and_repairs() {
+        let mut storage = cluster(DistributedConfig::default());
+        storage.save("key", b"v1").unwrap();
+        let stale = Envelope::decode(storage.with_backend(0, |b| b.load("key")).unwrap().unwrap());
+        storage.save("key", b"v2").unwrap();
+        corrupt(&storage, 1, "key", &stale);
+
+        assert_eq!(storage.load("key").unwrap(), Some(b"v2".to_vec()));
+        assert_eq!(storage.stats().read_repairs, 1);
+        assert_eq!(storage.stats().conflicts_resolved, 1);
+        let repaired = storage.with_backend(1, |b| b.load("key")).unwrap().unwrap();
+        assert_eq!(Envelope::decode(repaired).payload, b"v2");
+    }
+
+    #[test]
+    fn test_majority_and_irreconcilable_conflicts() {
+        let mut storage = cluster(DistributedConfig {
+            resolution: ConflictResolution::Majority,
+            ..DistributedConfig::default()
+        });
+        storage.save("key", b"agreed").unwrap();
+        let odd = Envelope { version: 99, tombstone: false, payload: b"odd".to_vec() };
+        corrupt(&storage, 2, "key", &odd);
+        assert_eq!(storage.load("key").unwrap(), Some(b"agreed".to_vec()));
+
+        let other = Envelope { version: 7, tombstone: false, payload: b"other".to_vec() };
+        corrupt(&storage, 0, "key", &odd);
+        corrupt(&storage, 1, "key", &other);
+        corrupt(&storage, 2, "key", &Envelope { version: 8, tombstone: false, payload: b"third".to_vec() });
+        assert!(matches!(storage.load("key"), Err(ChronoMerkleError::ReplicaConflict { candidates: 3, .. })));
+    }
+
+    #[test]
+    fn test_delete_is_not_resurrected() {
+        let mut storage = cluster(DistributedConfig::default());
+        storage.save("key", b"value").unwrap();
+        let old = Envelope::decode(storage.with_backend(0, |b| b.load("key")).unwrap().unwrap());
+        storage.delete("key").unwrap();
+        corrupt(&storage, 0, "key", &old);
+
+        assert_eq!(storage.load("key").unwrap(), None);
+        assert!(storage.list_keys().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_keys_spread_across_ring() {
+        let backends: Vec<Box<dyn StorageBackend>> = (0..5)
+            .map(|_| Box::new(MemoryStorage::new()) as Box<dyn StorageBackend>)
+            .collect();
+        let config = DistributedConfig { replication_factor: 2, write_quorum: 2, read_quorum: 1, ..Default::default() };
+        let mut storage = DistributedStorage::new(backends, config).unwrap();
+        for i in 0..500 {
+            storage.save(&format!("key{}", i), b"value").unwrap();
+        }
+        for index in 0..5 {
+            let held = storage.with_backend(index, |b| b.list_keys()).unwrap().len();
+            assert!(held > 100 && held < 300, "backend {} holds {} keys", index, held);
+        }
+        assert_eq!(storage.replicas_for("key1"), storage.replicas_for("key1"));
+    }
+
+    #[test]
+    fn test_add_and_remove_backends_rebalance() {
+        let mut storage = cluster(DistributedConfig::default());
+        for i in 0..200 {
+            storage.save(&format!("key{}", i), format!("value{}", i).as_bytes()).unwrap();
+        }
+
+        let report = storage.add_backend(Box::new(MemoryStorage::new())).unwrap();
+        assert_eq!(report.keys, 200);
+        assert!(report.copied > 0);
+        assert_eq!(report.copied, report.removed);
+        assert!(!storage.with_backend(3, |b| b.list_keys()).unwrap().is_empty());
+
+        let (removed, _) = storage.remove_backend(0).unwrap();
+        assert!(removed.list_keys().unwrap().is_empty());
+        for i in 0..200 {
+            let expected = format!("value{}", i).into_bytes();
+            assert_eq!(storage.load(&format!("key{}", i)).unwrap(), Some(expected));
+        }
+        assert!(storage.remove_backend(0).is_err());
+    }
+
+    /// Backend that fails every operation while `down` is set
+    struct Switchable {
+        inner: MemoryStorage,
+        down: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    impl Switchable {
+        fn check(&self) -> Result<()> {
+            if self.down.load(Ordering::Relaxed) {
+                return Err(ChronoMerkleError::StorageError { reason: "replica down".to_string() });
+            }
+            Ok(())
+        }
+    }
+
+    impl StorageBackend for Switchable {
+        fn save(&mut self, key: &str, data: &[u8]) -> Result<()> {
+            self.check()?;
+            self.inner.save(key, data)
+        }
+
+        fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            self.check()?;
+            self.inner.load(key)
+        }
+
+        fn delete(&mut self, key: &str) -> Result<()> {
+            self.check()?;
+            self.inner.delete(key)
+        }
+
+        fn list_keys(&self) -> Result<Vec<String>> {
+            self.check()?;
+            self.inner.list_keys()
+        }
+
+        fn exists(&self, key: &str) -> Result<bool> {
+            self.check()?;
+            self.inner.exists(key)
+        }
+    }
+
+    #[test]
+    fn test_failed_replica_writes_are_handed_off() {
+        let down = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let backends: Vec<Box<dyn StorageBackend>> = vec![
+            Box::new(MemoryStorage::new()),
+            Box::new(MemoryStorage::new()),
+            Box::new(Switchable { inner: MemoryStorage::new(), down: down.clone() }),
+        ];
+        let mut storage = DistributedStorage::new(backends, DistributedConfig::default()).unwrap();
+
+        down.store(true, Ordering::Relaxed);
+        storage.save("key", b"v1").unwrap();
+        storage.save("key", b"v2").unwrap();
+        assert_eq!(storage.replication_lag().pending_hints, 1);
+        assert_eq!(storage.replay_hints(), 0);
+
+        down.store(false, Ordering::Relaxed);
+        assert_eq!(storage.replay_hints(), 1);
+        let lag = storage.replication_lag();
+        assert_eq!((lag.pending_hints, lag.hints_replayed), (0, 1));
+        let replica = Envelope::decode(storage.with_backend(2, |b| b.load("key")).unwrap().unwrap());
+        assert_eq!(replica.payload, b"v2");
+    }
+
+    #[test]
+    fn test_stale_hints_do_not_overwrite_newer_writes() {
+        let down = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let backends: Vec<Box<dyn StorageBackend>> = vec![
+            Box::new(MemoryStorage::new()),
+            Box::new(MemoryStorage::new()),
+            Box::new(Switchable { inner: MemoryStorage::new(), down: down.clone() }),
+        ];
+        let mut storage = DistributedStorage::new(backends, DistributedConfig::default()).unwrap();
+        let replica = |storage: &DistributedStorage| Envelope::decode(storage.with_backend(2, |b| b.load("key")).unwrap().unwrap());
+
+        // A direct write to the recovered replica drops its pending hint
+        down.store(true, Ordering::Relaxed);
+        storage.save("key", b"v1").unwrap();
+        down.store(false, Ordering::Relaxed);
+        storage.save("key", b"v2").unwrap();
+        assert_eq!(storage.replication_lag().pending_hints, 0);
+        assert_eq!(storage.replay_hints(), 0);
+        assert_eq!(replica(&storage).payload, b"v2");
+
+        // A hint older than the replica's copy is discarded on replay
+        down.store(true, Ordering::Relaxed);
+        storage.save("key", b"v3").unwrap();
+        down.store(false, Ordering::Relaxed);
+        let newer = Envelope { version: u64::MAX, tombstone: false, payload: b"v4".to_vec() };
+        storage.with_backend(2, |b| b.save("key", &newer.encode())).unwrap();
+        assert_eq!(storage.replay_hints(), 0);
+        assert_eq!(storage.replication_lag().pending_hints, 0);
+        assert_eq!(replica(&storage).payload, b"v4");
+    }
+
+    #[test]
+    fn test_async_mode_acks_after_quorum() {
+        let mut storage = cluster(DistributedConfig {
+            replication: ReplicationMode::Async,
+            ..DistributedConfig::default()
+        });
+        storage.save("key", b"value").unwrap();
+        let holders = (0..3)
+            .filter(|&i| storage.with_backend(i, |b| b.exists("key")).unwrap())
+            .count();
+        assert_eq!(holders, 2);
+        assert_eq!(storage.replication_lag().pending_hints, 1);
+        assert!(storage.replication_lag().oldest_hint_age.is_some());
+
+        storage.replay_hints();
+        let holders = (0..3)
+            .filter(|&i| storage.with_backend(i, |b| b.exists("key")).unwrap())
+            .count();
+        assert_eq!(holders, 3);
+    }
+
+    #[test]
+    fn test_invalid_quorums_rejected() {
+        let backends: Vec<Box<dyn StorageBackend>> = vec![Box::new(MemoryStorage::new())];
+        let config = DistributedConfig { replication_factor: 1, write_quorum: 2, ..DistributedConfig::default() };
+        assert!(DistributedStorage::new(backends, config).is_err());
+    }
+}
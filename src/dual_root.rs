@@ -0,0 +1,176 @@
+//! Algorithm agility through two hash functions at once
+//!
+//! During a hash function transition some verifiers may only accept the old
+//! algorithm and others only the new one. [`DualRootTree`] keeps one tree per
+//! hasher over the same leaves, so both roots are available after every insert
+//! and proofs can be generated under either.
+
+use crate::config::TreeConfig;
+use crate::error::Result;
+use crate::hash::HashFunction;
+use crate::proof::ChronoProof;
+use crate::security::{NoOpLogger, SecurityLogger};
+use crate::tree::ChronoMerkleTree;
+
+/// Tree maintaining roots under a primary and a secondary hash function
+///
+/// Security events are logged by the primary tree only.
+pub struct DualRootTree<Primary, Secondary, Logger = NoOpLogger>
+where
+    Primary: HashFunction + Sync,
+    Primary::Output: Send + Sync + crate::traits::MaybeSerde,
+    Secondary: HashFunction + Sync,
+    Secondary::Output: Send + Sync + crate::traits::MaybeSerde,
+    Logger: SecurityLogger,
+{
+    primary: ChronoMerkleTree<Primary::Output, Primary, Logger>,
+    secondary: ChronoMerkleTree<Secondary::Output, Secondary, NoOpLogger>,
+}
+
+impl<Primary, Secondary, Logger> DualRootTree<Primary, Secondary, Logger>
+where
+    Primary: HashFunction + Default + Sync,
+    Primary::Output: Send + Sync + crate::traits::MaybeSerde,
+    Secondary: HashFunction + Default + Sync,
+    Secondary::Output: Send + Sync + crate::traits::MaybeSerde,
+    Logger: SecurityLogger + Default,
+{
+    /// Create an empty dual-root tree
+    pub fn new(primary: Primary, secondary: Secondary) -> Self {
+        Self {
+            primary: ChronoMerkleTree::new(primary),
+            secondary: ChronoMerkleTree::new(secondary),
+        }
+    }
+
+    /// Create an empty dual-root tree sharing `config` between both hashers
+    pub fn with_config(primary: Primary, secondary: Secondary, config: TreeConfig) -> Result<Self> {
+        Ok(Self {
+            primary: ChronoMerkleTree::with_config(primary, config.clone())?,
+            secondary: ChronoMerkleTree::with_config(secondary, config)?,
+        })
+    }
+}
+
+impl<Primary, Secondary, Logger> DualRootTree<Primary, Secondary, Logger>
+where
+    Primary: HashFunction + Sync,
+    Primary::Output: Send + Sync + crate::traits::MaybeSerde,
+    Secondary: HashFunction + Sync,
+    Secondary::Output: Send + Sync + crate::traits::MaybeSerde,
+    Logger: SecurityLogger,
+{
+    /// Insert a leaf under both hashers
+    ///
+    /// If the secondary insert fails the primary tree is restored, so both
+    /// trees always cover the same leaves.
+    pub fn insert(&mut self, data: &[u8], timestamp: u64) -> Result<()> {
+        let checkpoint = self.primary.snapshot();
+        self.primary.insert(data, timestamp)?;
+        if let Err(e) = self.secondary.insert(data, timestamp) {
+            self.primary.restore_snapshot(checkpoint);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Insert several leaves under both hashers with one rebuild per tree
+    pub fn insert_batch(&mut self, entries: &[(&[u8], u64)]) -> Result<()> {
+        let checkpoint = self.primary.snapshot();
+        self.primary.insert_batch(entries)?;
+        if let Err(e) = self.secondary.insert_batch(entries) {
+            self.primary.restore_snapshot(checkpoint);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Get both roots as `(primary, secondary)`
+    pub fn roots(&self) -> (Option<Primary::Output>, Option<Secondary::Output>) {
+        (self.primary.root(), self.secondary.root())
+    }
+
+    /// Generate a proof under the primary hasher
+    pub fn generate_primary_proof(&self, leaf_index: usize) -> Result<ChronoProof<Primary::Output>> {
+        self.primary.generate_proof(leaf_index)
+    }
+
+    /// Generate a proof under the secondary hasher
+    pub fn generate_secondary_proof(&self, leaf_index: usize) -> Result<ChronoProof<Secondary::Output>> {
+        self.secondary.generate_proof(leaf_index)
+    }
+
+    /// Number of leaves in both trees
+    pub fn leaf_count(&self) -> usize {
+        self.primary.leaf_count()
+    }
+
+    /// Tree hashed with the primary hasher
+    pub fn primary(&self) -> &ChronoMerkleTree<Primary::Output, Primary, Logger> {
+        &self.primary
+    }
+
+    /// Tree hashed with the secondary hasher
+    pub fn secondary(&self) -> &ChronoMerkleTree<Secondary::Output, Secondary, NoOpLogger> {
+        &self.secondary
+    }
+
+    /// Split into the primary and secondary trees, e.g. to drop one hasher
+    /// at the end of a transition
+    #[allow(clippy::type_complexity)]
+    pub fn into_parts(
+        self,
+    ) -> (
+        ChronoMerkleTree<Primary::Output, Primary, Logger>,
+        ChronoMerkleTree<Secondary::Output, Secondary, NoOpLogger>,
+    ) {
+        (self.primary, self.secondary)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    #[derive(Clone, Default)]
+    struct Prefixed;
+
+    impl HashFunction for Prefixed {
+        type Output = [u8; 32];
+
+        fn name(&self) -> &str {
+            "prefixed-blake3"
+        }
+
+        fn hash(&self, data: &[u8]) -> Self::Output {
+            Blake3Hasher.hash_multiple(&[b"v2", data])
+        }
+    }
+
+    #[test]
+    fn test_dual_roots() {
+        let mut dual: DualRootTree<Blake3Hasher, Prefixed> = DualRootTree::new(Blake3Hasher, Prefixed);
+        let mut primary: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut secondary: ChronoMerkleTree<[u8; 32], Prefixed> = ChronoMerkleTree::default();
+        for i in 0..3u64 {
+            let data = format!("data{}", i);
+            dual.insert(data.as_bytes(), 1000 + i).unwrap();
+            primary.insert(data.as_bytes(), 1000 + i).unwrap();
+            secondary.insert(data.as_bytes(), 1000 + i).unwrap();
+        }
+        dual.insert_batch(&[(b"data3", 1003), (b"data4", 1004)]).unwrap();
+        primary.insert_batch(&[(b"data3", 1003), (b"data4", 1004)]).unwrap();
+        secondary.insert_batch(&[(b"data3", 1003), (b"data4", 1004)]).unwrap();
+
+        assert_eq!(dual.roots(), (primary.root(), secondary.root()));
+        assert_eq!(dual.leaf_count(), 5);
+        let proof = dual.generate_secondary_proof(2).unwrap();
+        assert!(secondary.verify_proof(&proof).unwrap());
+        assert!(!primary.verify_proof(&proof).unwrap());
+        assert!(primary.verify_proof(&dual.generate_primary_proof(2).unwrap()).unwrap());
+
+        assert!(dual.insert(b"", 1005).is_err());
+        assert_eq!(dual.primary().leaf_count(), dual.secondary().leaf_count());
+    }
+}
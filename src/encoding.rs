@@ -0,0 +1,75 @@
+//! Fixed-width little-endian integer encoding
+//!
+//! Every integer that enters a hash pre-image, a signed message or a wire
+//! format (timestamps, leaf indices, counters, versions) is encoded with these
+//! helpers. The output does not depend on the host's byte order or pointer
+//! width, so roots, checksums and signatures computed on big-endian or wasm32
+//! targets match those computed on x86-64. Indices are always widened to
+//! 64 bits.
+
+/// Encode a `u64`, e.g. a timestamp, as 8 little-endian bytes
+pub fn encode_u64(value: u64) -> [u8; 8] {
+    value.to_le_bytes()
+}
+
+/// Encode a `u32` as 4 little-endian bytes
+pub fn encode_u32(value: u32) -> [u8; 4] {
+    value.to_le_bytes()
+}
+
+/// Encode a leaf or node index as 8 little-endian bytes on every target
+pub fn encode_index(index: usize) -> [u8; 8] {
+    encode_u64(index as u64)
+}
+
+/// Decode 8 little-endian bytes, or `None` if `bytes` has another length
+pub fn decode_u64(bytes: &[u8]) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Decode 4 little-endian bytes, or `None` if `bytes` has another length
+pub fn decode_u32(bytes: &[u8]) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Decode an index written by [`encode_index`], or `None` if it does not fit this target
+pub fn decode_index(bytes: &[u8]) -> Option<usize> {
+    usize::try_from(decode_u64(bytes)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_byte_order() {
+        assert_eq!(encode_u64(0x0102_0304_0506_0708), [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(encode_u32(0x0102_0304), [4, 3, 2, 1]);
+        assert_eq!(encode_index(0x0102_0304), [4, 3, 2, 1, 0, 0, 0, 0]);
+        assert_eq!(decode_u64(&[8, 7, 6, 5, 4, 3, 2, 1]), Some(0x0102_0304_0506_0708));
+        assert_eq!(decode_u32(&[4, 3, 2, 1]), Some(0x0102_0304));
+        assert_eq!(decode_u64(&[1, 2, 3]), None);
+    }
+
+    #[test]
+    fn test_matches_big_endian_host() {
+        // A big-endian host stores the byte-swapped value in native order
+        for value in [0u64, 1, 1_700_000_000, u64::MAX - 7, 0x8000_0000_0000_0001] {
+            assert_eq!(encode_u64(value), value.swap_bytes().to_be_bytes());
+            assert_eq!(decode_u64(&value.swap_bytes().to_be_bytes()), Some(value));
+            let small = value as u32;
+            assert_eq!(encode_u32(small), small.swap_bytes().to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn test_index_width_is_target_independent() {
+        // wasm32 and other 32-bit targets must produce the same 8 bytes
+        let index = u32::MAX as usize;
+        assert_eq!(encode_index(index), encode_u64(u32::MAX as u64));
+        assert_eq!(decode_index(&encode_index(index)), Some(index));
+        if usize::BITS == 32 {
+            assert_eq!(decode_index(&encode_u64(u64::MAX)), None);
+        }
+    }
+}
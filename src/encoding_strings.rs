@@ -0,0 +1,107 @@
+//! Hex and base64 string forms for REST and JSON consumers
+//!
+//! Services exchanging roots, records and proofs as JSON otherwise convert
+//! every field by hand. With the `encoding-strings` feature, roots are
+//! available as lowercase hex ([`ChronoMerkleTree::root_hex`]), records can be
+//! inserted from hex ([`ChronoMerkleTree::insert_hex`]), published roots parse
+//! back into a [`RootHash`], and proofs travel as a single base64 string
+//! ([`ChronoProof::to_base64`]).
+//!
+//! The base64 form is the byte encoding of [`CompressedProof`] with every
+//! sibling stored, so unlike a compressed proof it is restored without the
+//! leaf hash.
+
+use crate::codec;
+use crate::display::RootHash;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::ChronoProof;
+use crate::proof_compression::CompressedProof;
+use crate::security::SecurityLogger;
+use crate::traits::HashOutput;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::string::{String, ToString};
+
+impl<H: HashOutput> ChronoProof<H> {
+    /// Encode the proof as standard padded base64
+    pub fn to_base64(&self) -> String {
+        codec::to_base64(&self.pack().to_bytes())
+    }
+
+    /// Decode a proof encoded with [`to_base64`](Self::to_base64)
+    pub fn from_base64(text: &str) -> Result<Self> {
+        let bytes = codec::from_base64(text.trim()).ok_or_else(|| ChronoMerkleError::InvalidProof {
+            message: "Proof is not valid base64".to_string(),
+        })?;
+        Self::unpack(&CompressedProof::from_bytes(&bytes)?)
+    }
+}
+
+impl<H: HashOutput> core::str::FromStr for RootHash<H> {
+    type Err = ChronoMerkleError;
+
+    /// Parse a root from hex of either case
+    fn from_str(text: &str) -> Result<Self> {
+        codec::from_hex(text.trim())
+            .and_then(|bytes| H::from_bytes(&bytes))
+            .map(RootHash)
+            .ok_or_else(|| ChronoMerkleError::HashError {
+                message: "Root is not a hex hash of the expected length".to_string(),
+            })
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Get the root as lowercase hex
+    pub fn root_hex(&self) -> Option<String> {
+        self.root().map(|root| codec::to_hex(root.as_ref()))
+    }
+
+    /// Insert a record given as hex of either case
+    pub fn insert_hex(&mut self, data_hex: &str, timestamp: u64) -> Result<()> {
+        let data = codec::from_hex(data_hex.trim()).ok_or_else(|| ChronoMerkleError::InvalidConfiguration {
+            parameter: "data".to_string(),
+            reason: "Data is not valid hex".to_string(),
+        })?;
+        self.insert(&data, timestamp)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_string_apis_roundtrip() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert_hex("DEADbeef", 1000).unwrap();
+        tree.insert_hex("00ff", 1001).unwrap();
+        tree.insert(b"raw", 1002).unwrap();
+        assert_eq!(tree.get_leaf_data(0).unwrap().data(), Some(&[0xde, 0xad, 0xbe, 0xef][..]));
+        assert!(tree.insert_hex("abc", 1003).is_err());
+        assert!(tree.insert_hex("zz", 1003).is_err());
+        assert_eq!(tree.leaf_count(), 3);
+        assert_eq!(tree.root_hex(), Some(hex::encode(tree.root().unwrap())));
+        let root: RootHash<[u8; 32]> = tree.root_hex().unwrap().to_uppercase().parse().unwrap();
+        assert_eq!(Some(root.into_inner()), tree.root());
+        assert!("abcd".parse::<RootHash<[u8; 32]>>().is_err());
+
+        // Leaf 2 is paired with itself, which a compressed proof would elide
+        let proof = tree.generate_proof(2).unwrap();
+        let encoded = proof.to_base64();
+        let decoded = ChronoProof::<[u8; 32]>::from_base64(&encoded).unwrap();
+        assert_eq!(decoded.path, proof.path);
+        assert_eq!((decoded.leaf_index, decoded.timestamp), (2, 1002));
+        assert!(tree.verify_proof(&decoded).unwrap());
+
+        assert!(ChronoProof::<[u8; 32]>::from_base64("not base64!").is_err());
+        assert!(ChronoProof::<[u8; 32]>::from_base64(&encoded[..encoded.len() - 4]).is_err());
+    }
+}
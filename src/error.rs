@@ -24,6 +24,42 @@ pub enum ChronoMerkleError {
         message: String
     },
 
+    /// Proof path is longer than any tree allowed by the configuration
+    #[error("Proof has {steps} steps, more than the limit of {max}")]
+    ProofTooLong {
+        /// Number of steps in the proof
+        steps: usize,
+        /// Largest number of steps accepted
+        max: usize,
+    },
+
+    /// Proof carries more delta hashes than any proof can use
+    #[error("Proof delta chain has {length} entries, more than the limit of {max}")]
+    DeltaChainTooLong {
+        /// Number of entries in the delta chain
+        length: usize,
+        /// Largest delta chain accepted
+        max: usize,
+    },
+
+    /// Leaf index lies beyond every leaf a path of this length can reach
+    #[error("Leaf index {index} is unreachable with {levels} sibling steps")]
+    LeafIndexUnreachable {
+        /// Leaf index claimed by the proof
+        index: usize,
+        /// Number of sibling steps in the path
+        levels: usize,
+    },
+
+    /// Roots or proofs follow a spec version the verifier does not accept
+    #[error("Spec version {version} is not accepted (this crate implements version {current})")]
+    UnsupportedSpecVersion {
+        /// Version advertised by the producer
+        version: u32,
+        /// Version this crate implements
+        current: u32,
+    },
+
     /// Proof verification failed
     #[error("Proof verification failed: {reason}")]
     ProofVerificationFailed {
@@ -79,6 +115,38 @@ pub enum ChronoMerkleError {
         reason: String
     },
 
+    /// Tree does not match an integrity manifest
+    #[error("Manifest mismatch in {field}: {reason}")]
+    ManifestMismatch {
+        /// Manifest field that differs
+        field: String,
+        /// Description of the difference
+        reason: String,
+    },
+
+    /// A long operation was aborted through its cancellation token
+    #[error("Operation cancelled: {operation}")]
+    Cancelled {
+        /// Operation that was aborted
+        operation: String,
+    },
+
+    /// An insert was refused by the tree's admission controller
+    #[error("Insert quota exceeded: {quota}")]
+    QuotaExceeded {
+        /// Budget that ran out, `inserts` or `bytes`
+        quota: String,
+        /// Milliseconds until the write would be admitted, `None` if it never will
+        retry_after_ms: Option<u64>,
+    },
+
+    /// An insert was refused by an insert interceptor
+    #[error("Insert rejected: {reason}")]
+    InsertRejected {
+        /// Why the record was refused
+        reason: String,
+    },
+
     /// ClockHash integration error
     #[cfg(feature = "clockhash")]
     #[error("ClockHash integration error: {0}")]
@@ -99,6 +167,55 @@ pub enum ChronoMerkleError {
     #[error("Storage error: {reason}")]
     StorageError { reason: String },
 
+    /// A bulk load stopped at a record that failed parsing or validation
+    #[cfg(all(feature = "storage", feature = "std", not(feature = "no-std")))]
+    #[error("Record {record}: {source}")]
+    InvalidRecord {
+        /// One-based number of the record in the input
+        record: usize,
+        /// Why the record was refused
+        source: Box<ChronoMerkleError>,
+    },
+
+    /// Too few replicas answered a distributed storage operation
+    #[cfg(feature = "distributed-storage")]
+    #[error("Quorum not reached for {operation}: {received} of {required} replicas responded")]
+    QuorumNotReached {
+        /// Operation that failed
+        operation: String,
+        /// Number of successful replica responses required
+        required: usize,
+        /// Number of successful replica responses received
+        received: usize,
+    },
+
+    /// Replicas hold conflicting values that cannot be resolved automatically
+    #[cfg(feature = "distributed-storage")]
+    #[error("Irreconcilable replica conflict for key {key}: {candidates} competing values")]
+    ReplicaConflict {
+        /// Key with conflicting replicas
+        key: String,
+        /// Number of distinct values tied for selection
+        candidates: usize,
+    },
+
+    /// Write rejected because the value was written by a newer leader
+    #[cfg(feature = "coordination")]
+    #[error("Write to {key} fenced: token {token} is older than stored token {current}")]
+    Fenced {
+        /// Key being written
+        key: String,
+        /// Fencing token of the rejected writer
+        token: u64,
+        /// Fencing token the stored value was written under
+        current: u64,
+    },
+
+    /// Async tree writer is no longer running
+    #[cfg(feature = "async")]
+    #[error("Async tree writer has shut down")]
+    WriterClosed,
+
     /// Invalid configuration parameter
     #[error("Invalid configuration: {parameter} - {reason}")]
     InvalidConfiguration {
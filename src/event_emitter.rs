@@ -0,0 +1,462 @@
+//! Structured notifications about tree changes
+//!
+//! An [`EventEmitter`] registered with
+//! [`ChronoMerkleTree::set_event_emitter`] receives a [`TreeEvent`] for every
+//! inserted leaf, root change, rollback and delta pruning, so downstream
+//! systems can react without polling. Emitters run on the mutating thread and
+//! should hand events off quickly; [`WebhookEmitter`] queues them and delivers
+//! them from a background thread with retries.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// A change to a tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeEvent {
+    /// A leaf was appended
+    LeafInserted {
+        /// Index of the new leaf
+        index: usize,
+        /// Timestamp of the new leaf
+        timestamp: u64,
+        /// Hash of the new leaf
+        hash: Vec<u8>,
+    },
+    /// The root hash changed
+    RootChanged {
+        /// Root before the change
+        old_root: Option<Vec<u8>>,
+        /// Root after the change
+        new_root: Option<Vec<u8>>,
+    },
+    /// Leaves were removed by a rollback
+    RolledBack {
+        /// Timestamp rolled back to
+        target_timestamp: u64,
+        /// Number of leaves removed
+        removed_leaves: usize,
+    },
+    /// Old deltas were pruned
+    Pruned {
+        /// Deltas before this timestamp were removed
+        before_timestamp: u64,
+        /// Number of deltas removed
+        removed_deltas: usize,
+    },
+}
+
+impl TreeEvent {
+    /// Encode the event as a JSON object with a `type` field and hex-encoded hashes
+    pub fn to_json(&self) -> String {
+        let hex = crate::codec::to_hex;
+        let optional_hex = |bytes: &Option<Vec<u8>>| match bytes {
+            Some(bytes) => format!("\"{}\"", hex(bytes)),
+            None => "null".to_string(),
+        };
+        match self {
+            TreeEvent::LeafInserted { index, timestamp, hash } => format!(
+                "{{\"type\":\"leaf_inserted\",\"index\":{},\"timestamp\":{},\"hash\":\"{}\"}}",
+                index,
+                timestamp,
+                hex(hash)
+            ),
+            TreeEvent::RootChanged { old_root, new_root } => format!(
+                "{{\"type\":\"root_changed\",\"old_root\":{},\"new_root\":{}}}",
+                optional_hex(old_root),
+                optional_hex(new_root)
+            ),
+            TreeEvent::RolledBack { target_timestamp, removed_leaves } => format!(
+                "{{\"type\":\"rolled_back\",\"target_timestamp\":{},\"removed_leaves\":{}}}",
+                target_timestamp, removed_leaves
+            ),
+            TreeEvent::Pruned { before_timestamp, removed_deltas } => format!(
+                "{{\"type\":\"pruned\",\"before_timestamp\":{},\"removed_deltas\":{}}}",
+                before_timestamp, removed_deltas
+            ),
+        }
+    }
+}
+
+/// Receiver of tree events
+pub trait EventEmitter: Send + Sync {
+    /// Handle an event; called synchronously by the mutating operation
+    fn emit(&self, event: &TreeEvent);
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Send events about changes to this tree to `emitter`
+    pub fn set_event_emitter(&mut self, emitter: Arc<dyn EventEmitter>) {
+        self.event_emitter = Some(emitter);
+    }
+
+    /// Stop emitting events
+    pub fn clear_event_emitter(&mut self) {
+        self.event_emitter = None;
+    }
+
+    /// Emit the event built by `event` if an emitter is registered
+    pub(crate) fn emit_event(&self, event: impl FnOnce() -> TreeEvent) {
+        if let Some(emitter) = &self.event_emitter {
+            emitter.emit(&event());
+        }
+    }
+
+    /// Emit `RootChanged` if the root differs from `old_root`
+    pub(crate) fn emit_root_change(&self, old_root: Option<H>) {
+        let new_root = self.root();
+        if new_root != old_root {
+            self.emit_event(|| TreeEvent::RootChanged {
+                old_root: old_root.map(|root| root.as_ref().to_vec()),
+                new_root: new_root.map(|root| root.as_ref().to_vec()),
+            });
+        }
+    }
+}
+
+/// Delivery settings of a [`WebhookEmitter`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookConfig {
+    /// Endpoint receiving one `POST` per event, as `http://host[:port][/path]`
+    pub url: String,
+    /// Delivery attempts per event, including the first (at least 1)
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles after each retry
+    pub initial_backoff: Duration,
+    /// Connect, read and write timeout of each attempt
+    pub timeout: Duration,
+    /// Events that may wait for delivery; further events are dropped
+    pub queue_capacity: usize,
+}
+
+impl WebhookConfig {
+    /// Default settings for `url`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            timeout: Duration::from_secs(5),
+            queue_capacity: 1024,
+        }
+    }
+}
+
+/// Delivery counters of a [`WebhookEmitter`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WebhookStats {
+    /// Events delivered with a 2xx response
+    pub delivered: u64,
+    /// Events given up on after all attempts failed
+    pub failed: u64,
+    /// Events dropped because the queue was full
+    pub dropped: u64,
+    /// Retries performed
+    pub retries: u64,
+}
+
+#[derive(Default)]
+struct WebhookCounters {
+    delivered: AtomicU64,
+    failed: AtomicU64,
+    dropped: AtomicU64,
+    retries: AtomicU64,
+    pending: AtomicUsize,
+}
+
+/// Parsed `http://` endpoint
+#[derive(Debug, Clone)]
+struct Endpoint {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self> {
+        let invalid = |reason: &str| ChronoMerkleError::InvalidConfiguration {
+            parameter: "url".to_string(),
+            reason: format!("{}: {}", reason, url),
+        };
+        let rest = url.strip_prefix("http://").ok_or_else(|| invalid("Only http:// URLs are supported"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid("Invalid port"))?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid("Missing host"));
+        }
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// Emitter posting events as JSON to an HTTP endpoint
+///
+/// Events are queued and delivered in order by a background thread, so a
+/// slow endpoint never blocks tree operations. Dropping the emitter delivers
+/// the queued events before returning.
+pub struct WebhookEmitter {
+    sender: Option<SyncSender<String>>,
+    worker: Option<JoinHandle<()>>,
+    counters: Arc<WebhookCounters>,
+}
+
+impl WebhookEmitter {
+    /// Start delivering to the configured endpoint
+    pub fn new(config: WebhookConfig) -> Result<Self> {
+        let endpoint = Endpoint::parse(&config.url)?;
+        let (sender, receiver) = sync_channel::<String>(config.queue_capacity.max(1));
+        let counters = Arc::new(WebhookCounters::default());
+        let worker_counters = counters.clone();
+        let worker = std::thread::Builder::new()
+            .name("chrono-merkle-webhook".to_string())
+            .spawn(move || {
+                for body in receiver {
+                    deliver(&endpoint, &config, &body, &worker_counters);
+                    worker_counters.pending.fetch_sub(1, Ordering::SeqCst);
+                }
+            })
+            .map_err(|e| ChronoMerkleError::InvalidConfiguration {
+                parameter: "webhook".to_string(),
+                reason: format!("Failed to start delivery thread: {}", e),
+            })?;
+        Ok(Self {
+            sender: Some(sender),
+            worker: Some(worker),
+            counters,
+        })
+    }
+
+    /// Get delivery counters
+    pub fn stats(&self) -> WebhookStats {
+        WebhookStats {
+            delivered: self.counters.delivered.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            retries: self.counters.retries.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Number of events queued or being delivered
+    pub fn pending(&self) -> usize {
+        self.counters.pending.load(Ordering::SeqCst)
+    }
+
+    /// Wait until every queued event was delivered or given up on
+    ///
+    /// Returns `false` if events are still pending after `timeout`.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.pending() > 0 {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        true
+    }
+}
+
+impl EventEmitter for WebhookEmitter {
+    fn emit(&self, event: &TreeEvent) {
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        self.counters.pending.fetch_add(1, Ordering::SeqCst);
+        if let Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) = sender.try_send(event.to_json()) {
+            self.counters.pending.fetch_sub(1, Ordering::SeqCst);
+            self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for WebhookEmitter {
+    fn drop(&mut self) {
+        // Closing the channel lets the worker finish the queue and exit
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Deliver one event body, retrying with exponential backoff
+fn deliver(endpoint: &Endpoint, config: &WebhookConfig, body: &str, counters: &WebhookCounters) {
+    let mut backoff = config.initial_backoff;
+    for attempt in 1..=config.max_attempts.max(1) {
+        if matches!(post(endpoint, body, config.timeout), Ok(status) if (200..300).contains(&status)) {
+            counters.delivered.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        if attempt < config.max_attempts {
+            std::thread::sleep(backoff);
+            backoff = backoff.saturating_mul(2);
+            counters.retries.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    counters.failed.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Send a JSON `POST` and return the response status code
+fn post(endpoint: &Endpoint, body: &str, timeout: Duration) -> std::io::Result<u16> {
+    let address = (endpoint.host.as_str(), endpoint.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "Host did not resolve"))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        endpoint.path,
+        endpoint.host,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 256];
+    while !response.windows(2).any(|window| window == b"\r\n") {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        response.extend_from_slice(&buffer[..read]);
+    }
+    let status_line = String::from_utf8_lossy(&response);
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "Malformed HTTP response"))
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<TreeEvent>>);
+
+    impl EventEmitter for Recorder {
+        fn emit(&self, event: &TreeEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_events_for_tree_changes() {
+        let recorder = Arc::new(Recorder::default());
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.set_event_emitter(recorder.clone());
+
+        tree.insert(b"data1", 1000).unwrap();
+        tree.insert_batch(&[(b"data2", 1001), (b"data3", 1002)]).unwrap();
+        tree.rollback_to_timestamp(1000).unwrap();
+        tree.prune_deltas(2000);
+
+        let events = recorder.0.lock().unwrap();
+        let kinds: Vec<&str> = events
+            .iter()
+            .map(|event| match event {
+                TreeEvent::LeafInserted { .. } => "leaf",
+                TreeEvent::RootChanged { .. } => "root",
+                TreeEvent::RolledBack { .. } => "rollback",
+                TreeEvent::Pruned { .. } => "pruned",
+            })
+            .collect();
+        assert_eq!(kinds, ["leaf", "root", "leaf", "leaf", "root", "rollback", "root", "pruned"]);
+        assert_eq!(
+            events[5],
+            TreeEvent::RolledBack { target_timestamp: 1000, removed_leaves: 2 }
+        );
+    }
+
+    /// Accept `responses.len()` requests, answering with the given status codes
+    fn serve(responses: Vec<u16>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for status in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                loop {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length: usize = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("Content-Length: "))
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        if body.len() >= length {
+                            bodies.push(body.to_string());
+                            break;
+                        }
+                    }
+                }
+                write!(stream, "HTTP/1.1 {} X\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            }
+            bodies
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_webhook_delivers_with_retries() {
+        let (url, server) = serve(vec![500, 200, 200]);
+        let webhook = Arc::new(
+            WebhookEmitter::new(WebhookConfig {
+                initial_backoff: Duration::ZERO,
+                ..WebhookConfig::new(url)
+            })
+            .unwrap(),
+        );
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.set_event_emitter(webhook.clone());
+        tree.insert(b"data1", 1000).unwrap();
+
+        assert!(webhook.flush(Duration::from_secs(10)));
+        let stats = webhook.stats();
+        assert_eq!((stats.delivered, stats.retries, stats.failed), (2, 1, 0));
+        let bodies = server.join().unwrap();
+        assert!(bodies[0].starts_with("{\"type\":\"leaf_inserted\",\"index\":0"));
+        assert!(bodies[2].starts_with("{\"type\":\"root_changed\",\"old_root\":null"));
+    }
+
+    #[test]
+    fn test_webhook_rejects_unsupported_urls() {
+        assert!(WebhookEmitter::new(WebhookConfig::new("https://example.com")).is_err());
+        assert!(WebhookEmitter::new(WebhookConfig::new("http://:80/")).is_err());
+    }
+}
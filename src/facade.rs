@@ -0,0 +1,321 @@
+//! Ready-made logs built from the patterns in the examples
+//!
+//! [`TransactionLog`] packages the block structure of
+//! `examples/blockchain_example.rs`: transactions are appended to the open
+//! block's tree, a block is sealed once it holds `block_size` transactions,
+//! and every sealed block gets a [`BlockHeader`] whose hash commits to its
+//! height, its transaction root and the previous block's hash.
+//!
+//! [`TransactionLog::append`] returns a [`TxReceipt`] proving the
+//! transaction against its block's root right after the append. Once the
+//! block is sealed, [`TransactionLog::prove`] issues a receipt against the
+//! block's final root, which is the one published through
+//! [`TransactionLog::roots`].
+
+use crate::encoding::encode_u64;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::{verify_proof, ChronoProof};
+use crate::tree::{ChronoMerkleTree, TreeConfig};
+
+/// Position of a transaction in a [`TransactionLog`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TxId {
+    /// Height of the block holding the transaction
+    pub block: u64,
+    /// Index of the transaction within its block
+    pub index: usize,
+}
+
+impl core::fmt::Display for TxId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}:{}", self.block, self.index)
+    }
+}
+
+/// Proof that a transaction is in a block, returned by [`TransactionLog::append`] and [`TransactionLog::prove`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone)]
+pub struct TxReceipt<H> {
+    /// Where the transaction was appended
+    pub tx_id: TxId,
+    /// When the transaction was appended (Unix timestamp)
+    pub timestamp: u64,
+    /// Hash of the transaction bytes
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub tx_hash: H,
+    /// Root of the block's transactions the proof leads to
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub root: H,
+    /// Inclusion proof of the transaction against `root`
+    pub proof: ChronoProof<H>,
+}
+
+/// Header of a sealed block
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader<H> {
+    /// Position of the block in the chain, starting at 0
+    pub height: u64,
+    /// Timestamp of the block's first transaction
+    pub timestamp: u64,
+    /// Number of transactions in the block
+    pub tx_count: usize,
+    /// Root of the block's transaction tree
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub merkle_root: H,
+    /// Hash of the previous block, `None` for the first block
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash_option"))]
+    pub previous_hash: Option<H>,
+    /// Hash of this block, see [`block_hash`]
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub hash: H,
+}
+
+/// Hash of a block header
+///
+/// The root of a tree over the records `height` (8 bytes little-endian),
+/// `merkle_root` and, except for the first block, `previous_hash`, as
+/// computed by `Block::hash` in the blockchain example.
+pub fn block_hash<Hasher: HashFunction>(
+    hasher: &Hasher,
+    height: u64,
+    merkle_root: &Hasher::Output,
+    previous_hash: Option<&Hasher::Output>,
+) -> Hasher::Output {
+    let mut records = vec![crate::spec::leaf_hash(hasher, &encode_u64(height)), crate::spec::leaf_hash(hasher, merkle_root.as_ref())];
+    if let Some(previous_hash) = previous_hash {
+        records.push(crate::spec::leaf_hash(hasher, previous_hash.as_ref()));
+    }
+    crate::spec::root(hasher, &records).expect("at least two records")
+}
+
+/// Append-only log of transactions grouped into hash-chained blocks
+pub struct TransactionLog<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Clone + Default + Sync,
+{
+    hasher: Hasher,
+    config: TreeConfig,
+    block_size: usize,
+    /// Sealed blocks with their transaction trees, by height
+    blocks: Vec<(BlockHeader<H>, ChronoMerkleTree<H, Hasher>)>,
+    open: ChronoMerkleTree<H, Hasher>,
+}
+
+impl<H, Hasher> TransactionLog<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Clone + Default + Sync,
+{
+    /// Create an empty log sealing a block every `block_size` transactions
+    pub fn new(hasher: Hasher, block_size: usize) -> Self {
+        Self::with_config(hasher, TreeConfig::default(), block_size).expect("default config is valid")
+    }
+
+    /// Create an empty log whose block trees use `config`
+    pub fn with_config(hasher: Hasher, config: TreeConfig, block_size: usize) -> Result<Self> {
+        let open = ChronoMerkleTree::with_config(hasher.clone(), config.clone())?;
+        Ok(Self {
+            hasher,
+            config,
+            block_size: block_size.max(1),
+            blocks: Vec::new(),
+            open,
+        })
+    }
+
+    /// Append a transaction under the current time and return its receipt
+    pub fn append(&mut self, tx: &[u8]) -> Result<TxReceipt<H>> {
+        self.append_at(tx, crate::security::current_timestamp())
+    }
+
+    /// Append a transaction with an explicit timestamp and return its receipt
+    ///
+    /// The receipt proves the transaction against the open block's root
+    /// right after the append. The transaction that fills a block seals it,
+    /// so its receipt is already against the block's final root.
+    pub fn append_at(&mut self, tx: &[u8], timestamp: u64) -> Result<TxReceipt<H>> {
+        let inserted = self.open.insert_with_receipt(tx, timestamp)?;
+        let tx_id = TxId {
+            block: self.height(),
+            index: inserted.leaf_index,
+        };
+        let receipt = TxReceipt {
+            tx_id,
+            timestamp: inserted.timestamp,
+            tx_hash: self.open.get_leaf_hash(inserted.leaf_index)?,
+            proof: self.open.generate_proof(inserted.leaf_index)?,
+            root: inserted.new_root,
+        };
+        if self.open.leaf_count() >= self.block_size {
+            self.seal_block()?;
+        }
+        Ok(receipt)
+    }
+
+    /// Seal the open block now, even if it is not full
+    ///
+    /// Returns the new header, or `None` if the open block has no transactions.
+    pub fn seal_block(&mut self) -> Result<Option<&BlockHeader<H>>> {
+        let Some(merkle_root) = self.open.root() else {
+            return Ok(None);
+        };
+        let height = self.height();
+        let previous_hash = self.blocks.last().map(|(header, _)| header.hash.clone());
+        let header = BlockHeader {
+            height,
+            timestamp: self.open.get_leaf_timestamp(0)?,
+            tx_count: self.open.leaf_count(),
+            hash: block_hash(&self.hasher, height, &merkle_root, previous_hash.as_ref()),
+            merkle_root,
+            previous_hash,
+        };
+        let tree = core::mem::replace(&mut self.open, ChronoMerkleTree::with_config(self.hasher.clone(), self.config.clone())?);
+        self.blocks.push((header, tree));
+        Ok(self.blocks.last().map(|(header, _)| header))
+    }
+
+    /// Issue a receipt for a transaction against its block's current root
+    ///
+    /// For a sealed block this is the root in its [`BlockHeader`].
+    pub fn prove(&self, tx_id: TxId) -> Result<TxReceipt<H>> {
+        let tree = self.block_tree(tx_id.block).ok_or(ChronoMerkleError::IndexOutOfBounds {
+            index: tx_id.index,
+            leaf_count: 0,
+        })?;
+        Ok(TxReceipt {
+            tx_id,
+            timestamp: tree.get_leaf_timestamp(tx_id.index)?,
+            tx_hash: tree.get_leaf_hash(tx_id.index)?,
+            proof: tree.generate_proof(tx_id.index)?,
+            root: tree.root().ok_or(ChronoMerkleError::EmptyTree)?,
+        })
+    }
+
+    /// Check that `receipt` proves its transaction against `root`
+    ///
+    /// `root` is a block root the verifier trusts, e.g. one from
+    /// [`roots`](Self::roots); the log's own state is not consulted.
+    pub fn verify(&self, receipt: &TxReceipt<H>, root: &H) -> Result<bool> {
+        if receipt.root != *root || receipt.proof.leaf_index != receipt.tx_id.index || receipt.proof.timestamp != receipt.timestamp {
+            return Ok(false);
+        }
+        verify_proof(&receipt.proof, &receipt.tx_hash, root, &self.hasher)
+    }
+
+    /// Check that `tx` is the transaction `receipt` was issued for, and [`verify`](Self::verify) it
+    pub fn verify_transaction(&self, tx: &[u8], receipt: &TxReceipt<H>, root: &H) -> Result<bool> {
+        Ok(self.hasher.hash(tx) == receipt.tx_hash && self.verify(receipt, root)?)
+    }
+
+    /// Transaction roots of the sealed blocks, by height
+    pub fn roots(&self) -> impl Iterator<Item = &H> + '_ {
+        self.blocks.iter().map(|(header, _)| &header.merkle_root)
+    }
+
+    /// Headers of the sealed blocks, by height
+    pub fn headers(&self) -> impl Iterator<Item = &BlockHeader<H>> + '_ {
+        self.blocks.iter().map(|(header, _)| header)
+    }
+
+    /// Check that every header's hash and link to its predecessor are intact
+    pub fn verify_chain(&self) -> bool {
+        let mut previous_hash = None;
+        self.headers().all(|header| {
+            let linked = header.previous_hash.as_ref() == previous_hash
+                && header.hash == block_hash(&self.hasher, header.height, &header.merkle_root, previous_hash);
+            previous_hash = Some(&header.hash);
+            linked
+        })
+    }
+
+    /// Transaction tree of the block at `height`, sealed or open
+    pub fn block_tree(&self, height: u64) -> Option<&ChronoMerkleTree<H, Hasher>> {
+        match usize::try_from(height).ok()? {
+            index if index < self.blocks.len() => Some(&self.blocks[index].1),
+            index if index == self.blocks.len() => Some(&self.open),
+            _ => None,
+        }
+    }
+
+    /// Height of the open block, which is the number of sealed blocks
+    pub fn height(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+
+    /// Number of transactions in the open block
+    pub fn pending(&self) -> usize {
+        self.open.leaf_count()
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::Blake3Hasher;
+
+    #[test]
+    fn test_blocks_are_sealed_and_chained() {
+        let mut log = TransactionLog::new(Blake3Hasher, 3);
+        let receipts: Vec<_> = (0..7).map(|i| log.append_at(format!("tx{}", i).as_bytes(), 1000 + i).unwrap()).collect();
+        assert_eq!((log.height(), log.pending()), (2, 1));
+        assert_eq!(receipts[4].tx_id, TxId { block: 1, index: 1 });
+        assert_eq!(receipts[6].tx_id.to_string(), "2:0");
+
+        let headers: Vec<_> = log.headers().cloned().collect();
+        assert_eq!((headers[1].timestamp, headers[1].tx_count), (1003, 3));
+        assert_eq!(headers[1].previous_hash, Some(headers[0].hash));
+        assert!(log.verify_chain());
+        assert!(log.seal_block().unwrap().is_some());
+        assert!(log.seal_block().unwrap().is_none());
+        assert_eq!(log.roots().count(), 3);
+
+        log.blocks[1].0.merkle_root = headers[0].merkle_root;
+        assert!(!log.verify_chain());
+    }
+
+    #[test]
+    fn test_receipts_against_block_roots() {
+        let mut log = TransactionLog::new(Blake3Hasher, 4);
+        let receipts: Vec<_> = (0..6).map(|i| log.append_at(format!("tx{}", i).as_bytes(), 1000 + i).unwrap()).collect();
+        let roots: Vec<_> = log.roots().copied().collect();
+
+        // The receipt of the transaction that filled the block is final
+        assert!(log.verify(&receipts[3], &roots[0]).unwrap());
+        assert!(!log.verify(&receipts[1], &roots[0]).unwrap());
+        let proved = log.prove(receipts[1].tx_id).unwrap();
+        assert!(log.verify_transaction(b"tx1", &proved, &roots[0]).unwrap());
+        assert!(!log.verify_transaction(b"tx2", &proved, &roots[0]).unwrap());
+        assert!(!log.verify(&proved, &receipts[5].root).unwrap());
+
+        // Receipts from the open block verify against its current root
+        assert!(log.verify(&receipts[5], &log.block_tree(1).unwrap().root().unwrap()).unwrap());
+        let mut moved = proved.clone();
+        moved.tx_id.index = 2;
+        assert!(!log.verify(&moved, &roots[0]).unwrap());
+        assert!(log.prove(TxId { block: 5, index: 0 }).is_err());
+    }
+
+    #[test]
+    fn test_block_hash_matches_example() {
+        let mut log = TransactionLog::new(Blake3Hasher, 2);
+        for i in 0..4u64 {
+            log.append_at(&i.to_le_bytes(), 1_000_000 + i).unwrap();
+        }
+        let headers: Vec<_> = log.headers().collect();
+        for header in headers {
+            let mut tree: ChronoMerkleTree = ChronoMerkleTree::new(Blake3Hasher);
+            tree.insert(&encode_u64(header.height), header.timestamp).unwrap();
+            tree.insert(&header.merkle_root, header.timestamp + 1).unwrap();
+            if let Some(previous_hash) = header.previous_hash {
+                tree.insert(&previous_hash, header.timestamp + 2).unwrap();
+            }
+            assert_eq!(tree.root(), Some(header.hash));
+        }
+    }
+}
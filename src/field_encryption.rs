@@ -0,0 +1,304 @@
+//! Field-level encryption of leaf data in persisted states
+//!
+//! Whole-blob encryption hides everything from the storage backend, including
+//! the hashes and timestamps needed to serve proofs and audit roots. Encrypting
+//! only the leaf `data` fields of a [`TreeState`] with a per-tree data key
+//! leaves hashes, timestamps and the tree structure in the clear, so trees can
+//! be persisted to semi-trusted backends that may inspect them but must not
+//! read the records.
+//!
+//! Each ciphertext is bound to its leaf by associated data covering the leaf
+//! index, timestamp and hash, so ciphertexts cannot be swapped between leaves.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::node::NodeType;
+use crate::security::SecurityLogger;
+use crate::storage::{StorageBackend, TreeState};
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{format, string::ToString, vec::Vec};
+
+/// Authenticated encryption of leaf data
+pub trait DataCipher {
+    /// Identifier of the algorithm, recorded in the state
+    fn algorithm(&self) -> &str;
+
+    /// Encrypt `plaintext`, authenticating `aad` alongside it
+    fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>>;
+
+    /// Decrypt `ciphertext`, failing if it or `aad` was altered
+    fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Associated data binding a ciphertext to its leaf
+pub fn leaf_aad(index: usize, timestamp: u64, hash: &[u8]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(16 + hash.len());
+    aad.extend_from_slice(&crate::encoding::encode_index(index));
+    aad.extend_from_slice(&crate::encoding::encode_u64(timestamp));
+    aad.extend_from_slice(hash);
+    aad
+}
+
+/// 256-bit key encrypting the leaf data of one tree
+///
+/// `Debug` does not print the key.
+#[cfg(feature = "encrypted-storage")]
+#[derive(Clone, PartialEq, Eq)]
+pub struct DataKey(pub [u8; 32]);
+
+#[cfg(feature = "encrypted-storage")]
+impl DataKey {
+    /// Draw a key from the thread-local cryptographically secure generator
+    pub fn generate() -> Self {
+        Self(rand::random())
+    }
+}
+
+#[cfg(feature = "encrypted-storage")]
+impl core::fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("DataKey(..)")
+    }
+}
+
+/// AES-256-GCM with a random 96-bit nonce prefixed to each ciphertext
+#[cfg(feature = "encrypted-storage")]
+pub struct Aes256GcmCipher {
+    cipher: aes_gcm::Aes256Gcm,
+}
+
+#[cfg(feature = "encrypted-storage")]
+impl Aes256GcmCipher {
+    /// Nonce bytes leading each ciphertext
+    pub const NONCE_LEN: usize = 12;
+
+    /// Encrypt with `key`
+    pub fn new(key: &DataKey) -> Self {
+        use aes_gcm::KeyInit;
+        Self {
+            cipher: aes_gcm::Aes256Gcm::new(&key.0.into()),
+        }
+    }
+}
+
+#[cfg(feature = "encrypted-storage")]
+impl DataCipher for Aes256GcmCipher {
+    fn algorithm(&self) -> &str {
+        "aes-256-gcm"
+    }
+
+    fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        use aes_gcm::aead::{Aead, Payload};
+        let nonce: [u8; Self::NONCE_LEN] = rand::random();
+        let sealed = self
+            .cipher
+            .encrypt(&nonce.into(), Payload { msg: plaintext, aad })
+            .map_err(|_| ChronoMerkleError::StorageError { reason: "Leaf data encryption failed".to_string() })?;
+        let mut out = Vec::with_capacity(Self::NONCE_LEN + sealed.len());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        use aes_gcm::aead::{Aead, Payload};
+        let failed = || ChronoMerkleError::StorageError { reason: "Leaf data decryption failed".to_string() };
+        if ciphertext.len() < Self::NONCE_LEN {
+            return Err(failed());
+        }
+        let (nonce, sealed) = ciphertext.split_at(Self::NONCE_LEN);
+        let nonce: [u8; Self::NONCE_LEN] = nonce.try_into().map_err(|_| failed())?;
+        self.cipher.decrypt(&nonce.into(), Payload { msg: sealed, aad }).map_err(|_| failed())
+    }
+}
+
+impl<H> TreeState<H>
+where
+    H: AsRef<[u8]> + crate::traits::MaybeSerde,
+{
+    /// Encrypt the data of every leaf with `cipher`
+    pub fn encrypt_leaf_data(&mut self, cipher: &dyn DataCipher) -> Result<()> {
+        if let Some(algorithm) = &self.data_cipher {
+            return Err(ChronoMerkleError::StorageError {
+                reason: format!("Leaf data is already encrypted with {}", algorithm),
+            });
+        }
+        self.map_leaf_data(|index, timestamp, hash, data| cipher.encrypt(data, &leaf_aad(index, timestamp, hash)))?;
+        self.data_cipher = Some(cipher.algorithm().to_string());
+        Ok(())
+    }
+
+    /// Decrypt the data of every leaf with `cipher`
+    ///
+    /// Fails without changes if any leaf does not decrypt, e.g. under a wrong
+    /// key or after its ciphertext was moved to another leaf.
+    pub fn decrypt_leaf_data(&mut self, cipher: &dyn DataCipher) -> Result<()> {
+        match self.data_cipher.as_deref() {
+            None => return Ok(()),
+            Some(algorithm) if algorithm != cipher.algorithm() => {
+                return Err(ChronoMerkleError::StorageError {
+                    reason: format!("Leaf data is encrypted with {}, not {}", algorithm, cipher.algorithm()),
+                });
+            }
+            Some(_) => {}
+        }
+        self.map_leaf_data(|index, timestamp, hash, data| cipher.decrypt(data, &leaf_aad(index, timestamp, hash)))?;
+        self.data_cipher = None;
+        Ok(())
+    }
+
+    /// Replace the data of every leaf holding data, all or nothing
+    fn map_leaf_data(&mut self, f: impl Fn(usize, u64, &[u8], &[u8]) -> Result<Vec<u8>>) -> Result<()> {
+        let leaf_count = self.leaf_count.min(self.nodes.len());
+        let mut replaced = Vec::with_capacity(leaf_count);
+        for (index, node) in self.nodes[..leaf_count].iter().enumerate() {
+            replaced.push(match &node.node_type {
+                NodeType::Leaf { hash, timestamp, data: Some(data) } => Some(f(index, *timestamp, hash.as_ref(), data)?),
+                _ => None,
+            });
+        }
+        for (node, new_data) in self.nodes[..leaf_count].iter_mut().zip(replaced) {
+            if let (NodeType::Leaf { data, .. }, Some(new_data)) = (&mut node.node_type, new_data) {
+                *data = Some(new_data);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Save the tree state with leaf data encrypted by `cipher`
+    pub fn save_state_encrypted(
+        &self,
+        storage: &mut (impl StorageBackend + ?Sized),
+        key: &str,
+        cipher: &dyn DataCipher,
+    ) -> Result<()> {
+        let mut state = self.extract_state();
+        state.encrypt_leaf_data(cipher)?;
+        state.save_to(storage, key)
+    }
+
+    /// Load a tree state saved with [`save_state_encrypted`](Self::save_state_encrypted)
+    pub fn load_state_encrypted(
+        storage: &(impl StorageBackend + ?Sized),
+        key: &str,
+        cipher: &dyn DataCipher,
+        hasher: Hasher,
+        logger: Logger,
+    ) -> Result<Self> {
+        let mut state = TreeState::load_from(storage, key)?;
+        state.decrypt_leaf_data(cipher)?;
+        Ok(Self::from_state(state, hasher, logger))
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+    use crate::storage::MemoryStorage;
+
+    /// XOR with a keystream derived from the key and AAD, tagged with a keyed hash
+    struct TestCipher([u8; 32]);
+
+    impl TestCipher {
+        fn tag(&self, body: &[u8], aad: &[u8]) -> [u8; 32] {
+            Blake3Hasher.hash_multiple(&[&self.0, aad, body])
+        }
+    }
+
+    impl DataCipher for TestCipher {
+        fn algorithm(&self) -> &str {
+            "test-xor"
+        }
+
+        fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+            let pad = Blake3Hasher.hash_multiple(&[&self.0, aad]);
+            let mut out: Vec<u8> = plaintext.iter().zip(pad.iter().cycle()).map(|(b, p)| b ^ p).collect();
+            out.extend_from_slice(&self.tag(&out, aad));
+            Ok(out)
+        }
+
+        fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+            let (body, tag) = ciphertext.split_at(ciphertext.len().saturating_sub(32));
+            if tag != self.tag(body, aad) {
+                return Err(ChronoMerkleError::StorageError { reason: "bad tag".to_string() });
+            }
+            let pad = Blake3Hasher.hash_multiple(&[&self.0, aad]);
+            Ok(body.iter().zip(pad.iter().cycle()).map(|(b, p)| b ^ p).collect())
+        }
+    }
+
+    #[test]
+    fn test_only_leaf_data_is_encrypted() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..5u64 {
+            tree.insert(format!("secret record {}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        let mut storage = MemoryStorage::new();
+        tree.save_state_encrypted(&mut storage, "tree", &TestCipher([7; 32])).unwrap();
+
+        let raw = String::from_utf8(storage.load("tree").unwrap().unwrap()).unwrap();
+        assert!(!raw.contains(&format!("{:?}", b"secret record 0".to_vec()).replace(' ', "")));
+        let state: TreeState<[u8; 32]> = TreeState::load_from(&storage, "tree").unwrap();
+        assert_eq!(state.data_cipher.as_deref(), Some("test-xor"));
+        assert_eq!(state.nodes[2].hash(), tree.get_leaf_hash(2).unwrap());
+        assert!(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::load_state(&storage, "tree", Blake3Hasher, Default::default()).is_err());
+        assert!(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::load_state_encrypted(
+            &storage,
+            "tree",
+            &TestCipher([8; 32]),
+            Blake3Hasher,
+            Default::default()
+        )
+        .is_err());
+
+        let loaded = ChronoMerkleTree::<[u8; 32], Blake3Hasher>::load_state_encrypted(
+            &storage,
+            "tree",
+            &TestCipher([7; 32]),
+            Blake3Hasher,
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(loaded.root(), tree.root());
+        assert_eq!(loaded.get_leaf_data(3).unwrap().data(), Some(b"secret record 3".as_slice()));
+    }
+
+    #[test]
+    fn test_ciphertexts_are_bound_to_leaves() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert(b"first", 1000).unwrap();
+        tree.insert(b"second", 1001).unwrap();
+        let cipher = TestCipher([1; 32]);
+        let mut state = tree.extract_state();
+        state.encrypt_leaf_data(&cipher).unwrap();
+        assert!(state.encrypt_leaf_data(&cipher).is_err());
+
+        let mut swapped = state.clone();
+        let data = |state: &TreeState<[u8; 32]>, i: usize| match &state.nodes[i].node_type {
+            NodeType::Leaf { data, .. } => data.clone(),
+            _ => unreachable!(),
+        };
+        let (first, second) = (data(&state, 0), data(&state, 1));
+        for (i, value) in [second, first].into_iter().enumerate() {
+            if let NodeType::Leaf { data, .. } = &mut swapped.nodes[i].node_type {
+                *data = value;
+            }
+        }
+        assert!(swapped.decrypt_leaf_data(&cipher).is_err());
+        // A failed decryption leaves the state untouched
+        assert_eq!(swapped.data_cipher.as_deref(), Some("test-xor"));
+
+        state.decrypt_leaf_data(&cipher).unwrap();
+        assert_eq!(data(&state, 1), Some(b"second".to_vec()));
+    }
+}
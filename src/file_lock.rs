@@ -0,0 +1,118 @@
+//! Advisory directory locks for [`FileStorage`](crate::storage::FileStorage)
+//!
+//! Locks are operating system file locks (`flock` on Unix, `LockFileEx` on
+//! Windows) held on a `.lock` file inside the storage directory: exclusive for
+//! a writer, shared for readers. The operating system releases a lock when its
+//! file is closed, including when the owning process exits or crashes, so a
+//! lock never outlives its holder and is never taken over while the holder
+//! runs. The lock file itself is left in place; its presence means nothing.
+
+use crate::error::{ChronoMerkleError, Result};
+
+use fs4::{FileExt, TryLockError};
+use std::fs::{self, File, OpenOptions};
+use std::path::Path;
+
+const LOCK_FILE: &str = ".lock";
+
+/// Kind of lock held on a storage directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// Single writer; no other exclusive or shared locks may exist
+    Exclusive,
+    /// Read-only access shared with other readers
+    Shared,
+}
+
+/// A held directory lock, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    // Closing the file releases the lock
+    _file: File,
+    mode: LockMode,
+}
+
+impl FileLock {
+    /// Acquire a lock on `dir` without waiting, failing if a conflicting lock is held
+    pub fn acquire(dir: &Path, mode: LockMode) -> Result<Self> {
+        fs::create_dir_all(dir).map_err(|e| ChronoMerkleError::StorageError {
+            reason: format!("Failed to create directory: {}", e),
+        })?;
+
+        let path = dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| ChronoMerkleError::StorageError {
+                reason: format!("Failed to open lock file {}: {}", path.display(), e),
+            })?;
+        // Called through the trait: `File` has inherent methods of the same name from Rust 1.89
+        let locked = match mode {
+            LockMode::Exclusive => FileExt::try_lock(&file),
+            LockMode::Shared => FileExt::try_lock_shared(&file),
+        };
+        match locked {
+            Ok(()) => Ok(Self { _file: file, mode }),
+            Err(TryLockError::WouldBlock) => Err(ChronoMerkleError::StorageError {
+                reason: format!("Storage directory {} is locked by another storage handle", dir.display()),
+            }),
+            Err(TryLockError::Error(e)) => Err(ChronoMerkleError::StorageError {
+                reason: format!("Failed to lock {}: {}", path.display(), e),
+            }),
+        }
+    }
+
+    /// Mode of this lock
+    pub fn mode(&self) -> LockMode {
+        self.mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exclusive_excludes_everyone() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = FileLock::acquire(dir.path(), LockMode::Exclusive).unwrap();
+        assert!(FileLock::acquire(dir.path(), LockMode::Exclusive).is_err());
+        assert!(FileLock::acquire(dir.path(), LockMode::Shared).is_err());
+        drop(lock);
+        assert!(FileLock::acquire(dir.path(), LockMode::Exclusive).is_ok());
+    }
+
+    #[test]
+    fn test_shared_locks_coexist() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = FileLock::acquire(dir.path(), LockMode::Shared).unwrap();
+        let b = FileLock::acquire(dir.path(), LockMode::Shared).unwrap();
+        assert!(FileLock::acquire(dir.path(), LockMode::Exclusive).is_err());
+        drop((a, b));
+        assert!(FileLock::acquire(dir.path(), LockMode::Exclusive).is_ok());
+    }
+
+    #[test]
+    fn test_leftover_lock_file_is_not_a_lock() {
+        // A lock file left by a crashed process holds no OS lock
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(LOCK_FILE), "4294967295\n").unwrap();
+        let lock = FileLock::acquire(dir.path(), LockMode::Exclusive).unwrap();
+        assert_eq!(lock.mode(), LockMode::Exclusive);
+        assert!(dir.path().join(LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn test_idle_lock_is_kept() {
+        // However long the holder stays idle, the lock is not taken over
+        let dir = tempfile::tempdir().unwrap();
+        let lock = FileLock::acquire(dir.path(), LockMode::Exclusive).unwrap();
+        let lock_file = File::options().write(true).open(dir.path().join(LOCK_FILE)).unwrap();
+        lock_file.set_modified(std::time::SystemTime::UNIX_EPOCH).unwrap();
+        assert!(FileLock::acquire(dir.path(), LockMode::Exclusive).is_err());
+        drop(lock);
+    }
+}
@@ -0,0 +1,169 @@
+//! Clock skew policy for timestamps checked at verification time
+//!
+//! A proof that a leaf is in a tree says nothing about whether the leaf's
+//! timestamp is plausible to the verifier. A [`SkewPolicy`] compares the
+//! timestamp carried by a proof with the verifier's clock, and optionally
+//! with the time the root was anchored, e.g. a notary anchor's `anchored_at`
+//! or a [`RootRecord`](crate::RootRecord) timestamp, and classifies it as a
+//! [`Freshness`].
+//!
+//! Timestamps are Unix seconds, like the rest of the crate. They are not part
+//! of any hash (see [`spec::TIMESTAMPS_HASHED`](crate::spec::TIMESTAMPS_HASHED)),
+//! so the policy judges the timestamp the prover claims; requiring an anchor
+//! ties that claim to a root whose time the verifier trusts.
+
+use crate::error::Result;
+use crate::hash::HashFunction;
+use crate::proof::{verify_proof, ChronoProof};
+
+/// Limits on how far a committed timestamp may lie from the verifier's clock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SkewPolicy {
+    /// Seconds a timestamp may lie ahead of the reference time
+    pub max_skew: u64,
+    /// Seconds a timestamp may lie behind local time, `None` for no limit
+    pub max_age: Option<u64>,
+    /// Whether a timestamp must be backed by the anchor time of its root
+    pub require_anchor: bool,
+}
+
+impl SkewPolicy {
+    /// Allow `max_skew` seconds of clock difference, accepting timestamps of any age
+    pub fn new(max_skew: u64) -> Self {
+        Self { max_skew, ..Self::default() }
+    }
+
+    /// Also classify timestamps older than `max_age` seconds as stale
+    pub fn with_max_age(mut self, max_age: u64) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Require the anchor time of the root to be known
+    pub fn requiring_anchor(mut self) -> Self {
+        self.require_anchor = true;
+        self
+    }
+
+    /// Classify `timestamp` against local time `now` and the root's `anchored_at`
+    ///
+    /// The reference for future timestamps is the earlier of `now` and the
+    /// anchor time, since a leaf cannot be included in a root anchored before
+    /// it was committed. Ages are measured against `now`.
+    pub fn check(&self, timestamp: u64, now: u64, anchored_at: Option<u64>) -> Freshness {
+        if self.require_anchor && anchored_at.is_none() {
+            return Freshness::Unanchored;
+        }
+        let reference = anchored_at.map_or(now, |anchored_at| anchored_at.min(now));
+        let skew = timestamp.saturating_sub(reference);
+        if skew > self.max_skew {
+            return Freshness::Future { skew };
+        }
+        let age = now.saturating_sub(timestamp);
+        match self.max_age {
+            Some(max_age) if age > max_age => Freshness::Stale { age },
+            _ => Freshness::Fresh,
+        }
+    }
+
+    /// Classify `timestamp` against the system clock
+    #[cfg(feature = "std")]
+    pub fn check_now(&self, timestamp: u64, anchored_at: Option<u64>) -> Freshness {
+        self.check(timestamp, crate::security::current_timestamp(), anchored_at)
+    }
+}
+
+/// How a committed timestamp relates to the verifier's clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// Within the policy's skew and age limits
+    Fresh,
+    /// Older than the policy's maximum age
+    Stale {
+        /// Seconds between the timestamp and local time
+        age: u64,
+    },
+    /// Further ahead of local or anchor time than the allowed skew
+    Future {
+        /// Seconds the timestamp lies ahead of the reference time
+        skew: u64,
+    },
+    /// The policy requires an anchor time and none was given
+    Unanchored,
+}
+
+impl Freshness {
+    /// Whether the timestamp satisfies the policy
+    pub fn is_fresh(&self) -> bool {
+        matches!(self, Freshness::Fresh)
+    }
+}
+
+/// Verify a proof against a root and classify its timestamp under `policy`
+///
+/// Returns `None` if the proof does not lead to `root_hash`, and otherwise
+/// the [`Freshness`] of `proof.timestamp` at local time `now` for a root
+/// anchored at `anchored_at`. Errors are those of [`verify_proof`].
+pub fn verify_proof_freshness<H, Hasher>(
+    proof: &ChronoProof<H>,
+    leaf_hash: &H,
+    root_hash: &H,
+    hasher: &Hasher,
+    policy: &SkewPolicy,
+    now: u64,
+    anchored_at: Option<u64>,
+) -> Result<Option<Freshness>>
+where
+    H: AsRef<[u8]> + Clone + Eq,
+    Hasher: HashFunction<Output = H>,
+{
+    if !verify_proof(proof, leaf_hash, root_hash, hasher)? {
+        return Ok(None);
+    }
+    Ok(Some(policy.check(proof.timestamp, now, anchored_at)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_classification() {
+        let policy = SkewPolicy::new(30).with_max_age(3600);
+        assert_eq!(policy.check(1_000, 1_000, None), Freshness::Fresh);
+        assert_eq!(policy.check(1_030, 1_000, None), Freshness::Fresh);
+        assert_eq!(policy.check(1_031, 1_000, None), Freshness::Future { skew: 31 });
+        assert_eq!(policy.check(1_000, 4_601, None), Freshness::Stale { age: 3_601 });
+        assert!(SkewPolicy::new(0).check(0, u64::MAX, None).is_fresh());
+    }
+
+    #[test]
+    fn test_anchor_bounds_timestamps() {
+        let policy = SkewPolicy::new(10).requiring_anchor();
+        assert_eq!(policy.check(1_000, 2_000, None), Freshness::Unanchored);
+        assert_eq!(policy.check(1_000, 2_000, Some(1_005)), Freshness::Fresh);
+        // Committed after the root was anchored
+        assert_eq!(policy.check(1_100, 2_000, Some(1_005)), Freshness::Future { skew: 95 });
+        // An anchor time ahead of local time does not widen the window
+        assert_eq!(policy.check(2_100, 2_000, Some(3_000)), Freshness::Future { skew: 100 });
+    }
+
+    #[cfg(feature = "blake3-hash")]
+    #[test]
+    fn test_verify_proof_freshness() {
+        use crate::{Blake3Hasher, ChronoMerkleTree};
+
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::new(Blake3Hasher);
+        tree.insert(b"data1", 1_000).unwrap();
+        tree.insert(b"data2", 1_500).unwrap();
+        let (proof, leaf, root) = (tree.generate_proof(1).unwrap(), tree.get_leaf_hash(1).unwrap(), tree.root().unwrap());
+        let policy = SkewPolicy::new(60).with_max_age(600);
+
+        let freshness = verify_proof_freshness(&proof, &leaf, &root, &Blake3Hasher, &policy, 1_550, None).unwrap();
+        assert_eq!(freshness, Some(Freshness::Fresh));
+        let freshness = verify_proof_freshness(&proof, &leaf, &root, &Blake3Hasher, &policy, 2_500, None).unwrap();
+        assert_eq!(freshness, Some(Freshness::Stale { age: 1_000 }));
+        let other = tree.get_leaf_hash(0).unwrap();
+        assert_eq!(verify_proof_freshness(&proof, &other, &root, &Blake3Hasher, &policy, 1_550, None).unwrap(), None);
+    }
+}
@@ -5,6 +5,9 @@ use crate::error::Result;
 #[cfg(feature = "no-std")]
 use alloc::vec::Vec;
 
+/// Size of the stack buffer used by [`HashFunction::hash_pair`]: two 64-byte outputs
+const PAIR_BUFFER_LEN: usize = 128;
+
 /// Trait for hash functions used in ChronoMerkle trees
 pub trait HashFunction {
     /// The output type of the hash function
@@ -13,11 +16,28 @@ pub trait HashFunction {
     /// Hash a single piece of data
     fn hash(&self, data: &[u8]) -> Self::Output;
 
+    /// Stable identifier of the algorithm, e.g. `"blake3"`
+    ///
+    /// Spec digests and snapshot packages commit to it, so it must not change
+    /// between releases of the implementation.
+    fn name(&self) -> &str;
+
     /// Hash a pair of hashes (for internal nodes)
+    ///
+    /// Pairs of outputs up to 64 bytes are concatenated in a stack buffer, so
+    /// hashing them does not allocate.
     fn hash_pair(&self, left: &Self::Output, right: &Self::Output) -> Self::Output {
-        let mut combined = Vec::with_capacity(left.as_ref().len() + right.as_ref().len());
-        combined.extend_from_slice(left.as_ref());
-        combined.extend_from_slice(right.as_ref());
+        let (left, right) = (left.as_ref(), right.as_ref());
+        let len = left.len() + right.len();
+        if len <= PAIR_BUFFER_LEN {
+            let mut buffer = [0u8; PAIR_BUFFER_LEN];
+            buffer[..left.len()].copy_from_slice(left);
+            buffer[left.len()..len].copy_from_slice(right);
+            return self.hash(&buffer[..len]);
+        }
+        let mut combined = Vec::with_capacity(len);
+        combined.extend_from_slice(left);
+        combined.extend_from_slice(right);
         self.hash(&combined)
     }
 
@@ -40,12 +60,24 @@ pub struct DefaultHasher;
 impl HashFunction for DefaultHasher {
     type Output = [u8; 32];
 
+    fn name(&self) -> &str {
+        "sha256"
+    }
+
     fn hash(&self, data: &[u8]) -> Self::Output {
         use sha2::{Digest, Sha256};
         let mut hasher = Sha256::new();
         hasher.update(data);
         hasher.finalize().into()
     }
+
+    fn hash_pair(&self, left: &Self::Output, right: &Self::Output) -> Self::Output {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
 }
 
 /// Blake3 hasher (recommended for performance)
@@ -57,9 +89,157 @@ pub struct Blake3Hasher;
 impl HashFunction for Blake3Hasher {
     type Output = [u8; 32];
 
+    fn name(&self) -> &str {
+        "blake3"
+    }
+
     fn hash(&self, data: &[u8]) -> Self::Output {
         *blake3::hash(data).as_bytes()
     }
+
+    fn hash_pair(&self, left: &Self::Output, right: &Self::Output) -> Self::Output {
+        *blake3::Hasher::new().update(left).update(right).finalize().as_bytes()
+    }
+}
+
+/// Hash function producing `N`-byte digests
+///
+/// Wrapping an implementation in [`Fixed`] makes it a [`HashFunction`] whose
+/// [`Digest<N>`] output is serializable for every `N`, including sizes above
+/// 32 bytes where plain arrays lack serde support.
+pub trait FixedHasher<const N: usize> {
+    /// Hash `data` to `N` bytes
+    fn digest(&self, data: &[u8]) -> [u8; N];
+
+    /// Stable identifier of the algorithm, used as the [`Fixed`] adapter's [`HashFunction::name`]
+    fn name(&self) -> &str;
+}
+
+/// [`HashFunction`] adapter for a [`FixedHasher`] of size `N`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fixed<T, const N: usize>(pub T);
+
+impl<T: FixedHasher<N>, const N: usize> HashFunction for Fixed<T, N> {
+    type Output = Digest<N>;
+
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn hash(&self, data: &[u8]) -> Self::Output {
+        Digest(self.0.digest(data))
+    }
+}
+
+/// Blake3 extended output; `N = 32` matches [`Blake3Hasher`]'s regular output
+#[cfg(feature = "blake3-hash")]
+impl<const N: usize> FixedHasher<N> for Blake3Hasher {
+    fn digest(&self, data: &[u8]) -> [u8; N] {
+        let mut out = [0u8; N];
+        blake3::Hasher::new().update(data).finalize_xof().fill(&mut out);
+        out
+    }
+
+    fn name(&self) -> &str {
+        "blake3"
+    }
+}
+
+#[cfg(feature = "sha2-hash")]
+impl FixedHasher<32> for DefaultHasher {
+    fn digest(&self, data: &[u8]) -> [u8; 32] {
+        self.hash(data)
+    }
+
+    fn name(&self) -> &str {
+        "sha256"
+    }
+}
+
+/// `N`-byte hash output
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Digest<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Default for Digest<N> {
+    fn default() -> Self {
+        Digest([0u8; N])
+    }
+}
+
+impl<const N: usize> core::fmt::Debug for Digest<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Digest(")?;
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for Digest<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for Digest<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Digest(bytes)
+    }
+}
+
+impl<const N: usize> From<Digest<N>> for [u8; N] {
+    fn from(digest: Digest<N>) -> Self {
+        digest.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const N: usize> serde::Serialize for Digest<N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        crate::serde_impl::hash::serialize(&self.0, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> serde::Deserialize<'de> for Digest<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        struct DigestVisitor<const N: usize>;
+
+        impl<'de, const N: usize> serde::de::Visitor<'de> for DigestVisitor<N> {
+            type Value = Digest<N>;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "{} bytes", N)
+            }
+
+            fn visit_str<E: serde::de::Error>(self, text: &str) -> core::result::Result<Self::Value, E> {
+                let bytes = crate::codec::from_hex(text).ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(text), &self))?;
+                self.visit_bytes(&bytes)
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> core::result::Result<Self::Value, E> {
+                bytes.try_into().map(Digest).map_err(|_| E::invalid_length(bytes.len(), &self))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error> {
+                let mut out = [0u8; N];
+                for (i, byte) in out.iter_mut().enumerate() {
+                    *byte = seq.next_element()?.ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
+                }
+                if seq.next_element::<u8>()?.is_some() {
+                    return Err(serde::de::Error::invalid_length(N + 1, &self));
+                }
+                Ok(Digest(out))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(DigestVisitor)
+        } else {
+            deserializer.deserialize_bytes(DigestVisitor)
+        }
+    }
 }
 
 /// Fallback hasher when no hash feature is enabled
@@ -73,6 +253,10 @@ pub struct DefaultHasher;
 impl HashFunction for DefaultHasher {
     type Output = [u8; 32];
 
+    fn name(&self) -> &str {
+        "insecure-fallback"
+    }
+
     fn hash(&self, data: &[u8]) -> Self::Output {
         // SECURITY: Panic to prevent accidental use of insecure hasher
         panic!(
@@ -89,6 +273,7 @@ pub fn hash_to_bytes<H: HashFunction>(hasher: &H, data: &[u8]) -> Result<Vec<u8>
 }
 
 #[cfg(test)]
+#[allow(clippy::default_constructed_unit_structs)]
 mod tests {
     use super::*;
 
@@ -106,7 +291,7 @@ mod tests {
     #[cfg(feature = "sha2-hash")]
     #[test]
     fn test_sha2_hasher() {
-        let hasher = DefaultHasher::default();
+        let hasher = DefaultHasher;
         let data = b"test data";
         let hash1 = hasher.hash(data);
         let hash2 = hasher.hash(data);
@@ -114,6 +299,23 @@ mod tests {
         assert_ne!(hasher.hash(b"different"), hash1);
     }
 
+    #[cfg(feature = "blake3-hash")]
+    #[test]
+    fn test_fixed_size_digests() {
+        let hasher = Fixed::<Blake3Hasher, 32>(Blake3Hasher);
+        assert_eq!(hasher.hash(b"data").0, Blake3Hasher.hash(b"data"));
+        let long = Fixed::<Blake3Hasher, 64>(Blake3Hasher).hash(b"data");
+        assert_eq!(long.0[..32], Blake3Hasher.hash(b"data"));
+        assert_eq!(Fixed::<Blake3Hasher, 20>(Blake3Hasher).hash_pair(&Digest([1; 20]), &Digest([2; 20])).as_ref().len(), 20);
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&long).unwrap();
+            assert_eq!(serde_json::from_str::<Digest<64>>(&json).unwrap(), long);
+            assert!(serde_json::from_str::<Digest<48>>(&json).is_err());
+        }
+    }
+
     #[test]
     fn test_hash_pair() {
         #[cfg(feature = "blake3-hash")]
@@ -132,5 +334,19 @@ mod tests {
         // Should be different from individual hashes
         assert_ne!(pair_hash, left);
         assert_ne!(pair_hash, right);
+
+        // Should equal the hash of the concatenation
+        assert_eq!(pair_hash, hasher.hash(&[left, right].concat()));
+    }
+
+    #[cfg(feature = "blake3-hash")]
+    #[test]
+    fn test_hash_pair_beyond_stack_buffer() {
+        let hasher = Fixed::<Blake3Hasher, 80>(Blake3Hasher);
+        let (left, right) = (Digest([1; 80]), Digest([2; 80]));
+        assert_eq!(hasher.hash_pair(&left, &right), hasher.hash(&[left.0, right.0].concat()));
+        let hasher = Fixed::<Blake3Hasher, 64>(Blake3Hasher);
+        let (left, right) = (Digest([1; 64]), Digest([2; 64]));
+        assert_eq!(hasher.hash_pair(&left, &right), hasher.hash(&[left.0, right.0].concat()));
     }
 }
@@ -0,0 +1,114 @@
+//! Secondary indexes persisted apart from the tree state
+//!
+//! [`save_indexes`](ChronoMerkleTree::save_indexes) writes the timestamp index
+//! under `"{key}.index.timestamps"` and the delta chain index under
+//! `"{key}.index.deltas"` in the binary form of [`SparseIndex::to_bytes`].
+//! [`save_state_split`](ChronoMerkleTree::save_state_split) stores the state
+//! without its indexes, so reopening a large tree with
+//! [`load_state_split`](ChronoMerkleTree::load_state_split) decodes the indexes
+//! separately instead of inside the state blob. Missing or stale index records
+//! are rebuilt from the leaves and deltas.
+
+use crate::error::Result;
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::sparse_index::SparseIndex;
+use crate::storage::StorageBackend;
+use crate::tree::ChronoMerkleTree;
+
+fn timestamps_key(key: &str) -> String {
+    format!("{}.index.timestamps", key)
+}
+
+fn deltas_key(key: &str) -> String {
+    format!("{}.index.deltas", key)
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Save the timestamp and delta chain indexes under their own keys
+    pub fn save_indexes(&self, storage: &mut (impl StorageBackend + ?Sized), key: &str) -> Result<()> {
+        storage.save(&timestamps_key(key), &self.sparse_index.to_bytes())?;
+        storage.save(&deltas_key(key), &self.delta_chains.to_bytes())
+    }
+
+    /// Load indexes saved with [`save_indexes`](Self::save_indexes)
+    ///
+    /// If either record is missing or points past the current leaves or
+    /// deltas, both indexes are rebuilt with the configured sparsity instead.
+    /// Returns whether the stored indexes were used.
+    pub fn load_indexes(&mut self, storage: &(impl StorageBackend + ?Sized), key: &str) -> Result<bool> {
+        let timestamps = storage.load(&timestamps_key(key))?;
+        let deltas = storage.load(&deltas_key(key))?;
+        if let (Some(timestamps), Some(deltas)) = (timestamps, deltas) {
+            let sparse_index = SparseIndex::from_bytes(&timestamps)?;
+            let delta_chains = SparseIndex::from_bytes(&deltas)?;
+            let fits = |index: &SparseIndex, len: usize| index.iter().all(|(_, &position)| position < len);
+            if fits(&sparse_index, self.leaf_count) && fits(&delta_chains, self.stored_deltas.len()) {
+                self.sparse_index = sparse_index;
+                self.delta_chains = delta_chains;
+                return Ok(true);
+            }
+        }
+
+        self.reindex(self.config.sparse_index_sparsity)?;
+        Ok(false)
+    }
+
+    /// Save the tree state without its indexes, and the indexes under their own keys
+    pub fn save_state_split(&self, storage: &mut (impl StorageBackend + ?Sized), key: &str) -> Result<()> {
+        self.snapshot().without_indexes().save(storage, key)?;
+        self.save_indexes(storage, key)
+    }
+
+    /// Load a tree saved with [`save_state_split`](Self::save_state_split)
+    pub fn load_state_split(
+        storage: &(impl StorageBackend + ?Sized),
+        key: &str,
+        hasher: Hasher,
+        logger: Logger,
+    ) -> Result<Self> {
+        let mut tree = Self::load_state(storage, key, hasher, logger)?;
+        tree.load_indexes(storage, key)?;
+        Ok(tree)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+    use crate::security::NoOpLogger;
+    use crate::storage::MemoryStorage;
+
+    #[test]
+    fn test_split_state_roundtrip() {
+        let config = crate::config::TreeConfig { sparse_index_sparsity: 2, ..Default::default() };
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+        for i in 0..9u64 {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        let mut storage = MemoryStorage::new();
+        tree.save_state_split(&mut storage, "tree").unwrap();
+
+        let mut loaded: ChronoMerkleTree =
+            ChronoMerkleTree::load_state_split(&storage, "tree", Blake3Hasher, NoOpLogger).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+        assert_eq!(loaded.sparse_index.timestamps(), tree.sparse_index.timestamps());
+        assert_eq!(loaded.delta_chains.timestamps(), tree.delta_chains.timestamps());
+        loaded.rollback_to_timestamp(1004).unwrap();
+        assert_eq!(loaded.leaf_count(), 5);
+
+        // Missing index records are rebuilt
+        storage.delete("tree.index.deltas").unwrap();
+        let mut reopened: ChronoMerkleTree = ChronoMerkleTree::load_state(&storage, "tree", Blake3Hasher, NoOpLogger).unwrap();
+        assert!(reopened.sparse_index.is_empty());
+        assert!(!reopened.load_indexes(&storage, "tree").unwrap());
+        assert_eq!(reopened.sparse_index.timestamps(), tree.sparse_index.timestamps());
+        assert_eq!(reopened.find_range(1002, 1003), vec![2, 3]);
+    }
+}
@@ -0,0 +1,175 @@
+//! Middleware around inserts
+//!
+//! Interceptors added with [`ChronoMerkleTree::add_interceptor`] run in the
+//! order they were added around every `insert`, `insert_with_receipt` and
+//! `insert_batch`, and therefore around loaders built on them. Each
+//! [`InsertInterceptor::before_insert`] sees the record as left by the previous
+//! one and may rewrite its data or timestamp, e.g. to scrub PII or add
+//! metadata, or refuse it with
+//! [`ChronoMerkleError::InsertRejected`](crate::ChronoMerkleError::InsertRejected). A refused
+//! record is not inserted; in a batch, nothing is. Once the leaves are in the
+//! tree, [`InsertInterceptor::after_insert`] is called with a receipt for each.
+//!
+//! Prehashed inserts carry no data and bypass interceptors.
+
+use crate::error::Result;
+use crate::hash::HashFunction;
+use crate::operations::InsertReceipt;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{borrow::Cow, sync::Arc, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::{borrow::Cow, sync::Arc};
+
+/// A record on its way into the tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingInsert<'a> {
+    /// Data to be hashed into the leaf; borrowed until an interceptor changes it
+    pub data: Cow<'a, [u8]>,
+    /// Timestamp of the leaf
+    pub timestamp: u64,
+}
+
+/// Hook run around inserts
+pub trait InsertInterceptor<H>: Send + Sync {
+    /// Inspect or rewrite a record before it is validated and inserted
+    ///
+    /// Returning an error refuses the insert and is passed to the caller.
+    fn before_insert(&self, record: &mut PendingInsert<'_>) -> Result<()> {
+        let _ = record;
+        Ok(())
+    }
+
+    /// Observe an inserted leaf
+    ///
+    /// For a batch, `old_root` and `new_root` are the roots around the whole batch.
+    fn after_insert(&self, receipt: &InsertReceipt<H>) {
+        let _ = receipt;
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Run `interceptor` around inserts, after the interceptors already added
+    pub fn add_interceptor(&mut self, interceptor: Arc<dyn InsertInterceptor<H>>) {
+        self.interceptors.push(interceptor);
+    }
+
+    /// Remove all insert interceptors
+    pub fn clear_interceptors(&mut self) {
+        self.interceptors.clear();
+    }
+
+    /// Pass a record through every interceptor's `before_insert`
+    pub(crate) fn intercept_before<'a>(&self, data: &'a [u8], timestamp: u64) -> Result<PendingInsert<'a>> {
+        let mut record = PendingInsert { data: Cow::Borrowed(data), timestamp };
+        for interceptor in &self.interceptors {
+            interceptor.before_insert(&mut record)?;
+        }
+        Ok(record)
+    }
+
+    /// Report an inserted leaf to every interceptor
+    pub(crate) fn intercept_after(&self, receipt: &InsertReceipt<H>) {
+        for interceptor in &self.interceptors {
+            interceptor.after_insert(receipt);
+        }
+    }
+
+    /// Insert a batch through the interceptors, reporting each leaf once all are in
+    pub(crate) fn insert_intercepted_batch(&mut self, entries: &[(&[u8], u64)]) -> Result<()> {
+        let records = entries
+            .iter()
+            .map(|(data, timestamp)| self.intercept_before(data, *timestamp))
+            .collect::<Result<Vec<_>>>()?;
+        let entries: Vec<(&[u8], u64)> = records.iter().map(|record| (&*record.data, record.timestamp)).collect();
+        let old_root = self.root();
+        let old_leaf_count = self.leaf_count;
+        self.insert_records(&entries)?;
+        let Some(new_root) = self.root() else {
+            return Ok(());
+        };
+        for (leaf_index, (_, timestamp)) in (old_leaf_count..).zip(entries) {
+            let receipt = InsertReceipt { leaf_index, timestamp, old_root: old_root.clone(), new_root: new_root.clone() };
+            self.intercept_after(&receipt);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::error::ChronoMerkleError;
+    use std::sync::Mutex;
+
+    /// Replaces e-mail addresses with a fixed token
+    struct ScrubEmails;
+
+    impl InsertInterceptor<[u8; 32]> for ScrubEmails {
+        fn before_insert(&self, record: &mut PendingInsert<'_>) -> Result<()> {
+            if record.data.contains(&b'@') {
+                record.data = Cow::Owned(b"<redacted>".to_vec());
+            }
+            Ok(())
+        }
+    }
+
+    /// Refuses records before a cutoff and remembers inserted leaves
+    #[derive(Default)]
+    struct Audit {
+        seen: Mutex<Vec<(usize, Vec<u8>)>>,
+        inserted: Mutex<Vec<usize>>,
+    }
+
+    impl InsertInterceptor<[u8; 32]> for Audit {
+        fn before_insert(&self, record: &mut PendingInsert<'_>) -> Result<()> {
+            if record.timestamp < 1000 {
+                return Err(ChronoMerkleError::InsertRejected { reason: "timestamp before cutoff".to_string() });
+            }
+            self.seen.lock().unwrap().push((record.timestamp as usize, record.data.to_vec()));
+            Ok(())
+        }
+
+        fn after_insert(&self, receipt: &InsertReceipt<[u8; 32]>) {
+            self.inserted.lock().unwrap().push(receipt.leaf_index);
+        }
+    }
+
+    #[test]
+    fn test_interceptors_run_in_order() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let audit = Arc::new(Audit::default());
+        tree.add_interceptor(Arc::new(ScrubEmails));
+        tree.add_interceptor(audit.clone());
+
+        tree.insert(b"alice@example.com", 1000).unwrap();
+        let receipt = tree.insert_with_receipt(b"plain", 1001).unwrap();
+        assert_eq!(receipt.leaf_index, 1);
+        assert_eq!(tree.get_leaf_data(0).unwrap().data(), Some(&b"<redacted>"[..]));
+
+        // Audit sees the scrubbed record
+        assert_eq!(audit.seen.lock().unwrap()[0], (1000, b"<redacted>".to_vec()));
+
+        let err = tree.insert(b"late", 999).unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::InsertRejected { .. }));
+        assert_eq!(tree.leaf_count(), 2);
+
+        // A rejected record aborts the whole batch
+        assert!(tree.insert_batch(&[(b"x", 1002), (b"y", 998)]).is_err());
+        assert_eq!(tree.leaf_count(), 2);
+        tree.insert_batch(&[(b"bob@example.com", 1002), (b"y", 1003)]).unwrap();
+        assert_eq!(tree.get_leaf_data(2).unwrap().data(), Some(&b"<redacted>"[..]));
+        assert_eq!(*audit.inserted.lock().unwrap(), vec![0, 1, 2, 3]);
+
+        tree.clear_interceptors();
+        tree.insert(b"carol@example.com", 900).unwrap();
+        assert_eq!(tree.get_leaf_data(4).unwrap().data(), Some(&b"carol@example.com"[..]));
+    }
+}
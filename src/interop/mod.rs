@@ -0,0 +1,5 @@
+//! Formats of other transparency-log systems
+//!
+//! - [`trillian`]: mirroring a tree into Trillian and Rekor logs
+
+pub mod trillian;
@@ -0,0 +1,484 @@
+//! Mirroring into Trillian and Rekor transparency logs
+//!
+//! Trillian, and Rekor on top of it, keep an RFC 6962 Merkle tree: leaves are
+//! hashed as `H(0x00 || value)`, interior nodes as `H(0x01 || left || right)`,
+//! and a tree of `n` leaves splits at the largest power of two below `n`. A
+//! ChronoMerkle tree pairs an odd node with itself instead, so its roots and
+//! proofs mean nothing to Trillian clients. A [`MirrorLog`] keeps the RFC 6962
+//! tree over a ChronoMerkle tree's leaves: [`MirrorLog::sync`] appends the
+//! leaves added since the last sync and returns them as [`LogLeaf`]s to queue,
+//! and the mirror answers inclusion and consistency proofs, signed-root
+//! payloads and checkpoints for any size it has reached.
+//!
+//! Each mirrored leaf value is [`leaf_value`]: the leaf index and timestamp,
+//! big-endian, then the ChronoMerkle leaf hash. The index keeps equal records
+//! apart, since Trillian deduplicates leaves with equal identity hashes, and
+//! the leaf hash survives redaction of the leaf data.
+
+use crate::codec::{to_base64, to_hex};
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+/// Domain prefix of RFC 6962 leaf hashes
+pub const LEAF_PREFIX: u8 = 0x00;
+/// Domain prefix of RFC 6962 interior node hashes
+pub const NODE_PREFIX: u8 = 0x01;
+
+/// Leaf in the shape of Trillian's `LogLeaf` message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLeaf {
+    /// RFC 6962 hash of `leaf_value`
+    pub merkle_leaf_hash: Vec<u8>,
+    /// The value committed to by the log, see [`leaf_value`]
+    pub leaf_value: Vec<u8>,
+    /// The leaf data, if the tree retains it; not part of the Merkle tree
+    pub extra_data: Vec<u8>,
+    /// Position in the log, equal to the ChronoMerkle leaf index
+    pub leaf_index: i64,
+    /// Hash Trillian deduplicates on, here the Merkle leaf hash
+    pub leaf_identity_hash: Vec<u8>,
+}
+
+/// Proof in the shape of Trillian's `Proof` message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proof {
+    /// Leaf the proof is for; 0 for consistency proofs
+    pub leaf_index: i64,
+    /// Sibling hashes, bottom-up
+    pub hashes: Vec<Vec<u8>>,
+}
+
+/// Trillian's `LogRootV1`, the payload a log signs for each root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRootV1 {
+    /// Number of leaves
+    pub tree_size: u64,
+    /// RFC 6962 root
+    pub root_hash: Vec<u8>,
+    /// Time the root was produced, in nanoseconds since the Unix epoch
+    pub timestamp_nanos: u64,
+    /// Root revision; the mirror uses the tree size
+    pub revision: u64,
+    /// Opaque metadata
+    pub metadata: Vec<u8>,
+}
+
+impl LogRootV1 {
+    /// TLS encoding of the versioned `LogRoot` structure, as signed by Trillian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(29 + self.root_hash.len() + self.metadata.len());
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&self.tree_size.to_be_bytes());
+        out.push(self.root_hash.len() as u8);
+        out.extend_from_slice(&self.root_hash);
+        out.extend_from_slice(&self.timestamp_nanos.to_be_bytes());
+        out.extend_from_slice(&self.revision.to_be_bytes());
+        out.extend_from_slice(&(self.metadata.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.metadata);
+        out
+    }
+}
+
+/// Inclusion proof as returned by Rekor's log entry API
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RekorInclusionProof {
+    /// Index of the entry
+    pub log_index: u64,
+    /// Hex root of the tree the proof is for
+    pub root_hash: String,
+    /// Size of the tree the proof is for
+    pub tree_size: u64,
+    /// Hex sibling hashes, bottom-up
+    pub hashes: Vec<String>,
+    /// Signed checkpoint for `tree_size`, added by the caller after signing
+    /// [`MirrorLog::checkpoint`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<String>,
+}
+
+/// Consistency proof as returned by Rekor's `/api/v1/log/proof`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RekorConsistencyProof {
+    /// Hex root of the larger tree
+    pub root_hash: String,
+    /// Hex proof hashes
+    pub hashes: Vec<String>,
+}
+
+/// Value of the mirrored leaf for a ChronoMerkle leaf
+pub fn leaf_value(leaf_index: u64, timestamp: u64, leaf_hash: &[u8]) -> Vec<u8> {
+    let mut value = Vec::with_capacity(16 + leaf_hash.len());
+    value.extend_from_slice(&leaf_index.to_be_bytes());
+    value.extend_from_slice(&timestamp.to_be_bytes());
+    value.extend_from_slice(leaf_hash);
+    value
+}
+
+/// RFC 6962 tree mirroring the leaves of a ChronoMerkle tree
+#[derive(Debug, Clone)]
+pub struct MirrorLog<Th: HashFunction> {
+    hasher: Th,
+    leaves: Vec<Th::Output>,
+}
+
+impl<Th: HashFunction> MirrorLog<Th> {
+    /// Empty mirror hashing with `hasher`, SHA-256 for Trillian and Rekor
+    pub fn new(hasher: Th) -> Self {
+        Self { hasher, leaves: Vec::new() }
+    }
+
+    /// Number of mirrored leaves
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Whether no leaf has been mirrored
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Append a raw leaf value, returning its index
+    pub fn push(&mut self, value: &[u8]) -> u64 {
+        self.leaves.push(self.hasher.hash_multiple(&[&[LEAF_PREFIX], value]));
+        self.leaves.len() as u64 - 1
+    }
+
+    /// Append the leaves `tree` gained since the last sync and return them for queueing
+    ///
+    /// Leaves already mirrored are not compared again. Fails if `tree` has
+    /// fewer leaves than the mirror, which an append-only log cannot follow.
+    pub fn sync<H, Hasher, Logger>(&mut self, tree: &ChronoMerkleTree<H, Hasher, Logger>) -> Result<Vec<LogLeaf>>
+    where
+        H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+        Hasher: HashFunction<Output = H> + Sync,
+        Logger: SecurityLogger,
+    {
+        if tree.leaf_count() < self.leaves.len() {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "tree".to_string(),
+                reason: format!("tree has {} leaves but {} are mirrored", tree.leaf_count(), self.leaves.len()),
+            });
+        }
+        let mut added = Vec::with_capacity(tree.leaf_count() - self.leaves.len());
+        for index in self.leaves.len()..tree.leaf_count() {
+            let value = leaf_value(index as u64, tree.get_leaf_timestamp(index)?, tree.get_leaf_hash(index)?.as_ref());
+            let extra_data = tree.get_leaf_data(index)?.data().unwrap_or_default().to_vec();
+            self.push(&value);
+            let merkle_leaf_hash = self.leaves[index].as_ref().to_vec();
+            added.push(LogLeaf {
+                leaf_identity_hash: merkle_leaf_hash.clone(),
+                merkle_leaf_hash,
+                leaf_value: value,
+                extra_data,
+                leaf_index: index as i64,
+            });
+        }
+        Ok(added)
+    }
+
+    /// RFC 6962 root of the first `size` leaves
+    pub fn root(&self, size: usize) -> Result<Th::Output> {
+        Ok(self.subtree_hash(self.prefix(size)?))
+    }
+
+    /// Inclusion proof of leaf `index` in the tree of the first `size` leaves
+    pub fn inclusion_proof(&self, index: usize, size: usize) -> Result<Proof> {
+        let leaves = self.prefix(size)?;
+        if index >= size {
+            return Err(ChronoMerkleError::IndexOutOfBounds { index, leaf_count: size });
+        }
+        let mut hashes = Vec::new();
+        self.path(index, leaves, &mut hashes);
+        Ok(Proof { leaf_index: index as i64, hashes })
+    }
+
+    /// Proof that the tree of the first `old_size` leaves is a prefix of the tree of `new_size`
+    pub fn consistency_proof(&self, old_size: usize, new_size: usize) -> Result<Proof> {
+        let leaves = self.prefix(new_size)?;
+        if old_size > new_size {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: format!("old size {} exceeds new size {}", old_size, new_size),
+            });
+        }
+        let mut hashes = Vec::new();
+        if old_size > 0 {
+            self.subproof(old_size, leaves, true, &mut hashes);
+        }
+        Ok(Proof { leaf_index: 0, hashes })
+    }
+
+    /// Root of the first `size` leaves as a `LogRootV1` stamped `timestamp_nanos`
+    pub fn log_root(&self, size: usize, timestamp_nanos: u64) -> Result<LogRootV1> {
+        Ok(LogRootV1 {
+            tree_size: size as u64,
+            root_hash: self.root(size)?.as_ref().to_vec(),
+            timestamp_nanos,
+            revision: size as u64,
+            metadata: Vec::new(),
+        })
+    }
+
+    /// Unsigned checkpoint note body for the first `size` leaves of the log named `origin`
+    pub fn checkpoint(&self, origin: &str, size: usize) -> Result<String> {
+        Ok(format!("{}\n{}\n{}\n", origin, size, to_base64(self.root(size)?.as_ref())))
+    }
+
+    /// [`inclusion_proof`](Self::inclusion_proof) in Rekor's format
+    pub fn rekor_inclusion_proof(&self, index: usize, size: usize) -> Result<RekorInclusionProof> {
+        let proof = self.inclusion_proof(index, size)?;
+        Ok(RekorInclusionProof {
+            log_index: index as u64,
+            root_hash: to_hex(self.root(size)?.as_ref()),
+            tree_size: size as u64,
+            hashes: proof.hashes.iter().map(|hash| to_hex(hash)).collect(),
+            checkpoint: None,
+        })
+    }
+
+    /// [`consistency_proof`](Self::consistency_proof) in Rekor's format
+    pub fn rekor_consistency_proof(&self, old_size: usize, new_size: usize) -> Result<RekorConsistencyProof> {
+        let proof = self.consistency_proof(old_size, new_size)?;
+        Ok(RekorConsistencyProof {
+            root_hash: to_hex(self.root(new_size)?.as_ref()),
+            hashes: proof.hashes.iter().map(|hash| to_hex(hash)).collect(),
+        })
+    }
+
+    fn prefix(&self, size: usize) -> Result<&[Th::Output]> {
+        self.leaves.get(..size).ok_or(ChronoMerkleError::IndexOutOfBounds {
+            index: size,
+            leaf_count: self.leaves.len(),
+        })
+    }
+
+    /// MTH of RFC 6962 section 2.1
+    fn subtree_hash(&self, leaves: &[Th::Output]) -> Th::Output {
+        match leaves.len() {
+            0 => self.hasher.hash(&[]),
+            1 => leaves[0].clone(),
+            n => {
+                let (left, right) = leaves.split_at(split_point(n));
+                node_hash(&self.hasher, self.subtree_hash(left).as_ref(), self.subtree_hash(right).as_ref())
+            }
+        }
+    }
+
+    /// PATH of RFC 6962 section 2.1.1
+    fn path(&self, index: usize, leaves: &[Th::Output], out: &mut Vec<Vec<u8>>) {
+        if leaves.len() <= 1 {
+            return;
+        }
+        let (left, right) = leaves.split_at(split_point(leaves.len()));
+        if index < left.len() {
+            self.path(index, left, out);
+            out.push(self.subtree_hash(right).as_ref().to_vec());
+        } else {
+            self.path(index - left.len(), right, out);
+            out.push(self.subtree_hash(left).as_ref().to_vec());
+        }
+    }
+
+    /// SUBPROOF of RFC 6962 section 2.1.2
+    fn subproof(&self, old_size: usize, leaves: &[Th::Output], complete: bool, out: &mut Vec<Vec<u8>>) {
+        if old_size == leaves.len() {
+            if !complete {
+                out.push(self.subtree_hash(leaves).as_ref().to_vec());
+            }
+            return;
+        }
+        let (left, right) = leaves.split_at(split_point(leaves.len()));
+        if old_size <= left.len() {
+            self.subproof(old_size, left, complete, out);
+            out.push(self.subtree_hash(right).as_ref().to_vec());
+        } else {
+            self.subproof(old_size - left.len(), right, false, out);
+            out.push(self.subtree_hash(left).as_ref().to_vec());
+        }
+    }
+}
+
+/// Check an inclusion proof received from a Trillian or Rekor log (RFC 9162 section 2.1.3.2)
+pub fn verify_inclusion<Th: HashFunction>(
+    hasher: &Th,
+    index: u64,
+    size: u64,
+    leaf_hash: &[u8],
+    proof: &[Vec<u8>],
+    root: &[u8],
+) -> bool {
+    if index >= size {
+        return false;
+    }
+    let (mut fn_, mut sn) = (index, size - 1);
+    let mut r = leaf_hash.to_vec();
+    for p in proof {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            r = node_hash(hasher, p, &r).as_ref().to_vec();
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            r = node_hash(hasher, &r, p).as_ref().to_vec();
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    sn == 0 && r == root
+}
+
+/// Check a consistency proof received from a Trillian or Rekor log (RFC 9162 section 2.1.4.2)
+pub fn verify_consistency<Th: HashFunction>(
+    hasher: &Th,
+    old_size: u64,
+    new_size: u64,
+    old_root: &[u8],
+    new_root: &[u8],
+    proof: &[Vec<u8>],
+) -> bool {
+    if old_size > new_size {
+        return false;
+    }
+    if old_size == new_size {
+        return proof.is_empty() && old_root == new_root;
+    }
+    if old_size == 0 {
+        return proof.is_empty();
+    }
+
+    // A power-of-two old tree is a complete subtree and its root starts the path
+    let mut path = proof.iter().map(Vec::as_slice);
+    let first = if old_size.is_power_of_two() { Some(old_root) } else { path.next() };
+    let Some(first) = first else {
+        return false;
+    };
+    let (mut fn_, mut sn) = (old_size - 1, new_size - 1);
+    while fn_ & 1 == 1 {
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    let (mut fr, mut sr) = (first.to_vec(), first.to_vec());
+    for c in path {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            fr = node_hash(hasher, c, &fr).as_ref().to_vec();
+            sr = node_hash(hasher, c, &sr).as_ref().to_vec();
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            sr = node_hash(hasher, &sr, c).as_ref().to_vec();
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    sn == 0 && fr == old_root && sr == new_root
+}
+
+fn node_hash<Th: HashFunction>(hasher: &Th, left: &[u8], right: &[u8]) -> Th::Output {
+    hasher.hash_multiple(&[&[NODE_PREFIX], left, right])
+}
+
+/// Largest power of two below `n`, for `n >= 2`
+fn split_point(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+#[cfg(all(test, feature = "sha2-hash", feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::DefaultHasher;
+
+    /// Leaves of the certificate-transparency Merkle tree test vectors
+    const VECTOR_LEAVES: [&[u8]; 8] = [
+        b"",
+        b"\x00",
+        b"\x10",
+        b"\x20\x21",
+        b"\x30\x31",
+        b"\x40\x41\x42\x43",
+        b"\x50\x51\x52\x53\x54\x55\x56\x57",
+        b"\x60\x61\x62\x63\x64\x65\x66\x67\x68\x69\x6a\x6b\x6c\x6d\x6e\x6f",
+    ];
+
+    #[test]
+    fn test_rfc6962_vectors() {
+        let mut log = MirrorLog::new(DefaultHasher);
+        for leaf in VECTOR_LEAVES {
+            log.push(leaf);
+        }
+        let root = |size| to_hex(log.root(size).unwrap().as_ref());
+        assert_eq!(root(0), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(root(1), "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d");
+        assert_eq!(root(3), "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77");
+        assert_eq!(root(7), "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c");
+        assert_eq!(root(8), "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328");
+
+        for size in 1..=8 {
+            let root = log.root(size).unwrap();
+            for index in 0..size {
+                let proof = log.inclusion_proof(index, size).unwrap();
+                let leaf = log.leaves[index];
+                assert!(verify_inclusion(&DefaultHasher, index as u64, size as u64, &leaf, &proof.hashes, &root));
+                let other = log.leaves[(index + 1) % size];
+                assert_eq!(verify_inclusion(&DefaultHasher, index as u64, size as u64, &other, &proof.hashes, &root), size == 1);
+            }
+            for old_size in 0..=size {
+                let old_root = log.root(old_size).unwrap();
+                let proof = log.consistency_proof(old_size, size).unwrap();
+                assert!(verify_consistency(&DefaultHasher, old_size as u64, size as u64, &old_root, &root, &proof.hashes));
+                if old_size > 0 && old_size < size {
+                    assert!(!verify_consistency(&DefaultHasher, old_size as u64, size as u64, &root, &root, &proof.hashes));
+                }
+            }
+        }
+        assert!(log.inclusion_proof(8, 8).is_err());
+        assert!(log.consistency_proof(5, 9).is_err());
+    }
+
+    #[test]
+    fn test_sync_from_tree() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut log = MirrorLog::new(DefaultHasher);
+        for i in 0..5u64 {
+            tree.insert(b"same record", 1000 + i).unwrap();
+        }
+        let leaves = log.sync(&tree).unwrap();
+        assert_eq!(leaves.len(), 5);
+        assert_eq!(leaves[2].leaf_value, leaf_value(2, 1002, &tree.get_leaf_hash(2).unwrap()));
+        assert_eq!(leaves[2].extra_data, b"same record");
+        assert_eq!(leaves[2].merkle_leaf_hash, DefaultHasher.hash_multiple(&[&[LEAF_PREFIX], &leaves[2].leaf_value]));
+
+        tree.insert(b"late", 1005).unwrap();
+        let added = log.sync(&tree).unwrap();
+        assert_eq!((added.len(), added[0].leaf_index, log.len()), (1, 5, 6));
+        assert!(log.sync(&tree).unwrap().is_empty());
+
+        let proof = log.rekor_consistency_proof(5, 6).unwrap();
+        let hashes: Vec<Vec<u8>> = proof.hashes.iter().map(|hash| crate::codec::from_hex(hash).unwrap()).collect();
+        assert!(verify_consistency(&DefaultHasher, 5, 6, &log.root(5).unwrap(), &log.root(6).unwrap(), &hashes));
+
+        let json = serde_json::to_value(log.rekor_inclusion_proof(3, 6).unwrap()).unwrap();
+        assert_eq!(json["logIndex"], 3);
+        assert_eq!(json["treeSize"], 6);
+        assert_eq!(json["rootHash"], to_hex(&log.root(6).unwrap()));
+        assert!(json.get("checkpoint").is_none());
+
+        let root = log.log_root(6, 7).unwrap().to_bytes();
+        assert_eq!((root.len(), &root[..2], root[10]), (2 + 8 + 1 + 32 + 8 + 8 + 2, &[0, 1][..], 32));
+        assert!(log.checkpoint("example.org/log", 6).unwrap().starts_with("example.org/log\n6\n"));
+
+        let shorter: ChronoMerkleTree = ChronoMerkleTree::default();
+        assert!(log.sync(&shorter).is_err());
+    }
+}
@@ -0,0 +1,421 @@
+//! Storage-backed read-only trees that load nodes on demand
+//!
+//! [`save_paged`](ChronoMerkleTree::save_paged) splits a tree's nodes into fixed
+//! size pages stored under `"{key}.page.{n}"`, plus a small metadata record under
+//! `"{key}.meta"`. [`LazyTree`] opens that metadata only and fetches pages as
+//! queries and proofs need them, so trees far larger than memory can be served.
+
+use crate::config::TreeConfig;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::node::{Node, NodeType};
+use crate::proof::{ChronoProof, ProofStep};
+use crate::security::NoOpLogger;
+use crate::sparse_index::SparseIndex;
+use crate::storage::StorageBackend;
+use crate::tree::ChronoMerkleTree;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Default number of nodes per page written by `save_paged`
+pub const DEFAULT_PAGE_SIZE: usize = 1024;
+
+/// Default number of pages a [`LazyTree`] keeps in memory
+pub const DEFAULT_PAGE_CACHE: usize = 64;
+
+/// Metadata record describing a paged tree
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(bound(serialize = "H: serde::Serialize", deserialize = "H: serde::de::DeserializeOwned"))]
+struct PagedMeta<H> {
+    root: Option<H>,
+    leaf_count: usize,
+    node_count: usize,
+    page_size: usize,
+    /// Number of nodes on each level, leaves first
+    level_sizes: Vec<usize>,
+    /// Timestamp range of the leaves on each page that holds leaves
+    leaf_page_ranges: Vec<(u64, u64)>,
+    sparse_index: SparseIndex,
+    config: TreeConfig,
+}
+
+fn meta_key(key: &str) -> String {
+    format!("{}.meta", key)
+}
+
+fn page_key(key: &str, page: usize) -> String {
+    format!("{}.page.{}", key, page)
+}
+
+/// Page layout fields of a [`PagedMeta`] record, readable without knowing `H`
+#[derive(serde::Deserialize)]
+struct PageLayout {
+    node_count: usize,
+    page_size: usize,
+}
+
+/// Keys of the manifest and pages of the paged tree saved under `key`, if there is one
+pub(crate) fn paged_tree_keys(storage: &(impl StorageBackend + ?Sized), key: &str) -> Result<Option<Vec<String>>> {
+    let Some(data) = storage.load(&meta_key(key))? else {
+        return Ok(None);
+    };
+    let layout: PageLayout =
+        serde_json::from_slice(&data).map_err(|e| ChronoMerkleError::DeserializationError(e.to_string()))?;
+    let pages = layout.node_count.div_ceil(layout.page_size.max(1));
+    let mut keys = vec![meta_key(key)];
+    keys.extend((0..pages).map(|page| page_key(key, page)));
+    Ok(Some(keys))
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: crate::security::SecurityLogger,
+{
+    /// Save the tree as fixed-size node pages that can be opened with `open_lazy`
+    ///
+    /// Honors the tree's cancellation token between pages; a cancelled save
+    /// may have overwritten some pages of a previous save under `key`.
+    pub fn save_paged(
+        &self,
+        storage: &mut (impl StorageBackend + ?Sized),
+        key: &str,
+        page_size: usize,
+    ) -> Result<()> {
+        if page_size == 0 {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "page_size".to_string(),
+                reason: "Page size must be greater than 0".to_string(),
+            });
+        }
+
+        let mut level_sizes = Vec::new();
+        let mut count = self.leaf_count;
+        if count > 0 {
+            level_sizes.push(count);
+            while count > 1 {
+                count = count.div_ceil(2);
+                level_sizes.push(count);
+            }
+        }
+        if level_sizes.iter().sum::<usize>() != self.nodes.len() {
+            return Err(ChronoMerkleError::StorageError {
+                reason: "Tree nodes are not in the standard level layout".to_string(),
+            });
+        }
+
+        let mut leaf_page_ranges = Vec::new();
+        let page_count = self.nodes.len().div_ceil(page_size);
+        let mut nodes = self.nodes.iter();
+        for page in 0..page_count {
+            self.check_cancelled("paged save")?;
+            let chunk: Vec<_> = self.with_leaf_data(page * page_size, nodes.by_ref().take(page_size)).collect();
+            let serialized = serde_json::to_vec(&chunk)
+                .map_err(|e| ChronoMerkleError::SerializationError(e.to_string()))?;
+            storage.save(&page_key(key, page), &serialized)?;
+
+            let leaves = chunk.iter().take(self.leaf_count.saturating_sub(page * page_size));
+            let range = leaves.fold(None, |range: Option<(u64, u64)>, leaf| {
+                let (ts, _) = leaf.timestamp_info();
+                Some(range.map_or((ts, ts), |(lo, hi)| (lo.min(ts), hi.max(ts))))
+            });
+            if let Some(range) = range {
+                leaf_page_ranges.push(range);
+            }
+        }
+
+        // Drop pages left over from a previous, larger save
+        let mut stale = page_count;
+        while storage.exists(&page_key(key, stale))? {
+            storage.delete(&page_key(key, stale))?;
+            stale += 1;
+        }
+
+        let meta = PagedMeta {
+            root: self.root(),
+            leaf_count: self.leaf_count,
+            node_count: self.nodes.len(),
+            page_size,
+            level_sizes,
+            leaf_page_ranges,
+            sparse_index: self.sparse_index.clone(),
+            config: self.config.clone(),
+        };
+        let serialized =
+            serde_json::to_vec(&meta).map_err(|e| ChronoMerkleError::SerializationError(e.to_string()))?;
+        storage.save(&meta_key(key), &serialized)
+    }
+}
+
+impl<H, Hasher> ChronoMerkleTree<H, Hasher, NoOpLogger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+{
+    /// Open a tree written by `save_paged` without loading its nodes
+    pub fn open_lazy<'a, S: StorageBackend + ?Sized>(
+        storage: &'a S,
+        key: &str,
+        hasher: Hasher,
+    ) -> Result<LazyTree<'a, S, H, Hasher>> {
+        LazyTree::open(storage, key, hasher)
+    }
+}
+
+/// Bounded FIFO cache of loaded pages
+struct PageCache<H> {
+    pages: BTreeMap<usize, Arc<Vec<Node<H>>>>,
+    order: VecDeque<usize>,
+    loads: u64,
+}
+
+/// A read-only tree whose nodes are fetched from storage on demand
+pub struct LazyTree<'a, S: ?Sized, H, Hasher> {
+    storage: &'a S,
+    key: String,
+    hasher: Hasher,
+    meta: PagedMeta<H>,
+    cache_capacity: usize,
+    cache: Mutex<PageCache<H>>,
+}
+
+impl<'a, S, H, Hasher> LazyTree<'a, S, H, Hasher>
+where
+    S: StorageBackend + ?Sized,
+    H: AsRef<[u8]> + Clone + Eq + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H>,
+{
+    /// Open a paged tree, loading only its metadata
+    pub fn open(storage: &'a S, key: &str, hasher: Hasher) -> Result<Self> {
+        let data = storage.load(&meta_key(key))?.ok_or_else(|| ChronoMerkleError::StorageError {
+            reason: format!("No paged tree found for key: {}", key),
+        })?;
+        let meta: PagedMeta<H> = serde_json::from_slice(&data)
+            .map_err(|e| ChronoMerkleError::DeserializationError(e.to_string()))?;
+        Ok(Self {
+            storage,
+            key: key.to_string(),
+            hasher,
+            meta,
+            cache_capacity: DEFAULT_PAGE_CACHE,
+            cache: Mutex::new(PageCache {
+                pages: BTreeMap::new(),
+                order: VecDeque::new(),
+                loads: 0,
+            }),
+        })
+    }
+
+    /// Set how many pages are kept in memory (at least one)
+    pub fn with_page_cache(mut self, pages: usize) -> Self {
+        self.cache_capacity = pages.max(1);
+        self
+    }
+
+    /// Get the root hash of the tree
+    pub fn root(&self) -> Option<H> {
+        self.meta.root.clone()
+    }
+
+    /// Get the number of leaves in the tree
+    pub fn leaf_count(&self) -> usize {
+        self.meta.leaf_count
+    }
+
+    /// Check if the tree is empty
+    pub fn is_empty(&self) -> bool {
+        self.meta.leaf_count == 0
+    }
+
+    /// Get the configuration the tree was saved with
+    pub fn config(&self) -> &TreeConfig {
+        &self.meta.config
+    }
+
+    /// Get the sparse timestamp index
+    pub fn sparse_index(&self) -> &SparseIndex {
+        &self.meta.sparse_index
+    }
+
+    /// Number of pages fetched from storage so far
+    pub fn pages_loaded(&self) -> u64 {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).loads
+    }
+
+    /// Get a leaf by index
+    pub fn get_leaf(&self, index: usize) -> Result<Node<H>> {
+        self.check_leaf_index(index)?;
+        self.node_at(index)
+    }
+
+    /// Get the hash of a leaf by index
+    pub fn get_leaf_hash(&self, index: usize) -> Result<H> {
+        Ok(self.get_leaf(index)?.hash())
+    }
+
+    /// Get the timestamp of a leaf by index
+    pub fn get_leaf_timestamp(&self, index: usize) -> Result<u64> {
+        Ok(self.get_leaf(index)?.timestamp_info().0)
+    }
+
+    /// Find leaves by timestamp (exact match)
+    pub fn find_by_timestamp(&self, timestamp: u64) -> Result<Vec<usize>> {
+        self.find_range(timestamp, timestamp)
+    }
+
+    /// Find leaves in a timestamp range, loading only pages that can contain matches
+    pub fn find_range(&self, start: u64, end: u64) -> Result<Vec<usize>> {
+        let mut indices = Vec::new();
+        for (page, &(lo, hi)) in self.meta.leaf_page_ranges.iter().enumerate() {
+            if hi < start || lo > end {
+                continue;
+            }
+            let nodes = self.page(page)?;
+            let first = page * self.meta.page_size;
+            for (offset, node) in nodes.iter().enumerate() {
+                let index = first + offset;
+                if index >= self.meta.leaf_count {
+                    break;
+                }
+                if let NodeType::Leaf { timestamp, .. } = &node.node_type {
+                    if *timestamp >= start && *timestamp <= end {
+                        indices.push(index);
+                    }
+                }
+            }
+        }
+        Ok(indices)
+    }
+
+    /// Generate a proof for a leaf, fetching only the nodes on its path
+    pub fn generate_proof(&self, leaf_index: usize) -> Result<ChronoProof<H>> {
+        self.check_leaf_index(leaf_index)?;
+        let timestamp = self.node_at(leaf_index)?.timestamp_info().0;
+        let mut proof = ChronoProof::new(leaf_index, timestamp);
+        proof.leaf_hash_mode = self.meta.config.leaf_hash_mode;
+
+        let levels = &self.meta.level_sizes;
+        let mut level_start = 0;
+        let mut index = leaf_index;
+        for &size in &levels[..levels.len().saturating_sub(1)] {
+            let sibling = index ^ 1;
+            let sibling_hash = if sibling < size {
+                self.node_at(level_start + sibling)?.hash()
+            } else {
+                // Odd level: the last node is paired with itself
+                self.node_at(level_start + index)?.hash()
+            };
+            proof.add_step(if index % 2 == 0 {
+                ProofStep::Right(sibling_hash)
+            } else {
+                ProofStep::Left(sibling_hash)
+            });
+            level_start += size;
+            index /= 2;
+        }
+        Ok(proof)
+    }
+
+    /// Verify a proof against the stored root and leaf
+    pub fn verify_proof(&self, proof: &ChronoProof<H>) -> Result<bool> {
+        let root = self.root().ok_or(ChronoMerkleError::EmptyTree)?;
+        let leaf = self.get_leaf(proof.leaf_index)?;
+        if leaf.timestamp_info().0 != proof.timestamp {
+            return Ok(false);
+        }
+        crate::proof::verify_proof(proof, &leaf.hash(), &root, &self.hasher)
+    }
+
+    fn check_leaf_index(&self, index: usize) -> Result<()> {
+        if index >= self.meta.leaf_count {
+            return Err(ChronoMerkleError::IndexOutOfBounds {
+                index,
+                leaf_count: self.meta.leaf_count,
+            });
+        }
+        Ok(())
+    }
+
+    fn node_at(&self, index: usize) -> Result<Node<H>> {
+        let page = self.page(index / self.meta.page_size)?;
+        page.get(index % self.meta.page_size)
+            .cloned()
+            .ok_or_else(|| ChronoMerkleError::StorageError {
+                reason: format!("Node {} missing from page storage", index),
+            })
+    }
+
+    fn page(&self, page: usize) -> Result<Arc<Vec<Node<H>>>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(nodes) = cache.pages.get(&page) {
+            return Ok(nodes.clone());
+        }
+
+        let key = page_key(&self.key, page);
+        let data = self.storage.load(&key)?.ok_or_else(|| ChronoMerkleError::StorageError {
+            reason: format!("No data found for key: {}", key),
+        })?;
+        let nodes: Arc<Vec<Node<H>>> = Arc::new(
+            serde_json::from_slice(&data).map_err(|e| ChronoMerkleError::DeserializationError(e.to_string()))?,
+        );
+
+        if cache.order.len() >= self.cache_capacity {
+            if let Some(evicted) = cache.order.pop_front() {
+                cache.pages.remove(&evicted);
+            }
+        }
+        cache.order.push_back(page);
+        cache.pages.insert(page, nodes.clone());
+        cache.loads += 1;
+        Ok(nodes)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[test]
+    fn test_lazy_tree_matches_loaded_tree() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..37u64 {
+            tree.insert(format!("event {}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        let mut storage = MemoryStorage::new();
+        tree.save_paged(&mut storage, "tree", 8).unwrap();
+
+        let lazy = ChronoMerkleTree::open_lazy(&storage, "tree", crate::hash::Blake3Hasher).unwrap();
+        assert_eq!(lazy.pages_loaded(), 0);
+        assert_eq!(lazy.root(), tree.root());
+        assert_eq!(lazy.leaf_count(), 37);
+
+        for i in [0, 17, 36] {
+            let proof = lazy.generate_proof(i).unwrap();
+            assert_eq!(proof.path, tree.generate_proof(i).unwrap().path);
+            assert!(lazy.verify_proof(&proof).unwrap());
+        }
+        assert_eq!(lazy.find_range(1010, 1012).unwrap(), tree.find_range(1010, 1012));
+        assert!(lazy.get_leaf(37).is_err());
+    }
+
+    #[test]
+    fn test_lazy_tree_loads_pages_on_demand() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..64u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        let mut storage = MemoryStorage::new();
+        tree.save_paged(&mut storage, "tree", 16).unwrap();
+
+        let lazy = LazyTree::open(&storage, "tree", crate::hash::Blake3Hasher)
+            .unwrap()
+            .with_page_cache(2);
+        assert_eq!(lazy.find_by_timestamp(1003).unwrap(), vec![3]);
+        assert_eq!(lazy.pages_loaded(), 1);
+        lazy.get_leaf(5).unwrap();
+        assert_eq!(lazy.pages_loaded(), 1);
+    }
+}
@@ -0,0 +1,559 @@
+//! Many small records packed into one leaf
+//!
+//! High-frequency events make trees deep and proofs long when each event is
+//! its own leaf. A [`LeafBatch`] collects records into a mini-tree, and
+//! [`BatchedTree`] inserts one leaf per sealed batch whose data is the batch
+//! root. A record is then proven by a [`BatchRecordProof`]: its path to the
+//! batch root followed by the ordinary proof of the batch leaf.
+//!
+//! Mini-tree leaves are `H(timestamp_le || record)` and interior nodes use
+//! [`HashFunction::hash_pair`], pairing an odd node with itself like the main
+//! tree does.
+
+use crate::encoding::encode_u64;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::{ChronoProof, ProofStep};
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{string::ToString, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::vec::Vec;
+
+/// Hash of a record as a mini-tree leaf
+pub fn record_hash<Hasher: HashFunction>(hasher: &Hasher, record: &[u8], timestamp: u64) -> Hasher::Output {
+    hasher.hash_multiple(&[&encode_u64(timestamp), record])
+}
+
+/// Records committed together as one leaf
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeafBatch {
+    records: Vec<(Vec<u8>, u64)>,
+}
+
+impl LeafBatch {
+    /// Create an empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a record with its timestamp
+    pub fn push(&mut self, record: &[u8], timestamp: u64) -> Result<()> {
+        if record.is_empty() {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "record".to_string(),
+                reason: "Empty records not allowed".to_string(),
+            });
+        }
+        self.records.push((record.to_vec(), timestamp));
+        Ok(())
+    }
+
+    /// Records and their timestamps in insertion order
+    pub fn records(&self) -> &[(Vec<u8>, u64)] {
+        &self.records
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the batch has no records
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Latest record timestamp, used as the timestamp of the batch leaf
+    pub fn timestamp(&self) -> Option<u64> {
+        self.records.iter().map(|(_, timestamp)| *timestamp).max()
+    }
+
+    /// Root of the mini-tree, or `None` for an empty batch
+    pub fn root<Hasher: HashFunction>(&self, hasher: &Hasher) -> Option<Hasher::Output> {
+        let mut level = self.leaf_hashes(hasher);
+        while level.len() > 1 {
+            level = Self::parent_level(&level, hasher);
+        }
+        level.pop()
+    }
+
+    /// Path from record `record_index` to the mini-tree root
+    pub fn record_path<Hasher: HashFunction>(
+        &self,
+        record_index: usize,
+        hasher: &Hasher,
+    ) -> Result<Vec<ProofStep<Hasher::Output>>> {
+        if record_index >= self.records.len() {
+            return Err(ChronoMerkleError::IndexOutOfBounds {
+                index: record_index,
+                leaf_count: self.records.len(),
+            });
+        }
+        let mut path = Vec::new();
+        let mut level = self.leaf_hashes(hasher);
+        let mut index = record_index;
+        while level.len() > 1 {
+            // An odd last node is its own sibling
+            let sibling = level.get(index ^ 1).unwrap_or(&level[index]).clone();
+            path.push(if index % 2 == 0 {
+                ProofStep::Right(sibling)
+            } else {
+                ProofStep::Left(sibling)
+            });
+            level = Self::parent_level(&level, hasher);
+            index /= 2;
+        }
+        Ok(path)
+    }
+
+    fn leaf_hashes<Hasher: HashFunction>(&self, hasher: &Hasher) -> Vec<Hasher::Output> {
+        self.records
+            .iter()
+            .map(|(record, timestamp)| record_hash(hasher, record, *timestamp))
+            .collect()
+    }
+
+    fn parent_level<Hasher: HashFunction>(level: &[Hasher::Output], hasher: &Hasher) -> Vec<Hasher::Output> {
+        level
+            .chunks(2)
+            .map(|pair| hasher.hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+            .collect()
+    }
+}
+
+/// Proof that a record is part of a batch leaf under a tree root
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone)]
+pub struct BatchRecordProof<H> {
+    /// Index of the record inside its batch
+    pub record_index: usize,
+    /// Timestamp of the record
+    pub record_timestamp: u64,
+    /// Path from the record to the batch root
+    pub record_path: Vec<ProofStep<H>>,
+    /// Proof of the batch leaf in the main tree
+    pub batch_proof: ChronoProof<H>,
+}
+
+impl<H: AsRef<[u8]> + Clone + Eq> BatchRecordProof<H> {
+    /// Index of the batch leaf in the main tree
+    pub fn batch_index(&self) -> usize {
+        self.batch_proof.leaf_index
+    }
+
+    /// Verify that `record` is included under `root`
+    pub fn verify<Hasher>(&self, record: &[u8], root: &H, hasher: &Hasher) -> Result<bool>
+    where
+        Hasher: HashFunction<Output = H>,
+    {
+        let batch_root = self.record_path.iter().try_fold(
+            record_hash(hasher, record, self.record_timestamp),
+            |current, step| match step {
+                ProofStep::Left(sibling) => Ok(hasher.hash_pair(sibling, &current)),
+                ProofStep::Right(sibling) => Ok(hasher.hash_pair(&current, sibling)),
+                ProofStep::Delta(..) => Err(ChronoMerkleError::InvalidProof {
+                    message: "Batch record paths do not support delta steps".to_string(),
+                }),
+            },
+        )?;
+        let leaf_hash = match self.batch_proof.leaf_hash_mode {
+            crate::config::LeafHashMode::HashData => hasher.hash(batch_root.as_ref()),
+            crate::config::LeafHashMode::TakeAsIs => batch_root,
+        };
+        crate::proof::verify_proof(&self.batch_proof, &leaf_hash, root, hasher)
+    }
+}
+
+/// When [`BatchedTree`] closes its open batch
+///
+/// A batch is closed as soon as any limit is reached. `max_age` is measured
+/// from the first record of the batch and is only enforced with the `std`
+/// feature, on [`push`](BatchedTree::push) and [`poll`](BatchedTree::poll).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchPolicy {
+    /// Close after this many records
+    pub max_records: usize,
+    /// Close once the records hold at least this many bytes
+    pub max_bytes: usize,
+    /// Close once the first record has waited this long
+    pub max_age: Option<core::time::Duration>,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        Self {
+            max_records: 256,
+            max_bytes: 64 * 1024,
+            max_age: Some(core::time::Duration::from_millis(1000)),
+        }
+    }
+}
+
+impl BatchPolicy {
+    /// Close batches by record count only
+    pub fn records(max_records: usize) -> Self {
+        Self {
+            max_records,
+            max_bytes: usize::MAX,
+            max_age: None,
+        }
+    }
+
+    /// Check that every limit can be reached
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |parameter: &str| ChronoMerkleError::InvalidConfiguration {
+            parameter: parameter.to_string(),
+            reason: "Batch limits must be greater than zero".to_string(),
+        };
+        if self.max_records == 0 {
+            return Err(invalid("max_records"));
+        }
+        if self.max_bytes == 0 {
+            return Err(invalid("max_bytes"));
+        }
+        if self.max_age.is_some_and(|age| age.is_zero()) {
+            return Err(invalid("max_age"));
+        }
+        Ok(())
+    }
+}
+
+/// Limit that closed a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchCloseReason {
+    /// `max_records` was reached
+    Records,
+    /// `max_bytes` was reached
+    Bytes,
+    /// `max_age` was reached
+    Age,
+    /// [`BatchedTree::seal`] was called
+    Manual,
+}
+
+/// Batch sizes achieved by a [`BatchedTree`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchStats {
+    /// Number of sealed batches
+    pub batches: u64,
+    /// Records in sealed batches
+    pub records: u64,
+    /// Record bytes in sealed batches
+    pub bytes: u64,
+    /// Fewest records in a sealed batch
+    pub min_records: usize,
+    /// Most records in a sealed batch
+    pub max_records: usize,
+    /// Batches closed by `max_records`
+    pub closed_by_records: u64,
+    /// Batches closed by `max_bytes`
+    pub closed_by_bytes: u64,
+    /// Batches closed by `max_age`
+    pub closed_by_age: u64,
+    /// Batches closed by [`BatchedTree::seal`]
+    pub closed_manually: u64,
+}
+
+impl BatchStats {
+    /// Mean records per sealed batch
+    pub fn mean_records(&self) -> f64 {
+        if self.batches == 0 {
+            0.0
+        } else {
+            self.records as f64 / self.batches as f64
+        }
+    }
+
+    fn record(&mut self, records: usize, bytes: usize, reason: BatchCloseReason) {
+        self.min_records = if self.batches == 0 { records } else { self.min_records.min(records) };
+        self.max_records = self.max_records.max(records);
+        self.batches += 1;
+        self.records += records as u64;
+        self.bytes += bytes as u64;
+        match reason {
+            BatchCloseReason::Records => self.closed_by_records += 1,
+            BatchCloseReason::Bytes => self.closed_by_bytes += 1,
+            BatchCloseReason::Age => self.closed_by_age += 1,
+            BatchCloseReason::Manual => self.closed_manually += 1,
+        }
+    }
+}
+
+/// Tree whose leaves are batches of records
+///
+/// Records are buffered in an open batch that is sealed into a leaf once the
+/// [`BatchPolicy`] closes it, or earlier with [`seal`](Self::seal). Sealed
+/// batches are kept so that their records can be proven.
+pub struct BatchedTree<H, Hasher, Logger = crate::security::NoOpLogger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    tree: ChronoMerkleTree<H, Hasher, Logger>,
+    batches: Vec<LeafBatch>,
+    open: LeafBatch,
+    open_bytes: usize,
+    #[cfg(feature = "std")]
+    opened_at: Option<std::time::Instant>,
+    policy: BatchPolicy,
+    stats: BatchStats,
+}
+
+impl<H, Hasher, Logger> BatchedTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Pack records into batches of `batch_size` in the empty `tree`
+    pub fn new(tree: ChronoMerkleTree<H, Hasher, Logger>, batch_size: usize) -> Result<Self> {
+        Self::with_policy(tree, BatchPolicy::records(batch_size.max(1)))
+    }
+
+    /// Pack records into batches closed by `policy` in the empty `tree`
+    pub fn with_policy(tree: ChronoMerkleTree<H, Hasher, Logger>, policy: BatchPolicy) -> Result<Self> {
+        policy.validate()?;
+        if !tree.is_empty() {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "tree".to_string(),
+                reason: "Batched trees must start empty".to_string(),
+            });
+        }
+        Ok(Self {
+            tree,
+            batches: Vec::new(),
+            open: LeafBatch::new(),
+            open_bytes: 0,
+            #[cfg(feature = "std")]
+            opened_at: None,
+            policy,
+            stats: BatchStats::default(),
+        })
+    }
+
+    /// Add a record to the open batch, sealing it when the policy closes it
+    ///
+    /// Returns the index of the sealed batch leaf, if one was inserted.
+    pub fn push(&mut self, record: &[u8], timestamp: u64) -> Result<Option<usize>> {
+        self.open.push(record, timestamp)?;
+        self.open_bytes += record.len();
+        #[cfg(feature = "std")]
+        if self.opened_at.is_none() {
+            self.opened_at = Some(std::time::Instant::now());
+        }
+
+        if self.open.len() >= self.policy.max_records {
+            self.close(BatchCloseReason::Records)
+        } else if self.open_bytes >= self.policy.max_bytes {
+            self.close(BatchCloseReason::Bytes)
+        } else {
+            self.poll()
+        }
+    }
+
+    /// Seal the open batch if it has reached `max_age`
+    ///
+    /// Call this periodically when records may stop arriving, so that the last
+    /// batch is not held open indefinitely.
+    pub fn poll(&mut self) -> Result<Option<usize>> {
+        #[cfg(feature = "std")]
+        if let (Some(max_age), Some(opened_at)) = (self.policy.max_age, self.opened_at) {
+            if opened_at.elapsed() >= max_age {
+                return self.close(BatchCloseReason::Age);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Insert the open batch as a leaf now
+    ///
+    /// Returns the index of the new leaf, or `None` if the open batch is empty.
+    /// On failure the open batch is kept.
+    pub fn seal(&mut self) -> Result<Option<usize>> {
+        self.close(BatchCloseReason::Manual)
+    }
+
+    fn close(&mut self, reason: BatchCloseReason) -> Result<Option<usize>> {
+        let (Some(root), Some(timestamp)) = (self.open.root(&self.tree.hasher), self.open.timestamp()) else {
+            return Ok(None);
+        };
+        match self.tree.config.leaf_hash_mode {
+            crate::config::LeafHashMode::HashData => self.tree.insert(root.as_ref(), timestamp)?,
+            crate::config::LeafHashMode::TakeAsIs => {
+                self.tree.insert_prehashed(crate::operations::PrehashedLeaf::new(root, timestamp))?
+            }
+        }
+        self.stats.record(self.open.len(), self.open_bytes, reason);
+        self.batches.push(core::mem::take(&mut self.open));
+        self.open_bytes = 0;
+        #[cfg(feature = "std")]
+        {
+            self.opened_at = None;
+        }
+        Ok(Some(self.batches.len() - 1))
+    }
+
+    /// Policy closing the batches
+    pub fn policy(&self) -> &BatchPolicy {
+        &self.policy
+    }
+
+    /// Batch sizes achieved so far
+    pub fn stats(&self) -> BatchStats {
+        self.stats
+    }
+
+    /// Prove record `record_index` of the sealed batch at leaf `batch_index`
+    pub fn prove_record_in_batch(&self, batch_index: usize, record_index: usize) -> Result<BatchRecordProof<H>> {
+        let batch = self.batches.get(batch_index).ok_or(ChronoMerkleError::IndexOutOfBounds {
+            index: batch_index,
+            leaf_count: self.batches.len(),
+        })?;
+        let record_path = batch.record_path(record_index, &self.tree.hasher)?;
+        Ok(BatchRecordProof {
+            record_index,
+            record_timestamp: batch.records[record_index].1,
+            record_path,
+            batch_proof: self.tree.generate_proof(batch_index)?,
+        })
+    }
+
+    /// Sealed batch at leaf `batch_index`
+    pub fn batch(&self, batch_index: usize) -> Option<&LeafBatch> {
+        self.batches.get(batch_index)
+    }
+
+    /// Records waiting in the open batch
+    pub fn pending(&self) -> &LeafBatch {
+        &self.open
+    }
+
+    /// Number of records in sealed batches
+    pub fn sealed_records(&self) -> usize {
+        self.batches.iter().map(LeafBatch::len).sum()
+    }
+
+    /// Underlying tree with one leaf per sealed batch
+    pub fn tree(&self) -> &ChronoMerkleTree<H, Hasher, Logger> {
+        &self.tree
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    #[test]
+    fn test_record_proofs_compose() {
+        let mut batched = BatchedTree::new(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default(), 5).unwrap();
+        let mut sealed = Vec::new();
+        for i in 0..23u64 {
+            sealed.extend(batched.push(format!("event{}", i).as_bytes(), 1000 + i).unwrap());
+        }
+        assert_eq!(sealed, vec![0, 1, 2, 3]);
+        assert_eq!((batched.pending().len(), batched.sealed_records()), (3, 20));
+        assert_eq!(batched.seal().unwrap(), Some(4));
+        assert_eq!(batched.seal().unwrap(), None);
+        assert_eq!(batched.tree().leaf_count(), 5);
+        assert_eq!(batched.tree().get_leaf_timestamp(4).unwrap(), 1022);
+
+        let root = batched.tree().root().unwrap();
+        for (batch_index, record_index) in [(0, 0), (1, 4), (3, 2), (4, 2)] {
+            let proof = batched.prove_record_in_batch(batch_index, record_index).unwrap();
+            let i = batch_index * 5 + record_index;
+            let record = format!("event{}", i);
+            assert_eq!(proof.batch_index(), batch_index);
+            assert_eq!(proof.record_timestamp, 1000 + i as u64);
+            assert!(proof.verify(record.as_bytes(), &root, &Blake3Hasher).unwrap());
+            assert!(!proof.verify(b"forged", &root, &Blake3Hasher).unwrap());
+
+            let mut wrong_time = proof.clone();
+            wrong_time.record_timestamp += 1;
+            assert!(!wrong_time.verify(record.as_bytes(), &root, &Blake3Hasher).unwrap());
+        }
+        assert!(batched.prove_record_in_batch(4, 3).is_err());
+        assert!(batched.prove_record_in_batch(5, 0).is_err());
+    }
+
+    #[test]
+    fn test_batch_leaf_is_batch_root() {
+        let mut batch = LeafBatch::new();
+        assert_eq!(batch.root(&Blake3Hasher), None);
+        assert!(batch.push(b"", 1).is_err());
+        for record in [b"a", b"b", b"c"] {
+            batch.push(record, 7).unwrap();
+        }
+        let [a, b, c] = [b"a", b"b", b"c"].map(|record| record_hash(&Blake3Hasher, record, 7));
+        let expected = Blake3Hasher.hash_pair(&Blake3Hasher.hash_pair(&a, &b), &Blake3Hasher.hash_pair(&c, &c));
+        assert_eq!(batch.root(&Blake3Hasher), Some(expected));
+
+        let mut batched = BatchedTree::new(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default(), 3).unwrap();
+        for (record, timestamp) in batch.records() {
+            batched.push(record, *timestamp).unwrap();
+        }
+        assert_eq!(batched.batch(0), Some(&batch));
+        assert_eq!(batched.tree().get_leaf_data(0).unwrap().data(), Some(expected.as_slice()));
+    }
+
+    #[test]
+    fn test_prehashed_batch_leaves() {
+        let config = crate::config::TreeConfig {
+            leaf_hash_mode: crate::config::LeafHashMode::TakeAsIs,
+            ..Default::default()
+        };
+        let tree = ChronoMerkleTree::<[u8; 32], Blake3Hasher>::with_config(Blake3Hasher, config).unwrap();
+        let mut batched = BatchedTree::new(tree, 2).unwrap();
+        for i in 0..4u64 {
+            batched.push(&[i as u8 + 1], 1000 + i).unwrap();
+        }
+        let root = batched.tree().root().unwrap();
+        assert_eq!(batched.tree().get_leaf_hash(1).unwrap(), batched.batch(1).unwrap().root(&Blake3Hasher).unwrap());
+        assert!(batched.prove_record_in_batch(1, 1).unwrap().verify(&[4], &root, &Blake3Hasher).unwrap());
+    }
+
+    #[test]
+    fn test_policy_closes_batches() {
+        assert!(BatchPolicy { max_records: 0, ..Default::default() }.validate().is_err());
+        assert!(BatchPolicy { max_age: Some(core::time::Duration::ZERO), ..Default::default() }.validate().is_err());
+
+        let policy = BatchPolicy {
+            max_records: 4,
+            max_bytes: 10,
+            max_age: Some(core::time::Duration::from_millis(20)),
+        };
+        let mut batched = BatchedTree::with_policy(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default(), policy).unwrap();
+        // Four one-byte records reach max_records
+        for i in 0..4u8 {
+            batched.push(&[i + 1], 1000).unwrap();
+        }
+        // Two six-byte records reach max_bytes
+        batched.push(b"sixsix", 1001).unwrap();
+        assert_eq!(batched.push(b"sixsix", 1001).unwrap(), Some(1));
+        // A lone record waits for max_age
+        batched.push(b"late", 1002).unwrap();
+        assert_eq!(batched.poll().unwrap(), None);
+        std::thread::sleep(core::time::Duration::from_millis(25));
+        assert_eq!(batched.poll().unwrap(), Some(2));
+        assert_eq!(batched.poll().unwrap(), None);
+        batched.push(b"last", 1003).unwrap();
+        batched.seal().unwrap();
+
+        let stats = batched.stats();
+        assert_eq!((stats.batches, stats.records, stats.bytes), (4, 8, 24));
+        assert_eq!((stats.min_records, stats.max_records), (1, 4));
+        assert_eq!(
+            (stats.closed_by_records, stats.closed_by_bytes, stats.closed_by_age, stats.closed_manually),
+            (1, 1, 1, 1)
+        );
+        assert_eq!(stats.mean_records(), 2.0);
+        assert_eq!(batched.tree().leaf_count(), 4);
+    }
+}
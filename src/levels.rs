@@ -0,0 +1,230 @@
+//! Level-by-level node storage
+//!
+//! A tree keeps its nodes one vector per level, leaves first and root last.
+//! Appending a leaf then pushes at most one node onto the end of each level
+//! instead of shifting every level above the leaves along a single vector.
+//!
+//! Indexing and iteration still follow the flat level-by-level order that
+//! saved states, snapshots and paged trees use, so a node's flat offset is
+//! the same as before.
+
+use crate::snapshot::SharedVec;
+
+#[cfg(feature = "no-std")]
+use alloc::vec::Vec;
+#[cfg(not(feature = "no-std"))]
+use std::vec::Vec;
+
+/// Nodes of a tree, one copy-on-write vector per level
+///
+/// Snapshots share every level; a write after a snapshot copies only the
+/// chunk of the level it touches.
+pub(crate) struct Levels<T> {
+    levels: Vec<SharedVec<T>>,
+    /// Returned for levels above the top
+    empty: SharedVec<T>,
+}
+
+impl<T> Levels<T> {
+    /// Create an empty store
+    pub(crate) fn new() -> Self {
+        Self { levels: Vec::new(), empty: SharedVec::new() }
+    }
+
+    /// Split nodes in flat level-by-level order into the levels of a tree with `leaf_count` leaves
+    ///
+    /// Nodes beyond the expected layout are kept on the last level, so
+    /// iterating returns `nodes` unchanged even for a malformed state.
+    pub(crate) fn from_flat(nodes: Vec<T>, leaf_count: usize) -> Self {
+        let mut levels = Vec::new();
+        let mut rest = nodes.into_iter();
+        let mut width = leaf_count;
+        while width > 0 && rest.len() > 0 {
+            levels.push(rest.by_ref().take(width).collect::<Vec<_>>());
+            width = if width > 1 { width.div_ceil(2) } else { 0 };
+        }
+        match levels.last_mut() {
+            Some(last) => last.extend(rest),
+            None if rest.len() > 0 => levels.push(rest.collect()),
+            None => {}
+        }
+        Self { levels: levels.into_iter().map(SharedVec::from).collect(), empty: SharedVec::new() }
+    }
+
+    /// Total number of nodes on all levels
+    pub(crate) fn len(&self) -> usize {
+        self.levels.iter().map(|level| level.len()).sum()
+    }
+
+    /// Whether no level holds a node
+    #[cfg_attr(any(not(feature = "visualization"), feature = "minimal"), allow(dead_code))]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.levels.iter().all(|level| level.is_empty())
+    }
+
+    /// Number of levels, including empty ones
+    pub(crate) fn depth(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Nodes of `level`, empty beyond the top
+    pub(crate) fn level(&self, level: usize) -> &SharedVec<T> {
+        self.levels.get(level).unwrap_or(&self.empty)
+    }
+
+    /// The leaf level
+    pub(crate) fn leaves(&self) -> &SharedVec<T> {
+        self.level(0)
+    }
+
+    /// Node at flat offset `index`
+    #[cfg_attr(feature = "minimal", allow(dead_code))]
+    pub(crate) fn get(&self, index: usize) -> Option<&T> {
+        let (level, position) = self.locate(index)?;
+        self.levels[level].get(position)
+    }
+
+    /// The last node, which is the root of a well-formed tree
+    pub(crate) fn last(&self) -> Option<&T> {
+        self.levels.iter().rev().find_map(|level| level.last())
+    }
+
+    /// All nodes in flat level-by-level order
+    #[cfg_attr(not(all(feature = "storage", feature = "std")), allow(dead_code))]
+    pub(crate) fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + '_ {
+        self.levels.iter().flat_map(|level| level.iter())
+    }
+
+    /// Nodes above the leaf level in flat order
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn upper_levels(&self) -> impl Iterator<Item = &T> + '_ {
+        self.levels.iter().skip(1).flat_map(|level| level.iter())
+    }
+
+    /// Drop every level from `depth` up
+    pub(crate) fn truncate_levels(&mut self, depth: usize) {
+        self.levels.truncate(depth);
+    }
+
+    /// Level and position of flat offset `index`
+    fn locate(&self, mut index: usize) -> Option<(usize, usize)> {
+        for (level, nodes) in self.levels.iter().enumerate() {
+            if index < nodes.len() {
+                return Some((level, index));
+            }
+            index -= nodes.len();
+        }
+        None
+    }
+}
+
+impl<T: Clone> Levels<T> {
+    /// Mutable nodes of `level`, adding empty levels below it as needed
+    pub(crate) fn level_mut(&mut self, level: usize) -> &mut SharedVec<T> {
+        while self.levels.len() <= level {
+            self.levels.push(SharedVec::new());
+        }
+        &mut self.levels[level]
+    }
+
+    /// Nodes of `level - 1` next to the mutable nodes of `level`, for rehashing a level from its children
+    pub(crate) fn parent_level_mut(&mut self, level: usize) -> (&SharedVec<T>, &mut SharedVec<T>) {
+        self.level_mut(level);
+        let (below, above) = self.levels.split_at_mut(level);
+        (&below[level - 1], &mut above[0])
+    }
+
+    /// Mutable leaf level
+    pub(crate) fn leaves_mut(&mut self) -> &mut SharedVec<T> {
+        self.level_mut(0)
+    }
+
+    /// Copy all nodes into one vector in flat level-by-level order
+    #[cfg(test)]
+    pub(crate) fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Clone for Levels<T> {
+    fn clone(&self) -> Self {
+        Self { levels: self.levels.clone(), empty: SharedVec::new() }
+    }
+}
+
+impl<T> Default for Levels<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> core::ops::Index<usize> for Levels<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.locate(index) {
+            Some((level, position)) => &self.levels[level][position],
+            None => panic!("node index {} out of range for {} nodes", index, self.len()),
+        }
+    }
+}
+
+impl<T: Clone> core::ops::IndexMut<usize> for Levels<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        match self.locate(index) {
+            Some((level, position)) => &mut self.levels[level][position],
+            None => panic!("node index {} out of range for {} nodes", index, self.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flat_round_trip() {
+        // 5 leaves: levels of 5, 3, 2 and 1 nodes
+        let nodes: Vec<usize> = (0..11).collect();
+        let levels = Levels::from_flat(nodes.clone(), 5);
+        assert_eq!(levels.depth(), 4);
+        assert_eq!(levels.level(1).to_vec(), [5, 6, 7]);
+        assert_eq!(levels.level(3).to_vec(), [10]);
+        assert_eq!(levels.to_vec(), nodes);
+        assert_eq!((levels[7], levels.get(11), levels.last()), (7, None, Some(&10)));
+
+        // Malformed layouts survive the round trip
+        for (count, leaf_count) in [(14, 5), (3, 5), (4, 0), (0, 0)] {
+            let nodes: Vec<usize> = (0..count).collect();
+            assert_eq!(Levels::from_flat(nodes.clone(), leaf_count).to_vec(), nodes);
+        }
+    }
+
+    #[test]
+    fn test_levels_are_copied_on_write() {
+        let mut levels = Levels::from_flat((0..6).collect::<Vec<usize>>(), 3);
+        let snapshot = levels.clone();
+        levels.leaves_mut().push(9);
+        levels[5] = 8;
+        assert_eq!(snapshot.to_vec(), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(levels.to_vec(), [0, 1, 2, 9, 3, 8, 5]);
+        assert!(levels.level(2).shares_chunk(snapshot.level(2), 0));
+        assert!(!levels.leaves().shares_chunk(snapshot.leaves(), 0));
+    }
+
+    #[test]
+    fn test_write_after_clone_copies_only_its_chunk() {
+        let mut levels = Levels::from_flat((0..1000).collect::<Vec<usize>>(), 1000);
+        let snapshot = levels.clone();
+        levels[100] = 0;
+        levels.leaves_mut().push(1000);
+        assert_eq!(snapshot.leaves()[100], 100);
+        assert_eq!(snapshot.leaves().len(), 1000);
+        for index in [300, 600] {
+            assert!(levels.leaves().shares_chunk(snapshot.leaves(), index));
+        }
+        for index in [100, 999] {
+            assert!(!levels.leaves().shares_chunk(snapshot.leaves(), index));
+        }
+    }
+}
@@ -29,23 +29,143 @@
 #[cfg(feature = "no-std")]
 extern crate alloc;
 
+#[cfg(all(feature = "server", not(feature = "minimal")))]
+pub mod access_control;
+#[cfg(not(feature = "minimal"))]
+pub mod accumulator;
+#[cfg(not(feature = "minimal"))]
+pub mod admission;
+#[cfg(not(feature = "minimal"))]
+pub mod aggregate;
+#[cfg(feature = "std")]
+pub mod audit_log;
+#[cfg(not(feature = "minimal"))]
+pub mod auditor;
+#[cfg(feature = "async")]
+pub mod async_writer;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "blinding")]
+pub mod blinding;
+#[cfg(all(feature = "storage", feature = "std", not(feature = "no-std")))]
+pub mod bulk_load;
+#[cfg(not(feature = "minimal"))]
+pub mod cancellation;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub(crate) mod codec;
+#[cfg(not(feature = "minimal"))]
+pub mod compare;
+#[cfg(feature = "compat")]
+pub mod compat;
+#[cfg(feature = "coordination")]
+pub mod coordination;
 pub mod config;
+#[cfg(feature = "connectors")]
+pub mod connectors;
 pub mod constructors;
+#[cfg(not(feature = "minimal"))]
+pub mod crdt;
+pub mod dedup;
 pub mod delta;
+pub mod display;
+#[cfg(not(feature = "minimal"))]
+pub mod delta_log;
+#[cfg(not(feature = "minimal"))]
+pub mod dual_root;
+pub mod encoding;
+#[cfg(feature = "encoding-strings")]
+pub mod encoding_strings;
+#[cfg(feature = "distributed-storage")]
+pub mod distributed_storage;
 pub mod error;
+#[cfg(feature = "std")]
+pub mod event_emitter;
+#[cfg(feature = "std")]
+pub mod facade;
+#[cfg(feature = "storage")]
+pub mod field_encryption;
+#[cfg(all(feature = "file-storage", not(feature = "no-std")))]
+pub mod file_lock;
+#[cfg(not(feature = "minimal"))]
+pub mod freshness;
 pub mod hash;
+#[cfg(not(feature = "minimal"))]
+pub mod interceptor;
+#[cfg(feature = "trillian")]
+pub mod interop;
+#[cfg(feature = "storage")]
+pub mod index_store;
+#[cfg(not(feature = "minimal"))]
+pub mod leaf_batch;
+#[cfg(all(feature = "storage", feature = "std"))]
+pub mod lazy_tree;
+pub(crate) mod levels;
+#[cfg(not(feature = "minimal"))]
+pub mod manifest;
+#[cfg(feature = "storage")]
+pub mod migrating_storage;
+#[cfg(not(feature = "minimal"))]
+pub mod mutation;
 pub mod node;
+#[cfg(feature = "std")]
+pub mod notary;
 pub mod operations;
+#[cfg(all(feature = "server", not(feature = "minimal")))]
+pub mod openapi;
+#[cfg(feature = "storage")]
+pub(crate) mod pipe;
+#[cfg(feature = "std")]
+pub mod progress;
 pub mod proof;
+#[cfg(not(feature = "minimal"))]
+pub mod proof_archive;
+#[cfg(feature = "std")]
+pub mod proof_cache;
+pub mod proof_compression;
 pub mod proofs;
+#[cfg(feature = "encoding-strings")]
+pub mod receipt;
+pub mod query_plan;
+pub mod redaction;
 pub mod rebuild;
+#[cfg(not(feature = "minimal"))]
+pub mod rehash;
+#[cfg(not(feature = "minimal"))]
+pub mod reindex;
+pub mod root_history;
+#[cfg(not(feature = "minimal"))]
+pub mod replay;
+#[cfg(all(feature = "storage", feature = "std"))]
+pub mod retrying_storage;
 pub mod security;
+#[cfg(not(feature = "minimal"))]
+pub mod shuffle;
+#[cfg(all(feature = "server", not(feature = "minimal")))]
+pub mod server;
+pub mod snapshot;
+#[cfg(not(feature = "minimal"))]
+pub mod snapshot_export;
 pub mod sparse_index;
+pub mod spec;
+#[cfg(feature = "storage")]
+pub mod state_codec;
 pub mod storage;
+#[cfg(not(feature = "minimal"))]
+pub mod streaming;
+#[cfg(not(feature = "minimal"))]
+pub mod subtree;
+#[cfg(feature = "std")]
+pub mod syslog;
+#[cfg(not(feature = "minimal"))]
+pub mod transition;
 pub mod tree;
 pub mod traits;
 pub mod validation;
+#[cfg(not(feature = "minimal"))]
 pub mod visualization;
+#[cfg(not(feature = "minimal"))]
+pub mod witness;
 
 // Re-export tree configuration and types
 
@@ -59,21 +179,132 @@ pub mod clockhash;
 pub use clockhash::ClockHashAdapter;
 
 // Re-exports
+#[cfg(not(feature = "minimal"))]
+pub use accumulator::{Accumulator, HashChainAccumulator, MerkleAccumulator};
+#[cfg(all(feature = "rsa-accumulator", not(feature = "minimal")))]
+pub use accumulator::RsaAccumulator;
+#[cfg(not(feature = "minimal"))]
+pub use admission::{AdmissionController, AdmissionPolicy, AdmissionStats, OverQuota};
+#[cfg(not(feature = "minimal"))]
+pub use aggregate::BucketCount;
+#[cfg(all(feature = "std", not(feature = "minimal")))]
+pub use aggregate::{PrivacyPolicy, PrivateBucket};
+#[cfg(not(feature = "minimal"))]
+pub use auditor::{EquivocationProof, ObservationSource, RootAuditor, RootObservation};
+#[cfg(feature = "async")]
+pub use async_writer::{AsyncTreeWriter, InsertFuture, WriterStats};
+#[cfg(all(feature = "storage", feature = "std", not(feature = "no-std")))]
+pub use bulk_load::{BulkLoadOptions, BulkLoadProgress, CsvSchema};
+#[cfg(feature = "blinding")]
+pub use blinding::{verify_blinded, BlindingFactor};
+#[cfg(not(feature = "minimal"))]
+pub use cancellation::CancellationToken;
+#[cfg(feature = "chaos")]
+pub use chaos::{crash_consistency_harness, ChaosConfig, ChaosStorage};
+#[cfg(not(feature = "minimal"))]
+pub use compare::TreeDifference;
+#[cfg(feature = "compat")]
+pub use compat::{cross_validate, merkle_light_root, CompatTarget, MerkleLightHasher};
+#[cfg(not(feature = "minimal"))]
+pub use crdt::{CrdtOp, CrdtTree, Hlc};
+pub use dedup::DedupStats;
+pub use display::{ProofSummary, RootHash};
+#[cfg(not(feature = "minimal"))]
+pub use delta_log::{DeltaEntry, DeltaLog};
+#[cfg(not(feature = "minimal"))]
+pub use dual_root::DualRootTree;
 pub use error::ChronoMerkleError;
+#[cfg(feature = "std")]
+pub use event_emitter::{EventEmitter, TreeEvent, WebhookConfig, WebhookEmitter};
+#[cfg(feature = "storage")]
+pub use field_encryption::DataCipher;
+#[cfg(feature = "encrypted-storage")]
+pub use field_encryption::{Aes256GcmCipher, DataKey};
+#[cfg(not(feature = "minimal"))]
+pub use freshness::{Freshness, SkewPolicy};
 #[cfg(feature = "blake3-hash")]
 pub use hash::Blake3Hasher;
 pub use hash::HashFunction;
-pub use node::{Node, NodeType};
+pub use hash::{Digest, Fixed, FixedHasher};
+#[cfg(not(feature = "minimal"))]
+pub use interceptor::{InsertInterceptor, PendingInsert};
+#[cfg(not(feature = "minimal"))]
+pub use leaf_batch::{BatchCloseReason, BatchPolicy, BatchRecordProof, BatchStats, BatchedTree, LeafBatch};
+#[cfg(not(feature = "minimal"))]
+pub use manifest::TreeManifest;
+#[cfg(not(feature = "minimal"))]
+pub use mutation::{verify_update_proof, CommitReport, Mutation, MutationBatch, UpdateProof};
+pub use node::{Node, NodeType, ReverseDelta};
+pub use operations::{InsertReceipt, PrehashedLeaf};
 pub use proof::{ChronoProof, ProofStep};
-pub use security::{SecurityEvent, SecurityEventType, SecurityLevel, SecurityLogger, NoOpLogger};
+#[cfg(not(feature = "minimal"))]
+pub use proof_archive::{ArchiveEntry, ProofArchive};
+#[cfg(feature = "std")]
+pub use progress::ProgressObserver;
+#[cfg(feature = "std")]
+pub use proof_cache::{ProofCache, ProofCacheStats};
+pub use proof_compression::CompressedProof;
+#[cfg(feature = "encoding-strings")]
+pub use receipt::PrintableReceipt;
+pub use query_plan::{QueryPlan, RangeStrategy};
+pub use redaction::{LeafData, Redaction, RedactionSigner};
+#[cfg(not(feature = "minimal"))]
+pub use rehash::RehashReport;
+#[cfg(not(feature = "minimal"))]
+pub use reindex::{IndexDiscrepancies, IndexRebuildReport};
+pub use root_history::{RootHistory, RootRecord};
+#[cfg(not(feature = "minimal"))]
+pub use replay::{ReplayDivergence, ReplayReport};
+#[cfg(all(feature = "server", not(feature = "minimal")))]
+pub use access_control::{ApiKeyAuthenticator, Authenticator, BearerAuthenticator, RateLimit, TokenVerifier};
+#[cfg(all(feature = "server", not(feature = "minimal")))]
+pub use server::{AggregateResponse, ProofResponse, RangeResponse, Request, Response, RootResponse, TreeService};
+pub use security::{CorrelationId, SecurityEvent, SecurityEventType, SecurityLevel, SecurityLogger, NoOpLogger, SamplingLogger, SamplingPolicy};
 #[cfg(feature = "std")]
 pub use security::StdErrLogger;
+#[cfg(feature = "std")]
+pub use audit_log::TreeBackedLogger;
+#[cfg(feature = "std")]
+pub use facade::{BlockHeader, TransactionLog, TxId, TxReceipt};
+#[cfg(feature = "std")]
+pub use notary::{Anchor, AnchorSink, Notary, NotaryReceipt};
+#[cfg(feature = "std")]
+pub use syslog::{Facility, SyslogFormat, SyslogLogger};
+#[cfg(not(feature = "minimal"))]
+pub use shuffle::{ShuffleProof, ShuffledTree};
+pub use snapshot::TreeSnapshot;
+#[cfg(not(feature = "minimal"))]
+pub use snapshot_export::{verify_snapshot, RangeMultiproof, SnapshotLeaf, SnapshotPackage, SnapshotSigner};
 pub use sparse_index::SparseIndex;
+pub use spec::SPEC_VERSION;
+#[cfg(not(feature = "minimal"))]
+pub use streaming::StreamingRoot;
+#[cfg(all(feature = "storage", feature = "std"))]
+pub use lazy_tree::LazyTree;
+#[cfg(feature = "distributed-storage")]
+pub use distributed_storage::{
+    ConflictResolution, DistributedConfig, DistributedStorage, RebalanceReport, ReplicationLag, ReplicationMode,
+};
+#[cfg(feature = "connectors")]
+pub use connectors::{HeadPublisher, HeadSink, Ingestor, RecordSource, SourceRecord, TreeHead};
+#[cfg(feature = "coordination")]
+pub use coordination::{FencedStorage, FencingToken, LeaderElector, LeaseStore};
+#[cfg(feature = "storage")]
+pub use migrating_storage::{MigratingStorage, MigrationMode, MigrationProgress};
+#[cfg(all(feature = "storage", feature = "std"))]
+pub use retrying_storage::{RetryPolicy, RetryingStorage};
 #[cfg(feature = "storage")]
-pub use storage::MemoryStorage;
+pub use storage::{BackendHealth, GcReport, MemoryStorage, NamespacedStorage, StateCodec, StateDiff, UpgradeReport, Version};
 #[cfg(all(feature = "storage", feature = "std", not(feature = "no-std")))]
 pub use storage::FileStorage;
+#[cfg(all(feature = "file-storage", not(feature = "no-std")))]
+pub use file_lock::LockMode;
+#[cfg(not(feature = "minimal"))]
+pub use transition::{verify_transition, StateTransitionProof};
 pub use tree::{ChronoMerkleTree, TreeConfig};
+pub use config::{LeafHashMode, TuningProfile};
+#[cfg(not(feature = "minimal"))]
+pub use witness::WitnessUpdater;
 
 /// Type alias for the most common ChronoMerkleTree configuration.
 ///
@@ -0,0 +1,203 @@
+//! Integrity manifests for backed-up trees
+//!
+//! A [`TreeManifest`] records a tree's root, leaf count, per-level node counts
+//! and a checksum chained over every leaf hash and timestamp. Keeping it next
+//! to a backup lets an operator confirm with
+//! [`ChronoMerkleTree::verify_against_manifest`] that a restored tree matches
+//! the original exactly, including every internal node.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::node::{Node, NodeType};
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{format, string::{String, ToString}, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::vec::Vec;
+
+/// Domain separator seeding the leaf checksum chain
+const CHECKSUM_SEED: &[u8] = b"chrono-merkle-manifest-v1";
+
+/// Summary of a tree for integrity checks after restore
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeManifest<H> {
+    /// Root hash, `None` for an empty tree
+    pub root: Option<H>,
+    /// Number of leaves
+    pub leaf_count: usize,
+    /// Number of nodes on each level, leaves first
+    pub level_counts: Vec<usize>,
+    /// Hash chained over every leaf hash and timestamp in index order
+    pub leaf_checksum: H,
+}
+
+/// Node counts per level for `leaf_count` leaves, leaves first
+fn level_counts(leaf_count: usize) -> Vec<usize> {
+    let mut counts = Vec::new();
+    let mut count = leaf_count;
+    while count > 0 {
+        counts.push(count);
+        if count == 1 {
+            break;
+        }
+        count = count.div_ceil(2);
+    }
+    counts
+}
+
+fn mismatch(field: &str, reason: impl Into<String>) -> ChronoMerkleError {
+    ChronoMerkleError::ManifestMismatch {
+        field: field.to_string(),
+        reason: reason.into(),
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Summarize the tree for a later [`verify_against_manifest`](Self::verify_against_manifest)
+    pub fn generate_manifest(&self) -> TreeManifest<H> {
+        TreeManifest {
+            root: self.root(),
+            leaf_count: self.leaf_count,
+            level_counts: level_counts(self.leaf_count),
+            leaf_checksum: self.leaf_checksum(),
+        }
+    }
+
+    /// Check that this tree matches `manifest` exactly
+    ///
+    /// Verifies the leaf count, level layout and leaf checksum, that every
+    /// retained leaf data hashes to its leaf hash, that every internal node is
+    /// the hash of its children, and the root. Fails with
+    /// [`ChronoMerkleError::ManifestMismatch`] naming the first difference.
+    pub fn verify_against_manifest(&self, manifest: &TreeManifest<H>) -> Result<()> {
+        if self.leaf_count != manifest.leaf_count {
+            return Err(mismatch(
+                "leaf_count",
+                format!("expected {}, found {}", manifest.leaf_count, self.leaf_count),
+            ));
+        }
+        let counts = level_counts(self.leaf_count);
+        if counts != manifest.level_counts {
+            return Err(mismatch(
+                "level_counts",
+                format!("expected {:?}, found {:?}", manifest.level_counts, counts),
+            ));
+        }
+        let total: usize = counts.iter().sum();
+        if self.nodes.len() != total {
+            return Err(mismatch(
+                "level_counts",
+                format!("layout needs {} nodes, tree holds {}", total, self.nodes.len()),
+            ));
+        }
+
+        for (index, node) in self.nodes.leaves().iter().enumerate() {
+            let NodeType::Leaf { hash, data, .. } = &node.node_type else {
+                return Err(mismatch("leaves", format!("node {} is not a leaf", index)));
+            };
+            if let Some(data) = data {
+                if self.hasher.hash(data) != *hash {
+                    return Err(mismatch("leaves", format!("data of leaf {} does not match its hash", index)));
+                }
+            }
+        }
+        if self.leaf_checksum() != manifest.leaf_checksum {
+            return Err(mismatch("leaf_checksum", "leaf hashes or timestamps differ"));
+        }
+
+        let mut start = 0;
+        for window in counts.windows(2) {
+            let (count, parents) = (window[0], window[1]);
+            for i in 0..parents {
+                let left = &self.nodes[start + 2 * i];
+                let right = self.nodes.get(start + 2 * i + 1).filter(|_| 2 * i + 1 < count).unwrap_or(left);
+                let parent: &Node<H> = &self.nodes[start + count + i];
+                if self.hasher.hash_pair(&left.hash(), &right.hash()) != parent.hash() {
+                    return Err(mismatch(
+                        "internal_nodes",
+                        format!("node {} is not the hash of its children", start + count + i),
+                    ));
+                }
+            }
+            start += count;
+        }
+
+        if self.root() != manifest.root {
+            return Err(mismatch("root", "root hash differs"));
+        }
+        Ok(())
+    }
+
+    /// Hash chained over every leaf hash and timestamp
+    fn leaf_checksum(&self) -> H {
+        let mut checksum = self.hasher.hash(CHECKSUM_SEED);
+        for node in self.nodes.leaves() {
+            let (timestamp, _) = node.timestamp_info();
+            checksum = self.hasher.hash_multiple(&[
+                checksum.as_ref(),
+                node.hash().as_ref(),
+                &crate::encoding::encode_u64(timestamp),
+            ]);
+        }
+        checksum
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    fn tree(count: u64) -> ChronoMerkleTree {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..count {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn test_restored_tree_matches_manifest() {
+        let original = tree(5);
+        let manifest = original.generate_manifest();
+        assert_eq!(manifest.level_counts, vec![5, 3, 2, 1]);
+
+        let (leaves, deltas) = original.extract_leaves_and_deltas();
+        let restored: ChronoMerkleTree = ChronoMerkleTree::reconstruct_from_leaves_and_deltas(
+            leaves,
+            deltas,
+            crate::hash::Blake3Hasher,
+            crate::config::TreeConfig::default(),
+        )
+        .unwrap();
+        restored.verify_against_manifest(&manifest).unwrap();
+        tree(0).verify_against_manifest(&tree(0).generate_manifest()).unwrap();
+    }
+
+    #[test]
+    fn test_detects_differences() {
+        let manifest = tree(4).generate_manifest();
+        let err = tree(3).verify_against_manifest(&manifest).unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::ManifestMismatch { ref field, .. } if field == "leaf_count"));
+
+        let mut tampered = tree(4);
+        tampered.nodes[5] = Node::internal([0u8; 32], [0u8; 32], [0u8; 32], (0, 0));
+        let err = tampered.verify_against_manifest(&manifest).unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::ManifestMismatch { ref field, .. } if field == "internal_nodes"));
+
+        let mut reordered = tree(4);
+        let leaves = reordered.nodes.leaves_mut();
+        let first = leaves[0].clone();
+        leaves[0] = leaves[1].clone();
+        leaves[1] = first;
+        let err = reordered.verify_against_manifest(&manifest).unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::ManifestMismatch { ref field, .. } if field == "leaf_checksum"));
+    }
+}
@@ -0,0 +1,187 @@
+//! Zero-downtime migration between storage backends
+//!
+//! [`MigratingStorage`] fronts an old and a new backend during a move. In
+//! [`MigrationMode::DualWrite`] every write goes to both and reads prefer the old
+//! backend; in [`MigrationMode::NewOnly`] writes go to the new backend only and
+//! reads fall back to the old one for keys not yet copied. [`migrate_all`]
+//! backfills the new backend.
+//!
+//! [`migrate_all`]: MigratingStorage::migrate_all
+
+use crate::error::Result;
+use crate::storage::StorageBackend;
+
+#[cfg(feature = "no-std")]
+use alloc::{string::String, vec::Vec};
+
+/// Write strategy of a [`MigratingStorage`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationMode {
+    /// Write to both backends; read from the old backend first
+    DualWrite,
+    /// Write to the new backend only; read from it first, falling back to the old one
+    NewOnly,
+}
+
+/// Progress of a [`MigratingStorage::migrate_all`] run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationProgress {
+    /// Keys in the old backend
+    pub total: usize,
+    /// Keys examined so far
+    pub processed: usize,
+    /// Keys copied to the new backend
+    pub copied: usize,
+    /// Keys skipped because the new backend already had them
+    pub skipped: usize,
+}
+
+/// Storage wrapper migrating data from `Old` to `New`
+pub struct MigratingStorage<Old, New> {
+    old: Old,
+    new: New,
+    mode: MigrationMode,
+}
+
+impl<Old: StorageBackend, New: StorageBackend> MigratingStorage<Old, New> {
+    /// Start migrating from `old` to `new`
+    pub fn new(old: Old, new: New, mode: MigrationMode) -> Self {
+        Self { old, new, mode }
+    }
+
+    /// Get the current mode
+    pub fn mode(&self) -> MigrationMode {
+        self.mode
+    }
+
+    /// Switch the write strategy, e.g. to `NewOnly` once dual writes are verified
+    pub fn set_mode(&mut self, mode: MigrationMode) {
+        self.mode = mode;
+    }
+
+    /// Get the old backend
+    pub fn old(&self) -> &Old {
+        &self.old
+    }
+
+    /// Get the new backend
+    pub fn new_backend(&self) -> &New {
+        &self.new
+    }
+
+    /// Finish the migration and return the new backend
+    pub fn into_new(self) -> New {
+        self.new
+    }
+
+    /// Copy every key missing from the new backend, reporting progress after each key
+    ///
+    /// Keys already present in the new backend are assumed current, since
+    /// writes made during the migration reach the new backend in both modes.
+    pub fn migrate_all(&mut self, mut progress: impl FnMut(&MigrationProgress)) -> Result<MigrationProgress> {
+        let keys = self.old.list_keys()?;
+        let mut state = MigrationProgress {
+            total: keys.len(),
+            ..MigrationProgress::default()
+        };
+        for key in keys {
+            if self.new.exists(&key)? {
+                state.skipped += 1;
+            } else if let Some(data) = self.old.load(&key)? {
+                self.new.save(&key, &data)?;
+                state.copied += 1;
+            }
+            state.processed += 1;
+            progress(&state);
+        }
+        Ok(state)
+    }
+}
+
+impl<Old: StorageBackend, New: StorageBackend> StorageBackend for MigratingStorage<Old, New> {
+    fn save(&mut self, key: &str, data: &[u8]) -> Result<()> {
+        if self.mode == MigrationMode::DualWrite {
+            self.old.save(key, data)?;
+        }
+        self.new.save(key, data)
+    }
+
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.mode {
+            MigrationMode::DualWrite => match self.old.load(key)? {
+                Some(data) => Ok(Some(data)),
+                None => self.new.load(key),
+            },
+            MigrationMode::NewOnly => match self.new.load(key)? {
+                Some(data) => Ok(Some(data)),
+                None => self.old.load(key),
+            },
+        }
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        // Delete from both, otherwise reads would fall back to the stale copy
+        self.old.delete(key)?;
+        self.new.delete(key)
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>> {
+        let mut keys = self.old.list_keys()?;
+        keys.extend(self.new.list_keys()?);
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        Ok(self.new.exists(key)? || self.old.exists(key)?)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use crate::ChronoMerkleTree;
+
+    #[test]
+    fn test_dual_write_then_backfill() {
+        let mut old = MemoryStorage::new();
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert(b"data1", 1000).unwrap();
+        tree.save_state(&mut old, "legacy").unwrap();
+
+        let mut storage = MigratingStorage::new(old, MemoryStorage::new(), MigrationMode::DualWrite);
+        tree.insert(b"data2", 1001).unwrap();
+        tree.save_state(&mut storage, "current").unwrap();
+        assert!(storage.old().exists("current").unwrap());
+        assert!(storage.new_backend().exists("current").unwrap());
+        assert!(!storage.new_backend().exists("legacy").unwrap());
+
+        let mut updates = 0;
+        let report = storage.migrate_all(|_| updates += 1).unwrap();
+        assert_eq!((report.total, report.copied, report.skipped), (2, 1, 1));
+        assert_eq!(updates, 2);
+
+        let new = storage.into_new();
+        let restored: ChronoMerkleTree =
+            ChronoMerkleTree::load_state(&new, "legacy", crate::hash::Blake3Hasher, crate::security::NoOpLogger)
+                .unwrap();
+        assert_eq!(restored.leaf_count(), 1);
+    }
+
+    #[test]
+    fn test_new_only_reads_fall_back_to_old() {
+        let mut old = MemoryStorage::new();
+        old.save("a", b"old").unwrap();
+        let mut storage = MigratingStorage::new(old, MemoryStorage::new(), MigrationMode::NewOnly);
+        storage.save("b", b"new").unwrap();
+
+        assert_eq!(storage.load("a").unwrap(), Some(b"old".to_vec()));
+        assert!(!storage.old().exists("b").unwrap());
+        assert_eq!(storage.list_keys().unwrap(), vec!["a".to_string(), "b".to_string()]);
+
+        storage.delete("a").unwrap();
+        assert!(!storage.exists("a").unwrap());
+    }
+}
@@ -0,0 +1,531 @@
+//! Batches of leaf updates, inserts and deletes applied in one commit
+//!
+//! A [`MutationBatch`] collects changes to a tree and
+//! [`ChronoMerkleTree::commit`] applies them together. Rather than
+//! recomputing the path of each changed leaf in turn, the commit marks the
+//! changed leaves dirty and rehashes, level by level, only the parents of
+//! dirty nodes, so a node above several changed leaves is hashed once per
+//! commit and untouched subtrees keep their cached hashes.
+//!
+//! Leaves are never removed. A deleted leaf is replaced by a marker hashing
+//! to `hash(DELETED_LEAF_DOMAIN || old leaf hash)` that holds no data and
+//! keeps the old timestamp, so leaf indices and the proofs of other leaves'
+//! positions stay valid.
+//!
+//! [`ChronoMerkleTree::update_leaf`] replaces a single leaf and returns an
+//! [`UpdateProof`]: inclusion proofs of the old and new leaf hash sharing
+//! their siblings, which [`verify_update_proof`] checks against the roots
+//! before and after the update.
+
+use crate::config::LeafHashMode;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::node::Node;
+use crate::operations::InsertReceipt;
+use crate::proof::{verify_proof_with_size, ChronoProof};
+use crate::security::SecurityLogger;
+#[cfg(not(feature = "minimal"))]
+use crate::security::{SecurityEventType, SecurityLevel};
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::vec::Vec;
+
+/// Domain separator of deleted leaf hashes
+pub const DELETED_LEAF_DOMAIN: &[u8] = b"chrono-merkle-deleted-leaf-v1";
+
+/// One change in a [`MutationBatch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mutation {
+    /// Replace the data and timestamp of leaf `index`
+    Update {
+        /// Leaf to replace
+        index: usize,
+        /// New data
+        data: Vec<u8>,
+        /// New timestamp
+        timestamp: u64,
+    },
+    /// Append a leaf
+    Insert {
+        /// Data of the leaf
+        data: Vec<u8>,
+        /// Timestamp of the leaf
+        timestamp: u64,
+    },
+    /// Replace leaf `index` by a deletion marker
+    Delete {
+        /// Leaf to delete
+        index: usize,
+    },
+}
+
+/// Changes applied together by [`ChronoMerkleTree::commit`]
+///
+/// Mutations apply in the order they were added; an index may refer to a
+/// leaf inserted earlier in the same batch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MutationBatch {
+    mutations: Vec<Mutation>,
+}
+
+impl MutationBatch {
+    /// Create an empty batch
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the data and timestamp of leaf `index`
+    pub fn update(&mut self, index: usize, data: impl Into<Vec<u8>>, timestamp: u64) -> &mut Self {
+        self.mutations.push(Mutation::Update { index, data: data.into(), timestamp });
+        self
+    }
+
+    /// Append a leaf
+    pub fn insert(&mut self, data: impl Into<Vec<u8>>, timestamp: u64) -> &mut Self {
+        self.mutations.push(Mutation::Insert { data: data.into(), timestamp });
+        self
+    }
+
+    /// Replace leaf `index` by a deletion marker
+    pub fn delete(&mut self, index: usize) -> &mut Self {
+        self.mutations.push(Mutation::Delete { index });
+        self
+    }
+
+    /// Mutations in order
+    pub fn mutations(&self) -> &[Mutation] {
+        &self.mutations
+    }
+
+    /// Number of mutations
+    pub fn len(&self) -> usize {
+        self.mutations.len()
+    }
+
+    /// Whether the batch holds no mutation
+    pub fn is_empty(&self) -> bool {
+        self.mutations.is_empty()
+    }
+}
+
+/// Outcome of a [`ChronoMerkleTree::commit`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitReport<H> {
+    /// Root before the commit
+    pub old_root: Option<H>,
+    /// Root after the commit
+    pub new_root: Option<H>,
+    /// Existing leaves replaced, by update or deletion
+    pub changed_leaves: usize,
+    /// Leaves appended
+    pub inserted_leaves: usize,
+    /// Internal nodes rehashed
+    pub nodes_rehashed: usize,
+}
+
+/// Proof that one leaf was replaced and nothing else changed, returned by [`ChronoMerkleTree::update_leaf`]
+///
+/// Replacing a leaf leaves every sibling on its path unchanged, so the proofs
+/// of the old and the new leaf share their siblings. They differ only where
+/// the path pairs a node with itself at the end of an odd level.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone)]
+pub struct UpdateProof<H> {
+    /// Number of leaves before and after the update
+    pub tree_size: usize,
+    /// Hash of the leaf before the update
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub old_hash: H,
+    /// Hash of the leaf after the update
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub new_hash: H,
+    /// Timestamp of the leaf after the update
+    pub new_timestamp: u64,
+    /// Root before the update
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub old_root: H,
+    /// Root after the update
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub new_root: H,
+    /// Inclusion proof of the old leaf against `old_root`, carrying its index and old timestamp
+    pub proof: ChronoProof<H>,
+    /// Inclusion proof of the new leaf against `new_root`
+    pub new_proof: ChronoProof<H>,
+}
+
+/// Check that `proof` replaces one leaf of a tree with root `old_root`, giving `new_root`
+///
+/// Both roots must match the ones the proof names, the old and new leaf
+/// must verify with [`verify_proof_with_size`] against them for the same
+/// position in a tree of `tree_size` leaves, and the two paths must share
+/// every sibling except where a node is paired with itself. Any mismatch
+/// yields `false`.
+pub fn verify_update_proof<H, Hasher>(proof: &UpdateProof<H>, old_root: &H, new_root: &H, hasher: &Hasher) -> bool
+where
+    H: AsRef<[u8]> + Clone + Eq,
+    Hasher: HashFunction<Output = H>,
+{
+    let (old, new) = (&proof.proof, &proof.new_proof);
+    proof.old_root == *old_root
+        && proof.new_root == *new_root
+        && old.leaf_index == new.leaf_index
+        && shares_siblings(old, new, proof.tree_size)
+        && verify_proof_with_size(old, &proof.old_hash, old_root, proof.tree_size, hasher).unwrap_or(false)
+        && verify_proof_with_size(new, &proof.new_hash, new_root, proof.tree_size, hasher).unwrap_or(false)
+}
+
+/// Whether the paths of `old` and `new` have the same steps, except where the leaf's ancestor is the last node of an odd level
+fn shares_siblings<H: Eq>(old: &ChronoProof<H>, new: &ChronoProof<H>, tree_size: usize) -> bool {
+    let (mut position, mut width) = (old.leaf_index, tree_size);
+    old.path.len() == new.path.len()
+        && old.path.iter().zip(&new.path).all(|(old_step, new_step)| {
+            let paired_with_itself = position % 2 == 0 && position + 1 == width;
+            position /= 2;
+            width = width.div_ceil(2);
+            paired_with_itself || old_step == new_step
+        })
+}
+
+/// Leaf as it will be after the batch
+struct Staged<H> {
+    hash: H,
+    timestamp: u64,
+    data: Option<Vec<u8>>,
+    #[cfg_attr(feature = "minimal", allow(dead_code))]
+    deleted: bool,
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Apply every mutation of `batch`, rehashing each affected internal node once
+    ///
+    /// Inserted and updated records pass through the insert interceptors and
+    /// are validated before anything changes, so a rejected record aborts
+    /// the whole batch. A single delta and root history entry cover the
+    /// commit; deltas of commits that replace leaves cannot be rolled back
+    /// without a rebuild.
+    pub fn commit(&mut self, batch: MutationBatch) -> Result<CommitReport<H>> {
+        let mut staged: Vec<(usize, Staged<H>)> = Vec::new();
+        let mut inserted = Vec::new();
+        let mut bytes = 0;
+        let mut leaf_count = self.leaf_count;
+        for mutation in batch.mutations {
+            let (index, data, timestamp) = match mutation {
+                Mutation::Update { index, data, timestamp } => (index, Some(data), timestamp),
+                Mutation::Insert { data, timestamp } => {
+                    leaf_count += 1;
+                    (leaf_count - 1, Some(data), timestamp)
+                }
+                Mutation::Delete { index } => (index, None, 0),
+            };
+            if index >= leaf_count {
+                return Err(ChronoMerkleError::IndexOutOfBounds { index, leaf_count });
+            }
+            let leaf = match data {
+                Some(data) => {
+                    self.require_leaf_hash_mode(LeafHashMode::HashData)?;
+                    let record = self.intercept_before(&data, timestamp)?;
+                    self.validate_insert_inputs(&record.data, record.timestamp)?;
+                    bytes += record.data.len();
+                    Staged {
+                        hash: self.hasher.hash(&record.data),
+                        timestamp: record.timestamp,
+                        data: Some(record.data.into_owned()),
+                        deleted: false,
+                    }
+                }
+                None => {
+                    let (hash, timestamp) = match staged.iter().rev().find(|(staged, _)| *staged == index) {
+                        Some((_, leaf)) => (leaf.hash.clone(), leaf.timestamp),
+                        None => (self.nodes[index].hash(), self.nodes[index].timestamp_info().0),
+                    };
+                    Staged {
+                        hash: self.hasher.hash_multiple(&[DELETED_LEAF_DOMAIN, hash.as_ref()]),
+                        timestamp,
+                        data: None,
+                        deleted: true,
+                    }
+                }
+            };
+            if index >= self.leaf_count && !inserted.contains(&index) {
+                inserted.push(index);
+            }
+            staged.push((index, leaf));
+        }
+        self.check_admission(inserted.len(), bytes)?;
+
+        let old_root = self.root();
+        #[cfg(feature = "std")]
+        let old_root_for_event = old_root.clone();
+        let checkpoint = self.cancellation_checkpoint();
+        let reverse = if staged.iter().all(|(index, _)| *index >= self.leaf_count) { self.reverse_point() } else { None };
+        let old_leaf_count = self.leaf_count;
+
+        // Only the last mutation of each leaf takes effect
+        let mut last: Vec<(usize, Staged<H>)> = Vec::with_capacity(staged.len());
+        for (index, leaf) in staged.into_iter().rev() {
+            if !last.iter().any(|(seen, _)| *seen == index) {
+                last.push((index, leaf));
+            }
+        }
+        last.sort_by_key(|(index, _)| *index);
+        let delta_timestamp = last.iter().map(|(_, leaf)| leaf.timestamp).max().unwrap_or(0);
+
+        let mut changed = Vec::new();
+        let mut reindex = false;
+        for (index, leaf) in last {
+            let data = leaf.data.and_then(|data| self.retain_leaf_data(&leaf.hash, &data));
+            let node = Node::leaf(leaf.hash, leaf.timestamp, data);
+            if index < old_leaf_count {
+                reindex |= leaf.timestamp != self.nodes[index].timestamp_info().0;
+                self.release_leaf_data(index);
+                self.remove_redaction(index);
+                self.nodes[index] = node;
+                changed.push(index);
+                crate::security::log_security_event!(self.security_logger, SecurityEventType::LeafUpdate, SecurityLevel::Warning, || {
+                    crate::security::events::leaf_update(index, leaf.deleted)
+                });
+            } else {
+                self.nodes.leaves_mut().push(node);
+                self.sparse_index.insert(leaf.timestamp, self.leaf_count);
+                self.leaf_count += 1;
+            }
+        }
+        if reindex {
+            self.rebuild_sparse_index();
+        }
+
+        if let Some(&first) = changed.first() {
+            self.invalidate_reverse_deltas(first);
+        }
+
+        let changed_leaves = changed.len();
+        let refreshed = self.refresh_dirty_nodes(old_leaf_count, changed);
+        let nodes_rehashed = self.restore_if_cancelled(checkpoint, refreshed)?;
+        if old_root != self.root() {
+            self.record_root_delta(old_root.clone(), delta_timestamp, reverse);
+        }
+
+        let new_root = self.root();
+        for leaf_index in old_leaf_count..self.leaf_count {
+            let timestamp = self.nodes[leaf_index].timestamp_info().0;
+            #[cfg(any(feature = "std", not(feature = "minimal")))]
+            let hash = self.nodes[leaf_index].hash();
+            crate::security::log_security_event!(self.security_logger, SecurityEventType::LeafInsertion, SecurityLevel::Info, || {
+                crate::security::events::leaf_insertion(leaf_index, timestamp, hash.as_ref())
+            });
+            #[cfg(feature = "std")]
+            self.emit_event(|| crate::event_emitter::TreeEvent::LeafInserted {
+                index: leaf_index,
+                timestamp,
+                hash: hash.as_ref().to_vec(),
+            });
+            if let Some(new_root) = &new_root {
+                let receipt = InsertReceipt { leaf_index, timestamp, old_root: old_root.clone(), new_root: new_root.clone() };
+                self.intercept_after(&receipt);
+            }
+        }
+        #[cfg(feature = "std")]
+        self.emit_root_change(old_root_for_event);
+
+        Ok(CommitReport {
+            old_root,
+            new_root,
+            changed_leaves,
+            inserted_leaves: self.leaf_count - old_leaf_count,
+            nodes_rehashed,
+        })
+    }
+
+    /// Replace the data and timestamp of leaf `index` and prove the replacement
+    ///
+    /// The update is a [`commit`](Self::commit) of a single
+    /// [`Mutation::Update`], so it passes the insert interceptors and
+    /// validation, rehashes only the leaf's path and records a delta. The
+    /// returned [`UpdateProof`] can be checked with [`verify_update_proof`].
+    pub fn update_leaf(&mut self, index: usize, new_data: &[u8], new_timestamp: u64) -> Result<UpdateProof<H>> {
+        let proof = self.generate_proof(index)?;
+        let old_hash = self.get_leaf_hash(index)?;
+        let mut batch = MutationBatch::new();
+        batch.update(index, new_data, new_timestamp);
+        let report = self.commit(batch)?;
+        Ok(UpdateProof {
+            tree_size: self.leaf_count,
+            old_hash,
+            new_hash: self.get_leaf_hash(index)?,
+            new_timestamp: self.get_leaf_timestamp(index)?,
+            old_root: report.old_root.ok_or(ChronoMerkleError::EmptyTree)?,
+            new_root: report.new_root.ok_or(ChronoMerkleError::EmptyTree)?,
+            proof,
+            new_proof: self.generate_proof(index)?,
+        })
+    }
+
+    /// Recompute internal nodes after leaves were appended and the `changed` leaves replaced
+    ///
+    /// Returns the number of internal nodes rehashed.
+    fn refresh_dirty_nodes(&mut self, old_leaf_count: usize, changed: Vec<usize>) -> Result<usize> {
+        #[cfg(feature = "parallel")]
+        if self.config.parallel_construction {
+            self.rebuild_tree_parallel()?;
+            return Ok(self.nodes.len() - self.leaf_count);
+        }
+        if self.incremental_updates {
+            self.update_tree_dirty(old_leaf_count, changed)
+        } else {
+            self.rebuild_tree()?;
+            Ok(self.nodes.len() - self.leaf_count)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::tree::TreeConfig;
+    use crate::Blake3Hasher;
+
+    fn tree_with(incremental_updates: bool, leaves: u64) -> ChronoMerkleTree {
+        let config = TreeConfig { incremental_updates, ..Default::default() };
+        let mut tree = ChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+        for i in 0..leaves {
+            tree.insert(format!("leaf{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn test_commit_matches_full_rebuild() {
+        let mut batch = MutationBatch::new();
+        batch.update(2, b"changed".to_vec(), 1002).delete(5).insert(b"new".to_vec(), 1100).update(3, b"again".to_vec(), 1003);
+
+        let mut incremental = tree_with(true, 13);
+        let mut rebuilt = tree_with(false, 13);
+        let report = incremental.commit(batch.clone()).unwrap();
+        rebuilt.commit(batch).unwrap();
+
+        assert_eq!(incremental.root(), rebuilt.root());
+        assert_eq!(report.new_root, incremental.root());
+        assert_eq!((report.changed_leaves, report.inserted_leaves), (3, 1));
+        assert_eq!(incremental.leaf_count(), 14);
+        for index in 0..incremental.leaf_count() {
+            let proof = incremental.generate_proof(index).unwrap();
+            assert!(incremental.verify_proof(&proof).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_shared_ancestors_rehashed_once() {
+        let mut tree = tree_with(true, 16);
+        let mut batch = MutationBatch::new();
+        batch.update(0, b"a".to_vec(), 1000).update(1, b"b".to_vec(), 1001);
+        let report = tree.commit(batch).unwrap();
+        // Both leaves share every ancestor: one node per level above the leaves
+        assert_eq!(report.nodes_rehashed, 4);
+
+        let mut expected = tree_with(true, 0);
+        for i in 0..16u64 {
+            let data = match i {
+                0 => b"a".to_vec(),
+                1 => b"b".to_vec(),
+                _ => format!("leaf{}", i).into_bytes(),
+            };
+            expected.insert(&data, 1000 + i).unwrap();
+        }
+        assert_eq!(tree.root(), expected.root());
+    }
+
+    #[test]
+    fn test_delete_keeps_positions() {
+        let mut tree = tree_with(true, 4);
+        let mut batch = MutationBatch::new();
+        batch.delete(1);
+        tree.commit(batch).unwrap();
+        assert_eq!(tree.leaf_count(), 4);
+        assert_eq!(tree.get_leaf_data(1).unwrap().data(), None);
+        assert_eq!(tree.get_leaf_data(2).unwrap().data(), Some(&b"leaf2"[..]));
+
+        let mut batch = MutationBatch::new();
+        batch.update(9, b"x".to_vec(), 1000);
+        assert!(matches!(tree.commit(batch), Err(ChronoMerkleError::IndexOutOfBounds { index: 9, .. })));
+    }
+
+    #[test]
+    fn test_rollback_after_update() {
+        let mut tree = tree_with(true, 6);
+        let mut batch = MutationBatch::new();
+        batch.update(1, b"x".to_vec(), 1001);
+        tree.commit(batch).unwrap();
+        tree.rollback_to_timestamp(1003).unwrap();
+
+        assert_eq!(tree.leaf_count(), 4);
+        assert_eq!(tree.get_leaf_data(1).unwrap().data(), Some(&b"x"[..]));
+        let mut expected = tree_with(true, 0);
+        for (i, data) in [&b"leaf0"[..], b"x", b"leaf2", b"leaf3"].into_iter().enumerate() {
+            expected.insert(data, 1000 + i as u64).unwrap();
+        }
+        assert_eq!(tree.root(), expected.root());
+        for index in 0..4 {
+            assert!(tree.verify_proof(&tree.generate_proof(index).unwrap()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_update_leaf_proof() {
+        for leaves in [1, 2, 5, 7, 8] {
+            for index in 0..leaves as usize {
+                let mut tree = tree_with(true, leaves);
+                let old_root = tree.root().unwrap();
+                let deltas = tree.stored_deltas.len();
+                let proof = tree.update_leaf(index, b"replaced", 2000).unwrap();
+                let new_root = tree.root().unwrap();
+
+                assert_eq!(proof.new_hash, Blake3Hasher.hash(b"replaced"));
+                assert_eq!((proof.proof.timestamp, proof.new_timestamp), (1000 + index as u64, 2000));
+                assert!(verify_update_proof(&proof, &old_root, &new_root, &Blake3Hasher), "{} leaves, leaf {}", leaves, index);
+                assert!(!verify_update_proof(&proof, &new_root, &old_root, &Blake3Hasher));
+                assert_eq!(tree.stored_deltas.len(), deltas + 1);
+
+                let mut expected = tree_with(true, 0);
+                for i in 0..leaves {
+                    let data = if i as usize == index { b"replaced".to_vec() } else { format!("leaf{}", i).into_bytes() };
+                    expected.insert(&data, 1000 + i).unwrap();
+                }
+                assert_eq!(tree.root(), expected.root());
+            }
+        }
+    }
+
+    #[test]
+    fn test_update_proof_binds_position() {
+        let mut tree = tree_with(true, 6);
+        let old_root = tree.root().unwrap();
+        let proof = tree.update_leaf(4, b"replaced", 2000).unwrap();
+        let new_root = tree.root().unwrap();
+
+        let mut moved = proof.clone();
+        moved.proof.leaf_index = 5;
+        assert!(!verify_update_proof(&moved, &old_root, &new_root, &Blake3Hasher));
+        let mut forged = proof.clone();
+        forged.new_hash = Blake3Hasher.hash(b"other");
+        assert!(!verify_update_proof(&forged, &old_root, &new_root, &Blake3Hasher));
+        let mut resized = proof.clone();
+        resized.tree_size = 8;
+        assert!(!verify_update_proof(&resized, &old_root, &new_root, &Blake3Hasher));
+
+        // A new root in which another leaf changed as well is rejected
+        let mut tampered = tree.clone();
+        tampered.update_leaf(1, b"also replaced", 2000).unwrap();
+        let mut widened = proof;
+        widened.new_proof = tampered.generate_proof(4).unwrap();
+        widened.new_root = tampered.root().unwrap();
+        assert!(!verify_update_proof(&widened, &old_root, &widened.new_root.clone(), &Blake3Hasher));
+        assert!(matches!(tree.update_leaf(6, b"x", 2000), Err(ChronoMerkleError::IndexOutOfBounds { index: 6, .. })));
+    }
+}
@@ -6,6 +6,7 @@ use alloc::{string::ToString, sync::Arc, vec::Vec};
 
 /// Types of nodes in the ChronoMerkle tree
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Eq)]
 pub enum NodeType<H> {
     /// Leaf node containing data hash and timestamp
     Leaf {
@@ -24,6 +25,9 @@ pub enum NodeType<H> {
         base_hash: H,
         /// Timestamp when delta was applied
         timestamp: u64,
+        /// Nodes replaced by the change, for restoring the prior tree exactly
+        #[cfg_attr(feature = "serde", serde(default = "Option::default"))]
+        reverse: Option<ReverseDelta<H>>,
     },
     /// Internal node connecting two subtrees
     Internal {
@@ -38,9 +42,23 @@ pub enum NodeType<H> {
     },
 }
 
+/// State needed to undo an append without rehashing
+///
+/// Appending leaves only changes the last node of each level, so the prior
+/// tree is the current one cut back to `leaf_count` leaves with those nodes
+/// put back.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ReverseDelta<H> {
+    /// Number of leaves before the change
+    pub leaf_count: usize,
+    /// Last node of each internal level before the change, lowest level first
+    pub boundary: Vec<Node<H>>,
+}
+
 /// A node in the ChronoMerkle tree
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Node<H> {
     /// The type of this node
     pub node_type: NodeType<H>,
@@ -56,10 +74,11 @@ impl<H: Clone> Clone for NodeType<H> {
                 timestamp: *timestamp,
                 data: data.clone(),
             },
-            NodeType::Delta { delta_hash, base_hash, timestamp } => NodeType::Delta {
+            NodeType::Delta { delta_hash, base_hash, timestamp, reverse } => NodeType::Delta {
                 delta_hash: delta_hash.clone(),
                 base_hash: base_hash.clone(),
                 timestamp: *timestamp,
+                reverse: reverse.clone(),
             },
             NodeType::Internal { hash, left_hash, right_hash, timestamp_range } => NodeType::Internal {
                 hash: hash.clone(),
@@ -76,23 +95,24 @@ impl<H: AsRef<[u8]> + Clone> core::fmt::Debug for NodeType<H> {
         match self {
             NodeType::Leaf { hash, timestamp, data } => {
                 f.debug_struct("Leaf")
-                    .field("hash", &format_args!("{:?}", hash.as_ref()))
+                    .field("hash", &crate::display::ShortHex(hash.as_ref()))
                     .field("timestamp", timestamp)
                     .field("data_len", &data.as_ref().map(|d| d.len()))
                     .finish()
             }
-            NodeType::Delta { delta_hash, base_hash, timestamp } => {
+            NodeType::Delta { delta_hash, base_hash, timestamp, reverse } => {
                 f.debug_struct("Delta")
-                    .field("delta_hash", &format_args!("{:?}", delta_hash.as_ref()))
-                    .field("base_hash", &format_args!("{:?}", base_hash.as_ref()))
+                    .field("delta_hash", &crate::display::ShortHex(delta_hash.as_ref()))
+                    .field("base_hash", &crate::display::ShortHex(base_hash.as_ref()))
                     .field("timestamp", timestamp)
+                    .field("reversible", &reverse.is_some())
                     .finish()
             }
             NodeType::Internal { hash, left_hash, right_hash, timestamp_range } => {
                 f.debug_struct("Internal")
-                    .field("hash", &format_args!("{:?}", hash.as_ref()))
-                    .field("left_hash", &format_args!("{:?}", left_hash.as_ref()))
-                    .field("right_hash", &format_args!("{:?}", right_hash.as_ref()))
+                    .field("hash", &crate::display::ShortHex(hash.as_ref()))
+                    .field("left_hash", &crate::display::ShortHex(left_hash.as_ref()))
+                    .field("right_hash", &crate::display::ShortHex(right_hash.as_ref()))
                     .field("timestamp_range", timestamp_range)
                     .finish()
             }
@@ -129,6 +149,7 @@ impl<H: Clone> Node<H> {
                 delta_hash,
                 base_hash,
                 timestamp,
+                reverse: None,
             },
             children: Vec::new(),
         }
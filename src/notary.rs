@@ -0,0 +1,237 @@
+//! Document timestamping on top of a tree
+//!
+//! [`Notary`] is for applications that only need to prove a document existed
+//! at a point in time. [`Notary::notarize`] inserts the document under the
+//! current time and returns a [`NotaryReceipt`] holding its inclusion proof
+//! against the root right after the insert. Every `n` documents the root is
+//! handed to an [`AnchorSink`], such as a blockchain or a timestamping
+//! authority, so receipts can later be tied to an external record.
+//! [`Notary::verify`] confirms that a receipt's root is a past root of the log
+//! and that the document is still in it.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::ChronoProof;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+/// Proof that a document was notarized, returned by [`Notary::notarize`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone)]
+pub struct NotaryReceipt<H> {
+    /// Leaf holding the document
+    pub leaf_index: usize,
+    /// When the document was notarized (Unix timestamp)
+    pub timestamp: u64,
+    /// Hash of the document
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub leaf_hash: H,
+    /// Root right after the document was inserted
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub root: H,
+    /// Inclusion proof of the document against `root`
+    pub proof: ChronoProof<H>,
+}
+
+impl<H> NotaryReceipt<H> {
+    /// Number of leaves `root` covers
+    pub fn tree_size(&self) -> usize {
+        self.leaf_index + 1
+    }
+}
+
+/// A root recorded outside the log
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    /// Number of leaves the anchored root covers
+    pub tree_size: usize,
+    /// Reference returned by the sink, e.g. a transaction ID
+    pub reference: String,
+    /// When the root was anchored (Unix timestamp)
+    pub anchored_at: u64,
+}
+
+/// External record of roots, such as a blockchain or timestamping authority
+pub trait AnchorSink {
+    /// Record `root` covering `tree_size` leaves, returning a reference to the record
+    fn anchor(&mut self, root: &[u8], tree_size: usize) -> Result<String>;
+}
+
+/// Timestamping facade over a [`ChronoMerkleTree`]
+pub struct Notary<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    tree: ChronoMerkleTree<H, Hasher, Logger>,
+    sink: Option<(Box<dyn AnchorSink + Send + Sync>, usize)>,
+    anchors: Vec<Anchor>,
+}
+
+impl<H, Hasher, Logger> Notary<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Notarize documents into `tree`, without anchoring
+    pub fn new(tree: ChronoMerkleTree<H, Hasher, Logger>) -> Self {
+        Self {
+            tree,
+            sink: None,
+            anchors: Vec::new(),
+        }
+    }
+
+    /// Anchor the root to `sink` after every `every` documents
+    pub fn with_anchoring(mut self, sink: impl AnchorSink + Send + Sync + 'static, every: usize) -> Self {
+        self.sink = Some((Box::new(sink), every.max(1)));
+        self
+    }
+
+    /// Insert `data` under the current time and return its receipt
+    ///
+    /// A failure to anchor is returned after the document was inserted; the
+    /// next successful anchor covers it.
+    pub fn notarize(&mut self, data: &[u8]) -> Result<NotaryReceipt<H>> {
+        let timestamp = crate::security::current_timestamp();
+        let inserted = self.tree.insert_with_receipt(data, timestamp)?;
+        let receipt = NotaryReceipt {
+            leaf_index: inserted.leaf_index,
+            timestamp,
+            leaf_hash: self.tree.get_leaf_hash(inserted.leaf_index)?,
+            proof: self.tree.generate_proof(inserted.leaf_index)?,
+            root: inserted.new_root,
+        };
+        if let Some((_, every)) = &self.sink {
+            if receipt.tree_size() % every == 0 {
+                self.anchor_now()?;
+            }
+        }
+        Ok(receipt)
+    }
+
+    /// Anchor the current root now, if anchoring is configured and the tree is not empty
+    pub fn anchor_now(&mut self) -> Result<Option<&Anchor>> {
+        let (Some((sink, _)), Some(root)) = (&mut self.sink, self.tree.root()) else {
+            return Ok(None);
+        };
+        let tree_size = self.tree.leaf_count();
+        let reference = sink.anchor(root.as_ref(), tree_size)?;
+        self.anchors.push(Anchor {
+            tree_size,
+            reference,
+            anchored_at: crate::security::current_timestamp(),
+        });
+        Ok(self.anchors.last())
+    }
+
+    /// Anchors made so far, oldest first
+    pub fn anchors(&self) -> &[Anchor] {
+        &self.anchors
+    }
+
+    /// First anchor covering the receipt's document, if it was anchored yet
+    pub fn anchor_for(&self, receipt: &NotaryReceipt<H>) -> Option<&Anchor> {
+        self.anchors.iter().find(|anchor| anchor.tree_size >= receipt.tree_size())
+    }
+
+    /// Check that `receipt` was issued by this notary
+    ///
+    /// The document must still be in the log under the receipt's timestamp,
+    /// its proof must lead to the receipt's root, and that root must be the
+    /// root of the log when it held `tree_size` documents.
+    pub fn verify(&self, receipt: &NotaryReceipt<H>) -> Result<bool> {
+        if receipt.leaf_index >= self.tree.leaf_count() {
+            return Ok(false);
+        }
+        if self.tree.get_leaf_hash(receipt.leaf_index)? != receipt.leaf_hash
+            || self.tree.get_leaf_timestamp(receipt.leaf_index)? != receipt.timestamp
+            || receipt.proof.leaf_index != receipt.leaf_index
+        {
+            return Ok(false);
+        }
+        if !crate::proof::verify_proof(&receipt.proof, &receipt.leaf_hash, &receipt.root, &self.tree.hasher)? {
+            return Ok(false);
+        }
+        Ok(self.root_at_size(receipt.tree_size())? == receipt.root)
+    }
+
+    /// Check that `data` is the document `receipt` was issued for, and [`verify`](Self::verify) it
+    pub fn verify_document(&self, data: &[u8], receipt: &NotaryReceipt<H>) -> Result<bool> {
+        Ok(self.tree.hasher.hash(data) == receipt.leaf_hash && self.verify(receipt)?)
+    }
+
+    /// Root the log had when it held its first `size` leaves
+    fn root_at_size(&self, size: usize) -> Result<H> {
+        let mut level = (0..size).map(|index| self.tree.get_leaf_hash(index)).collect::<Result<Vec<_>>>()?;
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| self.tree.hasher.hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+                .collect();
+        }
+        level.pop().ok_or(ChronoMerkleError::EmptyTree)
+    }
+
+    /// Get the underlying tree
+    pub fn tree(&self) -> &ChronoMerkleTree<H, Hasher, Logger> {
+        &self.tree
+    }
+
+    /// Stop notarizing and return the tree
+    pub fn into_tree(self) -> ChronoMerkleTree<H, Hasher, Logger> {
+        self.tree
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Anchored roots and their tree sizes
+    type Entries = Vec<(Vec<u8>, usize)>;
+
+    #[derive(Clone, Default)]
+    struct Ledger(Arc<Mutex<Entries>>);
+
+    impl AnchorSink for Ledger {
+        fn anchor(&mut self, root: &[u8], tree_size: usize) -> Result<String> {
+            let mut entries = self.0.lock().unwrap();
+            entries.push((root.to_vec(), tree_size));
+            Ok(format!("tx-{}", entries.len()))
+        }
+    }
+
+    #[test]
+    fn test_notarize_and_verify() {
+        let ledger = Ledger::default();
+        let tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut notary = Notary::new(tree).with_anchoring(ledger.clone(), 3);
+        let receipts: Vec<_> = (0..7).map(|i| notary.notarize(format!("document {}", i).as_bytes()).unwrap()).collect();
+
+        // Old receipts stay valid as the log grows
+        for (i, receipt) in receipts.iter().enumerate() {
+            assert!(notary.verify(receipt).unwrap());
+            assert!(notary.verify_document(format!("document {}", i).as_bytes(), receipt).unwrap());
+        }
+        assert!(!notary.verify_document(b"document 9", &receipts[1]).unwrap());
+
+        assert_eq!(notary.anchors().len(), 2);
+        assert_eq!(notary.anchor_for(&receipts[1]).unwrap().reference, "tx-1");
+        assert_eq!(notary.anchor_for(&receipts[4]).unwrap().tree_size, 6);
+        assert!(notary.anchor_for(&receipts[6]).is_none());
+        assert_eq!(ledger.0.lock().unwrap()[0], (receipts[2].root.to_vec(), 3));
+
+        let mut forged = receipts[3].clone();
+        forged.root = receipts[4].root;
+        assert!(!notary.verify(&forged).unwrap());
+        let mut moved = receipts[3].clone();
+        moved.timestamp += 1;
+        assert!(!notary.verify(&moved).unwrap());
+    }
+}
@@ -0,0 +1,207 @@
+//! OpenAPI description of the [`TreeService`](crate::server::TreeService) API
+//!
+//! [`openapi_spec`] returns an OpenAPI 3.0 document whose `components.schemas`
+//! are JSON schemas of the response types in [`crate::server`], so clients in
+//! other languages can be generated from it. The service serves it at
+//! `GET /openapi.json`.
+//!
+//! Proofs are transported as base64 of the compressed binary encoding
+//! ([`CompressedProof::to_bytes`](crate::CompressedProof::to_bytes)), in order:
+//!
+//! 1. leaf index (LEB128 varint), leaf timestamp (u64 little-endian), step count (varint)
+//! 2. step codes, 2 bits per step, four per byte, least significant bits first:
+//!    `0` sibling on the right, `1` sibling on the left, `2` sibling equal to the
+//!    current node (not stored), `3` delta step (old and new hash stored)
+//! 3. hash length (varint), hash count (varint), then the stored hashes in path order
+//! 4. delta chain marker byte (`0` none, `1` present); when present, the chain
+//!    length (varint) followed by length-prefixed hashes
+//! 5. programmable result count (varint) and the results as a bitset, least significant bit first
+//!
+//! Hashing a node pair is `hash(left || right)`; the root is reached by applying
+//! the steps to the leaf hash.
+
+use crate::server::PROOF_ENCODING;
+
+use serde_json::{json, Value};
+
+/// JSON schema of a hex-encoded hash
+fn hex_hash() -> Value {
+    json!({ "type": "string", "pattern": "^[0-9a-f]*$", "description": "Hex-encoded hash" })
+}
+
+/// Schema of an object whose properties are all required
+fn object(description: &str, properties: Value) -> Value {
+    let required: Vec<&String> = properties.as_object().map(|p| p.keys().collect()).unwrap_or_default();
+    json!({
+        "type": "object",
+        "description": description,
+        "required": required,
+        "properties": properties,
+    })
+}
+
+fn json_response(description: &str, schema: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": { "$ref": format!("#/components/schemas/{}", schema) } } }
+    })
+}
+
+fn error_response(description: &str) -> Value {
+    json_response(description, "ErrorResponse")
+}
+
+/// Build the OpenAPI 3.0 document of the service
+pub fn openapi_spec() -> Value {
+    let timestamp = json!({ "type": "integer", "format": "int64", "minimum": 0 });
+    let index = json!({ "type": "integer", "minimum": 0 });
+
+    let schemas = json!({
+        "RootResponse": object("Current root of the tree", json!({
+            "root": { "type": "string", "pattern": "^[0-9a-f]*$", "nullable": true,
+                      "description": "Hex-encoded root hash, null for an empty tree" },
+            "leaf_count": index,
+        })),
+        "ProofResponse": object("Inclusion proof of a leaf", json!({
+            "leaf_index": index,
+            "timestamp": timestamp,
+            "leaf_hash": hex_hash(),
+            "root": hex_hash(),
+            "encoding": { "type": "string", "enum": [PROOF_ENCODING] },
+            "proof": { "type": "string", "format": "byte",
+                       "description": "Base64 (RFC 4648, padded) of the compressed binary proof encoding" },
+        })),
+        "RangeLeaf": object("Leaf matched by a range query", json!({
+            "index": index,
+            "timestamp": timestamp,
+            "hash": hex_hash(),
+        })),
+        "RangeResponse": object("Leaves with timestamps in an inclusive range", json!({
+            "start": timestamp,
+            "end": timestamp,
+            "leaves": { "type": "array", "items": { "$ref": "#/components/schemas/RangeLeaf" } },
+        })),
+        "AggregateBucket": object("Leaf count of one time bucket", json!({
+            "start": timestamp,
+            "count": { "type": "integer", "minimum": 0, "nullable": true,
+                       "description": "Leaves in the bucket, noised if epsilon is set; null if suppressed" },
+        })),
+        "AggregateResponse": object("Leaf counts per time bucket", json!({
+            "start": timestamp,
+            "end": timestamp,
+            "width": { "type": "integer", "minimum": 1 },
+            "epsilon": { "type": "number", "nullable": true,
+                         "description": "Differential privacy budget of the noise, null for exact counts" },
+            "buckets": { "type": "array", "items": { "$ref": "#/components/schemas/AggregateBucket" } },
+        })),
+        "ErrorResponse": object("Error description", json!({
+            "error": { "type": "string" },
+        })),
+    });
+
+    let timestamp_param = |name: &str, description: &str| {
+        json!({ "name": name, "in": "query", "required": true, "description": description, "schema": timestamp })
+    };
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "ChronoMerkle proof service",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/root": {
+                "get": {
+                    "operationId": "getRoot",
+                    "summary": "Get the current root",
+                    "responses": { "200": json_response("Current root", "RootResponse") },
+                }
+            },
+            "/proof/{index}": {
+                "get": {
+                    "operationId": "getProof",
+                    "summary": "Get an inclusion proof for a leaf",
+                    "parameters": [{ "name": "index", "in": "path", "required": true, "schema": index }],
+                    "responses": {
+                        "200": json_response("Inclusion proof", "ProofResponse"),
+                        "400": error_response("Malformed index"),
+                        "404": error_response("No leaf at this index"),
+                    },
+                }
+            },
+            "/range": {
+                "get": {
+                    "operationId": "getRange",
+                    "summary": "List leaves with timestamps in an inclusive range",
+                    "parameters": [
+                        timestamp_param("start", "Inclusive start timestamp"),
+                        timestamp_param("end", "Inclusive end timestamp"),
+                    ],
+                    "responses": {
+                        "200": json_response("Matching leaves", "RangeResponse"),
+                        "400": error_response("Missing or malformed bounds"),
+                    },
+                }
+            },
+            "/aggregate": {
+                "get": {
+                    "operationId": "getAggregate",
+                    "summary": "Count leaves per time bucket, with differential privacy if configured",
+                    "parameters": [
+                        timestamp_param("start", "Inclusive start timestamp"),
+                        timestamp_param("end", "Inclusive end timestamp"),
+                        { "name": "width", "in": "query", "required": true, "description": "Bucket width",
+                          "schema": { "type": "integer", "minimum": 1 } },
+                    ],
+                    "responses": {
+                        "200": json_response("Bucket counts", "AggregateResponse"),
+                        "400": error_response("Missing or malformed parameters, or too many buckets"),
+                    },
+                }
+            },
+        },
+        "components": { "schemas": schemas },
+    })
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::server::{Request, TreeService};
+    use crate::ChronoMerkleTree;
+    use std::sync::{Arc, RwLock};
+
+    /// Check that `value` has every required property of the named schema
+    fn assert_matches(spec: &Value, schema: &str, value: &Value) {
+        let schema = &spec["components"]["schemas"][schema];
+        for property in schema["required"].as_array().unwrap() {
+            assert!(value.get(property.as_str().unwrap()).is_some(), "missing {}", property);
+        }
+        assert_eq!(
+            value.as_object().unwrap().len(),
+            schema["properties"].as_object().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_spec_matches_responses() {
+        let spec = openapi_spec();
+        assert_eq!(spec["openapi"], "3.0.3");
+
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert(b"data1", 1000).unwrap();
+        let service = TreeService::new(Arc::new(RwLock::new(tree)));
+        let get = |path: &str| -> Value { serde_json::from_slice(&service.handle(&Request::get(path)).body).unwrap() };
+
+        assert_matches(&spec, "RootResponse", &get("/root"));
+        assert_matches(&spec, "ProofResponse", &get("/proof/0"));
+        let range = get("/range?start=0&end=2000");
+        assert_matches(&spec, "RangeResponse", &range);
+        assert_matches(&spec, "RangeLeaf", &range["leaves"][0]);
+        let aggregate = get("/aggregate?start=0&end=2000&width=1000");
+        assert_matches(&spec, "AggregateResponse", &aggregate);
+        assert_matches(&spec, "AggregateBucket", &aggregate["buckets"][1]);
+        assert_matches(&spec, "ErrorResponse", &get("/proof/7"));
+        assert_eq!(get("/openapi.json"), spec);
+    }
+}
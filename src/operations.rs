@@ -1,19 +1,76 @@
 //! Core tree operations for ChronoMerkleTree
 
+use crate::config::{LeafHashMode, TreeConfig};
 use crate::error::{ChronoMerkleError, Result};
 use crate::hash::HashFunction;
+use crate::levels::Levels;
 use crate::node::{Node, NodeType};
+use crate::redaction::LeafData;
+#[cfg(not(feature = "minimal"))]
+use crate::security::{SecurityEventType, SecurityLevel};
 use crate::security::SecurityLogger;
 use crate::tree::ChronoMerkleTree;
 
 #[cfg(feature = "no-std")]
-use alloc::vec::Vec;
+use alloc::{format, vec::Vec};
 #[cfg(not(feature = "no-std"))]
 use std::vec::Vec;
 
+/// Domain tag of [`ChronoMerkleTree::spec_digest`]
+const SPEC_DIGEST_DOMAIN: &[u8] = b"chrono-merkle/spec";
+
+/// A leaf hash computed outside the tree, e.g. a transaction ID
+///
+/// Inserted with [`ChronoMerkleTree::insert_prehashed`] into trees configured
+/// with [`LeafHashMode::TakeAsIs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrehashedLeaf<H> {
+    /// Leaf hash, used as is
+    pub hash: H,
+    /// Timestamp associated with the leaf
+    pub timestamp: u64,
+}
+
+impl<H> PrehashedLeaf<H> {
+    /// Create a prehashed leaf
+    pub fn new(hash: H, timestamp: u64) -> Self {
+        Self { hash, timestamp }
+    }
+}
+
+/// [`ChronoMerkleTree::spec_digest`] of a tree using `hasher` and `mode`
+pub(crate) fn spec_digest<Hasher: HashFunction>(hasher: &Hasher, mode: LeafHashMode) -> Hasher::Output {
+    let name = hasher.name().as_bytes();
+    let probe = hasher.hash_pair(&hasher.hash(b"left"), &hasher.hash(b"right"));
+    let mode = match mode {
+        LeafHashMode::HashData => 0u8,
+        LeafHashMode::TakeAsIs => 1,
+    };
+    hasher.hash_multiple(&[
+        SPEC_DIGEST_DOMAIN,
+        &crate::encoding::encode_u32(name.len() as u32),
+        name,
+        probe.as_ref(),
+        &[mode],
+    ])
+}
+
+/// Outcome of an insert, returned by [`ChronoMerkleTree::insert_with_receipt`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsertReceipt<H> {
+    /// Index of the inserted leaf
+    pub leaf_index: usize,
+    /// Timestamp of the inserted leaf
+    pub timestamp: u64,
+    /// Root before the insert (`None` for the first leaf)
+    pub old_root: Option<H>,
+    /// Root after the insert
+    pub new_root: H,
+}
+
 impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: HashFunction<Output = H> + Sync,
     Logger: SecurityLogger,
 {
@@ -24,59 +81,198 @@ where
     /// * `data` - The data to insert
     /// * `timestamp` - Timestamp associated with this data
     pub fn insert(&mut self, data: &[u8], timestamp: u64) -> Result<()> {
+        #[cfg(not(feature = "minimal"))]
+        if !self.interceptors.is_empty() {
+            return self.insert_with_receipt(data, timestamp).map(drop);
+        }
+        self.insert_record(data, timestamp)
+    }
+
+    /// Insert a new leaf and return its index and the roots around it
+    ///
+    /// Unlike reading [`leaf_count`](Self::leaf_count) and [`root`](Self::root)
+    /// after [`insert`](Self::insert), the receipt cannot observe another
+    /// writer's insert when the tree is shared behind a lock.
+    pub fn insert_with_receipt(&mut self, data: &[u8], timestamp: u64) -> Result<InsertReceipt<H>> {
+        #[cfg(not(feature = "minimal"))]
+        let record = self.intercept_before(data, timestamp)?;
+        #[cfg(not(feature = "minimal"))]
+        let (data, timestamp) = (&*record.data, record.timestamp);
+        let old_root = self.root();
+        self.insert_record(data, timestamp)?;
+        let receipt = InsertReceipt {
+            leaf_index: self.leaf_count - 1,
+            timestamp,
+            old_root,
+            new_root: self.root().ok_or(ChronoMerkleError::EmptyTree)?,
+        };
+        #[cfg(not(feature = "minimal"))]
+        self.intercept_after(&receipt);
+        Ok(receipt)
+    }
+
+    /// Validate and insert a record that already passed the interceptors
+    fn insert_record(&mut self, data: &[u8], timestamp: u64) -> Result<()> {
         // SECURITY: Validate inputs
+        self.require_leaf_hash_mode(LeafHashMode::HashData)?;
         self.validate_insert_inputs(data, timestamp)?;
+        self.check_admission(1, data.len())?;
+
+        let hash = self.hasher.hash(data);
+        self.insert_leaf(hash, timestamp, Some(data))
+    }
+
+    /// Insert a leaf whose hash was computed outside the tree
+    ///
+    /// Requires [`LeafHashMode::TakeAsIs`]. The hash becomes the leaf without
+    /// being hashed again, and no data is stored.
+    pub fn insert_prehashed(&mut self, leaf: PrehashedLeaf<H>) -> Result<()> {
+        self.require_leaf_hash_mode(LeafHashMode::TakeAsIs)?;
+        self.validate_insert_inputs(leaf.hash.as_ref(), leaf.timestamp)?;
+        self.check_admission(1, 0)?;
+        self.insert_leaf(leaf.hash, leaf.timestamp, None)
+    }
 
+    /// Append a leaf with a precomputed hash; inputs must already be validated
+    pub(crate) fn insert_leaf(&mut self, hash: H, timestamp: u64, data: Option<&[u8]>) -> Result<()> {
         // Capture the old root for delta creation
         let old_root = self.root();
+        #[cfg(feature = "std")]
+        let old_root_for_event = old_root.clone();
 
-        let hash = self.hasher.hash(data);
-        let leaf = Node::leaf(hash.clone(), timestamp, Some(data.to_vec()));
+        let checkpoint = self.cancellation_checkpoint();
+        let reverse = self.reverse_point();
+        let data = data.and_then(|data| self.retain_leaf_data(&hash, data));
+        let leaf = Node::leaf(hash.clone(), timestamp, data);
 
-        self.nodes.push(leaf);
+        self.nodes.leaves_mut().push(leaf);
         self.leaf_count += 1;
 
         let leaf_index = self.leaf_count - 1;
         self.sparse_index.insert(timestamp, leaf_index);
 
+        let refreshed = self.refresh_internal_nodes(leaf_index);
+        self.restore_if_cancelled(checkpoint, refreshed)?;
+        self.record_root_delta(old_root, timestamp, reverse);
+
+        // Log successful insertion
+        crate::security::log_security_event!(self.security_logger, SecurityEventType::LeafInsertion, SecurityLevel::Info, || {
+            crate::security::events::leaf_insertion(leaf_index, timestamp, hash.as_ref())
+        });
+
+        #[cfg(feature = "std")]
+        {
+            self.emit_event(|| crate::event_emitter::TreeEvent::LeafInserted {
+                index: leaf_index,
+                timestamp,
+                hash: hash.as_ref().to_vec(),
+            });
+            self.emit_root_change(old_root_for_event);
+        }
+
+        Ok(())
+    }
+
+    /// Insert several leaves with a single tree rebuild
+    ///
+    /// All entries are validated before any is inserted, so the batch is applied
+    /// atomically. A single delta covering the whole batch is recorded.
+    pub fn insert_batch(&mut self, entries: &[(&[u8], u64)]) -> Result<()> {
+        #[cfg(not(feature = "minimal"))]
+        if !self.interceptors.is_empty() {
+            return self.insert_intercepted_batch(entries);
+        }
+        self.insert_records(entries)
+    }
+
+    /// Insert a batch of records that already passed the interceptors
+    pub(crate) fn insert_records(&mut self, entries: &[(&[u8], u64)]) -> Result<()> {
+        self.require_leaf_hash_mode(LeafHashMode::HashData)?;
+        for (data, timestamp) in entries {
+            self.validate_insert_inputs(data, *timestamp)?;
+        }
+        let Some(&(_, last_timestamp)) = entries.last() else {
+            return Ok(());
+        };
+        self.check_admission(entries.len(), entries.iter().map(|(data, _)| data.len()).sum())?;
+
+        let old_root = self.root();
+        #[cfg(feature = "std")]
+        let old_root_for_event = old_root.clone();
+
+        let checkpoint = self.cancellation_checkpoint();
+        let reverse = self.reverse_point();
+        let old_leaf_count = self.leaf_count;
+        for (data, timestamp) in entries {
+            let hash = self.hasher.hash(data);
+            let data = self.retain_leaf_data(&hash, data);
+            self.nodes.leaves_mut().push(Node::leaf(hash, *timestamp, data));
+            self.sparse_index.insert(*timestamp, self.leaf_count);
+            self.leaf_count += 1;
+        }
+
+        let refreshed = self.refresh_internal_nodes(old_leaf_count);
+        self.restore_if_cancelled(checkpoint, refreshed)?;
+        self.record_root_delta(old_root, last_timestamp, reverse);
+
+        // The new leaves now follow the old ones at the front of `nodes`
+        #[cfg(any(feature = "std", not(feature = "minimal")))]
+        for (leaf_index, (_, timestamp)) in (old_leaf_count..).zip(entries) {
+            let hash = self.nodes[leaf_index].hash();
+            crate::security::log_security_event!(self.security_logger, SecurityEventType::LeafInsertion, SecurityLevel::Info, || {
+                crate::security::events::leaf_insertion(leaf_index, *timestamp, hash.as_ref())
+            });
+            #[cfg(feature = "std")]
+            self.emit_event(|| crate::event_emitter::TreeEvent::LeafInserted {
+                index: leaf_index,
+                timestamp: *timestamp,
+                hash: hash.as_ref().to_vec(),
+            });
+        }
+        #[cfg(feature = "std")]
+        self.emit_root_change(old_root_for_event);
+
+        Ok(())
+    }
+
+    /// Recompute internal nodes after leaves were appended
+    ///
+    /// The first `old_leaf_count` leaves must still be followed by their
+    /// internal nodes, with the new leaves after them; pass 0 when `nodes`
+    /// holds only leaves.
+    pub(crate) fn refresh_internal_nodes(&mut self, old_leaf_count: usize) -> Result<()> {
         // Use incremental update or rebuild based on configuration
+        #[cfg(feature = "parallel")]
+        if self.config.parallel_construction {
+            return self.rebuild_tree_parallel();
+        }
         if self.incremental_updates {
-            self.update_tree_incremental()?;
+            self.update_tree_incremental(old_leaf_count)
         } else {
-            #[cfg(feature = "parallel")]
-            if self.config.parallel_construction {
-                self.rebuild_tree_parallel()?;
-            } else {
-                self.rebuild_tree()?;
-            }
-            #[cfg(not(feature = "parallel"))]
-            {
-                self.rebuild_tree()?;
-            }
+            self.rebuild_tree()
         }
+    }
 
-        // Create delta if root changed and deltas are enabled
-        if self.config.enable_deltas {
-            if let Some(old_root_hash) = old_root {
-                let new_root_hash = self.root().unwrap();
-                if old_root_hash != new_root_hash {
-                    // Create a delta node representing the change
-                    let delta_hash = self.hasher.hash_pair(&old_root_hash, &new_root_hash);
-                    let delta_node = Node::delta(delta_hash, old_root_hash, timestamp);
-                    self.stored_deltas.push(delta_node);
-                    self.delta_chains.insert(timestamp, self.stored_deltas.len() - 1);
+    /// Record the new root and create a delta if the root changed, as far as enabled
+    ///
+    /// `reverse` is the [`reverse_point`](Self::reverse_point) taken before the change.
+    pub(crate) fn record_root_delta(&mut self, old_root: Option<H>, timestamp: u64, reverse: Option<crate::node::ReverseDelta<H>>) {
+        self.record_root(old_root.as_ref(), timestamp);
+        if !self.config.enable_deltas {
+            return;
+        }
+        if let (Some(old_root_hash), Some(new_root_hash)) = (old_root, self.root()) {
+            if old_root_hash != new_root_hash {
+                // Create a delta node representing the change
+                let delta_hash = self.hasher.hash_pair(&old_root_hash, &new_root_hash);
+                let mut delta_node = Node::delta(delta_hash, old_root_hash, timestamp);
+                if let NodeType::Delta { reverse: slot, .. } = &mut delta_node.node_type {
+                    *slot = reverse;
                 }
+                self.stored_deltas.push(delta_node);
+                self.delta_chains.insert(timestamp, self.stored_deltas.len() - 1);
             }
         }
-
-        // Log successful insertion
-        let _ = self.security_logger.log_event(&crate::security::events::leaf_insertion(
-            leaf_index,
-            timestamp,
-            hash.as_ref()
-        ));
-
-        Ok(())
     }
 
     /// Get the root hash of the tree
@@ -84,6 +280,22 @@ where
         self.nodes.last().map(|n| n.hash())
     }
 
+    /// Reserve room for `additional` more leaves and their internal nodes
+    ///
+    /// With room reserved, deltas disabled and a no-op logger, appending
+    /// leaves allocates nothing beyond each leaf's stored data and new
+    /// sparse index entries.
+    pub fn reserve(&mut self, additional: usize) {
+        let mut width = self.leaf_count + additional;
+        let mut level = 0;
+        while width > 0 {
+            let nodes = self.nodes.level_mut(level);
+            nodes.reserve(width.saturating_sub(nodes.len()));
+            level += 1;
+            width = if width > 1 { width.div_ceil(2) } else { 0 };
+        }
+    }
+
     /// Get the number of leaves in the tree
     pub fn leaf_count(&self) -> usize {
         self.leaf_count
@@ -94,6 +306,31 @@ where
         self.leaf_count == 0
     }
 
+    /// Get the configuration the tree runs with
+    pub fn config(&self) -> &TreeConfig {
+        &self.config
+    }
+
+    /// Get the name of the hash algorithm, e.g. `"blake3"`
+    pub fn hasher_name(&self) -> &str {
+        self.hasher.name()
+    }
+
+    /// Check whether updates are recorded as deltas
+    pub fn is_deltas_enabled(&self) -> bool {
+        self.config.enable_deltas
+    }
+
+    /// Digest of the rules that determine roots and proofs
+    ///
+    /// Commits to the hasher's name, its output for a fixed pair of inputs and
+    /// the leaf hash mode. Trees with the same digest compute the same root
+    /// from the same records and verify each other's proofs; indexing, delta
+    /// and parallelism settings do not affect it.
+    pub fn spec_digest(&self) -> H {
+        spec_digest(&self.hasher, self.config.leaf_hash_mode)
+    }
+
     /// Get a leaf by index
     pub fn get_leaf(&self, index: usize) -> Result<&Node<H>> {
         if index >= self.leaf_count {
@@ -116,51 +353,86 @@ where
         Ok(timestamp)
     }
 
-    /// Find leaves by timestamp (exact match)
-    pub fn find_by_timestamp(&self, timestamp: u64) -> Vec<usize> {
-        // Search all leaves for matches (sparse index doesn't handle duplicates)
-        self.nodes[..self.leaf_count]
+    /// Get the original data of a leaf by index
+    ///
+    /// Distinguishes leaves whose data was never retained, e.g. for trees
+    /// restored from hashes only, from leaves that were redacted.
+    pub fn get_leaf_data(&self, index: usize) -> Result<LeafData<'_, H>> {
+        Ok(self.leaf_data(index, self.get_leaf(index)?))
+    }
+
+    /// Iterate over `(timestamp, data)` of every leaf in index order
+    pub fn leaf_entries(&self) -> impl Iterator<Item = (u64, LeafData<'_, H>)> + '_ {
+        self.nodes.leaves()
             .iter()
             .enumerate()
-            .filter_map(|(idx, node)| {
-                if let NodeType::Leaf { timestamp: ts, .. } = &node.node_type {
-                    if *ts == timestamp {
-                        Some(idx)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect()
+            .map(|(index, node)| (node.timestamp_info().0, self.leaf_data(index, node)))
     }
 
-    /// Find leaves in a timestamp range
-    pub fn find_range(&self, start: u64, end: u64) -> Vec<usize> {
-        // Check all leaves for matches (sparse index doesn't handle duplicates)
-        let mut indices = Vec::new();
-        for (idx, node) in self.nodes[..self.leaf_count].iter().enumerate() {
-            if let NodeType::Leaf { timestamp: ts, .. } = &node.node_type {
-                if *ts >= start && *ts <= end {
-                    indices.push(idx);
-                }
+    /// Data carried by a leaf node, or why it has none
+    pub(crate) fn leaf_data<'a>(&'a self, index: usize, node: &'a Node<H>) -> LeafData<'a, H> {
+        match &node.node_type {
+            NodeType::Leaf { data: Some(data), .. } => LeafData::Present(data),
+            NodeType::Leaf { hash, .. } => match (self.redactions.get(&index), &self.leaf_store) {
+                (Some(redaction), _) => LeafData::Redacted(redaction),
+                (None, Some(store)) => store.get(hash).map_or(LeafData::NotRetained, LeafData::Present),
+                (None, None) => LeafData::NotRetained,
+            },
+            _ => LeafData::NotRetained,
+        }
+    }
+
+    /// Find leaves by timestamp (exact match)
+    pub fn find_by_timestamp(&self, timestamp: u64) -> Vec<usize> {
+        // Sparse index doesn't handle duplicates, so scan the candidate leaves
+        self.find_range(timestamp, timestamp)
+    }
+
+    /// Find the index of the leaf holding `data` at `timestamp`
+    ///
+    /// Consults the sparse index first and falls back to scanning leaves with
+    /// that timestamp, which covers sparse indexes and duplicate timestamps.
+    /// If the same record was inserted more than once, any one of its
+    /// positions is returned; proofs for each are equally valid.
+    pub fn position_of(&self, data: &[u8], timestamp: u64) -> Option<usize> {
+        let hash = self.hasher.hash(data);
+        let matches = |index: &usize| {
+            let node = &self.nodes[*index];
+            node.timestamp_info().0 == timestamp && node.hash() == hash
+        };
+        if let Some(index) = self.sparse_index.find_exact(timestamp).filter(|i| *i < self.leaf_count) {
+            if matches(&index) {
+                return Some(index);
             }
         }
+        self.find_by_timestamp(timestamp).into_iter().find(matches)
+    }
 
-        indices.sort();
+    /// Find leaves in a timestamp range
+    ///
+    /// If leaves were inserted in timestamp order, only the leaves between
+    /// the sparse index entries enclosing the range are scanned; otherwise
+    /// subtrees whose time range misses the query are skipped. See
+    /// [`explain_range`](Self::explain_range) for the work a query does.
+    pub fn find_range(&self, start: u64, end: u64) -> Vec<usize> {
+        let mut indices = Vec::new();
+        self.search_range(start, end, |index| indices.push(index));
         indices
     }
 
     /// Extract leaves and deltas for serialization
     /// Returns (leaves, deltas) that can be serialized and later reconstructed
     pub fn extract_leaves_and_deltas(&self) -> (Vec<Node<H>>, Vec<Node<H>>) {
-        let leaves = self.nodes[..self.leaf_count].to_vec();
-        let deltas = self.stored_deltas.clone();
+        let leaves = self.with_leaf_data(0, self.nodes.leaves()).map(|leaf| leaf.into_owned()).collect();
+        let deltas = self.stored_deltas.to_vec();
         (leaves, deltas)
     }
 
     /// Reconstruct tree from serialized leaves and deltas
+    ///
+    /// Leaves keep their stored hashes, so leaves whose data was stripped or
+    /// redacted are restored intact. Leaf data that does not hash to its leaf
+    /// is rejected.
     pub fn reconstruct_from_leaves_and_deltas(
         leaves: Vec<Node<H>>,
         deltas: Vec<Node<H>>,
@@ -173,24 +445,55 @@ where
     {
         let mut tree = Self::with_config(hasher, config)?;
 
-        // Add leaves
+        let mut restored = Vec::with_capacity(leaves.len());
         for leaf in leaves {
-            if let NodeType::Leaf { data, timestamp, .. } = &leaf.node_type {
-                tree.insert(data.as_ref().unwrap_or(&vec![]), *timestamp)?;
+            if let NodeType::Leaf { data, .. } = &leaf.node_type {
+                if let (Some(data), LeafHashMode::HashData) = (data, tree.config.leaf_hash_mode) {
+                    if tree.hasher.hash(data) != leaf.hash() {
+                        return Err(ChronoMerkleError::HashError {
+                            message: format!("Data of leaf {} does not match its hash", restored.len()),
+                        });
+                    }
+                }
+                restored.push(leaf);
             }
         }
+        tree.restore_leaves(restored)?;
 
         // Restore deltas
-        tree.stored_deltas = deltas;
-        for (i, delta) in tree.stored_deltas.iter().enumerate() {
-            if let NodeType::Delta { timestamp, .. } = &delta.node_type {
-                tree.delta_chains.insert(*timestamp, i);
-            }
-        }
+        tree.stored_deltas = deltas.into();
+        tree.rebuild_delta_chains();
+
+        Ok(tree)
+    }
 
+    /// Create a tree from leaf hashes and timestamps alone
+    ///
+    /// The leaves are used as is whatever the leaf hash mode, so roots, proofs
+    /// and timestamp queries match the original tree; the leaves hold no data.
+    pub fn restore_from_hashes(
+        leaves: impl IntoIterator<Item = PrehashedLeaf<H>>,
+        hasher: Hasher,
+        config: TreeConfig,
+    ) -> Result<Self>
+    where
+        Logger: Default,
+        Hasher: Default,
+    {
+        let mut tree = Self::with_config(hasher, config)?;
+        let leaves = leaves.into_iter().map(|leaf| Node::leaf(leaf.hash, leaf.timestamp, None)).collect();
+        tree.restore_leaves(leaves)?;
         Ok(tree)
     }
 
+    /// Replace an empty tree's leaves and rebuild its nodes and timestamp index
+    fn restore_leaves(&mut self, leaves: Vec<Node<H>>) -> Result<()> {
+        self.leaf_count = leaves.len();
+        self.nodes = Levels::from_flat(leaves, self.leaf_count);
+        self.rebuild_sparse_index();
+        self.refresh_internal_nodes(0)
+    }
+
     /// Calculate the smallest power of two greater than or equal to n
     #[allow(dead_code)]
     pub(crate) fn next_power_of_two(&self, n: usize) -> usize {
@@ -212,34 +515,63 @@ where
     }
 
     /// Get all stored deltas
-    pub fn get_all_deltas(&self) -> &[Node<H>] {
-        &self.stored_deltas
+    ///
+    /// [`delta_log`](Self::delta_log) offers the same records as typed entries.
+    pub fn get_all_deltas(&self) -> Vec<Node<H>> {
+        self.stored_deltas.to_vec()
     }
 
     /// Clear old deltas to save memory (keep only recent ones)
     pub fn prune_deltas(&mut self, keep_after_timestamp: u64) {
         // Remove deltas older than the specified timestamp
-        let mut indices_to_remove = Vec::new();
+        #[cfg(feature = "std")]
+        let before = self.stored_deltas.len();
+        self.stored_deltas.retain(|delta| match &delta.node_type {
+            NodeType::Delta { timestamp, .. } => *timestamp >= keep_after_timestamp,
+            _ => true,
+        });
+        #[cfg(feature = "std")]
+        self.emit_event(|| crate::event_emitter::TreeEvent::Pruned {
+            before_timestamp: keep_after_timestamp,
+            removed_deltas: before - self.stored_deltas.len(),
+        });
+
+        self.rebuild_delta_chains();
+    }
 
+    /// Rebuild the delta chains index from the stored deltas
+    pub(crate) fn rebuild_delta_chains(&mut self) {
+        self.delta_chains = crate::sparse_index::SparseIndex::new(self.config.sparse_index_sparsity);
         for (i, delta) in self.stored_deltas.iter().enumerate() {
             if let NodeType::Delta { timestamp, .. } = &delta.node_type {
-                if *timestamp < keep_after_timestamp {
-                    indices_to_remove.push(i);
-                }
+                self.delta_chains.insert(*timestamp, i);
             }
         }
+    }
 
-        // Remove from back to front to maintain indices
-        for &index in indices_to_remove.iter().rev() {
-            self.stored_deltas.remove(index);
-        }
+    /// Rebuild the timestamp and delta indexes with a new sparsity
+    ///
+    /// The sparse index budget of the configuration is kept. Returns an
+    /// error if `sparsity` is 0.
+    pub fn reindex(&mut self, sparsity: u64) -> Result<()> {
+        let mut config = self.config.clone();
+        config.sparse_index_sparsity = sparsity;
+        config.validate()?;
+        self.config = config;
+
+        self.rebuild_sparse_index();
+        self.rebuild_delta_chains();
+        Ok(())
+    }
 
-        // Rebuild delta chains index
-        self.delta_chains = crate::sparse_index::SparseIndex::new(self.config.sparse_index_sparsity);
-        for (i, delta) in self.stored_deltas.iter().enumerate() {
-            if let NodeType::Delta { timestamp, .. } = &delta.node_type {
-                self.delta_chains.insert(*timestamp, i);
-            }
+    /// Rebuild the timestamp index from the leaves
+    pub(crate) fn rebuild_sparse_index(&mut self) {
+        self.sparse_index = crate::sparse_index::SparseIndex::with_budget(
+            self.config.sparse_index_sparsity,
+            self.config.sparse_index_max_entries,
+        );
+        for (index, node) in self.nodes.leaves().iter().enumerate() {
+            self.sparse_index.insert(node.timestamp_info().0, index);
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,147 @@
+//! Bounded in-memory pipe connecting a `Write` producer to a `Read` consumer
+//!
+//! Used to stream serialized state into `StorageBackend::save_stream` without
+//! materializing the whole blob. The producer must call [`PipeWriter::finish`]
+//! or [`PipeWriter::fail`]; if it disappears without doing either (e.g. it
+//! panicked), the reader reports `UnexpectedEof` instead of a truncated stream.
+
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+
+/// Size of the chunks handed from writer to reader
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Number of chunks that may be in flight before the writer blocks
+const CHANNEL_DEPTH: usize = 4;
+
+enum Message {
+    Chunk(Vec<u8>),
+    End,
+    Error(io::Error),
+}
+
+/// Writing half of a pipe
+pub(crate) struct PipeWriter {
+    sender: SyncSender<Message>,
+    buffer: Vec<u8>,
+}
+
+/// Reading half of a pipe
+pub(crate) struct PipeReader {
+    receiver: Receiver<Message>,
+    current: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+/// Create a connected writer/reader pair
+pub(crate) fn pipe() -> (PipeWriter, PipeReader) {
+    let (sender, receiver) = sync_channel(CHANNEL_DEPTH);
+    (
+        PipeWriter {
+            sender,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        },
+        PipeReader {
+            receiver,
+            current: Vec::new(),
+            position: 0,
+            finished: false,
+        },
+    )
+}
+
+impl PipeWriter {
+    fn send(&self, message: Message) -> io::Result<()> {
+        self.sender
+            .send(message)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "pipe reader was dropped"))
+    }
+
+    /// Flush buffered data and signal a complete stream
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        self.flush()?;
+        self.send(Message::End)
+    }
+
+    /// Signal that the stream failed; the reader returns `error`
+    pub(crate) fn fail(self, error: io::Error) {
+        let _ = self.send(Message::Error(error));
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = core::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+        self.send(Message::Chunk(chunk))
+    }
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.current.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            match self.receiver.recv() {
+                Ok(Message::Chunk(chunk)) => {
+                    self.current = chunk;
+                    self.position = 0;
+                }
+                Ok(Message::End) => self.finished = true,
+                Ok(Message::Error(error)) => return Err(error),
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "pipe writer ended without finishing the stream",
+                    ));
+                }
+            }
+        }
+        let count = out.len().min(self.current.len() - self.position);
+        out[..count].copy_from_slice(&self.current[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipe_transfers_data() {
+        let (mut writer, mut reader) = pipe();
+        let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        let expected = data.clone();
+        let producer = std::thread::spawn(move || {
+            writer.write_all(&data).unwrap();
+            writer.finish().unwrap();
+        });
+        let mut received = Vec::new();
+        reader.read_to_end(&mut received).unwrap();
+        producer.join().unwrap();
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_unfinished_pipe_is_an_error() {
+        let (mut writer, mut reader) = pipe();
+        writer.write_all(b"partial").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+        let mut received = Vec::new();
+        assert!(reader.read_to_end(&mut received).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+//! Progress reporting for long rebuilds
+//!
+//! Rebuilding a tree with millions of leaves can take minutes. A
+//! [`ProgressObserver`] registered with
+//! [`ChronoMerkleTree::set_progress_observer`] is told about every level as it
+//! is built and when the rebuild completes, so UIs and logs can show progress.
+//! Bulk loads rebuild once per batch and report through the same observer.
+
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Receiver of rebuild progress
+pub trait ProgressObserver: Send + Sync {
+    /// Called after level `level` (1 = parents of the leaves) was built with `nodes` nodes
+    fn on_level_built(&self, level: usize, nodes: usize);
+
+    /// Called once the root has been built
+    fn on_rebuild_complete(&self, duration: Duration);
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Report rebuild progress of this tree to `observer`
+    pub fn set_progress_observer(&mut self, observer: Arc<dyn ProgressObserver>) {
+        self.progress_observer = Some(observer);
+    }
+
+    /// Stop reporting rebuild progress
+    pub fn clear_progress_observer(&mut self) {
+        self.progress_observer = None;
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use crate::ChronoMerkleTree;
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        levels: Mutex<Vec<(usize, usize)>>,
+        completed: Mutex<usize>,
+    }
+
+    impl ProgressObserver for Recorder {
+        fn on_level_built(&self, level: usize, nodes: usize) {
+            self.levels.lock().unwrap().push((level, nodes));
+        }
+
+        fn on_rebuild_complete(&self, _duration: Duration) {
+            *self.completed.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn test_levels_reported() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let recorder = Arc::new(Recorder::default());
+        tree.set_progress_observer(recorder.clone());
+
+        let entries: Vec<(&[u8], u64)> = (0..5u64).map(|i| (&b"data"[..], 1000 + i)).collect();
+        tree.insert_batch(&entries).unwrap();
+        assert_eq!(*recorder.levels.lock().unwrap(), vec![(1, 3), (2, 2), (3, 1)]);
+        assert_eq!(*recorder.completed.lock().unwrap(), 1);
+
+        tree.clear_progress_observer();
+        tree.insert(b"more", 2000).unwrap();
+        assert_eq!(*recorder.completed.lock().unwrap(), 1);
+    }
+}
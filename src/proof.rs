@@ -9,20 +9,32 @@ use alloc::{string::ToString, vec::Vec};
 #[cfg(not(feature = "no-std"))]
 use std::vec::Vec;
 
+/// Most steps accepted in a proof path: one per level of the deepest tree
+/// [`TreeConfig::max_depth`](crate::TreeConfig::max_depth) allows
+pub const MAX_PROOF_STEPS: usize = 64;
+
+/// Most entries accepted in a proof's delta chain; each delta step uses one
+pub const MAX_DELTA_CHAIN: usize = MAX_PROOF_STEPS;
+
 /// A step in a Merkle proof path
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ProofStep<H> {
     /// Sibling is on the left (current node is right child)
-    Left(H),
+    Left(#[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))] H),
     /// Sibling is on the right (current node is left child)
-    Right(H),
+    Right(#[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))] H),
     /// Delta update step (old hash, new hash)
-    Delta(H, H),
+    Delta(
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))] H,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))] H,
+    ),
 }
 
 /// A complete Merkle proof for a leaf
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
 #[derive(Debug, Clone)]
 pub struct ChronoProof<H> {
     /// Index of the leaf being proven
@@ -30,11 +42,15 @@ pub struct ChronoProof<H> {
     /// Path from leaf to root (sibling hashes)
     pub path: Vec<ProofStep<H>>,
     /// Optional delta chain for incremental updates
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash_seq_option"))]
     pub delta_chain: Option<Vec<H>>,
     /// Results from programmable node validations along the path
     pub programmable_results: Vec<bool>,
     /// Timestamp of the leaf
     pub timestamp: u64,
+    /// Whether the leaf hash is the hash of the record or the record itself
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub leaf_hash_mode: crate::config::LeafHashMode,
 }
 
 impl<H: Clone> ChronoProof<H> {
@@ -46,6 +62,7 @@ impl<H: Clone> ChronoProof<H> {
             delta_chain: None,
             programmable_results: Vec::new(),
             timestamp,
+            leaf_hash_mode: crate::config::LeafHashMode::HashData,
         }
     }
 
@@ -66,10 +83,32 @@ impl<H: Clone> ChronoProof<H> {
     pub fn add_validation_result(&mut self, result: bool) {
         self.programmable_results.push(result);
     }
+
+    /// Check the proof against structural limits before any hashing
+    ///
+    /// The path may have at most `max_steps` steps and the delta chain at most
+    /// [`MAX_DELTA_CHAIN`] entries, and a path with `n` sibling steps can only
+    /// reach leaf indices below `2^n`. Each violation has its own error.
+    pub fn check_bounds(&self, max_steps: usize) -> Result<()> {
+        if self.path.len() > max_steps {
+            return Err(ChronoMerkleError::ProofTooLong { steps: self.path.len(), max: max_steps });
+        }
+        if let Some(chain) = self.delta_chain.as_ref().filter(|chain| chain.len() > MAX_DELTA_CHAIN) {
+            return Err(ChronoMerkleError::DeltaChainTooLong { length: chain.len(), max: MAX_DELTA_CHAIN });
+        }
+        let levels = self.path.iter().filter(|step| !matches!(step, ProofStep::Delta(..))).count();
+        if self.leaf_index.checked_shr(levels as u32).unwrap_or(0) != 0 {
+            return Err(ChronoMerkleError::LeafIndexUnreachable { index: self.leaf_index, levels });
+        }
+        Ok(())
+    }
 }
 
 
 /// Verify a proof against a root hash
+///
+/// Proofs outside [`ChronoProof::check_bounds`] for [`MAX_PROOF_STEPS`] are
+/// rejected with an error before any hashing.
 pub fn verify_proof<H, Hasher>(
     proof: &ChronoProof<H>,
     leaf_hash: &H,
@@ -80,6 +119,8 @@ where
     H: AsRef<[u8]> + Clone + Eq,
     Hasher: HashFunction<Output = H>,
 {
+    proof.check_bounds(MAX_PROOF_STEPS)?;
+
     let mut current_hash = leaf_hash.clone();
     let mut delta_index = 0;
 
@@ -152,6 +193,122 @@ where
     Ok(crate::security::constant_time_eq(current_hash.as_ref(), root_hash.as_ref()))
 }
 
+/// Verify a proof of leaf `proof.leaf_index` in a tree of `tree_size` leaves
+///
+/// A path on its own does not say how large the tree was, so a prover could
+/// present a valid path under a root and claim a different position or tree
+/// size for it. Here the leaf index must lie inside the tree, the path must
+/// have exactly one step per level of a tree with `tree_size` leaves, every
+/// step must put the sibling on the side the index dictates, and the last
+/// node of an odd level must be paired with itself. Delta steps never occur
+/// in such a path. Any mismatch yields `false`.
+pub fn verify_proof_with_size<H, Hasher>(
+    proof: &ChronoProof<H>,
+    leaf_hash: &H,
+    root_hash: &H,
+    tree_size: usize,
+    hasher: &Hasher,
+) -> Result<bool>
+where
+    H: AsRef<[u8]> + Clone + Eq,
+    Hasher: HashFunction<Output = H>,
+{
+    if proof.leaf_index >= tree_size {
+        return Ok(false);
+    }
+
+    let mut current_hash = leaf_hash.clone();
+    let (mut position, mut width) = (proof.leaf_index, tree_size);
+    let mut steps = proof.path.iter();
+    while width > 1 {
+        match steps.next() {
+            Some(ProofStep::Left(sibling)) if position % 2 == 1 => {
+                current_hash = hasher.hash_pair(sibling, &current_hash);
+            }
+            Some(ProofStep::Right(sibling)) if position % 2 == 0 => {
+                if position + 1 == width && !constant_time_eq(sibling.as_ref(), current_hash.as_ref()) {
+                    return Ok(false);
+                }
+                current_hash = hasher.hash_pair(&current_hash, sibling);
+            }
+            _ => return Ok(false),
+        }
+        position /= 2;
+        width = width.div_ceil(2);
+    }
+    if steps.next().is_some() {
+        return Ok(false);
+    }
+
+    if proof.programmable_results.iter().any(|&result| !result) {
+        return Err(ChronoMerkleError::ValidationFailed {
+            reason: "Programmable node validation failed".to_string(),
+        });
+    }
+    Ok(constant_time_eq(current_hash.as_ref(), root_hash.as_ref()))
+}
+
+/// Verify a proof without data-dependent early exits
+///
+/// Every step costs one hash operation and failures are accumulated instead
+/// of returned, so timing does not reveal which step mismatched. Proofs
+/// shorter than `depth` are padded with dummy hash operations. All failures,
+/// including malformed delta steps and proofs outside
+/// [`ChronoProof::check_bounds`] for `depth` or [`MAX_PROOF_STEPS`],
+/// whichever is larger, yield `false`.
+pub fn verify_proof_constant_time<H, Hasher>(
+    proof: &ChronoProof<H>,
+    leaf_hash: &H,
+    root_hash: &H,
+    hasher: &Hasher,
+    depth: usize,
+) -> bool
+where
+    H: AsRef<[u8]> + Clone + Eq,
+    Hasher: HashFunction<Output = H>,
+{
+    if proof.check_bounds(depth.max(MAX_PROOF_STEPS)).is_err() {
+        return false;
+    }
+
+    let mut current_hash = leaf_hash.clone();
+    let mut valid = true;
+    let mut delta_index = 0;
+    let delta_chain = proof.delta_chain.as_deref().unwrap_or(&[]);
+
+    for step in &proof.path {
+        match step {
+            ProofStep::Left(sibling) => {
+                current_hash = hasher.hash_pair(sibling, &current_hash);
+            }
+            ProofStep::Right(sibling) => {
+                current_hash = hasher.hash_pair(&current_hash, sibling);
+            }
+            ProofStep::Delta(old_hash, new_hash) => {
+                let expected_delta_hash = hasher.hash_pair(old_hash, new_hash);
+                valid &= constant_time_eq(current_hash.as_ref(), old_hash.as_ref());
+                valid &= !constant_time_eq(new_hash.as_ref(), old_hash.as_ref());
+                // A missing chain entry is compared against a hash that cannot match
+                let delta_hash = delta_chain.get(delta_index).unwrap_or(old_hash);
+                valid &= delta_index < delta_chain.len();
+                valid &= constant_time_eq(delta_hash.as_ref(), expected_delta_hash.as_ref());
+                delta_index += 1;
+                current_hash = new_hash.clone();
+            }
+        }
+    }
+
+    for _ in proof.path.len()..depth {
+        core::hint::black_box(hasher.hash_pair(&current_hash, &current_hash));
+    }
+
+    for &result in &proof.programmable_results {
+        valid &= result;
+    }
+
+    valid & constant_time_eq(current_hash.as_ref(), root_hash.as_ref())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +335,29 @@ mod tests {
         assert_eq!(proof.path.len(), 2);
     }
 
+    #[cfg(feature = "blake3-hash")]
+    #[test]
+    fn test_constant_time_verification_agrees() {
+        use crate::hash::Blake3Hasher;
+
+        let hasher = Blake3Hasher;
+        let leaf = hasher.hash(b"leaf");
+        let sibling = hasher.hash(b"sibling");
+        let root = hasher.hash_pair(&leaf, &sibling);
+        let mut proof = ChronoProof::new(0, 1000);
+        proof.add_step(ProofStep::Right(sibling));
+
+        assert!(verify_proof(&proof, &leaf, &root, &hasher).unwrap());
+        assert!(verify_proof_constant_time(&proof, &leaf, &root, &hasher, 1));
+        assert!(verify_proof_constant_time(&proof, &leaf, &root, &hasher, 4));
+        assert!(!verify_proof_constant_time(&proof, &sibling, &root, &hasher, 4));
+
+        // Malformed delta steps fail instead of erroring
+        proof.add_step(ProofStep::Delta(root, root));
+        assert!(verify_proof(&proof, &leaf, &root, &hasher).is_err());
+        assert!(!verify_proof_constant_time(&proof, &leaf, &root, &hasher, 4));
+    }
+
     #[cfg(feature = "blake3-hash")]
     #[test]
     fn test_delta_chain() {
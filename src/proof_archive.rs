@@ -0,0 +1,495 @@
+//! Batched storage of many proofs against a single root
+//!
+//! Proofs for leaves of the same tree share most of their upper-level siblings.
+//! A [`ProofArchive`] stores every distinct sibling hash once and describes each
+//! proof as a run of references into that table. All proofs of an archive share
+//! one [`LeafHashMode`].
+
+use crate::codec::{self, Reader};
+use crate::config::LeafHashMode;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::{ChronoProof, ProofStep, MAX_PROOF_STEPS};
+use crate::traits::HashOutput;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{collections::BTreeMap, string::ToString, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::{collections::BTreeMap, vec::Vec};
+
+/// Location of a single proof inside a [`ProofArchive`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Index of the proven leaf
+    pub leaf_index: usize,
+    /// Timestamp of the proven leaf
+    pub timestamp: u64,
+    /// Offset of the first step in the archive's step table
+    pub offset: usize,
+    /// Number of steps in this proof
+    pub len: usize,
+}
+
+/// Many proofs against the same root with deduplicated sibling storage
+///
+/// Deserializing an archive applies the same checks as
+/// [`from_bytes`](ProofArchive::from_bytes).
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofArchive<H> {
+    /// Root hash all archived proofs verify against
+    pub root: H,
+    /// Distinct sibling hashes referenced by the proofs
+    siblings: Vec<H>,
+    /// Step table: `sibling_id << 1 | is_left`
+    steps: Vec<u64>,
+    /// Per-proof offsets into the step table
+    entries: Vec<ArchiveEntry>,
+    /// How the leaves of the archived proofs are hashed
+    leaf_hash_mode: LeafHashMode,
+    /// Lookup from sibling bytes to its id (rebuilt on decode)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    lookup: BTreeMap<Vec<u8>, usize>,
+}
+
+impl<H: AsRef<[u8]> + Clone + Eq> ProofArchive<H> {
+    /// Create an empty archive for the given root
+    pub fn new(root: H) -> Self {
+        Self {
+            root,
+            siblings: Vec::new(),
+            steps: Vec::new(),
+            entries: Vec::new(),
+            leaf_hash_mode: LeafHashMode::HashData,
+            lookup: BTreeMap::new(),
+        }
+    }
+
+    /// Add a proof to the archive, returning its position
+    ///
+    /// Only plain sibling paths are supported; proofs carrying delta steps,
+    /// delta chains or programmable results are rejected. The first proof sets
+    /// the archive's leaf hash mode, and later proofs must match it.
+    pub fn add_proof(&mut self, proof: &ChronoProof<H>) -> Result<usize> {
+        if proof.delta_chain.is_some() || !proof.programmable_results.is_empty() {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Proof archives do not support delta chains or programmable results".to_string(),
+            });
+        }
+        // Check the whole path before interning, so a rejected proof leaves no siblings behind
+        let path = proof
+            .path
+            .iter()
+            .map(|step| match step {
+                ProofStep::Left(sibling) => Some((sibling, true)),
+                ProofStep::Right(sibling) => Some((sibling, false)),
+                ProofStep::Delta(..) => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ChronoMerkleError::InvalidProof {
+                message: "Proof archives do not support delta steps".to_string(),
+            })?;
+        if !self.entries.is_empty() && proof.leaf_hash_mode != self.leaf_hash_mode {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Proof leaf hash mode does not match the archive".to_string(),
+            });
+        }
+        self.leaf_hash_mode = proof.leaf_hash_mode;
+
+        let offset = self.steps.len();
+        for (sibling, is_left) in path {
+            let id = self.intern(sibling);
+            self.steps.push(((id as u64) << 1) | u64::from(is_left));
+        }
+
+        self.entries.push(ArchiveEntry {
+            leaf_index: proof.leaf_index,
+            timestamp: proof.timestamp,
+            offset,
+            len: proof.path.len(),
+        });
+        Ok(self.entries.len() - 1)
+    }
+
+    /// Reconstruct the proof stored at `position`
+    pub fn get_proof(&self, position: usize) -> Result<ChronoProof<H>> {
+        let entry = self.entries.get(position).ok_or(ChronoMerkleError::IndexOutOfBounds {
+            index: position,
+            leaf_count: self.entries.len(),
+        })?;
+
+        let steps = entry
+            .offset
+            .checked_add(entry.len)
+            .and_then(|end| self.steps.get(entry.offset..end))
+            .ok_or_else(|| ChronoMerkleError::InvalidProof {
+                message: "Archive entry lies outside the step table".to_string(),
+            })?;
+        let mut proof = ChronoProof::new(entry.leaf_index, entry.timestamp);
+        proof.leaf_hash_mode = self.leaf_hash_mode;
+        for &step in steps {
+            let sibling = self.siblings.get((step >> 1) as usize).cloned().ok_or_else(|| {
+                ChronoMerkleError::InvalidProof {
+                    message: "Archive step references unknown sibling".to_string(),
+                }
+            })?;
+            proof.add_step(if step & 1 == 1 {
+                ProofStep::Left(sibling)
+            } else {
+                ProofStep::Right(sibling)
+            });
+        }
+        Ok(proof)
+    }
+
+    /// Find the archived proof for a leaf index
+    pub fn proof_for_leaf(&self, leaf_index: usize) -> Option<ChronoProof<H>> {
+        let position = self.entries.iter().position(|e| e.leaf_index == leaf_index)?;
+        self.get_proof(position).ok()
+    }
+
+    /// Verify every archived proof against the archive root given the leaf hashes
+    ///
+    /// `leaf_hashes` must be in the same order as the archived proofs.
+    pub fn verify_all<Hasher>(&self, leaf_hashes: &[H], hasher: &Hasher) -> Result<bool>
+    where
+        Hasher: HashFunction<Output = H>,
+    {
+        if leaf_hashes.len() != self.entries.len() {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Leaf hash count does not match archived proof count".to_string(),
+            });
+        }
+        for (position, leaf_hash) in leaf_hashes.iter().enumerate() {
+            let proof = self.get_proof(position)?;
+            if !crate::proof::verify_proof(&proof, leaf_hash, &self.root, hasher)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Archived proof entries
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// How the leaves of the archived proofs are hashed
+    pub fn leaf_hash_mode(&self) -> LeafHashMode {
+        self.leaf_hash_mode
+    }
+
+    /// Number of distinct sibling hashes stored
+    pub fn unique_siblings(&self) -> usize {
+        self.siblings.len()
+    }
+
+    /// Number of archived proofs
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the archive is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Assemble an archive from its tables, applying the checks of [`from_bytes`](Self::from_bytes)
+    #[cfg(feature = "serde")]
+    fn from_parts(
+        root: H,
+        siblings: Vec<H>,
+        steps: Vec<u64>,
+        entries: Vec<ArchiveEntry>,
+        leaf_hash_mode: LeafHashMode,
+    ) -> Result<Self> {
+        let hash_len = root.as_ref().len();
+        if siblings.iter().any(|sibling| sibling.as_ref().len() != hash_len) {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Archive sibling length does not match root length".to_string(),
+            });
+        }
+        let lookup = Self::sibling_lookup(&siblings)?;
+        if steps.iter().any(|&step| (step >> 1) as usize >= siblings.len()) {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Archive step references unknown sibling".to_string(),
+            });
+        }
+        for entry in &entries {
+            if entry.len > MAX_PROOF_STEPS {
+                return Err(ChronoMerkleError::ProofTooLong { steps: entry.len, max: MAX_PROOF_STEPS });
+            }
+            if entry.offset.checked_add(entry.len).is_none_or(|end| end > steps.len()) {
+                return Err(ChronoMerkleError::InvalidProof {
+                    message: "Archive entry lies outside the step table".to_string(),
+                });
+            }
+        }
+
+        Ok(Self { root, siblings, steps, entries, leaf_hash_mode, lookup })
+    }
+
+    /// Map each sibling to its id, rejecting siblings stored twice
+    fn sibling_lookup(siblings: &[H]) -> Result<BTreeMap<Vec<u8>, usize>> {
+        let mut lookup = BTreeMap::new();
+        for (id, sibling) in siblings.iter().enumerate() {
+            if lookup.insert(sibling.as_ref().to_vec(), id).is_some() {
+                return Err(ChronoMerkleError::InvalidProof {
+                    message: "Archive stores a sibling twice".to_string(),
+                });
+            }
+        }
+        Ok(lookup)
+    }
+
+    fn intern(&mut self, sibling: &H) -> usize {
+        if let Some(&id) = self.lookup.get(sibling.as_ref()) {
+            return id;
+        }
+        let id = self.siblings.len();
+        self.siblings.push(sibling.clone());
+        self.lookup.insert(sibling.as_ref().to_vec(), id);
+        id
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, H> serde::Deserialize<'de> for ProofArchive<H>
+where
+    H: AsRef<[u8]> + Clone + Eq + serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        struct ProofArchiveHelper<H> {
+            root: H,
+            siblings: Vec<H>,
+            steps: Vec<u64>,
+            entries: Vec<ArchiveEntry>,
+            #[serde(default)]
+            leaf_hash_mode: LeafHashMode,
+        }
+
+        let helper = ProofArchiveHelper::deserialize(deserializer)?;
+        Self::from_parts(helper.root, helper.siblings, helper.steps, helper.entries, helper.leaf_hash_mode)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl<H: HashOutput> ProofArchive<H> {
+    /// Encode the archive into a single compact blob
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let root = self.root.as_bytes();
+        let mut out = Vec::new();
+        codec::write_varint(&mut out, root.len() as u64);
+        out.extend_from_slice(root);
+        codec::write_varint(&mut out, self.siblings.len() as u64);
+        for sibling in &self.siblings {
+            out.extend_from_slice(sibling.as_bytes());
+        }
+        codec::write_varint(&mut out, self.entries.len() as u64);
+        for entry in &self.entries {
+            codec::write_varint(&mut out, entry.leaf_index as u64);
+            codec::write_u64(&mut out, entry.timestamp);
+            codec::write_varint(&mut out, entry.len as u64);
+            for &step in &self.steps[entry.offset..entry.offset + entry.len] {
+                codec::write_varint(&mut out, step);
+            }
+        }
+        if self.leaf_hash_mode == LeafHashMode::TakeAsIs {
+            out.push(1);
+        }
+        out
+    }
+
+    /// Decode an archive produced by [`ProofArchive::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let hash_len = reader.read_len()?;
+        let root = reader.read_hash(hash_len)?;
+        let mut archive = Self::new(root);
+
+        let sibling_count = reader.read_len()?;
+        for _ in 0..sibling_count {
+            archive.siblings.push(reader.read_hash(hash_len)?);
+        }
+        archive.lookup = Self::sibling_lookup(&archive.siblings)?;
+
+        let entry_count = reader.read_len()?;
+        for _ in 0..entry_count {
+            let leaf_index = reader.read_len()?;
+            let timestamp = reader.read_u64()?;
+            let len = reader.read_len()?;
+            if len > MAX_PROOF_STEPS {
+                return Err(ChronoMerkleError::ProofTooLong { steps: len, max: MAX_PROOF_STEPS });
+            }
+            let offset = archive.steps.len();
+            for _ in 0..len {
+                let step = reader.read_varint()?;
+                if (step >> 1) as usize >= archive.siblings.len() {
+                    return Err(ChronoMerkleError::InvalidProof {
+                        message: "Archive step references unknown sibling".to_string(),
+                    });
+                }
+                archive.steps.push(step);
+            }
+            archive.entries.push(ArchiveEntry { leaf_index, timestamp, offset, len });
+        }
+        archive.leaf_hash_mode = if reader.is_empty() {
+            LeafHashMode::HashData
+        } else if reader.read_u8()? == 1 {
+            LeafHashMode::TakeAsIs
+        } else {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Invalid leaf hash mode marker".to_string(),
+            });
+        };
+
+        if !reader.is_empty() {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Trailing bytes after proof archive".to_string(),
+            });
+        }
+        Ok(archive)
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: crate::security::SecurityLogger,
+{
+    /// Generate proofs for the given leaves and collect them into a [`ProofArchive`]
+    pub fn generate_proof_archive(&self, leaf_indices: &[usize]) -> Result<ProofArchive<H>> {
+        let root = self.root().ok_or(ChronoMerkleError::EmptyTree)?;
+        let mut archive = ProofArchive::new(root);
+        archive.leaf_hash_mode = self.config.leaf_hash_mode;
+        for &leaf_index in leaf_indices {
+            archive.add_proof(&self.generate_proof(leaf_index)?)?;
+        }
+        Ok(archive)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    fn build_tree(leaves: u64) -> ChronoMerkleTree {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..leaves {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let tree = build_tree(37);
+        let indices: Vec<usize> = (0..37).collect();
+        let archive = tree.generate_proof_archive(&indices).unwrap();
+        assert_eq!(archive.len(), 37);
+
+        let decoded = ProofArchive::<[u8; 32]>::from_bytes(&archive.to_bytes()).unwrap();
+        assert_eq!(decoded.len(), archive.len());
+        for i in indices {
+            let proof = decoded.proof_for_leaf(i).unwrap();
+            assert_eq!(proof.path, tree.generate_proof(i).unwrap().path);
+            assert!(tree.verify_proof(&proof).unwrap());
+        }
+
+        let leaf_hashes: Vec<[u8; 32]> = (0..37).map(|i| tree.get_leaf_hash(i).unwrap()).collect();
+        assert!(decoded.verify_all(&leaf_hashes, &Blake3Hasher).unwrap());
+    }
+
+    #[test]
+    fn test_archive_deduplicates_siblings() {
+        let tree = build_tree(64);
+        let indices: Vec<usize> = (0..64).collect();
+        let archive = tree.generate_proof_archive(&indices).unwrap();
+        // Every node below the root appears once as a sibling
+        assert_eq!(archive.unique_siblings(), 126);
+        assert!(archive.to_bytes().len() < 64 * 6 * 32);
+    }
+
+    #[test]
+    fn test_archive_rejects_delta_steps() {
+        let mut archive = ProofArchive::new([0u8; 32]);
+        let mut proof = ChronoProof::new(0, 1000);
+        proof.add_step(ProofStep::Right([1u8; 32]));
+        proof.add_step(ProofStep::Delta([2u8; 32], [3u8; 32]));
+        assert!(archive.add_proof(&proof).is_err());
+        assert!(archive.is_empty());
+        assert_eq!(archive.unique_siblings(), 0);
+    }
+
+    #[test]
+    fn test_archive_keeps_leaf_hash_mode() {
+        use crate::config::TreeConfig;
+        use crate::operations::PrehashedLeaf;
+
+        let config = TreeConfig { leaf_hash_mode: LeafHashMode::TakeAsIs, ..Default::default() };
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+        for i in 0..5u64 {
+            tree.insert_prehashed(PrehashedLeaf::new(Blake3Hasher.hash(&i.to_le_bytes()), 1000 + i)).unwrap();
+        }
+        let archive = tree.generate_proof_archive(&[0, 3]).unwrap();
+        assert_eq!(archive.leaf_hash_mode(), LeafHashMode::TakeAsIs);
+
+        let decoded = ProofArchive::<[u8; 32]>::from_bytes(&archive.to_bytes()).unwrap();
+        assert_eq!(decoded, archive);
+        let proof = decoded.proof_for_leaf(3).unwrap();
+        assert_eq!(proof.leaf_hash_mode, LeafHashMode::TakeAsIs);
+        assert!(tree.verify_proof(&proof).unwrap());
+
+        // Proofs from a tree hashing its records cannot join the archive
+        let mut archive = archive;
+        assert!(archive.add_proof(&build_tree(5).generate_proof(0).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_archive_rejects_duplicate_siblings() {
+        let tree = build_tree(4);
+        let archive = tree.generate_proof_archive(&[0]).unwrap();
+        let mut bytes = archive.to_bytes();
+        // Sibling count follows the root; store the first sibling twice
+        let count_at = 1 + 32;
+        assert_eq!(bytes[count_at], 2);
+        bytes[count_at] = 3;
+        let first = bytes[count_at + 1..count_at + 33].to_vec();
+        bytes.splice(count_at + 1..count_at + 1, first);
+        assert!(ProofArchive::<[u8; 32]>::from_bytes(&bytes).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_archive_deserialize_is_validated() {
+        let tree = build_tree(9);
+        let archive = tree.generate_proof_archive(&[0, 4, 8]).unwrap();
+        let json = serde_json::to_value(&archive).unwrap();
+
+        // The sibling lookup is rebuilt, so appending keeps deduplicating
+        let mut decoded: ProofArchive<[u8; 32]> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(decoded, archive);
+        let siblings = decoded.unique_siblings();
+        decoded.add_proof(&tree.generate_proof(0).unwrap()).unwrap();
+        assert_eq!(decoded.unique_siblings(), siblings);
+
+        let mut bad_entry = json.clone();
+        bad_entry["entries"][0]["offset"] = serde_json::json!(usize::MAX);
+        let mut bad_step = json.clone();
+        bad_step["steps"][0] = serde_json::json!(u64::MAX);
+        let mut duplicate = json;
+        let first = duplicate["siblings"][0].clone();
+        duplicate["siblings"].as_array_mut().unwrap().push(first);
+        for bad in [bad_entry, bad_step, duplicate] {
+            assert!(serde_json::from_value::<ProofArchive<[u8; 32]>>(bad).is_err());
+        }
+    }
+}
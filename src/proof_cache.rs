@@ -0,0 +1,222 @@
+//! LRU cache for generated proofs
+//!
+//! Proof-serving workloads tend to request the same hot leaves repeatedly. When
+//! enabled on a tree, [`ProofCache`] is consulted by `generate_proof` and is
+//! invalidated automatically whenever the root changes.
+
+use crate::hash::HashFunction;
+use crate::proof::ChronoProof;
+use crate::tree::ChronoMerkleTree;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Hit/miss counters for a [`ProofCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProofCacheStats {
+    /// Lookups served from the cache
+    pub hits: u64,
+    /// Lookups that required proof generation
+    pub misses: u64,
+    /// Entries dropped because the cache was full
+    pub evictions: u64,
+    /// Times the cache was cleared because the root changed
+    pub invalidations: u64,
+}
+
+/// Least-recently-used cache of proofs keyed by `(root, leaf_index)`
+///
+/// All entries belong to a single root; a lookup or insert under a different
+/// root drops every cached proof.
+#[derive(Debug)]
+pub struct ProofCache<H> {
+    capacity: usize,
+    root: Option<Vec<u8>>,
+    entries: HashMap<usize, (ChronoProof<H>, u64)>,
+    recency: BTreeMap<u64, usize>,
+    tick: u64,
+    stats: ProofCacheStats,
+}
+
+impl<H: AsRef<[u8]> + Clone> ProofCache<H> {
+    /// Create a cache holding at most `capacity` proofs
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            root: None,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            stats: ProofCacheStats::default(),
+        }
+    }
+
+    /// Look up a proof for `leaf_index` under `root`
+    pub fn get(&mut self, root: &H, leaf_index: usize) -> Option<ChronoProof<H>> {
+        self.sync_root(root);
+        self.tick += 1;
+        match self.entries.get_mut(&leaf_index) {
+            Some((proof, last_used)) => {
+                self.recency.remove(last_used);
+                *last_used = self.tick;
+                self.recency.insert(self.tick, leaf_index);
+                self.stats.hits += 1;
+                Some(proof.clone())
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store a proof for `leaf_index` under `root`, evicting the least recently used entry if full
+    pub fn insert(&mut self, root: &H, leaf_index: usize, proof: ChronoProof<H>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.sync_root(root);
+        self.tick += 1;
+
+        if let Some((_, last_used)) = self.entries.remove(&leaf_index) {
+            self.recency.remove(&last_used);
+        } else if self.entries.len() >= self.capacity {
+            if let Some((_, evicted)) = self.recency.pop_first() {
+                self.entries.remove(&evicted);
+                self.stats.evictions += 1;
+            }
+        }
+
+        self.entries.insert(leaf_index, (proof, self.tick));
+        self.recency.insert(self.tick, leaf_index);
+    }
+
+    /// Drop all cached proofs
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    /// Number of cached proofs
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Maximum number of cached proofs
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Get hit/miss statistics
+    pub fn stats(&self) -> ProofCacheStats {
+        self.stats
+    }
+
+    fn sync_root(&mut self, root: &H) {
+        if self.root.as_deref() != Some(root.as_ref()) {
+            if !self.entries.is_empty() {
+                self.stats.invalidations += 1;
+            }
+            self.clear();
+            self.root = Some(root.as_ref().to_vec());
+        }
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: crate::security::SecurityLogger,
+{
+    /// Enable an LRU proof cache holding up to `capacity` proofs
+    pub fn enable_proof_cache(&mut self, capacity: usize) {
+        self.proof_cache = Some(Mutex::new(ProofCache::new(capacity)));
+    }
+
+    /// Disable and drop the proof cache
+    pub fn disable_proof_cache(&mut self) {
+        self.proof_cache = None;
+    }
+
+    /// Get proof cache statistics, if the cache is enabled
+    pub fn proof_cache_stats(&self) -> Option<ProofCacheStats> {
+        self.proof_cache
+            .as_ref()
+            .map(|cache| cache.lock().unwrap_or_else(|e| e.into_inner()).stats())
+    }
+
+    /// Look up a cached proof for the current root
+    pub(crate) fn cached_proof(&self, leaf_index: usize) -> Option<ChronoProof<H>> {
+        let cache = self.proof_cache.as_ref()?;
+        let root = self.root()?;
+        cache.lock().unwrap_or_else(|e| e.into_inner()).get(&root, leaf_index)
+    }
+
+    /// Remember a freshly generated proof for the current root
+    pub(crate) fn cache_proof(&self, proof: &ChronoProof<H>) {
+        if let (Some(cache), Some(root)) = (self.proof_cache.as_ref(), self.root()) {
+            cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(&root, proof.leaf_index, proof.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction() {
+        let root = [1u8; 32];
+        let mut cache = ProofCache::new(2);
+        cache.insert(&root, 0, ChronoProof::new(0, 1000));
+        cache.insert(&root, 1, ChronoProof::new(1, 1001));
+        assert!(cache.get(&root, 0).is_some());
+        cache.insert(&root, 2, ChronoProof::new(2, 1002));
+
+        assert!(cache.get(&root, 1).is_none());
+        assert!(cache.get(&root, 0).is_some());
+        assert!(cache.get(&root, 2).is_some());
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(cache.stats().hits, 3);
+        assert_eq!(cache.stats().misses, 1);
+    }
+
+    #[test]
+    fn test_root_change_invalidates() {
+        let mut cache = ProofCache::new(4);
+        cache.insert(&[1u8; 32], 0, ChronoProof::new(0, 1000));
+        assert!(cache.get(&[2u8; 32], 0).is_none());
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().invalidations, 1);
+    }
+
+    #[cfg(feature = "blake3-hash")]
+    #[test]
+    fn test_tree_uses_cache() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.enable_proof_cache(16);
+        tree.insert(b"data1", 1000).unwrap();
+        tree.insert(b"data2", 1001).unwrap();
+
+        let first = tree.generate_proof(0).unwrap();
+        let second = tree.generate_proof(0).unwrap();
+        assert_eq!(first.path, second.path);
+        assert_eq!(tree.proof_cache_stats().unwrap().hits, 1);
+
+        tree.insert(b"data3", 1002).unwrap();
+        let third = tree.generate_proof(0).unwrap();
+        assert!(tree.verify_proof(&third).unwrap());
+        let stats = tree.proof_cache_stats().unwrap();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.invalidations, 1);
+    }
+}
@@ -0,0 +1,417 @@
+//! Compact proof representation for on-chain and wire usage
+//!
+//! Proofs generated for trees with an odd number of nodes on some level contain
+//! "duplicate" siblings: the sibling hash is the current node itself. These can be
+//! recomputed by the verifier, so the compressed form stores only a 2-bit code for
+//! them and keeps real sibling hashes in a flat list.
+
+use crate::codec::{self, Reader};
+use crate::config::LeafHashMode;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::{ChronoProof, ProofStep, MAX_DELTA_CHAIN, MAX_PROOF_STEPS};
+use crate::traits::HashOutput;
+
+#[cfg(feature = "no-std")]
+use alloc::{string::ToString, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::vec::Vec;
+
+/// Sibling is on the right, hash stored
+const CODE_RIGHT: u8 = 0;
+/// Sibling is on the left, hash stored
+const CODE_LEFT: u8 = 1;
+/// Sibling is a duplicate of the current node (odd-count padding), nothing stored
+const CODE_DUPLICATE: u8 = 2;
+/// Delta step, old and new hashes stored
+const CODE_DELTA: u8 = 3;
+
+/// A compressed [`ChronoProof`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedProof<H> {
+    /// Index of the leaf being proven
+    pub leaf_index: usize,
+    /// Timestamp of the leaf
+    pub timestamp: u64,
+    /// Number of steps in the original proof path
+    pub step_count: usize,
+    /// Step codes packed four per byte (2 bits each)
+    pub step_codes: Vec<u8>,
+    /// Sibling and delta hashes that could not be elided, in path order
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash_seq"))]
+    pub hashes: Vec<H>,
+    /// Optional delta chain carried over unchanged
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash_seq_option"))]
+    pub delta_chain: Option<Vec<H>>,
+    /// Results from programmable node validations
+    pub programmable_results: Vec<bool>,
+    /// Leaf hash mode of the original proof
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub leaf_hash_mode: LeafHashMode,
+}
+
+impl<H> CompressedProof<H> {
+    /// Get the 2-bit code of the step at `index`
+    fn step_code(&self, index: usize) -> u8 {
+        (self.step_codes[index / 4] >> ((index % 4) * 2)) & 0b11
+    }
+}
+
+impl<H: AsRef<[u8]> + Clone + Eq> ChronoProof<H> {
+    /// Compress this proof by eliding duplicate-node siblings and packing direction bits
+    ///
+    /// `leaf_hash` is needed to follow the path and recognise siblings that are
+    /// copies of the current node.
+    pub fn compress<Hasher>(&self, leaf_hash: &H, hasher: &Hasher) -> CompressedProof<H>
+    where
+        Hasher: HashFunction<Output = H>,
+    {
+        let mut step_codes = vec![0u8; self.path.len().div_ceil(4)];
+        let mut hashes = Vec::new();
+        let mut current = leaf_hash.clone();
+
+        for (i, step) in self.path.iter().enumerate() {
+            let code = match step {
+                ProofStep::Right(sibling) if *sibling == current => {
+                    current = hasher.hash_pair(&current, &current);
+                    CODE_DUPLICATE
+                }
+                ProofStep::Right(sibling) => {
+                    current = hasher.hash_pair(&current, sibling);
+                    hashes.push(sibling.clone());
+                    CODE_RIGHT
+                }
+                ProofStep::Left(sibling) => {
+                    current = hasher.hash_pair(sibling, &current);
+                    hashes.push(sibling.clone());
+                    CODE_LEFT
+                }
+                ProofStep::Delta(old_hash, new_hash) => {
+                    current = new_hash.clone();
+                    hashes.push(old_hash.clone());
+                    hashes.push(new_hash.clone());
+                    CODE_DELTA
+                }
+            };
+            step_codes[i / 4] |= code << ((i % 4) * 2);
+        }
+
+        CompressedProof {
+            leaf_index: self.leaf_index,
+            timestamp: self.timestamp,
+            step_count: self.path.len(),
+            step_codes,
+            hashes,
+            delta_chain: self.delta_chain.clone(),
+            programmable_results: self.programmable_results.clone(),
+            leaf_hash_mode: self.leaf_hash_mode,
+        }
+    }
+
+    /// Restore a full proof from its compressed form
+    pub fn decompress<Hasher>(
+        compressed: &CompressedProof<H>,
+        leaf_hash: &H,
+        hasher: &Hasher,
+    ) -> Result<Self>
+    where
+        Hasher: HashFunction<Output = H>,
+    {
+        if compressed.step_count > MAX_PROOF_STEPS {
+            return Err(ChronoMerkleError::ProofTooLong { steps: compressed.step_count, max: MAX_PROOF_STEPS });
+        }
+        if compressed.step_codes.len() != compressed.step_count.div_ceil(4) {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Step code length does not match step count".to_string(),
+            });
+        }
+
+        let mut hashes = compressed.hashes.iter();
+        let mut next_hash = || {
+            hashes.next().cloned().ok_or_else(|| ChronoMerkleError::InvalidProof {
+                message: "Compressed proof is missing sibling hashes".to_string(),
+            })
+        };
+
+        let mut proof = ChronoProof::new(compressed.leaf_index, compressed.timestamp);
+        let mut current = leaf_hash.clone();
+
+        for i in 0..compressed.step_count {
+            let step = match compressed.step_code(i) {
+                CODE_RIGHT => {
+                    let sibling = next_hash()?;
+                    current = hasher.hash_pair(&current, &sibling);
+                    ProofStep::Right(sibling)
+                }
+                CODE_LEFT => {
+                    let sibling = next_hash()?;
+                    current = hasher.hash_pair(&sibling, &current);
+                    ProofStep::Left(sibling)
+                }
+                CODE_DUPLICATE => {
+                    let sibling = current.clone();
+                    current = hasher.hash_pair(&current, &sibling);
+                    ProofStep::Right(sibling)
+                }
+                _ => {
+                    let old_hash = next_hash()?;
+                    let new_hash = next_hash()?;
+                    current = new_hash.clone();
+                    ProofStep::Delta(old_hash, new_hash)
+                }
+            };
+            proof.add_step(step);
+        }
+
+        if next_hash().is_ok() {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Compressed proof contains unused hashes".to_string(),
+            });
+        }
+
+        proof.delta_chain = compressed.delta_chain.clone();
+        proof.programmable_results = compressed.programmable_results.clone();
+        proof.leaf_hash_mode = compressed.leaf_hash_mode;
+        Ok(proof)
+    }
+
+    /// Pack this proof without eliding any sibling
+    ///
+    /// The result restores with [`unpack`](Self::unpack) without the leaf hash.
+    #[cfg(feature = "encoding-strings")]
+    pub(crate) fn pack(&self) -> CompressedProof<H> {
+        let mut step_codes = vec![0u8; self.path.len().div_ceil(4)];
+        let mut hashes = Vec::new();
+        for (i, step) in self.path.iter().enumerate() {
+            let code = match step {
+                ProofStep::Right(sibling) => {
+                    hashes.push(sibling.clone());
+                    CODE_RIGHT
+                }
+                ProofStep::Left(sibling) => {
+                    hashes.push(sibling.clone());
+                    CODE_LEFT
+                }
+                ProofStep::Delta(old_hash, new_hash) => {
+                    hashes.push(old_hash.clone());
+                    hashes.push(new_hash.clone());
+                    CODE_DELTA
+                }
+            };
+            step_codes[i / 4] |= code << ((i % 4) * 2);
+        }
+
+        CompressedProof {
+            leaf_index: self.leaf_index,
+            timestamp: self.timestamp,
+            step_count: self.path.len(),
+            step_codes,
+            hashes,
+            delta_chain: self.delta_chain.clone(),
+            programmable_results: self.programmable_results.clone(),
+            leaf_hash_mode: self.leaf_hash_mode,
+        }
+    }
+
+    /// Restore a proof packed with [`pack`](Self::pack)
+    #[cfg(feature = "encoding-strings")]
+    pub(crate) fn unpack(compressed: &CompressedProof<H>) -> Result<Self> {
+        let invalid = |message: &str| ChronoMerkleError::InvalidProof { message: message.to_string() };
+        if compressed.step_codes.len() != compressed.step_count.div_ceil(4) {
+            return Err(invalid("Step code length does not match step count"));
+        }
+
+        let mut hashes = compressed.hashes.iter().cloned();
+        let mut proof = ChronoProof::new(compressed.leaf_index, compressed.timestamp);
+        for i in 0..compressed.step_count {
+            let mut next_hash = || hashes.next().ok_or_else(|| invalid("Packed proof is missing sibling hashes"));
+            let step = match compressed.step_code(i) {
+                CODE_RIGHT => ProofStep::Right(next_hash()?),
+                CODE_LEFT => ProofStep::Left(next_hash()?),
+                CODE_DELTA => ProofStep::Delta(next_hash()?, next_hash()?),
+                _ => return Err(invalid("Packed proof has elided siblings; decompress it with the leaf hash")),
+            };
+            proof.add_step(step);
+        }
+        if hashes.next().is_some() {
+            return Err(invalid("Packed proof contains unused hashes"));
+        }
+
+        proof.delta_chain = compressed.delta_chain.clone();
+        proof.programmable_results = compressed.programmable_results.clone();
+        proof.leaf_hash_mode = compressed.leaf_hash_mode;
+        Ok(proof)
+    }
+}
+
+impl<H: HashOutput> CompressedProof<H> {
+    /// Encode the compressed proof into a compact byte representation
+    ///
+    /// Proofs of prehashed leaves end with one extra byte marking
+    /// [`LeafHashMode::TakeAsIs`]; other proofs keep the original layout.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let hash_len = self.hashes.first().map_or(0, |h| h.as_bytes().len());
+        let mut out = Vec::new();
+        codec::write_varint(&mut out, self.leaf_index as u64);
+        codec::write_u64(&mut out, self.timestamp);
+        codec::write_varint(&mut out, self.step_count as u64);
+        out.extend_from_slice(&self.step_codes);
+        codec::write_varint(&mut out, hash_len as u64);
+        codec::write_varint(&mut out, self.hashes.len() as u64);
+        for hash in &self.hashes {
+            out.extend_from_slice(hash.as_bytes());
+        }
+        match &self.delta_chain {
+            Some(chain) => {
+                out.push(1);
+                codec::write_varint(&mut out, chain.len() as u64);
+                for hash in chain {
+                    codec::write_varint(&mut out, hash.as_bytes().len() as u64);
+                    out.extend_from_slice(hash.as_bytes());
+                }
+            }
+            None => out.push(0),
+        }
+        codec::write_varint(&mut out, self.programmable_results.len() as u64);
+        out.extend_from_slice(&codec::pack_bits(&self.programmable_results));
+        if self.leaf_hash_mode == LeafHashMode::TakeAsIs {
+            out.push(1);
+        }
+        out
+    }
+
+    /// Decode a compressed proof produced by [`CompressedProof::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let leaf_index = reader.read_len()?;
+        let timestamp = reader.read_u64()?;
+        let step_count = reader.read_len()?;
+        if step_count > MAX_PROOF_STEPS {
+            return Err(ChronoMerkleError::ProofTooLong { steps: step_count, max: MAX_PROOF_STEPS });
+        }
+        let step_codes = reader.take(step_count.div_ceil(4))?.to_vec();
+        let hash_len = reader.read_len()?;
+        let hash_count = reader.read_len()?;
+        // A delta step stores two hashes, every other step at most one
+        if hash_count > 2 * step_count {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Compressed proof has more hashes than its steps can use".to_string(),
+            });
+        }
+        let hashes = (0..hash_count)
+            .map(|_| reader.read_hash(hash_len))
+            .collect::<Result<Vec<H>>>()?;
+        let delta_chain = match reader.read_u8()? {
+            0 => None,
+            1 => {
+                let len = reader.read_len()?;
+                if len > MAX_DELTA_CHAIN {
+                    return Err(ChronoMerkleError::DeltaChainTooLong { length: len, max: MAX_DELTA_CHAIN });
+                }
+                let mut chain = Vec::new();
+                for _ in 0..len {
+                    let delta_len = reader.read_len()?;
+                    chain.push(reader.read_hash(delta_len)?);
+                }
+                Some(chain)
+            }
+            _ => {
+                return Err(ChronoMerkleError::InvalidProof {
+                    message: "Invalid delta chain marker".to_string(),
+                });
+            }
+        };
+        let result_count = reader.read_len()?;
+        let programmable_results = codec::unpack_bits(reader.take(result_count.div_ceil(8))?, result_count);
+        let leaf_hash_mode = if reader.is_empty() {
+            LeafHashMode::HashData
+        } else if reader.read_u8()? == 1 {
+            LeafHashMode::TakeAsIs
+        } else {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Invalid leaf hash mode marker".to_string(),
+            });
+        };
+
+        if !reader.is_empty() {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: "Trailing bytes after compressed proof".to_string(),
+            });
+        }
+
+        Ok(Self {
+            leaf_index,
+            timestamp,
+            step_count,
+            step_codes,
+            hashes,
+            delta_chain,
+            programmable_results,
+            leaf_hash_mode,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+    use crate::tree::ChronoMerkleTree;
+
+    #[test]
+    fn test_compress_roundtrip_all_leaves() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..13u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        let hasher = Blake3Hasher;
+
+        for i in 0..13 {
+            let proof = tree.generate_proof(i).unwrap();
+            let leaf_hash = tree.get_leaf_hash(i).unwrap();
+            let compressed = proof.compress(&leaf_hash, &hasher);
+            let encoded = compressed.to_bytes();
+            let decoded = CompressedProof::from_bytes(&encoded).unwrap();
+            assert_eq!(decoded, compressed);
+
+            let restored = ChronoProof::decompress(&decoded, &leaf_hash, &hasher).unwrap();
+            assert_eq!(restored.path, proof.path);
+            assert!(tree.verify_proof(&restored).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_duplicate_siblings_are_elided() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..5u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        // The last leaf of a 5-leaf tree is padded on every level above it
+        let proof = tree.generate_proof(4).unwrap();
+        let leaf_hash = tree.get_leaf_hash(4).unwrap();
+        let compressed = proof.compress(&leaf_hash, &Blake3Hasher);
+        assert_eq!(proof.path.len(), 3);
+        assert_eq!(compressed.hashes.len(), 1);
+        assert!(compressed.to_bytes().len() < proof.path.len() * 32);
+    }
+
+    #[test]
+    fn test_decompress_rejects_missing_hashes() {
+        let mut compressed: CompressedProof<[u8; 32]> = CompressedProof {
+            leaf_index: 0,
+            timestamp: 1000,
+            step_count: 1,
+            step_codes: vec![CODE_RIGHT],
+            hashes: Vec::new(),
+            delta_chain: None,
+            programmable_results: Vec::new(),
+            leaf_hash_mode: LeafHashMode::HashData,
+        };
+        assert!(ChronoProof::decompress(&compressed, &[0u8; 32], &Blake3Hasher).is_err());
+
+        compressed.hashes = vec![[1u8; 32], [2u8; 32]];
+        assert!(ChronoProof::decompress(&compressed, &[0u8; 32], &Blake3Hasher).is_err());
+    }
+}
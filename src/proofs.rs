@@ -2,6 +2,8 @@
 
 use crate::error::{ChronoMerkleError, Result};
 use crate::hash::HashFunction;
+#[cfg(not(feature = "minimal"))]
+use crate::security::{SecurityEventType, SecurityLevel};
 use crate::tree::ChronoMerkleTree;
 
 #[cfg(feature = "no-std")]
@@ -11,7 +13,7 @@ use std::vec::Vec;
 
 impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: HashFunction<Output = H> + Sync,
     Logger: crate::security::SecurityLogger,
 {
@@ -24,10 +26,20 @@ where
             });
         }
 
+        // A cache hit would reveal which leaves were proven recently
+        #[cfg(feature = "std")]
+        if let Some(proof) = self.cached_proof(leaf_index).filter(|_| !self.config.constant_time_proofs) {
+            crate::security::log_security_event!(self.security_logger, SecurityEventType::ProofGeneration, SecurityLevel::Info, || {
+                crate::security::events::proof_generation(leaf_index)
+            });
+            return Ok(proof);
+        }
+
         let leaf = &self.nodes[leaf_index];
         let (timestamp, _) = leaf.timestamp_info();
 
         let mut proof = crate::proof::ChronoProof::new(leaf_index, timestamp);
+        proof.leaf_hash_mode = self.config.leaf_hash_mode;
 
         if self.leaf_count == 1 {
             // Single leaf, no proof path needed
@@ -80,22 +92,74 @@ where
             current_index = next_index;
         }
 
+        #[cfg(feature = "std")]
+        if !self.config.constant_time_proofs {
+            self.cache_proof(&proof);
+        }
+
         // Log proof generation
-        let _ = self.security_logger.log_event(&crate::security::events::proof_generation(leaf_index));
+        crate::security::log_security_event!(self.security_logger, SecurityEventType::ProofGeneration, SecurityLevel::Info, || {
+            crate::security::events::proof_generation(leaf_index)
+        });
 
         Ok(proof)
     }
 
     /// Verify a proof against the current root
+    ///
+    /// Proofs whose `leaf_hash_mode` differs from the tree's are rejected.
+    ///
+    /// With `TreeConfig::constant_time_proofs` the path is checked by
+    /// [`verify_proof_constant_time`](crate::proof::verify_proof_constant_time),
+    /// padded to the depth of the tree, and malformed proofs yield `false`
+    /// rather than an error.
+    ///
+    /// Proofs longer than [`TreeConfig::max_depth`](crate::TreeConfig::max_depth)
+    /// or otherwise outside [`ChronoProof::check_bounds`](crate::proof::ChronoProof::check_bounds)
+    /// are rejected before any hashing.
     pub fn verify_proof(&self, proof: &crate::proof::ChronoProof<H>) -> Result<bool> {
+        if let Err(err) = proof.check_bounds(self.config.max_depth.max(self.depth())) {
+            crate::security::log_security_event!(self.security_logger, crate::security::events::proof_verification_failure(
+                proof.leaf_index,
+                proof.timestamp,
+                "Proof exceeds structural limits",
+            ));
+            return if self.config.constant_time_proofs { Ok(false) } else { Err(err) };
+        }
         let root_hash = self.root().ok_or(ChronoMerkleError::EmptyTree)?;
         let leaf_hash = self.get_leaf_hash(proof.leaf_index)?;
 
+        // A proof documenting another leaf hash mode does not describe this tree
+        if proof.leaf_hash_mode != self.config.leaf_hash_mode {
+            crate::security::log_security_event!(self.security_logger, crate::security::events::proof_verification_failure(
+                proof.leaf_index,
+                proof.timestamp,
+                "Proof leaf hash mode does not match the tree",
+            ));
+            return Ok(false);
+        }
+
         // Verify that proof timestamp matches the actual leaf timestamp
         let actual_timestamp = self.get_leaf_timestamp(proof.leaf_index)?;
+        if self.config.constant_time_proofs {
+            let timestamp_ok = crate::security::constant_time_eq(
+                &crate::encoding::encode_u64(proof.timestamp),
+                &crate::encoding::encode_u64(actual_timestamp),
+            );
+            let path_ok = crate::proof::verify_proof_constant_time(
+                proof,
+                &leaf_hash,
+                &root_hash,
+                &self.hasher,
+                self.depth(),
+            );
+            let result = timestamp_ok & path_ok;
+            self.log_verification_result(proof, result);
+            return Ok(result);
+        }
         if proof.timestamp != actual_timestamp {
             // Log the verification failure
-            let _ = self.security_logger.log_event(&crate::security::events::proof_verification_failure(
+            crate::security::log_security_event!(self.security_logger, crate::security::events::proof_verification_failure(
                 proof.leaf_index,
                 proof.timestamp,
                 &format!("Proof timestamp mismatch: expected {}, got {}", actual_timestamp, proof.timestamp),
@@ -104,21 +168,23 @@ where
         }
 
         let result = crate::proof::verify_proof(proof, &leaf_hash, &root_hash, &self.hasher)?;
+        self.log_verification_result(proof, result);
+        Ok(result)
+    }
 
-        // Log proof verification result
+    /// Log the outcome of a proof verification
+    #[cfg_attr(feature = "minimal", allow(unused_variables))]
+    fn log_verification_result(&self, proof: &crate::proof::ChronoProof<H>, result: bool) {
         if result {
-            let _ = self.security_logger.log_event(&crate::security::events::proof_verification_success(
-                proof.leaf_index,
-                proof.timestamp,
-            ));
+            crate::security::log_security_event!(self.security_logger, SecurityEventType::ProofVerificationSuccess, SecurityLevel::Info, || {
+                crate::security::events::proof_verification_success(proof.leaf_index, proof.timestamp)
+            });
         } else {
-            let _ = self.security_logger.log_event(&crate::security::events::proof_verification_failure(
+            crate::security::log_security_event!(self.security_logger, crate::security::events::proof_verification_failure(
                 proof.leaf_index,
                 proof.timestamp,
                 "Proof verification failed - invalid proof or tampered data",
             ));
         }
-
-        Ok(result)
     }
 }
\ No newline at end of file
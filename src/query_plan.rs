@@ -0,0 +1,204 @@
+//! Explanations of how range queries are answered
+//!
+//! [`ChronoMerkleTree::explain_range`] runs the same search as
+//! [`find_range`](ChronoMerkleTree::find_range) and reports the strategy it
+//! took, how much of the tree it touched and how well the sparse index
+//! predicted the number of matches, to help choose sparsity and index budgets
+//! on real data.
+//!
+//! While timestamps arrive in order, the sparse index bounds the leaves to
+//! scan. Once a timestamp arrives out of order the index no longer does, and
+//! the search descends from the root instead, skipping every subtree whose
+//! time range misses the query. A single leaf, or a query covering the whole
+//! tree, is answered by scanning the leaves.
+
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::vec::Vec;
+
+/// How a range query found its leaves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeStrategy {
+    /// Only leaves between the sparse index entries enclosing the range were scanned
+    SparseIndex,
+    /// Subtrees whose time range misses the query were skipped
+    IntervalDescent,
+    /// Every leaf was checked
+    FullScan,
+}
+
+/// Account of a range query, returned by [`ChronoMerkleTree::explain_range`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPlan {
+    /// Strategy used
+    pub strategy: RangeStrategy,
+    /// Leaves and internal nodes examined
+    pub nodes_visited: usize,
+    /// Leaves whose timestamp was compared with the range
+    pub leaves_scanned: usize,
+    /// Entries in the sparse index
+    pub index_entries: usize,
+    /// Sparsity of the sparse index when the query ran
+    pub sparsity: u64,
+    /// Matches predicted from the index entries inside the range
+    pub estimated_matches: usize,
+    /// Leaves in the range
+    pub actual_matches: usize,
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Describe how [`find_range`](Self::find_range) answers `start..=end`
+    pub fn explain_range(&self, start: u64, end: u64) -> QueryPlan {
+        self.search_range(start, end, |_| ())
+    }
+
+    /// Find the leaves with timestamps in `start..=end`, in leaf order
+    pub(crate) fn search_range(&self, start: u64, end: u64, mut on_match: impl FnMut(usize)) -> QueryPlan {
+        let index = &self.sparse_index;
+        let estimated = (index.count_range(start, end) as u64).saturating_mul(index.sparsity());
+        let mut plan = QueryPlan {
+            strategy: RangeStrategy::FullScan,
+            nodes_visited: 0,
+            leaves_scanned: 0,
+            index_entries: index.len(),
+            sparsity: index.sparsity(),
+            estimated_matches: estimated.min(self.leaf_count as u64) as usize,
+            actual_matches: 0,
+        };
+        let mut scan = |leaf_index: usize, plan: &mut QueryPlan| {
+            plan.nodes_visited += 1;
+            plan.leaves_scanned += 1;
+            let (timestamp, _) = self.nodes[leaf_index].timestamp_info();
+            if (start..=end).contains(&timestamp) {
+                plan.actual_matches += 1;
+                on_match(leaf_index);
+            }
+        };
+
+        let (first, last) = if index.is_ordered() {
+            plan.strategy = RangeStrategy::SparseIndex;
+            let (before, after) = index.bounding_indices(start, end);
+            let last = after.map_or(self.leaf_count, |idx| idx.min(self.leaf_count));
+            (before.map_or(0, |idx| (idx + 1).min(last)), last)
+        } else {
+            let covers_tree = self.root_node().is_none_or(|root| {
+                let (low, high) = root.timestamp_info();
+                start <= low && high.unwrap_or(low) <= end
+            });
+            if self.leaf_count > 1 && !covers_tree {
+                plan.strategy = RangeStrategy::IntervalDescent;
+                self.descend_range(start, end, &mut plan, &mut scan);
+                return plan;
+            }
+            (0, self.leaf_count)
+        };
+        for leaf_index in first..last {
+            scan(leaf_index, &mut plan);
+        }
+        plan
+    }
+
+    /// Offset in `nodes` and width of every level, leaves first and root last
+    pub(crate) fn level_spans(&self) -> Vec<(usize, usize)> {
+        let mut spans = Vec::new();
+        let (mut offset, mut width) = (0, self.leaf_count);
+        while width > 1 {
+            spans.push((offset, width));
+            offset += width;
+            width = width.div_ceil(2);
+        }
+        spans.push((offset, width));
+        spans
+    }
+
+    /// Root node, which for a single leaf is the leaf itself
+    fn root_node(&self) -> Option<&crate::node::Node<H>> {
+        self.nodes.last().filter(|_| self.leaf_count > 0)
+    }
+
+    /// Visit the leaves under internal nodes whose time range meets `start..=end`
+    fn descend_range(
+        &self,
+        start: u64,
+        end: u64,
+        plan: &mut QueryPlan,
+        scan: &mut impl FnMut(usize, &mut QueryPlan),
+    ) {
+        let levels = self.level_spans();
+
+        // Right child pushed first so leaves are visited in order
+        let mut stack = Vec::from([(levels.len() - 1, 0)]);
+        while let Some((level, position)) = stack.pop() {
+            if level == 0 {
+                scan(position, plan);
+                continue;
+            }
+            plan.nodes_visited += 1;
+            let (low, high) = self.nodes[levels[level].0 + position].timestamp_info();
+            if high.unwrap_or(low) < start || low > end {
+                continue;
+            }
+            let child = 2 * position;
+            if child + 1 < levels[level - 1].1 {
+                stack.push((level - 1, child + 1));
+            }
+            stack.push((level - 1, child));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::{Blake3Hasher, TreeConfig};
+
+    #[test]
+    fn test_explain_range() {
+        let config = TreeConfig { sparse_index_sparsity: 4, ..TreeConfig::default() };
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+        for i in 0..64u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+
+        let plan = tree.explain_range(1010, 1019);
+        assert_eq!(plan.strategy, RangeStrategy::SparseIndex);
+        assert_eq!((plan.index_entries, plan.sparsity), (16, 4));
+        assert_eq!((plan.actual_matches, plan.estimated_matches), (10, 8));
+        assert!(plan.leaves_scanned < 20);
+
+        // Out-of-order timestamps switch to descending the tree
+        tree.insert(b"late", 1015).unwrap();
+        let plan = tree.explain_range(1010, 1019);
+        assert_eq!(plan.strategy, RangeStrategy::IntervalDescent);
+        assert_eq!(plan.actual_matches, 11);
+        assert!(plan.leaves_scanned < 30 && plan.nodes_visited < tree.nodes.len());
+        assert_eq!(tree.find_range(1014, 1016), vec![14, 15, 16, 64]);
+        assert_eq!(tree.find_range(2000, 3000), Vec::<usize>::new());
+
+        let plan = tree.explain_range(0, u64::MAX);
+        assert_eq!((plan.strategy, plan.actual_matches, plan.leaves_scanned), (RangeStrategy::FullScan, 65, 65));
+    }
+
+    #[test]
+    fn test_descent_matches_scan() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..37u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + (i * 7919) % 50).unwrap();
+        }
+        assert_eq!(tree.explain_range(1010, 1020).strategy, RangeStrategy::IntervalDescent);
+        for (start, end) in [(1000, 1000), (1010, 1020), (1049, 1100), (990, 1003), (1021, 1019)] {
+            let expected: Vec<usize> = (0..tree.leaf_count())
+                .filter(|&i| (start..=end).contains(&tree.get_leaf_timestamp(i).unwrap()))
+                .collect();
+            assert_eq!(tree.find_range(start, end), expected);
+        }
+    }
+}
@@ -2,7 +2,8 @@
 
 use crate::error::Result;
 use crate::hash::HashFunction;
-use crate::node::{Node, NodeType};
+use crate::node::Node;
+use crate::snapshot::SharedVec;
 use crate::tree::ChronoMerkleTree;
 
 #[cfg(feature = "no-std")]
@@ -10,78 +11,61 @@ use alloc::vec::Vec;
 #[cfg(not(feature = "no-std"))]
 use std::vec::Vec;
 
+/// Parent of `children[left]` and its right sibling, duplicating the last child of an odd level
+fn parent_node<H, Hasher>(children: &SharedVec<Node<H>>, left: usize, hasher: &Hasher) -> Node<H>
+where
+    H: AsRef<[u8]> + Clone,
+    Hasher: HashFunction<Output = H>,
+{
+    let left_node = &children[left];
+    let right_node = children.get(left + 1).unwrap_or(left_node);
+    let (left_start, left_end) = left_node.timestamp_info();
+    let (right_start, right_end) = right_node.timestamp_info();
+    let timestamp_range = (
+        left_start.min(right_start),
+        left_end.unwrap_or(left_start).max(right_end.unwrap_or(right_start)),
+    );
+    let (left_hash, right_hash) = (left_node.hash(), right_node.hash());
+    Node::internal(hasher.hash_pair(&left_hash, &right_hash), left_hash, right_hash, timestamp_range)
+}
+
 impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: HashFunction<Output = H> + Sync,
     Logger: crate::security::SecurityLogger,
 {
     /// Rebuild the entire tree from leaves (used when incremental updates are disabled)
     pub(crate) fn rebuild_tree(&mut self) -> Result<()> {
-        if self.leaf_count == 0 {
-            // Clear all internal nodes (everything after leaf_count)
-            self.nodes.truncate(self.leaf_count);
-            return Ok(());
-        }
-
-        // For trees with 1 leaf, no internal nodes needed
-        if self.leaf_count == 1 {
-            // Ensure we only have the leaf
-            self.nodes.truncate(1);
+        // Only the leaves are kept; every level above them is rebuilt
+        self.nodes.truncate_levels(1);
+        if self.leaf_count <= 1 {
+            self.nodes.leaves_mut().truncate(self.leaf_count);
             return Ok(());
         }
 
-        // Extract current leaves (all leaf nodes in the tree)
-        let current_leaves: Vec<Node<H>> = self.nodes.iter()
-            .filter(|node| matches!(node.node_type, NodeType::Leaf { .. }))
-            .cloned()
-            .collect();
-
-        // Clear all nodes and re-insert the leaves
-        self.nodes.clear();
-        self.nodes.extend(current_leaves);
-
         // Build the complete binary tree by iteratively combining nodes
-        let mut current_start = 0;
-        let mut current_count = self.leaf_count;
-
-        while current_count > 1 {
-            let next_count = current_count.div_ceil(2);
-
-            // Prepare the parent nodes for this level
-            let parent_nodes: Vec<Node<H>> = (0..next_count)
-                .map(|i| {
-                    let left_idx = current_start + 2 * i;
-                    let right_idx = current_start + 2 * i + 1;
-
-                    let left_node = &self.nodes[left_idx];
-                    let right_node = if right_idx < current_start + current_count {
-                        &self.nodes[right_idx]
-                    } else {
-                        // Duplicate the last node for odd counts
-                        &self.nodes[current_start + current_count - 1]
-                    };
-
-                    let left_hash = left_node.hash();
-                    let right_hash = right_node.hash();
-
-                    let (left_start, left_end) = left_node.timestamp_info();
-                    let (right_start, right_end) = right_node.timestamp_info();
-
-                    let timestamp_range = (
-                        left_start.min(right_start),
-                        left_end.unwrap_or(left_start).max(right_end.unwrap_or(right_start)),
-                    );
-
-                    let internal_hash = self.hasher.hash_pair(&left_hash, &right_hash);
-                    Node::internal(internal_hash, left_hash, right_hash, timestamp_range)
-                })
+        let mut level = 0;
+        #[cfg(feature = "std")]
+        let started = std::time::Instant::now();
+
+        while self.nodes.level(level).len() > 1 {
+            self.check_cancelled("rebuild")?;
+            let children = self.nodes.level(level);
+            // Duplicate the last node for odd counts
+            let parent_nodes: Vec<Node<H>> = (0..children.len().div_ceil(2))
+                .map(|i| parent_node(children, 2 * i, &self.hasher))
                 .collect();
 
-            self.nodes.extend(parent_nodes);
+            level += 1;
+            let next_count = parent_nodes.len();
+            *self.nodes.level_mut(level) = parent_nodes.into();
+            self.report_level_built(level, next_count);
+        }
 
-            current_start += current_count;
-            current_count = next_count;
+        #[cfg(feature = "std")]
+        if let Some(observer) = &self.progress_observer {
+            observer.on_rebuild_complete(started.elapsed());
         }
 
         Ok(())
@@ -91,268 +75,173 @@ where
     pub(crate) fn rebuild_tree_parallel(&mut self) -> Result<()> {
         use rayon::prelude::*;
 
-        if self.leaf_count == 0 {
-            // Clear all internal nodes (everything after leaf_count)
-            self.nodes.truncate(self.leaf_count);
-            return Ok(());
-        }
-
-        // For trees with 1 leaf, no internal nodes needed
-        if self.leaf_count == 1 {
-            // Ensure we only have the leaf
-            self.nodes.truncate(1);
+        // Only the leaves are kept; every level above them is rebuilt
+        self.nodes.truncate_levels(1);
+        if self.leaf_count <= 1 {
+            self.nodes.leaves_mut().truncate(self.leaf_count);
             return Ok(());
         }
 
-        // Extract current leaves (all leaf nodes in the tree)
-        let current_leaves: Vec<Node<H>> = self.nodes.iter()
-            .filter(|node| matches!(node.node_type, NodeType::Leaf { .. }))
-            .cloned()
-            .collect();
-
-        // Clear all nodes and re-insert the leaves
-        self.nodes.clear();
-        self.nodes.extend(current_leaves);
+        let pool = self.parallel_pool()?;
 
         // Build the complete binary tree by iteratively combining nodes
-        let mut current_start = 0;
-        let mut current_count = self.leaf_count;
+        let mut level = 0;
+        #[cfg(feature = "std")]
+        let started = std::time::Instant::now();
 
-        while current_count > 1 {
-            let next_count = current_count.div_ceil(2);
+        while self.nodes.level(level).len() > 1 {
+            self.check_cancelled("rebuild")?;
+            let children = self.nodes.level(level);
+            let next_count = children.len().div_ceil(2);
+            let hasher = &self.hasher;
 
             // Compute all parent nodes in parallel using Rayon's work-stealing scheduler
             // This leverages intra-level parallelization and hash computation parallelization
-            let parent_nodes: Vec<Node<H>> = (0..next_count)
-                .into_par_iter()
-                .map(|i| {
-                    let left_idx = current_start + 2 * i;
-                    let right_idx = current_start + 2 * i + 1;
-
-                    let left_node = &self.nodes[left_idx];
-                    let right_node = if right_idx < current_start + current_count {
-                        &self.nodes[right_idx]
-                    } else {
-                        // Duplicate the last node for odd counts
-                        &self.nodes[current_start + current_count - 1]
-                    };
-
-                    let left_hash = left_node.hash();
-                    let right_hash = right_node.hash();
-
-                    let (left_start, left_end) = left_node.timestamp_info();
-                    let (right_start, right_end) = right_node.timestamp_info();
+            let build_level = || -> Vec<Node<H>> {
+                (0..next_count)
+                    .into_par_iter()
+                    .map(|i| parent_node(children, 2 * i, hasher))
+                    .collect()
+            };
 
-                    let timestamp_range = (
-                        left_start.min(right_start),
-                        left_end.unwrap_or(left_start).max(right_end.unwrap_or(right_start)),
-                    );
+            // Run on the configured pool if there is one, otherwise on the global pool
+            let mut parent_nodes = match &pool {
+                Some(pool) => pool.install(build_level),
+                None => build_level(),
+            };
 
-                    // Hash computation is parallelized here
-                    let internal_hash = self.hasher.hash_pair(&left_hash, &right_hash);
-                    Node::internal(internal_hash, left_hash, right_hash, timestamp_range)
-                })
-                .collect();
+            level += 1;
+            if self.config.parallel_verify_every.is_some_and(|every| level % every == 0) {
+                self.verify_parallel_level(level, &mut parent_nodes);
+            }
 
-            self.nodes.extend(parent_nodes);
+            *self.nodes.level_mut(level) = parent_nodes.into();
+            self.report_level_built(level, next_count);
+        }
 
-            current_start += current_count;
-            current_count = next_count;
+        #[cfg(feature = "std")]
+        if let Some(observer) = &self.progress_observer {
+            observer.on_rebuild_complete(started.elapsed());
         }
 
         Ok(())
     }
 
-    /// Update the tree incrementally after inserting a new leaf
-    pub(crate) fn update_tree_incremental(&mut self) -> Result<()> {
-        if self.leaf_count == 0 {
-            return Ok(());
-        }
-
-        if self.leaf_count == 1 {
-            // Single leaf tree - no internal nodes needed
-            // Remove any existing internal nodes
-            self.nodes.truncate(1);
-            return Ok(());
-        }
-
-        // Ensure we have enough capacity for internal nodes
-        self.ensure_tree_capacity()?;
-
-        // Rebuild internal nodes from the bottom up
-        // This is more efficient than full rebuild since leaves stay in place
-        self.rebuild_internal_nodes_incremental()?;
-        #[cfg(feature = "parallel")]
-        if self.config.parallel_construction {
-            self.rebuild_tree_parallel()?;
-        } else {
-            self.rebuild_tree()?;
-        }
-        #[cfg(not(feature = "parallel"))]
-        {
-            self.rebuild_tree()?;
+    /// Compare a level built in parallel against a sequential rebuild
+    ///
+    /// Nodes that differ are logged as Critical events and replaced by the
+    /// sequential result, so a faulty parallel build cannot reach the root.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn verify_parallel_level(&self, level: usize, parents: &mut [Node<H>]) {
+        let children = self.nodes.level(level - 1);
+        for (i, parent) in parents.iter_mut().enumerate() {
+            let expected = parent_node(children, 2 * i, &self.hasher);
+            if expected.hash() != parent.hash() {
+                crate::security::log_security_event!(
+                    self.security_logger,
+                    crate::security::events::parallel_construction_divergence(level, i)
+                );
+                *parent = expected;
+            }
         }
-
-        Ok(())
     }
 
-    /// Rebuild internal nodes starting from a leaf up to the root
-    #[allow(dead_code)]
-    pub(crate) fn rebuild_from_leaf(&mut self, leaf_index: usize) -> Result<()> {
-        // Rebuild internal nodes starting from the affected leaf up to the root
-        // This is more efficient than rebuilding the entire tree
-
-        if self.leaf_count <= 1 {
-            return Ok(());
+    /// Tell the progress observer, if any, that a level was built
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn report_level_built(&self, level: usize, nodes: usize) {
+        #[cfg(feature = "std")]
+        if let Some(observer) = &self.progress_observer {
+            observer.on_level_built(level, nodes);
         }
+    }
 
+    /// Use an external rayon thread pool for parallel construction
+    ///
+    /// Takes precedence over `TreeConfig::parallel_threads`, letting embedding
+    /// applications bound the CPU used by tree rebuilds.
+    #[cfg(feature = "parallel")]
+    pub fn set_thread_pool(&mut self, pool: std::sync::Arc<rayon::ThreadPool>) {
+        self.thread_pool = Some(pool);
+    }
 
-        // Leaves are stored at the beginning of the nodes array (indices 0, 1, 2, ...)
-        let leaf_node_index = leaf_index;
-
-        // Start from this leaf and work up to the root
-        let mut current_index = leaf_node_index;
-
-        while current_index > 0 {
-            // Find the parent of current_index
-            let parent_index = (current_index - 1) / 2;
-
-            // Get both children of this parent
-            let left_child_index = 2 * parent_index + 1;
-            let right_child_index = 2 * parent_index + 2;
-
-            // Make sure both children exist
-            if left_child_index >= self.nodes.len() || right_child_index >= self.nodes.len() {
-                // If we don't have both children yet, we can't rebuild this parent
-                break;
+    /// Get the thread pool used for parallel construction, building one from
+    /// `parallel_threads` on first use
+    #[cfg(feature = "parallel")]
+    pub(crate) fn parallel_pool(&mut self) -> Result<Option<std::sync::Arc<rayon::ThreadPool>>> {
+        if self.thread_pool.is_none() {
+            if let Some(threads) = self.config.parallel_threads {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(|e| crate::error::ChronoMerkleError::InvalidConfiguration {
+                        parameter: "parallel_threads".to_string(),
+                        reason: format!("Failed to build thread pool: {}", e),
+                    })?;
+                self.thread_pool = Some(std::sync::Arc::new(pool));
             }
-
-            let left_child = &self.nodes[left_child_index];
-            let right_child = &self.nodes[right_child_index];
-
-            // Compute new parent hash
-            let parent_hash = self.hasher.hash_pair(&left_child.hash(), &right_child.hash());
-
-            // Compute timestamp range
-            let left_ts = left_child.timestamp_info();
-            let right_ts = right_child.timestamp_info();
-            let start = left_ts.0.min(right_ts.0);
-            let end = match (left_ts.1, right_ts.1) {
-                (Some(l), Some(r)) => Some(l.max(r)),
-                (Some(l), None) => Some(l.max(right_ts.0)),
-                (None, Some(r)) => Some(r.max(left_ts.0)),
-                (None, None) => Some(left_ts.0.max(right_ts.0)),
-            };
-
-            // Update the parent node
-            let new_parent = Node::internal(
-                parent_hash,
-                left_child.hash(),
-                right_child.hash(),
-                (start, end.unwrap_or(start))
-            );
-
-            self.nodes[parent_index] = new_parent;
-
-            // Move up to parent
-            current_index = parent_index;
         }
-
-        Ok(())
+        Ok(self.thread_pool.clone())
     }
 
-    /// Rebuild internal nodes incrementally after a leaf insertion
-    pub(crate) fn rebuild_internal_nodes_incremental(&mut self) -> Result<()> {
-        // Extract current leaves (maintain insertion order for consistency with rebuild_tree)
-        let current_leaves: Vec<Node<H>> = self.nodes.iter()
-            .filter(|node| matches!(node.node_type, NodeType::Leaf { .. }))
-            .cloned()
-            .collect();
-
-        // Clear all nodes and re-insert leaves
-        self.nodes.clear();
-        self.nodes.extend(current_leaves);
-
-        // Build the complete binary tree by iteratively combining nodes
-        let mut current_start = 0;
-        let mut current_count = self.leaf_count;
-
-        while current_count > 1 {
-            let next_count = current_count.div_ceil(2);
-
-            // Prepare the parent nodes for this level
-            let parent_nodes: Vec<Node<H>> = (0..next_count)
-                .map(|i| {
-                    let left_idx = current_start + 2 * i;
-                    let right_idx = current_start + 2 * i + 1;
-
-                    let left_node = &self.nodes[left_idx];
-                    let right_node = if right_idx < current_start + current_count {
-                        &self.nodes[right_idx]
-                    } else {
-                        // Duplicate the last node for odd counts
-                        &self.nodes[current_start + current_count - 1]
-                    };
-
-                    let left_hash = left_node.hash();
-                    let right_hash = right_node.hash();
-
-                    let (left_start, left_end) = left_node.timestamp_info();
-                    let (right_start, right_end) = right_node.timestamp_info();
-
-                    let timestamp_range = (
-                        left_start.min(right_start),
-                        left_end.unwrap_or(left_start).max(right_end.unwrap_or(right_start)),
-                    );
-
-                    let internal_hash = self.hasher.hash_pair(&left_hash, &right_hash);
-                    Node::internal(internal_hash, left_hash, right_hash, timestamp_range)
-                })
-                .collect();
-
-            self.nodes.extend(parent_nodes);
-
-            current_start += current_count;
-            current_count = next_count;
-        }
-
-        Ok(())
+    /// Update the internal nodes after leaves were appended
+    ///
+    /// Expects the first `old_leaf_count` leaves followed by the new ones on
+    /// the leaf level, and the internal levels of the old leaves. Only nodes
+    /// above the new leaves are rehashed and each level is only truncated and
+    /// pushed onto, so appending one leaf touches one node per level.
+    pub(crate) fn update_tree_incremental(&mut self, old_leaf_count: usize) -> Result<()> {
+        self.update_tree_dirty(old_leaf_count, Vec::new()).map(drop)
     }
 
-    /// Ensure the tree has enough capacity for all nodes
-    pub(crate) fn ensure_tree_capacity(&mut self) -> Result<()> {
-        // Calculate the total number of nodes needed for a complete binary tree
-        // with self.leaf_count leaves
-
-        if self.leaf_count == 0 {
-            return Ok(());
+    /// Update the internal nodes after leaves were appended and existing leaves replaced
+    ///
+    /// As [`update_tree_incremental`](Self::update_tree_incremental), and
+    /// additionally rehashes the ancestors of the replaced leaves in `changed`,
+    /// sorted and below `old_leaf_count`. Each level rehashes every dirty node
+    /// once, however many changed leaves lie below it. Returns the number of
+    /// nodes rehashed.
+    pub(crate) fn update_tree_dirty(&mut self, old_leaf_count: usize, mut changed: Vec<usize>) -> Result<usize> {
+        let appended = self.leaf_count - old_leaf_count;
+        if self.leaf_count <= 1 {
+            self.nodes.truncate_levels(1);
+            return Ok(0);
         }
 
-        let total_nodes_needed = if self.leaf_count == 1 {
-            1
-        } else {
-            // Find the smallest power of 2 greater than or equal to leaf_count
-            let mut capacity = 1;
-            while capacity < self.leaf_count {
-                capacity *= 2;
+        // Rehash from the first parent of a new or previously unpaired child,
+        // and the parents of changed nodes before it
+        #[cfg(feature = "std")]
+        let started = std::time::Instant::now();
+        let (mut width, mut old_width, mut dirty) = (self.leaf_count, old_leaf_count, old_leaf_count);
+        let (mut level, mut rehashed) = (0, 0);
+        while width > 1 {
+            self.check_cancelled("rebuild")?;
+            level += 1;
+            width = width.div_ceil(2);
+            old_width = if old_width > 1 { old_width.div_ceil(2) } else { 0 };
+            dirty = if appended == 0 { width } else { (dirty / 2).min(old_width) };
+            for position in &mut changed {
+                *position /= 2;
             }
-            // Total nodes = 2^ceil(log2(leaf_count)) - 1
-            capacity * 2 - 1
-        };
+            changed.dedup();
+            changed.retain(|&position| position < dirty);
 
-        // Ensure we have enough space by adding placeholder nodes
-        while self.nodes.len() < total_nodes_needed {
-            // Add placeholder internal nodes that will be properly initialized
-            let zero_hash = self.hasher.hash(b""); // Use hash of empty string as placeholder
-            let placeholder = Node::internal(
-                zero_hash.clone(), // Will be updated
-                zero_hash.clone(), // Will be updated
-                zero_hash, // Will be updated
-                (0, 0) // Will be updated
-            );
-            self.nodes.push(placeholder);
+            let (children, parents) = self.nodes.parent_level_mut(level);
+            parents.truncate(dirty);
+            for &i in &changed {
+                parents[i] = parent_node(children, 2 * i, &self.hasher);
+            }
+            for i in parents.len()..width {
+                parents.push(parent_node(children, 2 * i, &self.hasher));
+            }
+            rehashed += changed.len() + width - dirty;
+            self.report_level_built(level, width);
         }
 
-        Ok(())
+        #[cfg(feature = "std")]
+        if let Some(observer) = &self.progress_observer {
+            observer.on_rebuild_complete(started.elapsed());
+        }
+
+        Ok(rehashed)
     }
-}
\ No newline at end of file
+}
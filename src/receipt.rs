@@ -0,0 +1,249 @@
+//! Printable inclusion receipts for emails and paper
+//!
+//! A [`PrintableReceipt`] is an inclusion proof small enough to put in an
+//! email footer or print under a PDF. The text form holds the root and leaf
+//! hash in hex, the leaf timestamp, the compressed proof path in base32 and a
+//! CRC-32 checksum:
+//!
+//! ```text
+//! CHRONO-MERKLE RECEIPT 1
+//! ROOT 5f2c...
+//! LEAF 9a01...
+//! TIME 1700000000
+//! PATH AEAQCAIB EAYDGMBQ GAYDAMBQ ...
+//! CHECK 1c291ca3
+//! ```
+//!
+//! [`PrintableReceipt::parse`] accepts text that was re-wrapped, re-indented
+//! or lowercased on the way, and the checksum catches typos when a receipt
+//! is typed back in. A parsed receipt only proves inclusion under the root it
+//! names; [`PrintableReceipt::verify`] therefore takes the root the verifier
+//! trusts, e.g. one published by the log operator.
+
+use crate::codec;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::{verify_proof, ChronoProof};
+use crate::proof_compression::CompressedProof;
+use crate::security::SecurityLogger;
+use crate::traits::HashOutput;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{format, string::{String, ToString}, vec::Vec};
+
+/// First line of every receipt
+const HEADER: &str = "CHRONO-MERKLE RECEIPT 1";
+
+/// Base32 characters per group and groups per line of the path
+const GROUP: usize = 8;
+const GROUPS_PER_LINE: usize = 6;
+
+/// Inclusion proof in a compact printable form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintableReceipt<H> {
+    /// Root the leaf is proven against
+    pub root: H,
+    /// Hash of the leaf
+    pub leaf_hash: H,
+    /// Proof path with duplicate siblings elided
+    pub proof: CompressedProof<H>,
+}
+
+impl<H: HashOutput> PrintableReceipt<H> {
+    /// Build a receipt from an inclusion proof of `leaf_hash` under `root`
+    pub fn new<Hasher>(proof: &ChronoProof<H>, leaf_hash: H, root: H, hasher: &Hasher) -> Self
+    where
+        Hasher: HashFunction<Output = H>,
+    {
+        let proof = proof.compress(&leaf_hash, hasher);
+        Self { root, leaf_hash, proof }
+    }
+
+    /// Timestamp of the leaf
+    pub fn timestamp(&self) -> u64 {
+        self.proof.timestamp
+    }
+
+    /// Index of the leaf
+    pub fn leaf_index(&self) -> usize {
+        self.proof.leaf_index
+    }
+
+    /// Format the receipt as text
+    pub fn to_text(&self) -> String {
+        let path = codec::to_base32(&self.proof.to_bytes());
+        let mut text = format!(
+            "{}\nROOT {}\nLEAF {}\nTIME {}\n",
+            HEADER,
+            codec::to_hex(self.root.as_bytes()),
+            codec::to_hex(self.leaf_hash.as_bytes()),
+            self.proof.timestamp,
+        );
+        let groups: Vec<&str> = path
+            .as_bytes()
+            .chunks(GROUP)
+            .map(|group| core::str::from_utf8(group).unwrap_or_default())
+            .collect();
+        for line in groups.chunks(GROUPS_PER_LINE) {
+            text.push_str("PATH ");
+            text.push_str(&line.join(" "));
+            text.push('\n');
+        }
+        text.push_str(&format!("CHECK {:08x}\n", self.checksum()));
+        text
+    }
+
+    /// Parse a receipt produced by [`to_text`](Self::to_text)
+    ///
+    /// Whitespace and case are not significant, so receipts survive mail
+    /// clients re-wrapping them. Lines before the header and after the checksum
+    /// are ignored.
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid = |message: &str| ChronoMerkleError::InvalidProof { message: message.to_string() };
+        let lines = text
+            .lines()
+            .map(str::trim)
+            .skip_while(|line| !line.eq_ignore_ascii_case(HEADER))
+            .skip(1)
+            .filter(|line| !line.is_empty());
+
+        // Field values in order ROOT, LEAF, TIME, PATH, CHECK; lines that do
+        // not start with a field name continue the previous field
+        const FIELDS: [&str; 5] = ["ROOT", "LEAF", "TIME", "PATH", "CHECK"];
+        let mut values: [Option<String>; 5] = Default::default();
+        let mut current = None;
+        for line in lines {
+            let (first, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let (field, value) = match FIELDS.iter().position(|name| first.eq_ignore_ascii_case(name)) {
+                Some(field) => (field, rest),
+                None => (current.ok_or_else(|| invalid("Unknown receipt field"))?, line),
+            };
+            values[field].get_or_insert_with(String::new).extend(value.split_whitespace());
+            current = Some(field);
+            if field == 4 && values[4].as_ref().is_some_and(|check| check.len() >= 8) {
+                break;
+            }
+        }
+
+        let hash = |value: Option<String>, name: &str| {
+            let value = value.ok_or_else(|| invalid(&format!("Receipt is missing {}", name)))?;
+            codec::from_hex(&value)
+                .and_then(|bytes| H::from_bytes(&bytes))
+                .ok_or_else(|| invalid(&format!("Receipt {} is not a valid hash", name)))
+        };
+        let [root, leaf_hash, timestamp, path, checksum] = values;
+        let root = hash(root, "ROOT")?;
+        let leaf_hash = hash(leaf_hash, "LEAF")?;
+        let timestamp: u64 = timestamp
+            .ok_or_else(|| invalid("Receipt is missing TIME"))?
+            .parse()
+            .map_err(|_| invalid("Receipt TIME is not a number"))?;
+        let checksum = checksum
+            .and_then(|value| u32::from_str_radix(&value, 16).ok())
+            .ok_or_else(|| invalid("Receipt is missing a valid CHECK"))?;
+        let bytes = codec::from_base32(&path.unwrap_or_default()).ok_or_else(|| invalid("Receipt PATH is not valid base32"))?;
+        let proof = CompressedProof::from_bytes(&bytes)?;
+
+        let receipt = Self { root, leaf_hash, proof };
+        if receipt.checksum() != checksum {
+            return Err(invalid("Receipt checksum mismatch"));
+        }
+        if receipt.proof.timestamp != timestamp {
+            return Err(invalid("Receipt TIME does not match its proof"));
+        }
+        Ok(receipt)
+    }
+
+    /// Check that the receipt proves its leaf under `trusted_root`
+    ///
+    /// Returns `Ok(false)` if the receipt names another root or its path does
+    /// not lead to it.
+    pub fn verify<Hasher>(&self, trusted_root: &H, hasher: &Hasher) -> Result<bool>
+    where
+        Hasher: HashFunction<Output = H>,
+    {
+        if self.root != *trusted_root {
+            return Ok(false);
+        }
+        let proof = ChronoProof::decompress(&self.proof, &self.leaf_hash, hasher)?;
+        verify_proof(&proof, &self.leaf_hash, &self.root, hasher)
+    }
+
+    /// CRC-32 over the binary content, independent of how the text is laid out
+    fn checksum(&self) -> u32 {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(self.root.as_bytes());
+        bytes.extend_from_slice(self.leaf_hash.as_bytes());
+        bytes.extend_from_slice(&self.proof.to_bytes());
+        codec::crc32(&bytes)
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: HashOutput + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Issue a printable receipt for the leaf at `leaf_index` against the current root
+    pub fn printable_receipt(&self, leaf_index: usize) -> Result<PrintableReceipt<H>> {
+        let proof = self.generate_proof(leaf_index)?;
+        let leaf_hash = self.get_leaf_hash(leaf_index)?;
+        let root = self.root().ok_or_else(|| ChronoMerkleError::InvalidProof {
+            message: "Tree is empty".to_string(),
+        })?;
+        Ok(PrintableReceipt::new(&proof, leaf_hash, root, &self.hasher))
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_roundtrip() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..11u64 {
+            tree.insert(format!("invoice{}", i).as_bytes(), 1_700_000_000 + i).unwrap();
+        }
+        let root = tree.root().unwrap();
+        let receipt = tree.printable_receipt(10).unwrap();
+        let text = receipt.to_text();
+        assert!(text.starts_with(HEADER) && text.contains("TIME 1700000010\n"));
+
+        let parsed = PrintableReceipt::<[u8; 32]>::parse(&text).unwrap();
+        assert_eq!(parsed, receipt);
+        assert_eq!(parsed.leaf_index(), 10);
+        assert!(parsed.verify(&root, &crate::Blake3Hasher).unwrap());
+        assert!(!parsed.verify(&[0u8; 32], &crate::Blake3Hasher).unwrap());
+
+        // Lowercased, indented and re-wrapped by a mail client
+        let mut mangled = text.to_lowercase().replace('\n', "\n  ");
+        let wrap = mangled.find("path ").unwrap() + 9;
+        mangled.insert_str(wrap, "\n    ");
+        let mangled = format!("Your receipt:\n\n  {}\nThank you", mangled);
+        assert_eq!(PrintableReceipt::<[u8; 32]>::parse(&mangled).unwrap(), receipt);
+    }
+
+    #[test]
+    fn test_receipt_rejects_typos() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..4u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        let text = tree.printable_receipt(1).unwrap().to_text();
+
+        let path_line = text.lines().find(|line| line.starts_with("PATH")).unwrap();
+        let mut typo = path_line.to_string();
+        let last = typo.pop().unwrap();
+        typo.push(if last == 'A' { 'B' } else { 'A' });
+        let err = PrintableReceipt::<[u8; 32]>::parse(&text.replace(path_line, &typo));
+        assert!(err.is_err());
+
+        let time_changed = text.replace("TIME 1001", "TIME 1002");
+        assert!(PrintableReceipt::<[u8; 32]>::parse(&time_changed).is_err());
+        assert!(PrintableReceipt::<[u8; 32]>::parse(&text.replace("CHECK", "CHEK")).is_err());
+        assert!(PrintableReceipt::<[u8; 32]>::parse("no receipt here").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Erasure of leaf data with verifiable tombstones
+//!
+//! [`ChronoMerkleTree::redact_leaf`] drops the stored data of a leaf but keeps
+//! its hash, so the root and every proof issued before stay valid. The
+//! erasure is recorded as a [`Redaction`], signed by the tree's
+//! [`RedactionSigner`] if one is registered, and later reads of the leaf
+//! return [`LeafData::Redacted`] instead of the data.
+
+use crate::error::Result;
+use crate::hash::HashFunction;
+use crate::node::NodeType;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{collections::BTreeMap, string::{String, ToString}, sync::Arc, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::{collections::BTreeMap, sync::Arc, vec::Vec};
+
+/// Domain separator of the signed redaction record
+const REDACTION_DOMAIN: &[u8] = b"chrono-merkle-redaction-v1";
+
+/// Tombstone recording that a leaf's data was erased
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction<H> {
+    /// Index of the redacted leaf
+    pub index: usize,
+    /// Hash of the leaf, unchanged by the redaction
+    pub leaf_hash: H,
+    /// Why the data was erased
+    pub reason: String,
+    /// Unix timestamp of the redaction
+    pub redacted_at: u64,
+    /// Signature over [`signing_bytes`](Self::signing_bytes), if a signer was registered
+    pub signature: Option<Vec<u8>>,
+}
+
+impl<H: AsRef<[u8]>> Redaction<H> {
+    /// Bytes covered by the signature
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = REDACTION_DOMAIN.to_vec();
+        bytes.extend_from_slice(&crate::encoding::encode_index(self.index));
+        bytes.extend_from_slice(self.leaf_hash.as_ref());
+        bytes.extend_from_slice(&crate::encoding::encode_u64(self.redacted_at));
+        bytes.extend_from_slice(self.reason.as_bytes());
+        bytes
+    }
+}
+
+/// Redaction records of a tree by leaf index, shared with snapshots until written
+pub(crate) type RedactionMap<H> = Arc<BTreeMap<usize, Redaction<H>>>;
+
+/// Signs redaction records
+pub trait RedactionSigner: Send + Sync {
+    /// Sign `message`
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+}
+
+/// Stored data of a leaf
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeafData<'a, H> {
+    /// The original data
+    Present(&'a [u8]),
+    /// The data was never stored, e.g. for blinded leaves or trees restored from hashes
+    NotRetained,
+    /// The data was erased
+    Redacted(&'a Redaction<H>),
+}
+
+impl<'a, H> LeafData<'a, H> {
+    /// The data, if present
+    pub fn data(&self) -> Option<&'a [u8]> {
+        match self {
+            LeafData::Present(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Sign redaction records of this tree with `signer`
+    pub fn set_redaction_signer(&mut self, signer: Arc<dyn RedactionSigner>) {
+        self.redaction_signer = Some(signer);
+    }
+
+    /// Erase the stored data of leaf `index`, keeping its hash
+    ///
+    /// The root does not change. Redacting a leaf twice returns the first
+    /// record.
+    pub fn redact_leaf(&mut self, index: usize, reason: &str) -> Result<Redaction<H>> {
+        let leaf_hash = self.get_leaf_hash(index)?;
+        if let Some(existing) = self.redactions.get(&index) {
+            return Ok(existing.clone());
+        }
+
+        self.release_leaf_data(index);
+        if let NodeType::Leaf { data, .. } = &mut self.nodes[index].node_type {
+            *data = None;
+        }
+        let mut redaction = Redaction {
+            index,
+            leaf_hash,
+            reason: reason.to_string(),
+            redacted_at: crate::security::current_timestamp(),
+            signature: None,
+        };
+        if let Some(signer) = &self.redaction_signer {
+            redaction.signature = Some(signer.sign(&redaction.signing_bytes()));
+        }
+        Arc::make_mut(&mut self.redactions).insert(index, redaction.clone());
+
+        crate::security::log_security_event!(self.security_logger, crate::security::events::leaf_redaction(index, reason));
+        Ok(redaction)
+    }
+
+    /// Get the redaction record of leaf `index`, if it was redacted
+    pub fn redaction(&self, index: usize) -> Option<&Redaction<H>> {
+        self.redactions.get(&index)
+    }
+
+    /// Iterate over all redaction records in leaf order
+    pub fn redactions(&self) -> impl Iterator<Item = &Redaction<H>> + '_ {
+        self.redactions.values()
+    }
+
+    /// Drop redaction records of leaves that no longer exist
+    pub(crate) fn prune_redactions(&mut self) {
+        let leaf_count = self.leaf_count;
+        if self.redactions.range(leaf_count..).next().is_some() {
+            Arc::make_mut(&mut self.redactions).retain(|index, _| *index < leaf_count);
+        }
+    }
+
+    /// Drop the redaction record of leaf `index` after its data was replaced
+    #[cfg_attr(feature = "minimal", allow(dead_code))]
+    pub(crate) fn remove_redaction(&mut self, index: usize) {
+        if self.redactions.contains_key(&index) {
+            Arc::make_mut(&mut self.redactions).remove(&index);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    struct Reverse;
+
+    impl RedactionSigner for Reverse {
+        fn sign(&self, message: &[u8]) -> Vec<u8> {
+            message.iter().rev().copied().collect()
+        }
+    }
+
+    #[test]
+    fn test_redaction_keeps_root_and_proofs() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..3u64 {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        let root = tree.root();
+        let proof = tree.generate_proof(1).unwrap();
+        tree.set_redaction_signer(Arc::new(Reverse));
+
+        let redaction = tree.redact_leaf(1, "erasure request").unwrap();
+        assert_eq!(tree.root(), root);
+        assert!(tree.verify_proof(&proof).unwrap());
+        assert_eq!(tree.get_leaf_data(1).unwrap(), LeafData::Redacted(&redaction));
+        assert_eq!(tree.get_leaf_data(0).unwrap().data(), Some(&b"data0"[..]));
+        let mut signed = redaction.signing_bytes();
+        signed.reverse();
+        assert_eq!(redaction.signature, Some(signed));
+
+        assert_eq!(tree.redact_leaf(1, "again").unwrap(), redaction);
+        assert!(tree.redact_leaf(3, "missing").is_err());
+
+        tree.rollback_to_timestamp(1000).unwrap();
+        assert_eq!(tree.redactions().count(), 0);
+    }
+
+    #[cfg(feature = "storage")]
+    #[test]
+    fn test_redactions_persist() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert(b"data0", 1000).unwrap();
+        tree.insert(b"data1", 1001).unwrap();
+        let redaction = tree.redact_leaf(0, "retention expired").unwrap();
+
+        let json = serde_json::to_vec(&tree.extract_state()).unwrap();
+        let state: crate::storage::TreeState<[u8; 32]> = serde_json::from_slice(&json).unwrap();
+        let restored: ChronoMerkleTree =
+            ChronoMerkleTree::from_state(state, crate::hash::Blake3Hasher, crate::security::NoOpLogger);
+        assert_eq!(restored.get_leaf_data(0).unwrap(), LeafData::Redacted(&redaction));
+        assert_eq!(restored.root(), tree.root());
+    }
+}
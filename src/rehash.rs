@@ -0,0 +1,165 @@
+//! Migration of a tree to another hash function
+//!
+//! [`ChronoMerkleTree::rehash_with`] rebuilds a tree from its stored leaf data
+//! under a new [`HashFunction`], e.g. when deprecating SHA-256. Leaf order,
+//! timestamps, configuration and the timestamp index are kept; the delta
+//! history is dropped because its roots were computed with the old hasher. The
+//! returned [`RehashReport`] maps the old root to the new one so published
+//! roots can be re-anchored.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::node::Node;
+use crate::security::SecurityLogger;
+use crate::levels::Levels;
+use crate::snapshot::SharedVec;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{format, vec::Vec};
+
+/// Outcome of a [`ChronoMerkleTree::rehash_with`] migration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RehashReport<OldH, NewH> {
+    /// Root under the old hash function
+    pub old_root: Option<OldH>,
+    /// Root of the same leaves under the new hash function
+    pub new_root: Option<NewH>,
+    /// Number of rehashed leaves
+    pub leaf_count: usize,
+    /// Delta nodes of the old tree that were not carried over
+    pub dropped_deltas: usize,
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Rebuild the tree from stored leaf data under `hasher`
+    ///
+    /// Every leaf must still hold its data: blinded leaves, redacted leaves
+    /// and trees restored from hashes alone cannot be migrated and return an
+    /// error before the tree is consumed. The new tree keeps the logger,
+    /// cancellation token, observers and leaf dedup setting of this one but
+    /// starts without delta or root history, proof cache or insert
+    /// interceptors, which are typed by the old hash.
+    #[allow(clippy::type_complexity)]
+    pub fn rehash_with<NewHasher>(
+        self,
+        hasher: NewHasher,
+    ) -> Result<(
+        ChronoMerkleTree<NewHasher::Output, NewHasher, Logger>,
+        RehashReport<H, NewHasher::Output>,
+    )>
+    where
+        NewHasher: HashFunction + Sync,
+        NewHasher::Output: Send + Sync + crate::traits::MaybeSerde,
+    {
+        let mut leaves = Vec::with_capacity(self.leaf_count);
+        for (index, node) in self.nodes.leaves().iter().enumerate() {
+            match self.leaf_data(index, node).data() {
+                Some(data) => {
+                    leaves.push(Node::leaf(hasher.hash(data), node.timestamp_info().0, Some(data.to_vec())));
+                }
+                None => {
+                    return Err(ChronoMerkleError::HashError {
+                        message: format!("leaf {} has no stored data to rehash", index),
+                    });
+                }
+            }
+        }
+
+        let old_root = self.root();
+        let dropped_deltas = self.stored_deltas.len();
+        let dedup = self.leaf_store.is_some();
+        let mut tree = ChronoMerkleTree {
+            nodes: Levels::from_flat(leaves, self.leaf_count),
+            leaf_count: self.leaf_count,
+            sparse_index: self.sparse_index,
+            hasher,
+            config: self.config,
+            incremental_updates: self.incremental_updates,
+            delta_chains: crate::sparse_index::SparseIndex::new(self.delta_chains.sparsity()),
+            stored_deltas: SharedVec::new(),
+            root_history: SharedVec::new(),
+            security_logger: self.security_logger,
+            #[cfg(feature = "std")]
+            proof_cache: None,
+            #[cfg(feature = "std")]
+            event_emitter: self.event_emitter,
+            redactions: Default::default(),
+            leaf_store: None,
+            redaction_signer: self.redaction_signer,
+            cancellation: self.cancellation,
+            admission: self.admission,
+            interceptors: Vec::new(),
+            #[cfg(feature = "std")]
+            progress_observer: self.progress_observer,
+            #[cfg(feature = "parallel")]
+            thread_pool: self.thread_pool,
+        };
+        if tree.leaf_count > 0 {
+            tree.rebuild_tree()?;
+        }
+        if dedup {
+            tree.enable_leaf_dedup()?;
+        }
+
+        let report = RehashReport {
+            old_root,
+            new_root: tree.root(),
+            leaf_count: tree.leaf_count,
+            dropped_deltas,
+        };
+        Ok((tree, report))
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    /// Blake3 with a domain prefix, standing in for a different hash function
+    #[derive(Clone, Default)]
+    struct Prefixed;
+
+    impl HashFunction for Prefixed {
+        type Output = [u8; 32];
+
+        fn name(&self) -> &str {
+            "prefixed-blake3"
+        }
+
+        fn hash(&self, data: &[u8]) -> Self::Output {
+            Blake3Hasher.hash_multiple(&[b"v2", data])
+        }
+    }
+
+    #[test]
+    fn test_rehash_with() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut expected: ChronoMerkleTree<[u8; 32], Prefixed> = ChronoMerkleTree::default();
+        for i in 0..5u64 {
+            let data = format!("data{}", i);
+            tree.insert(data.as_bytes(), 1000 + i).unwrap();
+            expected.insert(data.as_bytes(), 1000 + i).unwrap();
+        }
+        let old_root = tree.root();
+
+        let (migrated, report) = tree.rehash_with(Prefixed).unwrap();
+        assert_eq!(report.old_root, old_root);
+        assert_eq!(report.new_root, expected.root());
+        assert_ne!(report.new_root, report.old_root);
+        assert_eq!(report.leaf_count, 5);
+        assert_eq!(migrated.find_range(1001, 1003), vec![1, 2, 3]);
+        assert!(migrated.verify_proof(&migrated.generate_proof(4).unwrap()).unwrap());
+
+        let mut redacted: ChronoMerkleTree = ChronoMerkleTree::default();
+        redacted.insert(b"data", 1000).unwrap();
+        redacted.redact_leaf(0, "erasure").unwrap();
+        assert!(redacted.rehash_with(Prefixed).is_err());
+    }
+}
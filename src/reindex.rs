@@ -0,0 +1,111 @@
+//! Reconstruction of the tree's indexes
+//!
+//! The timestamp index and the delta chains are derived data: they can always
+//! be rebuilt from the leaves and the stored deltas. After an import, a manual
+//! repair or a change of sparsity, [`ChronoMerkleTree::rebuild_indexes`]
+//! recreates both in a single pass over the leaves and the deltas and reports
+//! how the previous indexes differed from the rebuilt ones.
+
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::sparse_index::SparseIndex;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::vec::Vec;
+
+/// Differences between a previous index and its rebuilt form
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexDiscrepancies {
+    /// Entries in the rebuilt index
+    pub entries: usize,
+    /// `(timestamp, position)` entries the previous index lacked
+    pub missing: Vec<(u64, usize)>,
+    /// `(timestamp, position)` entries of the previous index that were dropped
+    pub stale: Vec<(u64, usize)>,
+}
+
+impl IndexDiscrepancies {
+    fn between(previous: &SparseIndex, rebuilt: &SparseIndex) -> Self {
+        let absent_from = |index: &SparseIndex, (timestamp, position): (&u64, &usize)| {
+            (index.find_exact(*timestamp) != Some(*position)).then_some((*timestamp, *position))
+        };
+        Self {
+            entries: rebuilt.len(),
+            missing: rebuilt.iter().filter_map(|entry| absent_from(previous, entry)).collect(),
+            stale: previous.iter().filter_map(|entry| absent_from(rebuilt, entry)).collect(),
+        }
+    }
+
+    /// Whether the previous index matched the rebuilt one
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.stale.is_empty()
+    }
+}
+
+/// Outcome of [`ChronoMerkleTree::rebuild_indexes`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexRebuildReport {
+    /// Timestamp index over the leaves
+    pub timestamp_index: IndexDiscrepancies,
+    /// Delta chains over the stored deltas
+    pub delta_chains: IndexDiscrepancies,
+}
+
+impl IndexRebuildReport {
+    /// Whether no index needed repair
+    pub fn is_clean(&self) -> bool {
+        self.timestamp_index.is_clean() && self.delta_chains.is_clean()
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Rebuild the timestamp index and delta chains from the leaves and deltas
+    ///
+    /// Uses the sparsity and budget of the current configuration, so calling
+    /// this after changing them applies them to existing entries. The report
+    /// lists the entries the previous indexes lacked or held wrongly.
+    pub fn rebuild_indexes(&mut self) -> IndexRebuildReport {
+        let previous_timestamps = core::mem::replace(&mut self.sparse_index, SparseIndex::new(1));
+        let previous_deltas = core::mem::replace(&mut self.delta_chains, SparseIndex::new(1));
+        self.rebuild_sparse_index();
+        self.rebuild_delta_chains();
+        IndexRebuildReport {
+            timestamp_index: IndexDiscrepancies::between(&previous_timestamps, &self.sparse_index),
+            delta_chains: IndexDiscrepancies::between(&previous_deltas, &self.delta_chains),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use crate::ChronoMerkleTree;
+
+    #[test]
+    fn test_rebuild_reports_and_repairs_discrepancies() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..10u64 {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        assert!(tree.rebuild_indexes().is_clean());
+
+        // An entry pointing at the wrong leaf and a lost delta entry
+        tree.sparse_index.insert(1003, 7);
+        tree.delta_chains.clear();
+        let report = tree.rebuild_indexes();
+        assert!(!report.is_clean());
+        assert_eq!(report.timestamp_index.missing, vec![(1003, 3)]);
+        assert_eq!(report.timestamp_index.stale, vec![(1003, 7)]);
+        assert_eq!(report.timestamp_index.entries, 10);
+        assert_eq!(report.delta_chains.missing.len(), report.delta_chains.entries);
+        assert!(report.delta_chains.stale.is_empty());
+
+        assert_eq!(tree.find_by_timestamp(1003), vec![3]);
+        assert!(tree.rebuild_indexes().is_clean());
+    }
+}
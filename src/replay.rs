@@ -0,0 +1,126 @@
+//! Forensic replay of a tree's history
+//!
+//! [`ChronoMerkleTree::replay`] rebuilds a tree from genesis one leaf at a
+//! time and compares each intermediate root with an expected sequence, such as
+//! roots published by the original writer or the bases of its
+//! [`DeltaLog`](crate::DeltaLog). The [`ReplayReport`] names the first leaf
+//! after which the roots diverge.
+
+use crate::config::TreeConfig;
+use crate::error::Result;
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+/// First step at which a replayed root differed from the expected one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayDivergence<H> {
+    /// Index of the leaf whose insertion produced the differing root
+    pub leaf_index: usize,
+    /// Timestamp of that leaf
+    pub timestamp: u64,
+    /// Root expected after the insertion
+    pub expected: H,
+    /// Root produced by the replay
+    pub actual: H,
+}
+
+/// Outcome of [`ChronoMerkleTree::replay`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayReport<H> {
+    /// Leaves inserted before the replay stopped
+    pub leaves_replayed: usize,
+    /// Intermediate roots compared with the expected sequence
+    pub roots_checked: usize,
+    /// First mismatch, if any; the replay stops there
+    pub divergence: Option<ReplayDivergence<H>>,
+    /// Root after the last replayed leaf
+    pub final_root: Option<H>,
+}
+
+impl<H> ReplayReport<H> {
+    /// Whether every checked root matched
+    pub fn is_consistent(&self) -> bool {
+        self.divergence.is_none()
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Default + Sync,
+    Logger: SecurityLogger + Default,
+{
+    /// Replay `entries` into an empty tree, checking the root after each one
+    ///
+    /// `expected_roots[i]` is the root expected after inserting `entries[i]`.
+    /// Entries beyond the expected sequence are replayed unchecked. Invalid
+    /// entries abort the replay with their insert error.
+    pub fn replay(
+        hasher: Hasher,
+        config: TreeConfig,
+        entries: &[(&[u8], u64)],
+        expected_roots: &[H],
+    ) -> Result<ReplayReport<H>> {
+        let mut tree = Self::with_config(hasher, config)?;
+        let mut report = ReplayReport {
+            leaves_replayed: 0,
+            roots_checked: 0,
+            divergence: None,
+            final_root: None,
+        };
+
+        for (leaf_index, (data, timestamp)) in entries.iter().enumerate() {
+            tree.insert(data, *timestamp)?;
+            report.leaves_replayed += 1;
+            report.final_root = tree.root();
+
+            let (Some(expected), Some(actual)) = (expected_roots.get(leaf_index), tree.root()) else {
+                continue;
+            };
+            report.roots_checked += 1;
+            if *expected != actual {
+                report.divergence = Some(ReplayDivergence {
+                    leaf_index,
+                    timestamp: *timestamp,
+                    expected: expected.clone(),
+                    actual,
+                });
+                break;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    #[test]
+    fn test_replay_finds_first_divergence() {
+        let data: Vec<Vec<u8>> = (0..6).map(|i| format!("data{}", i).into_bytes()).collect();
+        let mut entries: Vec<(&[u8], u64)> = data.iter().enumerate().map(|(i, d)| (d.as_slice(), 1000 + i as u64)).collect();
+        let mut original: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut roots = Vec::new();
+        for (data, timestamp) in &entries {
+            original.insert(data, *timestamp).unwrap();
+            roots.push(original.root().unwrap());
+        }
+
+        let report = ChronoMerkleTree::<[u8; 32], Blake3Hasher>::replay(Blake3Hasher, TreeConfig::default(), &entries, &roots).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!((report.leaves_replayed, report.roots_checked), (6, 6));
+        assert_eq!(report.final_root, original.root());
+
+        // A tampered record shows up at its own position
+        entries[3].0 = b"forged";
+        let report = ChronoMerkleTree::<[u8; 32], Blake3Hasher>::replay(Blake3Hasher, TreeConfig::default(), &entries, &roots).unwrap();
+        let divergence = report.divergence.unwrap();
+        assert_eq!((divergence.leaf_index, divergence.timestamp), (3, 1003));
+        assert_eq!(divergence.expected, roots[3]);
+        assert_eq!(report.leaves_replayed, 4);
+    }
+}
@@ -0,0 +1,233 @@
+//! Retry wrapper for storage backends with transient failures
+//!
+//! [`RetryingStorage`] retries operations that fail with
+//! [`ChronoMerkleError::StorageError`], sleeping with exponential backoff between
+//! attempts. Other errors are returned immediately. The optional per-operation
+//! timeout bounds the total time spent retrying; an attempt that is already
+//! running is never interrupted, since backends are synchronous.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::storage::{BackendHealth, StorageBackend};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Retry behaviour of a [`RetryingStorage`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts per operation, including the first (at least 1)
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts
+    pub max_backoff: Duration,
+    /// Factor the delay grows by after each retry
+    pub multiplier: u32,
+    /// Give up retrying once an operation has taken this long
+    pub op_timeout: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            multiplier: 2,
+            op_timeout: None,
+        }
+    }
+}
+
+/// Storage wrapper retrying transient failures of the inner backend
+pub struct RetryingStorage<S> {
+    inner: S,
+    policy: RetryPolicy,
+    retries: AtomicU64,
+}
+
+impl<S: StorageBackend> RetryingStorage<S> {
+    /// Wrap `inner` with the given retry policy
+    pub fn new(inner: S, policy: RetryPolicy) -> Self {
+        Self {
+            inner,
+            policy,
+            retries: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the retry policy
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Number of retries performed so far
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Get a reference to the wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwrap into the inner backend
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+/// Run `op` under `policy`, counting retries in `retries`
+fn with_retries<T>(
+    policy: &RetryPolicy,
+    retries: &AtomicU64,
+    operation: &str,
+    mut op: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let started = Instant::now();
+    let mut backoff = policy.initial_backoff;
+    let mut attempt = 1;
+    loop {
+        let error = match op() {
+            Err(error @ ChronoMerkleError::StorageError { .. }) => error,
+            result => return result,
+        };
+        if attempt >= policy.max_attempts.max(1) {
+            return Err(error);
+        }
+        if let Some(timeout) = policy.op_timeout {
+            if started.elapsed() + backoff >= timeout {
+                return Err(ChronoMerkleError::StorageError {
+                    reason: format!("{} timed out after {} attempts: {}", operation, attempt, error),
+                });
+            }
+        }
+
+        std::thread::sleep(backoff);
+        backoff = backoff.saturating_mul(policy.multiplier).min(policy.max_backoff);
+        retries.fetch_add(1, Ordering::Relaxed);
+        attempt += 1;
+    }
+}
+
+impl<S: StorageBackend> StorageBackend for RetryingStorage<S> {
+    fn save(&mut self, key: &str, data: &[u8]) -> Result<()> {
+        let inner = &mut self.inner;
+        with_retries(&self.policy, &self.retries, "save", || inner.save(key, data))
+    }
+
+    fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        with_retries(&self.policy, &self.retries, "load", || self.inner.load(key))
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        let inner = &mut self.inner;
+        with_retries(&self.policy, &self.retries, "delete", || inner.delete(key))
+    }
+
+    fn list_keys(&self) -> Result<Vec<String>> {
+        with_retries(&self.policy, &self.retries, "list_keys", || self.inner.list_keys())
+    }
+
+    fn exists(&self, key: &str) -> Result<bool> {
+        with_retries(&self.policy, &self.retries, "exists", || self.inner.exists(key))
+    }
+
+    // A partially consumed reader cannot be replayed, so streamed saves are not retried
+    fn save_stream(&mut self, key: &str, reader: &mut dyn std::io::Read) -> Result<u64> {
+        self.inner.save_stream(key, reader)
+    }
+
+    fn load_stream(&self, key: &str) -> Result<Option<Box<dyn std::io::Read + '_>>> {
+        with_retries(&self.policy, &self.retries, "load_stream", || self.inner.load_stream(key))
+    }
+
+    fn health_check(&self) -> Result<BackendHealth> {
+        self.inner.health_check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use std::sync::atomic::AtomicU32;
+
+    /// Backend failing the first `failures` calls to `load`
+    struct Flaky {
+        inner: MemoryStorage,
+        failures: AtomicU32,
+    }
+
+    impl StorageBackend for Flaky {
+        fn save(&mut self, key: &str, data: &[u8]) -> Result<()> {
+            self.inner.save(key, data)
+        }
+
+        fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            if self.failures.load(Ordering::Relaxed) > 0 {
+                self.failures.fetch_sub(1, Ordering::Relaxed);
+                return Err(ChronoMerkleError::StorageError {
+                    reason: "connection reset".to_string(),
+                });
+            }
+            self.inner.load(key)
+        }
+
+        fn delete(&mut self, key: &str) -> Result<()> {
+            self.inner.delete(key)
+        }
+
+        fn list_keys(&self) -> Result<Vec<String>> {
+            self.inner.list_keys()
+        }
+
+        fn exists(&self, key: &str) -> Result<bool> {
+            self.inner.exists(key)
+        }
+    }
+
+    fn flaky(failures: u32) -> Flaky {
+        Flaky {
+            inner: MemoryStorage::new(),
+            failures: AtomicU32::new(failures),
+        }
+    }
+
+    fn fast_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::ZERO,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_transient_failures_are_retried() {
+        let mut storage = RetryingStorage::new(flaky(2), fast_policy(3));
+        storage.save("key", b"value").unwrap();
+        assert_eq!(storage.load("key").unwrap(), Some(b"value".to_vec()));
+        assert_eq!(storage.retries(), 2);
+    }
+
+    #[test]
+    fn test_gives_up_after_max_attempts() {
+        let storage = RetryingStorage::new(flaky(5), fast_policy(3));
+        assert!(storage.load("key").is_err());
+        assert_eq!(storage.retries(), 2);
+    }
+
+    #[test]
+    fn test_timeout_stops_retrying() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(20),
+            op_timeout: Some(Duration::from_millis(10)),
+            ..RetryPolicy::default()
+        };
+        let storage = RetryingStorage::new(flaky(5), policy);
+        let err = storage.load("key").unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert_eq!(storage.retries(), 0);
+    }
+}
@@ -0,0 +1,119 @@
+//! Record of past roots by tree size
+//!
+//! With [`TreeConfig::record_root_history`](crate::TreeConfig::record_root_history)
+//! set, every root change appends a [`RootRecord`]. [`RootHistory`] looks up
+//! the root the tree had at any recorded size, e.g. to check a receipt issued
+//! against an older root, and is saved with the tree state unless
+//! [`persist_root_history`](crate::TreeConfig::persist_root_history) is off.
+
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::snapshot::SharedVec;
+use crate::tree::ChronoMerkleTree;
+
+/// Root of the tree after a change
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootRecord<H> {
+    /// Number of leaves the root covers
+    pub tree_size: usize,
+    /// Timestamp of the last leaf of the change
+    pub timestamp: u64,
+    /// Root after the change
+    pub root: H,
+}
+
+/// Recorded roots of a tree, oldest first
+#[derive(Debug, Clone, Copy)]
+pub struct RootHistory<'a, H> {
+    records: &'a SharedVec<RootRecord<H>>,
+}
+
+impl<'a, H> RootHistory<'a, H> {
+    /// Iterate over all records in recording order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &'a RootRecord<H>> + 'a {
+        self.records.iter()
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether no root was recorded
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Root the tree had when it held `tree_size` leaves, if it was recorded
+    pub fn root_at_size(&self, tree_size: usize) -> Option<&'a H> {
+        let records = self.records;
+        let position = records.partition_point(|record| record.tree_size < tree_size);
+        records.get(position).filter(|record| record.tree_size == tree_size).map(|record| &record.root)
+    }
+
+    /// Most recent record with a timestamp at or before `timestamp`
+    pub fn latest_for(&self, timestamp: u64) -> Option<&'a RootRecord<H>> {
+        self.records.iter().rev().find(|record| record.timestamp <= timestamp)
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Get the recorded roots of the tree
+    pub fn root_history(&self) -> RootHistory<'_, H> {
+        RootHistory { records: &self.root_history }
+    }
+
+    /// Record the current root if it differs from `old_root` and root history is enabled
+    pub(crate) fn record_root(&mut self, old_root: Option<&H>, timestamp: u64) {
+        if !self.config.record_root_history {
+            return;
+        }
+        if let Some(root) = self.root().filter(|root| Some(root) != old_root) {
+            let tree_size = self.leaf_count;
+            self.root_history.push(RootRecord { tree_size, timestamp, root });
+        }
+    }
+
+    /// Drop records of roots covering leaves that no longer exist
+    pub(crate) fn prune_root_history(&mut self) {
+        let leaf_count = self.leaf_count;
+        if self.root_history.last().is_some_and(|record| record.tree_size > leaf_count) {
+            self.root_history.retain(|record| record.tree_size <= leaf_count);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::TreeConfig;
+
+    #[test]
+    fn test_root_history() {
+        let config = TreeConfig { record_root_history: true, ..TreeConfig::default() };
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(crate::Blake3Hasher, config).unwrap();
+        let mut roots = Vec::new();
+        for i in 0..4u64 {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+            roots.push(tree.root().unwrap());
+        }
+        tree.insert_batch(&[(b"data4", 1004), (b"data5", 1005)]).unwrap();
+
+        let history = tree.root_history();
+        assert_eq!(history.len(), 5);
+        assert_eq!(history.root_at_size(3), Some(&roots[2]));
+        assert_eq!(history.root_at_size(5), None);
+        assert_eq!(history.root_at_size(6), tree.root().as_ref());
+        assert_eq!(history.latest_for(1002).unwrap().tree_size, 3);
+
+        tree.rollback_to_timestamp(1002).unwrap();
+        assert_eq!(tree.root_history().len(), 3);
+        assert_eq!(tree.root_history().root_at_size(3), tree.root().as_ref());
+    }
+}
@@ -5,11 +5,13 @@
 
 use crate::error::Result;
 
+use core::sync::atomic::{AtomicU64, Ordering};
+
 #[cfg(feature = "no-std")]
-use alloc::string::String;
+use alloc::{string::String, vec::Vec};
 
 /// Security event severity levels
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SecurityLevel {
     /// Informational events (tree operations, configurations)
@@ -42,6 +44,35 @@ pub enum SecurityEventType {
     InputValidationFailure,
     /// Cryptographic operation failure
     CryptoOperationFailure,
+    /// Request rejected by authentication or rate limiting
+    AccessDenied,
+    /// Leaf data erased
+    LeafRedaction,
+    /// Existing leaf replaced or deleted
+    LeafUpdate,
+}
+
+impl SecurityEventType {
+    /// Number of event types
+    pub const COUNT: usize = 12;
+
+    /// Position of the type in declaration order
+    pub fn index(&self) -> usize {
+        match self {
+            SecurityEventType::TreeInitialization => 0,
+            SecurityEventType::LeafInsertion => 1,
+            SecurityEventType::ProofGeneration => 2,
+            SecurityEventType::ProofVerificationSuccess => 3,
+            SecurityEventType::ProofVerificationFailure => 4,
+            SecurityEventType::ConfigurationChange => 5,
+            SecurityEventType::TamperingDetected => 6,
+            SecurityEventType::InputValidationFailure => 7,
+            SecurityEventType::CryptoOperationFailure => 8,
+            SecurityEventType::AccessDenied => 9,
+            SecurityEventType::LeafRedaction => 10,
+            SecurityEventType::LeafUpdate => 11,
+        }
+    }
 }
 
 /// Security event data
@@ -58,6 +89,98 @@ pub struct SecurityEvent {
     pub description: String,
     /// Event-specific metadata (optional)
     pub metadata: Option<SecurityMetadata>,
+    /// Correlation ID of the operation the event belongs to (optional)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub context: Option<CorrelationId>,
+}
+
+impl SecurityEvent {
+    /// Attribute the event to the operation identified by `id`
+    pub fn with_context(mut self, id: CorrelationId) -> Self {
+        self.context = Some(id);
+        self
+    }
+}
+
+/// Identifier tying together the events of one multi-step operation
+///
+/// Events built by the [`events`] helpers, and therefore every event a tree
+/// emits, carry the ID set with [`with_context`] on the calling thread, so a
+/// request that inserts, proves and verifies can be followed through
+/// downstream log systems.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CorrelationId(pub String);
+
+impl CorrelationId {
+    /// The ID as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for CorrelationId {
+    fn from(id: &str) -> Self {
+        Self(id.into())
+    }
+}
+
+impl From<String> for CorrelationId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl From<u64> for CorrelationId {
+    fn from(id: u64) -> Self {
+        Self(format!("{:016x}", id))
+    }
+}
+
+impl core::fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static CONTEXT: core::cell::RefCell<Option<CorrelationId>> = const { core::cell::RefCell::new(None) };
+}
+
+/// Run `f` with `id` as the correlation ID of the events emitted on this thread
+///
+/// Scopes nest; the enclosing ID is restored when `f` returns or panics.
+/// Work handed to other threads, such as the batches applied by an
+/// `AsyncTreeWriter`, does not inherit it.
+#[cfg(feature = "std")]
+pub fn with_context<R>(id: impl Into<CorrelationId>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<CorrelationId>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CONTEXT.with(|context| *context.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(CONTEXT.with(|context| context.borrow_mut().replace(id.into())));
+    f()
+}
+
+/// Correlation ID set by the innermost [`with_context`] on this thread
+///
+/// Always `None` without the `std` feature; events can be attributed with
+/// [`SecurityEvent::with_context`] instead.
+pub fn current_context() -> Option<CorrelationId> {
+    #[cfg(feature = "std")]
+    {
+        CONTEXT.with(|context| context.borrow().clone())
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        None
+    }
 }
 
 /// Additional metadata for security events
@@ -93,6 +216,15 @@ pub enum SecurityMetadata {
         /// New value of the parameter
         new_value: String,
     },
+    /// Access denial metadata
+    AccessDenied {
+        /// Client identifier or address, if known
+        client: String,
+        /// Requested resource
+        resource: String,
+        /// Why the request was rejected
+        reason: String,
+    },
     /// Validation failure metadata
     ValidationFailure {
         /// Type of input that failed validation
@@ -116,8 +248,39 @@ pub trait SecurityLogger: Send + Sync {
         }
         Ok(())
     }
+
+    /// Log the event made by `build`, which is only called if the event is kept
+    ///
+    /// Frequent events are logged through this method so that filtering
+    /// loggers can drop them without formatting them first.
+    fn log_lazy(&self, event_type: SecurityEventType, level: SecurityLevel, build: &dyn Fn() -> SecurityEvent) -> Result<()> {
+        let _ = (event_type, level);
+        self.log_event(&build())
+    }
+}
+
+/// Log a tree's security event unless the `minimal` feature is enabled
+///
+/// With four arguments the event is logged through
+/// [`SecurityLogger::log_lazy`]. In minimal builds the expansion is empty, so
+/// the event is never built and its strings are not compiled in.
+macro_rules! log_security_event {
+    ($logger:expr, $event:expr) => {
+        #[cfg(not(feature = "minimal"))]
+        {
+            let _ = $logger.log_event(&$event);
+        }
+    };
+    ($logger:expr, $event_type:expr, $level:expr, $build:expr) => {
+        #[cfg(not(feature = "minimal"))]
+        {
+            let _ = $logger.log_lazy($event_type, $level, &$build);
+        }
+    };
 }
 
+pub(crate) use log_security_event;
+
 /// No-op logger for when security logging is disabled
 #[derive(Debug, Clone, Default)]
 pub struct NoOpLogger;
@@ -126,6 +289,121 @@ impl SecurityLogger for NoOpLogger {
     fn log_event(&self, _event: &SecurityEvent) -> Result<()> {
         Ok(())
     }
+
+    fn log_lazy(&self, _event_type: SecurityEventType, _level: SecurityLevel, _build: &dyn Fn() -> SecurityEvent) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Sampling rates of a [`SamplingLogger`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SamplingPolicy {
+    /// Keep 1 in N events of types without their own rate; 0 drops them all
+    pub default_rate: u32,
+    /// Per-type rates overriding `default_rate`
+    pub rates: Vec<(SecurityEventType, u32)>,
+    /// Events at or above this level are always kept
+    pub always_log_at: SecurityLevel,
+}
+
+impl Default for SamplingPolicy {
+    fn default() -> Self {
+        Self {
+            default_rate: 1,
+            rates: Vec::new(),
+            always_log_at: SecurityLevel::Critical,
+        }
+    }
+}
+
+impl SamplingPolicy {
+    /// Keep 1 in `rate` events of `event_type`
+    pub fn with_rate(mut self, event_type: SecurityEventType, rate: u32) -> Self {
+        self.rates.retain(|(existing, _)| *existing != event_type);
+        self.rates.push((event_type, rate));
+        self
+    }
+}
+
+/// Logger forwarding a sample of events to an inner logger
+///
+/// Each event type is sampled deterministically: of every `rate` events of a
+/// type, the first is forwarded. Events at or above the policy's
+/// `always_log_at` level bypass sampling and are not counted as suppressed.
+pub struct SamplingLogger<L> {
+    inner: L,
+    rates: [u32; SecurityEventType::COUNT],
+    always_log_at: SecurityLevel,
+    seen: [AtomicU64; SecurityEventType::COUNT],
+    suppressed: [AtomicU64; SecurityEventType::COUNT],
+}
+
+impl<L: SecurityLogger> SamplingLogger<L> {
+    /// Sample the events sent to `inner` according to `policy`
+    pub fn new(inner: L, policy: SamplingPolicy) -> Self {
+        let mut rates = [policy.default_rate; SecurityEventType::COUNT];
+        for (event_type, rate) in &policy.rates {
+            rates[event_type.index()] = *rate;
+        }
+        Self {
+            inner,
+            rates,
+            always_log_at: policy.always_log_at,
+            seen: core::array::from_fn(|_| AtomicU64::new(0)),
+            suppressed: core::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    /// Number of events of `event_type` not forwarded
+    pub fn suppressed(&self, event_type: &SecurityEventType) -> u64 {
+        self.suppressed[event_type.index()].load(Ordering::Relaxed)
+    }
+
+    /// Number of events of all types not forwarded
+    pub fn total_suppressed(&self) -> u64 {
+        self.suppressed.iter().map(|count| count.load(Ordering::Relaxed)).sum()
+    }
+
+    /// Inner logger
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    fn keep(&self, event_type: &SecurityEventType, level: SecurityLevel) -> bool {
+        if level >= self.always_log_at {
+            return true;
+        }
+        let index = event_type.index();
+        let seen = self.seen[index].fetch_add(1, Ordering::Relaxed);
+        let rate = u64::from(self.rates[index]);
+        let keep = rate != 0 && seen % rate == 0;
+        if !keep {
+            self.suppressed[index].fetch_add(1, Ordering::Relaxed);
+        }
+        keep
+    }
+}
+
+impl<L: SecurityLogger + Default> Default for SamplingLogger<L> {
+    fn default() -> Self {
+        Self::new(L::default(), SamplingPolicy::default())
+    }
+}
+
+impl<L: SecurityLogger> SecurityLogger for SamplingLogger<L> {
+    fn log_event(&self, event: &SecurityEvent) -> Result<()> {
+        if self.keep(&event.event_type, event.level) {
+            self.inner.log_event(event)?;
+        }
+        Ok(())
+    }
+
+    fn log_lazy(&self, event_type: SecurityEventType, level: SecurityLevel, build: &dyn Fn() -> SecurityEvent) -> Result<()> {
+        if self.keep(&event_type, level) {
+            self.inner.log_event(&build())?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "std")]
@@ -136,10 +414,17 @@ pub struct StdErrLogger;
 #[cfg(feature = "std")]
 impl SecurityLogger for StdErrLogger {
     fn log_event(&self, event: &SecurityEvent) -> Result<()> {
-        eprintln!("[SECURITY] {} - {}: {}",
-                 event.timestamp,
-                 format!("{:?}", event.level).to_uppercase(),
-                 event.description);
+        match &event.context {
+            Some(id) => eprintln!("[SECURITY] {} - {} [{}]: {}",
+                                  event.timestamp,
+                                  format!("{:?}", event.level).to_uppercase(),
+                                  id,
+                                  event.description),
+            None => eprintln!("[SECURITY] {} - {}: {}",
+                              event.timestamp,
+                              format!("{:?}", event.level).to_uppercase(),
+                              event.description),
+        }
         Ok(())
     }
 }
@@ -156,6 +441,7 @@ pub mod events {
             level: SecurityLevel::Info,
             description: format!("ChronoMerkle tree initialized with config: {}", config_summary),
             metadata: None,
+            context: current_context(),
         }
     }
 
@@ -180,6 +466,7 @@ pub mod events {
                     }
                 },
             }),
+            context: current_context(),
         }
     }
 
@@ -191,6 +478,7 @@ pub mod events {
             level: SecurityLevel::Info,
             description: format!("Merkle proof generated for leaf index {}", leaf_index),
             metadata: None,
+            context: current_context(),
         }
     }
 
@@ -207,6 +495,7 @@ pub mod events {
                 verification_result: true,
                 failure_reason: None,
             }),
+            context: current_context(),
         }
     }
 
@@ -223,6 +512,7 @@ pub mod events {
                 verification_result: false,
                 failure_reason: Some(reason.to_string()),
             }),
+            context: current_context(),
         }
     }
 
@@ -238,6 +528,93 @@ pub mod events {
                 reason: reason.to_string(),
                 input_value: input_value.map(|s| s.to_string()),
             }),
+            context: current_context(),
+        }
+    }
+
+    /// Create an access denied event
+    pub fn access_denied(client: &str, resource: &str, reason: &str) -> SecurityEvent {
+        SecurityEvent {
+            timestamp: current_timestamp(),
+            event_type: SecurityEventType::AccessDenied,
+            level: SecurityLevel::Warning,
+            description: format!("Access denied for {} to {}: {}", client, resource, reason),
+            metadata: Some(SecurityMetadata::AccessDenied {
+                client: client.to_string(),
+                resource: resource.to_string(),
+                reason: reason.to_string(),
+            }),
+            context: current_context(),
+        }
+    }
+
+    /// Create an event for inserts refused by admission control
+    pub fn insert_quota_exceeded(inserts: usize, bytes: usize, quota: &str) -> SecurityEvent {
+        SecurityEvent {
+            timestamp: current_timestamp(),
+            event_type: SecurityEventType::AccessDenied,
+            level: SecurityLevel::Warning,
+            description: format!("Rejected {} inserts of {} bytes: {} quota exceeded", inserts, bytes, quota),
+            metadata: Some(SecurityMetadata::AccessDenied {
+                client: "producer".to_string(),
+                resource: "insert".to_string(),
+                reason: format!("{} quota exceeded", quota),
+            }),
+            context: current_context(),
+        }
+    }
+
+    /// Create an event for parallel construction disagreeing with a sequential rebuild
+    pub fn parallel_construction_divergence(level: usize, node: usize) -> SecurityEvent {
+        SecurityEvent {
+            timestamp: current_timestamp(),
+            event_type: SecurityEventType::CryptoOperationFailure,
+            level: SecurityLevel::Critical,
+            description: format!(
+                "Parallel construction diverged from sequential rebuild at level {} node {}",
+                level, node
+            ),
+            metadata: None,
+            context: current_context(),
+        }
+    }
+
+    /// Create a leaf redaction event
+    pub fn leaf_redaction(leaf_index: usize, reason: &str) -> SecurityEvent {
+        SecurityEvent {
+            timestamp: current_timestamp(),
+            event_type: SecurityEventType::LeafRedaction,
+            level: SecurityLevel::Warning,
+            description: format!("Data of leaf {} redacted: {}", leaf_index, reason),
+            metadata: None,
+            context: current_context(),
+        }
+    }
+
+    /// Create an event for a leaf replaced, or tombstoned if `deleted`
+    pub fn leaf_update(leaf_index: usize, deleted: bool) -> SecurityEvent {
+        SecurityEvent {
+            timestamp: current_timestamp(),
+            event_type: SecurityEventType::LeafUpdate,
+            level: SecurityLevel::Warning,
+            description: format!("Leaf {} {}", leaf_index, if deleted { "deleted" } else { "updated" }),
+            metadata: None,
+            context: current_context(),
+        }
+    }
+
+    /// Create an event for two different roots claimed for the same tree size
+    pub fn root_equivocation(tree_size: u64, first_source: &str, second_source: &str) -> SecurityEvent {
+        SecurityEvent {
+            timestamp: current_timestamp(),
+            event_type: SecurityEventType::TamperingDetected,
+            level: SecurityLevel::Critical,
+            description: format!(
+                "ROOT EQUIVOCATION at tree size {}: {} and {} observed different roots",
+                tree_size, first_source, second_source
+            ),
+            metadata: None,
+            context: current_context(),
         }
     }
 
@@ -253,6 +630,7 @@ pub mod events {
                 old_value: old_value.to_string(),
                 new_value: new_value.to_string(),
             }),
+            context: current_context(),
         }
     }
 }
@@ -317,6 +695,83 @@ mod tests {
         logger.log_event(&event).unwrap();
     }
 
+    #[test]
+    fn test_sampling_logger() {
+        struct Counter(AtomicU64);
+
+        impl SecurityLogger for Counter {
+            fn log_event(&self, _event: &SecurityEvent) -> Result<()> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+        }
+
+        let policy = SamplingPolicy { default_rate: 0, ..Default::default() }
+            .with_rate(SecurityEventType::ProofGeneration, 10);
+        let logger = SamplingLogger::new(Counter(AtomicU64::new(0)), policy);
+        for i in 0..25 {
+            logger.log_event(&events::proof_generation(i)).unwrap();
+        }
+        logger.log_event(&events::config_change("sparsity", "1", "2")).unwrap();
+        logger.log_event(&events::proof_verification_failure(0, 0, "tampered")).unwrap();
+        let built = AtomicU64::new(0);
+        let build = || {
+            built.fetch_add(1, Ordering::Relaxed);
+            events::proof_generation(25)
+        };
+        for _ in 0..10 {
+            logger.log_lazy(SecurityEventType::ProofGeneration, SecurityLevel::Info, &build).unwrap();
+        }
+
+        // 3 of the first 25 and 1 of the lazy 10, plus the critical failure
+        assert_eq!(logger.inner().0.load(Ordering::Relaxed), 5);
+        assert_eq!(built.load(Ordering::Relaxed), 1);
+        assert_eq!(logger.suppressed(&SecurityEventType::ProofGeneration), 31);
+        assert_eq!(logger.suppressed(&SecurityEventType::ConfigurationChange), 1);
+        assert_eq!(logger.total_suppressed(), 32);
+    }
+
+    #[cfg(all(feature = "std", feature = "blake3-hash", not(feature = "minimal")))]
+    #[test]
+    fn test_correlation_context() {
+        use crate::{Blake3Hasher, ChronoMerkleTree};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Collector(Arc<Mutex<Vec<SecurityEvent>>>);
+
+        impl SecurityLogger for Collector {
+            fn log_event(&self, event: &SecurityEvent) -> Result<()> {
+                self.0.lock().unwrap().push(event.clone());
+                Ok(())
+            }
+        }
+
+        let collector = Collector::default();
+        let mut tree = ChronoMerkleTree::<[u8; 32], Blake3Hasher, _>::with_logger(Blake3Hasher, collector.clone());
+        collector.0.lock().unwrap().clear();
+
+        with_context("request-7", || {
+            tree.insert(b"a", 1000).unwrap();
+            let proof = tree.generate_proof(0).unwrap();
+            assert!(with_context(42u64, || tree.verify_proof(&proof).unwrap()));
+            assert_eq!(current_context(), Some(CorrelationId::from("request-7")));
+        });
+        tree.insert(b"b", 1001).unwrap();
+        assert_eq!(current_context(), None);
+
+        let contexts: Vec<Option<String>> = collector.0.lock().unwrap().iter()
+            .map(|event| event.context.as_ref().map(|id| id.to_string()))
+            .collect();
+        assert_eq!(contexts, vec![Some("request-7".to_string()), Some("000000000000002a".to_string()), None]);
+
+        // The enclosing context survives a panic inside a scope
+        with_context("outer", || {
+            let _ = std::panic::catch_unwind(|| with_context("inner", || panic!("boom")));
+            assert_eq!(current_context().unwrap().as_str(), "outer");
+        });
+    }
+
     #[test]
     fn test_constant_time_eq() {
         // Test with equal arrays
@@ -18,4 +18,270 @@ pub use serde::{Deserialize, Serialize};
 //
 // For full tree serialization including deltas, use:
 // - extract_leaves_and_deltas() -> (Vec<Node<H>>, Vec<Node<H>>)
-// - reconstruct_from_leaves_and_deltas()
\ No newline at end of file
+// - reconstruct_from_leaves_and_deltas()
+
+use core::marker::PhantomData;
+use serde::de::value::{SeqAccessDeserializer, SeqDeserializer};
+use serde::{Deserializer, Serializer};
+
+#[cfg(feature = "no-std")]
+use alloc::{format, string::{String, ToString}, vec::Vec};
+
+/// Hash fields as hex strings in human-readable formats and raw bytes otherwise
+///
+/// For use as `#[serde(with = "chrono_merkle::serde_impl::hash")]`. JSON gets
+/// `"3f9a…"` instead of an array of 32 numbers, while bincode and CBOR get a
+/// plain byte string. Deserialization also accepts the array form written by
+/// the default array impls, so earlier output still loads.
+pub mod hash {
+    use super::*;
+
+    /// Serialize `hash` as hex or bytes, depending on the format
+    pub fn serialize<H: AsRef<[u8]>, S: Serializer>(hash: &H, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(&crate::display::RootHash(hash.as_ref()))
+        } else {
+            serializer.serialize_bytes(hash.as_ref())
+        }
+    }
+
+    /// Deserialize a hash from hex, bytes or a byte sequence
+    pub fn deserialize<'de, H: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<H, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(HashVisitor(PhantomData))
+        } else {
+            deserializer.deserialize_bytes(HashVisitor(PhantomData))
+        }
+    }
+}
+
+/// [`hash`] for each element of a `Vec`
+pub mod hash_seq {
+    use super::*;
+
+    /// Serialize every hash with [`hash::serialize`]
+    pub fn serialize<H: AsRef<[u8]>, S: Serializer>(hashes: &[H], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(hashes.iter().map(HashRef))
+    }
+
+    /// Deserialize every hash with [`hash::deserialize`]
+    pub fn deserialize<'de, H: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<H>, D::Error> {
+        let hashes = Vec::<HashOwned<H>>::deserialize(deserializer)?;
+        Ok(hashes.into_iter().map(|hash| hash.0).collect())
+    }
+}
+
+/// [`hash`] for each element of an optional `Vec`
+pub mod hash_seq_option {
+    use super::*;
+
+    /// Serialize every hash with [`hash::serialize`]
+    pub fn serialize<H: AsRef<[u8]>, S: Serializer>(hashes: &Option<Vec<H>>, serializer: S) -> Result<S::Ok, S::Error> {
+        match hashes {
+            Some(hashes) => serializer.serialize_some(&HashSeq(hashes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserialize every hash with [`hash::deserialize`]
+    pub fn deserialize<'de, H: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<H>>, D::Error> {
+        let hashes = Option::<Vec<HashOwned<H>>>::deserialize(deserializer)?;
+        Ok(hashes.map(|hashes| hashes.into_iter().map(|hash| hash.0).collect()))
+    }
+}
+
+/// [`hash`] for an optional hash
+pub mod hash_option {
+    use super::*;
+
+    /// Serialize the hash, if any, with [`hash::serialize`]
+    pub fn serialize<H: AsRef<[u8]>, S: Serializer>(hash: &Option<H>, serializer: S) -> Result<S::Ok, S::Error> {
+        match hash {
+            Some(hash) => serializer.serialize_some(&HashRef(hash)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserialize the hash, if any, with [`hash::deserialize`]
+    pub fn deserialize<'de, H: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<Option<H>, D::Error> {
+        Ok(Option::<HashOwned<H>>::deserialize(deserializer)?.map(|hash| hash.0))
+    }
+}
+
+struct HashRef<'a, H>(&'a H);
+
+impl<H: AsRef<[u8]>> Serialize for HashRef<'_, H> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hash::serialize(self.0, serializer)
+    }
+}
+
+struct HashSeq<'a, H>(&'a [H]);
+
+impl<H: AsRef<[u8]>> Serialize for HashSeq<'_, H> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hash_seq::serialize(self.0, serializer)
+    }
+}
+
+struct HashOwned<H>(H);
+
+impl<'de, H: Deserialize<'de>> Deserialize<'de> for HashOwned<H> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        hash::deserialize(deserializer).map(HashOwned)
+    }
+}
+
+struct HashVisitor<H>(PhantomData<H>);
+
+impl<'de, H: Deserialize<'de>> serde::de::Visitor<'de> for HashVisitor<H> {
+    type Value = H;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("a hash as a hex string, bytes or a byte sequence")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, text: &str) -> Result<H, E> {
+        let bytes = crate::codec::from_hex(text).ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(text), &self))?;
+        self.visit_bytes(&bytes)
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<H, E> {
+        H::deserialize(SeqDeserializer::<_, E>::new(bytes.iter().copied()))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> Result<H, A::Error> {
+        H::deserialize(SeqAccessDeserializer::new(seq))
+    }
+}
+
+/// How deserialization treats fields it does not know
+///
+/// Unknown fields are ignored by default, so an older binary can read states
+/// and proofs written by a newer version that added fields, e.g. during a
+/// rolling upgrade. [`UnknownFields::Reject`] restores strict parsing for
+/// callers that would rather fail than silently drop data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownFields {
+    /// Skip fields that are not part of the type
+    #[default]
+    Ignore,
+    /// Fail on the first field that is not part of the type
+    Reject,
+}
+
+/// Deserialize `T` from JSON, treating unknown fields as `unknown` says
+///
+/// Strict mode finds unknown fields by serializing the parsed value again and
+/// reporting any input field, at any depth, that did not survive the round
+/// trip; the error names its path, e.g. `config.compaction`.
+#[cfg(feature = "storage")]
+pub fn from_json<T>(json: &[u8], unknown: UnknownFields) -> crate::error::Result<T>
+where
+    T: Serialize + serde::de::DeserializeOwned,
+{
+    let error = |e: serde_json::Error| crate::error::ChronoMerkleError::DeserializationError(e.to_string());
+    if unknown == UnknownFields::Ignore {
+        return serde_json::from_slice(json).map_err(error);
+    }
+    let input: serde_json::Value = serde_json::from_slice(json).map_err(error)?;
+    let value = T::deserialize(&input).map_err(error)?;
+    let known = serde_json::to_value(&value).map_err(|e| crate::error::ChronoMerkleError::SerializationError(e.to_string()))?;
+    match unknown_field(&input, &known, "") {
+        Some(path) => Err(crate::error::ChronoMerkleError::DeserializationError(format!("unknown field `{}`", path))),
+        None => Ok(value),
+    }
+}
+
+/// Path of the first field of `input` missing from `known`
+///
+/// Null fields are skipped: optional fields that are omitted when unset read
+/// back as null.
+#[cfg(feature = "storage")]
+fn unknown_field(input: &serde_json::Value, known: &serde_json::Value, path: &str) -> Option<String> {
+    use serde_json::Value;
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match (input, known) {
+        (Value::Object(input), Value::Object(known)) => input.iter().find_map(|(key, value)| match known.get(key) {
+            Some(known) => unknown_field(value, known, &join(key)),
+            None if value.is_null() => None,
+            None => Some(join(key)),
+        }),
+        (Value::Array(input), Value::Array(known)) => input
+            .iter()
+            .zip(known)
+            .enumerate()
+            .find_map(|(i, (value, known))| unknown_field(value, known, &format!("{}[{}]", path, i))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::Digest;
+    use crate::proof::{ChronoProof, ProofStep};
+
+    fn proof() -> ChronoProof<[u8; 32]> {
+        let mut proof = ChronoProof::new(3, 1003);
+        proof.add_step(ProofStep::Left([0xab; 32]));
+        proof.add_step(ProofStep::Delta([1; 32], [2; 32]));
+        proof.delta_chain = Some(vec![[0x0f; 32]]);
+        proof
+    }
+
+    #[test]
+    fn test_hashes_are_hex_in_json() {
+        let original = proof();
+        let json = serde_json::to_string(&original).unwrap();
+        assert!(json.contains(&format!("{{\"Left\":\"{}\"}}", "ab".repeat(32))), "{}", json);
+        assert!(json.contains(&format!("\"delta_chain\":[\"{}\"]", "0f".repeat(32))), "{}", json);
+        let decoded: ChronoProof<[u8; 32]> = serde_json::from_str(&json).unwrap();
+        assert_eq!((decoded.path, decoded.delta_chain), (original.path, original.delta_chain));
+
+        // Proofs written with hashes as number arrays still load
+        let legacy = json.replace(&format!("\"{}\"", "ab".repeat(32)), &format!("{:?}", [0xabu8; 32]));
+        assert_ne!(legacy, json);
+        assert_eq!(serde_json::from_str::<ChronoProof<[u8; 32]>>(&legacy).unwrap().path, proof().path);
+        assert!(serde_json::from_str::<ChronoProof<[u8; 32]>>(&json.replace("abab", "zzzz")).is_err());
+
+        let digest = Digest([7u8; 48]);
+        let json = serde_json::to_string(&digest).unwrap();
+        assert_eq!(json, format!("\"{}\"", "07".repeat(48)));
+        assert_eq!(serde_json::from_str::<Digest<48>>(&json).unwrap(), digest);
+        assert!(serde_json::from_str::<Digest<48>>("\"0707\"").is_err());
+    }
+
+    #[test]
+    fn test_hashes_are_bytes_in_cbor() {
+        let proof = proof();
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&proof, &mut cbor).unwrap();
+        // The sibling is a 32-byte byte string (major type 2), not an array of numbers
+        let mut sibling = vec![0x58, 32];
+        sibling.extend_from_slice(&[0xab; 32]);
+        assert!(cbor.windows(sibling.len()).any(|window| window == sibling.as_slice()));
+        let decoded: ChronoProof<[u8; 32]> = ciborium::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!((decoded.path, decoded.delta_chain), (proof.path, proof.delta_chain));
+
+        let digest = Digest([7u8; 48]);
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&digest, &mut cbor).unwrap();
+        assert_eq!(cbor.len(), 2 + 48);
+        assert_eq!(ciborium::from_reader::<Digest<48>, _>(cbor.as_slice()).unwrap(), digest);
+    }
+
+    #[cfg(feature = "storage")]
+    #[test]
+    fn test_unknown_proof_fields() {
+        let mut proof: ChronoProof<[u8; 32]> = ChronoProof::new(1, 1001);
+        proof.add_step(crate::proof::ProofStep::Right([3; 32]));
+        let json = serde_json::to_string(&proof).unwrap();
+        let newer = json.replacen('{', "{\"signature\":\"00\",", 1);
+
+        let lenient: ChronoProof<[u8; 32]> = super::from_json(newer.as_bytes(), super::UnknownFields::Ignore).unwrap();
+        assert_eq!(lenient.path, proof.path);
+        let err = super::from_json::<ChronoProof<[u8; 32]>>(newer.as_bytes(), super::UnknownFields::Reject).unwrap_err();
+        assert!(err.to_string().contains("unknown field `signature`"), "{}", err);
+        assert!(super::from_json::<ChronoProof<[u8; 32]>>(json.as_bytes(), super::UnknownFields::Reject).is_ok());
+    }
+}
@@ -0,0 +1,904 @@
+//! HTTP service exposing a tree
+//!
+//! [`TreeService`] answers [`Request`]s for a shared tree and can be mounted in
+//! any HTTP framework through [`TreeService::handle`], or run stand-alone with
+//! the minimal HTTP/1.1 listener in [`TreeService::serve`].
+//!
+//! Proof endpoints, described by the OpenAPI document at `GET /openapi.json`
+//! (see [`openapi_spec`](crate::openapi::openapi_spec)):
+//!
+//! - `GET /root`: current root ([`RootResponse`])
+//! - `GET /proof/{index}`: inclusion proof for a leaf ([`ProofResponse`])
+//! - `GET /range?start=&end=`: leaves with timestamps in `[start, end]` ([`RangeResponse`])
+//! - `GET /aggregate?start=&end=&width=`: leaf counts per time bucket ([`AggregateResponse`]),
+//!   noised and suppressed according to the [`PrivacyPolicy`] set with
+//!   [`TreeService::with_aggregate_privacy`]; the noise of a bucket is the
+//!   same on every request, so repeating a query does not average it away
+//!
+//! Admin endpoints:
+//!
+//! - `GET /metrics`: Prometheus text exposition of tree gauges and request counters
+//! - `GET /healthz`: `200` while the tree is readable, `503` otherwise
+//! - `GET /debug/tree`: JSON summary with leaf count, depth, root, newest leaf timestamp and delta count
+//!
+//! With an [`Authenticator`] and [`RateLimiter`] configured, every endpoint
+//! except `/healthz` requires authentication and is rate limited per client.
+//! Failed authentications are rate limited per address with the same limit,
+//! and an address out of attempts is refused before its credentials are
+//! checked, so guessing keys is throttled too. Rejected requests are answered with `401` or `429` and logged through the
+//! tree's [`SecurityLogger`] as warnings.
+
+use crate::access_control::{Authenticator, RateLimit, RateLimiter};
+use crate::aggregate::{PrivacyPolicy, PrivateBucket};
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::traits::HashOutput;
+use crate::tree::ChronoMerkleTree;
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Largest request body accepted by [`TreeService::serve`]
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Longest request line or header line accepted by [`TreeService::serve`], in bytes
+const MAX_LINE: usize = 8 * 1024;
+
+/// Most headers accepted in one request by [`TreeService::serve`]
+const MAX_HEADERS: usize = 100;
+
+/// Longest [`TreeService::serve`] waits on a single read or write of a connection
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most connections [`TreeService::serve`] handles at once
+const MAX_CONNECTIONS: usize = 64;
+
+/// Name of the binary proof encoding carried in [`ProofResponse::proof`]
+pub const PROOF_ENCODING: &str = "chrono-merkle-compressed-v1";
+
+/// Body of `GET /root`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RootResponse {
+    /// Hex-encoded root hash, `null` for an empty tree
+    pub root: Option<String>,
+    /// Number of leaves
+    pub leaf_count: usize,
+}
+
+/// Body of `GET /proof/{index}`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProofResponse {
+    /// Index of the proven leaf
+    pub leaf_index: usize,
+    /// Timestamp of the proven leaf
+    pub timestamp: u64,
+    /// Hex-encoded hash of the proven leaf
+    pub leaf_hash: String,
+    /// Hex-encoded root the proof verifies against
+    pub root: String,
+    /// Encoding of `proof`, always [`PROOF_ENCODING`]
+    pub encoding: String,
+    /// Base64 of [`CompressedProof::to_bytes`](crate::CompressedProof::to_bytes)
+    pub proof: String,
+}
+
+/// A leaf in a [`RangeResponse`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RangeLeaf {
+    /// Leaf index
+    pub index: usize,
+    /// Leaf timestamp
+    pub timestamp: u64,
+    /// Hex-encoded leaf hash
+    pub hash: String,
+}
+
+/// Body of `GET /range`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RangeResponse {
+    /// Inclusive start timestamp
+    pub start: u64,
+    /// Inclusive end timestamp
+    pub end: u64,
+    /// Matching leaves in index order
+    pub leaves: Vec<RangeLeaf>,
+}
+
+/// Body of `GET /aggregate`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AggregateResponse {
+    /// Inclusive start timestamp
+    pub start: u64,
+    /// Inclusive end timestamp
+    pub end: u64,
+    /// Bucket width in timestamps
+    pub width: u64,
+    /// Privacy budget of the noise added to the counts, `null` for exact counts
+    pub epsilon: Option<f64>,
+    /// Every bucket from `start`, in order
+    pub buckets: Vec<PrivateBucket>,
+}
+
+/// Body of error responses
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ErrorResponse {
+    /// Human-readable error description
+    pub error: String,
+}
+
+/// An HTTP request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Request {
+    /// Method, e.g. `GET`
+    pub method: String,
+    /// Path including any query string
+    pub path: String,
+    /// Header names (lowercase) and values
+    pub headers: Vec<(String, String)>,
+    /// Request body
+    pub body: Vec<u8>,
+    /// Address of the peer, if known
+    pub remote_addr: Option<String>,
+}
+
+impl Request {
+    /// Create a `GET` request for `path`
+    pub fn get(path: impl Into<String>) -> Self {
+        Self {
+            method: "GET".to_string(),
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Path without the query string
+    pub fn route(&self) -> &str {
+        self.path.split_once('?').map_or(&self.path, |(route, _)| route)
+    }
+
+    /// Value of a query string parameter
+    pub fn query(&self, name: &str) -> Option<&str> {
+        let (_, query) = self.path.split_once('?')?;
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Value of a header, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// An HTTP response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// Status code
+    pub status: u16,
+    /// Value of the `Content-Type` header
+    pub content_type: &'static str,
+    /// Response body
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// JSON response with the given status
+    pub fn json(status: u16, value: &serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    /// Plain text response with the given status
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into().into_bytes(),
+        }
+    }
+
+    /// JSON response serialized from `body`
+    pub fn json_body(status: u16, body: &impl serde::Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec(body).unwrap_or_default(),
+        }
+    }
+
+    /// Error response with an [`ErrorResponse`] body
+    pub fn error(status: u16, error: impl Into<String>) -> Self {
+        Self::json_body(status, &ErrorResponse { error: error.into() })
+    }
+
+    fn not_found() -> Self {
+        Self::error(404, "not found")
+    }
+
+    fn unavailable(reason: &str) -> Self {
+        Self::json(503, &serde_json::json!({ "status": "unavailable", "reason": reason }))
+    }
+}
+
+/// Request handler serving a shared tree
+pub struct TreeService<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    tree: Arc<RwLock<ChronoMerkleTree<H, Hasher, Logger>>>,
+    started: Instant,
+    requests: Mutex<BTreeMap<&'static str, u64>>,
+    authenticator: Option<Box<dyn Authenticator>>,
+    rate_limiter: Option<RateLimiter>,
+    auth_failures: Option<RateLimiter>,
+    aggregate_privacy: Option<PrivacyPolicy>,
+    aggregate_key: [u8; 32],
+}
+
+impl<H, Hasher, Logger> TreeService<H, Hasher, Logger>
+where
+    H: HashOutput + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Serve `tree`, which may still be modified through other clones of the `Arc`
+    pub fn new(tree: Arc<RwLock<ChronoMerkleTree<H, Hasher, Logger>>>) -> Self {
+        Self {
+            tree,
+            started: Instant::now(),
+            requests: Mutex::new(BTreeMap::new()),
+            authenticator: None,
+            rate_limiter: None,
+            auth_failures: None,
+            aggregate_privacy: None,
+            aggregate_key: rand::random(),
+        }
+    }
+
+    /// Require requests to be authenticated by `authenticator`
+    pub fn with_authenticator(mut self, authenticator: impl Authenticator + 'static) -> Self {
+        self.authenticator = Some(Box::new(authenticator));
+        self
+    }
+
+    /// Limit each client, identified by authentication or else by address, to `limit`
+    ///
+    /// Failed authentications from each address are limited to `limit` as well.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(limit));
+        self.auth_failures = Some(RateLimiter::new(limit));
+        self
+    }
+
+    /// Publish `GET /aggregate` counts through `policy` instead of exactly
+    ///
+    /// Noise is derived with [`PrivacyPolicy::apply_keyed`] from a key drawn
+    /// when the service is created, so a bucket gets new noise only after a
+    /// restart; see [`with_aggregate_key`](Self::with_aggregate_key).
+    pub fn with_aggregate_privacy(mut self, policy: PrivacyPolicy) -> Self {
+        self.aggregate_privacy = Some(policy);
+        self
+    }
+
+    /// Derive `GET /aggregate` noise from the secret `key`
+    ///
+    /// Replicas serving the same tree, and restarts of a service, must share
+    /// the key; otherwise each one releases the same buckets with different
+    /// noise and readers can average it away across them.
+    pub fn with_aggregate_key(mut self, key: [u8; 32]) -> Self {
+        self.aggregate_key = key;
+        self
+    }
+
+    /// Get the served tree
+    pub fn tree(&self) -> &Arc<RwLock<ChronoMerkleTree<H, Hasher, Logger>>> {
+        &self.tree
+    }
+
+    /// Answer a request
+    pub fn handle(&self, request: &Request) -> Response {
+        let route = match request.route() {
+            "/root" => "/root",
+            path if path.starts_with("/proof/") => "/proof",
+            "/range" => "/range",
+            "/aggregate" => "/aggregate",
+            "/openapi.json" => "/openapi.json",
+            "/metrics" => "/metrics",
+            "/healthz" => "/healthz",
+            "/debug/tree" => "/debug/tree",
+            _ => "other",
+        };
+        *self
+            .requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(route)
+            .or_insert(0) += 1;
+
+        if route != "/healthz" {
+            if let Err(response) = self.admit(request) {
+                return response;
+            }
+        }
+        if request.method != "GET" {
+            return Response::error(405, "method not allowed");
+        }
+        match route {
+            "/root" => self.root(),
+            "/proof" => self.proof(&request.route()["/proof/".len()..]),
+            "/range" => self.range(request),
+            "/aggregate" => self.aggregate(request),
+            "/openapi.json" => Response::json(200, &crate::openapi::openapi_spec()),
+            "/metrics" => self.metrics(),
+            "/healthz" => self.health(),
+            "/debug/tree" => self.debug_tree(),
+            _ => Response::not_found(),
+        }
+    }
+
+    /// Authenticate and rate limit a request, logging rejections
+    fn admit(&self, request: &Request) -> core::result::Result<(), Response> {
+        let address = request.remote_addr.as_deref().unwrap_or("unknown");
+        let client = match &self.authenticator {
+            Some(authenticator) => {
+                if let Some(failures) = self.auth_failures.as_ref().filter(|failures| failures.is_exhausted(address)) {
+                    return Err(self.rate_limited(failures, address, request.route(), "too many failed authentications"));
+                }
+                match authenticator.authenticate(request) {
+                    Ok(client) => client,
+                    Err(reason) => {
+                        if let Some(failures) = &self.auth_failures {
+                            failures.try_acquire(address);
+                        }
+                        self.log_denied(address, request.route(), &reason);
+                        return Err(Response::error(401, "unauthorized"));
+                    }
+                }
+            }
+            None => address.to_string(),
+        };
+        if let Some(limiter) = &self.rate_limiter {
+            if !limiter.try_acquire(&client) {
+                return Err(self.rate_limited(limiter, &client, request.route(), "rate limit exceeded"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Log a request refused by `limiter` and build its `429` response
+    fn rate_limited(&self, limiter: &RateLimiter, client: &str, resource: &str, reason: &str) -> Response {
+        let retry_after = limiter.retry_after(client);
+        self.log_denied(client, resource, reason);
+        Response::error(429, format!("{}, retry after {} ms", reason, retry_after.as_millis()))
+    }
+
+    fn log_denied(&self, client: &str, resource: &str, reason: &str) {
+        let tree = self.tree.read().unwrap_or_else(|e| e.into_inner());
+        let _ = tree
+            .security_logger
+            .log_event(&crate::security::events::access_denied(client, resource, reason));
+    }
+
+    fn root(&self) -> Response {
+        let Ok(tree) = self.tree.read() else {
+            return Response::unavailable("tree lock poisoned by a panicked writer");
+        };
+        Response::json_body(
+            200,
+            &RootResponse {
+                root: tree.root().map(|root| crate::codec::to_hex(root.as_ref())),
+                leaf_count: tree.leaf_count(),
+            },
+        )
+    }
+
+    fn proof(&self, index: &str) -> Response {
+        let Ok(index) = index.parse::<usize>() else {
+            return Response::error(400, "leaf index must be a non-negative integer");
+        };
+        let Ok(tree) = self.tree.read() else {
+            return Response::unavailable("tree lock poisoned by a panicked writer");
+        };
+        let (proof, leaf_hash, root) = match (tree.generate_proof(index), tree.get_leaf_hash(index), tree.root()) {
+            (Ok(proof), Ok(leaf_hash), Some(root)) => (proof, leaf_hash, root),
+            _ => return Response::error(404, format!("no leaf at index {}", index)),
+        };
+        let compressed = proof.compress(&leaf_hash, &tree.hasher);
+        Response::json_body(
+            200,
+            &ProofResponse {
+                leaf_index: index,
+                timestamp: proof.timestamp,
+                leaf_hash: crate::codec::to_hex(leaf_hash.as_ref()),
+                root: crate::codec::to_hex(root.as_ref()),
+                encoding: PROOF_ENCODING.to_string(),
+                proof: crate::codec::to_base64(&compressed.to_bytes()),
+            },
+        )
+    }
+
+    fn range(&self, request: &Request) -> Response {
+        let bound = |name| request.query(name).map(str::parse::<u64>);
+        let (Some(Ok(start)), Some(Ok(end))) = (bound("start"), bound("end")) else {
+            return Response::error(400, "start and end must be integer timestamps");
+        };
+        let Ok(tree) = self.tree.read() else {
+            return Response::unavailable("tree lock poisoned by a panicked writer");
+        };
+        let leaves = tree
+            .find_range(start, end)
+            .into_iter()
+            .filter_map(|index| {
+                Some(RangeLeaf {
+                    index,
+                    timestamp: tree.get_leaf_timestamp(index).ok()?,
+                    hash: crate::codec::to_hex(tree.get_leaf_hash(index).ok()?.as_ref()),
+                })
+            })
+            .collect();
+        Response::json_body(200, &RangeResponse { start, end, leaves })
+    }
+
+    fn aggregate(&self, request: &Request) -> Response {
+        let param = |name| request.query(name).map(str::parse::<u64>);
+        let (Some(Ok(start)), Some(Ok(end)), Some(Ok(width))) = (param("start"), param("end"), param("width")) else {
+            return Response::error(400, "start, end and width must be integers");
+        };
+        let Ok(tree) = self.tree.read() else {
+            return Response::unavailable("tree lock poisoned by a panicked writer");
+        };
+        let counts = match tree.aggregate_by_bucket(start, end, width) {
+            Ok(counts) => counts,
+            Err(e) => return Response::error(400, e.to_string()),
+        };
+        let buckets = match &self.aggregate_privacy {
+            Some(policy) => policy.apply_keyed(&counts, width, &self.aggregate_key, &tree.hasher),
+            None => counts
+                .iter()
+                .map(|bucket| PrivateBucket { start: bucket.start, count: Some(bucket.count) })
+                .collect(),
+        };
+        drop(tree);
+        Response::json_body(
+            200,
+            &AggregateResponse {
+                start,
+                end,
+                width,
+                epsilon: self.aggregate_privacy.map(|policy| policy.epsilon),
+                buckets,
+            },
+        )
+    }
+
+    fn health(&self) -> Response {
+        match self.tree.read() {
+            Ok(tree) => Response::json(200, &serde_json::json!({ "status": "ok", "leaf_count": tree.leaf_count() })),
+            Err(_) => Response::unavailable("tree lock poisoned by a panicked writer"),
+        }
+    }
+
+    fn debug_tree(&self) -> Response {
+        let Ok(tree) = self.tree.read() else {
+            return Response::unavailable("tree lock poisoned by a panicked writer");
+        };
+        let last_insert = tree.leaf_count().checked_sub(1).and_then(|index| tree.get_leaf_timestamp(index).ok());
+        Response::json(
+            200,
+            &serde_json::json!({
+                "leaf_count": tree.leaf_count(),
+                "depth": tree.depth(),
+                "root": tree.root().map(|root| crate::codec::to_hex(root.as_ref())),
+                "last_insert_timestamp": last_insert,
+                "delta_count": tree.delta_log().len(),
+            }),
+        )
+    }
+
+    fn metrics(&self) -> Response {
+        use core::fmt::Write;
+
+        let Ok(tree) = self.tree.read() else {
+            return Response::unavailable("tree lock poisoned by a panicked writer");
+        };
+        let mut out = String::new();
+        let mut gauge = |name: &str, help: &str, value: u64| {
+            let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge\n{} {}", name, help, name, name, value);
+        };
+        gauge("chrono_merkle_leaves", "Number of leaves in the tree", tree.leaf_count() as u64);
+        gauge("chrono_merkle_depth", "Depth of the tree", tree.depth() as u64);
+        gauge("chrono_merkle_deltas", "Number of stored deltas", tree.delta_log().len() as u64);
+        if let Some(index) = tree.leaf_count().checked_sub(1) {
+            gauge(
+                "chrono_merkle_last_insert_timestamp",
+                "Timestamp of the newest leaf",
+                tree.get_leaf_timestamp(index).unwrap_or(0),
+            );
+        }
+        gauge("chrono_merkle_uptime_seconds", "Seconds since the service started", self.started.elapsed().as_secs());
+        if let Some(stats) = tree.proof_cache_stats() {
+            let _ = writeln!(
+                out,
+                "# HELP chrono_merkle_proof_cache_lookups_total Proof cache lookups by result\n\
+                 # TYPE chrono_merkle_proof_cache_lookups_total counter\n\
+                 chrono_merkle_proof_cache_lookups_total{{result=\"hit\"}} {}\n\
+                 chrono_merkle_proof_cache_lookups_total{{result=\"miss\"}} {}",
+                stats.hits, stats.misses
+            );
+        }
+        drop(tree);
+
+        let _ = writeln!(
+            out,
+            "# HELP chrono_merkle_http_requests_total HTTP requests by route\n# TYPE chrono_merkle_http_requests_total counter"
+        );
+        for (route, count) in self.requests.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let _ = writeln!(out, "chrono_merkle_http_requests_total{{route=\"{}\"}} {}", route, count);
+        }
+
+        Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            body: out.into_bytes(),
+        }
+    }
+}
+
+impl<H, Hasher, Logger> TreeService<H, Hasher, Logger>
+where
+    H: HashOutput + Send + Sync + crate::traits::MaybeSerde + 'static,
+    Hasher: HashFunction<Output = H> + Send + Sync + 'static,
+    Logger: SecurityLogger + Send + Sync + 'static,
+{
+    /// Accept connections on `listener` until it fails, one thread per connection
+    ///
+    /// Each connection carries a single request and is closed after the response.
+    /// At most 64 connections are handled at once; further connections wait in
+    /// the listen backlog. Reads and writes that stall for 10 seconds close the
+    /// connection, and oversized request lines, headers and bodies are refused.
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        let slots = Arc::new(ConnectionSlots::default());
+        for stream in listener.incoming() {
+            let stream = stream.map_err(|e| ChronoMerkleError::InvalidConfiguration {
+                parameter: "listener".to_string(),
+                reason: format!("Failed to accept connection: {}", e),
+            })?;
+            let slot = slots.acquire();
+            let service = self.clone();
+            std::thread::spawn(move || {
+                let _ = service.handle_connection(stream);
+                drop(slot);
+            });
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let response = match read_request(&mut reader) {
+            Ok(mut request) => {
+                request.remote_addr = stream.peer_addr().ok().map(|address| address.ip().to_string());
+                self.handle(&request)
+            }
+            Err(e) => Response::text(400, format!("Bad request: {}", e)),
+        };
+        write_response(stream, &response)
+    }
+}
+
+/// Number of connections being handled by [`TreeService::serve`]
+#[derive(Default)]
+struct ConnectionSlots {
+    active: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl ConnectionSlots {
+    /// Wait until fewer than [`MAX_CONNECTIONS`] connections are handled, then take a slot
+    fn acquire(self: &Arc<Self>) -> ConnectionSlot {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        while *active >= MAX_CONNECTIONS {
+            active = self.freed.wait(active).unwrap_or_else(|e| e.into_inner());
+        }
+        *active += 1;
+        ConnectionSlot(self.clone())
+    }
+}
+
+/// A slot taken from [`ConnectionSlots`], given back when dropped
+struct ConnectionSlot(Arc<ConnectionSlots>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        *self.0.active.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+        self.0.freed.notify_one();
+    }
+}
+
+/// Read one line of at most [`MAX_LINE`] bytes into `line`, returning its length
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> std::io::Result<usize> {
+    let read = reader.by_ref().take(MAX_LINE as u64 + 1).read_line(line)?;
+    if read > MAX_LINE {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "line too long"));
+    }
+    Ok(read)
+}
+
+fn read_request(reader: &mut impl BufRead) -> std::io::Result<Request> {
+    let invalid = |reason: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, reason.to_string());
+
+    let mut line = String::new();
+    read_line(reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(invalid("malformed request line"));
+    };
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        ..Request::default()
+    };
+
+    loop {
+        line.clear();
+        if read_line(reader, &mut line)? == 0 {
+            return Err(invalid("connection closed inside headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if request.headers.len() == MAX_HEADERS {
+            return Err(invalid("too many headers"));
+        }
+        let (name, value) = header.split_once(':').ok_or_else(|| invalid("malformed header"))?;
+        request.headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    let length = match request.header("content-length") {
+        Some(length) => length.parse::<usize>().map_err(|_| invalid("invalid content-length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(invalid("request body too large"));
+    }
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+    Ok(request)
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Status",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    fn service() -> TreeService<[u8; 32], crate::hash::Blake3Hasher, crate::security::NoOpLogger> {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert(b"data1", 1000).unwrap();
+        tree.insert(b"data2", 1001).unwrap();
+        TreeService::new(Arc::new(RwLock::new(tree)))
+    }
+
+    fn body(response: &Response) -> String {
+        String::from_utf8(response.body.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_admin_endpoints() {
+        let service = service();
+
+        let health = service.handle(&Request::get("/healthz"));
+        assert_eq!(health.status, 200);
+
+        let debug: serde_json::Value = serde_json::from_slice(&service.handle(&Request::get("/debug/tree")).body).unwrap();
+        assert_eq!(debug["leaf_count"], 2);
+        assert_eq!(debug["last_insert_timestamp"], 1001);
+        assert_eq!(debug["root"].as_str().unwrap().len(), 64);
+
+        let metrics = service.handle(&Request::get("/metrics?verbose=1"));
+        assert!(metrics.content_type.starts_with("text/plain; version=0.0.4"));
+        let metrics = body(&metrics);
+        assert!(metrics.contains("chrono_merkle_leaves 2\n"));
+        assert!(metrics.contains("chrono_merkle_http_requests_total{route=\"/healthz\"} 1\n"));
+
+        assert_eq!(service.handle(&Request::get("/nope")).status, 404);
+    }
+
+    #[test]
+    fn test_proof_endpoint_round_trips() {
+        let service = service();
+        let response: ProofResponse =
+            serde_json::from_slice(&service.handle(&Request::get("/proof/1")).body).unwrap();
+        assert_eq!((response.leaf_index, response.timestamp), (1, 1001));
+        assert_eq!(response.encoding, PROOF_ENCODING);
+
+        let bytes = decode_base64(&response.proof);
+        let compressed = crate::CompressedProof::<[u8; 32]>::from_bytes(&bytes).unwrap();
+        let tree = service.tree().read().unwrap();
+        let proof = crate::ChronoProof::decompress(&compressed, &tree.get_leaf_hash(1).unwrap(), &tree.hasher).unwrap();
+        assert!(tree.verify_proof(&proof).unwrap());
+
+        let range: RangeResponse =
+            serde_json::from_slice(&service.handle(&Request::get("/range?start=1001&end=5000")).body).unwrap();
+        assert_eq!(range.leaves.len(), 1);
+        assert_eq!(service.handle(&Request::get("/proof/x")).status, 400);
+        assert_eq!(service.handle(&Request::get("/range?start=1")).status, 400);
+    }
+
+    #[test]
+    fn test_aggregate_endpoint() {
+        let get = |service: &TreeService<_, _, _>, path: &str| -> AggregateResponse {
+            serde_json::from_slice(&service.handle(&Request::get(path)).body).unwrap()
+        };
+        let exact = get(&service(), "/aggregate?start=1000&end=1009&width=5");
+        assert_eq!(exact.epsilon, None);
+        assert_eq!(
+            exact.buckets,
+            vec![PrivateBucket { start: 1000, count: Some(2) }, PrivateBucket { start: 1005, count: Some(0) }]
+        );
+
+        // A threshold far above any plausible noise suppresses every bucket
+        let private = service().with_aggregate_privacy(PrivacyPolicy::new(1.0, 1_000).unwrap());
+        let noisy = get(&private, "/aggregate?start=1000&end=1009&width=5");
+        assert_eq!(noisy.epsilon, Some(1.0));
+        assert!(noisy.buckets.iter().all(|bucket| bucket.count.is_none()));
+
+        // Repeating a query returns the same noise; replicas sharing a key agree
+        let key = [7u8; 32];
+        let policy = PrivacyPolicy::new(0.01, 0).unwrap();
+        let path = "/aggregate?start=1000&end=1099&width=5";
+        let replica = service().with_aggregate_privacy(policy).with_aggregate_key(key);
+        let released = get(&replica, path);
+        assert_eq!(get(&replica, path), released);
+        assert_eq!(get(&service().with_aggregate_privacy(policy).with_aggregate_key(key), path), released);
+
+        assert_eq!(private.handle(&Request::get("/aggregate?start=1000&end=1009")).status, 400);
+        assert_eq!(private.handle(&Request::get("/aggregate?start=0&end=1000000000&width=1")).status, 400);
+    }
+
+    fn decode_base64(text: &str) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let values: Vec<u32> = text
+            .bytes()
+            .filter(|&byte| byte != b'=')
+            .map(|byte| ALPHABET.iter().position(|&c| c == byte).unwrap() as u32)
+            .collect();
+        let mut out = Vec::new();
+        for chunk in values.chunks(4) {
+            let group = chunk.iter().enumerate().fold(0, |acc, (i, v)| acc | v << (18 - 6 * i));
+            out.extend_from_slice(&group.to_be_bytes()[1..chunk.len()]);
+        }
+        out
+    }
+
+    /// Logger keeping events for inspection
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<crate::security::SecurityEvent>>>);
+
+    impl SecurityLogger for Recorder {
+        fn log_event(&self, event: &crate::security::SecurityEvent) -> Result<()> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_auth_and_rate_limit() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let tree = ChronoMerkleTree::with_logger(crate::hash::Blake3Hasher, Recorder(events.clone()));
+        let service = TreeService::new(Arc::new(RwLock::new(tree)))
+            .with_authenticator(crate::access_control::ApiKeyAuthenticator::new().with_key("secret", "alice"))
+            .with_rate_limit(RateLimit { burst: 1, per_second: 0.0 });
+        // From another address than the failed request, which used up its attempts
+        let authorized = Request {
+            headers: vec![("x-api-key".to_string(), "secret".to_string())],
+            remote_addr: Some("10.0.0.1".to_string()),
+            ..Request::get("/root")
+        };
+
+        assert_eq!(service.handle(&Request::get("/healthz")).status, 200);
+        assert_eq!(service.handle(&Request::get("/root")).status, 401);
+        assert_eq!(service.handle(&authorized).status, 200);
+        assert_eq!(service.handle(&authorized).status, 429);
+
+        let events = events.lock().unwrap();
+        let denied: Vec<_> = events
+            .iter()
+            .filter(|event| event.event_type == crate::security::SecurityEventType::AccessDenied)
+            .collect();
+        assert_eq!(denied.len(), 2);
+        assert!(denied.iter().all(|event| event.level == crate::security::SecurityLevel::Warning));
+        assert!(denied[1].description.contains("alice"));
+    }
+
+    #[test]
+    fn test_failed_authentication_is_rate_limited() {
+        let service = service()
+            .with_authenticator(crate::access_control::ApiKeyAuthenticator::new().with_key("secret", "alice"))
+            .with_rate_limit(RateLimit { burst: 2, per_second: 0.0 });
+        let from = |address: &str, key: &str| Request {
+            headers: vec![("x-api-key".to_string(), key.to_string())],
+            remote_addr: Some(address.to_string()),
+            ..Request::get("/root")
+        };
+
+        // Guesses from one address stop reaching the authenticator
+        assert_eq!(service.handle(&from("10.0.0.1", "guess")).status, 401);
+        assert_eq!(service.handle(&from("10.0.0.1", "guess")).status, 401);
+        assert_eq!(service.handle(&from("10.0.0.1", "guess")).status, 429);
+        assert_eq!(service.handle(&from("10.0.0.1", "secret")).status, 429);
+
+        // Other addresses and their clients are unaffected
+        assert_eq!(service.handle(&from("10.0.0.2", "guess")).status, 401);
+        assert_eq!(service.handle(&from("10.0.0.2", "secret")).status, 200);
+    }
+
+    #[test]
+    fn test_request_size_limits() {
+        let parse = |raw: Vec<u8>| read_request(&mut std::io::Cursor::new(raw));
+        let request = |headers: usize, line: usize| {
+            let mut raw = format!("GET /{} HTTP/1.1\r\n", "a".repeat(line)).into_bytes();
+            raw.extend((0..headers).flat_map(|i| format!("x-{}: 1\r\n", i).into_bytes()));
+            raw.extend_from_slice(b"\r\n");
+            raw
+        };
+
+        assert_eq!(parse(request(MAX_HEADERS, 100)).unwrap().headers.len(), MAX_HEADERS);
+        assert!(parse(request(MAX_HEADERS + 1, 100)).is_err());
+        assert!(parse(request(0, MAX_LINE)).is_err());
+        assert!(parse(b"GET / HTTP/1.1\r\ncontent-length: 1048577\r\n\r\n".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_serves_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let service = Arc::new(service());
+        std::thread::spawn(move || service.serve(listener));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("{\"leaf_count\":2,\"status\":\"ok\"}"));
+    }
+}
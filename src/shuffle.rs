@@ -0,0 +1,288 @@
+//! Committed leaf order decoupled from insertion order
+//!
+//! Leaf positions reveal insertion order, so a public server answering
+//! position-based queries leaks which records clients are interested in and
+//! when they were written. [`ChronoMerkleTree::shuffled`] commits to the same
+//! leaves in a pseudorandom order and publishes the root of that arrangement
+//! alongside the logical root. Clients query committed positions, and a
+//! [`ShuffleProof`] relates a logical index to its committed position.
+//!
+//! The permutation is a four-round Feistel network over the smallest
+//! even-width bit domain covering the leaves, with cycle walking, keyed by a
+//! seed derived from the logical root and an optional key. It can be evaluated
+//! for a single index, so verification does not depend on the tree size. With
+//! an empty key anyone holding the logical root can compute the permutation; a
+//! key shared only with authorized clients hides it from traffic observers.
+
+use crate::encoding::encode_u64;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::{ChronoProof, ProofStep};
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{string::ToString, vec, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::vec::Vec;
+
+/// Domain separator of the permutation seed
+const SHUFFLE_DOMAIN: &[u8] = b"chrono-merkle/shuffle/v1";
+/// Feistel rounds of the permutation
+const ROUNDS: u8 = 4;
+
+/// Seed of the permutation for a logical root and key
+pub fn shuffle_seed<Hasher: HashFunction>(hasher: &Hasher, logical_root: &Hasher::Output, key: &[u8]) -> Hasher::Output {
+    hasher.hash_multiple(&[SHUFFLE_DOMAIN, logical_root.as_ref(), key])
+}
+
+/// Committed position of logical index `index` among `leaf_count` leaves
+pub fn shuffled_position<Hasher: HashFunction>(hasher: &Hasher, seed: &Hasher::Output, leaf_count: usize, index: usize) -> usize {
+    cycle_walk(hasher, seed, leaf_count, index, false)
+}
+
+/// Logical index of the leaf at committed position `position`
+pub fn logical_index<Hasher: HashFunction>(hasher: &Hasher, seed: &Hasher::Output, leaf_count: usize, position: usize) -> usize {
+    cycle_walk(hasher, seed, leaf_count, position, true)
+}
+
+fn cycle_walk<Hasher: HashFunction>(hasher: &Hasher, seed: &Hasher::Output, leaf_count: usize, value: usize, inverse: bool) -> usize {
+    if leaf_count <= 1 {
+        return value;
+    }
+    let width = usize::BITS - (leaf_count - 1).leading_zeros();
+    let half = width.div_ceil(2);
+    let mut value = value as u64;
+    loop {
+        value = feistel(hasher, seed, half, value, inverse);
+        if value < leaf_count as u64 {
+            return value as usize;
+        }
+    }
+}
+
+fn feistel<Hasher: HashFunction>(hasher: &Hasher, seed: &Hasher::Output, half: u32, value: u64, inverse: bool) -> u64 {
+    let mask = (1u64 << half) - 1;
+    let round = |round: u8, input: u64| {
+        let digest = hasher.hash_multiple(&[seed.as_ref(), &[round], &encode_u64(input)]);
+        let mut bytes = [0u8; 8];
+        let len = digest.as_ref().len().min(8);
+        bytes[..len].copy_from_slice(&digest.as_ref()[..len]);
+        u64::from_le_bytes(bytes) & mask
+    };
+    let (mut left, mut right) = (value >> half, value & mask);
+    if inverse {
+        for r in (0..ROUNDS).rev() {
+            (left, right) = (right ^ round(r, left), left);
+        }
+    } else {
+        for r in 0..ROUNDS {
+            (left, right) = (right, left ^ round(r, right));
+        }
+    }
+    (left << half) | right
+}
+
+/// Proof that the leaf at a logical index sits at its committed position
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone)]
+pub struct ShuffleProof<H> {
+    /// Number of leaves, taken from the published tree head
+    pub leaf_count: usize,
+    /// Inclusion proof at the logical index under the logical root
+    pub logical_proof: ChronoProof<H>,
+    /// Inclusion proof at the committed position under the committed root
+    pub committed_proof: ChronoProof<H>,
+}
+
+impl<H: AsRef<[u8]> + Clone + Eq> ShuffleProof<H> {
+    /// Verify that `leaf_hash` is at the logical index under `logical_root` and
+    /// at the position the permutation assigns it under `committed_root`
+    pub fn verify<Hasher>(&self, leaf_hash: &H, logical_root: &H, committed_root: &H, key: &[u8], hasher: &Hasher) -> Result<bool>
+    where
+        Hasher: HashFunction<Output = H>,
+    {
+        let seed = shuffle_seed(hasher, logical_root, key);
+        let index = self.logical_proof.leaf_index;
+        if index >= self.leaf_count
+            || shuffled_position(hasher, &seed, self.leaf_count, index) != self.committed_proof.leaf_index
+        {
+            return Ok(false);
+        }
+        Ok(crate::proof::verify_proof(&self.logical_proof, leaf_hash, logical_root, hasher)?
+            && crate::proof::verify_proof(&self.committed_proof, leaf_hash, committed_root, hasher)?)
+    }
+}
+
+/// Leaves of a tree committed in shuffled order
+#[derive(Debug, Clone)]
+pub struct ShuffledTree<H> {
+    /// Logical leaf order, then parent levels up to the root
+    logical: Vec<Vec<H>>,
+    /// Shuffled leaf order, then parent levels up to the root
+    committed: Vec<Vec<H>>,
+    timestamps: Vec<u64>,
+    /// Committed position of each logical index
+    positions: Vec<usize>,
+    /// Logical index at each committed position
+    indices: Vec<usize>,
+}
+
+impl<H: Clone> ShuffledTree<H> {
+    /// Root of the tree in insertion order
+    pub fn logical_root(&self) -> &H {
+        &self.logical[self.logical.len() - 1][0]
+    }
+
+    /// Root of the tree in shuffled order, published for position queries
+    pub fn committed_root(&self) -> &H {
+        &self.committed[self.committed.len() - 1][0]
+    }
+
+    /// Number of leaves
+    pub fn leaf_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Committed position of logical index `index`
+    pub fn position_of(&self, index: usize) -> Option<usize> {
+        self.positions.get(index).copied()
+    }
+
+    /// Proof at committed position `position`, as served to position queries
+    pub fn prove_position(&self, position: usize) -> Result<ChronoProof<H>> {
+        let index = *self.indices.get(position).ok_or(ChronoMerkleError::IndexOutOfBounds {
+            index: position,
+            leaf_count: self.leaf_count(),
+        })?;
+        Ok(path_proof(&self.committed, position, self.timestamps[index]))
+    }
+
+    /// Proof relating logical index `index` to its committed position
+    pub fn prove(&self, index: usize) -> Result<ShuffleProof<H>> {
+        let position = self.position_of(index).ok_or(ChronoMerkleError::IndexOutOfBounds {
+            index,
+            leaf_count: self.leaf_count(),
+        })?;
+        Ok(ShuffleProof {
+            leaf_count: self.leaf_count(),
+            logical_proof: path_proof(&self.logical, index, self.timestamps[index]),
+            committed_proof: path_proof(&self.committed, position, self.timestamps[index]),
+        })
+    }
+}
+
+/// All levels of a tree over `leaves`, pairing an odd last node with itself
+fn levels<Hasher: HashFunction>(leaves: Vec<Hasher::Output>, hasher: &Hasher) -> Vec<Vec<Hasher::Output>> {
+    let mut levels = vec![leaves];
+    while levels[levels.len() - 1].len() > 1 {
+        let next = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|pair| hasher.hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+fn path_proof<H: Clone>(levels: &[Vec<H>], index: usize, timestamp: u64) -> ChronoProof<H> {
+    let mut proof = ChronoProof::new(index, timestamp);
+    let mut index = index;
+    for level in &levels[..levels.len() - 1] {
+        let sibling = level.get(index ^ 1).unwrap_or(&level[index]).clone();
+        proof.add_step(if index % 2 == 0 {
+            ProofStep::Right(sibling)
+        } else {
+            ProofStep::Left(sibling)
+        });
+        index /= 2;
+    }
+    proof
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Commit to the current leaves in an order derived from the root and `key`
+    pub fn shuffled(&self, key: &[u8]) -> Result<ShuffledTree<H>> {
+        let logical_root = self.root().ok_or(ChronoMerkleError::EmptyTree)?;
+        let seed = shuffle_seed(&self.hasher, &logical_root, key);
+        let leaf_count = self.leaf_count();
+        let leaves: Vec<H> = self.nodes.leaves().iter().map(|node| node.hash()).collect();
+        let timestamps = self.nodes.leaves().iter().map(|node| node.timestamp_info().0).collect();
+        let positions: Vec<usize> =
+            (0..leaf_count).map(|index| shuffled_position(&self.hasher, &seed, leaf_count, index)).collect();
+
+        let mut indices = vec![0; leaf_count];
+        for (index, position) in positions.iter().enumerate() {
+            indices[*position] = index;
+        }
+        let shuffled = indices.iter().map(|index| leaves[*index].clone()).collect();
+        Ok(ShuffledTree {
+            logical: levels(leaves, &self.hasher),
+            committed: levels(shuffled, &self.hasher),
+            timestamps,
+            positions,
+            indices,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    #[test]
+    fn test_permutation_is_a_bijection() {
+        let seed = Blake3Hasher.hash(b"seed");
+        for leaf_count in [1, 2, 3, 5, 16, 17, 100] {
+            let mut seen = vec![false; leaf_count];
+            for index in 0..leaf_count {
+                let position = shuffled_position(&Blake3Hasher, &seed, leaf_count, index);
+                assert!(!seen[position]);
+                seen[position] = true;
+                assert_eq!(logical_index(&Blake3Hasher, &seed, leaf_count, position), index);
+            }
+        }
+        let identity = (0..100).all(|i| shuffled_position(&Blake3Hasher, &seed, 100, i) == i);
+        assert!(!identity);
+    }
+
+    #[test]
+    fn test_shuffle_proofs() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..13u64 {
+            tree.insert(format!("record{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        let shuffled = tree.shuffled(b"client key").unwrap();
+        assert_eq!(Some(*shuffled.logical_root()), tree.root());
+        assert_ne!(shuffled.committed_root(), shuffled.logical_root());
+        assert_ne!(tree.shuffled(b"").unwrap().committed_root(), shuffled.committed_root());
+
+        let (logical_root, committed_root) = (*shuffled.logical_root(), *shuffled.committed_root());
+        // Another key only lands a leaf on its position by chance
+        let mut wrong_key_matches = 0;
+        for index in 0..13 {
+            let leaf_hash = tree.get_leaf_hash(index).unwrap();
+            let proof = shuffled.prove(index).unwrap();
+            assert!(tree.verify_proof(&proof.logical_proof).unwrap());
+            assert!(proof.verify(&leaf_hash, &logical_root, &committed_root, b"client key", &Blake3Hasher).unwrap());
+            wrong_key_matches += usize::from(
+                proof.verify(&leaf_hash, &logical_root, &committed_root, b"other key", &Blake3Hasher).unwrap(),
+            );
+
+            let position = shuffled.position_of(index).unwrap();
+            let served = shuffled.prove_position(position).unwrap();
+            assert_eq!(served.timestamp, 1000 + index as u64);
+            assert!(crate::proof::verify_proof(&served, &leaf_hash, &committed_root, &Blake3Hasher).unwrap());
+        }
+        assert!(wrong_key_matches < 13);
+        assert!(shuffled.prove(13).is_err());
+        assert!(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default().shuffled(b"").is_err());
+    }
+}
@@ -0,0 +1,556 @@
+//! Copy-on-write tree snapshots
+//!
+//! Tree nodes, deltas and root history live in [`SharedVec`]s of fixed-size
+//! chunks, and the timestamp indexes and redactions are shared the same way,
+//! so taking a [`TreeSnapshot`] only bumps reference counts. A mutation after
+//! a snapshot copies just the chunks it writes to, leaving the snapshot
+//! untouched; the snapshot can then be serialized on another thread while
+//! inserts continue.
+
+use crate::config::TreeConfig;
+use crate::hash::HashFunction;
+use crate::levels::Levels;
+use crate::node::Node;
+use crate::sparse_index::SparseIndex;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{sync::Arc, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::{sync::Arc, vec::Vec};
+#[cfg(all(feature = "serde", feature = "no-std"))]
+use alloc::borrow::Cow;
+#[cfg(all(feature = "serde", not(feature = "no-std")))]
+use std::borrow::Cow;
+
+/// Elements per chunk of a [`SharedVec`]
+const CHUNK_LEN: usize = 256;
+
+/// A vector shared between a tree and its snapshots, copied chunk by chunk on write
+///
+/// Elements live in chunks of [`CHUNK_LEN`], each behind its own `Arc`, so a
+/// clone only bumps their reference counts and a write copies just the chunk
+/// it touches. Every chunk before the one holding the last element is full;
+/// chunks after it are empty room left by [`reserve`](Self::reserve).
+pub(crate) struct SharedVec<T> {
+    chunks: Vec<Arc<Vec<T>>>,
+    len: usize,
+}
+
+impl<T> SharedVec<T> {
+    /// Create an empty shared vector
+    pub(crate) fn new() -> Self {
+        Self { chunks: Vec::new(), len: 0 }
+    }
+
+    /// Number of elements
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no elements
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Element at `index`
+    pub(crate) fn get(&self, index: usize) -> Option<&T> {
+        (index < self.len).then(|| &self.chunks[index / CHUNK_LEN][index % CHUNK_LEN])
+    }
+
+    /// The first element
+    pub(crate) fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// The last element
+    pub(crate) fn last(&self) -> Option<&T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    /// Iterate over the elements in order
+    pub(crate) fn iter(&self) -> Iter<'_, T> {
+        Iter { vec: self, front: 0, back: self.len }
+    }
+
+    /// Number of leading elements for which `pred` holds, like [`slice::partition_point`]
+    pub(crate) fn partition_point(&self, mut pred: impl FnMut(&T) -> bool) -> usize {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if pred(&self[middle]) {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    }
+
+    /// Whether `self` and `other` share the storage of the chunk holding `index`
+    #[cfg(test)]
+    pub(crate) fn shares_chunk(&self, other: &Self, index: usize) -> bool {
+        match (self.chunks.get(index / CHUNK_LEN), other.chunks.get(index / CHUNK_LEN)) {
+            (Some(mine), Some(theirs)) => Arc::ptr_eq(mine, theirs),
+            _ => false,
+        }
+    }
+}
+
+impl<T: Clone> SharedVec<T> {
+    /// Chunk holding `index`, copied first if a clone still shares it
+    fn chunk_mut(&mut self, index: usize) -> &mut Vec<T> {
+        Arc::make_mut(&mut self.chunks[index / CHUNK_LEN])
+    }
+
+    /// Mutable element at `index`
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        Some(&mut self.chunk_mut(index)[index % CHUNK_LEN])
+    }
+
+    /// Append an element
+    pub(crate) fn push(&mut self, value: T) {
+        if self.len / CHUNK_LEN == self.chunks.len() {
+            self.chunks.push(Arc::new(Vec::with_capacity(CHUNK_LEN)));
+        }
+        let len = self.len;
+        self.chunk_mut(len).push(value);
+        self.len += 1;
+    }
+
+    /// Append every element of `values`
+    #[cfg_attr(feature = "minimal", allow(dead_code))]
+    pub(crate) fn extend(&mut self, values: impl IntoIterator<Item = T>) {
+        for value in values {
+            self.push(value);
+        }
+    }
+
+    /// Keep the first `len` elements, leaving the emptied chunks as reserved room
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        for chunk in &mut self.chunks[len.div_ceil(CHUNK_LEN)..self.len.div_ceil(CHUNK_LEN)] {
+            match Arc::get_mut(chunk) {
+                Some(chunk) => chunk.clear(),
+                None => *chunk = Arc::new(Vec::new()),
+            }
+        }
+        if len % CHUNK_LEN != 0 {
+            self.chunk_mut(len).truncate(len % CHUNK_LEN);
+        }
+        self.len = len;
+    }
+
+    /// Keep only the elements for which `keep` holds
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        *self = self.iter().filter(|value| keep(value)).cloned().collect();
+    }
+
+    /// Iterate mutably over the elements, copying every shared chunk
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        self.chunks.iter_mut().flat_map(|chunk| Arc::make_mut(chunk).iter_mut())
+    }
+
+    /// Make room for `additional` more elements, so pushing them allocates nothing
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        let needed = (self.len + additional).div_ceil(CHUNK_LEN);
+        if let Some(chunk) = self.chunks.get_mut(self.len / CHUNK_LEN) {
+            let room = CHUNK_LEN - chunk.len();
+            Arc::make_mut(chunk).reserve_exact(room);
+        }
+        self.chunks.reserve(needed.saturating_sub(self.chunks.len()));
+        while self.chunks.len() < needed {
+            self.chunks.push(Arc::new(Vec::with_capacity(CHUNK_LEN)));
+        }
+    }
+
+    /// Copy all elements into one vector
+    pub(crate) fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T> Clone for SharedVec<T> {
+    fn clone(&self) -> Self {
+        Self { chunks: self.chunks.clone(), len: self.len }
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for SharedVec<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<T> Default for SharedVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Vec<T>> for SharedVec<T> {
+    fn from(vec: Vec<T>) -> Self {
+        let len = vec.len();
+        let mut chunks = Vec::with_capacity(len.div_ceil(CHUNK_LEN));
+        let mut rest = vec.into_iter();
+        while rest.len() > 0 {
+            chunks.push(Arc::new(rest.by_ref().take(CHUNK_LEN).collect()));
+        }
+        Self { chunks, len }
+    }
+}
+
+impl<T> FromIterator<T> for SharedVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+impl<T> core::ops::Index<usize> for SharedVec<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(value) => value,
+            None => panic!("index {} out of range for {} elements", index, self.len),
+        }
+    }
+}
+
+impl<T: Clone> core::ops::IndexMut<usize> for SharedVec<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let len = self.len;
+        match self.get_mut(index) {
+            Some(value) => value,
+            None => panic!("index {} out of range for {} elements", index, len),
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SharedVec<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for SharedVec<T> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self)
+    }
+}
+
+/// Iterator over the elements of a [`SharedVec`]
+pub(crate) struct Iter<'a, T> {
+    vec: &'a SharedVec<T>,
+    front: usize,
+    back: usize,
+}
+
+impl<T> Clone for Iter<'_, T> {
+    fn clone(&self) -> Self {
+        Self { vec: self.vec, front: self.front, back: self.back }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(&self.vec[self.front - 1])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.back - self.front, Some(self.back - self.front))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a T> {
+        self.front = self.front.saturating_add(n).min(self.back);
+        self.next()
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(&self.vec[self.back])
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+/// A consistent, immutable view of a tree's state at one point in time
+#[derive(Clone)]
+pub struct TreeSnapshot<H> {
+    nodes: Levels<Node<H>>,
+    leaf_count: usize,
+    sparse_index: SparseIndex,
+    // Only read when the snapshot is serialized
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    config: TreeConfig,
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    incremental_updates: bool,
+    stored_deltas: SharedVec<Node<H>>,
+    delta_chains: SparseIndex,
+    root_history: SharedVec<crate::root_history::RootRecord<H>>,
+    redactions: crate::redaction::RedactionMap<H>,
+    leaf_store: Option<Arc<crate::dedup::LeafStore<H>>>,
+}
+
+impl<H: AsRef<[u8]> + Clone> TreeSnapshot<H> {
+    /// Root hash at the time the snapshot was taken
+    pub fn root(&self) -> Option<H> {
+        self.nodes.last().map(|n| n.hash())
+    }
+
+    /// Number of leaves at the time the snapshot was taken
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Convert the snapshot into an owned [`TreeState`](crate::storage::TreeState)
+    #[cfg(feature = "storage")]
+    pub fn to_state(&self) -> crate::storage::TreeState<H>
+    where
+        H: crate::traits::MaybeSerde,
+    {
+        let (stored_deltas, delta_chains) = self.persisted_deltas();
+        crate::storage::TreeState {
+            nodes: self.leaves_with_data().map(Cow::into_owned).chain(self.nodes.upper_levels().cloned()).collect(),
+            leaf_count: self.leaf_count,
+            sparse_index: self.sparse_index.clone(),
+            config: self.config.clone(),
+            incremental_updates: self.incremental_updates,
+            stored_deltas: stored_deltas.to_vec(),
+            delta_chains,
+            root_history: self.persisted_root_history().to_vec(),
+            redactions: self.redactions.values().cloned().collect(),
+            data_cipher: None,
+        }
+    }
+
+    /// Delta log and its index as saved under the configured persistence
+    #[cfg(feature = "serde")]
+    fn persisted_deltas(&self) -> (SharedVec<Node<H>>, SparseIndex) {
+        if self.config.persist_deltas {
+            (self.stored_deltas.clone(), self.delta_chains.clone())
+        } else {
+            (SharedVec::new(), SparseIndex::new(self.delta_chains.sparsity()))
+        }
+    }
+
+    /// Root history as saved under the configured persistence
+    #[cfg(feature = "serde")]
+    fn persisted_root_history(&self) -> SharedVec<crate::root_history::RootRecord<H>> {
+        if self.config.persist_root_history {
+            self.root_history.clone()
+        } else {
+            SharedVec::new()
+        }
+    }
+
+    /// Leaves with shared payloads copied back into them
+    #[cfg(feature = "serde")]
+    fn leaves_with_data(&self) -> impl Iterator<Item = Cow<'_, Node<H>>> + '_ {
+        crate::dedup::LeafStore::fill(self.leaf_store.as_deref(), self.nodes.leaves(), 0, self.leaf_count, |index| {
+            self.redactions.contains_key(&index)
+        })
+    }
+
+    /// Drop the timestamp and delta indexes, keeping their settings
+    #[cfg(feature = "storage")]
+    pub(crate) fn without_indexes(mut self) -> Self {
+        self.sparse_index = SparseIndex::with_budget(self.sparse_index.sparsity(), self.sparse_index.max_entries());
+        self.delta_chains = SparseIndex::new(self.delta_chains.sparsity());
+        self
+    }
+
+    /// Serialize the snapshot and save it under `key`
+    ///
+    /// The encoding is identical to `save_state`, so the result can be restored
+    /// with `load_state`.
+    ///
+    /// Serialization runs on a scoped thread and is streamed into
+    /// `save_stream`, so the encoded state is never held in memory as a whole.
+    #[cfg(feature = "storage")]
+    pub fn save(
+        &self,
+        storage: &mut (impl crate::storage::StorageBackend + ?Sized),
+        key: &str,
+    ) -> crate::error::Result<()>
+    where
+        H: serde::Serialize + Send + Sync,
+    {
+        let (writer, mut reader) = crate::pipe::pipe();
+        std::thread::scope(|scope| {
+            let producer = scope.spawn(move || {
+                let mut writer = writer;
+                match serde_json::to_writer(&mut writer, self) {
+                    Ok(()) => writer.finish().map_err(|e| e.to_string()),
+                    Err(e) => {
+                        let message = e.to_string();
+                        writer.fail(std::io::Error::other(message.clone()));
+                        Err(message)
+                    }
+                }
+            });
+            let saved = storage.save_stream(key, &mut reader);
+            drop(reader);
+            let serialized = producer.join().unwrap_or_else(|_| Err("serializer panicked".to_string()));
+            // A serialization failure also fails the stream; report its root cause
+            serialized.map_err(crate::error::ChronoMerkleError::SerializationError)?;
+            saved.map(|_| ())
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<H: AsRef<[u8]> + Clone + serde::Serialize> serde::Serialize for TreeSnapshot<H> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        // Field layout mirrors TreeState so snapshots load through load_state
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("TreeState", 9)?;
+        state.serialize_field("nodes", &FlatNodes(self))?;
+        state.serialize_field("leaf_count", &self.leaf_count)?;
+        state.serialize_field("sparse_index", &self.sparse_index)?;
+        state.serialize_field("config", &self.config)?;
+        state.serialize_field("incremental_updates", &self.incremental_updates)?;
+        let (stored_deltas, delta_chains) = self.persisted_deltas();
+        state.serialize_field("stored_deltas", &stored_deltas)?;
+        state.serialize_field("delta_chains", &delta_chains)?;
+        state.serialize_field("redactions", &self.redactions.values().collect::<Vec<_>>())?;
+        match self.persisted_root_history() {
+            records if records.is_empty() => state.skip_field("root_history")?,
+            records => state.serialize_field("root_history", &records)?,
+        }
+        state.end()
+    }
+}
+
+/// Nodes of a snapshot serialized in flat level-by-level order, with shared payloads back in the leaves
+#[cfg(feature = "serde")]
+struct FlatNodes<'a, H>(&'a TreeSnapshot<H>);
+
+#[cfg(feature = "serde")]
+impl<H: AsRef<[u8]> + Clone + serde::Serialize> serde::Serialize for FlatNodes<'_, H> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.0.leaves_with_data().chain(self.0.nodes.upper_levels().map(Cow::Borrowed)))
+    }
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: crate::security::SecurityLogger,
+{
+    /// Take a copy-on-write snapshot of the current state
+    ///
+    /// This does not copy nodes, deltas or indexes; while the snapshot is
+    /// alive, the tree copies only the chunks its later mutations write to.
+    pub fn snapshot(&self) -> TreeSnapshot<H> {
+        TreeSnapshot {
+            nodes: self.nodes.clone(),
+            leaf_count: self.leaf_count,
+            sparse_index: self.sparse_index.clone(),
+            config: self.config.clone(),
+            incremental_updates: self.incremental_updates,
+            stored_deltas: self.stored_deltas.clone(),
+            delta_chains: self.delta_chains.clone(),
+            root_history: self.root_history.clone(),
+            redactions: self.redactions.clone(),
+            leaf_store: self.leaf_store.clone(),
+        }
+    }
+
+    /// Return the tree to the state captured by `snapshot`
+    #[cfg_attr(feature = "minimal", allow(dead_code))]
+    pub(crate) fn restore_snapshot(&mut self, snapshot: TreeSnapshot<H>) {
+        self.nodes = snapshot.nodes;
+        self.leaf_count = snapshot.leaf_count;
+        self.sparse_index = snapshot.sparse_index;
+        self.stored_deltas = snapshot.stored_deltas;
+        self.delta_chains = snapshot.delta_chains;
+        self.root_history = snapshot.root_history;
+        self.redactions = snapshot.redactions;
+        self.leaf_store = snapshot.leaf_store;
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_is_isolated_from_later_inserts() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert(b"data1", 1000).unwrap();
+        tree.insert(b"data2", 1001).unwrap();
+
+        let snapshot = tree.snapshot();
+        let root = tree.root();
+        tree.insert(b"data3", 1002).unwrap();
+
+        assert_eq!(snapshot.root(), root);
+        assert_eq!(snapshot.leaf_count(), 2);
+        assert_ne!(tree.root(), root);
+    }
+
+    #[cfg(feature = "storage")]
+    #[test]
+    fn test_snapshot_saved_concurrently_with_inserts() {
+        use crate::storage::MemoryStorage;
+
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..50u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        let snapshot = tree.snapshot();
+        let expected_root = snapshot.root();
+
+        let saver = std::thread::spawn(move || {
+            let mut storage = MemoryStorage::new();
+            snapshot.save(&mut storage, "snap").unwrap();
+            storage
+        });
+        for i in 50..100u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        let storage = saver.join().unwrap();
+
+        let restored: ChronoMerkleTree = ChronoMerkleTree::load_state(
+            &storage,
+            "snap",
+            crate::hash::Blake3Hasher,
+            crate::security::NoOpLogger,
+        )
+        .unwrap();
+        assert_eq!(restored.root(), expected_root);
+        assert_eq!(restored.leaf_count(), 50);
+        assert_eq!(tree.leaf_count(), 100);
+    }
+}
@@ -0,0 +1,446 @@
+//! Self-contained exports of a range of leaves for regulators and auditors
+//!
+//! [`ChronoMerkleTree::export_snapshot`] packages a contiguous run of leaves
+//! with their data, a [`RangeMultiproof`] tying all of them to the root, the
+//! root itself and the tree's [`spec_digest`](ChronoMerkleTree::spec_digest).
+//! [`verify_snapshot`] checks such a package offline with nothing but the
+//! hash function. With the `snapshot-archive` feature a package is written to
+//! and read from a tar archive holding `snapshot.json` and one `data/<index>`
+//! file per leaf.
+
+use crate::config::LeafHashMode;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+use core::ops::Range;
+
+#[cfg(feature = "no-std")]
+use alloc::{format, string::{String, ToString}, vec::Vec};
+
+/// Domain separator of the signed snapshot
+const SNAPSHOT_DOMAIN: &[u8] = b"chrono-merkle-snapshot-v1";
+
+/// Proof that a contiguous run of leaves belongs to a tree
+///
+/// Holds only the siblings outside the run, bottom-up, so a run of `n` leaves
+/// needs at most two siblings per level instead of `n` separate proofs.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeMultiproof<H> {
+    /// Number of leaves in the tree
+    pub leaf_count: usize,
+    /// Index of the first leaf of the run
+    pub start: usize,
+    /// Siblings of the run's edges, bottom-up, left before right on each level
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash_seq"))]
+    pub siblings: Vec<H>,
+}
+
+impl<H: AsRef<[u8]> + Clone + Eq> RangeMultiproof<H> {
+    /// Compute the root from the hashes of the run's leaves
+    ///
+    /// Fails if the run does not fit the tree or the sibling count is wrong.
+    pub fn root<Hasher: HashFunction<Output = H>>(&self, leaves: &[H], hasher: &Hasher) -> Result<H> {
+        let invalid = |message: &str| ChronoMerkleError::InvalidProof { message: message.to_string() };
+        if leaves.is_empty() || self.start + leaves.len() > self.leaf_count {
+            return Err(invalid("Leaf run does not fit the tree"));
+        }
+
+        let mut siblings = self.siblings.iter();
+        let mut next_sibling = || siblings.next().cloned().ok_or_else(|| invalid("Multiproof has too few siblings"));
+        let (mut level, mut start, mut width) = (leaves.to_vec(), self.start, self.leaf_count);
+        while width > 1 {
+            let end = start + level.len() - 1;
+            let mut row = Vec::with_capacity(level.len() + 2);
+            if start % 2 == 1 {
+                row.push(next_sibling()?);
+            }
+            row.extend(level);
+            if end % 2 == 0 {
+                // The last node of an odd level is paired with itself
+                row.push(if end + 1 < width { next_sibling()? } else { row[row.len() - 1].clone() });
+            }
+            level = row.chunks(2).map(|pair| hasher.hash_pair(&pair[0], &pair[1])).collect();
+            start /= 2;
+            width = width.div_ceil(2);
+        }
+        if siblings.next().is_some() {
+            return Err(invalid("Multiproof has unused siblings"));
+        }
+        Ok(level.remove(0))
+    }
+}
+
+/// One exported leaf
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotLeaf<H> {
+    /// Leaf index in the tree
+    pub index: usize,
+    /// Leaf hash
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub hash: H,
+    /// Leaf timestamp
+    pub timestamp: u64,
+    /// Leaf data, if the tree retained it
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub data: Option<Vec<u8>>,
+}
+
+/// Leaves, multiproof and root of an exported range, returned by
+/// [`ChronoMerkleTree::export_snapshot`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "H: AsRef<[u8]>", deserialize = "H: serde::Deserialize<'de>")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotPackage<H> {
+    /// Name of the tree's hash algorithm
+    pub hasher: String,
+    /// How the tree derives leaf hashes from data
+    pub leaf_hash_mode: LeafHashMode,
+    /// [`ChronoMerkleTree::spec_digest`] of the exporting tree
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub spec_digest: H,
+    /// Root of the tree at export
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_impl::hash"))]
+    pub root: H,
+    /// Exported leaves in index order
+    pub leaves: Vec<SnapshotLeaf<H>>,
+    /// Proof tying the leaves to the root
+    pub multiproof: RangeMultiproof<H>,
+    /// Signature over [`signing_bytes`](Self::signing_bytes), if the package was signed
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub signature: Option<Vec<u8>>,
+}
+
+impl<H: AsRef<[u8]>> SnapshotPackage<H> {
+    /// Bytes covered by the signature
+    ///
+    /// Leaf data is covered through the leaf hashes.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = SNAPSHOT_DOMAIN.to_vec();
+        bytes.extend_from_slice(&crate::encoding::encode_u32(self.hasher.len() as u32));
+        bytes.extend_from_slice(self.hasher.as_bytes());
+        bytes.extend_from_slice(self.spec_digest.as_ref());
+        bytes.extend_from_slice(self.root.as_ref());
+        bytes.extend_from_slice(&crate::encoding::encode_index(self.multiproof.leaf_count));
+        bytes.extend_from_slice(&crate::encoding::encode_index(self.multiproof.start));
+        for leaf in &self.leaves {
+            bytes.extend_from_slice(leaf.hash.as_ref());
+            bytes.extend_from_slice(&crate::encoding::encode_u64(leaf.timestamp));
+        }
+        bytes
+    }
+
+    /// Sign the package with `signer`, replacing any previous signature
+    pub fn sign(&mut self, signer: &dyn SnapshotSigner) -> Result<()> {
+        self.signature = Some(signer.sign(&self.signing_bytes())?);
+        Ok(())
+    }
+}
+
+/// Signs snapshot packages
+pub trait SnapshotSigner {
+    /// Sign `message`
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Check a snapshot package offline
+///
+/// Returns `Ok(false)` if the package was not produced by a tree using
+/// `hasher`, if any leaf data does not hash to its leaf, or if the leaves do
+/// not lead to the package's root. Malformed packages are errors. The
+/// signature, if any, is left to the caller, who checks it against
+/// [`SnapshotPackage::signing_bytes`] with the signer's public key.
+pub fn verify_snapshot<Hasher: HashFunction>(package: &SnapshotPackage<Hasher::Output>, hasher: &Hasher) -> Result<bool>
+where
+    Hasher::Output: AsRef<[u8]> + Clone + Eq,
+{
+    if package.hasher != hasher.name()
+        || package.spec_digest != crate::operations::spec_digest(hasher, package.leaf_hash_mode)
+    {
+        return Ok(false);
+    }
+    for (offset, leaf) in package.leaves.iter().enumerate() {
+        if leaf.index != package.multiproof.start + offset {
+            return Err(ChronoMerkleError::InvalidProof {
+                message: format!("Leaf {} is out of order in the snapshot", leaf.index),
+            });
+        }
+        if let (Some(data), LeafHashMode::HashData) = (&leaf.data, package.leaf_hash_mode) {
+            if hasher.hash(data) != leaf.hash {
+                return Ok(false);
+            }
+        }
+    }
+    let hashes: Vec<_> = package.leaves.iter().map(|leaf| leaf.hash.clone()).collect();
+    Ok(package.multiproof.root(&hashes, hasher)? == package.root)
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Export the leaves in `range` with a multiproof against the current root
+    ///
+    /// `range` holds leaf indices; [`find_range`](Self::find_range) gives the
+    /// indices of a time period.
+    pub fn export_snapshot(&self, range: Range<usize>) -> Result<SnapshotPackage<H>> {
+        if range.start >= range.end {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "range".to_string(),
+                reason: "Snapshot range is empty".to_string(),
+            });
+        }
+        if range.end > self.leaf_count {
+            return Err(ChronoMerkleError::IndexOutOfBounds {
+                index: range.end - 1,
+                leaf_count: self.leaf_count,
+            });
+        }
+        let root = self.root().ok_or(ChronoMerkleError::EmptyTree)?;
+
+        let leaves = range
+            .clone()
+            .map(|index| {
+                let (timestamp, _) = self.nodes[index].timestamp_info();
+                Ok(SnapshotLeaf {
+                    index,
+                    hash: self.nodes[index].hash(),
+                    timestamp,
+                    data: self.get_leaf_data(index)?.data().map(<[u8]>::to_vec),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Walk the stored levels, collecting the nodes just outside the run
+        let mut siblings = Vec::new();
+        let (mut offset, mut width) = (0, self.leaf_count);
+        let (mut start, mut end) = (range.start, range.end - 1);
+        while width > 1 {
+            if start % 2 == 1 {
+                siblings.push(self.nodes[offset + start - 1].hash());
+            }
+            if end % 2 == 0 && end + 1 < width {
+                siblings.push(self.nodes[offset + end + 1].hash());
+            }
+            offset += width;
+            width = width.div_ceil(2);
+            start /= 2;
+            end /= 2;
+        }
+
+        Ok(SnapshotPackage {
+            hasher: self.hasher_name().to_string(),
+            leaf_hash_mode: self.config.leaf_hash_mode,
+            spec_digest: self.spec_digest(),
+            root,
+            leaves,
+            multiproof: RangeMultiproof {
+                leaf_count: self.leaf_count,
+                start: range.start,
+                siblings,
+            },
+            signature: None,
+        })
+    }
+}
+
+#[cfg(feature = "snapshot-archive")]
+mod archive {
+    //! Minimal ustar writer and reader for snapshot archives
+
+    use super::SnapshotPackage;
+    use crate::error::{ChronoMerkleError, Result};
+
+    const BLOCK: usize = 512;
+    const MANIFEST: &str = "snapshot.json";
+
+    fn octal(field: &mut [u8], value: u64) {
+        let digits = format!("{:0width$o}", value, width = field.len() - 1);
+        field[..digits.len()].copy_from_slice(digits.as_bytes());
+    }
+
+    fn checksum(header: &[u8]) -> u64 {
+        header.iter().enumerate().map(|(i, &byte)| if (148..156).contains(&i) { b' ' as u64 } else { byte as u64 }).sum()
+    }
+
+    fn append(archive: &mut Vec<u8>, name: &str, contents: &[u8]) {
+        let mut header = [0u8; BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        octal(&mut header[100..108], 0o644);
+        octal(&mut header[108..116], 0);
+        octal(&mut header[116..124], 0);
+        octal(&mut header[124..136], contents.len() as u64);
+        // A fixed mtime keeps archives of the same package byte-identical
+        octal(&mut header[136..148], 0);
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        let sum = checksum(&header);
+        octal(&mut header[148..155], sum);
+        header[155] = b' ';
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(contents);
+        archive.resize(archive.len().next_multiple_of(BLOCK), 0);
+    }
+
+    fn entries(archive: &[u8]) -> Result<Vec<(String, &[u8])>> {
+        let malformed = |reason: &str| ChronoMerkleError::DeserializationError(format!("Malformed snapshot archive: {}", reason));
+        let mut entries = Vec::new();
+        let mut position = 0;
+        while position + BLOCK <= archive.len() {
+            let header = &archive[position..position + BLOCK];
+            if header.iter().all(|&byte| byte == 0) {
+                return Ok(entries);
+            }
+            let field = |range: core::ops::Range<usize>| {
+                let raw = &header[range];
+                let text = core::str::from_utf8(&raw[..raw.iter().position(|&b| b == 0).unwrap_or(raw.len())]).map_err(|_| malformed("invalid header"))?;
+                Ok::<_, ChronoMerkleError>(text.trim())
+            };
+            let number = |range| u64::from_str_radix(field(range)?, 8).map_err(|_| malformed("invalid number in header"));
+            if number(148..156)? != checksum(header) {
+                return Err(malformed("header checksum mismatch"));
+            }
+            let name = field(0..100)?.to_string();
+            let size = number(124..136)? as usize;
+            let start = position + BLOCK;
+            let contents = archive.get(start..start + size).ok_or_else(|| malformed("truncated entry"))?;
+            entries.push((name, contents));
+            position = (start + size).next_multiple_of(BLOCK);
+        }
+        Err(malformed("missing end of archive"))
+    }
+
+    impl<H> SnapshotPackage<H>
+    where
+        H: AsRef<[u8]> + serde::de::DeserializeOwned + Clone,
+    {
+        /// Write the package as a tar archive
+        ///
+        /// `snapshot.json` holds everything but the leaf data, which is
+        /// stored raw in `data/<index>`.
+        pub fn to_tar(&self) -> Result<Vec<u8>> {
+            let mut manifest = self.clone();
+            manifest.leaves.iter_mut().for_each(|leaf| leaf.data = None);
+            let json = serde_json::to_vec_pretty(&manifest).map_err(|e| ChronoMerkleError::SerializationError(e.to_string()))?;
+
+            let mut archive = Vec::new();
+            append(&mut archive, MANIFEST, &json);
+            for leaf in &self.leaves {
+                if let Some(data) = &leaf.data {
+                    append(&mut archive, &format!("data/{}", leaf.index), data);
+                }
+            }
+            archive.resize(archive.len() + 2 * BLOCK, 0);
+            Ok(archive)
+        }
+
+        /// Read a package written by [`to_tar`](Self::to_tar)
+        pub fn from_tar(archive: &[u8]) -> Result<Self> {
+            let entries = entries(archive)?;
+            let (_, json) = entries.iter().find(|(name, _)| name == MANIFEST).ok_or_else(|| {
+                ChronoMerkleError::DeserializationError(format!("Snapshot archive has no {}", MANIFEST))
+            })?;
+            let mut package: Self = serde_json::from_slice(json).map_err(|e| ChronoMerkleError::DeserializationError(e.to_string()))?;
+            for (name, contents) in &entries {
+                let Some(index) = name.strip_prefix("data/") else {
+                    continue;
+                };
+                let leaf = package.leaves.iter_mut().find(|leaf| leaf.index.to_string() == index).ok_or_else(|| {
+                    ChronoMerkleError::DeserializationError(format!("Snapshot archive has data for unknown leaf {}", index))
+                })?;
+                leaf.data = Some(contents.to_vec());
+            }
+            Ok(package)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    fn tree(count: u64) -> ChronoMerkleTree {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..count {
+            tree.insert(format!("record{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        tree
+    }
+
+    struct TestSigner;
+
+    impl SnapshotSigner for TestSigner {
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+            Ok(Blake3Hasher.hash(message).to_vec())
+        }
+    }
+
+    #[test]
+    fn test_every_range_verifies() {
+        for count in 1..12u64 {
+            let tree = tree(count);
+            for start in 0..count as usize {
+                for end in start + 1..=count as usize {
+                    let package = tree.export_snapshot(start..end).unwrap();
+                    assert_eq!(package.leaves.len(), end - start);
+                    assert!(verify_snapshot(&package, &Blake3Hasher).unwrap(), "{} leaves, {}..{}", count, start, end);
+                }
+            }
+        }
+        assert!(tree(5).export_snapshot(3..3).is_err());
+        assert!(tree(5).export_snapshot(3..6).is_err());
+    }
+
+    #[test]
+    fn test_tampered_snapshot_fails() {
+        let tree = tree(9);
+        let mut package = tree.export_snapshot(2..7).unwrap();
+        package.sign(&TestSigner).unwrap();
+        assert_eq!(package.signature.as_deref(), Some(&Blake3Hasher.hash(&package.signing_bytes())[..]));
+
+        let mut data = package.clone();
+        data.leaves[1].data = Some(b"forged".to_vec());
+        assert!(!verify_snapshot(&data, &Blake3Hasher).unwrap());
+
+        let mut hash = package.clone();
+        hash.leaves[1].hash = [0; 32];
+        hash.leaves[1].data = None;
+        assert!(!verify_snapshot(&hash, &Blake3Hasher).unwrap());
+
+        let mut siblings = package.clone();
+        siblings.multiproof.siblings.pop();
+        assert!(verify_snapshot(&siblings, &Blake3Hasher).is_err());
+
+        let mut hasher = package;
+        hasher.hasher = "sha256".to_string();
+        assert!(!verify_snapshot(&hasher, &Blake3Hasher).unwrap());
+    }
+
+    #[cfg(feature = "snapshot-archive")]
+    #[test]
+    fn test_tar_roundtrip() {
+        let package = tree(6).export_snapshot(1..5).unwrap();
+        let archive = package.to_tar().unwrap();
+        assert_eq!(archive.len() % 512, 0);
+        assert_eq!(&archive[257..262], b"ustar");
+        assert_eq!(archive, package.to_tar().unwrap());
+
+        let restored = SnapshotPackage::<[u8; 32]>::from_tar(&archive).unwrap();
+        assert_eq!(restored, package);
+        assert!(verify_snapshot(&restored, &Blake3Hasher).unwrap());
+
+        let mut corrupted = archive.clone();
+        corrupted[10] ^= 1;
+        assert!(SnapshotPackage::<[u8; 32]>::from_tar(&corrupted).is_err());
+        assert!(SnapshotPackage::<[u8; 32]>::from_tar(&archive[..1024]).is_err());
+    }
+}
@@ -1,19 +1,175 @@
 //! Sparse timestamp-based indexing for ChronoMerkle Tree
+//!
+//! With sparsity N every Nth inserted entry is indexed, whatever its
+//! timestamp, so irregular timestamps are indexed as evenly as regular ones.
+//! An optional entry budget doubles the sparsity and drops every other entry
+//! whenever the index grows past it.
+//!
+//! While timestamps are inserted in non-decreasing order, the indexed entries
+//! around a time range bound the leaves that can fall inside it (see
+//! [`SparseIndex::bounding_indices`]), so range scans only visit the leaves
+//! between two indexed positions.
+//!
+//! Entries are kept in chunks behind their own `Arc`, so cloning an index for
+//! a snapshot only bumps reference counts and a later insert copies just the
+//! chunk it lands in.
 
 
+#[cfg(feature = "storage")]
+use crate::codec::{self, Reader};
+#[cfg(feature = "storage")]
+use crate::error::{ChronoMerkleError, Result};
+
+use core::ops::Bound;
+
 #[cfg(feature = "no-std")]
-use alloc::{collections::BTreeMap, vec::Vec};
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+#[cfg(all(feature = "no-std", feature = "storage"))]
+use alloc::string::ToString;
 #[cfg(not(feature = "no-std"))]
-use std::{collections::BTreeMap, vec::Vec};
+use std::{collections::BTreeMap, sync::Arc, vec::Vec};
+
+/// Entries per chunk of a split chunk; chunks split once they hold twice as many
+const CHUNK_LEN: usize = 256;
+
+/// Indexed entries ordered by timestamp, in chunks shared between clones
+///
+/// Each chunk is keyed by a timestamp no greater than its own and greater
+/// than every timestamp of the chunks before it.
+#[derive(Clone, Default)]
+struct Entries {
+    chunks: BTreeMap<u64, Arc<BTreeMap<u64, usize>>>,
+    len: usize,
+}
+
+impl Entries {
+    fn insert(&mut self, timestamp: u64, leaf_index: usize) {
+        let key = match self.chunks.range(..=timestamp).next_back() {
+            Some((&key, _)) => key,
+            None => {
+                // Below every chunk: the first chunk now starts at `timestamp`
+                let first = self.chunks.pop_first().map(|(_, chunk)| chunk).unwrap_or_default();
+                self.chunks.insert(timestamp, first);
+                timestamp
+            }
+        };
+        let chunk = Arc::make_mut(self.chunks.entry(key).or_default());
+        if chunk.insert(timestamp, leaf_index).is_none() {
+            self.len += 1;
+        }
+        if chunk.len() > 2 * CHUNK_LEN {
+            if let Some(&split) = chunk.keys().nth(CHUNK_LEN) {
+                let upper = chunk.split_off(&split);
+                self.chunks.insert(split, Arc::new(upper));
+            }
+        }
+    }
+
+    fn get(&self, timestamp: u64) -> Option<usize> {
+        let (_, chunk) = self.chunks.range(..=timestamp).next_back()?;
+        chunk.get(&timestamp).copied()
+    }
+
+    /// Entries with timestamps in `start..=end`
+    fn range(&self, start: u64, end: u64) -> impl Iterator<Item = (&u64, &usize)> + '_ {
+        let first = self.chunks.range(..=start).next_back();
+        let rest = self.chunks.range((Bound::Excluded(start), Bound::Unbounded));
+        first
+            .into_iter()
+            .chain(rest)
+            .take_while(move |&(&key, _)| start <= end && key <= end)
+            .flat_map(move |(_, chunk)| chunk.range(start..=end))
+    }
+
+    /// Entry with the largest timestamp below `bound`
+    fn last_before(&self, bound: Bound<u64>) -> Option<(&u64, &usize)> {
+        let search = match bound {
+            Bound::Included(timestamp) | Bound::Excluded(timestamp) => (Bound::Unbounded, Bound::Included(timestamp)),
+            Bound::Unbounded => (Bound::Unbounded, Bound::Unbounded),
+        };
+        self.chunks
+            .range(search)
+            .rev()
+            .find_map(|(_, chunk)| chunk.range((Bound::Unbounded, bound)).next_back())
+    }
+
+    /// Entry with the smallest timestamp above `bound`
+    fn first_after(&self, bound: Bound<u64>) -> Option<(&u64, &usize)> {
+        let first = match bound {
+            Bound::Included(timestamp) | Bound::Excluded(timestamp) => self.chunks.range(..=timestamp).next_back(),
+            Bound::Unbounded => None,
+        };
+        first
+            .into_iter()
+            .chain(self.chunks.range((bound, Bound::Unbounded)))
+            .find_map(|(_, chunk)| chunk.range((bound, Bound::Unbounded)).next())
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&u64, &usize)> + '_ {
+        self.chunks.values().flat_map(|chunk| chunk.iter())
+    }
+
+    /// Keep the entries for which `keep` holds, visiting them in timestamp order
+    fn retain(&mut self, mut keep: impl FnMut(&u64, &usize) -> bool) {
+        for chunk in self.chunks.values_mut() {
+            Arc::make_mut(chunk).retain(|timestamp, leaf_index| keep(timestamp, leaf_index));
+        }
+        self.chunks.retain(|_, chunk| !chunk.is_empty());
+        self.len = self.chunks.values().map(|chunk| chunk.len()).sum();
+    }
+}
+
+impl core::fmt::Debug for Entries {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Entries {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Entries {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let map = BTreeMap::<u64, usize>::deserialize(deserializer)?;
+        let mut entries = Self::default();
+        for (timestamp, leaf_index) in map {
+            entries.insert(timestamp, leaf_index);
+        }
+        Ok(entries)
+    }
+}
 
 /// Sparse index for efficient timestamp-based lookups
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone)]
 pub struct SparseIndex {
     /// Map from timestamp to leaf index
-    index: BTreeMap<u64, usize>,
-    /// Store every Nth timestamp (sparsity factor)
+    index: Entries,
+    /// Index every Nth inserted entry (sparsity factor)
     sparsity: u64,
+    /// Entries inserted so far, indexed or not
+    #[cfg_attr(feature = "serde", serde(default))]
+    inserted: u64,
+    /// Maximum number of indexed entries (`None` = unbounded)
+    #[cfg_attr(feature = "serde", serde(default))]
+    max_entries: Option<usize>,
+    /// Timestamp of the last inserted entry
+    #[cfg_attr(feature = "serde", serde(default))]
+    last_timestamp: Option<u64>,
+    /// Whether all entries were inserted in non-decreasing timestamp order
+    #[cfg_attr(feature = "serde", serde(default))]
+    ordered: bool,
 }
 
 impl SparseIndex {
@@ -21,20 +177,48 @@ impl SparseIndex {
     ///
     /// # Arguments
     ///
-    /// * `sparsity` - Store every Nth timestamp (1 = store all, 10 = store every 10th)
+    /// * `sparsity` - Index every Nth inserted entry (1 = store all, 10 = store every 10th)
     pub fn new(sparsity: u64) -> Self {
+        Self::with_budget(sparsity, None)
+    }
+
+    /// Create a sparse index that keeps at most `max_entries` entries
+    ///
+    /// The sparsity starts at `sparsity` and doubles each time the budget is
+    /// exceeded.
+    pub fn with_budget(sparsity: u64, max_entries: Option<usize>) -> Self {
         Self {
-            index: BTreeMap::new(),
+            index: Entries::default(),
             sparsity: if sparsity == 0 { 1 } else { sparsity },
+            inserted: 0,
+            max_entries: max_entries.map(|max| max.max(1)),
+            last_timestamp: None,
+            ordered: true,
         }
     }
 
     /// Insert a timestamp and leaf index into the index
     pub fn insert(&mut self, timestamp: u64, leaf_index: usize) {
-        // Only index if it matches the sparsity pattern
-        if timestamp % self.sparsity == 0 {
+        // Only index every Nth inserted entry
+        if self.inserted % self.sparsity == 0 {
             self.index.insert(timestamp, leaf_index);
+            if self.max_entries.is_some_and(|max| self.index.len > max) {
+                self.thin();
+            }
         }
+        self.inserted += 1;
+        self.ordered &= self.last_timestamp.is_none_or(|last| last <= timestamp);
+        self.last_timestamp = Some(timestamp);
+    }
+
+    /// Double the sparsity and keep every other indexed entry
+    fn thin(&mut self) {
+        self.sparsity = self.sparsity.saturating_mul(2);
+        let mut keep = false;
+        self.index.retain(|_, _| {
+            keep = !keep;
+            keep
+        });
     }
 
     /// Find all leaf indices within a time range
@@ -48,10 +232,12 @@ impl SparseIndex {
     ///
     /// Vector of leaf indices that fall within the range
     pub fn find_range(&self, start: u64, end: u64) -> Vec<usize> {
-        self.index
-            .range(start..=end)
-            .map(|(_, &idx)| idx)
-            .collect()
+        self.index.range(start, end).map(|(_, &idx)| idx).collect()
+    }
+
+    /// Count the indexed entries with timestamps in `start..=end`
+    pub fn count_range(&self, start: u64, end: u64) -> usize {
+        self.index.range(start, end).count()
     }
 
     /// Find the nearest indexed timestamp to the given timestamp
@@ -65,10 +251,10 @@ impl SparseIndex {
     /// Option containing the leaf index if found
     pub fn find_nearest(&self, timestamp: u64) -> Option<usize> {
         // Find the largest timestamp <= target
-        let before = self.index.range(..=timestamp).next_back();
-        
+        let before = self.index.last_before(Bound::Included(timestamp));
+
         // Find the smallest timestamp >= target
-        let after = self.index.range(timestamp..).next();
+        let after = self.index.first_after(Bound::Included(timestamp));
 
         match (before, after) {
             (Some((t1, &idx1)), Some((t2, &idx2))) => {
@@ -85,19 +271,47 @@ impl SparseIndex {
         }
     }
 
+    /// Find the indexed entry with the largest timestamp before `timestamp`
+    pub fn nearest_before(&self, timestamp: u64) -> Option<usize> {
+        self.index.last_before(Bound::Excluded(timestamp)).map(|(_, &idx)| idx)
+    }
+
+    /// Find the indexed entry with the smallest timestamp after `timestamp`
+    pub fn nearest_after(&self, timestamp: u64) -> Option<usize> {
+        self.index.first_after(Bound::Excluded(timestamp)).map(|(_, &idx)| idx)
+    }
+
+    /// Find the indexed entries enclosing the time range `start..=end`
+    ///
+    /// Returns the entries of [`nearest_before`](Self::nearest_before) `start`
+    /// and [`nearest_after`](Self::nearest_after) `end`; `None` means the range
+    /// is open on that side. If the index [`is_ordered`](Self::is_ordered),
+    /// every entry with a timestamp in the range lies strictly between the two.
+    pub fn bounding_indices(&self, start: u64, end: u64) -> (Option<usize>, Option<usize>) {
+        (self.nearest_before(start), self.nearest_after(end))
+    }
+
+    /// Whether timestamps were inserted in non-decreasing order
+    ///
+    /// Indexes deserialized from states saved before this was tracked report
+    /// `false` until rebuilt.
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
     /// Find all timestamps that match a specific timestamp (exact match)
     pub fn find_exact(&self, timestamp: u64) -> Option<usize> {
-        self.index.get(&timestamp).copied()
+        self.index.get(timestamp)
     }
 
     /// Get the number of indexed entries
     pub fn len(&self) -> usize {
-        self.index.len()
+        self.index.len
     }
 
     /// Check if the index is empty
     pub fn is_empty(&self) -> bool {
-        self.index.is_empty()
+        self.index.len == 0
     }
 
     /// Get the sparsity factor
@@ -105,20 +319,77 @@ impl SparseIndex {
         self.sparsity
     }
 
+    /// Get the entry budget, if any
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
     /// Clear all entries from the index
     pub fn clear(&mut self) {
-        self.index.clear();
+        self.index = Entries::default();
+        self.inserted = 0;
+        self.last_timestamp = None;
+        self.ordered = true;
     }
 
     /// Get all indexed timestamps
     pub fn timestamps(&self) -> Vec<u64> {
-        self.index.keys().copied().collect()
+        self.index.iter().map(|(&timestamp, _)| timestamp).collect()
     }
 
     /// Iterate over all (timestamp, leaf_index) pairs
     pub fn iter(&self) -> impl Iterator<Item = (&u64, &usize)> {
         self.index.iter()
     }
+
+    /// Encode the index in a compact binary form
+    ///
+    /// Timestamps are stored as varint differences from the previous entry.
+    #[cfg(feature = "storage")]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        codec::write_varint(&mut out, self.sparsity);
+        codec::write_varint(&mut out, self.inserted);
+        codec::write_varint(&mut out, self.max_entries.map_or(0, |max| max as u64));
+        out.extend_from_slice(&codec::pack_bits(&[self.ordered, self.last_timestamp.is_some()]));
+        codec::write_u64(&mut out, self.last_timestamp.unwrap_or(0));
+        codec::write_varint(&mut out, self.index.len as u64);
+        let mut previous = 0;
+        for (&timestamp, &leaf_index) in self.index.iter() {
+            codec::write_varint(&mut out, timestamp - previous);
+            codec::write_varint(&mut out, leaf_index as u64);
+            previous = timestamp;
+        }
+        out
+    }
+
+    /// Decode an index encoded with [`to_bytes`](Self::to_bytes)
+    #[cfg(feature = "storage")]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let sparsity = reader.read_varint()?;
+        let inserted = reader.read_varint()?;
+        let max_entries = reader.read_len()?;
+        let flags = codec::unpack_bits(reader.take(1)?, 2);
+        let last_timestamp = reader.read_u64()?;
+        let mut index = Self::with_budget(sparsity, (max_entries > 0).then_some(max_entries));
+        index.inserted = inserted;
+        index.ordered = flags[0];
+        index.last_timestamp = flags[1].then_some(last_timestamp);
+
+        let count = reader.read_len()?;
+        let mut timestamp = 0u64;
+        for _ in 0..count {
+            timestamp = timestamp.checked_add(reader.read_varint()?).ok_or_else(|| {
+                ChronoMerkleError::DeserializationError("Timestamp overflows u64".to_string())
+            })?;
+            index.index.insert(timestamp, reader.read_len()?);
+        }
+        if !reader.is_empty() {
+            return Err(ChronoMerkleError::DeserializationError("Trailing bytes after sparse index".to_string()));
+        }
+        Ok(index)
+    }
 }
 
 #[cfg(test)]
@@ -139,15 +410,27 @@ mod tests {
 
     #[test]
     fn test_sparse_index_sparsity() {
-        let mut index = SparseIndex::new(10); // Store every 10th
-        index.insert(1000, 0); // Will be stored (1000 % 10 == 0)
+        let mut index = SparseIndex::new(2); // Store every 2nd insert
+        index.insert(1003, 0); // Will be stored (1st insert)
         index.insert(1005, 1); // Won't be stored
-        index.insert(1010, 2); // Will be stored
+        index.insert(1017, 2); // Will be stored (3rd insert)
 
         assert_eq!(index.len(), 2);
-        assert_eq!(index.find_exact(1000), Some(0));
+        assert_eq!(index.find_exact(1003), Some(0));
         assert_eq!(index.find_exact(1005), None);
-        assert_eq!(index.find_exact(1010), Some(2));
+        assert_eq!(index.find_exact(1017), Some(2));
+    }
+
+    #[test]
+    fn test_sparse_index_budget() {
+        let mut index = SparseIndex::with_budget(1, Some(4));
+        for i in 0..16 {
+            index.insert(1000 + 3 * i as u64, i);
+        }
+
+        assert!(index.len() <= 4);
+        assert_eq!(index.sparsity(), 4);
+        assert_eq!(index.find_range(0, u64::MAX), Vec::from([0, 4, 8, 12]));
     }
 
     #[test]
@@ -174,6 +457,77 @@ mod tests {
         assert_eq!(index.find_nearest(1000), Some(0)); // Exact match
     }
 
+    #[test]
+    fn test_bounding_indices() {
+        let mut index = SparseIndex::new(2);
+        for i in 0..10 {
+            index.insert(1000 + 10 * i as u64, i);
+        }
+
+        assert_eq!(index.nearest_before(1040), Some(2));
+        assert_eq!(index.nearest_after(1040), Some(6));
+        assert_eq!(index.bounding_indices(1030, 1050), (Some(2), Some(6)));
+        assert_eq!(index.bounding_indices(900, 2000), (None, None));
+        assert!(index.is_ordered());
+
+        index.insert(1005, 10);
+        assert!(!index.is_ordered());
+    }
+
+    #[cfg(feature = "storage")]
+    #[test]
+    fn test_binary_roundtrip() {
+        let mut index = SparseIndex::with_budget(2, Some(8));
+        for i in 0..10 {
+            index.insert(1_700_000_000 + 3 * i as u64, i);
+        }
+
+        let decoded = SparseIndex::from_bytes(&index.to_bytes()).unwrap();
+        assert_eq!(decoded.iter().collect::<Vec<_>>(), index.iter().collect::<Vec<_>>());
+        assert_eq!(decoded.sparsity(), 2);
+        assert_eq!(decoded.max_entries(), Some(8));
+        assert!(decoded.is_ordered());
+
+        let mut resumed = decoded.clone();
+        index.insert(1_700_000_030, 10);
+        resumed.insert(1_700_000_030, 10);
+        assert_eq!(resumed.iter().collect::<Vec<_>>(), index.iter().collect::<Vec<_>>());
+
+        let bytes = index.to_bytes();
+        assert!(SparseIndex::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_chunked_entries_match_a_map() {
+        let mut index = SparseIndex::new(1);
+        let mut expected = BTreeMap::new();
+        for i in 0..2000usize {
+            let timestamp = 100 + 10 * ((i * 7919) % 2000) as u64;
+            index.insert(timestamp, i);
+            expected.insert(timestamp, i);
+        }
+        assert!(index.index.chunks.len() > 1);
+        assert!(index.iter().eq(expected.iter()));
+        for (start, end) in [(0, 20_090), (2655, 2665), (5205, 7785), (30_000, 40_000)] {
+            let range: Vec<usize> = expected.range(start..=end).map(|(_, &i)| i).collect();
+            assert_eq!(index.find_range(start, end), range);
+        }
+        assert!(index.find_range(200, 100).is_empty());
+        for timestamp in [0, 100, 2660, 2665, 20_090, 25_000] {
+            assert_eq!(index.nearest_before(timestamp), expected.range(..timestamp).next_back().map(|(_, &i)| i));
+            assert_eq!(index.nearest_after(timestamp), expected.range(timestamp + 1..).next().map(|(_, &i)| i));
+            assert_eq!(index.find_exact(timestamp), expected.get(&timestamp).copied());
+        }
+
+        // A clone shares every chunk but the one a later insert lands in,
+        // even when it lands below the first chunk
+        let snapshot = index.clone();
+        index.insert(5, 2000);
+        let shared = index.index.chunks.values().zip(snapshot.index.chunks.values());
+        assert_eq!(shared.filter(|(mine, theirs)| !Arc::ptr_eq(mine, theirs)).count(), 1);
+        assert_eq!((index.len(), snapshot.len()), (2001, 2000));
+    }
+
     #[test]
     fn test_empty_index() {
         let index = SparseIndex::new(1);
@@ -0,0 +1,245 @@
+//! Fixed description of how roots and proofs are computed
+//!
+//! Every rule that determines a root, a proof path or a delta hash is stated
+//! here as a constant or a small reference function written independently of
+//! the tree's node layout. The tests hold the tree to these references and
+//! to known-answer vectors, so a refactor that changes root computation
+//! fails instead of silently producing different roots. Changing any rule
+//! requires a new [`SPEC_VERSION`].
+//!
+//! Verifiers that accept only particular layouts check the version a
+//! producer advertises, e.g. through [`ChronoMerkleTree::spec_version`],
+//! with [`require_version`].
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::ProofStep;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::vec::Vec;
+
+/// Version of the rules in this module
+pub const SPEC_VERSION: u32 = 1;
+
+/// Versions whose roots and proofs this crate computes and verifies
+pub const SUPPORTED_SPEC_VERSIONS: &[u32] = &[1];
+
+/// How a level with an odd number of nodes gets its last parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OddNodeRule {
+    /// The last node is paired with itself, so its parent is `H(node || node)`
+    Duplicate,
+    /// The last node moves up a level unchanged
+    Promote,
+}
+
+/// Odd-node rule of [`SPEC_VERSION`]
+pub const ODD_NODE_RULE: OddNodeRule = OddNodeRule::Duplicate;
+
+/// How the leaf level is widened before hashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingRule {
+    /// No padding: a level of `n` nodes has `ceil(n / 2)` parents
+    None,
+    /// Padded with empty leaves up to the next power of two
+    PowerOfTwo,
+}
+
+/// Padding rule of [`SPEC_VERSION`]
+pub const PADDING_RULE: PaddingRule = PaddingRule::None;
+
+/// Bytes hashed before a record to form its leaf hash
+///
+/// Empty: the leaf hash is `H(record)`, or the record itself in
+/// [`LeafHashMode::TakeAsIs`](crate::LeafHashMode::TakeAsIs) trees.
+pub const LEAF_PREFIX: &[u8] = b"";
+
+/// Bytes hashed before the two children of an internal node
+///
+/// Empty: an internal node is `H(left || right)`.
+pub const NODE_PREFIX: &[u8] = b"";
+
+/// Whether leaf timestamps are part of any hash pre-image
+///
+/// Timestamps are carried beside hashes for queries and proofs but never
+/// hashed, so a root commits to records and their order only.
+pub const TIMESTAMPS_HASHED: bool = false;
+
+/// Root of an empty tree: none
+pub const EMPTY_TREE_HAS_ROOT: bool = false;
+
+/// Check that `version` is one of [`SUPPORTED_SPEC_VERSIONS`]
+pub fn require_version(version: u32) -> Result<()> {
+    require_version_in(version, SUPPORTED_SPEC_VERSIONS)
+}
+
+/// Check that `version` is one of the versions a verifier pinned
+pub fn require_version_in(version: u32, accepted: &[u32]) -> Result<()> {
+    if accepted.contains(&version) {
+        return Ok(());
+    }
+    Err(ChronoMerkleError::UnsupportedSpecVersion { version, current: SPEC_VERSION })
+}
+
+/// Leaf hash of `record` in a tree hashing its records
+pub fn leaf_hash<Hasher: HashFunction>(hasher: &Hasher, record: &[u8]) -> Hasher::Output {
+    hasher.hash_multiple(&[LEAF_PREFIX, record])
+}
+
+/// Hash of the internal node above `left` and `right`
+pub fn node_hash<Hasher: HashFunction>(hasher: &Hasher, left: &Hasher::Output, right: &Hasher::Output) -> Hasher::Output {
+    hasher.hash_multiple(&[NODE_PREFIX, left.as_ref(), right.as_ref()])
+}
+
+/// Hash of the delta recorded when the root changes from `old_root` to `new_root`
+pub fn delta_hash<Hasher: HashFunction>(hasher: &Hasher, old_root: &Hasher::Output, new_root: &Hasher::Output) -> Hasher::Output {
+    node_hash(hasher, old_root, new_root)
+}
+
+/// Parent level of `level` under [`ODD_NODE_RULE`]
+fn parent_level<Hasher: HashFunction>(hasher: &Hasher, level: &[Hasher::Output]) -> Vec<Hasher::Output> {
+    level
+        .chunks(2)
+        .map(|pair| match (pair, ODD_NODE_RULE) {
+            ([left, right], _) => node_hash(hasher, left, right),
+            ([last], OddNodeRule::Duplicate) => node_hash(hasher, last, last),
+            ([last], OddNodeRule::Promote) => last.clone(),
+            _ => unreachable!("chunks of two"),
+        })
+        .collect()
+}
+
+/// Root over `leaves`, computed level by level from the rules above
+pub fn root<Hasher: HashFunction>(hasher: &Hasher, leaves: &[Hasher::Output]) -> Option<Hasher::Output> {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = parent_level(hasher, &level);
+    }
+    level.pop()
+}
+
+/// Sibling steps from leaf `index` to the root over `leaves`
+///
+/// A node without a sibling under [`OddNodeRule::Duplicate`] has itself as
+/// its right sibling.
+pub fn proof_path<Hasher: HashFunction>(hasher: &Hasher, leaves: &[Hasher::Output], mut index: usize) -> Option<Vec<ProofStep<Hasher::Output>>> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let mut level = leaves.to_vec();
+    let mut path = Vec::new();
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        match level.get(sibling) {
+            Some(hash) if index % 2 == 1 => path.push(ProofStep::Left(hash.clone())),
+            Some(hash) => path.push(ProofStep::Right(hash.clone())),
+            None if ODD_NODE_RULE == OddNodeRule::Duplicate => path.push(ProofStep::Right(level[index].clone())),
+            None => {}
+        }
+        level = parent_level(hasher, &level);
+        index /= 2;
+    }
+    Some(path)
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: crate::security::SecurityLogger,
+{
+    /// Version of the [`spec`](crate::spec) rules the tree's roots and proofs follow
+    pub fn spec_version(&self) -> u32 {
+        SPEC_VERSION
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::tree::TreeConfig;
+    use crate::Blake3Hasher;
+
+    fn leaves(count: usize) -> Vec<[u8; 32]> {
+        (0..count).map(|i| leaf_hash(&Blake3Hasher, format!("leaf{}", i).as_bytes())).collect()
+    }
+
+    fn tree(count: usize, incremental_updates: bool) -> ChronoMerkleTree {
+        let config = TreeConfig { incremental_updates, ..Default::default() };
+        let mut tree = ChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+        for i in 0..count {
+            tree.insert(format!("leaf{}", i).as_bytes(), 1000 + i as u64).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn test_rules_of_version_1() {
+        assert_eq!(SPEC_VERSION, 1);
+        assert_eq!(ODD_NODE_RULE, OddNodeRule::Duplicate);
+        assert_eq!(PADDING_RULE, PaddingRule::None);
+        assert!(LEAF_PREFIX.is_empty() && NODE_PREFIX.is_empty());
+        assert_eq!((TIMESTAMPS_HASHED, EMPTY_TREE_HAS_ROOT), (false, false));
+        assert_eq!(tree(0, true).root(), None);
+    }
+
+    #[test]
+    fn test_hashers_match_preimages() {
+        let (left, right) = (Blake3Hasher.hash(b"left"), Blake3Hasher.hash(b"right"));
+        let mut preimage = left.to_vec();
+        preimage.extend_from_slice(&right);
+        assert_eq!(Blake3Hasher.hash_pair(&left, &right), Blake3Hasher.hash(&preimage));
+        assert_eq!(node_hash(&Blake3Hasher, &left, &right), Blake3Hasher.hash(&preimage));
+        assert_eq!(leaf_hash(&Blake3Hasher, b"record"), Blake3Hasher.hash(b"record"));
+    }
+
+    #[test]
+    fn test_tree_follows_reference() {
+        for count in 1..=33 {
+            let leaves = leaves(count);
+            let expected = root(&Blake3Hasher, &leaves);
+            for incremental_updates in [true, false] {
+                let tree = tree(count, incremental_updates);
+                assert_eq!(tree.root(), expected, "{} leaves", count);
+                for index in [0, count / 2, count - 1] {
+                    let proof = tree.generate_proof(index).unwrap();
+                    assert_eq!(Some(proof.path), proof_path(&Blake3Hasher, &leaves, index));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_timestamps_not_hashed() {
+        let mut shifted: ChronoMerkleTree = ChronoMerkleTree::new(Blake3Hasher);
+        for i in 0..5 {
+            shifted.insert(format!("leaf{}", i).as_bytes(), 5000 + i as u64).unwrap();
+        }
+        assert_eq!(shifted.root(), tree(5, true).root());
+    }
+
+    #[test]
+    fn test_known_answer_roots() {
+        let vectors = [
+            (1, "c9ed7b28ea3182dc282e888247dad76bba1a5b1674cc2cf8c9b84ee9ee206b20"),
+            (2, "7fa4f2a318b96bb4e00514cb327f776e7b1d7b503753374089a9100dc3ccfb1c"),
+            (3, "323e90ebd4877f7ca10592d024c05946e68c81b04847f32db69a6b8954e337cd"),
+            (7, "a88575a73d21addbfc0ef6819cf57a18433adad3f3eb5b95b9c532480ba8801a"),
+        ];
+        for (count, expected) in vectors {
+            assert_eq!(hex::encode(tree(count, true).root().unwrap()), expected, "{} leaves", count);
+        }
+        assert_eq!(hex::encode(delta_hash(&Blake3Hasher, &[0; 32], &[1; 32])), "4e349255d382a791fd28b807968fce93d39ab678acfe8ad039e6e54ca3ea7e73");
+    }
+
+    #[test]
+    fn test_version_pinning() {
+        assert!(require_version(tree(1, true).spec_version()).is_ok());
+        assert!(matches!(
+            require_version(2),
+            Err(ChronoMerkleError::UnsupportedSpecVersion { version: 2, current: SPEC_VERSION })
+        ));
+        assert!(require_version_in(SPEC_VERSION, &[]).is_err());
+    }
+}
@@ -0,0 +1,747 @@
+//! Binary and chunked encodings of saved tree states
+//!
+//! [`TreeState`](crate::storage::TreeState)s are saved as JSON.
+//! [`StateCodec::Binary`] stores the same data model as self-described CBOR
+//! (RFC 8949), writing lowercase hex strings such as hashes as tagged byte
+//! strings (tag 23, "expected base16"), so they take half the space and
+//! still decode to the original text. [`StateCodec::Chunked`] splits the
+//! binary encoding across `"{key}.chunk.{generation}.{n}"` values for
+//! backends that limit value sizes, with a short manifest under `key` naming
+//! the generation. Rewriting a chunked state writes a new generation and only
+//! then replaces the manifest, so the saved state stays readable throughout.
+//!
+//! [`TreeState::load_from`](crate::storage::TreeState::load_from) recognizes
+//! all three encodings, so trees load from an upgraded key unchanged, and
+//! [`upgrade`] rewrites a saved state from one codec to another. Both
+//! transcode token by token and hold at most one string of the state in
+//! memory.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::storage::StorageBackend;
+
+use std::io::{self, Cursor, Read, Write};
+
+/// Encoding of a saved tree state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateCodec {
+    /// JSON text, as written by `save_state`
+    Json,
+    /// Self-described CBOR of the same data model
+    Binary,
+    /// The binary encoding split into values of at most `chunk_size` bytes
+    Chunked {
+        /// Largest chunk written
+        chunk_size: usize,
+    },
+}
+
+impl StateCodec {
+    /// Whether both codecs write the same kind of encoding, whatever their chunk sizes
+    fn same_kind(&self, other: &StateCodec) -> bool {
+        core::mem::discriminant(self) == core::mem::discriminant(other)
+    }
+}
+
+impl core::fmt::Display for StateCodec {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StateCodec::Json => write!(f, "json"),
+            StateCodec::Binary => write!(f, "binary"),
+            StateCodec::Chunked { chunk_size } => write!(f, "chunked ({} byte chunks)", chunk_size),
+        }
+    }
+}
+
+/// Outcome of an [`upgrade`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeReport {
+    /// Codec the state was stored with
+    pub from: StateCodec,
+    /// Codec the state is stored with now
+    pub to: StateCodec,
+    /// Bytes the state occupied before, including any chunk manifest
+    pub bytes_before: u64,
+    /// Bytes the state occupies now, including any chunk manifest
+    pub bytes_after: u64,
+    /// Chunk values written, 0 unless the target is chunked
+    pub chunks: usize,
+}
+
+impl UpgradeReport {
+    /// Bytes saved by the upgrade, negative if the state grew
+    pub fn bytes_saved(&self) -> i64 {
+        self.bytes_before as i64 - self.bytes_after as i64
+    }
+
+    /// Size after the upgrade as a fraction of the size before
+    pub fn ratio(&self) -> f64 {
+        if self.bytes_before == 0 {
+            return 1.0;
+        }
+        self.bytes_after as f64 / self.bytes_before as f64
+    }
+}
+
+/// Self-described CBOR tag opening every binary state
+const CBOR_MAGIC: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+/// Opening bytes of a chunk manifest, followed by chunk size, chunk count,
+/// total length and chunk generation as big-endian `u64`s
+const CHUNK_MAGIC: &[u8; 8] = b"CMCHUNK2";
+
+/// Length of a chunk manifest
+const MANIFEST_LEN: usize = CHUNK_MAGIC.len() + 32;
+
+/// CBOR tag marking a byte string that was a lowercase hex string
+const HEX_TAG: u64 = 23;
+
+/// Most nested arrays and maps accepted when decoding
+const MAX_DEPTH: usize = 128;
+
+/// Key of chunk `index` of generation `generation` of a chunked state saved under `key`
+pub(crate) fn chunk_key(key: &str, generation: u64, index: usize) -> String {
+    format!("{}.chunk.{}.{}", key, generation, index)
+}
+
+fn io_error(key: &str, error: io::Error) -> ChronoMerkleError {
+    ChronoMerkleError::StorageError { reason: format!("Failed to transcode state {}: {}", key, error) }
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Parsed chunk manifest
+struct ChunkManifest {
+    chunk_size: usize,
+    chunks: usize,
+    total: u64,
+    generation: u64,
+}
+
+impl ChunkManifest {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = CHUNK_MAGIC.to_vec();
+        bytes.extend_from_slice(&crate::encoding::encode_u64(self.chunk_size as u64));
+        bytes.extend_from_slice(&crate::encoding::encode_u64(self.chunks as u64));
+        bytes.extend_from_slice(&crate::encoding::encode_u64(self.total));
+        bytes.extend_from_slice(&crate::encoding::encode_u64(self.generation));
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != MANIFEST_LEN || !bytes.starts_with(CHUNK_MAGIC) {
+            return None;
+        }
+        let field = |index: usize| {
+            let start = CHUNK_MAGIC.len() + 8 * index;
+            crate::encoding::decode_u64(&bytes[start..start + 8])
+        };
+        Some(Self {
+            chunk_size: usize::try_from(field(0)?).ok()?,
+            chunks: usize::try_from(field(1)?).ok()?,
+            total: field(2)?,
+            generation: field(3)?,
+        })
+    }
+}
+
+/// Encoded state under `key`: its codec, the manifest if chunked, and a reader over the encoding
+///
+/// For chunked states the reader yields the chunks concatenated, i.e. the binary encoding.
+fn open_encoded<'a, S: StorageBackend + ?Sized>(
+    storage: &'a S,
+    key: &str,
+) -> Result<(StateCodec, Option<ChunkManifest>, Box<dyn Read + 'a>)> {
+    let mut reader = storage.load_stream(key)?.ok_or_else(|| ChronoMerkleError::StorageError {
+        reason: format!("No data found for key: {}", key),
+    })?;
+    let mut head = Vec::with_capacity(CHUNK_MAGIC.len());
+    reader.by_ref().take(CHUNK_MAGIC.len() as u64).read_to_end(&mut head).map_err(|e| io_error(key, e))?;
+
+    if head.starts_with(CHUNK_MAGIC) {
+        reader.read_to_end(&mut head).map_err(|e| io_error(key, e))?;
+        let manifest = ChunkManifest::decode(&head)
+            .ok_or_else(|| ChronoMerkleError::DeserializationError(format!("Malformed chunk manifest under {}", key)))?;
+        let chunks = ChunkReader {
+            storage,
+            key: key.to_string(),
+            generation: manifest.generation,
+            next: 0,
+            count: manifest.chunks,
+            current: None,
+        };
+        let codec = StateCodec::Chunked { chunk_size: manifest.chunk_size };
+        return Ok((codec, Some(manifest), Box::new(chunks)));
+    }
+    let codec = if head.starts_with(&CBOR_MAGIC) { StateCodec::Binary } else { StateCodec::Json };
+    Ok((codec, None, Box::new(Cursor::new(head).chain(reader))))
+}
+
+/// Codec of the state saved under `key`
+pub fn detect_codec(storage: &(impl StorageBackend + ?Sized), key: &str) -> Result<StateCodec> {
+    open_encoded(storage, key).map(|(codec, _, _)| codec)
+}
+
+/// Chunk keys of the chunked state saved under `key`, `None` if it is not chunked
+pub(crate) fn chunked_state_keys(storage: &(impl StorageBackend + ?Sized), key: &str) -> Result<Option<Vec<String>>> {
+    let (_, manifest, _) = open_encoded(storage, key)?;
+    Ok(manifest.map(|manifest| (0..manifest.chunks).map(|index| chunk_key(key, manifest.generation, index)).collect()))
+}
+
+/// Open the state saved under `key` in any codec as a JSON stream
+pub(crate) fn open_state_json<'a, S: StorageBackend + ?Sized>(storage: &'a S, key: &str) -> Result<Box<dyn Read + 'a>> {
+    let (codec, _, reader) = open_encoded(storage, key)?;
+    Ok(match codec {
+        StateCodec::Json => reader,
+        StateCodec::Binary | StateCodec::Chunked { .. } => Box::new(JsonFromCbor::new(io::BufReader::new(reader))),
+    })
+}
+
+/// Concatenation of the chunks of a chunked state, opened one at a time
+struct ChunkReader<'a, S: ?Sized> {
+    storage: &'a S,
+    key: String,
+    generation: u64,
+    next: usize,
+    count: usize,
+    current: Option<Box<dyn Read + 'a>>,
+}
+
+impl<S: StorageBackend + ?Sized> Read for ChunkReader<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(current) = &mut self.current {
+                let read = current.read(buf)?;
+                if read > 0 || buf.is_empty() {
+                    return Ok(read);
+                }
+                self.current = None;
+            }
+            if self.next == self.count {
+                return Ok(0);
+            }
+            let key = chunk_key(&self.key, self.generation, self.next);
+            let chunk = self.storage.load_stream(&key).map_err(|e| io::Error::other(e.to_string()))?;
+            self.current = Some(chunk.ok_or_else(|| invalid_data(format!("missing chunk {}", key)))?);
+            self.next += 1;
+        }
+    }
+}
+
+/// Writer of the CBOR items used by binary states
+struct CborWriter<W> {
+    out: W,
+    written: u64,
+}
+
+impl<W: Write> CborWriter<W> {
+    fn bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.out.write_all(bytes)?;
+        self.written += bytes.len() as u64;
+        Ok(())
+    }
+
+    fn head(&mut self, major: u8, value: u64) -> io::Result<()> {
+        let major = major << 5;
+        match value {
+            0..=23 => self.bytes(&[major | value as u8]),
+            24..=0xff => self.bytes(&[major | 24, value as u8]),
+            0x100..=0xffff => {
+                self.bytes(&[major | 25])?;
+                self.bytes(&(value as u16).to_be_bytes())
+            }
+            0x1_0000..=0xffff_ffff => {
+                self.bytes(&[major | 26])?;
+                self.bytes(&(value as u32).to_be_bytes())
+            }
+            _ => {
+                self.bytes(&[major | 27])?;
+                self.bytes(&value.to_be_bytes())
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) -> io::Result<()> {
+        let is_hex = !text.is_empty() && text.len() % 2 == 0 && text.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+        if !is_hex {
+            self.head(3, text.len() as u64)?;
+            return self.bytes(text.as_bytes());
+        }
+        let decoded = crate::codec::from_hex(text).expect("checked hex digits");
+        self.head(6, HEX_TAG)?;
+        self.head(2, decoded.len() as u64)?;
+        self.bytes(&decoded)
+    }
+}
+
+/// Seed and visitor copying one JSON value into a [`CborWriter`]
+struct Transcode<'a, W>(&'a mut CborWriter<W>);
+
+impl<'de, W: Write> serde::de::DeserializeSeed<'de> for Transcode<'_, W> {
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> core::result::Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, W: Write> serde::de::Visitor<'de> for Transcode<'_, W> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, value: bool) -> core::result::Result<(), E> {
+        self.0.bytes(&[if value { 0xf5 } else { 0xf4 }]).map_err(E::custom)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> core::result::Result<(), E> {
+        match u64::try_from(value) {
+            Ok(value) => self.0.head(0, value),
+            Err(_) => self.0.head(1, !(value as u64)),
+        }
+        .map_err(E::custom)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> core::result::Result<(), E> {
+        self.0.head(0, value).map_err(E::custom)
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> core::result::Result<(), E> {
+        self.0.bytes(&[0xfb]).and_then(|_| self.0.bytes(&value.to_be_bytes())).map_err(E::custom)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> core::result::Result<(), E> {
+        self.0.text(value).map_err(E::custom)
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> core::result::Result<(), E> {
+        self.0.bytes(&[0xf6]).map_err(E::custom)
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> core::result::Result<(), A::Error> {
+        use serde::de::Error;
+        self.0.bytes(&[0x9f]).map_err(A::Error::custom)?;
+        while seq.next_element_seed(Transcode(&mut *self.0))?.is_some() {}
+        self.0.bytes(&[0xff]).map_err(A::Error::custom)
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> core::result::Result<(), A::Error> {
+        use serde::de::Error;
+        self.0.bytes(&[0xbf]).map_err(A::Error::custom)?;
+        while map.next_key_seed(Transcode(&mut *self.0))?.is_some() {
+            map.next_value_seed(Transcode(&mut *self.0))?;
+        }
+        self.0.bytes(&[0xff]).map_err(A::Error::custom)
+    }
+}
+
+/// Copy the JSON document from `json` into `out` as a binary state, returning the bytes written
+fn json_to_cbor(json: impl Read, out: impl Write) -> Result<u64> {
+    use serde::de::DeserializeSeed;
+    let mut writer = CborWriter { out, written: 0 };
+    writer.bytes(&CBOR_MAGIC).map_err(|e| ChronoMerkleError::SerializationError(e.to_string()))?;
+    let mut deserializer = serde_json::Deserializer::from_reader(json);
+    Transcode(&mut writer)
+        .deserialize(&mut deserializer)
+        .and_then(|_| deserializer.end())
+        .map_err(|e| ChronoMerkleError::DeserializationError(e.to_string()))?;
+    writer.out.flush().map_err(|e| ChronoMerkleError::SerializationError(e.to_string()))?;
+    Ok(writer.written)
+}
+
+/// Open array or map while decoding a binary state
+enum Frame {
+    Array { first: bool },
+    Map { first: bool, key: bool },
+}
+
+/// Reader producing the JSON text of a binary state, one item at a time
+struct JsonFromCbor<R> {
+    input: R,
+    started: bool,
+    done: bool,
+    stack: Vec<Frame>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> JsonFromCbor<R> {
+    fn new(input: R) -> Self {
+        Self { input, started: false, done: false, stack: Vec::new(), buffer: Vec::new(), position: 0 }
+    }
+
+    fn read_exact_vec(&mut self, len: u64) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        (&mut self.input).take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0u8];
+        self.input.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn argument(&mut self, info: u8) -> io::Result<u64> {
+        let len = match info {
+            0..=23 => return Ok(u64::from(info)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err(invalid_data("unsupported CBOR length encoding")),
+        };
+        let mut bytes = [0u8; 8];
+        self.input.read_exact(&mut bytes[8 - len..])?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    /// Separator before the next array element or map entry
+    fn before_item(&mut self) {
+        if let Some(Frame::Array { first } | Frame::Map { first, key: true }) = self.stack.last_mut() {
+            if !core::mem::replace(first, false) {
+                self.buffer.push(b',');
+            }
+        }
+    }
+
+    /// Separator after a complete item, and the end of the document
+    fn after_item(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Map { key, .. }) => {
+                if *key {
+                    self.buffer.push(b':');
+                }
+                *key = !*key;
+            }
+            Some(Frame::Array { .. }) => {}
+            None => self.done = true,
+        }
+    }
+
+    fn json<T: serde::Serialize + ?Sized>(&mut self, value: &T) -> io::Result<()> {
+        serde_json::to_writer(&mut self.buffer, value).map_err(io::Error::other)
+    }
+
+    /// Decode the next CBOR item header into JSON text in `buffer`
+    fn next_item(&mut self) -> io::Result<()> {
+        if !self.started {
+            let mut magic = [0u8; 3];
+            self.input.read_exact(&mut magic)?;
+            if magic != CBOR_MAGIC {
+                return Err(invalid_data("not a binary tree state"));
+            }
+            self.started = true;
+        }
+        let initial = self.byte()?;
+        if initial == 0xff {
+            match self.stack.pop() {
+                Some(Frame::Array { .. }) => self.buffer.push(b']'),
+                Some(Frame::Map { key: true, .. }) => self.buffer.push(b'}'),
+                _ => return Err(invalid_data("unexpected CBOR break")),
+            }
+            self.after_item();
+            return Ok(());
+        }
+
+        self.before_item();
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        match (major, info) {
+            (0, _) => {
+                let value = self.argument(info)?;
+                self.json(&value)?;
+            }
+            (1, _) => {
+                let value = -1 - i128::from(self.argument(info)?);
+                self.json(&value)?;
+            }
+            (3, _) => {
+                let len = self.argument(info)?;
+                let text = String::from_utf8(self.read_exact_vec(len)?).map_err(|_| invalid_data("invalid UTF-8 in CBOR text"))?;
+                self.json(&text)?;
+            }
+            (6, _) => {
+                let (tag, header) = (self.argument(info)?, self.byte()?);
+                if tag != HEX_TAG || header >> 5 != 2 {
+                    return Err(invalid_data(format!("unsupported CBOR tag {}", tag)));
+                }
+                let len = self.argument(header & 0x1f)?;
+                let bytes = self.read_exact_vec(len)?;
+                self.json(&crate::codec::to_hex(&bytes))?;
+            }
+            (4, 31) | (5, 31) => {
+                if self.stack.len() == MAX_DEPTH {
+                    return Err(invalid_data("binary tree state nested too deeply"));
+                }
+                let (open, frame) = if major == 4 {
+                    (b'[', Frame::Array { first: true })
+                } else {
+                    (b'{', Frame::Map { first: true, key: true })
+                };
+                self.buffer.push(open);
+                self.stack.push(frame);
+                return Ok(());
+            }
+            (7, 20) => self.buffer.extend_from_slice(b"false"),
+            (7, 21) => self.buffer.extend_from_slice(b"true"),
+            (7, 22) => self.buffer.extend_from_slice(b"null"),
+            (7, 27) => {
+                let bits = self.argument(27)?;
+                self.json(&f64::from_bits(bits))?;
+            }
+            _ => return Err(invalid_data(format!("unsupported CBOR item 0x{:02x}", initial))),
+        }
+        self.after_item();
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for JsonFromCbor<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if self.done || out.is_empty() {
+                return Ok(0);
+            }
+            self.buffer.clear();
+            self.position = 0;
+            self.next_item()?;
+        }
+        let len = out.len().min(self.buffer.len() - self.position);
+        out[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// Temporary file holding an encoded state between reading and writing, removed on drop
+struct Spool {
+    path: std::path::PathBuf,
+    file: std::fs::File,
+}
+
+impl Spool {
+    fn create() -> io::Result<Self> {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let name = format!("chrono-merkle-upgrade-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        let file = std::fs::OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Rewrite the tree state saved under `key` from `from_codec` to `to_codec`
+///
+/// The state is transcoded as it is read and spooled to a temporary file,
+/// since the backend cannot be read and written at once, then written back
+/// under `key`. Chunks left over from a previous chunked encoding are
+/// deleted. Fails without writing if the stored state is not in
+/// `from_codec` (chunk sizes aside). Readers that load `key` while an
+/// upgrade to or from a chunked codec runs may see a mix of both encodings.
+pub fn upgrade(
+    storage: &mut (impl StorageBackend + ?Sized),
+    key: &str,
+    from_codec: StateCodec,
+    to_codec: StateCodec,
+) -> Result<UpgradeReport> {
+    if let StateCodec::Chunked { chunk_size: 0 } = to_codec {
+        return Err(ChronoMerkleError::InvalidConfiguration {
+            parameter: "chunk_size".to_string(),
+            reason: "Chunk size must be greater than 0".to_string(),
+        });
+    }
+
+    let mut spool = Spool::create().map_err(|e| io_error(key, e))?;
+    let (from, old_manifest, bytes_before, encoded) = {
+        let (codec, manifest, reader) = open_encoded(&*storage, key)?;
+        if !codec.same_kind(&from_codec) {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "from_codec".to_string(),
+                reason: format!("{} holds a {} state, not {}", key, codec, from_codec),
+            });
+        }
+        let mut counted = CountingReader { inner: reader, count: 0 };
+        let mut out = io::BufWriter::new(&spool.file);
+        let encoded = match (codec, to_codec) {
+            (StateCodec::Json, StateCodec::Binary | StateCodec::Chunked { .. }) => json_to_cbor(&mut counted, &mut out)?,
+            (StateCodec::Binary | StateCodec::Chunked { .. }, StateCodec::Json) => {
+                io::copy(&mut JsonFromCbor::new(io::BufReader::new(&mut counted)), &mut out).map_err(|e| io_error(key, e))?
+            }
+            _ => io::copy(&mut counted, &mut out).map_err(|e| io_error(key, e))?,
+        };
+        out.flush().map_err(|e| io_error(key, e))?;
+        let manifest_len = manifest.as_ref().map_or(0, |_| MANIFEST_LEN as u64);
+        (codec, manifest, counted.count + manifest_len, encoded)
+    };
+
+    use std::io::Seek;
+    spool.file.rewind().map_err(|e| io_error(key, e))?;
+    // The old chunks stay in place until the value under `key` no longer refers to them
+    let (chunks, bytes_after) = match to_codec {
+        StateCodec::Chunked { chunk_size } => {
+            let chunks = encoded.div_ceil(chunk_size as u64) as usize;
+            let generation = old_manifest.as_ref().map_or(0, |manifest| manifest.generation.wrapping_add(1));
+            for index in 0..chunks {
+                storage.save_stream(&chunk_key(key, generation, index), &mut (&spool.file).take(chunk_size as u64))?;
+            }
+            let manifest = ChunkManifest { chunk_size, chunks, total: encoded, generation };
+            storage.save(key, &manifest.encode())?;
+            (chunks, encoded + MANIFEST_LEN as u64)
+        }
+        StateCodec::Json | StateCodec::Binary => (0, storage.save_stream(key, &mut spool.file)?),
+    };
+    if let Some(old) = old_manifest {
+        for index in 0..old.chunks {
+            storage.delete(&chunk_key(key, old.generation, index))?;
+        }
+    }
+
+    Ok(UpgradeReport { from, to: to_codec, bytes_before, bytes_after, chunks })
+}
+
+/// Reader counting the bytes read through it
+struct CountingReader<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+    use crate::security::NoOpLogger;
+    use crate::storage::MemoryStorage;
+    use crate::ChronoMerkleTree;
+
+    fn saved_tree(storage: &mut MemoryStorage) -> ChronoMerkleTree {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::new(Blake3Hasher);
+        for i in 0..40u64 {
+            tree.insert(format!("record-{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        tree.save_state(storage, "tree").unwrap();
+        tree
+    }
+
+    #[test]
+    fn test_upgrade_roundtrip() {
+        let mut storage = MemoryStorage::new();
+        let tree = saved_tree(&mut storage);
+        let json = storage.load("tree").unwrap().unwrap();
+
+        let report = upgrade(&mut storage, "tree", StateCodec::Json, StateCodec::Binary).unwrap();
+        assert_eq!(report.bytes_before, json.len() as u64);
+        assert!(report.bytes_saved() > 0 && report.ratio() < 0.75, "{:?}", report);
+        assert_eq!(detect_codec(&storage, "tree").unwrap(), StateCodec::Binary);
+        let loaded: ChronoMerkleTree = ChronoMerkleTree::load_state(&storage, "tree", Blake3Hasher, NoOpLogger).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+
+        let binary_len = report.bytes_after;
+        let chunked = StateCodec::Chunked { chunk_size: 512 };
+        let report = upgrade(&mut storage, "tree", StateCodec::Binary, chunked).unwrap();
+        assert_eq!(report.chunks as u64, binary_len.div_ceil(512));
+        assert!(storage.exists(&chunk_key("tree", 0, report.chunks - 1)).unwrap());
+        let loaded: ChronoMerkleTree = ChronoMerkleTree::load_state(&storage, "tree", Blake3Hasher, NoOpLogger).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+
+        let report = crate::storage::gc(&mut storage, |_| Ok(vec!["tree".to_string()]), true).unwrap();
+        assert!(report.orphaned.is_empty(), "{:?}", report.orphaned);
+
+        upgrade(&mut storage, "tree", chunked, StateCodec::Json).unwrap();
+        assert_eq!(storage.load("tree").unwrap().unwrap(), json);
+        assert!(!storage.exists(&chunk_key("tree", 0, 0)).unwrap());
+    }
+
+    /// Storage refusing to save the value under `key`
+    struct FailingSave {
+        inner: MemoryStorage,
+        key: &'static str,
+    }
+
+    impl StorageBackend for FailingSave {
+        fn save(&mut self, key: &str, data: &[u8]) -> Result<()> {
+            if key == self.key {
+                return Err(ChronoMerkleError::StorageError { reason: "injected failure".to_string() });
+            }
+            self.inner.save(key, data)
+        }
+        fn load(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            self.inner.load(key)
+        }
+        fn delete(&mut self, key: &str) -> Result<()> {
+            self.inner.delete(key)
+        }
+        fn list_keys(&self) -> Result<Vec<String>> {
+            self.inner.list_keys()
+        }
+        fn exists(&self, key: &str) -> Result<bool> {
+            self.inner.exists(key)
+        }
+    }
+
+    #[test]
+    fn test_rechunking_keeps_the_old_chunks_until_the_manifest_moves() {
+        let mut storage = MemoryStorage::new();
+        let tree = saved_tree(&mut storage);
+        upgrade(&mut storage, "tree", StateCodec::Json, StateCodec::Chunked { chunk_size: 512 }).unwrap();
+        let old_chunks = chunked_state_keys(&storage, "tree").unwrap().unwrap();
+
+        // Interrupted before the manifest is replaced: the old state still loads
+        let chunked = StateCodec::Chunked { chunk_size: 512 };
+        let mut failing = FailingSave { inner: storage, key: "tree" };
+        let err = upgrade(&mut failing, "tree", chunked, StateCodec::Chunked { chunk_size: 256 }).unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::StorageError { .. }), "{:?}", err);
+        let mut storage = failing.inner;
+        let loaded: ChronoMerkleTree = ChronoMerkleTree::load_state(&storage, "tree", Blake3Hasher, NoOpLogger).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+
+        // Completed: the new generation is live and the old one is deleted
+        let report = upgrade(&mut storage, "tree", chunked, StateCodec::Chunked { chunk_size: 256 }).unwrap();
+        let new_chunks = chunked_state_keys(&storage, "tree").unwrap().unwrap();
+        assert_eq!(new_chunks.len(), report.chunks);
+        assert!(new_chunks.iter().all(|chunk| !old_chunks.contains(chunk)));
+        assert!(old_chunks.iter().all(|chunk| !storage.exists(chunk).unwrap()));
+        let report = crate::storage::gc(&mut storage, |_| Ok(vec!["tree".to_string()]), true).unwrap();
+        assert!(report.orphaned.is_empty(), "{:?}", report.orphaned);
+        let loaded: ChronoMerkleTree = ChronoMerkleTree::load_state(&storage, "tree", Blake3Hasher, NoOpLogger).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+    }
+
+    #[test]
+    fn test_upgrade_checks_source_codec() {
+        let mut storage = MemoryStorage::new();
+        saved_tree(&mut storage);
+        let err = upgrade(&mut storage, "tree", StateCodec::Binary, StateCodec::Json).unwrap_err();
+        assert!(matches!(err, ChronoMerkleError::InvalidConfiguration { ref parameter, .. } if parameter == "from_codec"));
+        assert_eq!(detect_codec(&storage, "tree").unwrap(), StateCodec::Json);
+    }
+
+    #[test]
+    fn test_cbor_transcoding_preserves_values() {
+        let json = br#"{"a":[1,-2,0.5,true,null,"text","00ff",""],"b":{"c":18446744073709551615}}"#;
+        let mut binary = Vec::new();
+        json_to_cbor(&json[..], &mut binary).unwrap();
+        let mut decoded = String::new();
+        JsonFromCbor::new(&binary[..]).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded.as_bytes(), &json[..]);
+    }
+}
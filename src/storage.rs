@@ -12,10 +12,16 @@ use std::fs;
 #[cfg(all(feature = "storage", feature = "std", not(feature = "no-std")))]
 use std::path::PathBuf;
 
+#[cfg(feature = "storage")]
 use crate::ChronoMerkleError;
+#[cfg(feature = "storage")]
 use crate::config::TreeConfig;
+#[cfg(feature = "storage")]
 use crate::node::Node;
+#[cfg(feature = "storage")]
 use crate::sparse_index::SparseIndex;
+#[cfg(feature = "storage")]
+pub use crate::state_codec::{detect_codec, upgrade, StateCodec, UpgradeReport};
 
 /// Trait for persistent storage backends
 #[cfg(feature = "storage")]
@@ -34,6 +40,256 @@ pub trait StorageBackend: Send + Sync {
 
     /// Check if a key exists in the storage backend
     fn exists(&self, key: &str) -> core::result::Result<bool, ChronoMerkleError>;
+
+    /// Save data read from `reader` under `key`, returning the number of bytes written
+    ///
+    /// The default implementation buffers the whole stream; backends that can
+    /// write incrementally should override it.
+    fn save_stream(&mut self, key: &str, reader: &mut dyn std::io::Read) -> core::result::Result<u64, ChronoMerkleError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).map_err(|e| ChronoMerkleError::StorageError {
+            reason: format!("Failed to read stream for key {}: {}", key, e),
+        })?;
+        self.save(key, &data)?;
+        Ok(data.len() as u64)
+    }
+
+    /// Open the data stored under `key` as a reader
+    ///
+    /// The default implementation loads the whole value into memory first.
+    fn load_stream(&self, key: &str) -> core::result::Result<Option<Box<dyn std::io::Read + '_>>, ChronoMerkleError> {
+        Ok(self
+            .load(key)?
+            .map(|data| Box::new(std::io::Cursor::new(data)) as Box<dyn std::io::Read>))
+    }
+
+    /// Probe whether the backend is reachable and writable
+    ///
+    /// The default implementation times an `exists` lookup and cannot tell
+    /// whether writes would succeed; backends that can probe writes should override it.
+    fn health_check(&self) -> core::result::Result<BackendHealth, ChronoMerkleError> {
+        let started = std::time::Instant::now();
+        let probe = self.exists(HEALTH_PROBE_KEY);
+        Ok(BackendHealth {
+            reachable: probe.is_ok(),
+            writable: None,
+            latency: started.elapsed(),
+            detail: probe.err().map(|e| e.to_string()),
+        })
+    }
+
+    /// Save data as a new version of `key`, keeping earlier versions
+    ///
+    /// Versions are stored under `"{key}@v{n}"` and numbered from 1.
+    fn save_versioned(&mut self, key: &str, data: &[u8]) -> core::result::Result<Version, ChronoMerkleError> {
+        let version = self.list_versions(key)?.last().map_or(Version(1), |v| Version(v.0 + 1));
+        self.save(&version.key_for(key), data)?;
+        Ok(version)
+    }
+
+    /// Load a specific version of `key`
+    fn load_version(&self, key: &str, version: Version) -> core::result::Result<Option<Vec<u8>>, ChronoMerkleError> {
+        self.load(&version.key_for(key))
+    }
+
+    /// List the stored versions of `key` in ascending order
+    fn list_versions(&self, key: &str) -> core::result::Result<Vec<Version>, ChronoMerkleError> {
+        let prefix = format!("{}@v", key);
+        let mut versions: Vec<Version> = self
+            .list_keys()?
+            .iter()
+            .filter_map(|k| k.strip_prefix(&prefix)?.parse().ok().map(Version))
+            .collect();
+        versions.sort();
+        Ok(versions)
+    }
+
+    /// Wrap this backend so all keys live under `namespace`
+    ///
+    /// Fails if `namespace` is empty or contains a `.`.
+    fn scoped(self, namespace: &str) -> core::result::Result<NamespacedStorage<Self>, ChronoMerkleError>
+    where
+        Self: Sized,
+    {
+        NamespacedStorage::new(self, namespace)
+    }
+}
+
+#[cfg(feature = "storage")]
+impl<S: StorageBackend + ?Sized> StorageBackend for &mut S {
+    fn save(&mut self, key: &str, data: &[u8]) -> core::result::Result<(), ChronoMerkleError> {
+        (**self).save(key, data)
+    }
+
+    fn load(&self, key: &str) -> core::result::Result<Option<Vec<u8>>, ChronoMerkleError> {
+        (**self).load(key)
+    }
+
+    fn delete(&mut self, key: &str) -> core::result::Result<(), ChronoMerkleError> {
+        (**self).delete(key)
+    }
+
+    fn list_keys(&self) -> core::result::Result<Vec<String>, ChronoMerkleError> {
+        (**self).list_keys()
+    }
+
+    fn exists(&self, key: &str) -> core::result::Result<bool, ChronoMerkleError> {
+        (**self).exists(key)
+    }
+
+    fn save_stream(&mut self, key: &str, reader: &mut dyn std::io::Read) -> core::result::Result<u64, ChronoMerkleError> {
+        (**self).save_stream(key, reader)
+    }
+
+    fn load_stream(&self, key: &str) -> core::result::Result<Option<Box<dyn std::io::Read + '_>>, ChronoMerkleError> {
+        (**self).load_stream(key)
+    }
+
+    fn health_check(&self) -> core::result::Result<BackendHealth, ChronoMerkleError> {
+        (**self).health_check()
+    }
+}
+
+/// Storage wrapper that prefixes every key with a namespace
+///
+/// Keys are stored as `"{namespace}.{key}"`, so several trees can share one
+/// backend without collisions. Namespaces may not contain `.`, so the first
+/// `.` of a stored key always ends its namespace. `list_keys` only returns keys
+/// in the namespace, with the prefix stripped. Namespaces nest by wrapping a
+/// `NamespacedStorage`.
+#[cfg(feature = "storage")]
+pub struct NamespacedStorage<S> {
+    inner: S,
+    prefix: String,
+}
+
+#[cfg(feature = "storage")]
+impl<S: StorageBackend> NamespacedStorage<S> {
+    /// Wrap `inner`, placing all keys under `namespace`
+    ///
+    /// Fails if `namespace` is empty or contains a `.`, which would let
+    /// `"a"` with key `"b.c"` and `"a.b"` with key `"c"` share a stored key.
+    pub fn new(inner: S, namespace: &str) -> core::result::Result<Self, ChronoMerkleError> {
+        if namespace.is_empty() || namespace.contains('.') {
+            return Err(ChronoMerkleError::InvalidConfiguration {
+                parameter: "namespace".to_string(),
+                reason: format!("namespace {:?} must be non-empty and must not contain '.'", namespace),
+            });
+        }
+        Ok(Self {
+            inner,
+            prefix: format!("{}.", namespace),
+        })
+    }
+
+    /// Get the namespace of this wrapper
+    pub fn namespace(&self) -> &str {
+        &self.prefix[..self.prefix.len() - 1]
+    }
+
+    /// Get a reference to the wrapped backend
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Unwrap into the inner backend
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+}
+
+#[cfg(feature = "storage")]
+impl<S: StorageBackend> StorageBackend for NamespacedStorage<S> {
+    fn save(&mut self, key: &str, data: &[u8]) -> core::result::Result<(), ChronoMerkleError> {
+        let key = self.full_key(key);
+        self.inner.save(&key, data)
+    }
+
+    fn load(&self, key: &str) -> core::result::Result<Option<Vec<u8>>, ChronoMerkleError> {
+        self.inner.load(&self.full_key(key))
+    }
+
+    fn delete(&mut self, key: &str) -> core::result::Result<(), ChronoMerkleError> {
+        let key = self.full_key(key);
+        self.inner.delete(&key)
+    }
+
+    fn list_keys(&self) -> core::result::Result<Vec<String>, ChronoMerkleError> {
+        Ok(self
+            .inner
+            .list_keys()?
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&self.prefix).map(|k| k.to_string()))
+            .collect())
+    }
+
+    fn exists(&self, key: &str) -> core::result::Result<bool, ChronoMerkleError> {
+        self.inner.exists(&self.full_key(key))
+    }
+
+    fn save_stream(&mut self, key: &str, reader: &mut dyn std::io::Read) -> core::result::Result<u64, ChronoMerkleError> {
+        let key = self.full_key(key);
+        self.inner.save_stream(&key, reader)
+    }
+
+    fn load_stream(&self, key: &str) -> core::result::Result<Option<Box<dyn std::io::Read + '_>>, ChronoMerkleError> {
+        self.inner.load_stream(&self.full_key(key))
+    }
+
+    fn health_check(&self) -> core::result::Result<BackendHealth, ChronoMerkleError> {
+        self.inner.health_check()
+    }
+}
+
+/// Key looked up by the default [`StorageBackend::health_check`]
+#[cfg(feature = "storage")]
+const HEALTH_PROBE_KEY: &str = "__health_probe";
+
+/// Result of [`StorageBackend::health_check`]
+#[cfg(feature = "storage")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendHealth {
+    /// Whether the backend answered the probe
+    pub reachable: bool,
+    /// Whether a write probe succeeded (`None` if the backend cannot tell)
+    pub writable: Option<bool>,
+    /// Time taken by the probe
+    pub latency: core::time::Duration,
+    /// Error message from a failed probe
+    pub detail: Option<String>,
+}
+
+#[cfg(feature = "storage")]
+impl BackendHealth {
+    /// Reachable and not known to reject writes
+    pub fn is_healthy(&self) -> bool {
+        self.reachable && self.writable != Some(false)
+    }
+}
+
+/// Version number of a checkpoint saved with [`StorageBackend::save_versioned`]
+#[cfg(feature = "storage")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version(pub u64);
+
+#[cfg(feature = "storage")]
+impl Version {
+    /// Storage key holding this version of `key`
+    pub fn key_for(&self, key: &str) -> String {
+        format!("{}@v{}", key, self.0)
+    }
+}
+
+#[cfg(feature = "storage")]
+impl core::fmt::Display for Version {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "v{}", self.0)
+    }
 }
 
 /// Serializable tree state for persistence
@@ -41,7 +297,7 @@ pub trait StorageBackend: Send + Sync {
 #[derive(Clone)]
 pub struct TreeState<H>
 where
-    H: serde::Serialize + serde::de::DeserializeOwned,
+    H: crate::traits::MaybeSerde,
 {
     /// All nodes stored in heap order (complete binary tree)
     pub nodes: Vec<Node<H>>,
@@ -57,19 +313,25 @@ where
     pub stored_deltas: Vec<Node<H>>,
     /// Maps timestamp -> list of deltas that led to that state
     pub delta_chains: SparseIndex,
+    /// Roots after each recorded change
+    pub root_history: Vec<crate::root_history::RootRecord<H>>,
+    /// Tombstones of redacted leaves
+    pub redactions: Vec<crate::redaction::Redaction<H>>,
+    /// Algorithm the leaf data is encrypted with, if it is
+    pub data_cipher: Option<String>,
 }
 
 #[cfg(feature = "storage")]
 impl<H> serde::Serialize for TreeState<H>
 where
-    H: serde::Serialize + serde::de::DeserializeOwned,
+    H: crate::traits::MaybeSerde,
 {
     fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
         use serde::ser::SerializeStruct;
-        let mut state = serializer.serialize_struct("TreeState", 7)?;
+        let mut state = serializer.serialize_struct("TreeState", 10)?;
         state.serialize_field("nodes", &self.nodes)?;
         state.serialize_field("leaf_count", &self.leaf_count)?;
         state.serialize_field("sparse_index", &self.sparse_index)?;
@@ -77,6 +339,17 @@ where
         state.serialize_field("incremental_updates", &self.incremental_updates)?;
         state.serialize_field("stored_deltas", &self.stored_deltas)?;
         state.serialize_field("delta_chains", &self.delta_chains)?;
+        state.serialize_field("redactions", &self.redactions)?;
+        // Omitted when empty, like the cipher below, so older readers still load the state
+        match self.root_history.as_slice() {
+            [] => state.skip_field("root_history")?,
+            records => state.serialize_field("root_history", records)?,
+        }
+        // Omitted for plaintext states, which keep their previous encoding
+        match &self.data_cipher {
+            Some(algorithm) => state.serialize_field("data_cipher", algorithm)?,
+            None => state.skip_field("data_cipher")?,
+        }
         state.end()
     }
 }
@@ -84,7 +357,7 @@ where
 #[cfg(feature = "storage")]
 impl<'de, H> serde::Deserialize<'de> for TreeState<H>
 where
-    H: serde::Serialize + serde::de::DeserializeOwned,
+    H: crate::traits::MaybeSerde,
 {
     fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
     where
@@ -100,6 +373,12 @@ where
             incremental_updates: bool,
             stored_deltas: Vec<Node<H>>,
             delta_chains: SparseIndex,
+            #[serde(default)]
+            redactions: Vec<crate::redaction::Redaction<H>>,
+            #[serde(default)]
+            root_history: Vec<crate::root_history::RootRecord<H>>,
+            #[serde(default)]
+            data_cipher: Option<String>,
         }
 
         let helper = TreeStateHelper::deserialize(deserializer)?;
@@ -111,10 +390,374 @@ where
             incremental_updates: helper.incremental_updates,
             stored_deltas: helper.stored_deltas,
             delta_chains: helper.delta_chains,
+            root_history: helper.root_history,
+            redactions: helper.redactions,
+            data_cipher: helper.data_cipher,
         })
     }
 }
 
+#[cfg(feature = "storage")]
+impl<H> TreeState<H>
+where
+    H: crate::traits::MaybeSerde,
+{
+    /// Load and deserialize a tree state saved under `key`
+    ///
+    /// Reads states in any [`StateCodec`](crate::state_codec::StateCodec).
+    /// Fields added by newer versions are ignored; see [`load_from_with`](Self::load_from_with).
+    pub fn load_from(
+        storage: &(impl StorageBackend + ?Sized),
+        key: &str,
+    ) -> core::result::Result<Self, ChronoMerkleError> {
+        let reader = crate::state_codec::open_state_json(storage, key)?;
+        serde_json::from_reader(std::io::BufReader::new(reader))
+            .map_err(|e| ChronoMerkleError::DeserializationError(e.to_string()))
+    }
+
+    /// Load a tree state saved under `key`, treating unknown fields as `unknown` says
+    pub fn load_from_with(
+        storage: &(impl StorageBackend + ?Sized),
+        key: &str,
+        unknown: crate::serde_impl::UnknownFields,
+    ) -> core::result::Result<Self, ChronoMerkleError> {
+        if unknown == crate::serde_impl::UnknownFields::Ignore {
+            return Self::load_from(storage, key);
+        }
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut crate::state_codec::open_state_json(storage, key)?, &mut bytes)
+            .map_err(|e| ChronoMerkleError::DeserializationError(e.to_string()))?;
+        crate::serde_impl::from_json(&bytes, unknown)
+    }
+
+    /// Serialize and save this tree state under `key`
+    pub fn save_to(
+        &self,
+        storage: &mut (impl StorageBackend + ?Sized),
+        key: &str,
+    ) -> core::result::Result<(), ChronoMerkleError> {
+        let serialized =
+            serde_json::to_vec(self).map_err(|e| ChronoMerkleError::SerializationError(e.to_string()))?;
+        storage.save(key, &serialized)
+    }
+}
+
+/// A changed or added node in a [`StateDiff`], addressed by tree level
+#[cfg(feature = "storage")]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(bound(serialize = "H: serde::Serialize", deserialize = "H: serde::de::DeserializeOwned"))]
+pub struct NodeChange<H: AsRef<[u8]> + Clone> {
+    /// Level of the node (0 = leaves)
+    pub level: usize,
+    /// Position of the node within its level
+    pub index: usize,
+    /// The node in the target state
+    pub node: Node<H>,
+}
+
+/// Difference between two tree states
+///
+/// Leaves and internal nodes are compared level by level, so appending leaves
+/// only transfers the new leaves and the O(log n) internal nodes above them.
+/// Stored deltas are encoded as a number of pruned oldest deltas plus the newly
+/// appended ones.
+#[cfg(feature = "storage")]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(bound(serialize = "H: serde::Serialize", deserialize = "H: serde::de::DeserializeOwned"))]
+pub struct StateDiff<H: AsRef<[u8]> + Clone> {
+    /// Root of the state the diff applies to
+    pub base_root: Option<H>,
+    /// Number of nodes in the base state
+    pub base_node_count: usize,
+    /// Root of the state produced by applying the diff
+    pub target_root: Option<H>,
+    /// Number of nodes on each level of the target state
+    pub level_sizes: Vec<usize>,
+    /// Nodes that differ from, or are missing in, the base state
+    pub changed_nodes: Vec<NodeChange<H>>,
+    /// Number of deltas dropped from the front of the base state's delta list
+    pub deltas_dropped: usize,
+    /// Deltas appended after the retained base deltas
+    pub appended_deltas: Vec<Node<H>>,
+    /// Target leaf count
+    pub leaf_count: usize,
+    /// Target sparse index
+    pub sparse_index: SparseIndex,
+    /// Target configuration
+    pub config: TreeConfig,
+    /// Target incremental update flag
+    pub incremental_updates: bool,
+    /// Target delta chain index
+    pub delta_chains: SparseIndex,
+    /// Number of root history records cut from the end of the base state's history
+    #[serde(default)]
+    pub root_history_dropped: usize,
+    /// Root history records appended after the retained ones
+    #[serde(default)]
+    pub appended_root_history: Vec<crate::root_history::RootRecord<H>>,
+}
+
+#[cfg(feature = "storage")]
+impl<H> StateDiff<H>
+where
+    H: AsRef<[u8]> + Clone + PartialEq + crate::traits::MaybeSerde,
+{
+    /// Compute the diff turning `base` into `target`
+    pub fn between(base: &TreeState<H>, target: &TreeState<H>) -> Self {
+        let base_levels = level_sizes(base);
+        let target_levels = level_sizes(target);
+
+        let mut changed_nodes = Vec::new();
+        let (mut base_offset, mut target_offset) = (0, 0);
+        for (level, &size) in target_levels.iter().enumerate() {
+            let base_size = base_levels.get(level).copied().unwrap_or(0);
+            for index in 0..size {
+                let node = &target.nodes[target_offset + index];
+                let unchanged = index < base_size && base.nodes[base_offset + index] == *node;
+                if !unchanged {
+                    changed_nodes.push(NodeChange { level, index, node: node.clone() });
+                }
+            }
+            base_offset += base_size;
+            target_offset += size;
+        }
+
+        // Deltas are appended and pruned oldest-first; find the longest retained run
+        let deltas_dropped = (0..=base.stored_deltas.len())
+            .find(|&dropped| {
+                let retained = &base.stored_deltas[dropped..];
+                retained.len() <= target.stored_deltas.len()
+                    && retained[..] == target.stored_deltas[..retained.len()]
+            })
+            .unwrap_or(base.stored_deltas.len());
+        let retained = base.stored_deltas.len() - deltas_dropped;
+
+        // Root history is appended and cut back on rollback; keep the common prefix
+        let root_history_retained = base
+            .root_history
+            .iter()
+            .zip(&target.root_history)
+            .take_while(|(base, target)| base == target)
+            .count();
+
+        Self {
+            base_root: base.nodes.last().map(|n| n.hash()),
+            base_node_count: base.nodes.len(),
+            target_root: target.nodes.last().map(|n| n.hash()),
+            level_sizes: target_levels,
+            changed_nodes,
+            deltas_dropped,
+            appended_deltas: target.stored_deltas[retained..].to_vec(),
+            leaf_count: target.leaf_count,
+            sparse_index: target.sparse_index.clone(),
+            config: target.config.clone(),
+            incremental_updates: target.incremental_updates,
+            delta_chains: target.delta_chains.clone(),
+            root_history_dropped: base.root_history.len() - root_history_retained,
+            appended_root_history: target.root_history[root_history_retained..].to_vec(),
+        }
+    }
+
+    /// Check whether the diff changes nothing but metadata
+    pub fn is_empty(&self) -> bool {
+        self.changed_nodes.is_empty()
+            && self.deltas_dropped == 0
+            && self.appended_deltas.is_empty()
+            && self.root_history_dropped == 0
+            && self.appended_root_history.is_empty()
+    }
+
+    /// Apply the diff to `base`, which must be the state the diff was computed from
+    pub fn apply(&self, base: &mut TreeState<H>) -> core::result::Result<(), ChronoMerkleError> {
+        if base.nodes.len() != self.base_node_count || base.nodes.last().map(|n| n.hash()) != self.base_root {
+            return Err(ChronoMerkleError::StorageError {
+                reason: "State diff does not apply to this base state".to_string(),
+            });
+        }
+        if self.deltas_dropped > base.stored_deltas.len() {
+            return Err(ChronoMerkleError::StorageError {
+                reason: "State diff drops more deltas than the base state holds".to_string(),
+            });
+        }
+        if self.root_history_dropped > base.root_history.len() {
+            return Err(ChronoMerkleError::StorageError {
+                reason: "State diff drops more root history than the base state holds".to_string(),
+            });
+        }
+
+        let base_levels = level_sizes(base);
+        let mut levels: Vec<Vec<Node<H>>> = Vec::with_capacity(self.level_sizes.len());
+        let mut base_offset = 0;
+        for (level, &size) in self.level_sizes.iter().enumerate() {
+            let base_size = base_levels.get(level).copied().unwrap_or(0);
+            let kept = base_size.min(size);
+            levels.push(base.nodes[base_offset..base_offset + kept].to_vec());
+            base_offset += base_size;
+        }
+
+        let mut changes = self.changed_nodes.iter().peekable();
+        for (level, nodes) in levels.iter_mut().enumerate() {
+            while let Some(change) = changes.next_if(|c| c.level == level) {
+                match change.index.cmp(&nodes.len()) {
+                    core::cmp::Ordering::Less => nodes[change.index] = change.node.clone(),
+                    core::cmp::Ordering::Equal => nodes.push(change.node.clone()),
+                    core::cmp::Ordering::Greater => {
+                        return Err(ChronoMerkleError::StorageError {
+                            reason: format!("State diff skips node {} on level {}", nodes.len(), level),
+                        });
+                    }
+                }
+            }
+            if nodes.len() != self.level_sizes[level] {
+                return Err(ChronoMerkleError::StorageError {
+                    reason: format!("State diff is missing nodes on level {}", level),
+                });
+            }
+        }
+
+        base.nodes = levels.into_iter().flatten().collect();
+        base.stored_deltas.drain(..self.deltas_dropped);
+        base.stored_deltas.extend(self.appended_deltas.iter().cloned());
+        base.root_history.truncate(base.root_history.len() - self.root_history_dropped);
+        base.root_history.extend(self.appended_root_history.iter().cloned());
+        base.leaf_count = self.leaf_count;
+        base.sparse_index = self.sparse_index.clone();
+        base.config = self.config.clone();
+        base.incremental_updates = self.incremental_updates;
+        base.delta_chains = self.delta_chains.clone();
+
+        if base.nodes.last().map(|n| n.hash()) != self.target_root {
+            return Err(ChronoMerkleError::StorageError {
+                reason: "Root after applying state diff does not match the target root".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Split a state's nodes into per-level sizes, falling back to a single level
+/// when the node count doesn't match the standard layout
+#[cfg(feature = "storage")]
+fn level_sizes<H>(state: &TreeState<H>) -> Vec<usize>
+where
+    H: crate::traits::MaybeSerde,
+{
+    let mut sizes = Vec::new();
+    let mut count = state.leaf_count;
+    if count > 0 {
+        sizes.push(count);
+        while count > 1 {
+            count = count.div_ceil(2);
+            sizes.push(count);
+        }
+    }
+    if sizes.iter().sum::<usize>() == state.nodes.len() {
+        sizes
+    } else {
+        vec![state.nodes.len()]
+    }
+}
+
+/// Compute the diff between the tree states saved under `key_a` and `key_b`
+#[cfg(feature = "storage")]
+pub fn diff_states<H>(
+    storage: &(impl StorageBackend + ?Sized),
+    key_a: &str,
+    key_b: &str,
+) -> core::result::Result<StateDiff<H>, ChronoMerkleError>
+where
+    H: AsRef<[u8]> + Clone + PartialEq + crate::traits::MaybeSerde,
+{
+    let base = TreeState::load_from(storage, key_a)?;
+    let target = TreeState::load_from(storage, key_b)?;
+    Ok(StateDiff::between(&base, &target))
+}
+
+/// Apply a diff to the tree state saved under `key` and save the result back
+#[cfg(feature = "storage")]
+pub fn apply_state_diff<H>(
+    storage: &mut (impl StorageBackend + ?Sized),
+    key: &str,
+    diff: &StateDiff<H>,
+) -> core::result::Result<(), ChronoMerkleError>
+where
+    H: AsRef<[u8]> + Clone + PartialEq + crate::traits::MaybeSerde,
+{
+    let mut state = TreeState::load_from(storage, key)?;
+    diff.apply(&mut state)?;
+    state.save_to(storage, key)
+}
+
+/// Outcome of a [`gc`] run
+#[cfg(feature = "storage")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Keys found in the backend
+    pub scanned: usize,
+    /// Keys kept because they are live or referenced by a live manifest
+    pub live: usize,
+    /// Keys not referenced by anything, sorted
+    pub orphaned: Vec<String>,
+    /// Keys actually deleted (zero in dry-run mode)
+    pub deleted: usize,
+}
+
+/// Delete storage keys that are not referenced by any live checkpoint
+///
+/// `live_keys` returns the keys to keep. A live key that names a paged tree
+/// (see `save_paged`) also keeps its manifest and every page it references,
+/// and a live chunked state (see [`upgrade`]) keeps its chunks.
+/// With `dry_run` set, orphans are reported but not deleted.
+#[cfg(feature = "storage")]
+pub fn gc<S: StorageBackend + ?Sized>(
+    storage: &mut S,
+    live_keys: impl FnOnce(&S) -> core::result::Result<Vec<String>, ChronoMerkleError>,
+    dry_run: bool,
+) -> core::result::Result<GcReport, ChronoMerkleError> {
+    let live: std::collections::BTreeSet<String> = live_keys(storage)?.into_iter().collect();
+
+    #[cfg(feature = "std")]
+    let live = {
+        let mut expanded = live.clone();
+        for key in &live {
+            let base = key.strip_suffix(".meta").unwrap_or(key);
+            if let Some(pages) = crate::lazy_tree::paged_tree_keys(storage, base)? {
+                expanded.extend(pages);
+            }
+        }
+        expanded
+    };
+
+    let mut live = live;
+    for key in live.clone() {
+        if storage.exists(&key)? {
+            live.extend(crate::state_codec::chunked_state_keys(storage, &key)?.unwrap_or_default());
+        }
+    }
+
+    let keys = storage.list_keys()?;
+    let mut report = GcReport {
+        scanned: keys.len(),
+        ..GcReport::default()
+    };
+    for key in keys {
+        if live.contains(&key) {
+            report.live += 1;
+        } else {
+            report.orphaned.push(key);
+        }
+    }
+    report.orphaned.sort();
+
+    if !dry_run {
+        for key in &report.orphaned {
+            storage.delete(key)?;
+            report.deleted += 1;
+        }
+    }
+    Ok(report)
+}
+
 /// In-memory storage backend for testing and temporary storage
 #[cfg(feature = "storage")]
 pub struct MemoryStorage {
@@ -132,6 +775,13 @@ impl MemoryStorage {
     }
 }
 
+#[cfg(feature = "storage")]
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(feature = "storage")]
 impl StorageBackend for MemoryStorage {
     fn save(&mut self, key: &str, data: &[u8]) -> core::result::Result<(), ChronoMerkleError> {
@@ -155,6 +805,15 @@ impl StorageBackend for MemoryStorage {
     fn exists(&self, key: &str) -> core::result::Result<bool, ChronoMerkleError> {
         Ok(self.data.contains_key(key))
     }
+
+    fn health_check(&self) -> core::result::Result<BackendHealth, ChronoMerkleError> {
+        Ok(BackendHealth {
+            reachable: true,
+            writable: Some(true),
+            latency: core::time::Duration::ZERO,
+            detail: None,
+        })
+    }
 }
 
 /// File-based storage backend for persistent tree storage
@@ -162,13 +821,31 @@ impl StorageBackend for MemoryStorage {
 pub struct FileStorage {
     /// Base directory for storing files
     base_dir: PathBuf,
+    /// Advisory lock held on the directory, if opened with one
+    #[cfg(feature = "file-storage")]
+    lock: Option<crate::file_lock::FileLock>,
 }
 
 #[cfg(all(feature = "storage", feature = "std", not(feature = "no-std")))]
 impl FileStorage {
     /// Create a new FileStorage instance
     pub fn new(base_dir: PathBuf) -> Self {
-        Self { base_dir }
+        Self {
+            base_dir,
+            #[cfg(feature = "file-storage")]
+            lock: None,
+        }
+    }
+
+    /// Reject writes under a shared lock
+    fn check_writable(&self) -> core::result::Result<(), ChronoMerkleError> {
+        #[cfg(feature = "file-storage")]
+        if self.lock.as_ref().is_some_and(|lock| lock.mode() == crate::file_lock::LockMode::Shared) {
+            return Err(ChronoMerkleError::StorageError {
+                reason: format!("Storage directory {} is opened read-only", self.base_dir.display()),
+            });
+        }
+        Ok(())
     }
 
     /// Get the file path for a given key
@@ -177,9 +854,34 @@ impl FileStorage {
     }
 }
 
+#[cfg(all(feature = "file-storage", not(feature = "no-std")))]
+impl FileStorage {
+    /// Open the directory holding an exclusive lock, so no other process can read or write it
+    pub fn open_exclusive(base_dir: PathBuf) -> core::result::Result<Self, ChronoMerkleError> {
+        Self::open_locked(base_dir, crate::file_lock::LockMode::Exclusive)
+    }
+
+    /// Open the directory read-only holding a shared lock, excluding writers
+    pub fn open_shared(base_dir: PathBuf) -> core::result::Result<Self, ChronoMerkleError> {
+        Self::open_locked(base_dir, crate::file_lock::LockMode::Shared)
+    }
+
+    /// Open the directory holding a lock of `mode`, held until the storage is dropped
+    pub fn open_locked(base_dir: PathBuf, mode: crate::file_lock::LockMode) -> core::result::Result<Self, ChronoMerkleError> {
+        let lock = crate::file_lock::FileLock::acquire(&base_dir, mode)?;
+        Ok(Self { base_dir, lock: Some(lock) })
+    }
+
+    /// Get the lock mode held on the directory, if any
+    pub fn lock_mode(&self) -> Option<crate::file_lock::LockMode> {
+        self.lock.as_ref().map(|lock| lock.mode())
+    }
+}
+
 #[cfg(all(feature = "storage", feature = "std", not(feature = "no-std")))]
 impl StorageBackend for FileStorage {
     fn save(&mut self, key: &str, data: &[u8]) -> core::result::Result<(), ChronoMerkleError> {
+        self.check_writable()?;
         let path = self.get_path(key);
         // Ensure the directory exists
         if let Some(parent) = path.parent() {
@@ -206,6 +908,7 @@ impl StorageBackend for FileStorage {
     }
 
     fn delete(&mut self, key: &str) -> core::result::Result<(), ChronoMerkleError> {
+        self.check_writable()?;
         let path = self.get_path(key);
         if path.exists() {
             fs::remove_file(&path).map_err(|e| ChronoMerkleError::StorageError {
@@ -231,8 +934,7 @@ impl StorageBackend for FileStorage {
                 if let Some(file_name) = path.file_name() {
                     if let Some(file_name_str) = file_name.to_str() {
                         // Remove .bin extension
-                        if file_name_str.ends_with(".bin") {
-                            let key = &file_name_str[..file_name_str.len() - 4];
+                        if let Some(key) = file_name_str.strip_suffix(".bin") {
                             keys.push(key.to_string());
                         }
                     }
@@ -245,4 +947,328 @@ impl StorageBackend for FileStorage {
     fn exists(&self, key: &str) -> core::result::Result<bool, ChronoMerkleError> {
         Ok(self.get_path(key).exists())
     }
-}
\ No newline at end of file
+
+    fn save_stream(&mut self, key: &str, reader: &mut dyn std::io::Read) -> core::result::Result<u64, ChronoMerkleError> {
+        self.check_writable()?;
+        let path = self.get_path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| ChronoMerkleError::StorageError {
+                reason: format!("Failed to create directory: {}", e),
+            })?;
+        }
+
+        // Stream into a temporary file and rename, so a failed stream never replaces the old value
+        let temp_path = path.with_extension("bin.tmp");
+        let written = fs::File::create(&temp_path)
+            .map(std::io::BufWriter::new)
+            .and_then(|mut file| {
+                let written = std::io::copy(reader, &mut file)?;
+                std::io::Write::flush(&mut file)?;
+                Ok(written)
+            })
+            .and_then(|written| fs::rename(&temp_path, &path).map(|_| written));
+        written.map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            ChronoMerkleError::StorageError {
+                reason: format!("Failed to write file {}: {}", path.display(), e),
+            }
+        })
+    }
+
+    fn load_stream(&self, key: &str) -> core::result::Result<Option<Box<dyn std::io::Read + '_>>, ChronoMerkleError> {
+        let path = self.get_path(key);
+        match fs::File::open(&path) {
+            Ok(file) => Ok(Some(Box::new(std::io::BufReader::new(file)))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(ChronoMerkleError::StorageError {
+                reason: format!("Failed to read file {}: {}", path.display(), e),
+            }),
+        }
+    }
+
+    fn health_check(&self) -> core::result::Result<BackendHealth, ChronoMerkleError> {
+        let started = std::time::Instant::now();
+        let reachable = fs::metadata(&self.base_dir).map(|meta| meta.is_dir());
+        let mut health = BackendHealth {
+            reachable: matches!(reachable, Ok(true)),
+            writable: Some(false),
+            latency: core::time::Duration::ZERO,
+            detail: match reachable {
+                Ok(true) => None,
+                Ok(false) => Some(format!("{} is not a directory", self.base_dir.display())),
+                Err(e) => Some(format!("Cannot access {}: {}", self.base_dir.display(), e)),
+            },
+        };
+
+        if health.reachable {
+            if let Err(e) = self.check_writable() {
+                health.detail = Some(e.to_string());
+            } else {
+                // Probe with a file list_keys ignores, so readers never see it
+                let probe = self.base_dir.join(format!(".health-probe-{}", std::process::id()));
+                match fs::write(&probe, b"ok").and_then(|_| fs::remove_file(&probe)) {
+                    Ok(()) => health.writable = Some(true),
+                    Err(e) => health.detail = Some(format!("Write probe failed: {}", e)),
+                }
+            }
+        }
+        health.latency = started.elapsed();
+        Ok(health)
+    }
+}
+#[cfg(all(test, feature = "storage", feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::ChronoMerkleTree;
+
+    fn tree_with(count: u64) -> ChronoMerkleTree {
+        let mut tree = ChronoMerkleTree::default();
+        for i in 0..count {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        tree
+    }
+
+    #[test]
+    fn test_state_diff_transfers_only_new_nodes() {
+        let mut storage = MemoryStorage::new();
+        let mut tree = tree_with(64);
+        tree.save_state(&mut storage, "a").unwrap();
+        tree.insert(b"appended", 2000).unwrap();
+        tree.save_state(&mut storage, "b").unwrap();
+
+        let diff: StateDiff<[u8; 32]> = diff_states(&storage, "a", "b").unwrap();
+        assert!(diff.changed_nodes.len() <= 10);
+        assert!(diff.appended_deltas.len() <= 1);
+
+        let mut replica = MemoryStorage::new();
+        replica.save("tree", &storage.load("a").unwrap().unwrap()).unwrap();
+        apply_state_diff(&mut replica, "tree", &diff).unwrap();
+
+        let restored: ChronoMerkleTree = ChronoMerkleTree::load_state(
+            &replica,
+            "tree",
+            crate::hash::Blake3Hasher,
+            crate::security::NoOpLogger,
+        )
+        .unwrap();
+        assert_eq!(restored.root(), tree.root());
+        assert_eq!(restored.leaf_count(), 65);
+    }
+
+    #[test]
+    fn test_versioned_checkpoints() {
+        let mut storage = MemoryStorage::new();
+        let mut tree = tree_with(2);
+        assert_eq!(tree.save_state_versioned(&mut storage, "tree").unwrap(), Version(1));
+        let first_root = tree.root();
+        tree.insert(b"more", 2000).unwrap();
+        assert_eq!(tree.save_state_versioned(&mut storage, "tree").unwrap(), Version(2));
+        storage.save("tree@vx", b"unrelated").unwrap();
+
+        assert_eq!(storage.list_versions("tree").unwrap(), vec![Version(1), Version(2)]);
+        assert!(storage.list_versions("other").unwrap().is_empty());
+        let restored: ChronoMerkleTree = ChronoMerkleTree::load_state_version(
+            &storage,
+            "tree",
+            Version(1),
+            crate::hash::Blake3Hasher,
+            crate::security::NoOpLogger,
+        )
+        .unwrap();
+        assert_eq!(restored.root(), first_root);
+        assert!(storage.load_version("tree", Version(3)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_namespaced_storage_isolates_trees() {
+        let mut backend = MemoryStorage::new();
+        tree_with(2).save_state(&mut (&mut backend).scoped("a").unwrap(), "state").unwrap();
+        tree_with(3).save_state(&mut (&mut backend).scoped("b").unwrap(), "state").unwrap();
+
+        let mut keys = backend.list_keys().unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["a.state".to_string(), "b.state".to_string()]);
+
+        let b = backend.scoped("b").unwrap();
+        assert_eq!(b.namespace(), "b");
+        assert_eq!(b.list_keys().unwrap(), vec!["state".to_string()]);
+        let restored: ChronoMerkleTree =
+            ChronoMerkleTree::load_state(&b, "state", crate::hash::Blake3Hasher, crate::security::NoOpLogger)
+                .unwrap();
+        assert_eq!(restored.leaf_count(), 3);
+        assert!(!b.exists("missing").unwrap());
+    }
+
+    #[test]
+    fn test_dotted_namespaces_rejected() {
+        let mut backend = MemoryStorage::new();
+        (&mut backend).scoped("a").unwrap().save("b.c", b"in a").unwrap();
+        (&mut backend).scoped("ab").unwrap().save("c", b"in ab").unwrap();
+
+        // "a.b" would otherwise map key "c" onto namespace "a"'s key "b.c"
+        for namespace in ["a.b", "", "."] {
+            assert!(matches!(
+                (&mut backend).scoped(namespace),
+                Err(ChronoMerkleError::InvalidConfiguration { .. })
+            ));
+        }
+
+        let a = (&mut backend).scoped("a").unwrap();
+        assert_eq!(a.list_keys().unwrap(), vec!["b.c".to_string()]);
+        assert!(!a.exists("c").unwrap());
+        let ab = (&mut backend).scoped("ab").unwrap();
+        assert_eq!(ab.list_keys().unwrap(), vec!["c".to_string()]);
+        assert!(!ab.exists("b.c").unwrap());
+    }
+
+    #[test]
+    fn test_states_from_newer_versions_load() {
+        use crate::serde_impl::UnknownFields;
+        let tree = tree_with(5);
+        let mut storage = MemoryStorage::new();
+        tree.save_state(&mut storage, "current").unwrap();
+        let current = storage.load("current").unwrap().unwrap();
+        let loaded: ChronoMerkleTree =
+            ChronoMerkleTree::load_state_with(&storage, "current", crate::hash::Blake3Hasher, crate::security::NoOpLogger, UnknownFields::Reject)
+                .unwrap();
+        assert_eq!(loaded.root(), tree.root());
+
+        // A newer version added a top-level field and a configuration option
+        let mut state: serde_json::Value = serde_json::from_slice(&current).unwrap();
+        state["compaction_epoch"] = serde_json::json!(7);
+        state["config"]["future_option"] = serde_json::json!({ "enabled": true });
+        storage.save("newer", &serde_json::to_vec(&state).unwrap()).unwrap();
+
+        let loaded: ChronoMerkleTree =
+            ChronoMerkleTree::load_state(&storage, "newer", crate::hash::Blake3Hasher, crate::security::NoOpLogger).unwrap();
+        assert_eq!(loaded.root(), tree.root());
+        let err = TreeState::<[u8; 32]>::load_from_with(&storage, "newer", UnknownFields::Reject).err().unwrap();
+        assert!(err.to_string().contains("unknown field `compaction_epoch`"), "{}", err);
+
+        state.as_object_mut().unwrap().remove("compaction_epoch");
+        storage.save("newer", &serde_json::to_vec(&state).unwrap()).unwrap();
+        let err = TreeState::<[u8; 32]>::load_from_with(&storage, "newer", UnknownFields::Reject).err().unwrap();
+        assert!(err.to_string().contains("unknown field `config.future_option`"), "{}", err);
+    }
+
+    #[test]
+    fn test_streaming_save_matches_buffered_save() {
+        let tree = tree_with(300);
+        let mut storage = MemoryStorage::new();
+        tree.save_state(&mut storage, "streamed").unwrap();
+        let buffered = serde_json::to_vec(&tree.extract_state()).unwrap();
+        assert_eq!(storage.load("streamed").unwrap().unwrap(), buffered);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = FileStorage::new(dir.path().to_path_buf());
+        tree.save_state(&mut files, "state").unwrap();
+        assert_eq!(files.list_keys().unwrap(), vec!["state".to_string()]);
+        let restored: ChronoMerkleTree =
+            ChronoMerkleTree::load_state(&files, "state", crate::hash::Blake3Hasher, crate::security::NoOpLogger)
+                .unwrap();
+        assert_eq!(restored.root(), tree.root());
+        assert!(files.load_stream("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_gc_keeps_live_checkpoints_and_pages() {
+        let mut storage = MemoryStorage::new();
+        let tree = tree_with(20);
+        tree.save_paged(&mut storage, "paged", 8).unwrap();
+        tree.save_state(&mut storage, "current").unwrap();
+        tree.save_state_versioned(&mut storage, "current").unwrap();
+        tree.save_state_versioned(&mut storage, "current").unwrap();
+        storage.save("paged.page.99", b"leaked").unwrap();
+
+        let keep_latest = |s: &MemoryStorage| {
+            let latest = s.list_versions("current")?.last().map(|v| v.key_for("current"));
+            Ok(["paged".to_string(), "current".to_string()].into_iter().chain(latest).collect())
+        };
+
+        let report = gc(&mut storage, keep_latest, true).unwrap();
+        assert_eq!(report.orphaned, vec!["current@v1".to_string(), "paged.page.99".to_string()]);
+        assert_eq!(report.deleted, 0);
+        assert!(storage.exists("current@v1").unwrap());
+
+        let report = gc(&mut storage, keep_latest, false).unwrap();
+        assert_eq!(report.deleted, 2);
+        assert_eq!(report.live, report.scanned - 2);
+        let lazy = crate::lazy_tree::LazyTree::open(&storage, "paged", crate::hash::Blake3Hasher).unwrap();
+        assert!(lazy.generate_proof(19).is_ok());
+    }
+
+    #[test]
+    fn test_health_checks() {
+        assert!(MemoryStorage::new().health_check().unwrap().is_healthy());
+
+        let dir = tempfile::tempdir().unwrap();
+        let files = FileStorage::new(dir.path().to_path_buf());
+        let health = files.health_check().unwrap();
+        assert_eq!((health.reachable, health.writable), (true, Some(true)));
+        assert!(files.list_keys().unwrap().is_empty());
+
+        let missing = FileStorage::new(dir.path().join("missing"));
+        let health = missing.health_check().unwrap();
+        assert!(!health.is_healthy());
+        assert!(health.detail.is_some());
+
+        #[cfg(feature = "file-storage")]
+        {
+            let shared = FileStorage::open_shared(dir.path().to_path_buf()).unwrap();
+            assert_eq!(shared.health_check().unwrap().writable, Some(false));
+        }
+    }
+
+    #[test]
+    fn test_state_diff_rejects_wrong_base() {
+        let base = tree_with(4).extract_state();
+        let target = tree_with(5).extract_state();
+        let diff = StateDiff::between(&base, &target);
+
+        let mut other = tree_with(3).extract_state();
+        assert!(diff.apply(&mut other).is_err());
+        let mut same = tree_with(4).extract_state();
+        diff.apply(&mut same).unwrap();
+        assert_eq!(same.nodes.last().map(|n| n.hash()), target.nodes.last().map(|n| n.hash()));
+        assert!(StateDiff::between(&target, &target).is_empty());
+    }
+
+    #[test]
+    fn test_history_persistence() {
+        let config = TreeConfig { record_root_history: true, ..TreeConfig::default() };
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(crate::hash::Blake3Hasher, config).unwrap();
+        for i in 0..6u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        let mut storage = MemoryStorage::new();
+        tree.save_state(&mut storage, "full").unwrap();
+        let base = tree.extract_state();
+        tree.rollback_to_timestamp(1003).unwrap();
+        tree.insert(b"replacement", 2000).unwrap();
+
+        // A restored tree answers for past roots and rolls back without the original
+        let mut restored: ChronoMerkleTree =
+            ChronoMerkleTree::load_state(&storage, "full", crate::hash::Blake3Hasher, crate::security::NoOpLogger).unwrap();
+        assert_eq!(restored.root_history().len(), 6);
+        assert_eq!(restored.root_history().root_at_size(4), tree.root_history().root_at_size(4));
+        restored.rollback_to_timestamp(1003).unwrap();
+        assert_eq!(restored.root_history().len(), 4);
+
+        let diff = StateDiff::between(&base, &tree.extract_state());
+        assert_eq!((diff.root_history_dropped, diff.appended_root_history.len()), (2, 1));
+        let mut replica = base.clone();
+        diff.apply(&mut replica).unwrap();
+        assert_eq!(replica.root_history, tree.root_history().iter().cloned().collect::<Vec<_>>());
+
+        tree.config.persist_deltas = false;
+        tree.config.persist_root_history = false;
+        let state = tree.extract_state();
+        assert!(state.stored_deltas.is_empty() && state.root_history.is_empty());
+        tree.save_state(&mut storage, "bare").unwrap();
+        let bare: ChronoMerkleTree =
+            ChronoMerkleTree::load_state(&storage, "bare", crate::hash::Blake3Hasher, crate::security::NoOpLogger).unwrap();
+        assert_eq!(bare.root(), tree.root());
+        assert!(bare.delta_log().is_empty() && bare.root_history().is_empty());
+    }
+}
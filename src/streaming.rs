@@ -0,0 +1,165 @@
+//! Roots of leaf streams without storing the tree
+//!
+//! A [`StreamingRoot`] computes the root a [`ChronoMerkleTree`](crate::ChronoMerkleTree)
+//! would have over the same leaf hashes while keeping only one hash per
+//! completed perfect subtree, at most one per bit of the leaf count. It suits
+//! pipelines that publish roots but never serve proofs.
+//!
+//! Odd nodes are paired with themselves as in the tree, so the pending
+//! subtrees are only combined when a root is asked for: the smallest is
+//! doubled up to the height of the next one before they are hashed together.
+
+use crate::hash::HashFunction;
+use crate::root_history::RootRecord;
+
+#[cfg(feature = "no-std")]
+use alloc::vec::Vec;
+
+/// Root accumulator over a stream of leaf hashes using O(log n) memory
+#[derive(Debug, Clone)]
+pub struct StreamingRoot<Hasher: HashFunction> {
+    hasher: Hasher,
+    /// Perfect subtrees not yet paired, as `(height, hash)`, tallest first
+    peaks: Vec<(u32, Hasher::Output)>,
+    leaf_count: usize,
+    timestamp_range: Option<(u64, u64)>,
+    epoch_leaves: Option<usize>,
+    epoch_roots: Vec<RootRecord<Hasher::Output>>,
+}
+
+impl<Hasher: HashFunction> StreamingRoot<Hasher> {
+    /// Create an empty accumulator
+    pub fn new(hasher: Hasher) -> Self {
+        Self {
+            hasher,
+            peaks: Vec::new(),
+            leaf_count: 0,
+            timestamp_range: None,
+            epoch_leaves: None,
+            epoch_roots: Vec::new(),
+        }
+    }
+
+    /// Create an accumulator recording the root after every `epoch_leaves` leaves
+    ///
+    /// The recorded roots are returned by [`epoch_roots`](Self::epoch_roots)
+    /// until taken with [`take_epoch_roots`](Self::take_epoch_roots). An
+    /// `epoch_leaves` of 0 records nothing.
+    pub fn with_epochs(hasher: Hasher, epoch_leaves: usize) -> Self {
+        Self { epoch_leaves: Some(epoch_leaves).filter(|&leaves| leaves > 0), ..Self::new(hasher) }
+    }
+
+    /// Append a leaf hash with its timestamp
+    ///
+    /// Timestamps are not hashed, as in the tree; they bound
+    /// [`timestamp_range`](Self::timestamp_range) and date epoch roots.
+    pub fn push(&mut self, hash: Hasher::Output, timestamp: u64) {
+        let mut node = (0, hash);
+        while matches!(self.peaks.last(), Some((height, _)) if *height == node.0) {
+            let (height, left) = self.peaks.pop().expect("peak matched above");
+            node = (height + 1, self.hasher.hash_pair(&left, &node.1));
+        }
+        self.peaks.push(node);
+        self.leaf_count += 1;
+        self.timestamp_range = Some(match self.timestamp_range {
+            Some((start, end)) => (start.min(timestamp), end.max(timestamp)),
+            None => (timestamp, timestamp),
+        });
+
+        if self.epoch_leaves.is_some_and(|leaves| self.leaf_count % leaves == 0) {
+            let root = self.root().expect("at least one leaf pushed");
+            self.epoch_roots.push(RootRecord { tree_size: self.leaf_count, timestamp, root });
+        }
+    }
+
+    /// Hash `data` as a leaf and append it
+    pub fn push_data(&mut self, data: &[u8], timestamp: u64) {
+        let hash = self.hasher.hash(data);
+        self.push(hash, timestamp);
+    }
+
+    /// Root over the leaves pushed so far, `None` before the first leaf
+    pub fn root(&self) -> Option<Hasher::Output> {
+        let mut peaks = self.peaks.iter().rev();
+        let (mut height, mut root) = peaks.next().cloned()?;
+        for (peak_height, peak) in peaks {
+            while height < *peak_height {
+                root = self.hasher.hash_pair(&root, &root);
+                height += 1;
+            }
+            root = self.hasher.hash_pair(peak, &root);
+            height += 1;
+        }
+        Some(root)
+    }
+
+    /// Consume the accumulator and return the root of the whole stream
+    pub fn finalize(self) -> Option<Hasher::Output> {
+        self.root()
+    }
+
+    /// Number of leaves pushed
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Smallest and largest timestamp pushed
+    pub fn timestamp_range(&self) -> Option<(u64, u64)> {
+        self.timestamp_range
+    }
+
+    /// Number of subtree hashes currently held
+    pub fn peak_count(&self) -> usize {
+        self.peaks.len()
+    }
+
+    /// Roots recorded at epoch boundaries, oldest first
+    pub fn epoch_roots(&self) -> &[RootRecord<Hasher::Output>] {
+        &self.epoch_roots
+    }
+
+    /// Remove and return the recorded epoch roots
+    pub fn take_epoch_roots(&mut self) -> Vec<RootRecord<Hasher::Output>> {
+        core::mem::take(&mut self.epoch_roots)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::{Blake3Hasher, ChronoMerkleTree};
+
+    #[test]
+    fn test_matches_tree_root() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::new(Blake3Hasher);
+        let mut stream = StreamingRoot::new(Blake3Hasher);
+        assert_eq!(stream.root(), None);
+        for i in 0..70u64 {
+            let data = format!("leaf{}", i);
+            tree.insert(data.as_bytes(), 1000 + i).unwrap();
+            stream.push_data(data.as_bytes(), 1000 + i);
+            assert_eq!(stream.root(), tree.root(), "{} leaves", i + 1);
+            assert!(stream.peak_count() <= usize::BITS as usize - stream.leaf_count().leading_zeros() as usize);
+        }
+        assert_eq!(stream.timestamp_range(), Some((1000, 1069)));
+        assert_eq!(stream.finalize(), tree.root());
+    }
+
+    #[test]
+    fn test_epoch_roots() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::new(Blake3Hasher);
+        let mut stream = StreamingRoot::with_epochs(Blake3Hasher, 4);
+        let mut expected = Vec::new();
+        for i in 0..10u64 {
+            tree.insert(&i.to_be_bytes(), 1000 + i).unwrap();
+            stream.push(Blake3Hasher.hash(&i.to_be_bytes()), 1000 + i);
+            if (i + 1) % 4 == 0 {
+                expected.push(tree.root().unwrap());
+            }
+        }
+        let epochs = stream.take_epoch_roots();
+        assert_eq!(epochs.iter().map(|epoch| epoch.root).collect::<Vec<_>>(), expected);
+        assert_eq!((epochs[1].tree_size, epochs[1].timestamp), (8, 1007));
+        assert!(stream.epoch_roots().is_empty());
+    }
+}
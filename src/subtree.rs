@@ -0,0 +1,130 @@
+//! Subtree roots committing to time windows
+//!
+//! An application that wants to commit to "all events in epoch E" can publish
+//! the root of the smallest subtree holding them instead of listing every
+//! leaf. [`ChronoMerkleTree::subtree_root_for`] finds that subtree for a
+//! timestamp window and [`ChronoMerkleTree::subtree_proof`] links its root to
+//! the root of the whole tree; the proof verifies with
+//! [`verify_proof`](crate::proof::verify_proof) taking the subtree root in
+//! place of a leaf hash.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::{ChronoProof, ProofStep};
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+use core::ops::Range;
+
+#[cfg(feature = "no-std")]
+use alloc::{string::ToString, vec::Vec};
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Root and leaf range of the smallest subtree holding every leaf stamped in `start..=end`
+    ///
+    /// The subtree may also hold neighbouring leaves outside the window; they
+    /// are part of the returned range. `None` if no leaf falls in the window.
+    pub fn subtree_root_for(&self, start: u64, end: u64) -> Option<(H, Range<usize>)> {
+        let (mut first, mut last) = (usize::MAX, 0);
+        self.search_range(start, end, |index| {
+            first = first.min(index);
+            last = last.max(index);
+        });
+        if first > last {
+            return None;
+        }
+
+        let mut level = 0;
+        while first >> level != last >> level {
+            level += 1;
+        }
+        let position = first >> level;
+        let (offset, _) = self.level_spans()[level];
+        Some((self.nodes[offset + position].hash(), self.subtree_leaves(level, position)))
+    }
+
+    /// Proof linking the root of the subtree over `leaves` to the root of the tree
+    ///
+    /// `leaves` must be a range returned by [`subtree_root_for`](Self::subtree_root_for),
+    /// or more generally the leaves under one node; when several levels cover
+    /// the same leaves, the lowest is meant. The proof's `leaf_index` is the
+    /// position of the subtree root within its level.
+    pub fn subtree_proof(&self, leaves: Range<usize>) -> Result<ChronoProof<H>> {
+        let spans = self.level_spans();
+        let (mut level, mut position) = (0..spans.len())
+            .map(|level| (level, leaves.start >> level))
+            .find(|&(level, position)| {
+                position << level == leaves.start && self.subtree_leaves(level, position) == leaves
+            })
+            .ok_or_else(|| ChronoMerkleError::InvalidProof {
+                message: "Leaf range is not covered by a single subtree".to_string(),
+            })?;
+
+        let (timestamp, _) = self.nodes[spans[level].0 + position].timestamp_info();
+        let mut proof = ChronoProof::new(position, timestamp);
+        proof.leaf_hash_mode = self.config.leaf_hash_mode;
+        while level + 1 < spans.len() {
+            let (offset, width) = spans[level];
+            let sibling = position ^ 1;
+            let step = if position % 2 == 1 {
+                ProofStep::Left(self.nodes[offset + sibling].hash())
+            } else {
+                // The last node of an odd level is paired with itself
+                ProofStep::Right(self.nodes[offset + sibling.min(width - 1)].hash())
+            };
+            proof.add_step(step);
+            level += 1;
+            position /= 2;
+        }
+        Ok(proof)
+    }
+
+    /// Leaves under the node at `position` of `level`
+    fn subtree_leaves(&self, level: usize, position: usize) -> Range<usize> {
+        let start = position << level;
+        start..((position + 1) << level).min(self.leaf_count)
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::proof::verify_proof;
+    use crate::Blake3Hasher;
+
+    #[test]
+    fn test_subtree_root_for_window() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..13u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+        let root = tree.root().unwrap();
+
+        let (hash, leaves) = tree.subtree_root_for(1008, 1011).unwrap();
+        assert_eq!(leaves, 8..12);
+        let proof = tree.subtree_proof(leaves).unwrap();
+        assert_eq!((proof.leaf_index, proof.path.len()), (2, 2));
+        assert!(verify_proof(&proof, &hash, &root, &Blake3Hasher).unwrap());
+
+        // Leaves 9 and 10 only meet four leaves up
+        let (hash, leaves) = tree.subtree_root_for(1009, 1010).unwrap();
+        assert_eq!(leaves, 8..12);
+        assert!(verify_proof(&tree.subtree_proof(leaves).unwrap(), &hash, &root, &Blake3Hasher).unwrap());
+
+        // The unpaired tail and a single leaf
+        for (window, expected) in [((1012, 1020), 12..13), ((1003, 1003), 3..4), ((0, 2000), 0..13)] {
+            let (hash, leaves) = tree.subtree_root_for(window.0, window.1).unwrap();
+            assert_eq!(leaves, expected);
+            assert!(verify_proof(&tree.subtree_proof(leaves).unwrap(), &hash, &root, &Blake3Hasher).unwrap());
+        }
+        assert_eq!(tree.subtree_root_for(1012, 1012).unwrap().0, tree.get_leaf_hash(12).unwrap());
+
+        assert!(tree.subtree_root_for(2000, 3000).is_none());
+        assert!(tree.subtree_proof(3..5).is_err());
+    }
+}
@@ -0,0 +1,396 @@
+//! Security event export over syslog
+//!
+//! [`SyslogLogger`] sends each [`SecurityEvent`] as an RFC 5424 message over
+//! UDP, TCP (octet-counted framing, RFC 6587) or a Unix datagram socket such
+//! as `/dev/log`. The [`SecurityMetadata`] of an event becomes an RFC 5424
+//! structured data element, or with [`SyslogFormat::Cef`] the message body is
+//! an ArcSight Common Event Format record, so events can be ingested by a SIEM
+//! without a custom parser.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::security::{SecurityEvent, SecurityEventType, SecurityLevel, SecurityLogger, SecurityMetadata};
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+
+/// Private enterprise number used in structured data IDs by default
+///
+/// 32473 is reserved for documentation (RFC 5612); deployments with their own
+/// number should set it with [`SyslogLogger::with_enterprise_number`].
+pub const DEFAULT_ENTERPRISE_NUMBER: u32 = 32473;
+
+/// Body format of the sent messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyslogFormat {
+    /// Description as message, metadata as structured data
+    #[default]
+    Rfc5424,
+    /// CEF record as message, without structured data
+    Cef,
+}
+
+/// Syslog facility codes used for the priority value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Facility {
+    /// Security/authorization messages (4)
+    Auth,
+    /// Log audit (13)
+    #[default]
+    Audit,
+    /// Local use 0-7 (16-23)
+    Local(u8),
+}
+
+impl Facility {
+    fn code(self) -> u8 {
+        match self {
+            Facility::Auth => 4,
+            Facility::Audit => 13,
+            Facility::Local(n) => 16 + n.min(7),
+        }
+    }
+}
+
+enum Transport {
+    Udp(UdpSocket),
+    Tcp(Mutex<TcpStream>),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixDatagram),
+}
+
+/// Security logger sending events to a syslog collector
+pub struct SyslogLogger {
+    transport: Transport,
+    format: SyslogFormat,
+    facility: Facility,
+    hostname: String,
+    app_name: String,
+    enterprise_number: u32,
+}
+
+impl SyslogLogger {
+    /// Send one datagram per event to `addr`
+    pub fn udp(addr: impl ToSocketAddrs) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0").and_then(|socket| {
+            socket.connect(addr)?;
+            Ok(socket)
+        });
+        Ok(Self::with_transport(Transport::Udp(socket.map_err(|e| send_error("connect", e))?)))
+    }
+
+    /// Send octet-counted frames over a TCP connection to `addr`
+    pub fn tcp(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr).map_err(|e| send_error("connect", e))?;
+        Ok(Self::with_transport(Transport::Tcp(Mutex::new(stream))))
+    }
+
+    /// Send one datagram per event to the Unix socket at `path`, e.g. `/dev/log`
+    #[cfg(unix)]
+    pub fn unix(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let socket = std::os::unix::net::UnixDatagram::unbound().and_then(|socket| {
+            socket.connect(path)?;
+            Ok(socket)
+        });
+        Ok(Self::with_transport(Transport::Unix(socket.map_err(|e| send_error("connect", e))?)))
+    }
+
+    fn with_transport(transport: Transport) -> Self {
+        Self {
+            transport,
+            format: SyslogFormat::default(),
+            facility: Facility::default(),
+            hostname: "-".to_string(),
+            app_name: "chrono-merkle".to_string(),
+            enterprise_number: DEFAULT_ENTERPRISE_NUMBER,
+        }
+    }
+
+    /// Set the message body format
+    pub fn with_format(mut self, format: SyslogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the facility
+    pub fn with_facility(mut self, facility: Facility) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    /// Set the HOSTNAME header field (`-` by default)
+    pub fn with_hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = hostname.into();
+        self
+    }
+
+    /// Set the APP-NAME header field (`chrono-merkle` by default)
+    pub fn with_app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = app_name.into();
+        self
+    }
+
+    /// Set the enterprise number of the structured data IDs
+    pub fn with_enterprise_number(mut self, enterprise_number: u32) -> Self {
+        self.enterprise_number = enterprise_number;
+        self
+    }
+
+    /// Render `event` as the message this logger would send
+    pub fn format_event(&self, event: &SecurityEvent) -> String {
+        let severity = match event.level {
+            SecurityLevel::Info => 6,
+            SecurityLevel::Warning => 4,
+            SecurityLevel::Critical => 2,
+        };
+        let mut message = format!(
+            "<{}>1 {} {} {} {} {} ",
+            self.facility.code() as u16 * 8 + severity,
+            rfc3339(event.timestamp),
+            header_field(&self.hostname),
+            header_field(&self.app_name),
+            std::process::id(),
+            type_name(&event.event_type),
+        );
+        match self.format {
+            SyslogFormat::Rfc5424 => {
+                message.push_str(&structured_data(event, self.enterprise_number));
+                message.push(' ');
+                message.push_str(&event.description);
+            }
+            SyslogFormat::Cef => {
+                message.push_str("- ");
+                message.push_str(&format_cef(event));
+            }
+        }
+        message
+    }
+}
+
+impl SecurityLogger for SyslogLogger {
+    fn log_event(&self, event: &SecurityEvent) -> Result<()> {
+        let message = self.format_event(event);
+        let sent = match &self.transport {
+            Transport::Udp(socket) => socket.send(message.as_bytes()).map(drop),
+            Transport::Tcp(stream) => {
+                let mut stream = stream.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                write!(stream, "{} {}", message.len(), message).and_then(|_| stream.flush())
+            }
+            #[cfg(unix)]
+            Transport::Unix(socket) => socket.send(message.as_bytes()).map(drop),
+        };
+        sent.map_err(|e| send_error("send", e))
+    }
+}
+
+/// Render `event` as a CEF record
+///
+/// The leaf, proof, configuration, access and validation metadata fields are
+/// mapped to labelled `cs1`..`cs4` custom strings and the correlation ID to `cs6`.
+pub fn format_cef(event: &SecurityEvent) -> String {
+    let severity = match event.level {
+        SecurityLevel::Info => 3,
+        SecurityLevel::Warning => 6,
+        SecurityLevel::Critical => 9,
+    };
+    let mut record = format!(
+        "CEF:0|ClockinChain|chrono-merkle|{}|{}|{}|{}|rt={}",
+        env!("CARGO_PKG_VERSION"),
+        type_name(&event.event_type),
+        cef_header(&event.description),
+        severity,
+        event.timestamp.saturating_mul(1000),
+    );
+    if let Some((category, fields)) = event.metadata.as_ref().map(metadata_fields) {
+        let _ = write!(record, " cat={}", category);
+        for (n, (label, value)) in fields.iter().enumerate() {
+            let _ = write!(record, " cs{n}Label={} cs{n}={}", label, cef_extension(value), n = n + 1);
+        }
+    }
+    if let Some(id) = &event.context {
+        let _ = write!(record, " cs6Label=correlationId cs6={}", cef_extension(id.as_str()));
+    }
+    let _ = write!(record, " msg={}", cef_extension(&event.description));
+    record
+}
+
+/// SD-ID name and parameters of each metadata variant
+fn metadata_fields(metadata: &SecurityMetadata) -> (&'static str, Vec<(&'static str, String)>) {
+    match metadata {
+        SecurityMetadata::LeafInsertion { leaf_index, timestamp, data_hash } => (
+            "leafInsertion",
+            vec![
+                ("leafIndex", leaf_index.to_string()),
+                ("timestamp", timestamp.to_string()),
+                ("dataHash", data_hash.clone()),
+            ],
+        ),
+        SecurityMetadata::ProofVerification { leaf_index, proof_timestamp, verification_result, failure_reason } => {
+            let mut fields = vec![
+                ("leafIndex", leaf_index.to_string()),
+                ("proofTimestamp", proof_timestamp.to_string()),
+                ("result", verification_result.to_string()),
+            ];
+            fields.extend(failure_reason.clone().map(|reason| ("failureReason", reason)));
+            ("proofVerification", fields)
+        }
+        SecurityMetadata::ConfigChange { parameter, old_value, new_value } => (
+            "configChange",
+            vec![
+                ("parameter", parameter.clone()),
+                ("oldValue", old_value.clone()),
+                ("newValue", new_value.clone()),
+            ],
+        ),
+        SecurityMetadata::AccessDenied { client, resource, reason } => (
+            "accessDenied",
+            vec![
+                ("client", client.clone()),
+                ("resource", resource.clone()),
+                ("reason", reason.clone()),
+            ],
+        ),
+        SecurityMetadata::ValidationFailure { input_type, reason, input_value } => {
+            let mut fields = vec![("inputType", input_type.clone()), ("reason", reason.clone())];
+            fields.extend(input_value.clone().map(|value| ("inputValue", value)));
+            ("validationFailure", fields)
+        }
+    }
+}
+
+/// Metadata element, then a `correlation` element carrying the event's context
+fn structured_data(event: &SecurityEvent, enterprise_number: u32) -> String {
+    let context = event.context.as_ref().map(|id| ("correlation", vec![("id", id.to_string())]));
+    let mut data = String::new();
+    for (name, fields) in event.metadata.as_ref().map(metadata_fields).into_iter().chain(context) {
+        let _ = write!(data, "[{}@{}", name, enterprise_number);
+        for (key, value) in fields {
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]");
+            let _ = write!(data, " {}=\"{}\"", key, escaped);
+        }
+        data.push(']');
+    }
+    if data.is_empty() { "-".to_string() } else { data }
+}
+
+fn type_name(event_type: &SecurityEventType) -> String {
+    format!("{:?}", event_type)
+}
+
+/// Header fields are printable ASCII without spaces, `-` when empty
+fn header_field(value: &str) -> String {
+    let field: String = value.chars().filter(|c| c.is_ascii_graphic()).take(48).collect();
+    if field.is_empty() { "-".to_string() } else { field }
+}
+
+fn cef_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn cef_extension(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+/// Format Unix seconds as an RFC 3339 UTC timestamp
+fn rfc3339(timestamp: u64) -> String {
+    let (days, secs) = (timestamp / 86_400, timestamp % 86_400);
+    // Civil date from days since 1970-01-01 (Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+fn send_error(operation: &str, e: std::io::Error) -> ChronoMerkleError {
+    ChronoMerkleError::InvalidConfiguration {
+        parameter: "syslog".to_string(),
+        reason: format!("Failed to {}: {}", operation, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::events;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(rfc3339(1_768_262_400 + 3_723), "2026-01-13T01:02:03Z");
+    }
+
+    #[test]
+    fn test_udp_rfc5424_structured_data() {
+        let collector = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let logger = SyslogLogger::udp(collector.local_addr().unwrap()).unwrap().with_hostname("node-1");
+        let mut event = events::access_denied("10.0.0.7", "/proof/3", "bad \"token\"]");
+        event.timestamp = 0;
+        logger.log_event(&event).unwrap();
+
+        let mut buf = [0u8; 1024];
+        let len = collector.recv(&mut buf).unwrap();
+        let message = std::str::from_utf8(&buf[..len]).unwrap();
+        let expected_header = format!("<108>1 1970-01-01T00:00:00Z node-1 chrono-merkle {} AccessDenied ", std::process::id());
+        assert!(message.starts_with(&expected_header), "{}", message);
+        assert!(message.contains(
+            r#"[accessDenied@32473 client="10.0.0.7" resource="/proof/3" reason="bad \"token\"\]"] "#
+        ));
+        assert!(message.ends_with(&event.description));
+
+        let message = logger.format_event(&event.with_context("req-1".into()));
+        assert!(message.contains(r#"\"\]"][correlation@32473 id="req-1"] "#), "{}", message);
+    }
+
+    #[test]
+    fn test_tcp_cef_framing() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let logger = SyslogLogger::tcp(listener.local_addr().unwrap())
+            .unwrap()
+            .with_format(SyslogFormat::Cef)
+            .with_facility(Facility::Local(3));
+        let (stream, _) = listener.accept().unwrap();
+        let event = events::config_change("sparsity", "4", "8|16");
+        logger.log_event(&event).unwrap();
+        logger.log_event(&events::tree_initialization("x=y")).unwrap();
+
+        let mut reader = BufReader::new(stream);
+        let mut frames = Vec::new();
+        for _ in 0..2 {
+            let mut length = Vec::new();
+            reader.read_until(b' ', &mut length).unwrap();
+            let length: usize = std::str::from_utf8(&length).unwrap().trim().parse().unwrap();
+            let mut frame = vec![0u8; length];
+            reader.read_exact(&mut frame).unwrap();
+            frames.push(String::from_utf8(frame).unwrap());
+        }
+
+        assert!(frames[0].starts_with("<158>1 "));
+        let cef = &frames[0][frames[0].find("CEF:0|").unwrap()..];
+        assert!(cef.starts_with(&format!("CEF:0|ClockinChain|chrono-merkle|{}|ConfigurationChange|", env!("CARGO_PKG_VERSION"))));
+        assert!(cef.contains("cat=configChange cs1Label=parameter cs1=sparsity"));
+        assert!(cef.contains("cs3Label=newValue cs3=8|16"));
+        assert!(frames[1].contains("|TreeInitialization|") && frames[1].contains("x\\=y"));
+    }
+}
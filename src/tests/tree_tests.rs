@@ -36,6 +36,19 @@ fn test_multiple_inserts() {
     assert!(tree.root().is_some());
 }
 
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_insert_with_receipt() {
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+    let first = tree.insert_with_receipt(b"data1", 1000).unwrap();
+    assert_eq!((first.leaf_index, first.timestamp, first.old_root), (0, 1000, None));
+    let second = tree.insert_with_receipt(b"data2", 1001).unwrap();
+    assert_eq!(second.leaf_index, 1);
+    assert_eq!(second.old_root, Some(first.new_root));
+    assert_eq!(tree.root(), Some(second.new_root));
+    assert!(tree.insert_with_receipt(b"", 1002).is_err());
+}
+
 #[cfg(all(feature = "blake3-hash", not(feature = "no-std")))]
 #[test]
 fn test_find_by_timestamp() {
@@ -127,4 +140,395 @@ fn test_visualize_empty_tree() {
     let json = tree.visualize_json().unwrap();
     assert!(json.contains("\"type\": \"empty\""));
     assert!(json.contains("\"leaf_count\": 0"));
-}
\ No newline at end of file
+}
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_insert_batch_matches_single_inserts() {
+    let mut single: ChronoMerkleTree = ChronoMerkleTree::default();
+    let mut batched: ChronoMerkleTree = ChronoMerkleTree::default();
+    single.insert(b"seed", 999).unwrap();
+    batched.insert(b"seed", 999).unwrap();
+
+    let data: Vec<Vec<u8>> = (0..7).map(|i| format!("data{}", i).into_bytes()).collect();
+    let entries: Vec<(&[u8], u64)> = data.iter().enumerate().map(|(i, d)| (d.as_slice(), 1000 + i as u64)).collect();
+    for (d, ts) in &entries {
+        single.insert(d, *ts).unwrap();
+    }
+    batched.insert_batch(&entries).unwrap();
+
+    assert_eq!(single.root(), batched.root());
+    assert_eq!(batched.leaf_count(), 8);
+    assert_eq!(batched.find_range(1000, 1006).len(), 7);
+
+    // An invalid entry rejects the whole batch
+    let before = batched.root();
+    assert!(batched.insert_batch(&[(b"ok", 2000), (b"", 2001)]).is_err());
+    assert_eq!(batched.root(), before);
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_leaf_data_accessors() {
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+    tree.insert(b"data1", 1000).unwrap();
+    tree.insert(b"data2", 1001).unwrap();
+
+    assert_eq!(tree.get_leaf_data(1).unwrap(), crate::LeafData::Present(&b"data2"[..]));
+    assert!(tree.get_leaf_data(2).is_err());
+
+    let entries: Vec<_> = tree.leaf_entries().map(|(timestamp, data)| (timestamp, data.data())).collect();
+    assert_eq!(entries, vec![(1000, Some(&b"data1"[..])), (1001, Some(&b"data2"[..]))]);
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_position_of() {
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+    tree.insert(b"data1", 1000).unwrap();
+    tree.insert(b"data2", 1001).unwrap();
+    tree.insert(b"other", 1001).unwrap();
+
+    assert_eq!(tree.position_of(b"data1", 1000), Some(0));
+    // The index points at the later leaf with this timestamp
+    assert_eq!(tree.position_of(b"data2", 1001), Some(1));
+    assert_eq!(tree.position_of(b"other", 1001), Some(2));
+    assert_eq!(tree.position_of(b"data1", 1001), None);
+
+    let index = tree.position_of(b"data2", 1001).unwrap();
+    assert!(tree.verify_proof(&tree.generate_proof(index).unwrap()).unwrap());
+}
+
+#[cfg(all(feature = "blake3-hash", feature = "parallel"))]
+#[test]
+fn test_parallel_verification_replaces_divergent_nodes() {
+    use crate::security::{SecurityEvent, SecurityLevel, SecurityLogger};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<SecurityEvent>>>);
+
+    impl SecurityLogger for Recorder {
+        fn log_event(&self, event: &SecurityEvent) -> crate::error::Result<()> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    let config = crate::config::TreeConfig {
+        parallel_construction: true,
+        parallel_verify_every: Some(1),
+        ..Default::default()
+    };
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut tree = ChronoMerkleTree::with_config_and_logger(Blake3Hasher, config, Recorder(events.clone())).unwrap();
+    let mut reference: ChronoMerkleTree = ChronoMerkleTree::default();
+    for i in 0..7u64 {
+        let data = format!("data{}", i);
+        tree.insert(data.as_bytes(), 1000 + i).unwrap();
+        reference.insert(data.as_bytes(), 1000 + i).unwrap();
+    }
+    assert_eq!(tree.root(), reference.root());
+    let critical = |events: &Arc<Mutex<Vec<SecurityEvent>>>| {
+        events.lock().unwrap().iter().filter(|e| e.level == SecurityLevel::Critical).count()
+    };
+    assert_eq!(critical(&events), 0);
+
+    // A corrupted parallel result is detected and replaced by the sequential one
+    let mut parents: Vec<_> = tree.nodes.level(1).to_vec();
+    parents[2] = crate::node::Node::internal([0u8; 32], [0u8; 32], [0u8; 32], (0, 0));
+    tree.verify_parallel_level(1, &mut parents);
+    assert_eq!(parents, tree.nodes.level(1).to_vec());
+    assert_eq!(critical(&events), 1);
+
+    let invalid = crate::config::TreeConfig { parallel_verify_every: Some(0), ..Default::default() };
+    assert!(invalid.validate().is_err());
+}
+
+#[cfg(all(feature = "blake3-hash", not(feature = "minimal")))]
+#[test]
+fn test_sampled_insert_logging() {
+    use crate::security::{NoOpLogger, SamplingLogger, SamplingPolicy, SecurityEventType};
+
+    let policy = SamplingPolicy::default().with_rate(SecurityEventType::LeafInsertion, 100);
+    let logger = SamplingLogger::new(NoOpLogger, policy);
+    let mut tree = ChronoMerkleTree::with_config_and_logger(Blake3Hasher, Default::default(), logger).unwrap();
+    for i in 0..250u64 {
+        tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+    }
+    let entries: Vec<(Vec<u8>, u64)> = (250..300u64).map(|i| (format!("data{}", i).into_bytes(), 1000 + i)).collect();
+    let batch: Vec<(&[u8], u64)> = entries.iter().map(|(data, ts)| (data.as_slice(), *ts)).collect();
+    tree.insert_batch(&batch).unwrap();
+
+    assert_eq!(tree.security_logger.suppressed(&SecurityEventType::LeafInsertion), 297);
+    assert_eq!(tree.security_logger.suppressed(&SecurityEventType::TreeInitialization), 0);
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_constant_time_proofs() {
+    let config = crate::config::TreeConfig { constant_time_proofs: true, ..Default::default() };
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+    for i in 0..5u64 {
+        tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+    }
+
+    let proof = tree.generate_proof(3).unwrap();
+    assert!(tree.verify_proof(&proof).unwrap());
+
+    let mut wrong_timestamp = proof.clone();
+    wrong_timestamp.timestamp += 1;
+    assert!(!tree.verify_proof(&wrong_timestamp).unwrap());
+
+    let mut truncated = proof.clone();
+    truncated.path.pop();
+    assert!(!tree.verify_proof(&truncated).unwrap());
+
+    // Malformed delta steps fail without an error naming the step
+    let mut malformed = proof;
+    let root = tree.root().unwrap();
+    malformed.add_step(crate::proof::ProofStep::Delta(root, root));
+    assert!(!tree.verify_proof(&malformed).unwrap());
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_sparse_index_every_nth_leaf() {
+    let config = crate::config::TreeConfig { sparse_index_sparsity: 3, ..Default::default() };
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+    // Irregular timestamps, none a multiple of the sparsity
+    for i in 0..7u64 {
+        tree.insert(format!("data{}", i).as_bytes(), 1001 + 7 * i).unwrap();
+    }
+    assert_eq!(tree.sparse_index.find_range(0, u64::MAX), vec![0, 3, 6]);
+    assert_eq!(tree.position_of(b"data4", 1029), Some(4));
+
+    tree.reindex(1).unwrap();
+    assert_eq!(tree.sparse_index.len(), 7);
+    assert_eq!(tree.config.sparse_index_sparsity, 1);
+    tree.rollback_to_timestamp(1015).unwrap();
+    assert_eq!(tree.leaf_count(), 3);
+    assert!(tree.reindex(0).is_err());
+
+    let budget = crate::config::TreeConfig { sparse_index_max_entries: Some(2), ..Default::default() };
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, budget).unwrap();
+    for i in 0..8u64 {
+        tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+    }
+    assert!(tree.sparse_index.len() <= 2);
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_find_range_with_sparse_index() {
+    let config = crate::config::TreeConfig { sparse_index_sparsity: 4, ..Default::default() };
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+    for i in 0..20u64 {
+        // Pairs of leaves share a timestamp
+        tree.insert(format!("data{}", i).as_bytes(), 1000 + i / 2).unwrap();
+    }
+
+    assert_eq!(tree.find_range(1003, 1005), vec![6, 7, 8, 9, 10, 11]);
+    assert_eq!(tree.find_by_timestamp(1004), vec![8, 9]);
+    assert_eq!(tree.find_range(900, 2000).len(), 20);
+
+    // Out-of-order inserts fall back to descending the tree
+    tree.insert(b"late", 1001).unwrap();
+    assert!(!tree.sparse_index.is_ordered());
+    assert_eq!(tree.find_by_timestamp(1001), vec![2, 3, 20]);
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_reverse_delta_rollback() {
+    let build = |count: u64| {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..count {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        tree
+    };
+
+    // Restored internal nodes match a tree built from the kept leaves
+    let mut tree = build(7);
+    tree.rollback_to_timestamp(1003).unwrap();
+    assert_eq!(tree.nodes.to_vec(), build(4).nodes.to_vec());
+    #[cfg(not(feature = "minimal"))]
+    assert_eq!(tree.delta_log().len(), 3);
+    tree.insert(b"data4", 1004).unwrap();
+    assert_eq!(tree.root(), build(5).root());
+
+    // A delta restores its base state even after later appends
+    let mut tree = build(6);
+    tree.insert_batch(&[(b"data6", 1006), (b"data7", 1007), (b"data8", 1008)]).unwrap();
+    let delta = tree.get_all_deltas()[2].clone();
+    tree.apply_delta_rollback(&delta).unwrap();
+    assert_eq!(tree.nodes.to_vec(), build(3).nodes.to_vec());
+    #[cfg(not(feature = "minimal"))]
+    {
+        assert_eq!(tree.delta_log().len(), 2);
+        assert!(tree.delta_log().verify_chain(&build(1).root().unwrap()));
+    }
+
+    // Deltas without reverse information still drop the last leaf
+    let legacy = crate::node::Node::delta([0u8; 32], [0u8; 32], 1002);
+    tree.apply_delta_rollback(&legacy).unwrap();
+    assert_eq!(tree.root(), build(2).root());
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_known_answer_root_and_checksum() {
+    // Fixed vectors: any host byte order or pointer width must reproduce them
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+    for i in 0..5u64 {
+        tree.insert(&crate::encoding::encode_u64(i), 1_700_000_000 + i).unwrap();
+    }
+    assert_eq!(
+        hex::encode(tree.root().unwrap()),
+        "471d754f2146af522115520bd1c27b2260bc620adb1322205411689cef2b08fd"
+    );
+    #[cfg(not(feature = "minimal"))]
+    assert_eq!(
+        hex::encode(tree.generate_manifest().leaf_checksum),
+        "6879a39e47aeb919c08bde4e8fcd4f3f012d50faeb7cfd80723f97d63e2dc980"
+    );
+}
+
+#[cfg(feature = "blake3-hash")]
+fn check_digest_size<const N: usize>() {
+    use crate::hash::{Digest, Fixed};
+
+    let mut tree: ChronoMerkleTree<Digest<N>, Fixed<Blake3Hasher, N>> = ChronoMerkleTree::default();
+    for i in 0..9u64 {
+        tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+    }
+    assert_eq!(tree.root().unwrap().as_ref().len(), N);
+    let proof = tree.generate_proof(6).unwrap();
+    assert!(tree.verify_proof(&proof).unwrap());
+    let (leaf, hasher) = (tree.get_leaf_hash(6).unwrap(), Fixed::<Blake3Hasher, N>(Blake3Hasher));
+    let compressed = crate::CompressedProof::<Digest<N>>::from_bytes(&proof.compress(&leaf, &hasher).to_bytes()).unwrap();
+    assert!(tree.verify_proof(&crate::ChronoProof::decompress(&compressed, &leaf, &hasher).unwrap()).unwrap());
+    let json = serde_json::to_string(&proof).unwrap();
+    assert!(tree.verify_proof(&serde_json::from_str::<crate::ChronoProof<Digest<N>>>(&json).unwrap()).unwrap());
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_const_generic_digest_sizes() {
+    check_digest_size::<16>();
+    check_digest_size::<20>();
+    check_digest_size::<48>();
+    check_digest_size::<64>();
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_prehashed_leaves() {
+    use crate::config::{LeafHashMode, TreeConfig};
+    use crate::hash::HashFunction;
+    use crate::operations::PrehashedLeaf;
+
+    let config = TreeConfig { leaf_hash_mode: LeafHashMode::TakeAsIs, ..Default::default() };
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+    let txids: Vec<[u8; 32]> = (0..5u8).map(|i| Blake3Hasher.hash(&[i])).collect();
+    for (i, txid) in txids.iter().enumerate() {
+        tree.insert_prehashed(PrehashedLeaf::new(*txid, 1000 + i as u64)).unwrap();
+    }
+    assert_eq!(tree.get_leaf_hash(3).unwrap(), txids[3]);
+    assert!(tree.insert(b"raw", 2000).is_err());
+
+    let proof = tree.generate_proof(3).unwrap();
+    assert_eq!(proof.leaf_hash_mode, LeafHashMode::TakeAsIs);
+    assert!(tree.verify_proof(&proof).unwrap());
+    assert!(crate::proof::verify_proof(&proof, &txids[3], &tree.root().unwrap(), &Blake3Hasher).unwrap());
+
+    let compressed = proof.compress(&txids[3], &Blake3Hasher);
+    let restored = crate::CompressedProof::<[u8; 32]>::from_bytes(&compressed.to_bytes()).unwrap();
+    assert_eq!(restored.leaf_hash_mode, LeafHashMode::TakeAsIs);
+    // Only prehashed proofs carry the extra mode byte
+    let mut unmarked = compressed.clone();
+    unmarked.leaf_hash_mode = LeafHashMode::HashData;
+    assert_eq!(compressed.to_bytes().len(), unmarked.to_bytes().len() + 1);
+
+    // A proof claiming the data was hashed does not verify against this tree
+    let mut relabeled = proof.clone();
+    relabeled.leaf_hash_mode = LeafHashMode::HashData;
+    assert!(!tree.verify_proof(&relabeled).unwrap());
+
+    let mut hashing: ChronoMerkleTree = ChronoMerkleTree::default();
+    assert!(hashing.insert_prehashed(PrehashedLeaf::new(txids[0], 1000)).is_err());
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_append_hashes_one_path() {
+    use crate::hash::HashFunction;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct CountingHasher(Arc<AtomicUsize>);
+
+    impl HashFunction for CountingHasher {
+        type Output = [u8; 32];
+
+        fn name(&self) -> &str {
+            "counting-blake3"
+        }
+
+        fn hash(&self, data: &[u8]) -> [u8; 32] {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Blake3Hasher.hash(data)
+        }
+    }
+
+    let hasher = CountingHasher::default();
+    let mut tree: ChronoMerkleTree<[u8; 32], CountingHasher> = ChronoMerkleTree::new(hasher.clone());
+    let mut leaves = Vec::new();
+    for i in 0..5000u64 {
+        let calls = hasher.0.swap(0, Ordering::Relaxed);
+        if i > 1 {
+            // 13 levels above 5000 leaves, plus the leaf and the delta hash
+            assert!(calls <= 15, "{} hashes for insert {}", calls, i);
+        }
+        tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        leaves.push(Blake3Hasher.hash(&i.to_le_bytes()));
+    }
+    assert_eq!(tree.root(), crate::spec::root(&Blake3Hasher, &leaves));
+}
+
+#[cfg(feature = "blake3-hash")]
+#[test]
+fn test_append_touches_one_node_per_level() {
+    use crate::hash::HashFunction;
+
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+    for i in 0..1000u64 {
+        tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+    }
+    tree.reserve(1000);
+    let addresses = |tree: &ChronoMerkleTree| {
+        (0..tree.nodes.depth())
+            .map(|level| tree.nodes.level(level).iter().map(core::ptr::from_ref).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    };
+    let reserved = addresses(&tree);
+
+    for i in 1000..2000u64 {
+        let before: Vec<Vec<_>> = (0..tree.nodes.depth()).map(|level| tree.nodes.level(level).to_vec()).collect();
+        tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        // Each level replaces at most its last node and pushes at most one
+        for (level, old) in before.iter().enumerate() {
+            let new = tree.nodes.level(level);
+            let kept = old.len().saturating_sub(1);
+            assert!(new.len() <= old.len() + 1, "level {} grew by {}", level, new.len() - old.len());
+            assert!(new.iter().take(kept).eq(&old[..kept]), "insert {} rewrote level {}", i, level);
+        }
+    }
+    // No node was reallocated or moved
+    for (level, (now, before)) in addresses(&tree).iter().zip(&reserved).enumerate() {
+        assert_eq!(now[..before.len()], before[..], "level {} moved", level);
+    }
+    let leaves: Vec<_> = (0..2000u64).map(|i| Blake3Hasher.hash(&i.to_le_bytes())).collect();
+    assert_eq!(tree.root(), crate::spec::root(&Blake3Hasher, &leaves));
+}
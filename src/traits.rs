@@ -19,6 +19,26 @@ impl Timestamp for u64 {
     }
 }
 
+/// Serde bounds required of hash outputs when the `serde` feature is enabled
+///
+/// With `serde` this is `Serialize + DeserializeOwned`; without it every type
+/// qualifies, so minimal builds can use custom hash types that do not
+/// implement serde.
+#[cfg(feature = "serde")]
+pub trait MaybeSerde: serde::Serialize + serde::de::DeserializeOwned {}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> MaybeSerde for T {}
+
+/// Serde bounds required of hash outputs when the `serde` feature is enabled
+///
+/// The `serde` feature is disabled, so every type qualifies.
+#[cfg(not(feature = "serde"))]
+pub trait MaybeSerde {}
+
+#[cfg(not(feature = "serde"))]
+impl<T> MaybeSerde for T {}
+
 /// Trait for types that can be used as hash outputs
 pub trait HashOutput: AsRef<[u8]> + Clone + Eq + core::fmt::Debug {
     /// Get the hash as bytes
@@ -28,18 +48,22 @@ pub trait HashOutput: AsRef<[u8]> + Clone + Eq + core::fmt::Debug {
     fn from_bytes(bytes: &[u8]) -> Option<Self>;
 }
 
-impl HashOutput for [u8; 32] {
+impl<const N: usize> HashOutput for [u8; N] {
     fn as_bytes(&self) -> &[u8] {
         self
     }
-    
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok()
+    }
+}
+
+impl<const N: usize> HashOutput for crate::hash::Digest<N> {
+    fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
     fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        if bytes.len() == 32 {
-            let mut array = [0u8; 32];
-            array.copy_from_slice(bytes);
-            Some(array)
-        } else {
-            None
-        }
+        bytes.try_into().ok().map(crate::hash::Digest)
     }
 }
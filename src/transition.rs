@@ -0,0 +1,169 @@
+//! Proofs that one root follows from another by appending leaves
+//!
+//! Appending leaves only changes the right edge of each tree level; every node
+//! left of it covers old leaves only and is shared by both trees. A
+//! [`StateTransitionProof`] carries the appended leaf hashes and the shared
+//! nodes bordering that edge, from which [`verify_transition`] recomputes both
+//! the old and the new root. Followers can therefore check each append without
+//! holding the tree.
+
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::security::SecurityLogger;
+use crate::tree::ChronoMerkleTree;
+
+#[cfg(feature = "no-std")]
+use alloc::{string::ToString, vec::Vec};
+
+/// Proof that appending `leaves` to a tree of `old_leaf_count` leaves turns
+/// one root into another
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateTransitionProof<H> {
+    /// Number of leaves before the append
+    pub old_leaf_count: usize,
+    /// Timestamp and hash of each appended leaf, in order
+    pub leaves: Vec<(u64, H)>,
+    /// Shared nodes left of the changed edge, lowest level first
+    pub frontier: Vec<H>,
+}
+
+/// Verify that `proof` turns `old_root` into `new_root`
+///
+/// Returns an error if the proof is malformed, e.g. appends no leaves or has
+/// too few frontier hashes.
+pub fn verify_transition<H, Hasher>(
+    old_root: &H,
+    new_root: &H,
+    proof: &StateTransitionProof<H>,
+    hasher: &Hasher,
+) -> Result<bool>
+where
+    H: AsRef<[u8]> + Clone + Eq,
+    Hasher: HashFunction<Output = H>,
+{
+    if proof.old_leaf_count == 0 || proof.leaves.is_empty() {
+        return Err(ChronoMerkleError::InvalidProof {
+            message: "Transition must start from a non-empty tree and append leaves".to_string(),
+        });
+    }
+    let mut frontier = proof.frontier.iter();
+    let mut next_frontier = || {
+        frontier.next().cloned().ok_or_else(|| ChronoMerkleError::InvalidProof {
+            message: "Transition proof frontier is too short".to_string(),
+        })
+    };
+
+    // Both tails start at the same even index of their level
+    let mut full = proof.old_leaf_count;
+    let (mut old_count, mut new_count) = (proof.old_leaf_count, proof.old_leaf_count + proof.leaves.len());
+    let mut old_tail = Vec::new();
+    if full % 2 == 1 {
+        old_tail.push(next_frontier()?);
+    }
+    let mut new_tail = old_tail.clone();
+    new_tail.extend(proof.leaves.iter().map(|(_, hash)| hash.clone()));
+
+    while new_count > 1 {
+        if old_count > 1 {
+            old_tail = parents(&old_tail, hasher);
+            old_count = old_count.div_ceil(2);
+        }
+        new_tail = parents(&new_tail, hasher);
+        new_count = new_count.div_ceil(2);
+
+        full /= 2;
+        if full % 2 == 1 {
+            let shared = next_frontier()?;
+            old_tail.insert(0, shared.clone());
+            new_tail.insert(0, shared);
+        }
+    }
+
+    Ok(frontier.next().is_none() && old_tail == [old_root.clone()] && new_tail == [new_root.clone()])
+}
+
+/// Hash pairs of a level tail starting at an even index, duplicating an unpaired last node
+fn parents<H, Hasher>(tail: &[H], hasher: &Hasher) -> Vec<H>
+where
+    H: AsRef<[u8]> + Clone + Eq,
+    Hasher: HashFunction<Output = H>,
+{
+    tail.chunks(2)
+        .map(|pair| hasher.hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: HashFunction<Output = H> + Sync,
+    Logger: SecurityLogger,
+{
+    /// Prove the transition from the first `old_leaf_count` leaves to the current tree
+    pub fn transition_proof(&self, old_leaf_count: usize) -> Result<StateTransitionProof<H>> {
+        if old_leaf_count == 0 || old_leaf_count >= self.leaf_count {
+            return Err(ChronoMerkleError::IndexOutOfBounds {
+                index: old_leaf_count,
+                leaf_count: self.leaf_count,
+            });
+        }
+
+        let leaves = self
+            .nodes
+            .leaves()
+            .iter()
+            .skip(old_leaf_count)
+            .map(|node| (node.timestamp_info().0, node.hash()))
+            .collect();
+        let mut frontier = Vec::new();
+        let (mut start, mut count, mut full) = (0, self.leaf_count, old_leaf_count);
+        loop {
+            if full % 2 == 1 {
+                frontier.push(self.nodes[start + full - 1].hash());
+            }
+            if count == 1 {
+                break;
+            }
+            start += count;
+            count = count.div_ceil(2);
+            full /= 2;
+        }
+
+        Ok(StateTransitionProof {
+            old_leaf_count,
+            leaves,
+            frontier,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+
+    #[test]
+    fn test_transition_proofs() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        let mut roots = Vec::new();
+        for i in 0..13u64 {
+            tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+            roots.push(tree.root().unwrap());
+        }
+
+        for old in 1..13 {
+            let proof = tree.transition_proof(old).unwrap();
+            assert_eq!(proof.leaves.len(), 13 - old);
+            assert!(verify_transition(&roots[old - 1], &roots[12], &proof, &Blake3Hasher).unwrap(), "from {}", old);
+            assert!(!verify_transition(&roots[old], &roots[12], &proof, &Blake3Hasher).unwrap());
+        }
+
+        let mut proof = tree.transition_proof(5).unwrap();
+        proof.leaves[2].1[0] ^= 1;
+        assert!(!verify_transition(&roots[4], &roots[12], &proof, &Blake3Hasher).unwrap());
+        proof.frontier.clear();
+        assert!(verify_transition(&roots[4], &roots[12], &proof, &Blake3Hasher).is_err());
+        assert!(tree.transition_proof(13).is_err());
+    }
+}
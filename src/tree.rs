@@ -1,6 +1,12 @@
 //! Core ChronoMerkleTree implementation
 
 use crate::node::Node;
+use crate::levels::Levels;
+use crate::snapshot::SharedVec;
+#[cfg(feature = "no-std")]
+use alloc::{sync::Arc, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::sync::Arc;
 pub use crate::config::TreeConfig;
 #[cfg(feature = "storage")]
 pub use crate::storage::{StorageBackend, TreeState};
@@ -8,13 +14,13 @@ pub use crate::storage::{StorageBackend, TreeState};
 /// ChronoMerkleTree - A time-aware Merkle tree with delta-based updates
 pub struct ChronoMerkleTree<H = [u8; 32], Hasher = crate::hash::Blake3Hasher, Logger = crate::security::NoOpLogger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: crate::hash::HashFunction<Output = H> + Sync,
     Logger: crate::security::SecurityLogger,
 {
-    /// All nodes stored in heap order (complete binary tree)
-    /// Leaves are stored first, then internal nodes
-    pub(crate) nodes: Vec<Node<H>>,
+    /// All nodes, one vector per level
+    /// Leaves are the first level, the root the last
+    pub(crate) nodes: Levels<Node<H>>,
     /// Number of leaves (first N nodes are leaves)
     pub(crate) leaf_count: usize,
     /// Sparse index for timestamp lookups
@@ -29,29 +35,50 @@ where
     /// Maps timestamp -> list of deltas that led to that state
     pub(crate) delta_chains: crate::sparse_index::SparseIndex,
     /// All stored delta nodes for rollback
-    pub(crate) stored_deltas: Vec<Node<H>>,
+    pub(crate) stored_deltas: SharedVec<Node<H>>,
+    /// Roots after each change, when recorded
+    pub(crate) root_history: SharedVec<crate::root_history::RootRecord<H>>,
     /// Security event logger
     pub(crate) security_logger: Logger,
+    /// Optional LRU cache of generated proofs
+    #[cfg(feature = "std")]
+    pub(crate) proof_cache: Option<std::sync::Mutex<crate::proof_cache::ProofCache<H>>>,
+    /// Receiver of change notifications
+    #[cfg(feature = "std")]
+    pub(crate) event_emitter: Option<std::sync::Arc<dyn crate::event_emitter::EventEmitter>>,
+    /// Tombstones of leaves whose data was erased, by leaf index
+    pub(crate) redactions: crate::redaction::RedactionMap<H>,
+    /// Leaf payloads shared by identical leaves, when dedup is enabled
+    pub(crate) leaf_store: Option<Arc<crate::dedup::LeafStore<H>>>,
+    /// Signer of redaction records
+    pub(crate) redaction_signer: Option<Arc<dyn crate::redaction::RedactionSigner>>,
+    /// Token aborting long operations
+    #[cfg(not(feature = "minimal"))]
+    pub(crate) cancellation: Option<crate::cancellation::CancellationToken>,
+    /// Quota consulted before inserts
+    #[cfg(not(feature = "minimal"))]
+    pub(crate) admission: Option<crate::admission::AdmissionController>,
+    /// Hooks run around inserts, in order
+    #[cfg(not(feature = "minimal"))]
+    pub(crate) interceptors: Vec<Arc<dyn crate::interceptor::InsertInterceptor<H>>>,
+    /// Receiver of rebuild progress
+    #[cfg(feature = "std")]
+    pub(crate) progress_observer: Option<std::sync::Arc<dyn crate::progress::ProgressObserver>>,
+    /// Thread pool used for parallel construction (external or built from `parallel_threads`)
+    #[cfg(feature = "parallel")]
+    pub(crate) thread_pool: Option<std::sync::Arc<rayon::ThreadPool>>,
 }
 
 #[cfg(feature = "storage")]
 impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: crate::hash::HashFunction<Output = H> + Sync,
     Logger: crate::security::SecurityLogger,
 {
     /// Extract the current tree state for serialization
     pub fn extract_state(&self) -> crate::storage::TreeState<H> {
-        crate::storage::TreeState {
-            nodes: self.nodes.clone(),
-            leaf_count: self.leaf_count,
-            sparse_index: self.sparse_index.clone(),
-            config: self.config.clone(),
-            incremental_updates: self.incremental_updates,
-            stored_deltas: self.stored_deltas.clone(),
-            delta_chains: self.delta_chains.clone(),
-        }
+        self.snapshot().to_state()
     }
 
     /// Create a tree from a previously extracted state
@@ -61,43 +88,128 @@ where
         logger: Logger,
     ) -> Self {
         Self {
-            nodes: state.nodes,
+            nodes: Levels::from_flat(state.nodes, state.leaf_count),
             leaf_count: state.leaf_count,
             sparse_index: state.sparse_index,
             hasher,
             config: state.config,
             incremental_updates: state.incremental_updates,
             delta_chains: state.delta_chains,
-            stored_deltas: state.stored_deltas,
+            stored_deltas: state.stored_deltas.into(),
+            root_history: state.root_history.into(),
             security_logger: logger,
+            #[cfg(feature = "std")]
+            proof_cache: None,
+            #[cfg(feature = "std")]
+            event_emitter: None,
+            redactions: Arc::new(state.redactions.into_iter().map(|redaction| (redaction.index, redaction)).collect()),
+            leaf_store: None,
+            redaction_signer: None,
+            #[cfg(not(feature = "minimal"))]
+            cancellation: None,
+            #[cfg(not(feature = "minimal"))]
+            admission: None,
+            #[cfg(not(feature = "minimal"))]
+            interceptors: Vec::new(),
+            #[cfg(feature = "std")]
+            progress_observer: None,
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
         }
     }
 
     /// Save the current tree state to persistent storage
     pub fn save_state(
         &self,
-        storage: &mut impl crate::storage::StorageBackend,
+        storage: &mut (impl crate::storage::StorageBackend + ?Sized),
         key: &str,
     ) -> crate::error::Result<()> {
-        let state = self.extract_state();
-        let serialized = serde_json::to_string(&state)
+        // Serialize from a copy-on-write snapshot rather than cloning every node
+        self.snapshot().save(storage, key)
+    }
+
+    /// Save the current tree state as a new version of `key`
+    pub fn save_state_versioned(
+        &self,
+        storage: &mut (impl crate::storage::StorageBackend + ?Sized),
+        key: &str,
+    ) -> crate::error::Result<crate::storage::Version> {
+        let serialized = serde_json::to_vec(&self.snapshot())
             .map_err(|e| crate::error::ChronoMerkleError::SerializationError(e.to_string()))?;
-        storage.save(key, serialized.as_bytes())
+        storage.save_versioned(key, &serialized)
+    }
+
+    /// Load a specific version of a tree state saved with `save_state_versioned`
+    pub fn load_state_version(
+        storage: &(impl crate::storage::StorageBackend + ?Sized),
+        key: &str,
+        version: crate::storage::Version,
+        hasher: Hasher,
+        logger: Logger,
+    ) -> crate::error::Result<Self> {
+        Self::load_state(storage, &version.key_for(key), hasher, logger)
     }
 
     /// Load a tree state from persistent storage
+    ///
+    /// States with encrypted leaf data must be loaded with
+    /// [`load_state_encrypted`](Self::load_state_encrypted). Fields written by
+    /// newer versions are ignored.
     pub fn load_state(
-        storage: &impl crate::storage::StorageBackend,
+        storage: &(impl crate::storage::StorageBackend + ?Sized),
+        key: &str,
+        hasher: Hasher,
+        logger: Logger,
+    ) -> crate::error::Result<Self> {
+        Self::load_state_with(storage, key, hasher, logger, crate::serde_impl::UnknownFields::Ignore)
+    }
+
+    /// Load a tree state, treating fields unknown to this version as `unknown` says
+    pub fn load_state_with(
+        storage: &(impl crate::storage::StorageBackend + ?Sized),
         key: &str,
         hasher: Hasher,
         logger: Logger,
+        unknown: crate::serde_impl::UnknownFields,
     ) -> crate::error::Result<Self> {
-        let data = storage.load(key)?
-            .ok_or_else(|| crate::error::ChronoMerkleError::StorageError {
-                reason: format!("No data found for key: {}", key),
-            })?;
-        let state: crate::storage::TreeState<H> = serde_json::from_slice(&data)
-            .map_err(|e| crate::error::ChronoMerkleError::DeserializationError(e.to_string()))?;
+        let state = crate::storage::TreeState::load_from_with(storage, key, unknown)?;
+        if let Some(algorithm) = &state.data_cipher {
+            return Err(crate::error::ChronoMerkleError::StorageError {
+                reason: format!("Leaf data of {} is encrypted with {}", key, algorithm),
+            });
+        }
         Ok(Self::from_state(state, hasher, logger))
     }
-}
\ No newline at end of file
+}
+/// Hooks of the auxiliary modules as no-ops, for `minimal` builds without them
+#[cfg(feature = "minimal")]
+impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
+where
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
+    Hasher: crate::hash::HashFunction<Output = H> + Sync,
+    Logger: crate::security::SecurityLogger,
+{
+    /// Admit every insert; there is no admission controller
+    pub(crate) fn check_admission(&mut self, _inserts: usize, _bytes: usize) -> crate::error::Result<()> {
+        Ok(())
+    }
+
+    /// Never cancelled; there is no cancellation token
+    pub(crate) fn check_cancelled(&self, _operation: &str) -> crate::error::Result<()> {
+        Ok(())
+    }
+
+    /// No checkpoint is needed without a cancellation token
+    pub(crate) fn cancellation_checkpoint(&self) -> Option<crate::snapshot::TreeSnapshot<H>> {
+        None
+    }
+
+    /// Return `result` unchanged
+    pub(crate) fn restore_if_cancelled<T>(
+        &mut self,
+        _checkpoint: Option<crate::snapshot::TreeSnapshot<H>>,
+        result: crate::error::Result<T>,
+    ) -> crate::error::Result<T> {
+        result
+    }
+}
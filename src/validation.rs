@@ -1,5 +1,6 @@
 //! Input validation methods for ChronoMerkleTree
 
+use crate::config::LeafHashMode;
 use crate::error::{ChronoMerkleError, Result};
 use crate::security::SecurityLogger;
 use crate::tree::ChronoMerkleTree;
@@ -12,16 +13,31 @@ use std::string::ToString;
 
 impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: HashFunction<Output = H> + Sync,
     Logger: SecurityLogger,
 {
+    /// Reject an insert path that does not match the configured leaf hash mode
+    pub(crate) fn require_leaf_hash_mode(&self, mode: LeafHashMode) -> Result<()> {
+        if self.config.leaf_hash_mode == mode {
+            return Ok(());
+        }
+        let reason = match mode {
+            LeafHashMode::HashData => "Leaves of a TakeAsIs tree must be inserted with insert_prehashed",
+            LeafHashMode::TakeAsIs => "insert_prehashed requires LeafHashMode::TakeAsIs",
+        };
+        Err(ChronoMerkleError::InvalidConfiguration {
+            parameter: "leaf_hash_mode".to_string(),
+            reason: reason.to_string(),
+        })
+    }
+
     /// Validate inputs for insert operation
     pub(crate) fn validate_insert_inputs(&self, data: &[u8], timestamp: u64) -> Result<()> {
         // SECURITY: Validate data size to prevent DoS through excessive memory usage
         const MAX_DATA_SIZE: usize = 1024 * 1024; // 1MB limit
         if data.len() > MAX_DATA_SIZE {
-            let _ = self.security_logger.log_event(&crate::security::events::input_validation_failure(
+            crate::security::log_security_event!(self.security_logger, crate::security::events::input_validation_failure(
                 "data",
                 &format!("Data size {} exceeds maximum allowed size {}", data.len(), MAX_DATA_SIZE),
                 None,
@@ -34,7 +50,7 @@ where
 
         // SECURITY: Validate data is not empty (empty data could cause issues)
         if data.is_empty() {
-            let _ = self.security_logger.log_event(&crate::security::events::input_validation_failure(
+            crate::security::log_security_event!(self.security_logger, crate::security::events::input_validation_failure(
                 "data",
                 "Empty data not allowed",
                 Some(""),
@@ -54,7 +70,7 @@ where
         let hundred_years_past = current_time.saturating_sub(hundred_years_ago);
 
         if timestamp > one_year_future {
-            let _ = self.security_logger.log_event(&crate::security::events::input_validation_failure(
+            crate::security::log_security_event!(self.security_logger, crate::security::events::input_validation_failure(
                 "timestamp",
                 &format!("Timestamp {} is too far in the future (current: {})", timestamp, current_time),
                 Some(&timestamp.to_string()),
@@ -63,7 +79,7 @@ where
         }
 
         if timestamp < hundred_years_past {
-            let _ = self.security_logger.log_event(&crate::security::events::input_validation_failure(
+            crate::security::log_security_event!(self.security_logger, crate::security::events::input_validation_failure(
                 "timestamp",
                 &format!("Timestamp {} is too far in the past (current: {})", timestamp, current_time),
                 Some(&timestamp.to_string()),
@@ -73,11 +89,16 @@ where
 
         // SECURITY: Check for duplicate timestamps (could indicate replay attacks)
         if self.sparse_index.find_exact(timestamp).is_some() {
-            let _ = self.security_logger.log_event(&crate::security::events::input_validation_failure(
-                "timestamp",
-                &format!("Duplicate timestamp {} detected", timestamp),
-                Some(&timestamp.to_string()),
-            ));
+            crate::security::log_security_event!(
+                self.security_logger,
+                crate::security::SecurityEventType::InputValidationFailure,
+                crate::security::SecurityLevel::Warning,
+                || crate::security::events::input_validation_failure(
+                    "timestamp",
+                    &format!("Duplicate timestamp {} detected", timestamp),
+                    Some(&timestamp.to_string()),
+                )
+            );
             // Note: We allow duplicate timestamps for now but log it as a warning
             // In production systems, you might want to reject duplicates
         }
@@ -1,6 +1,8 @@
 //! Visualization methods for ChronoMerkleTree
 
+#[cfg(all(feature = "visualization", not(feature = "no-std")))]
 use crate::error::ChronoMerkleError;
+#[cfg(all(feature = "visualization", not(feature = "no-std")))]
 use crate::node::{Node, NodeType};
 use crate::tree::ChronoMerkleTree;
 
@@ -9,7 +11,7 @@ use alloc::{format, string::String, vec::Vec};
 
 impl<H, Hasher, Logger> ChronoMerkleTree<H, Hasher, Logger>
 where
-    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
+    H: AsRef<[u8]> + Clone + Eq + core::fmt::Debug + Send + Sync + crate::traits::MaybeSerde,
     Hasher: crate::hash::HashFunction<Output = H> + Sync,
     Logger: crate::security::SecurityLogger,
 {
@@ -32,7 +34,7 @@ where
 
         // Add the current node
         let connector = if is_last { "└── " } else { "├── " };
-        let _ = write!(result, "{}{}{}\n", prefix, connector, self.format_node_label(node));
+        let _ = writeln!(result, "{}{}{}", prefix, connector, self.format_node_label(node));
 
         // Prepare prefix for children
         let child_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
@@ -82,13 +84,13 @@ where
         // Add node definition
         let label = self.format_node_dot_label(node);
         let color = self.get_node_color(node);
-        let _ = write!(result, "    {} [label=\"{}\", fillcolor=\"{}\", style=filled];\n",
+        let _ = writeln!(result, "    {} [label=\"{}\", fillcolor=\"{}\", style=filled];",
                       node_id, label, color);
 
         // Add edges to children
         for (i, child) in node.children.iter().enumerate() {
             let child_id = node_id * 100 + i + 1; // Simple ID generation
-            let _ = write!(result, "    {} -> {};\n", node_id, child_id);
+            let _ = writeln!(result, "    {} -> {};", node_id, child_id);
             self.visualize_node_dot(child, child_id, result);
         }
     }
@@ -173,7 +175,7 @@ where
                     "right_hash": format!("{:?}", right_hash)
                 })
             }
-            NodeType::Delta { timestamp, delta_hash, base_hash } => {
+            NodeType::Delta { timestamp, delta_hash, base_hash, .. } => {
                 json!({
                     "type": "delta",
                     "timestamp": timestamp,
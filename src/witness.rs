@@ -0,0 +1,241 @@
+//! Client-side refresh of cached inclusion proofs
+//!
+//! Appends only change the right-hand side of a leaf's path: every left
+//! sibling covers a complete subtree of earlier leaves and stays valid. A
+//! [`WitnessUpdater`] keeps those siblings, folds the hashes of later leaves
+//! into the complete right-hand subtrees they form, and rebuilds the proof for
+//! any newer tree size. A client following the append log can therefore keep
+//! its own witness current without asking the tree holder for a new proof.
+//!
+//! State is logarithmic in the tree size: one hash per path level plus the
+//! complete subtrees of the right-hand sibling currently being filled.
+
+use crate::config::LeafHashMode;
+use crate::error::{ChronoMerkleError, Result};
+use crate::hash::HashFunction;
+use crate::proof::{ChronoProof, ProofStep};
+
+#[cfg(feature = "no-std")]
+use alloc::{string::ToString, vec::Vec};
+#[cfg(not(feature = "no-std"))]
+use std::vec::Vec;
+
+/// Keeps an inclusion proof valid as leaves are appended
+#[derive(Debug, Clone)]
+pub struct WitnessUpdater<H, Hasher> {
+    leaf_index: usize,
+    leaf_hash: H,
+    timestamp: u64,
+    leaf_hash_mode: LeafHashMode,
+    /// Left siblings by level, `None` where the leaf's ancestor is a left child
+    left: Vec<Option<H>>,
+    /// Roots of the completed right-hand sibling subtrees, lowest level first
+    right: Vec<H>,
+    /// Complete subtrees of the right-hand sibling being filled, largest first
+    frontier: Vec<(u32, H)>,
+    tree_size: usize,
+    hasher: Hasher,
+}
+
+impl<H, Hasher> WitnessUpdater<H, Hasher>
+where
+    H: AsRef<[u8]> + Clone + Eq,
+    Hasher: HashFunction<Output = H>,
+{
+    /// Track `proof` for the leaf hashing to `leaf_hash`
+    ///
+    /// `later_leaves` are the hashes of the leaves that followed the proven
+    /// leaf when the proof was generated, which is none for a proof taken right
+    /// after inserting the leaf. They are checked against the proof.
+    pub fn new(proof: &ChronoProof<H>, leaf_hash: H, later_leaves: &[H], hasher: Hasher) -> Result<Self> {
+        let invalid = |message: &str| ChronoMerkleError::InvalidProof { message: message.to_string() };
+        if proof.leaf_index.checked_shr(proof.path.len() as u32).unwrap_or(0) != 0 {
+            return Err(invalid("Proof path is too short for its leaf index"));
+        }
+        let left = proof
+            .path
+            .iter()
+            .enumerate()
+            .map(|(level, step)| match (step, (proof.leaf_index >> level) & 1) {
+                (ProofStep::Left(sibling), 1) => Ok(Some(sibling.clone())),
+                (ProofStep::Right(_), 0) => Ok(None),
+                (ProofStep::Delta(..), _) => Err(invalid("Witness updates do not support delta steps")),
+                _ => Err(invalid("Proof step direction does not match the leaf index")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut updater = Self {
+            leaf_index: proof.leaf_index,
+            leaf_hash,
+            timestamp: proof.timestamp,
+            leaf_hash_mode: proof.leaf_hash_mode,
+            left,
+            right: Vec::new(),
+            frontier: Vec::new(),
+            tree_size: proof.leaf_index + 1,
+            hasher,
+        };
+        updater.update(later_leaves);
+        if updater.path() != proof.path {
+            return Err(invalid("Later leaves do not match the proof"));
+        }
+        Ok(updater)
+    }
+
+    /// Apply the hashes of leaves appended since the last update, in order
+    pub fn update(&mut self, append_log: &[H]) {
+        for leaf_hash in append_log {
+            self.frontier.push((0, leaf_hash.clone()));
+            while let [.., (left_height, left), (right_height, right)] = self.frontier.as_slice() {
+                if left_height != right_height {
+                    break;
+                }
+                let merged = (left_height + 1, self.hasher.hash_pair(left, right));
+                self.frontier.truncate(self.frontier.len() - 2);
+                self.frontier.push(merged);
+            }
+            if let [(height, _)] = self.frontier.as_slice() {
+                if *height == self.filling_level() {
+                    let (_, root) = self.frontier.pop().expect("frontier has one subtree");
+                    self.right.push(root);
+                }
+            }
+            self.tree_size += 1;
+        }
+    }
+
+    /// Proof for the current tree size
+    pub fn proof(&self) -> ChronoProof<H> {
+        let mut proof = ChronoProof::new(self.leaf_index, self.timestamp);
+        proof.leaf_hash_mode = self.leaf_hash_mode;
+        proof.path = self.path();
+        proof
+    }
+
+    /// Root the current proof verifies against
+    pub fn root(&self) -> H {
+        self.path().iter().fold(self.leaf_hash.clone(), |current, step| self.apply(&current, step))
+    }
+
+    /// Number of leaves the witness is current for
+    pub fn tree_size(&self) -> usize {
+        self.tree_size
+    }
+
+    fn path(&self) -> Vec<ProofStep<H>> {
+        let mut path = Vec::new();
+        let mut current = self.leaf_hash.clone();
+        let mut right = self.right.iter();
+        let mut filling = !self.frontier.is_empty();
+        let mut level = 0u32;
+        while 1usize.checked_shl(level).is_some_and(|width| width < self.tree_size) {
+            let step = if (self.leaf_index >> level) & 1 == 1 {
+                ProofStep::Left(self.left[level as usize].clone().expect("left sibling of a right child"))
+            } else if let Some(sibling) = right.next() {
+                ProofStep::Right(sibling.clone())
+            } else if filling {
+                filling = false;
+                ProofStep::Right(self.partial_root(level))
+            } else {
+                // Nothing to the right yet: the node is paired with itself
+                ProofStep::Right(current.clone())
+            };
+            current = self.apply(&current, &step);
+            path.push(step);
+            level += 1;
+        }
+        path
+    }
+
+    fn apply(&self, current: &H, step: &ProofStep<H>) -> H {
+        match step {
+            ProofStep::Left(sibling) => self.hasher.hash_pair(sibling, current),
+            ProofStep::Right(sibling) | ProofStep::Delta(_, sibling) => self.hasher.hash_pair(current, sibling),
+        }
+    }
+
+    /// Level of the right-hand sibling subtree being filled
+    fn filling_level(&self) -> u32 {
+        (0..usize::BITS)
+            .filter(|level| (self.leaf_index >> level) & 1 == 0)
+            .nth(self.right.len())
+            .unwrap_or(usize::BITS)
+    }
+
+    /// Root of the partially filled sibling subtree at `level`, padded the way
+    /// the tree pads an odd last node
+    fn partial_root(&self, level: u32) -> H {
+        let filled: usize = self.frontier.iter().map(|(height, _)| 1usize << height).sum();
+        let mut larger = self.frontier.iter().rev();
+        let (mut height, mut current) = larger.next().cloned().expect("frontier is not empty");
+        while height < level {
+            current = if ((filled - 1) >> height) & 1 == 1 {
+                let (_, left) = larger.next().expect("complete left subtree");
+                self.hasher.hash_pair(left, &current)
+            } else {
+                self.hasher.hash_pair(&current, &current)
+            };
+            height += 1;
+        }
+        current
+    }
+}
+
+#[cfg(all(test, feature = "blake3-hash"))]
+mod tests {
+    use super::*;
+    use crate::hash::Blake3Hasher;
+    use crate::tree::ChronoMerkleTree;
+
+    #[test]
+    fn test_witness_tracks_appends() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        tree.insert(b"leaf0", 1000).unwrap();
+        let mut witnesses = Vec::new();
+        for i in 1..70u64 {
+            tree.insert(format!("leaf{}", i).as_bytes(), 1000 + i).unwrap();
+            let index = i as usize;
+            if i % 7 == 3 {
+                // Witness taken right after inserting the leaf
+                let proof = tree.generate_proof(index).unwrap();
+                witnesses.push(WitnessUpdater::new(&proof, tree.get_leaf_hash(index).unwrap(), &[], Blake3Hasher).unwrap());
+            }
+            for witness in &mut witnesses {
+                if witness.tree_size() < tree.leaf_count() {
+                    witness.update(&[tree.get_leaf_hash(index).unwrap()]);
+                }
+                assert_eq!(witness.tree_size(), tree.leaf_count());
+                assert_eq!(witness.proof().path, tree.generate_proof(witness.proof().leaf_index).unwrap().path);
+                assert_eq!(Some(witness.root()), tree.root());
+                assert!(tree.verify_proof(&witness.proof()).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_witness_from_older_proof() {
+        let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+        for i in 0..11u64 {
+            tree.insert(format!("leaf{}", i).as_bytes(), 1000 + i).unwrap();
+        }
+        let hashes: Vec<[u8; 32]> = (0..11).map(|i| tree.get_leaf_hash(i).unwrap()).collect();
+        let proof = tree.generate_proof(4).unwrap();
+        assert!(WitnessUpdater::new(&proof, hashes[4], &hashes[5..10], Blake3Hasher).is_err());
+        assert!(WitnessUpdater::new(&tree.generate_proof(5).unwrap(), hashes[5], &hashes[6..], Blake3Hasher).is_ok());
+        let mut witness = WitnessUpdater::new(&proof, hashes[4], &hashes[5..], Blake3Hasher).unwrap();
+
+        let mut appended = Vec::new();
+        for i in 11..40u64 {
+            tree.insert(format!("leaf{}", i).as_bytes(), 1000 + i).unwrap();
+            appended.push(tree.get_leaf_hash(i as usize).unwrap());
+        }
+        witness.update(&appended);
+        assert_eq!(witness.proof().timestamp, 1004);
+        assert_eq!(Some(witness.root()), tree.root());
+        assert!(tree.verify_proof(&witness.proof()).unwrap());
+
+        let mut wrong_direction = proof.clone();
+        wrong_direction.path.swap(1, 2);
+        assert!(WitnessUpdater::new(&wrong_direction, hashes[4], &hashes[5..], Blake3Hasher).is_err());
+    }
+}
@@ -1,5 +1,7 @@
 //! Integration tests for ChronoMerkle Tree
 
+#![allow(clippy::default_constructed_unit_structs, clippy::clone_on_copy, clippy::bool_assert_comparison)]
+
 use chrono_merkle::{ChronoMerkleTree, Blake3Hasher, TreeConfig, DefaultChronoMerkleTree, HashFunction};
 
 
@@ -99,6 +101,7 @@ fn test_configuration_validation() {
         incremental_updates: false,
         max_depth: 32,
         parallel_construction: false,
+        ..Default::default()
     };
     let result = DefaultChronoMerkleTree::with_config(Blake3Hasher::default(), config);
     assert!(result.is_err());
@@ -110,6 +113,7 @@ fn test_configuration_validation() {
         incremental_updates: false,
         max_depth: 0,
         parallel_construction: false,
+        ..Default::default()
     };
     let result = DefaultChronoMerkleTree::with_config(Blake3Hasher::default(), config);
     assert!(result.is_err());
@@ -121,6 +125,7 @@ fn test_configuration_validation() {
         incremental_updates: false,
         max_depth: 65,
         parallel_construction: false,
+        ..Default::default()
     };
     let result = DefaultChronoMerkleTree::with_config(Blake3Hasher::default(), config);
     assert!(result.is_err());
@@ -242,6 +247,7 @@ fn test_tree_with_config() {
         incremental_updates: true,
         max_depth: 32,
         parallel_construction: false,
+        ..Default::default()
     };
     
     let mut tree = DefaultChronoMerkleTree::with_config(Blake3Hasher::default(), config).unwrap();
@@ -250,6 +256,66 @@ fn test_tree_with_config() {
     assert_eq!(tree.leaf_count(), 1);
 }
 
+#[test]
+fn test_config_introspection() {
+    let config = TreeConfig {
+        sparse_index_sparsity: 10,
+        enable_deltas: false,
+        ..Default::default()
+    };
+    let tree = DefaultChronoMerkleTree::with_config(Blake3Hasher, config).unwrap();
+    assert_eq!(tree.config().sparse_index_sparsity, 10);
+    assert!(!tree.is_deltas_enabled());
+    assert_eq!(tree.hasher_name(), "blake3");
+
+    // Indexing settings leave the spec alone, the leaf hash mode does not
+    let default_tree: ChronoMerkleTree = ChronoMerkleTree::default();
+    assert!(default_tree.is_deltas_enabled());
+    assert_eq!(tree.spec_digest(), default_tree.spec_digest());
+    let prehashed = DefaultChronoMerkleTree::with_config(Blake3Hasher, TreeConfig {
+        leaf_hash_mode: chrono_merkle::LeafHashMode::TakeAsIs,
+        ..Default::default()
+    }).unwrap();
+    assert_ne!(prehashed.spec_digest(), default_tree.spec_digest());
+}
+
+#[test]
+fn test_restore_without_leaf_data() {
+    let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher);
+    for i in 0..5u64 {
+        tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+    }
+    let root = tree.root().unwrap();
+
+    // Stripped data used to be replaced by empty records, changing the root
+    let (mut leaves, deltas) = tree.extract_leaves_and_deltas();
+    if let chrono_merkle::NodeType::Leaf { data, .. } = &mut leaves[2].node_type {
+        *data = None;
+    }
+    let restored = DefaultChronoMerkleTree::reconstruct_from_leaves_and_deltas(
+        leaves.clone(), deltas, Blake3Hasher, TreeConfig::default(),
+    ).unwrap();
+    assert_eq!(restored.root(), Some(root));
+    assert!(restored.get_leaf_data(1).unwrap().data().is_some());
+
+    if let chrono_merkle::NodeType::Leaf { data, .. } = &mut leaves[3].node_type {
+        *data = Some(b"tampered".to_vec());
+    }
+    assert!(DefaultChronoMerkleTree::reconstruct_from_leaves_and_deltas(
+        leaves, Vec::new(), Blake3Hasher, TreeConfig::default(),
+    ).is_err());
+
+    let hashes = (0..5).map(|i| {
+        chrono_merkle::PrehashedLeaf::new(tree.get_leaf_hash(i).unwrap(), tree.get_leaf_timestamp(i).unwrap())
+    });
+    let restored = DefaultChronoMerkleTree::restore_from_hashes(hashes, Blake3Hasher, TreeConfig::default()).unwrap();
+    assert_eq!(restored.root(), Some(root));
+    assert_eq!(restored.find_by_timestamp(1003), vec![3]);
+    let proof = restored.generate_proof(3).unwrap();
+    assert!(tree.verify_proof(&proof).unwrap());
+    assert!(restored.get_leaf_data(3).unwrap().data().is_none());
+}
+
 #[test]
 fn test_empty_tree() {
     let tree: ChronoMerkleTree<[u8; 32], Blake3Hasher> = ChronoMerkleTree::default();
@@ -415,6 +481,7 @@ fn test_rollback_accuracy() {
         incremental_updates: false,
         max_depth: 64,
         parallel_construction: false,
+        ..Default::default()
     };
     let mut tree = DefaultChronoMerkleTree::with_config(Blake3Hasher::default(), config).unwrap();
     // Note: We can't easily disable incremental_updates from the public API
@@ -564,6 +631,41 @@ fn test_parallel_vs_sequential_consistency() {
     }
 }
 
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parallel_thread_pool_control() {
+    let mut reference = DefaultChronoMerkleTree::new(Blake3Hasher);
+
+    let mut bounded_tree = DefaultChronoMerkleTree::with_config(
+        Blake3Hasher,
+        TreeConfig { parallel_construction: true, parallel_threads: Some(2), ..Default::default() }
+    ).unwrap();
+
+    let pool = std::sync::Arc::new(rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap());
+    let mut external_pool_tree = DefaultChronoMerkleTree::with_config(
+        Blake3Hasher,
+        TreeConfig { parallel_construction: true, ..Default::default() }
+    ).unwrap();
+    external_pool_tree.set_thread_pool(pool);
+
+    for i in 0..20u64 {
+        let data = format!("data{}", i);
+        reference.insert(data.as_bytes(), 1000 + i).unwrap();
+        bounded_tree.insert(data.as_bytes(), 1000 + i).unwrap();
+        external_pool_tree.insert(data.as_bytes(), 1000 + i).unwrap();
+    }
+
+    assert_eq!(reference.root(), bounded_tree.root());
+    assert_eq!(reference.root(), external_pool_tree.root());
+
+    // Zero threads is rejected up front
+    let result = DefaultChronoMerkleTree::with_config(
+        Blake3Hasher,
+        TreeConfig { parallel_threads: Some(0), ..Default::default() }
+    );
+    assert!(result.is_err());
+}
+
 #[cfg(feature = "storage")]
 #[test]
 fn test_tree_persistence() {
@@ -638,6 +740,34 @@ fn test_tree_persistence() {
     }
 }
 
+#[cfg(feature = "file-storage")]
+#[test]
+fn test_file_storage_locking() {
+    use chrono_merkle::storage::StorageBackend;
+    use chrono_merkle::{FileStorage, LockMode};
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path().to_path_buf();
+
+    let mut writer = FileStorage::open_exclusive(dir.clone()).unwrap();
+    assert_eq!(writer.lock_mode(), Some(LockMode::Exclusive));
+    assert!(FileStorage::open_exclusive(dir.clone()).is_err());
+    assert!(FileStorage::open_shared(dir.clone()).is_err());
+
+    let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher);
+    tree.insert(b"data", 1000).unwrap();
+    tree.save_state(&mut writer, "state").unwrap();
+    assert_eq!(writer.list_keys().unwrap(), vec!["state".to_string()]);
+    drop(writer);
+
+    let mut reader = FileStorage::open_shared(dir.clone()).unwrap();
+    let _other_reader = FileStorage::open_shared(dir.clone()).unwrap();
+    assert!(FileStorage::open_exclusive(dir).is_err());
+    assert!(reader.exists("state").unwrap());
+    assert!(reader.save("state", b"overwrite").is_err());
+}
+
 #[cfg(feature = "storage")]
 #[test]
 fn test_tree_state_extraction() {
@@ -0,0 +1,128 @@
+//! Allocation tests for the `minimal` build profile
+//!
+//! Run with `cargo test --no-default-features --features "minimal blake3-hash" --test minimal_profile`.
+
+#![cfg(feature = "minimal")]
+
+use chrono_merkle::security::{SecurityEvent, SecurityLogger};
+use chrono_merkle::{Blake3Hasher, ChronoMerkleTree, Digest, Fixed, TreeConfig};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Global allocator counting the allocations made by each thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Number of allocations made by `f`, excluding those of its dropped result
+fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    let count = ALLOCATIONS.with(Cell::get) - before;
+    drop(result);
+    count
+}
+
+static LOGGED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+struct CountingLogger;
+
+impl SecurityLogger for CountingLogger {
+    fn log_event(&self, _event: &SecurityEvent) -> chrono_merkle::error::Result<()> {
+        LOGGED.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+fn tree_with_leaves<Logger: SecurityLogger + Default>(count: u64) -> ChronoMerkleTree<[u8; 32], Blake3Hasher, Logger> {
+    let mut tree = ChronoMerkleTree::with_config_and_logger(Blake3Hasher, TreeConfig::default(), Logger::default()).unwrap();
+    for i in 0..count {
+        tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+    }
+    tree
+}
+
+#[test]
+fn test_logger_is_never_called() {
+    let mut tree = tree_with_leaves::<CountingLogger>(16);
+    tree.insert(b"duplicate", 1000).unwrap();
+    assert!(tree.insert(b"", 2000).is_err());
+    assert!(tree.insert(b"future", u64::MAX).is_err());
+
+    let mut proof = tree.generate_proof(3).unwrap();
+    assert!(tree.verify_proof(&proof).unwrap());
+    proof.timestamp += 1;
+    assert!(!tree.verify_proof(&proof).unwrap());
+
+    assert_eq!(LOGGED.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn test_failure_paths_do_not_format() {
+    let mut tree = tree_with_leaves::<chrono_merkle::NoOpLogger>(16);
+
+    // The rejected timestamp is carried in the error, so nothing is allocated
+    assert_eq!(allocations(|| tree.insert(b"future", u64::MAX).unwrap_err()), 0);
+
+    // Only the two strings of the error itself are allocated
+    assert_eq!(allocations(|| tree.insert(b"", 5000).unwrap_err()), 2);
+
+    // A mismatching proof timestamp costs no more than a valid proof
+    let proof = tree.generate_proof(3).unwrap();
+    let mut stale = proof.clone();
+    stale.timestamp += 1;
+    let valid = allocations(|| tree.verify_proof(&proof).unwrap());
+    let mismatch = allocations(|| tree.verify_proof(&stale).unwrap());
+    assert!(mismatch <= valid, "mismatch {mismatch} vs valid {valid}");
+}
+
+#[test]
+fn test_construction_does_not_format() {
+    let baseline = allocations(ChronoMerkleTree::<[u8; 32], Blake3Hasher>::default);
+    let configured = allocations(|| ChronoMerkleTree::<[u8; 32], Blake3Hasher>::with_config(Blake3Hasher, TreeConfig::default()).unwrap());
+    assert_eq!(configured, baseline);
+}
+
+#[test]
+fn test_verification_does_not_allocate() {
+    let tree = tree_with_leaves::<chrono_merkle::NoOpLogger>(1000);
+    let proof = tree.generate_proof(3).unwrap();
+    let (leaf, root) = (tree.get_leaf_hash(3).unwrap(), tree.root().unwrap());
+    assert_eq!(allocations(|| tree.verify_proof(&proof).unwrap()), 0);
+    assert_eq!(allocations(|| chrono_merkle::proof::verify_proof(&proof, &leaf, &root, &Blake3Hasher).unwrap()), 0);
+    assert_eq!(
+        allocations(|| chrono_merkle::proof::verify_proof_constant_time(&proof, &leaf, &root, &Blake3Hasher, tree.depth())),
+        0
+    );
+
+    // Outputs larger than 32 bytes are concatenated on the stack as well
+    let mut wide = ChronoMerkleTree::<Digest<64>, Fixed<Blake3Hasher, 64>>::new(Fixed(Blake3Hasher));
+    for i in 0..100u64 {
+        wide.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+    }
+    let proof = wide.generate_proof(42).unwrap();
+    assert_eq!(allocations(|| wide.verify_proof(&proof).unwrap()), 0);
+}
@@ -0,0 +1,109 @@
+//! Adversarial inputs to proof verification and decoding
+//!
+//! Crafted proofs must be rejected by their structure before any hashing,
+//! and arbitrary bytes must decode to an error rather than a panic or a
+//! huge allocation.
+
+#![cfg(feature = "blake3-hash")]
+
+use chrono_merkle::proof::{verify_proof, MAX_DELTA_CHAIN, MAX_PROOF_STEPS};
+use chrono_merkle::{
+    Blake3Hasher, ChronoMerkleError, ChronoMerkleTree, ChronoProof, CompressedProof, HashFunction, ProofStep,
+};
+#[cfg(not(feature = "minimal"))]
+use chrono_merkle::ProofArchive;
+use proptest::prelude::*;
+
+fn tree(leaves: u64) -> ChronoMerkleTree {
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+    for i in 0..leaves {
+        tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+    }
+    tree
+}
+
+#[test]
+fn test_oversized_proofs_rejected() {
+    let tree = tree(5);
+    let root = tree.root().unwrap();
+    let leaf = tree.get_leaf_hash(0).unwrap();
+
+    // A million steps would otherwise cost a million hashes
+    let mut long = tree.generate_proof(0).unwrap();
+    long.path = vec![ProofStep::Right(leaf); 1_000_000];
+    assert_eq!(
+        verify_proof(&long, &leaf, &root, &Blake3Hasher),
+        Err(ChronoMerkleError::ProofTooLong { steps: 1_000_000, max: MAX_PROOF_STEPS })
+    );
+    // The tree holds proofs to its own maximum depth
+    long.path.truncate(33);
+    assert!(matches!(tree.verify_proof(&long), Err(ChronoMerkleError::ProofTooLong { steps: 33, max: 32 })));
+
+    let mut chain = tree.generate_proof(0).unwrap();
+    chain.delta_chain = Some(vec![leaf; MAX_DELTA_CHAIN + 1]);
+    assert!(matches!(tree.verify_proof(&chain), Err(ChronoMerkleError::DeltaChainTooLong { .. })));
+
+    let mut index = tree.generate_proof(4).unwrap();
+    index.leaf_index = 1 << 40;
+    assert_eq!(
+        verify_proof(&index, &leaf, &root, &Blake3Hasher),
+        Err(ChronoMerkleError::LeafIndexUnreachable { index: 1 << 40, levels: 3 })
+    );
+    index.leaf_index = 8;
+    assert!(matches!(verify_proof(&index, &leaf, &root, &Blake3Hasher), Err(ChronoMerkleError::LeafIndexUnreachable { .. })));
+}
+
+#[test]
+fn test_oversized_encodings_rejected() {
+    // Step count of 2^40 and a hash count of 2^40
+    let mut bytes = vec![0u8];
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x20]);
+    assert!(matches!(
+        CompressedProof::<[u8; 32]>::from_bytes(&bytes),
+        Err(ChronoMerkleError::ProofTooLong { .. })
+    ));
+
+    let mut bytes = vec![0u8];
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&[1, 0, 32, 0x80, 0x80, 0x80, 0x80, 0x80, 0x20]);
+    assert!(CompressedProof::<[u8; 32]>::from_bytes(&bytes).is_err());
+}
+
+proptest! {
+    #[test]
+    fn prop_decoding_arbitrary_bytes_never_panics(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+        let _ = CompressedProof::<[u8; 32]>::from_bytes(&bytes);
+        #[cfg(not(feature = "minimal"))]
+        let _ = ProofArchive::<[u8; 32]>::from_bytes(&bytes);
+    }
+
+    #[test]
+    fn prop_mutated_proofs_never_verify(
+        leaves in 2u64..40,
+        leaf in 0usize..40,
+        mutation in 0usize..4,
+        steps in proptest::collection::vec((any::<bool>(), any::<[u8; 32]>()), 0..80),
+        leaf_index in any::<usize>(),
+    ) {
+        let tree = tree(leaves);
+        let leaf = leaf % leaves as usize;
+        let mut proof: ChronoProof<[u8; 32]> = tree.generate_proof(leaf).unwrap();
+        match mutation {
+            0 => proof.path = steps.iter().map(|&(left, hash)| if left { ProofStep::Left(hash) } else { ProofStep::Right(hash) }).collect(),
+            1 => proof.leaf_index = leaf_index,
+            2 => proof.path.extend(steps.iter().map(|&(_, hash)| ProofStep::Right(hash))),
+            _ => proof.delta_chain = Some(steps.iter().map(|&(_, hash)| hash).collect()),
+        }
+        let unchanged = proof.path == tree.generate_proof(leaf).unwrap().path
+            && proof.leaf_index == leaf;
+        if let Ok(valid) = tree.verify_proof(&proof) {
+            prop_assert!(!valid || unchanged);
+        }
+        let root = tree.root().unwrap();
+        let leaf_hash = Blake3Hasher.hash(&(leaf as u64).to_le_bytes());
+        if let Ok(true) = verify_proof(&proof, &leaf_hash, &root, &Blake3Hasher) {
+            prop_assert!(proof.path.len() <= MAX_PROOF_STEPS);
+        }
+    }
+}
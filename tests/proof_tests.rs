@@ -1,5 +1,7 @@
 //! Tests for proof generation and verification
 
+#![allow(clippy::default_constructed_unit_structs)]
+
 use chrono_merkle::{Blake3Hasher, ChronoProof, ProofStep, DefaultChronoMerkleTree};
 
 #[test]
@@ -63,3 +65,41 @@ fn test_delta_chain() {
     assert!(proof.delta_chain.is_some());
     assert_eq!(proof.delta_chain.as_ref().unwrap().len(), 2);
 }
+
+#[test]
+fn test_proof_bound_to_tree_size() {
+    use chrono_merkle::proof::{verify_proof, verify_proof_with_size};
+    use chrono_merkle::HashFunction;
+
+    let mut tree = DefaultChronoMerkleTree::new(Blake3Hasher);
+    for i in 0..7u64 {
+        tree.insert(format!("data{}", i).as_bytes(), 1000 + i).unwrap();
+    }
+    let root = tree.root().unwrap();
+    for i in 0..7 {
+        let proof = tree.generate_proof(i).unwrap();
+        let leaf = tree.get_leaf_hash(i).unwrap();
+        assert!(verify_proof_with_size(&proof, &leaf, &root, 7, &Blake3Hasher).unwrap());
+        assert!(!verify_proof_with_size(&proof, &leaf, &root, 4, &Blake3Hasher).unwrap());
+        assert!(!verify_proof_with_size(&proof, &leaf, &root, 9, &Blake3Hasher).unwrap());
+    }
+
+    // Leaf 6 is paired with itself, so its path also proves a phantom leaf 7
+    // of an eight-leaf tree; only the committed size tells them apart
+    let leaf = tree.get_leaf_hash(6).unwrap();
+    let mut phantom = tree.generate_proof(6).unwrap();
+    phantom.leaf_index = 7;
+    phantom.path[0] = ProofStep::Left(leaf);
+    assert!(verify_proof(&phantom, &leaf, &root, &Blake3Hasher).unwrap());
+    assert!(!verify_proof_with_size(&phantom, &leaf, &root, 7, &Blake3Hasher).unwrap());
+
+    // A path presented for another position fails the direction checks
+    let mut moved = tree.generate_proof(2).unwrap();
+    moved.leaf_index = 3;
+    assert!(!verify_proof_with_size(&moved, &tree.get_leaf_hash(2).unwrap(), &root, 7, &Blake3Hasher).unwrap());
+
+    // The unpaired node must be paired with itself
+    let mut forged = tree.generate_proof(6).unwrap();
+    forged.path[0] = ProofStep::Right(Blake3Hasher.hash(b"other"));
+    assert!(!verify_proof_with_size(&forged, &leaf, &root, 7, &Blake3Hasher).unwrap());
+}
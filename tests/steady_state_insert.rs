@@ -0,0 +1,100 @@
+//! Allocation tests for the steady-state insert path
+//!
+//! With deltas disabled and a no-op logger, inserting into a tree with
+//! reserved room must not allocate anything besides what the tree keeps.
+
+#![cfg(feature = "blake3-hash")]
+
+use chrono_merkle::{Blake3Hasher, ChronoMerkleTree, HashFunction, LeafHashMode, PrehashedLeaf, TreeConfig};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Global allocator counting the allocations and frees made by each thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static FREES: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        FREES.with(|count| count.set(count.get() + 1));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Number of allocations and frees made by `f`
+fn allocations(f: impl FnOnce()) -> (usize, usize) {
+    let before = (ALLOCATIONS.with(Cell::get), FREES.with(Cell::get));
+    f();
+    (ALLOCATIONS.with(Cell::get) - before.0, FREES.with(Cell::get) - before.1)
+}
+
+fn config(leaf_hash_mode: LeafHashMode) -> TreeConfig {
+    TreeConfig {
+        enable_deltas: false,
+        // Index only the first leaf so the index does not grow
+        sparse_index_sparsity: u64::MAX,
+        leaf_hash_mode,
+        ..TreeConfig::default()
+    }
+}
+
+#[test]
+fn test_prehashed_inserts_do_not_allocate() {
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, config(LeafHashMode::TakeAsIs)).unwrap();
+    for i in 0..100u64 {
+        tree.insert_prehashed(PrehashedLeaf::new(Blake3Hasher.hash(&i.to_le_bytes()), 1000 + i)).unwrap();
+    }
+    tree.reserve(1000);
+
+    let hashes: Vec<_> = (100..1100u64).map(|i| Blake3Hasher.hash(&i.to_le_bytes())).collect();
+    let (allocated, freed) = allocations(|| {
+        for (i, hash) in hashes.iter().enumerate() {
+            tree.insert_prehashed(PrehashedLeaf::new(*hash, 1100 + i as u64)).unwrap();
+        }
+    });
+    assert_eq!((allocated, freed), (0, 0));
+
+    // The in-place update agrees with a full rebuild
+    let mut rebuilt: ChronoMerkleTree = ChronoMerkleTree::with_config(
+        Blake3Hasher,
+        TreeConfig { incremental_updates: false, ..config(LeafHashMode::TakeAsIs) },
+    ).unwrap();
+    for i in 0..tree.leaf_count() {
+        let leaf = PrehashedLeaf::new(tree.get_leaf_hash(i).unwrap(), tree.get_leaf_timestamp(i).unwrap());
+        rebuilt.insert_prehashed(leaf).unwrap();
+    }
+    assert_eq!(tree.root(), rebuilt.root());
+}
+
+#[test]
+fn test_inserts_only_allocate_stored_data() {
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::with_config(Blake3Hasher, config(LeafHashMode::HashData)).unwrap();
+    tree.insert(b"first", 1000).unwrap();
+    tree.reserve(503);
+
+    let (allocated, freed) = allocations(|| {
+        for i in 1..=500u64 {
+            tree.insert(&i.to_le_bytes(), 1000 + i).unwrap();
+        }
+    });
+    assert_eq!((allocated, freed), (500, 0));
+
+    let (allocated, freed) = allocations(|| tree.insert_batch(&[(b"a", 2000), (b"b", 2001), (b"c", 2002)]).unwrap());
+    assert_eq!((allocated, freed), (3, 0));
+}
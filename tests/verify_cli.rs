@@ -0,0 +1,45 @@
+//! Exit codes of the `chrono-merkle-verify` binary
+
+#![cfg(feature = "verify-cli")]
+
+use chrono_merkle::ChronoMerkleTree;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run the verifier with `args`, feeding `stdin`, and return whether it succeeded
+fn verify(args: &[&str], stdin: &str) -> bool {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chrono-merkle-verify"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    let status = child.wait().unwrap();
+    assert!(matches!(status.code(), Some(0 | 1)));
+    status.success()
+}
+
+#[test]
+fn test_verify_cli_exit_codes() {
+    let mut tree: ChronoMerkleTree = ChronoMerkleTree::default();
+    for i in 0..5u64 {
+        tree.insert(format!("record{}", i).as_bytes(), 1000 + i).unwrap();
+    }
+    let root = tree.root_hex().unwrap();
+    let receipt = tree.printable_receipt(3).unwrap().to_text();
+
+    assert!(verify(&[&root], &receipt));
+    assert!(verify(&[&root, "-"], &receipt));
+
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), &receipt).unwrap();
+    assert!(verify(&[&root, file.path().to_str().unwrap()], ""));
+
+    tree.insert(b"record5", 1005).unwrap();
+    assert!(!verify(&[&tree.root_hex().unwrap()], &receipt));
+    assert!(!verify(&[&root], &receipt.replace("TIME 1003", "TIME 1004")));
+    assert!(!verify(&["not-hex"], &receipt));
+    assert!(!verify(&[], &receipt));
+}